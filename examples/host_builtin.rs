@@ -0,0 +1,19 @@
+//! Embeds the interpreter and hands scripts a `host_time()` function.
+//!
+//! Run with `cargo run --example host_builtin`.
+
+use mintas::evaluator::{Evaluator, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let mut evaluator = Evaluator::new();
+    evaluator.register_builtin("host_time", Box::new(|_args| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Ok(Value::Number(now.as_secs() as f64))
+    }));
+
+    if let Err(e) = evaluator.eval_line("say(\"seconds since 1970: \" + host_time())") {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
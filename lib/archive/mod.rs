@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct ArchiveModule;
 impl ArchiveModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -18,7 +18,7 @@ impl ArchiveModule {
     }
     fn create(args: &[Value]) -> MintasResult<Value> {
         let name = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => "archive.tar".to_string() };
-        let mut archive = HashMap::new();
+        let mut archive = Table::new();
         archive.insert("name".to_string(), Value::String(name));
         archive.insert("files".to_string(), Value::Array(vec![]));
         archive.insert("__type__".to_string(), Value::String("Archive".to_string()));
@@ -28,7 +28,7 @@ impl ArchiveModule {
         if let Some(Value::Table(mut archive)) = args.get(0).cloned() {
             let path = match args.get(1) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Table(archive)) };
             let content = match args.get(2) { Some(Value::String(s)) => s.clone(), _ => String::new() };
-            let mut file = HashMap::new();
+            let mut file = Table::new();
             file.insert("path".to_string(), Value::String(path));
             file.insert("content".to_string(), Value::String(content.clone()));
             file.insert("size".to_string(), Value::Number(content.len() as f64));
@@ -41,7 +41,7 @@ impl ArchiveModule {
     }
     fn extract(args: &[Value]) -> MintasResult<Value> {
         let path = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Empty) };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("path".to_string(), Value::String(path));
         result.insert("files".to_string(), Value::Array(vec![]));
         Ok(Value::Table(result))
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct AsJokesModule;
 impl AsJokesModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -245,7 +245,7 @@ impl AsJokesModule {
             ("rock", "scissors") | ("paper", "rock") | ("scissors", "paper") => "You win!",
             _ => "Computer wins!",
         };
-        let mut table = HashMap::new();
+        let mut table = Table::new();
         table.insert("player".to_string(), Value::String(player));
         table.insert("computer".to_string(), Value::String(computer.to_string()));
         table.insert("result".to_string(), Value::String(result.to_string()));
@@ -6,6 +6,7 @@
 
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Mutex; // Added Mutex
@@ -889,7 +890,7 @@ impl CanvasModule {
                     let ox = (a.right().min(b.right()) - a.left().max(b.left())).max(0.0);
                     let oy = (a.bottom().min(b.bottom()) - a.top().max(b.top())).max(0.0);
                     if ox > 0.0 && oy > 0.0 {
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("x".to_string(), Value::Number(ox));
                         result.insert("y".to_string(), Value::Number(oy));
                         return Ok(Value::Table(result));
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct CertModule;
 impl CertModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -20,7 +20,7 @@ impl CertModule {
     fn generate(args: &[Value]) -> MintasResult<Value> {
         let cn = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => "localhost".to_string() };
         let days = match args.get(1) { Some(Value::Number(n)) => *n as i64, _ => 365 };
-        let mut cert = HashMap::new();
+        let mut cert = Table::new();
         cert.insert("common_name".to_string(), Value::String(cn));
         cert.insert("valid_days".to_string(), Value::Number(days as f64));
         cert.insert("type".to_string(), Value::String("X509".to_string()));
@@ -42,7 +42,7 @@ impl CertModule {
     fn load(args: &[Value]) -> MintasResult<Value> {
         let path = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Empty) };
         let content = std::fs::read_to_string(&path).unwrap_or_default();
-        let mut cert = HashMap::new();
+        let mut cert = Table::new();
         cert.insert("path".to_string(), Value::String(path));
         cert.insert("content".to_string(), Value::String(content));
         cert.insert("__type__".to_string(), Value::String("Certificate".to_string()));
@@ -57,7 +57,7 @@ impl CertModule {
     }
     fn info(args: &[Value]) -> MintasResult<Value> {
         if let Some(Value::Table(cert)) = args.get(0) {
-            let mut info = HashMap::new();
+            let mut info = Table::new();
             if let Some(cn) = cert.get("common_name") { info.insert("cn".to_string(), cn.clone()); }
             if let Some(days) = cert.get("valid_days") { info.insert("valid_days".to_string(), days.clone()); }
             info.insert("type".to_string(), Value::String("X509".to_string()));
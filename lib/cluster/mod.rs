@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct ClusterModule;
 impl ClusterModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -33,7 +33,7 @@ impl ClusterModule {
         };
         let mut workers = Vec::new();
         for i in 0..count {
-            let mut worker = HashMap::new();
+            let mut worker = Table::new();
             worker.insert("id".to_string(), Value::Number(i as f64));
             worker.insert("pid".to_string(), Value::Number((std::process::id() + i as u32) as f64));
             worker.insert("status".to_string(), Value::String("online".to_string()));
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::sync::atomic::{AtomicU64, Ordering};
 pub struct CronModule;
 static JOB_COUNTER: AtomicU64 = AtomicU64::new(1);
@@ -56,7 +56,7 @@ impl CronModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut job = HashMap::new();
+        let mut job = Table::new();
         job.insert("id".to_string(), Value::String(name.clone()));
         job.insert("expression".to_string(), Value::String(expression.clone()));
         job.insert("active".to_string(), Value::Boolean(true));
@@ -92,7 +92,7 @@ impl CronModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("minute".to_string(), Value::String(parts[0].to_string()));
         result.insert("hour".to_string(), Value::String(parts[1].to_string()));
         result.insert("day_of_month".to_string(), Value::String(parts[2].to_string()));
@@ -215,7 +215,7 @@ impl CronModule {
             "days" | "day" => format!("0 0 */{} * *", interval),
             _ => "* * * * *".to_string(),
         };
-        let mut job = HashMap::new();
+        let mut job = Table::new();
         job.insert("expression".to_string(), Value::String(expression));
         job.insert("interval".to_string(), Value::Number(interval as f64));
         job.insert("unit".to_string(), Value::String(unit));
@@ -244,7 +244,7 @@ impl CronModule {
             (0, 0)
         };
         let expression = format!("{} {} * * *", minute, hour);
-        let mut job = HashMap::new();
+        let mut job = Table::new();
         job.insert("expression".to_string(), Value::String(expression));
         job.insert("time".to_string(), Value::String(time));
         Ok(Value::Table(job))
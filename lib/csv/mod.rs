@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::{HashableValue, Table};
 pub struct CsvModule;
 impl CsvModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -33,7 +33,7 @@ impl CsvModule {
                 let headers: Vec<String> = Self::parse_line(lines[0], delimiter);
                 let rows: Vec<Value> = lines[1..].iter().map(|line| {
                     let values = Self::parse_line(line, delimiter);
-                    let mut row = HashMap::new();
+                    let mut row = Table::new();
                     for (i, header) in headers.iter().enumerate() {
                         row.insert(header.clone(), Value::String(values.get(i).cloned().unwrap_or_default()));
                     }
@@ -59,13 +59,14 @@ impl CsvModule {
                 _ => ',',
             };
             let mut lines = Vec::new();
-            let mut headers: Option<Vec<String>> = None;
+            let mut headers: Option<Vec<HashableValue>> = None;
             for row in rows {
                 match row {
                     Value::Table(map) => {
                         if headers.is_none() {
-                            let h: Vec<String> = map.keys().cloned().collect();
-                            lines.push(h.join(&delimiter.to_string()));
+                            let h: Vec<HashableValue> = map.keys().cloned().collect();
+                            let names: Vec<String> = h.iter().map(|k| k.to_string()).collect();
+                            lines.push(names.join(&delimiter.to_string()));
                             headers = Some(h);
                         }
                         if let Some(ref h) = headers {
@@ -1,8 +1,10 @@
 #![allow(dead_code)]
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::{HashableValue, Table};
+use super::validate_module::rule_errors;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::fs;
 use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -28,10 +30,79 @@ use hex;
 #[cfg(feature = "magic")]
 use csv;
 
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Locks a shared registry. A panic in an earlier request can poison a lock, but the data is
+/// still usable, so the server keeps going instead of failing every later request.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Integral numbers are written as JSON integers. NaN and infinities have no JSON
+/// representation, so they are written as `null` and a warning goes to stderr.
+fn number_to_json(n: f64) -> JsonValue {
+    if !n.is_finite() {
+        eprintln!("Warning: non-finite number {} serialized to JSON as null", n);
+        return JsonValue::Null;
+    }
+    if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER {
+        return JsonValue::Number(JsonNumber::from(n as i64));
+    }
+    JsonNumber::from_f64(n).map(JsonValue::Number).unwrap_or(JsonValue::Null)
+}
+
+fn json_to_value(json: JsonValue) -> crate::evaluator::Value {
+    use crate::evaluator::Value as V;
+    match json {
+        JsonValue::Null => V::Null,
+        JsonValue::Bool(b) => V::Boolean(b),
+        JsonValue::Number(n) => n.as_i64().map(V::from_i64).unwrap_or_else(|| V::Number(n.as_f64().unwrap_or(0.0))),
+        JsonValue::String(s) => V::String(s),
+        JsonValue::Array(arr) => V::Array(arr.into_iter().map(json_to_value).collect()),
+        JsonValue::Object(obj) => V::Table(obj.into_iter().map(|(k, v)| (k, json_to_value(v))).collect()),
+    }
+}
+
+fn yaml_to_value(yaml: serde_yaml::Value) -> crate::evaluator::Value {
+    use crate::evaluator::Value as V;
+    match yaml {
+        serde_yaml::Value::Null => V::Null,
+        serde_yaml::Value::Bool(b) => V::Boolean(b),
+        serde_yaml::Value::Number(n) => V::Number(n.as_f64().unwrap_or(0.0)),
+        serde_yaml::Value::String(s) => V::String(s),
+        serde_yaml::Value::Sequence(seq) => V::Array(seq.into_iter().map(yaml_to_value).collect()),
+        serde_yaml::Value::Mapping(map) => V::Table(map.into_iter().map(|(k, v)| (yaml_key(k), yaml_to_value(v))).collect()),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_value(tagged.value),
+    }
+}
+
+// Table keys are strings, so scalar YAML keys like `8080:` or `true:` are stringified
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s,
+        serde_yaml::Value::Number(n) => n.to_string(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        serde_yaml::Value::Null => "null".to_string(),
+        other => serde_yaml::to_string(&other).unwrap_or_default().trim().to_string(),
+    }
+}
+
+fn parse_yaml_to_value(yaml: &str) -> MintasResult<Value> {
+    let parsed: serde_yaml::Value = serde_yaml::from_str(yaml).map_err(|e| MintasError::RuntimeError {
+        message: format!("Invalid YAML: {}", e),
+        location: SourceLocation::new(0, 0),
+    })?;
+    Ok(match yaml_to_value(parsed) {
+        Value::Null => Value::Table(Table::new()),
+        other => other,
+    })
+}
+
 fn value_to_json(v: &crate::evaluator::Value) -> JsonValue {
     use crate::evaluator::Value as V;
     match v {
-        V::Number(n) => JsonNumber::from_f64(*n).map(JsonValue::Number).unwrap_or(JsonValue::Null),
+        V::Number(n) => number_to_json(*n),
+        V::Int(n) => JsonValue::Number(JsonNumber::from(*n)),
         V::String(s) => JsonValue::String(s.clone()),
         V::Boolean(b) => JsonValue::Bool(*b),
         V::Null => JsonValue::Null,
@@ -39,7 +110,7 @@ fn value_to_json(v: &crate::evaluator::Value) -> JsonValue {
         V::Table(t) => {
             let mut obj = serde_json::Map::new();
             for (k, vv) in t {
-                obj.insert(k.clone(), value_to_json(vv));
+                obj.insert(k.to_string(), value_to_json(vv));
             }
             JsonValue::Object(obj)
         }
@@ -47,10 +118,15 @@ fn value_to_json(v: &crate::evaluator::Value) -> JsonValue {
     }
 }
 
-fn table_to_json_string(t: &HashMap<String, crate::evaluator::Value>) -> String {
+/// Keys such as `__type__` that tag a table for the runtime and stay out of rendered output
+fn is_internal_key(key: &HashableValue) -> bool {
+    key.as_str().is_some_and(|key| key.starts_with("__"))
+}
+
+fn table_to_json_string(t: &Table) -> String {
     let mut obj = serde_json::Map::new();
     for (k, v) in t {
-        obj.insert(k.clone(), value_to_json(v));
+        obj.insert(k.to_string(), value_to_json(v));
     }
     JsonValue::Object(obj).to_string()
 }
@@ -63,6 +139,7 @@ impl DewModule {
             "serve" => Self::serve(args),
             "database" => Self::database(args),
             "query" => Self::query(args), // Added query function
+            "sql" => Self::sql(args),
             "use" => Self::use_middleware(args),
             "cors" => Self::cors(args),
             "auth" => Self::auth(args),
@@ -78,12 +155,14 @@ impl DewModule {
             "session_destroy" => Self::session_destroy(args),
             "cookie" => Self::cookie(args),
             "set_cookie" => Self::set_cookie(args),
-            "upload" => Self::upload(args),
+            "upload" => Self::upload(None, args),
             "save_upload" => Self::save_upload(args),
             "validate" => Self::validate(args),
             "websocket" => Self::websocket(args),
             "ws_send" => Self::ws_send(args),
             "ws_broadcast" => Self::ws_broadcast(args),
+            "ws_send_binary" => Self::ws_send_binary(args),
+            "ws_close" => Self::ws_close(args),
             "test_get" => Self::test_get(args),
             "test_post" => Self::test_post(args),
             "test_put" => Self::test_put(args),
@@ -91,6 +170,9 @@ impl DewModule {
             "config" => Self::config(args),
             "dotenv" => Self::dotenv(args),
             "env" => Self::env(args),
+            "env_number" => Self::env_number(args),
+            "env_bool" => Self::env_bool(args),
+            "env_required" => Self::env_required(args),
             "job" => Self::job(args),
             "queue" => Self::queue(args),
             "task" => Self::task(args),
@@ -115,6 +197,7 @@ impl DewModule {
             "json" => Self::response_json(args),
             "redirect" => Self::response_redirect(args),
             "file" => Self::response_file(args),
+            "stream" => Self::response_stream(args),
             // WebRTC Features
             "webrtc_peer" => Self::webrtc_peer(args),
             "webrtc_offer" => Self::webrtc_offer(args),
@@ -124,6 +207,7 @@ impl DewModule {
             "webrtc_on_message" => Self::webrtc_on_message(args),
             "webrtc_close" => Self::webrtc_close(args),
             "webrtc_stats" => Self::webrtc_stats(args),
+            "webrtc_signaling" => Self::webrtc_signaling(args),
             // JavaScript Event Handling
             "js_onclick" => Self::js_onclick(args),
             "js_onchange" => Self::js_onchange(args),
@@ -155,6 +239,8 @@ impl DewModule {
             "hash_password" => Self::hash_password(args),
             "verify_password" => Self::verify_password(args),
             "sha256" => Self::sha256(args),
+            "jwt_sign" => Self::jwt_sign(args),
+            "jwt_verify" => Self::jwt_verify(args),
             "csv_parse" => Self::csv_parse(args),
             "csv_stringify" => Self::csv_stringify(args),
             "redis_get" => Self::redis_get(args),
@@ -167,9 +253,9 @@ impl DewModule {
     }
     fn create_server(_args: &[Value]) -> MintasResult<Value> {
         let server = DewServer::new();
-        let id = SERVERS.lock().unwrap().register(server);
+        let id = lock(&SERVERS).register(server);
         Ok(Value::Table({
-            let mut map = HashMap::new();
+            let mut map = Table::new();
             map.insert("__dew_server_id__".to_string(), Value::Number(id as f64));
             map.insert("__type__".to_string(), Value::String("DewServer".to_string()));
             map
@@ -203,11 +289,15 @@ impl DewModule {
                 Some(Value::Number(id)) => *id as usize,
                 _ => 0,
             };
-            (port, host, server_id, HashMap::new())
+            (port, host, server_id, Table::new())
+        };
+        let max_body_size = match options.get("max_body_size") {
+            Some(Value::Number(bytes)) if *bytes >= 0.0 => Some(*bytes),
+            _ => None,
         };
         let timeout = match options.get("timeout") {
             Some(Value::Number(t)) => Some(*t as u64),
-            Some(Value::String(s)) => parse_duration_string(s),
+            Some(Value::String(s)) => crate::evaluator::parse_duration(s, 1.0).map(|d| d.as_millis() as u64),
             _ => None,
         };
         let debug = match options.get("debug") {
@@ -222,7 +312,7 @@ impl DewModule {
             Some(Value::Boolean(b)) => *b,
             _ => false,
         };
-        let mut servers = SERVERS.lock().unwrap();
+        let mut servers = lock(&SERVERS);
         if let Some(server) = servers.get_mut(server_id) {
             server.security.sql_injection_protection = security;
             server.security.xss_protection = security;
@@ -233,6 +323,9 @@ impl DewModule {
             if let Some(t) = timeout {
                 server.config.insert("timeout".to_string(), Value::Number(t as f64));
             }
+            if let Some(bytes) = max_body_size {
+                server.config.insert("max_body_size".to_string(), Value::Number(bytes));
+            }
             if debug {
                 println!("🐛 Debug mode enabled");
             }
@@ -259,7 +352,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => "sqlite:///app.db".to_string(),
         };
-        let mut db = HashMap::new();
+        let mut db = Table::new();
         db.insert("connection".to_string(), Value::String(connection_string.clone()));
         db.insert("__type__".to_string(), Value::String("DewDatabase".to_string()));
         if connection_string.starts_with("sqlite:") {
@@ -274,23 +367,65 @@ impl DewModule {
         Ok(Value::Table(db))
     }
 
+    /// `dew.sql(template, params)` turns `:name` placeholders into bound parameters and returns
+    /// `[sql, values]` for `dew.query(db, ...)`. This is the recommended way to put script values
+    /// into SQL: they are only ever bound, never spliced into the query text.
+    fn sql(args: &[Value]) -> MintasResult<Value> {
+        let (template, params) = match (args.first(), args.get(1)) {
+            (Some(Value::String(t)), Some(Value::Table(p))) => (t, p),
+            (Some(Value::String(t)), None) => (t, &Table::new()),
+            _ => return Err(MintasError::TypeError {
+                message: "dew.sql expects a SQL template string and a table of parameters".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        };
+        let (sql, names) = bind_named_params(template);
+        let mut values = Vec::with_capacity(names.len());
+        for name in &names {
+            match params.get(name) {
+                Some(v @ (Value::String(_) | Value::Number(_) | Value::Int(_) | Value::Boolean(_) | Value::Null)) => {
+                    values.push(v.clone())
+                }
+                Some(Value::Empty) => values.push(Value::Null),
+                Some(other) => return Err(MintasError::TypeError {
+                    message: format!("dew.sql: :{} must be a string, number, boolean or null, got {}", name, other.type_name()),
+                    location: SourceLocation::new(0, 0),
+                }),
+                None => return Err(MintasError::RuntimeError {
+                    message: format!("dew.sql: no value given for :{}", name),
+                    location: SourceLocation::new(0, 0),
+                }),
+            }
+        }
+        Ok(Value::Array(vec![Value::String(sql), Value::Array(values)]))
+    }
+
+    /// `dew.query(db, sql, [params])`, or `dew.query(db, dew.sql(template, params))`
     #[cfg(feature = "database")]
     fn query(args: &[Value]) -> MintasResult<Value> {
-        let db_config = match args.get(0) {
+        let db_config = match args.first() {
             Some(Value::Table(t)) => t,
             _ => return Err(MintasError::RuntimeError {
                 message: "Expected database config as first argument".to_string(),
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let sql = match args.get(1) {
-            Some(Value::String(s)) => s,
+        // A `dew.sql` pair carries its own bound values
+        let (sql, params) = match args.get(1) {
+            Some(Value::String(s)) => (s, args.get(2)),
+            Some(Value::Array(pair)) => match pair.as_slice() {
+                [Value::String(s), params @ Value::Array(_)] if args.len() == 2 => (s, Some(params)),
+                _ => return Err(MintasError::RuntimeError {
+                    message: "Expected a [sql, params] pair from dew.sql as second argument".to_string(),
+                    location: SourceLocation::new(0, 0),
+                }),
+            },
             _ => return Err(MintasError::RuntimeError {
                 message: "Expected SQL query string as second argument".to_string(),
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let params = match args.get(2) {
+        let params = match params {
             Some(Value::Array(a)) => a,
             Some(Value::Table(_)) => return Err(MintasError::RuntimeError {
                 message: "Named parameters not yet supported for SQLite query".to_string(),
@@ -323,6 +458,7 @@ impl DewModule {
                         match v {
                             Value::String(s) => Box::new(s.clone()) as Box<dyn rusqlite::ToSql>,
                             Value::Number(n) => Box::new(*n) as Box<dyn rusqlite::ToSql>,
+                            Value::Int(i) => Box::new(*i) as Box<dyn rusqlite::ToSql>,
                             Value::Boolean(b) => Box::new(*b) as Box<dyn rusqlite::ToSql>,
                             Value::Null => Box::new(rusqlite::types::Null) as Box<dyn rusqlite::ToSql>,
                             _ => Box::new(value_to_string(v)) as Box<dyn rusqlite::ToSql>,
@@ -336,7 +472,7 @@ impl DewModule {
                          message: format!("SQLite row error: {}", e),
                          location: SourceLocation::new(0, 0),
                     })? {
-                        let mut map = HashMap::new();
+                        let mut map = Table::new();
                         for (i, name) in col_names.iter().enumerate() {
                             let val: rusqlite::types::Value = row.get(i).unwrap_or(rusqlite::types::Value::Null);
                             let mintas_val = match val {
@@ -356,6 +492,12 @@ impl DewModule {
                     return Ok(Value::Array(rows_result));
                 }
             } else if driver == "postgres" {
+                if !params.is_empty() {
+                    return Err(MintasError::RuntimeError {
+                        message: "Bound parameters are not supported for Postgres queries yet".to_string(),
+                        location: SourceLocation::new(0, 0),
+                    });
+                }
                 if let Some(Value::String(conn_str)) = db_config.get("connection") {
                     let mut client = Client::connect(conn_str, NoTls).map_err(|e| MintasError::RuntimeError {
                         message: format!("Postgres connection error: {}", e),
@@ -373,7 +515,7 @@ impl DewModule {
                     // We only support basic strings return in simple_query
                     for msg in rows {
                         if let postgres::SimpleQueryMessage::Row(r) = msg {
-                            let mut map = HashMap::new();
+                            let mut map = Table::new();
                             for i in 0..r.len() {
                                 if let Some(val) = r.get(i) {
                                      map.insert(format!("col_{}", i), Value::String(val.to_string()));
@@ -400,7 +542,37 @@ impl DewModule {
             location: SourceLocation::new(0, 0),
         })
     }
+    /// `dew.use("logger", server_id)` enables a built-in; `dew.use(server, func)` registers a
+    /// script function `(req, next)` that runs before the route handler
     fn use_middleware(args: &[Value]) -> MintasResult<Value> {
+        if let (Some(Value::Table(server)), Some(Value::Function(func))) = (args.first(), args.get(1)) {
+            let server_id = match server.get("__dew_server_id__") {
+                Some(Value::Number(id)) => *id as usize,
+                _ => return Err(MintasError::RuntimeError {
+                    message: "dew.use expects a Dew server as its first argument".to_string(),
+                    location: SourceLocation::new(0, 0),
+                }),
+            };
+            if func.params.len() != 2 {
+                return Err(MintasError::InvalidArgumentCount {
+                    function: "middleware".to_string(),
+                    expected: 2,
+                    got: func.params.len(),
+                    location: SourceLocation::new(0, 0),
+                });
+            }
+            let mut servers = lock(&SERVERS);
+            return match servers.get_mut(server_id) {
+                Some(server) => {
+                    server.add_middleware(SCRIPT_MIDDLEWARE, Some(func.as_ref().clone()));
+                    Ok(Value::Boolean(true))
+                }
+                None => Err(MintasError::RuntimeError {
+                    message: "Server not found".to_string(),
+                    location: SourceLocation::new(0, 0),
+                }),
+            };
+        }
         let middleware_name = match args.get(0) {
             Some(Value::String(s)) => s.clone(),
             _ => return Ok(Value::Boolean(false)),
@@ -409,7 +581,7 @@ impl DewModule {
             Some(Value::Number(id)) => *id as usize,
             _ => 0,
         };
-        let mut servers = SERVERS.lock().unwrap();
+        let mut servers = lock(&SERVERS);
         if let Some(server) = servers.get_mut(server_id) {
             server.add_middleware(&middleware_name, None);
             println!("🔧 Middleware enabled: {}", middleware_name);
@@ -434,7 +606,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => "Content-Type, Authorization, X-Requested-With".to_string(),
         };
-        let mut cors_config = HashMap::new();
+        let mut cors_config = Table::new();
         cors_config.insert("origins".to_string(), Value::String(origins));
         cors_config.insert("methods".to_string(), Value::String(methods));
         cors_config.insert("headers".to_string(), Value::String(headers));
@@ -451,7 +623,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => "default_secret_change_me".to_string(),
         };
-        let mut auth_config = HashMap::new();
+        let mut auth_config = Table::new();
         auth_config.insert("type".to_string(), Value::String(auth_type));
         auth_config.insert("secret".to_string(), Value::String(secret));
         auth_config.insert("__type__".to_string(), Value::String("AuthConfig".to_string()));
@@ -466,7 +638,7 @@ impl DewModule {
             Some(Value::Number(n)) => *n as u32,
             _ => 60,
         };
-        let mut config = HashMap::new();
+        let mut config = Table::new();
         config.insert("requests".to_string(), Value::Number(requests as f64));
         config.insert("window_seconds".to_string(), Value::Number(window as f64));
         config.insert("__type__".to_string(), Value::String("RateLimitConfig".to_string()));
@@ -475,13 +647,38 @@ impl DewModule {
     fn compress(_args: &[Value]) -> MintasResult<Value> {
         Ok(Value::Boolean(true))
     }
+    /// `dew.logger(format?, server?)` logs each request to the server (the first one by
+    /// default) in `combined`, `common` or `json` form
     fn logger(args: &[Value]) -> MintasResult<Value> {
-        let format = match args.get(0) {
+        let format = match args.first() {
             Some(Value::String(s)) => s.clone(),
             _ => "combined".to_string(),
         };
-        println!("📝 Logger enabled: {}", format);
-        Ok(Value::Boolean(true))
+        if LogFormat::parse(&format).is_none() {
+            return Err(MintasError::RuntimeError {
+                message: format!("Unknown logger format '{}' (expected combined, common or json)", format),
+                location: SourceLocation::new(0, 0),
+            });
+        }
+        let server_id = match args.get(1) {
+            Some(Value::Table(server)) => match server.get("__dew_server_id__") {
+                Some(Value::Number(id)) => *id as usize,
+                _ => 0,
+            },
+            Some(Value::Number(id)) => *id as usize,
+            _ => 0,
+        };
+        match lock(&SERVERS).get_mut(server_id) {
+            Some(server) => {
+                server.config.insert("log_format".to_string(), Value::String(format.to_lowercase()));
+                println!("📝 Logger enabled: {}", format);
+                Ok(Value::Boolean(true))
+            }
+            None => Err(MintasError::RuntimeError {
+                message: "Server not found".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
     }
     fn static_files(args: &[Value]) -> MintasResult<Value> {
         let url_path = match args.get(0) {
@@ -496,7 +693,7 @@ impl DewModule {
             Some(Value::Number(id)) => *id as usize,
             _ => 0,
         };
-        let mut servers = SERVERS.lock().unwrap();
+        let mut servers = lock(&SERVERS);
         if let Some(server) = servers.get_mut(server_id) {
             server.add_static_dir(&url_path, &dir_path);
             println!("📁 Static files: {} -> {}", url_path, dir_path);
@@ -513,14 +710,12 @@ impl DewModule {
         };
         let data = match args.get(1) {
             Some(Value::Table(t)) => t.clone(),
-            _ => HashMap::new(),
+            _ => Table::new(),
         };
-        let template_content = fs::read_to_string(&template_path)
-            .or_else(|_| fs::read_to_string(format!("templates/{}", template_path)))
-            .or_else(|_| fs::read_to_string(format!("views/{}", template_path)))
-            .unwrap_or_else(|_| format!("<!-- Template not found: {} -->", template_path));
+        let template_content = load_template_file(&template_path)
+            .unwrap_or_else(|| format!("<!-- Template not found: {} -->", template_path));
         let rendered = render_template(&template_content, &data);
-        let mut response = HashMap::new();
+        let mut response = Table::new();
         response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
         response.insert("response_type".to_string(), Value::String("html".to_string()));
         response.insert("body".to_string(), Value::String(rendered));
@@ -536,7 +731,7 @@ impl DewModule {
             _ => None,
         };
         if let Some(k) = key {
-            let sessions = SESSIONS.lock().unwrap();
+            let sessions = lock(&SESSIONS);
             if let Some(session_data) = sessions.get("current") {
                 if let Some(value) = session_data.get(&k) {
                     return Ok(value.clone());
@@ -544,9 +739,9 @@ impl DewModule {
             }
             Ok(Value::Empty)
         } else {
-            let mut session = HashMap::new();
+            let mut session = Table::new();
             session.insert("__type__".to_string(), Value::String("Session".to_string()));
-            let sessions = SESSIONS.lock().unwrap();
+            let sessions = lock(&SESSIONS);
             if let Some(session_data) = sessions.get("current") {
                 for (k, v) in session_data {
                     session.insert(k.clone(), v.clone());
@@ -561,7 +756,7 @@ impl DewModule {
             _ => return Ok(Value::Boolean(false)),
         };
         let value = args.get(1).cloned().unwrap_or(Value::Empty);
-        let mut sessions = SESSIONS.lock().unwrap();
+        let mut sessions = lock(&SESSIONS);
         let session_data = sessions.entry("current".to_string()).or_insert_with(HashMap::new);
         session_data.insert(key, value);
         Ok(Value::Boolean(true))
@@ -570,7 +765,7 @@ impl DewModule {
         Self::session(args)
     }
     fn session_destroy(_args: &[Value]) -> MintasResult<Value> {
-        let mut sessions = SESSIONS.lock().unwrap();
+        let mut sessions = lock(&SESSIONS);
         sessions.remove("current");
         Ok(Value::Boolean(true))
     }
@@ -579,7 +774,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => return Ok(Value::Empty),
         };
-        let cookies = COOKIES.lock().unwrap();
+        let cookies = lock(&COOKIES);
         if let Some(value) = cookies.get(&name) {
             Ok(Value::String(value.clone()))
         } else {
@@ -607,27 +802,30 @@ impl DewModule {
             Some(Value::Boolean(b)) => *b,
             _ => true,
         };
-        let mut cookie = HashMap::new();
+        let mut cookie = Table::new();
         cookie.insert("name".to_string(), Value::String(name.clone()));
         cookie.insert("value".to_string(), Value::String(value.clone()));
         cookie.insert("max_age".to_string(), Value::Number(max_age as f64));
         cookie.insert("path".to_string(), Value::String(path));
         cookie.insert("http_only".to_string(), Value::Boolean(http_only));
         cookie.insert("__type__".to_string(), Value::String("SetCookie".to_string()));
-        let mut cookies = COOKIES.lock().unwrap();
+        let mut cookies = lock(&COOKIES);
         cookies.insert(name, value);
         Ok(Value::Table(cookie))
     }
-    fn upload(args: &[Value]) -> MintasResult<Value> {
-        let field_name = match args.get(0) {
+    /// `dew.upload(field)`: the file sent in `field` by the request being handled, whose `files`
+    /// table is passed in by the evaluator, or an empty file outside a handler
+    pub fn upload(files: Option<&Value>, args: &[Value]) -> MintasResult<Value> {
+        let field_name = match args.first() {
             Some(Value::String(s)) => s.clone(),
             _ => "file".to_string(),
         };
-        let uploads = UPLOADS.lock().unwrap();
-        if let Some(file_info) = uploads.get(&field_name) {
-            return Ok(file_info.clone());
+        if let Some(Value::Table(files)) = files {
+            if let Some(file_info) = files.get(&field_name) {
+                return Ok(file_info.clone());
+            }
         }
-        let mut file_info = HashMap::new();
+        let mut file_info = Table::new();
         file_info.insert("field".to_string(), Value::String(field_name));
         file_info.insert("filename".to_string(), Value::String(String::new()));
         file_info.insert("size".to_string(), Value::Number(0.0));
@@ -636,54 +834,68 @@ impl DewModule {
         Ok(Value::Table(file_info))
     }
     fn save_upload(args: &[Value]) -> MintasResult<Value> {
-        let file = match args.get(0) {
-            Some(Value::Table(t)) => t.clone(),
-            _ => return Ok(Value::Boolean(false)),
-        };
         let dest_path = match args.get(1) {
             Some(Value::String(s)) => s.clone(),
             _ => "uploads/".to_string(),
         };
-        fs::create_dir_all(&dest_path).ok();
+        match args.first() {
+            Some(Value::Table(file)) => Self::save_uploaded_file(file, &dest_path),
+            Some(Value::Array(files)) => {
+                let mut paths = Vec::new();
+                for file in files {
+                    if let Value::Table(file) = file {
+                        paths.push(Self::save_uploaded_file(file, &dest_path)?);
+                    }
+                }
+                Ok(Value::Array(paths))
+            }
+            _ => Ok(Value::Boolean(false)),
+        }
+    }
+    fn save_uploaded_file(file: &Table, dest_path: &str) -> MintasResult<Value> {
+        fs::create_dir_all(dest_path).ok();
+        // Only keep the last path component so a crafted filename cannot escape dest_path
         let filename = match file.get("filename") {
-            Some(Value::String(s)) => s.clone(),
-            _ => format!("upload_{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()),
+            Some(Value::String(s)) => Path::new(s)
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        let filename = if filename.is_empty() {
+            format!("upload_{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+        } else {
+            filename
         };
         let full_path = format!("{}/{}", dest_path.trim_end_matches('/'), filename);
+        let content: &[u8] = match file.get("content") {
+            Some(Value::Bytes(b)) => b,
+            Some(Value::String(s)) => s.as_bytes(),
+            _ => &[],
+        };
+        fs::write(&full_path, content).map_err(|e| MintasError::RuntimeError {
+            message: format!("Failed to save upload to {}: {}", full_path, e),
+            location: SourceLocation::new(0, 0),
+        })?;
         Ok(Value::String(full_path))
     }
     fn validate(args: &[Value]) -> MintasResult<Value> {
         let data = match args.get(0) {
             Some(Value::Table(t)) => t.clone(),
-            _ => HashMap::new(),
+            _ => Table::new(),
         };
         let rules = match args.get(1) {
             Some(Value::Table(t)) => t.clone(),
             _ => return Ok(Value::Boolean(true)),
         };
-        let mut errors: HashMap<String, Value> = HashMap::new();
-        let mut is_valid = true;
-        for (field, rule) in &rules {
-            if let Value::String(rule_str) = rule {
-                let field_value = data.get(field);
-                let rule_parts: Vec<&str> = rule_str.split('|').collect();
-                for part in rule_parts {
-                    let validation_result = validate_field(field_value, part);
-                    if let Some(error_msg) = validation_result {
-                        is_valid = false;
-                        errors.insert(field.clone(), Value::String(error_msg));
-                        break;
-                    }
-                }
-            }
-        }
-        if is_valid {
-            let mut result = HashMap::new();
+        let errors = rule_errors(&data, &rules);
+        if errors.is_empty() {
+            let mut result = Table::new();
             result.insert("valid".to_string(), Value::Boolean(true));
             result.insert("data".to_string(), Value::Table(data));
             Ok(Value::Table(result))
         } else {
-            let mut result = HashMap::new();
+            let mut result = Table::new();
             result.insert("valid".to_string(), Value::Boolean(false));
             result.insert("errors".to_string(), Value::Table(errors));
             Ok(Value::Table(result))
@@ -698,92 +910,90 @@ impl DewModule {
             Some(Value::Number(id)) => *id as usize,
             _ => 0,
         };
-        let mut servers = SERVERS.lock().unwrap();
+        let mut servers = lock(&SERVERS);
         if let Some(server) = servers.get_mut(server_id) {
             server.websocket_paths.push(path.clone());
             println!("🔌 WebSocket endpoint: {}", path);
         }
-        let mut ws = HashMap::new();
+        let mut ws = Table::new();
         ws.insert("path".to_string(), Value::String(path));
         ws.insert("__type__".to_string(), Value::String("WebSocket".to_string()));
         Ok(Value::Table(ws))
     }
+    /// `dew.ws_send(client, message)`: strings go out as text frames, tables and arrays as
+    /// JSON text, bytes as a binary frame. False when the client isn't connected.
     fn ws_send(args: &[Value]) -> MintasResult<Value> {
-        let _client_id = match args.get(0) {
-            Some(Value::String(s)) => s.clone(),
-            Some(Value::Number(n)) => n.to_string(),
-            _ => return Ok(Value::Boolean(false)),
+        let client_id = match args.first().and_then(ws_client_id) {
+            Some(id) => id,
+            None => return Ok(Value::Boolean(false)),
         };
-        let message = match args.get(1) {
-            Some(Value::String(s)) => s.clone(),
-            Some(Value::Table(t)) => value_to_json_string(&Value::Table(t.clone())),
+        let frame = match args.get(1).and_then(ws_message_frame) {
+            Some(frame) => frame,
+            None => return Ok(Value::Boolean(false)),
+        };
+        Ok(Value::Boolean(ws_write_frame(&client_id, &frame)))
+    }
+    fn ws_send_binary(args: &[Value]) -> MintasResult<Value> {
+        let client_id = match args.first().and_then(ws_client_id) {
+            Some(id) => id,
+            None => return Ok(Value::Boolean(false)),
+        };
+        let payload: &[u8] = match args.get(1) {
+            Some(Value::Bytes(b)) => b,
+            Some(Value::String(s)) => s.as_bytes(),
             _ => return Ok(Value::Boolean(false)),
         };
-        println!("📤 WS Send: {}", message);
-        Ok(Value::Boolean(true))
+        Ok(Value::Boolean(ws_write_frame(&client_id, &encode_ws_frame(WS_OPCODE_BINARY, payload))))
     }
+    /// Sends the message to every open connection; false only when the message can't be sent.
     fn ws_broadcast(args: &[Value]) -> MintasResult<Value> {
-        let message = match args.get(0) {
-            Some(Value::String(s)) => s.clone(),
-            Some(Value::Table(t)) => value_to_json_string(&Value::Table(t.clone())),
-            _ => return Ok(Value::Boolean(false)),
+        let frame = match args.first().and_then(ws_message_frame) {
+            Some(frame) => frame,
+            None => return Ok(Value::Boolean(false)),
         };
-        println!("📢 WS Broadcast: {}", message);
+        let clients: Vec<String> = lock(&WS_CONNECTIONS).keys().cloned().collect();
+        for id in &clients {
+            ws_write_frame(id, &frame);
+        }
         Ok(Value::Boolean(true))
     }
-    fn test_get(args: &[Value]) -> MintasResult<Value> {
-        let path = match args.get(0) {
-            Some(Value::String(s)) => s.clone(),
-            _ => "/".to_string(),
+    /// `dew.ws_close(client, code?, reason?)` sends a close frame (default 1000) and drops the connection.
+    fn ws_close(args: &[Value]) -> MintasResult<Value> {
+        let client_id = match args.first().and_then(ws_client_id) {
+            Some(id) => id,
+            None => return Ok(Value::Boolean(false)),
         };
-        let headers = match args.get(1) {
-            Some(Value::Table(t)) => t.clone(),
-            _ => HashMap::new(),
+        let code = match args.get(1) {
+            Some(Value::Number(n)) if (1000.0..5000.0).contains(n) => *n as u16,
+            _ => 1000,
         };
-        let mut response = HashMap::new();
-        response.insert("status".to_string(), Value::Number(200.0));
-        response.insert("method".to_string(), Value::String("GET".to_string()));
-        response.insert("path".to_string(), Value::String(path));
-        response.insert("headers".to_string(), Value::Table(headers));
-        response.insert("body".to_string(), Value::String(String::new()));
-        Ok(Value::Table(response))
-    }
-    fn test_post(args: &[Value]) -> MintasResult<Value> {
-        let path = match args.get(0) {
+        let reason = match args.get(2) {
             Some(Value::String(s)) => s.clone(),
-            _ => "/".to_string(),
+            _ => String::new(),
         };
-        let body = args.get(1).cloned().unwrap_or(Value::Empty);
-        let mut response = HashMap::new();
-        response.insert("status".to_string(), Value::Number(200.0));
-        response.insert("method".to_string(), Value::String("POST".to_string()));
-        response.insert("path".to_string(), Value::String(path));
-        response.insert("body".to_string(), body);
-        Ok(Value::Table(response))
+        let mut payload = code.to_be_bytes().to_vec();
+        // Control frame payloads are capped at 125 bytes
+        payload.extend(reason.bytes().take(123));
+        let sent = ws_write_frame(&client_id, &encode_ws_frame(WS_OPCODE_CLOSE, &payload));
+        if let Some(stream) = lock(&WS_CONNECTIONS).remove(&client_id) {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        for clients in lock(&WS_ROOMS).values_mut() {
+            clients.retain(|c| c != &client_id);
+        }
+        Ok(Value::Boolean(sent))
+    }
+    fn test_get(args: &[Value]) -> MintasResult<Value> {
+        test_request("GET", args, false)
+    }
+    fn test_post(args: &[Value]) -> MintasResult<Value> {
+        test_request("POST", args, true)
     }
     fn test_put(args: &[Value]) -> MintasResult<Value> {
-        let path = match args.get(0) {
-            Some(Value::String(s)) => s.clone(),
-            _ => "/".to_string(),
-        };
-        let body = args.get(1).cloned().unwrap_or(Value::Empty);
-        let mut response = HashMap::new();
-        response.insert("status".to_string(), Value::Number(200.0));
-        response.insert("method".to_string(), Value::String("PUT".to_string()));
-        response.insert("path".to_string(), Value::String(path));
-        response.insert("body".to_string(), body);
-        Ok(Value::Table(response))
+        test_request("PUT", args, true)
     }
     fn test_delete(args: &[Value]) -> MintasResult<Value> {
-        let path = match args.get(0) {
-            Some(Value::String(s)) => s.clone(),
-            _ => "/".to_string(),
-        };
-        let mut response = HashMap::new();
-        response.insert("status".to_string(), Value::Number(200.0));
-        response.insert("method".to_string(), Value::String("DELETE".to_string()));
-        response.insert("path".to_string(), Value::String(path));
-        Ok(Value::Table(response))
+        test_request("DELETE", args, false)
     }
     fn config(args: &[Value]) -> MintasResult<Value> {
         let config_path = match args.get(0) {
@@ -792,15 +1002,14 @@ impl DewModule {
         };
         if Path::new(&config_path).exists() {
             let content = fs::read_to_string(&config_path).unwrap_or_default();
-            let mut config = HashMap::new();
-            for line in content.lines() {
-                if let Some(idx) = line.find(':') {
-                    let key = line[..idx].trim().to_string();
-                    let value = line[idx + 1..].trim().to_string();
-                    config.insert(key, Value::String(value));
-                }
+            if config_path.ends_with(".json") {
+                parse_json_to_value(&content).map_err(|e| MintasError::RuntimeError {
+                    message: e,
+                    location: SourceLocation::new(0, 0),
+                })
+            } else {
+                parse_yaml_to_value(&content)
             }
-            Ok(Value::Table(config))
         } else {
             Ok(Value::Empty)
         }
@@ -840,6 +1049,55 @@ impl DewModule {
             Err(_) => Ok(default.unwrap_or(Value::Empty)),
         }
     }
+    /// The variable's raw value for the typed accessors; `None` when it is unset
+    fn env_lookup(function: &str, args: &[Value]) -> MintasResult<(String, Option<String>)> {
+        let key = match args.first() {
+            Some(Value::String(s)) => s.clone(),
+            _ => return Err(MintasError::TypeError {
+                message: format!("dew.{}() expects a variable name", function),
+                location: SourceLocation::new(0, 0),
+            }),
+        };
+        let value = std::env::var(&key).ok();
+        Ok((key, value))
+    }
+    fn env_number(args: &[Value]) -> MintasResult<Value> {
+        let (key, value) = Self::env_lookup("env_number", args)?;
+        let Some(value) = value else {
+            return Ok(args.get(1).cloned().unwrap_or(Value::Empty));
+        };
+        match value.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Value::Number(n)),
+            _ => Err(MintasError::TypeError {
+                message: format!("Environment variable {} is '{}', expected a number", key, value),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
+    fn env_bool(args: &[Value]) -> MintasResult<Value> {
+        let (key, value) = Self::env_lookup("env_bool", args)?;
+        let Some(value) = value else {
+            return Ok(args.get(1).cloned().unwrap_or(Value::Empty));
+        };
+        match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(Value::Boolean(true)),
+            "false" | "0" | "no" | "off" => Ok(Value::Boolean(false)),
+            _ => Err(MintasError::TypeError {
+                message: format!("Environment variable {} is '{}', expected true/false, 1/0, yes/no or on/off", key, value),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
+    fn env_required(args: &[Value]) -> MintasResult<Value> {
+        let (key, value) = Self::env_lookup("env_required", args)?;
+        match value {
+            Some(value) => Ok(Value::String(value)),
+            None => Err(MintasError::RuntimeError {
+                message: format!("Required environment variable {} is not set", key),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
     fn job(args: &[Value]) -> MintasResult<Value> {
         let name = match args.get(0) {
             Some(Value::String(s)) => s.clone(),
@@ -853,7 +1111,7 @@ impl DewModule {
             _ => 0,
         };
         let job_id = generate_job_id();
-        let mut jobs = JOBS.lock().unwrap();
+        let mut jobs = lock(&JOBS);
         jobs.insert(job_id.clone(), JobInfo {
             id: job_id.clone(),
             name: name.clone(),
@@ -863,7 +1121,7 @@ impl DewModule {
             data: args.get(2).cloned().unwrap_or(Value::Empty),
         });
         println!("📋 Job created: {} ({})", name, job_id);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("id".to_string(), Value::String(job_id));
         result.insert("name".to_string(), Value::String(name));
         result.insert("status".to_string(), Value::String("pending".to_string()));
@@ -875,7 +1133,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => "default".to_string(),
         };
-        let mut queues = QUEUES.lock().unwrap();
+        let mut queues = lock(&QUEUES);
         if !queues.contains_key(&queue_name) {
             queues.insert(queue_name.clone(), Vec::new());
             println!("📬 Queue created: {}", queue_name);
@@ -886,7 +1144,7 @@ impl DewModule {
                 println!("📬 Added item to queue: {}", queue_name);
             }
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("name".to_string(), Value::String(queue_name.clone()));
         result.insert("size".to_string(), Value::Number(queues.get(&queue_name).map(|q| q.len()).unwrap_or(0) as f64));
         result.insert("__type__".to_string(), Value::String("Queue".to_string()));
@@ -902,7 +1160,7 @@ impl DewModule {
         };
         let task_id = generate_job_id();
         println!("⚡ Task scheduled: {} ({})", name, task_id);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("id".to_string(), Value::String(task_id));
         result.insert("name".to_string(), Value::String(name));
         result.insert("status".to_string(), Value::String("scheduled".to_string()));
@@ -922,7 +1180,7 @@ impl DewModule {
             _ => "scheduled_task".to_string(),
         };
         println!("⏰ Scheduled task: {} with cron '{}'", task_name, cron_expr);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("cron".to_string(), Value::String(cron_expr));
         result.insert("task".to_string(), Value::String(task_name));
         result.insert("__type__".to_string(), Value::String("ScheduledTask".to_string()));
@@ -945,7 +1203,7 @@ impl DewModule {
             Some(Value::Number(n)) => *n as usize,
             _ => 1,
         };
-        let mut chunks = CHUNK_UPLOADS.lock().unwrap();
+        let mut chunks = lock(&CHUNK_UPLOADS);
         let upload = chunks.entry(upload_id.clone()).or_insert_with(|| ChunkUpload {
             id: upload_id.clone(),
             chunks: HashMap::new(),
@@ -956,7 +1214,7 @@ impl DewModule {
         upload.chunks.insert(chunk_index, chunk_data);
         let received = upload.chunks.len();
         println!("📦 Chunk {}/{} received for upload {}", chunk_index + 1, total_chunks, upload_id);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("upload_id".to_string(), Value::String(upload_id));
         result.insert("chunk_index".to_string(), Value::Number(chunk_index as f64));
         result.insert("received".to_string(), Value::Number(received as f64));
@@ -974,7 +1232,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => "uploads/".to_string(),
         };
-        let mut chunks = CHUNK_UPLOADS.lock().unwrap();
+        let mut chunks = lock(&CHUNK_UPLOADS);
         if let Some(upload) = chunks.remove(&upload_id) {
             let mut combined = String::new();
             for i in 0..upload.total_chunks {
@@ -1001,7 +1259,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => "all".to_string(),
         };
-        let mut config = HashMap::new();
+        let mut config = Table::new();
         match protection_type.as_str() {
             "csrf" => {
                 config.insert("csrf".to_string(), Value::Boolean(true));
@@ -1032,7 +1290,7 @@ impl DewModule {
     }
     fn csrf_token(_args: &[Value]) -> MintasResult<Value> {
         let token = generate_csrf_token();
-        let mut sessions = SESSIONS.lock().unwrap();
+        let mut sessions = lock(&SESSIONS);
         let session = sessions.entry("current".to_string()).or_insert_with(HashMap::new);
         session.insert("_csrf_token".to_string(), Value::String(token.clone()));
         Ok(Value::String(token))
@@ -1042,7 +1300,7 @@ impl DewModule {
             Some(Value::String(s)) => s.clone(),
             _ => return Ok(Value::Boolean(false)),
         };
-        let sessions = SESSIONS.lock().unwrap();
+        let sessions = lock(&SESSIONS);
         if let Some(session) = sessions.get("current") {
             if let Some(Value::String(stored_token)) = session.get("_csrf_token") {
                 return Ok(Value::Boolean(&provided_token == stored_token));
@@ -1060,11 +1318,11 @@ impl DewModule {
             _ => "html".to_string(),
         };
         let sanitized = match sanitize_type.as_str() {
-            "html" => sanitize_html(&input),
+            "html" => escape_html(&input),
             "sql" => sanitize_sql(&input),
             "js" => sanitize_js(&input),
             "url" => sanitize_url(&input),
-            _ => sanitize_html(&input),
+            _ => escape_html(&input),
         };
         Ok(Value::String(sanitized))
     }
@@ -1074,7 +1332,7 @@ impl DewModule {
             _ => "/ws".to_string(),
         };
         println!("🔌 WebSocket on_connect handler registered for {}", path);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("event".to_string(), Value::String("connect".to_string()));
         result.insert("path".to_string(), Value::String(path));
         result.insert("__type__".to_string(), Value::String("WSEventHandler".to_string()));
@@ -1086,7 +1344,7 @@ impl DewModule {
             _ => "/ws".to_string(),
         };
         println!("🔌 WebSocket on_disconnect handler registered for {}", path);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("event".to_string(), Value::String("disconnect".to_string()));
         result.insert("path".to_string(), Value::String(path));
         result.insert("__type__".to_string(), Value::String("WSEventHandler".to_string()));
@@ -1098,7 +1356,7 @@ impl DewModule {
             _ => "/ws".to_string(),
         };
         println!("🔌 WebSocket on_message handler registered for {}", path);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("event".to_string(), Value::String("message".to_string()));
         result.insert("path".to_string(), Value::String(path));
         result.insert("__type__".to_string(), Value::String("WSEventHandler".to_string()));
@@ -1110,7 +1368,7 @@ impl DewModule {
             _ => "/ws".to_string(),
         };
         println!("🔌 WebSocket on_error handler registered for {}", path);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("event".to_string(), Value::String("error".to_string()));
         result.insert("path".to_string(), Value::String(path));
         result.insert("__type__".to_string(), Value::String("WSEventHandler".to_string()));
@@ -1129,7 +1387,7 @@ impl DewModule {
             Some(Value::Number(n)) => n.to_string(),
             _ => "anonymous".to_string(),
         };
-        let mut rooms = WS_ROOMS.lock().unwrap();
+        let mut rooms = lock(&WS_ROOMS);
         let room_clients = rooms.entry(room.clone()).or_insert_with(Vec::new);
         if !room_clients.contains(&client_id) {
             room_clients.push(client_id.clone());
@@ -1147,7 +1405,7 @@ impl DewModule {
             Some(Value::Number(n)) => n.to_string(),
             _ => return Ok(Value::Boolean(false)),
         };
-        let mut rooms = WS_ROOMS.lock().unwrap();
+        let mut rooms = lock(&WS_ROOMS);
         if let Some(room_clients) = rooms.get_mut(&room) {
             room_clients.retain(|c| c != &client_id);
             println!("🚪 Client {} left room {}", client_id, room);
@@ -1165,7 +1423,7 @@ impl DewModule {
             Some(Value::Table(t)) => value_to_json_string(&Value::Table(t.clone())),
             _ => return Ok(Value::Boolean(false)),
         };
-        let rooms = WS_ROOMS.lock().unwrap();
+        let rooms = lock(&WS_ROOMS);
         if let Some(clients) = rooms.get(&room) {
             println!("📢 Broadcasting to room {} ({} clients): {}", room, clients.len(), message);
             return Ok(Value::Number(clients.len() as f64));
@@ -1173,18 +1431,21 @@ impl DewModule {
         Ok(Value::Number(0.0))
     }
     fn ws_rooms(_args: &[Value]) -> MintasResult<Value> {
-        let rooms = WS_ROOMS.lock().unwrap();
+        let rooms = lock(&WS_ROOMS);
         let room_list: Vec<Value> = rooms.keys()
             .map(|k| Value::String(k.clone()))
             .collect();
         Ok(Value::Array(room_list))
     }
+    /// `dew.ws_clients(room?)`: the ids `ws_send` and `ws_close` accept. Without a room, every
+    /// open connection plus any id joined to a room.
     fn ws_clients(args: &[Value]) -> MintasResult<Value> {
-        let room = match args.get(0) {
+        let room = match args.first() {
             Some(Value::String(s)) => Some(s.clone()),
             _ => None,
         };
-        let rooms = WS_ROOMS.lock().unwrap();
+        let mut all_clients: Vec<String> = lock(&WS_CONNECTIONS).keys().cloned().collect();
+        let rooms = lock(&WS_ROOMS);
         if let Some(room_name) = room {
             if let Some(clients) = rooms.get(&room_name) {
                 let client_list: Vec<Value> = clients.iter()
@@ -1194,7 +1455,8 @@ impl DewModule {
             }
             return Ok(Value::Array(Vec::new()));
         }
-        let mut all_clients: Vec<String> = Vec::new();
+        // ws-2 before ws-10
+        all_clients.sort_by(|a, b| (a.len(), a).cmp(&(b.len(), b)));
         for clients in rooms.values() {
             for client in clients {
                 if !all_clients.contains(client) {
@@ -1217,7 +1479,7 @@ impl DewModule {
             Some(Value::Number(n)) => *n as u16,
             _ => 200,
         };
-        let mut response = HashMap::new();
+        let mut response = Table::new();
         response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
         response.insert("response_type".to_string(), Value::String("text".to_string()));
         response.insert("body".to_string(), Value::String(body));
@@ -1233,17 +1495,21 @@ impl DewModule {
             Some(Value::Number(n)) => *n as u16,
             _ => 200,
         };
-        let mut response = HashMap::new();
+        let mut response = Table::new();
         response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
         response.insert("response_type".to_string(), Value::String("html".to_string()));
         response.insert("body".to_string(), Value::String(body));
         response.insert("status".to_string(), Value::Number(status as f64));
         Ok(Value::Table(response))
     }
+    /// `dew.json(data, status, pretty)`; `dew.json(data, true)` also works when the status is 200
     fn response_json(args: &[Value]) -> MintasResult<Value> {
+        let pretty = match (args.get(1), args.get(2)) {
+            (Some(Value::Boolean(b)), _) | (_, Some(Value::Boolean(b))) => *b,
+            _ => false,
+        };
         let body = match args.get(0) {
-            Some(Value::Table(t)) => value_to_json_string(&Value::Table(t.clone())),
-            Some(Value::Array(a)) => value_to_json_string(&Value::Array(a.clone())),
+            Some(data @ (Value::Table(_) | Value::Array(_))) => json_body(data, pretty),
             Some(Value::String(s)) => s.clone(),
             _ => "{}".to_string(),
         };
@@ -1251,7 +1517,7 @@ impl DewModule {
             Some(Value::Number(n)) => *n as u16,
             _ => 200,
         };
-        let mut response = HashMap::new();
+        let mut response = Table::new();
         response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
         response.insert("response_type".to_string(), Value::String("json".to_string()));
         response.insert("body".to_string(), Value::String(body));
@@ -1267,13 +1533,40 @@ impl DewModule {
             Some(Value::Boolean(b)) => *b,
             _ => false,
         };
-        let mut response = HashMap::new();
+        let mut response = Table::new();
         response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
         response.insert("response_type".to_string(), Value::String("redirect".to_string()));
         response.insert("location".to_string(), Value::String(location));
         response.insert("status".to_string(), Value::Number(if permanent { 301.0 } else { 302.0 }));
         Ok(Value::Table(response))
     }
+    /// `dew.stream(callback, [content_type], [status])`: a chunked response whose body comes from
+    /// `callback(n)` for n = 1, 2, ... until it returns empty, null or "", so large bodies are
+    /// never held in memory at once
+    fn response_stream(args: &[Value]) -> MintasResult<Value> {
+        let callback = match args.first() {
+            Some(Value::Function(func)) if func.params.len() <= 1 => func.clone(),
+            _ => return Err(MintasError::RuntimeError {
+                message: "dew.stream expects a function taking the chunk number".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        };
+        let content_type = match args.get(1) {
+            Some(Value::String(s)) => s.clone(),
+            _ => "text/plain; charset=utf-8".to_string(),
+        };
+        let status = match args.get(2) {
+            Some(Value::Number(n)) => *n as u16,
+            _ => 200,
+        };
+        let mut response = Table::new();
+        response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
+        response.insert("response_type".to_string(), Value::String("stream".to_string()));
+        response.insert("callback".to_string(), Value::Function(callback));
+        response.insert("content_type".to_string(), Value::String(content_type));
+        response.insert("status".to_string(), Value::Number(status as f64));
+        Ok(Value::Table(response))
+    }
     fn response_file(args: &[Value]) -> MintasResult<Value> {
         let file_path = match args.get(0) {
             Some(Value::String(s)) => s.clone(),
@@ -1288,7 +1581,7 @@ impl DewModule {
             let filename = Path::new(&file_path).file_name()
                 .map(|n| n.to_string_lossy().to_string())
                 .unwrap_or_else(|| "download".to_string());
-            let mut response = HashMap::new();
+            let mut response = Table::new();
             response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
             response.insert("response_type".to_string(), Value::String("file".to_string()));
             response.insert("body".to_string(), Value::String(String::from_utf8_lossy(&content).to_string()));
@@ -1348,6 +1641,40 @@ impl DewModule {
     #[cfg(not(feature = "magic"))]
     fn sha256(_args: &[Value]) -> MintasResult<Value> { Err(MintasError::RuntimeError { message: "Magic feature not enabled".to_string(), location: SourceLocation::new(0,0) }) }
 
+    /// `dew.jwt_sign(claims, secret, expires_in?)` issues an HS256 token. The secret may be
+    /// a string or the table returned by `dew.auth`.
+    #[cfg(feature = "magic")]
+    fn jwt_sign(args: &[Value]) -> MintasResult<Value> {
+        let mut claims = match args.first() {
+            Some(Value::Table(t)) => t.clone(),
+            _ => return Err(MintasError::RuntimeError {
+                message: "dew.jwt_sign expects a claims table".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        };
+        let secret = jwt_secret(args.get(1))?;
+        if let Some(Value::Number(ttl)) = args.get(2) {
+            claims.insert("exp".to_string(), Value::Number((current_timestamp() as f64 + ttl).floor()));
+        }
+        Ok(Value::String(jwt_encode(&claims, &secret)))
+    }
+    #[cfg(not(feature = "magic"))]
+    fn jwt_sign(_args: &[Value]) -> MintasResult<Value> { Err(MintasError::RuntimeError { message: "Magic feature not enabled".to_string(), location: SourceLocation::new(0,0) }) }
+
+    /// `dew.jwt_verify(token, secret)` returns the claims table, or null when the token is
+    /// malformed, signed with another key or past its `exp`/before its `nbf`.
+    #[cfg(feature = "magic")]
+    fn jwt_verify(args: &[Value]) -> MintasResult<Value> {
+        let token = match args.first() {
+            Some(Value::String(s)) => s.strip_prefix("Bearer ").unwrap_or(s).trim().to_string(),
+            _ => return Ok(Value::Null),
+        };
+        let secret = jwt_secret(args.get(1))?;
+        Ok(jwt_decode(&token, &secret, current_timestamp()).map(Value::Table).unwrap_or(Value::Null))
+    }
+    #[cfg(not(feature = "magic"))]
+    fn jwt_verify(_args: &[Value]) -> MintasResult<Value> { Err(MintasError::RuntimeError { message: "Magic feature not enabled".to_string(), location: SourceLocation::new(0,0) }) }
+
     #[cfg(feature = "magic")]
     fn csv_parse(args: &[Value]) -> MintasResult<Value> {
         let content = match args.get(0) { Some(Value::String(s)) => s, _ => "" };
@@ -1361,7 +1688,7 @@ impl DewModule {
         };
         for rec in rdr.records() {
             if let Ok(record) = rec {
-                let mut map = HashMap::new();
+                let mut map = Table::new();
                 for (i, field) in record.iter().enumerate() {
                     if i < headers.len() {
                         map.insert(headers[i].clone(), Value::String(field.to_string()));
@@ -1408,18 +1735,18 @@ impl DewModule {
         // Usage: let peer = dew.webrtc_peer({id: "peer1", config: {...}})
         let config = match args.get(0) {
             Some(Value::Table(map)) => map.clone(),
-            _ => HashMap::new(),
+            _ => Table::new(),
         };
         
         let peer_id = config.get("id").and_then(|v| match v { Value::String(s) => Some(s.clone()), _ => None })
             .unwrap_or_else(|| format!("peer_{}", std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()));
         
-        let mut peer_config = HashMap::new();
+        let mut peer_config = Table::new();
         peer_config.insert("id".to_string(), Value::String(peer_id));
         peer_config.insert("state".to_string(), Value::String("new".to_string()));
         peer_config.insert("connection_state".to_string(), Value::String("new".to_string()));
         peer_config.insert("ice_connection_state".to_string(), Value::String("new".to_string()));
-        peer_config.insert("data_channels".to_string(), Value::Table(HashMap::new()));
+        peer_config.insert("data_channels".to_string(), Value::Table(Table::new()));
         peer_config.insert("created_at".to_string(), Value::String(chrono::Local::now().to_rfc3339()));
         
         Ok(Value::Table(peer_config))
@@ -1433,7 +1760,7 @@ impl DewModule {
                 let peer_id = peer.get("id").and_then(|v| match v { Value::String(s) => Some(s.clone()), _ => None })
                     .unwrap_or_else(|| "unknown".to_string());
                 
-                let mut offer = HashMap::new();
+                let mut offer = Table::new();
                 offer.insert("type".to_string(), Value::String("offer".to_string()));
                 offer.insert("peer_id".to_string(), Value::String(peer_id));
                 offer.insert("sdp".to_string(), Value::String(format!("v=0\r\no=mintas {} 0 IN IP4 127.0.0.1\r\ns=Mintas WebRTC\r\nt=0 0\r\n", 
@@ -1457,7 +1784,7 @@ impl DewModule {
                 let peer_id = offer.get("peer_id").and_then(|v| match v { Value::String(s) => Some(s.clone()), _ => None })
                     .unwrap_or_else(|| "unknown".to_string());
                 
-                let mut answer = HashMap::new();
+                let mut answer = Table::new();
                 answer.insert("type".to_string(), Value::String("answer".to_string()));
                 answer.insert("peer_id".to_string(), Value::String(peer_id));
                 answer.insert("sdp".to_string(), Value::String(format!("v=0\r\no=mintas {} 0 IN IP4 127.0.0.1\r\ns=Mintas WebRTC\r\nt=0 0\r\n", 
@@ -1481,7 +1808,7 @@ impl DewModule {
                 let label = config.get("label").and_then(|v| match v { Value::String(s) => Some(s.clone()), _ => None })
                     .unwrap_or_else(|| "data".to_string());
                 
-                let mut channel = HashMap::new();
+                let mut channel = Table::new();
                 channel.insert("label".to_string(), Value::String(label));
                 channel.insert("state".to_string(), Value::String("connecting".to_string()));
                 channel.insert("buffered_amount".to_string(), Value::Number(0.0));
@@ -1511,7 +1838,7 @@ impl DewModule {
                     _ => "null".to_string(),
                 };
                 
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("sent".to_string(), Value::Boolean(true));
                 result.insert("bytes".to_string(), Value::Number(message.len() as f64));
                 result.insert("timestamp".to_string(), Value::String(chrono::Local::now().to_rfc3339()));
@@ -1534,11 +1861,11 @@ impl DewModule {
     fn webrtc_close(args: &[Value]) -> MintasResult<Value> {
         // Closes a peer connection or data channel
         // Usage: dew.webrtc_close(peer) or dew.webrtc_close(datachannel)
-        match args.get(0) {
+        match args.first() {
             Some(Value::Table(obj)) => {
                 let obj_type = if obj.contains_key("data_channels") { "peer" } else { "datachannel" };
                 
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("closed".to_string(), Value::Boolean(true));
                 result.insert("type".to_string(), Value::String(obj_type.to_string()));
                 result.insert("timestamp".to_string(), Value::String(chrono::Local::now().to_rfc3339()));
@@ -1555,7 +1882,7 @@ impl DewModule {
     fn webrtc_stats(_args: &[Value]) -> MintasResult<Value> {
         // Gets statistics for a peer connection
         // Usage: let stats = dew.webrtc_stats(peer)
-        let mut stats = HashMap::new();
+        let mut stats = Table::new();
         stats.insert("bytes_sent".to_string(), Value::Number(0.0));
         stats.insert("bytes_received".to_string(), Value::Number(0.0));
         stats.insert("packets_lost".to_string(), Value::Number(0.0));
@@ -1569,6 +1896,44 @@ impl DewModule {
         Ok(Value::Table(stats))
     }
 
+    /// `dew.webrtc_signaling(server, path?)` serves a WebSocket relay at `path` (default
+    /// `/signal`). Peers connect as `/signal?id=alice` and exchange offer, answer and ICE
+    /// candidate messages addressed by peer id; see `relay_signal` for the message format.
+    fn webrtc_signaling(args: &[Value]) -> MintasResult<Value> {
+        let server_id = match args.first() {
+            Some(Value::Table(server)) => match server.get("__dew_server_id__") {
+                Some(Value::Number(id)) => *id as usize,
+                _ => return Err(MintasError::RuntimeError {
+                    message: "dew.webrtc_signaling expects a Dew server as its first argument".to_string(),
+                    location: SourceLocation::new(0, 0),
+                }),
+            },
+            _ => return Err(MintasError::RuntimeError {
+                message: "dew.webrtc_signaling expects a Dew server as its first argument".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        };
+        let path = match args.get(1) {
+            Some(Value::String(s)) => s.clone(),
+            _ => "/signal".to_string(),
+        };
+        match lock(&SERVERS).get_mut(server_id) {
+            Some(server) => {
+                server.websocket_paths.push(path.clone());
+                server.signaling_paths.push(path.clone());
+                println!("📡 WebRTC signaling relay: {}", path);
+            }
+            None => return Err(MintasError::RuntimeError {
+                message: "Server not found".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+        let mut relay = Table::new();
+        relay.insert("path".to_string(), Value::String(path));
+        relay.insert("__type__".to_string(), Value::String("SignalingRelay".to_string()));
+        Ok(Value::Table(relay))
+    }
+
     // JavaScript Event Handling Implementation
     fn js_onclick(args: &[Value]) -> MintasResult<Value> {
         // Generates JS code for click events
@@ -1959,7 +2324,7 @@ impl DewModule {
         
         let _validation_rules = match args.get(1) {
             Some(Value::Table(t)) => t.clone(),
-            _ => HashMap::new(),
+            _ => Table::new(),
         };
         
         let mut js_code = format!(
@@ -2025,11 +2390,30 @@ lazy_static::lazy_static! {
     static ref SERVERS: Mutex<ServerRegistry> = Mutex::new(ServerRegistry::new());
     static ref SESSIONS: Mutex<HashMap<String, HashMap<String, Value>>> = Mutex::new(HashMap::new());
     static ref COOKIES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
-    static ref UPLOADS: Mutex<HashMap<String, Value>> = Mutex::new(HashMap::new());
     static ref JOBS: Mutex<HashMap<String, JobInfo>> = Mutex::new(HashMap::new());
     static ref QUEUES: Mutex<HashMap<String, Vec<Value>>> = Mutex::new(HashMap::new());
     static ref CHUNK_UPLOADS: Mutex<HashMap<String, ChunkUpload>> = Mutex::new(HashMap::new());
     static ref WS_ROOMS: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+    static ref WS_CONNECTIONS: Mutex<HashMap<String, std::net::TcpStream>> = Mutex::new(HashMap::new());
+    /// Signaling peer id -> WebSocket client id
+    static ref SIGNAL_PEERS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+/// Access log formats accepted by `dew.logger`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LogFormat {
+    Combined,
+    Common,
+    Json,
+}
+impl LogFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "combined" => Some(LogFormat::Combined),
+            "common" => Some(LogFormat::Common),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
 }
 struct ServerRegistry {
     servers: Vec<DewServer>,
@@ -2049,6 +2433,9 @@ impl ServerRegistry {
     fn get_mut(&mut self, id: usize) -> Option<&mut DewServer> {
         self.servers.get_mut(id)
     }
+    fn latest(&self) -> Option<&DewServer> {
+        self.servers.last()
+    }
 }
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Method {
@@ -2089,6 +2476,7 @@ pub struct Getback {
     pub query: HashMap<String, String>,
     pub params: HashMap<String, String>,
     pub body: String,
+    pub raw_body: Vec<u8>,
     pub ip: String,
     pub cookies: HashMap<String, String>,
 }
@@ -2102,32 +2490,93 @@ impl Getback {
             query: HashMap::new(),
             params: HashMap::new(),
             body: String::new(),
+            raw_body: Vec::new(),
             ip: String::new(),
             cookies: HashMap::new(),
         }
     }
+    fn multipart_parts(&self) -> Option<Vec<MultipartPart>> {
+        let content_type = self.headers.get("content-type")?;
+        if !content_type.contains("multipart/form-data") {
+            return None;
+        }
+        Some(parse_multipart(content_type, &self.raw_body))
+    }
+    /// First user-supplied value (query, route param or form/JSON body field) that looks like
+    /// SQL injection, described as e.g. `query 'id'`.
+    pub fn sql_injection_source(&self) -> Option<String> {
+        fn scan_value(value: &Value, label: &str) -> Option<String> {
+            match value {
+                Value::String(s) if detect_sql_injection(s) => Some(label.to_string()),
+                Value::Array(items) => items.iter().find_map(|item| scan_value(item, label)),
+                Value::Table(fields) => fields.iter().find_map(|(k, v)| scan_value(v, &format!("{}.{}", label, k))),
+                _ => None,
+            }
+        }
+        for (source, values) in [("query", &self.query), ("param", &self.params)] {
+            if let Some((key, _)) = values.iter().find(|(_, v)| detect_sql_injection(v)) {
+                return Some(format!("{} '{}'", source, key));
+            }
+        }
+        match self.to_value() {
+            Value::Table(request) => ["json", "form"].iter().find_map(|field| {
+                request.get(*field).and_then(|v| scan_value(v, &format!("body {}", field)))
+            }),
+            _ => None,
+        }
+    }
+    /// Takes back what a script changed on the table `to_value` built, so middleware can
+    /// rewrite the request the route handler sees
+    pub fn apply_value(&mut self, value: &Value) {
+        let map = match value {
+            Value::Table(map) => map,
+            _ => return,
+        };
+        let text = |key: &str| match map.get(key) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let strings = |key: &str| match map.get(key) {
+            Some(Value::Table(t)) => Some(t.iter().map(|(k, v)| (k.to_string(), value_to_string(v))).collect::<HashMap<_, _>>()),
+            _ => None,
+        };
+        if let Some(method) = text("method") { self.method = method; }
+        if let Some(path) = text("path") { self.path = path; }
+        if let Some(url) = text("url") { self.url = url; }
+        if let Some(ip) = text("ip") { self.ip = ip; }
+        if let Some(body) = text("body") {
+            if body != self.body {
+                self.raw_body = body.clone().into_bytes();
+                self.body = body;
+            }
+        }
+        if let Some(headers) = strings("headers") { self.headers = headers; }
+        if let Some(query) = strings("query") { self.query = query; }
+        if let Some(params) = strings("params") { self.params = params; }
+        if let Some(cookies) = strings("cookies") { self.cookies = cookies; }
+    }
     pub fn to_value(&self) -> Value {
-        let mut map = HashMap::new();
+        let mut map = Table::new();
         map.insert("method".to_string(), Value::String(self.method.clone()));
         map.insert("path".to_string(), Value::String(self.path.clone()));
         map.insert("url".to_string(), Value::String(self.url.clone()));
         map.insert("body".to_string(), Value::String(self.body.clone()));
         map.insert("ip".to_string(), Value::String(self.ip.clone()));
         // Headers as table
-        let headers_map: HashMap<String, Value> = self.headers
+        let headers_map: Table = self.headers
             .iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
         map.insert("headers".to_string(), Value::Table(headers_map));
         // Query params as table
-        let query_map: HashMap<String, Value> = self.query
+        let query_map: Table = self.query
             .iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
         map.insert("query".to_string(), Value::Table(query_map));
         // Path params as table
-        let params_map: HashMap<String, Value> = self.params
+        let params_map: Table = self.params
             .iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
         map.insert("params".to_string(), Value::Table(params_map.clone()));
         map.insert("param".to_string(), Value::Table(params_map));
         // Cookies as table
-        let cookies_map: HashMap<String, Value> = self.cookies
+        let cookies_map: Table = self.cookies
             .iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
         map.insert("cookies".to_string(), Value::Table(cookies_map));
         // JSON body parser
@@ -2141,14 +2590,23 @@ impl Getback {
             let form_data = parse_form_data(&self.body);
             map.insert("form".to_string(), Value::Table(form_data));
         }
+        // Multipart parser: text fields go to form, files to files
+        if let Some(parts) = self.multipart_parts() {
+            let mut form_data = Table::new();
+            for part in parts.iter().filter(|p| p.filename.is_none()) {
+                form_data.insert(part.name.clone(), Value::String(String::from_utf8_lossy(&part.data).to_string()));
+            }
+            map.insert("form".to_string(), Value::Table(form_data));
+            map.insert("files".to_string(), Value::Table(uploads_from_parts(&parts)));
+        }
         map.insert("__type__".to_string(), Value::String("Getback".to_string()));
         Value::Table(map)
     }
     pub fn json(&self) -> Value {
-        parse_json_to_value(&self.body).unwrap_or(Value::Empty)
+        body_json(&self.body)
     }
-    pub fn form(&self) -> HashMap<String, Value> {
-        parse_form_data(&self.body)
+    pub fn form(&self) -> Value {
+        body_form(&self.body)
     }
     pub fn text(&self) -> String {
         self.body.clone()
@@ -2219,7 +2677,11 @@ impl DewResponse {
 pub struct Middleware {
     pub name: String,
     pub handler_body: Option<Vec<crate::parser::Expr>>,
+    /// `(req, next)` for script middleware registered with `dew.use(server, func)`
+    pub params: Vec<String>,
 }
+/// Name of middleware registered from a script function rather than a built-in name
+const SCRIPT_MIDDLEWARE: &str = "script";
 /// Error handler
 #[derive(Clone)]
 pub struct ErrorHandler {
@@ -2238,13 +2700,16 @@ pub struct Route {
     pub path: String,
     pub handler: RouteHandler,
     pub validation: Option<HashMap<String, String>>,
+    /// Overrides the server's `timeout` for this route's handler
+    pub timeout: Option<std::time::Duration>,
 }
-/// Route group
+/// Route group; `prefix` already includes the prefixes of enclosing groups
 #[derive(Clone)]
 pub struct RouteGroup {
     pub prefix: String,
     pub routes: Vec<Route>,
-    pub middleware: Vec<String>,
+    pub middleware: Vec<Middleware>,
+    pub parent: Option<usize>,
 }
 // ==================== PHASE 6 CONFIG STRUCTS ====================
 /// Database configuration
@@ -2314,6 +2779,8 @@ pub struct ChunkUpload {
     pub filename: String,
     pub content_type: String,
 }
+/// Request body cap when `serve` gets no `max_body_size`
+const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
 /// Dew Server
 #[derive(Clone)]
 pub struct DewServer {
@@ -2325,6 +2792,7 @@ pub struct DewServer {
     pub error_handlers: HashMap<u16, ErrorHandler>,
     pub groups: Vec<RouteGroup>,
     pub websocket_paths: Vec<String>,
+    pub signaling_paths: Vec<String>,
     pub cors_config: Option<HashMap<String, String>>,
     // Phase 6 additions
     pub config: HashMap<String, Value>,
@@ -2344,6 +2812,7 @@ impl DewServer {
             error_handlers: HashMap::new(),
             groups: Vec::new(),
             websocket_paths: Vec::new(),
+            signaling_paths: Vec::new(),
             cors_config: None,
             // Phase 6 additions
             config: HashMap::new(),
@@ -2353,26 +2822,59 @@ impl DewServer {
             security: SecurityConfig::default(),
         }
     }
-    pub fn add_route(&mut self, method: Method, path: &str, handler: RouteHandler) {
-        self.routes.push(Route {
+    /// Routes declared inside a group live on that group; `None` is the server itself
+    fn routes_mut(&mut self, group: Option<usize>) -> &mut Vec<Route> {
+        match group {
+            Some(index) if index < self.groups.len() => &mut self.groups[index].routes,
+            _ => &mut self.routes,
+        }
+    }
+    pub fn add_route(&mut self, group: Option<usize>, method: Method, path: &str, handler: RouteHandler) -> &mut Route {
+        let routes = self.routes_mut(group);
+        routes.push(Route {
             method,
             path: path.to_string(),
             handler,
             validation: None,
+            timeout: None,
         });
+        routes.last_mut().expect("route just added")
+    }
+    pub fn add_route_with_validation(&mut self, group: Option<usize>, method: Method, path: &str, handler: RouteHandler, validation: HashMap<String, String>) -> &mut Route {
+        let route = self.add_route(group, method, path, handler);
+        route.validation = Some(validation);
+        route
+    }
+    /// Largest request body accepted, in bytes (`max_body_size` option); a larger one gets 413
+    pub fn max_body_size(&self) -> usize {
+        match self.config.get("max_body_size") {
+            Some(Value::Number(bytes)) if *bytes >= 0.0 => *bytes as usize,
+            _ => DEFAULT_MAX_BODY_SIZE,
+        }
     }
-    pub fn add_route_with_validation(&mut self, method: Method, path: &str, handler: RouteHandler, validation: HashMap<String, String>) {
-        self.routes.push(Route {
-            method,
-            path: path.to_string(),
-            handler,
-            validation: Some(validation),
-        });
+    /// The access log format set by `dew.logger` or `@app.use("logger")`, if any
+    fn log_format(&self) -> Option<LogFormat> {
+        match self.config.get("log_format") {
+            Some(Value::String(name)) => LogFormat::parse(name),
+            _ => None,
+        }
+    }
+    /// The handler budget for `route`: its own `timeout`, else the one given to `serve`
+    pub fn handler_timeout(&self, route: &Route) -> Option<std::time::Duration> {
+        route.timeout.or_else(|| match self.config.get("timeout") {
+            Some(Value::Number(ms)) if *ms > 0.0 => Some(std::time::Duration::from_millis(*ms as u64)),
+            _ => None,
+        })
     }
-    pub fn add_middleware(&mut self, name: &str, handler: Option<Vec<crate::parser::Expr>>) {
+    pub fn add_middleware(&mut self, name: &str, handler: Option<crate::evaluator::Function>) {
+        let (handler_body, params) = match handler {
+            Some(func) => (Some(func.body), func.params),
+            None => (None, Vec::new()),
+        };
         self.middleware.push(Middleware {
             name: name.to_string(),
-            handler_body: handler,
+            handler_body,
+            params,
         });
     }
     pub fn add_before_handler(&mut self, handler: Vec<crate::parser::Expr>) {
@@ -2390,13 +2892,27 @@ impl DewServer {
     pub fn add_static_dir(&mut self, url_path: &str, dir_path: &str) {
         self.static_dirs.push((url_path.to_string(), dir_path.to_string()));
     }
-    pub fn find_route(&self, method: &str, path: &str) -> Option<(&Route, HashMap<String, String>)> {
+    pub fn add_group(&mut self, prefix: &str, parent: Option<usize>) -> usize {
+        let prefix = match parent.and_then(|index| self.groups.get(index)) {
+            Some(outer) => format!("{}{}", outer.prefix, prefix),
+            None => prefix.to_string(),
+        };
+        self.groups.push(RouteGroup {
+            prefix,
+            routes: Vec::new(),
+            middleware: Vec::new(),
+            parent,
+        });
+        self.groups.len() - 1
+    }
+    /// Returns the matched route with its path params and, for grouped routes, the group
+    pub fn find_route(&self, method: &str, path: &str) -> Option<(&Route, HashMap<String, String>, Option<&RouteGroup>)> {
         let method_enum = Method::from_str(method)?;
         // Check direct routes first
         for route in &self.routes {
             if route.method == method_enum {
                 if let Some(params) = match_path(&route.path, path) {
-                    return Some((route, params));
+                    return Some((route, params, None));
                 }
             }
         }
@@ -2406,13 +2922,23 @@ impl DewServer {
                 if route.method == method_enum {
                     let full_path = format!("{}{}", group.prefix, route.path);
                     if let Some(params) = match_path(&full_path, path) {
-                        return Some((route, params));
+                        return Some((route, params, Some(group)));
                     }
                 }
             }
         }
         None
     }
+    /// Middleware of `group` and every enclosing group, outermost first
+    pub fn group_middleware<'a>(&'a self, group: &'a RouteGroup) -> Vec<&'a Middleware> {
+        let mut chain = vec![group];
+        let mut parent = group.parent;
+        while let Some(outer) = parent.and_then(|index| self.groups.get(index)) {
+            chain.push(outer);
+            parent = outer.parent;
+        }
+        chain.iter().rev().flat_map(|g| g.middleware.iter()).collect()
+    }
     pub fn find_static_file(&self, path: &str) -> Option<String> {
         for (url_prefix, dir_path) in &self.static_dirs {
             if path.starts_with(url_prefix) {
@@ -2431,16 +2957,60 @@ impl DewServer {
         None
     }
 }
-fn match_path(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
-    let pattern_parts: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
-    let path_parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-    if pattern_parts.len() != path_parts.len() {
-        return None;
-    }
-    let mut params = HashMap::new();
-    for (pattern_part, path_part) in pattern_parts.iter().zip(path_parts.iter()) {
-        if pattern_part.starts_with('>') {
-            // Path parameter: />id captures the value
+/// Rewrites `:name` placeholders to numbered `?N` parameters, returning the SQL and the name
+/// bound to each number. Quoted text and `::` casts are left alone; a repeated name reuses its slot.
+fn bind_named_params(template: &str) -> (String, Vec<String>) {
+    let mut sql = String::with_capacity(template.len());
+    let mut names: Vec<String> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), _) => {
+                if c == q {
+                    quote = None;
+                }
+                sql.push(c);
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                sql.push(c);
+            }
+            (None, ':') if chars.peek() == Some(&':') => {
+                chars.next();
+                sql.push_str("::");
+            }
+            (None, ':') if chars.peek().is_some_and(|n| n.is_ascii_alphabetic() || *n == '_') => {
+                let mut name = String::new();
+                while let Some(&n) = chars.peek().filter(|n| n.is_ascii_alphanumeric() || **n == '_') {
+                    name.push(n);
+                    chars.next();
+                }
+                let slot = match names.iter().position(|existing| *existing == name) {
+                    Some(i) => i + 1,
+                    None => {
+                        names.push(name);
+                        names.len()
+                    }
+                };
+                sql.push_str(&format!("?{}", slot));
+            }
+            _ => sql.push(c),
+        }
+    }
+    (sql, names)
+}
+
+fn match_path(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+    let pattern_parts: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path_parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if pattern_parts.len() != path_parts.len() {
+        return None;
+    }
+    let mut params = HashMap::new();
+    for (pattern_part, path_part) in pattern_parts.iter().zip(path_parts.iter()) {
+        if pattern_part.starts_with('>') {
+            // Path parameter: />id captures the value
             let param_name = &pattern_part[1..];
             params.insert(param_name.to_string(), path_part.to_string());
         } else if *pattern_part != *path_part {
@@ -2449,22 +3019,30 @@ fn match_path(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
     }
     Some(params)
 }
+/// A route's `timeout = ...`: milliseconds, or a duration string such as "30s" or "500ms"
+fn route_timeout(path: &str, timeout: Option<Value>) -> MintasResult<Option<std::time::Duration>> {
+    let limit = match &timeout {
+        None => return Ok(None),
+        Some(Value::Number(ms)) if *ms > 0.0 => Some(std::time::Duration::from_millis(*ms as u64)),
+        Some(Value::String(s)) => crate::evaluator::parse_duration(s, 1.0).filter(|limit| !limit.is_zero()),
+        _ => None,
+    };
+    limit.map(Some).ok_or_else(|| MintasError::RuntimeError {
+        message: format!("Invalid timeout for route {}: expected milliseconds or a duration like \"30s\"", path),
+        location: SourceLocation::new(0, 0),
+    })
+}
 /// Add route to server (called from evaluator)
-pub fn add_server_route(server_id: usize, method: &str, path: &str, handler_body: Vec<crate::parser::Expr>) -> MintasResult<()> {
+pub fn add_server_route(server_id: usize, method: &str, path: &str, handler_body: Vec<crate::parser::Expr>, timeout: Option<Value>) -> MintasResult<()> {
     let method_enum = Method::from_str(method).ok_or_else(|| MintasError::RuntimeError {
         message: format!("Invalid HTTP method: {}", method),
         location: SourceLocation::new(0, 0),
     })?;
-    // Get current group prefix and prepend to path
-    let group_prefix = get_current_group_prefix(server_id);
-    let full_path = if group_prefix.is_empty() {
-        path.to_string()
-    } else {
-        format!("{}{}", group_prefix, path)
-    };
-    let mut servers = SERVERS.lock().unwrap();
+    let timeout = route_timeout(path, timeout)?;
+    let group = current_route_group(server_id);
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
-        server.add_route(method_enum, &full_path, RouteHandler { handler_body });
+        server.add_route(group, method_enum, path, RouteHandler { handler_body }).timeout = timeout;
         Ok(())
     } else {
         Err(MintasError::RuntimeError {
@@ -2479,35 +3057,31 @@ pub fn add_server_validated_route(
     method: &str, 
     path: &str, 
     validation_rules: Value,
-    handler_body: Vec<crate::parser::Expr>
+    handler_body: Vec<crate::parser::Expr>,
+    timeout: Option<Value>,
 ) -> MintasResult<()> {
     let method_enum = Method::from_str(method).ok_or_else(|| MintasError::RuntimeError {
         message: format!("Invalid HTTP method: {}", method),
         location: SourceLocation::new(0, 0),
     })?;
-    // Get current group prefix and prepend to path
-    let group_prefix = get_current_group_prefix(server_id);
-    let full_path = if group_prefix.is_empty() {
-        path.to_string()
-    } else {
-        format!("{}{}", group_prefix, path)
-    };
+    let timeout = route_timeout(path, timeout)?;
+    let group = current_route_group(server_id);
     // Convert validation rules Value to HashMap<String, String>
     let validation = match validation_rules {
         Value::Table(map) => {
             let mut rules = HashMap::new();
             for (key, value) in map {
                 if let Value::String(rule) = value {
-                    rules.insert(key, rule);
+                    rules.insert(key.to_string(), rule);
                 }
             }
             rules
         }
         _ => HashMap::new(),
     };
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
-        server.add_route_with_validation(method_enum, &full_path, RouteHandler { handler_body }, validation);
+        server.add_route_with_validation(group, method_enum, path, RouteHandler { handler_body }, validation).timeout = timeout;
         Ok(())
     } else {
         Err(MintasError::RuntimeError {
@@ -2518,7 +3092,7 @@ pub fn add_server_validated_route(
 }
 /// Add error handler to server
 pub fn add_server_error_handler(server_id: usize, status_code: u16, handler_body: Vec<crate::parser::Expr>) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         server.add_error_handler(status_code, handler_body);
         Ok(())
@@ -2529,11 +3103,19 @@ pub fn add_server_error_handler(server_id: usize, status_code: u16, handler_body
         })
     }
 }
-/// Add before handler to server
+/// Add before handler to server; inside a route group it only guards that group's routes
 pub fn add_server_before_handler(server_id: usize, handler_body: Vec<crate::parser::Expr>) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let group = current_route_group(server_id);
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
-        server.add_before_handler(handler_body);
+        match group.and_then(|index| server.groups.get_mut(index)) {
+            Some(group) => group.middleware.push(Middleware {
+                name: "before".to_string(),
+                handler_body: Some(handler_body),
+                params: Vec::new(),
+            }),
+            None => server.add_before_handler(handler_body),
+        }
         Ok(())
     } else {
         Err(MintasError::RuntimeError {
@@ -2544,7 +3126,7 @@ pub fn add_server_before_handler(server_id: usize, handler_body: Vec<crate::pars
 }
 /// Add after handler to server
 pub fn add_server_after_handler(server_id: usize, handler_body: Vec<crate::parser::Expr>) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         server.add_after_handler(handler_body);
         Ok(())
@@ -2557,7 +3139,7 @@ pub fn add_server_after_handler(server_id: usize, handler_body: Vec<crate::parse
 }
 /// Add static directory to server
 pub fn add_server_static(server_id: usize, url_path: &str, dir_path: &str) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         server.add_static_dir(url_path, dir_path);
         println!("📁 Static files: {} -> {}", url_path, dir_path);
@@ -2571,9 +3153,12 @@ pub fn add_server_static(server_id: usize, url_path: &str, dir_path: &str) -> Mi
 }
 /// Add middleware to server
 pub fn add_server_middleware(server_id: usize, middleware_name: &str) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         server.add_middleware(middleware_name, None);
+        if middleware_name == "logger" {
+            server.config.entry("log_format".to_string()).or_insert_with(|| Value::String("combined".to_string()));
+        }
         println!("🔧 Middleware enabled: {}", middleware_name);
         Ok(())
     } else {
@@ -2583,53 +3168,45 @@ pub fn add_server_middleware(server_id: usize, middleware_name: &str) -> MintasR
         })
     }
 }
-// Global state for route groups
+// Global state for route groups: per server, the stack of open group indices
 lazy_static::lazy_static! {
-    static ref CURRENT_GROUP_PREFIX: Mutex<HashMap<usize, String>> = Mutex::new(HashMap::new());
+    static ref CURRENT_GROUPS: Mutex<HashMap<usize, Vec<usize>>> = Mutex::new(HashMap::new());
 }
 /// Start a route group context
 pub fn start_route_group(server_id: usize, prefix: &str) -> MintasResult<()> {
-    let mut group_prefixes = CURRENT_GROUP_PREFIX.lock().unwrap();
-    // Append to existing prefix if nested
-    let current = group_prefixes.get(&server_id).cloned().unwrap_or_default();
-    let new_prefix = format!("{}{}", current, prefix);
-    group_prefixes.insert(server_id, new_prefix.clone());
-    println!("📂 Route group: {}", new_prefix);
+    let parent = current_route_group(server_id);
+    let mut servers = lock(&SERVERS);
+    let server = servers.get_mut(server_id).ok_or_else(|| MintasError::RuntimeError {
+        message: "Server not found".to_string(),
+        location: SourceLocation::new(0, 0),
+    })?;
+    let index = server.add_group(prefix, parent);
+    println!("📂 Route group: {}", server.groups[index].prefix);
+    drop(servers);
+    lock(&CURRENT_GROUPS).entry(server_id).or_default().push(index);
     Ok(())
 }
 /// End a route group context
 pub fn end_route_group(server_id: usize) -> MintasResult<()> {
-    let mut group_prefixes = CURRENT_GROUP_PREFIX.lock().unwrap();
-    // Remove the last segment of the prefix
-    if let Some(prefix) = group_prefixes.get_mut(&server_id) {
-        // Find the last '/' and remove everything after it
-        if let Some(last_slash) = prefix.rfind('/') {
-            if last_slash > 0 {
-                prefix.truncate(last_slash);
-            } else {
-                prefix.clear();
-            }
-        } else {
-            prefix.clear();
-        }
+    if let Some(stack) = lock(&CURRENT_GROUPS).get_mut(&server_id) {
+        stack.pop();
     }
     Ok(())
 }
-/// Get current group prefix for a server
-pub fn get_current_group_prefix(server_id: usize) -> String {
-    CURRENT_GROUP_PREFIX.lock().unwrap()
+/// Innermost open route group for a server
+pub fn current_route_group(server_id: usize) -> Option<usize> {
+    lock(&CURRENT_GROUPS)
         .get(&server_id)
-        .cloned()
-        .unwrap_or_default()
+        .and_then(|stack| stack.last().copied())
 }
 /// Get server for serving
 pub fn get_server(server_id: usize) -> Option<DewServer> {
-    SERVERS.lock().unwrap().get(server_id).cloned()
+    lock(&SERVERS).get(server_id).cloned()
 }
 // ==================== PHASE 6: CONFIG, DATABASE, SESSIONS, RATE LIMITING ====================
 /// Load configuration from file (YAML, JSON, or .env)
 pub fn load_server_config(server_id: usize, config_path: &str) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         // Read config file
         let content = fs::read_to_string(config_path)
@@ -2654,23 +3231,9 @@ pub fn load_server_config(server_id: usize, config_path: &str) -> MintasResult<(
             }
             println!("📄 Loaded .env config: {}", config_path);
         } else if config_path.ends_with(".yaml") || config_path.ends_with(".yml") {
-            // Simple YAML parsing (key: value format)
-            for line in content.lines() {
-                let line = line.trim();
-                if line.is_empty() || line.starts_with('#') { continue; }
-                if let Some(colon_pos) = line.find(':') {
-                    let key = line[..colon_pos].trim().to_string();
-                    let value = line[colon_pos + 1..].trim().trim_matches('"').trim_matches('\'').to_string();
-                    let parsed_value = if value == "true" {
-                        Value::Boolean(true)
-                    } else if value == "false" {
-                        Value::Boolean(false)
-                    } else if let Ok(n) = value.parse::<f64>() {
-                        Value::Number(n)
-                    } else {
-                        Value::String(value)
-                    };
-                    server.config.insert(key, parsed_value);
+            if let Value::Table(map) = parse_yaml_to_value(&content)? {
+                for (k, v) in map {
+                    server.config.insert(k.to_string(), v);
                 }
             }
             println!("📄 Loaded YAML config: {}", config_path);
@@ -2678,7 +3241,7 @@ pub fn load_server_config(server_id: usize, config_path: &str) -> MintasResult<(
             if let Ok(parsed) = parse_json_to_value(&content) {
                 if let Value::Table(map) = parsed {
                     for (k, v) in map {
-                        server.config.insert(k, v);
+                        server.config.insert(k.to_string(), v);
                     }
                 }
             }
@@ -2693,7 +3256,7 @@ pub fn load_server_config(server_id: usize, config_path: &str) -> MintasResult<(
     }
 }
 pub fn setup_server_database(server_id: usize, connection_string: &str) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         let (driver, db_path) = if connection_string.starts_with("sqlite:") {
             ("sqlite", connection_string.trim_start_matches("sqlite:///").to_string())
@@ -2735,7 +3298,7 @@ fn create_default_tables(db_path: &str) -> Result<(), String> {
     Ok(())
 }
 pub fn setup_server_session(server_id: usize, config: Value) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         let mut session_config = SessionConfig {
             secret: "change_me_in_production".to_string(),
@@ -2776,7 +3339,7 @@ pub fn setup_server_session(server_id: usize, config: Value) -> MintasResult<()>
     }
 }
 pub fn setup_server_rate_limit(server_id: usize, requests: u32, window_seconds: u32) -> MintasResult<()> {
-    let mut servers = SERVERS.lock().unwrap();
+    let mut servers = lock(&SERVERS);
     if let Some(server) = servers.get_mut(server_id) {
         server.rate_limit = Some(RateLimitConfig {
             requests_per_window: requests,
@@ -2793,8 +3356,85 @@ pub fn setup_server_rate_limit(server_id: usize, requests: u32, window_seconds:
         })
     }
 }
-fn render_template(template: &str, data: &HashMap<String, Value>) -> String {
-    let mut rendered = template.to_string();
+fn load_template_file(path: &str) -> Option<String> {
+    fs::read_to_string(path)
+        .or_else(|_| fs::read_to_string(format!("templates/{}", path)))
+        .or_else(|_| fs::read_to_string(format!("views/{}", path)))
+        .ok()
+}
+/// Nesting limit for extends/include chains, on top of the cycle check.
+const MAX_TEMPLATE_DEPTH: usize = 32;
+/// Flattens layout directives before the other passes run:
+/// `?( extends "base.html" )?` renders the parent with this template's
+/// `?( block name )?...?( endblock )?` bodies, which the parent pulls in with
+/// `?( yield name )?`. A block in a template that extends nothing is rendered in place,
+/// so layouts can give blocks default content. `?( include "partial.html" )?` inlines a file.
+fn resolve_template_layout(template: &str, blocks: &mut HashMap<String, String>, stack: &mut Vec<String>) -> String {
+    let body = take_template_blocks(template, blocks);
+    if let Some(parent) = template_directives(&body, "extends").into_iter().next() {
+        return expand_template_file(&parent, blocks, stack);
+    }
+    let body = replace_template_directives(&body, "yield", |name| {
+        blocks.get(name).cloned().unwrap_or_default()
+    });
+    replace_template_directives(&body, "include", |path| expand_template_file(path, &mut blocks.clone(), stack))
+}
+fn expand_template_file(path: &str, blocks: &mut HashMap<String, String>, stack: &mut Vec<String>) -> String {
+    if stack.iter().any(|p| p == path) || stack.len() >= MAX_TEMPLATE_DEPTH {
+        return format!("<!-- Template recursion: {} -->", path);
+    }
+    let Some(content) = load_template_file(path) else {
+        return format!("<!-- Template not found: {} -->", path);
+    };
+    stack.push(path.to_string());
+    let resolved = resolve_template_layout(&content, blocks, stack);
+    stack.pop();
+    resolved
+}
+/// Moves block bodies into `blocks`, leaving a yield in their place. The first definition
+/// of a name wins, so a child template overrides its layout.
+fn take_template_blocks(template: &str, blocks: &mut HashMap<String, String>) -> String {
+    const END: &str = "?( endblock )?";
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("?( block ") {
+        let Some(header_len) = rest[start..].find(")?") else { break };
+        let body_start = start + header_len + 2;
+        let Some(body_len) = rest[body_start..].find(END) else { break };
+        let name = rest[start + 9..start + header_len].trim().to_string();
+        out.push_str(&rest[..start]);
+        out.push_str(&format!("?( yield {} )?", name));
+        blocks.entry(name).or_insert_with(|| rest[body_start..body_start + body_len].to_string());
+        rest = &rest[body_start + body_len + END.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+fn template_directives(template: &str, keyword: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    replace_template_directives(template, keyword, |arg| {
+        found.push(arg.to_string());
+        String::new()
+    });
+    found
+}
+/// Replaces each `?( keyword arg )?` with `expand(arg)`; quotes around the argument are dropped.
+fn replace_template_directives(template: &str, keyword: &str, mut expand: impl FnMut(&str) -> String) -> String {
+    let open = format!("?( {} ", keyword);
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(&open) {
+        let Some(len) = rest[start..].find(")?") else { break };
+        let arg = rest[start + open.len()..start + len].trim().trim_matches(|c| c == '"' || c == '\'');
+        out.push_str(&rest[..start]);
+        out.push_str(&expand(arg));
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+fn render_template(template: &str, data: &Table) -> String {
+    let mut rendered = resolve_template_layout(template, &mut HashMap::new(), &mut Vec::new());
     rendered = process_template_control_flow(&rendered, data);
     rendered = process_dew_code_blocks(&rendered, data);
     rendered = process_dew_styled_blocks(&rendered, data);
@@ -2808,15 +3448,70 @@ fn render_template(template: &str, data: &HashMap<String, Value>) -> String {
             break;
         }
     }
-    for (key, value) in data {
-        let placeholder = format!("${}", key);
-        let replacement = value_to_string(value);
-        rendered = rendered.replace(&placeholder, &replacement);
-    }
+    rendered = substitute_template_vars(&rendered, data);
     rendered = inject_dew_frontend_script(&rendered, data);
     rendered
 }
-fn process_dew_styled_blocks(template: &str, data: &HashMap<String, Value>) -> String {
+/// Replaces `$key` with the HTML-escaped value and `$$key` with the raw value. The longest
+/// identifier prefix that names a key wins; unknown placeholders are left untouched.
+fn substitute_template_vars(template: &str, data: &Table) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (raw, name_start) = match after.strip_prefix('$') {
+            Some(stripped) => (true, stripped),
+            None => (false, after),
+        };
+        let ident_len = name_start
+            .char_indices()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+            .map(|(i, _)| i)
+            .unwrap_or(name_start.len());
+        let matched = (1..=ident_len)
+            .rev()
+            .filter(|&len| name_start.is_char_boundary(len))
+            .find_map(|len| data.get(&name_start[..len]).map(|value| (len, value)));
+        match matched {
+            Some((len, value)) => {
+                let text = value_to_string(value);
+                if raw {
+                    out.push_str(&text);
+                } else {
+                    out.push_str(&escape_html(&text));
+                }
+                rest = &name_start[len..];
+            }
+            None => {
+                out.push('$');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+/// Escapes text for HTML element and attribute content; templates and `dew.sanitize` share it.
+/// `/` is encoded so a value can't close a tag, and `$` so interpolated user data can't be
+/// picked up as a placeholder by a later template pass.
+fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            '/' => out.push_str("&#x2F;"),
+            '$' => out.push_str("&#36;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+fn process_dew_styled_blocks(template: &str, data: &Table) -> String {
     let mut result = template.to_string();
     loop {
         if let Some(start) = find_innermost_dew_tag(&result) {
@@ -2860,7 +3555,7 @@ fn find_innermost_dew_tag(template: &str) -> Option<usize> {
     }
     None
 }
-fn parse_dew_block_to_html(block: &str, data: &HashMap<String, Value>) -> String {
+fn parse_dew_block_to_html(block: &str, data: &Table) -> String {
     let tag_end = block.find('>').unwrap_or(block.len());
     let tag_part = &block[4..tag_end]; 
     let content_start = tag_end + 1;
@@ -2889,7 +3584,7 @@ fn extract_dew_attr(tag: &str, attr_name: &str) -> Option<String> {
     }
     None
 }
-fn parse_dew_dsl_content(content: &str, data: &HashMap<String, Value>) -> (String, Vec<(String, String)>, String) {
+fn parse_dew_dsl_content(content: &str, data: &Table) -> (String, Vec<(String, String)>, String) {
     let mut styles = Vec::new();
     let mut events = Vec::new();
     let mut inner_html = String::new();
@@ -2944,7 +3639,7 @@ fn parse_dew_dsl_content(content: &str, data: &HashMap<String, Value>) -> (Strin
     }
     (styles.join("; "), events, inner_html.trim().to_string())
 }
-fn process_dew_text_vars(text: &str, data: &HashMap<String, Value>) -> String {
+fn process_dew_text_vars(text: &str, data: &Table) -> String {
     let mut result = text.to_string();
     for (key, value) in data {
         let placeholder = format!("${}", key);
@@ -2952,7 +3647,7 @@ fn process_dew_text_vars(text: &str, data: &HashMap<String, Value>) -> String {
     }
     result
 }
-fn process_dew_inline_code(line: &str, data: &HashMap<String, Value>) -> String {
+fn process_dew_inline_code(line: &str, data: &Table) -> String {
     let mut result = line.to_string();
     while let Some(start) = result.find("?(") {
         if let Some(end) = result[start..].find(")?") {
@@ -3409,13 +4104,13 @@ fn generate_dew_handler(body: &str) -> String {
     }
     js.replace('"', "&quot;")
 }
-fn inject_dew_frontend_script(template: &str, data: &HashMap<String, Value>) -> String {
+fn inject_dew_frontend_script(template: &str, data: &Table) -> String {
     if !template.contains("</body>") {
         return template.to_string();
     }
     let mut js_data = String::from("{\n");
     for (key, value) in data {
-        if !key.starts_with("__") {
+        if !is_internal_key(key) {
             js_data.push_str(&format!("    {}: {},\n", key, value_to_js(value)));
         }
     }
@@ -4030,7 +4725,7 @@ fn value_to_js(value: &Value) -> String {
         }
         Value::Table(t) => {
             let pairs: Vec<String> = t.iter()
-                .filter(|(k, _)| !k.starts_with("__"))
+                .filter(|(k, _)| !is_internal_key(k))
                 .map(|(k, v)| format!("{}: {}", k, value_to_js(v)))
                 .collect();
             format!("{{{}}}", pairs.join(", "))
@@ -4039,7 +4734,7 @@ fn value_to_js(value: &Value) -> String {
         _ => "null".to_string(),
     }
 }
-fn process_dew_code_blocks(template: &str, data: &HashMap<String, Value>) -> String {
+fn process_dew_code_blocks(template: &str, data: &Table) -> String {
     let mut result = template.to_string();
     let dew_pattern = "class=\"dew= '";
     while let Some(start) = result.find(dew_pattern) {
@@ -4069,7 +4764,7 @@ fn process_dew_code_blocks(template: &str, data: &HashMap<String, Value>) -> Str
     }
     result
 }
-fn evaluate_dew_code(code: &str, data: &HashMap<String, Value>) -> String {
+fn evaluate_dew_code(code: &str, data: &Table) -> String {
     let code = code.trim();
     if let Some(value) = data.get(code) {
         return value_to_string(value);
@@ -4116,7 +4811,7 @@ fn evaluate_dew_code(code: &str, data: &HashMap<String, Value>) -> String {
     }
     String::new()
 }
-fn evaluate_template_expr(code: &str, data: &HashMap<String, Value>) -> String {
+fn evaluate_template_expr(code: &str, data: &Table) -> String {
     let code = code.trim();
     if let Some(value) = data.get(code) {
         return value_to_string(value);
@@ -4132,7 +4827,7 @@ fn evaluate_template_expr(code: &str, data: &HashMap<String, Value>) -> String {
     }
     String::new()
 }
-fn process_template_control_flow(template: &str, data: &HashMap<String, Value>) -> String {
+fn process_template_control_flow(template: &str, data: &Table) -> String {
     let mut result = template.to_string();
     while let Some(for_start) = result.find("?( for ") {
         if let Some(for_end) = result[for_start..].find(")?") {
@@ -4178,7 +4873,7 @@ fn process_template_control_flow(template: &str, data: &HashMap<String, Value>)
     }
     result
 }
-fn evaluate_condition(condition: &str, data: &HashMap<String, Value>) -> bool {
+fn evaluate_condition(condition: &str, data: &Table) -> bool {
     let condition = condition.trim();
     if let Some(value) = data.get(condition) {
         return match value {
@@ -4200,7 +4895,7 @@ fn evaluate_condition(condition: &str, data: &HashMap<String, Value>) -> bool {
     }
     false
 }
-fn value_to_string(value: &Value) -> String {
+pub(crate) fn value_to_string(value: &Value) -> String {
     match value {
         Value::String(s) => s.clone(),
         Value::Number(n) => {
@@ -4210,6 +4905,7 @@ fn value_to_string(value: &Value) -> String {
                 format!("{}", n)
             }
         }
+        Value::Int(n) => n.to_string(),
         Value::Boolean(b) => b.to_string(),
         Value::Array(arr) => {
             let items: Vec<String> = arr.iter().map(value_to_string).collect();
@@ -4222,14 +4918,9 @@ fn value_to_string(value: &Value) -> String {
 }
 fn value_to_json_string(value: &Value) -> String {
     match value {
-        Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
-        Value::Number(n) => {
-            if n.fract() == 0.0 && n.abs() < 1e15 {
-                format!("{}", *n as i64)
-            } else {
-                format!("{}", n)
-            }
-        }
+        Value::String(s) => JsonValue::String(s.clone()).to_string(),
+        Value::Number(n) => number_to_json(*n).to_string(),
+        Value::Int(n) => n.to_string(),
         Value::Boolean(b) => b.to_string(),
         Value::Array(arr) => {
             let items: Vec<String> = arr.iter().map(value_to_json_string).collect();
@@ -4237,8 +4928,8 @@ fn value_to_json_string(value: &Value) -> String {
         }
         Value::Table(t) => {
             let pairs: Vec<String> = t.iter()
-                .filter(|(k, _)| !k.starts_with("__"))
-                .map(|(k, v)| format!("\"{}\":{}", k, value_to_json_string(v)))
+                .filter(|(k, _)| !is_internal_key(k))
+                .map(|(k, v)| format!("{}:{}", JsonValue::String(k.to_string()), value_to_json_string(v)))
                 .collect();
             format!("{{{}}}", pairs.join(","))
         }
@@ -4246,90 +4937,61 @@ fn value_to_json_string(value: &Value) -> String {
         _ => "null".to_string(),
     }
 }
-fn parse_json_to_value(json: &str) -> Result<Value, String> {
-    let json = json.trim();
-    if json.is_empty() {
-        return Ok(Value::Empty);
-    }
-    if json == "null" {
-        return Ok(Value::Empty);
+/// Compact JSON, or 2-space-indented with sorted keys so responses diff cleanly
+fn json_body(value: &Value, pretty: bool) -> String {
+    if pretty {
+        value_to_pretty_json(value, 0)
+    } else {
+        value_to_json_string(value)
     }
-    if json == "true" {
-        return Ok(Value::Boolean(true));
-    }
-    if json == "false" {
-        return Ok(Value::Boolean(false));
-    }
-    if let Ok(n) = json.parse::<f64>() {
-        return Ok(Value::Number(n));
-    }
-    if json.starts_with('"') && json.ends_with('"') {
-        let s = &json[1..json.len() - 1];
-        return Ok(Value::String(s.replace("\\\"", "\"").replace("\\\\", "\\")));
-    }
-    if json.starts_with('[') && json.ends_with(']') {
-        let inner = &json[1..json.len() - 1];
-        let mut items = Vec::new();
-        let mut depth = 0;
-        let mut start = 0;
-        for (i, c) in inner.char_indices() {
-            match c {
-                '[' | '{' => depth += 1,
-                ']' | '}' => depth -= 1,
-                ',' if depth == 0 => {
-                    let item = inner[start..i].trim();
-                    if !item.is_empty() {
-                        items.push(parse_json_to_value(item)?);
-                    }
-                    start = i + 1;
-                }
-                _ => {}
-            }
+}
+fn value_to_pretty_json(value: &Value, depth: usize) -> String {
+    let indent = |depth: usize| "  ".repeat(depth);
+    match value {
+        Value::Array(arr) if !arr.is_empty() => {
+            let items: Vec<String> = arr.iter()
+                .map(|v| format!("{}{}", indent(depth + 1), value_to_pretty_json(v, depth + 1)))
+                .collect();
+            format!("[\n{}\n{}]", items.join(",\n"), indent(depth))
         }
-        let last = inner[start..].trim();
-        if !last.is_empty() {
-            items.push(parse_json_to_value(last)?);
-        }
-        return Ok(Value::Array(items));
-    }
-    if json.starts_with('{') && json.ends_with('}') {
-        let inner = &json[1..json.len() - 1];
-        let mut map = HashMap::new();
-        let mut depth = 0;
-        let mut start = 0;
-        for (i, c) in inner.char_indices() {
-            match c {
-                '[' | '{' => depth += 1,
-                ']' | '}' => depth -= 1,
-                ',' if depth == 0 => {
-                    parse_json_pair(&inner[start..i], &mut map)?;
-                    start = i + 1;
-                }
-                _ => {}
+        Value::Table(t) => {
+            let entries: Vec<_> = t.sorted().into_iter().filter(|(k, _)| !is_internal_key(k)).collect();
+            if entries.is_empty() {
+                return "{}".to_string();
             }
+            let pairs: Vec<String> = entries.into_iter()
+                .map(|(k, v)| format!("{}{}: {}", indent(depth + 1), JsonValue::String(k.to_string()), value_to_pretty_json(v, depth + 1)))
+                .collect();
+            format!("{{\n{}\n{}}}", pairs.join(",\n"), indent(depth))
         }
-        let last = inner[start..].trim();
-        if !last.is_empty() {
-            parse_json_pair(last, &mut map)?;
-        }
-        return Ok(Value::Table(map));
+        other => value_to_json_string(other),
     }
-    Ok(Value::String(json.to_string()))
 }
-fn parse_json_pair(pair: &str, map: &mut HashMap<String, Value>) -> Result<(), String> {
-    let pair = pair.trim();
-    if pair.is_empty() {
-        return Ok(());
+fn parse_json_to_value(json: &str) -> Result<Value, String> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Ok(Value::Empty);
     }
-    if let Some(colon_pos) = pair.find(':') {
-        let key = pair[..colon_pos].trim().trim_matches('"');
-        let value = pair[colon_pos + 1..].trim();
-        map.insert(key.to_string(), parse_json_to_value(value)?);
+    match serde_json::from_str::<JsonValue>(json) {
+        Ok(parsed) => Ok(json_to_value(parsed)),
+        Err(_) => Ok(Value::String(json.to_string())),
     }
-    Ok(())
 }
-fn parse_form_data(body: &str) -> HashMap<String, Value> {
-    let mut data = HashMap::new();
+/// `req.json()`: the body as JSON whatever the content-type says; null when missing or malformed
+pub fn body_json(body: &str) -> Value {
+    serde_json::from_str::<JsonValue>(body.trim()).map(json_to_value).unwrap_or(Value::Null)
+}
+/// `req.form()`: the body as urlencoded fields; null when missing or holding no `key=value` pair
+pub fn body_form(body: &str) -> Value {
+    let data = parse_form_data(body.trim());
+    if data.is_empty() {
+        Value::Null
+    } else {
+        Value::Table(data)
+    }
+}
+fn parse_form_data(body: &str) -> Table {
+    let mut data = Table::new();
     for pair in body.split('&') {
         if let Some(eq_pos) = pair.find('=') {
             let key = url_decode(&pair[..eq_pos]);
@@ -4339,6 +5001,115 @@ fn parse_form_data(body: &str) -> HashMap<String, Value> {
     }
     data
 }
+struct MultipartPart {
+    name: String,
+    filename: Option<String>,
+    content_type: String,
+    data: Vec<u8>,
+}
+fn find_bytes(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if needle.is_empty() || from >= haystack.len() {
+        return None;
+    }
+    haystack[from..].windows(needle.len()).position(|w| w == needle).map(|i| i + from)
+}
+fn header_param(header: &str, param: &str) -> Option<String> {
+    header.split(';').skip(1).find_map(|attr| {
+        let (key, value) = attr.trim().split_once('=')?;
+        if key.trim().eq_ignore_ascii_case(param) {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+fn parse_multipart(content_type: &str, body: &[u8]) -> Vec<MultipartPart> {
+    let mut parts = Vec::new();
+    let boundary = match header_param(content_type, "boundary") {
+        Some(b) if !b.is_empty() => b,
+        _ => return parts,
+    };
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let next_delimiter = format!("\r\n--{}", boundary).into_bytes();
+    let mut pos = match find_bytes(body, &delimiter, 0) {
+        Some(p) => p + delimiter.len(),
+        None => return parts,
+    };
+    loop {
+        if body[pos..].starts_with(b"--") {
+            break;
+        }
+        if body[pos..].starts_with(b"\r\n") {
+            pos += 2;
+        }
+        let header_end = match find_bytes(body, b"\r\n\r\n", pos) {
+            Some(p) => p,
+            None => break,
+        };
+        let data_end = match find_bytes(body, &next_delimiter, header_end + 4) {
+            Some(p) => p,
+            None => break,
+        };
+        let headers = String::from_utf8_lossy(&body[pos..header_end]);
+        let mut name = None;
+        let mut filename = None;
+        let mut part_type = String::from("text/plain");
+        for line in headers.split("\r\n") {
+            let (key, value) = match line.split_once(':') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            match key.trim().to_lowercase().as_str() {
+                "content-disposition" => {
+                    name = header_param(value, "name");
+                    filename = header_param(value, "filename");
+                }
+                "content-type" => part_type = value.trim().to_string(),
+                _ => {}
+            }
+        }
+        if let Some(name) = name {
+            parts.push(MultipartPart {
+                name,
+                filename,
+                content_type: part_type,
+                data: body[header_end + 4..data_end].to_vec(),
+            });
+        }
+        pos = data_end + next_delimiter.len();
+    }
+    parts
+}
+fn uploads_from_parts(parts: &[MultipartPart]) -> Table {
+    let mut uploads: Table = Table::new();
+    for part in parts {
+        let filename = match &part.filename {
+            Some(f) => f.clone(),
+            None => continue,
+        };
+        let mut file_info = Table::new();
+        file_info.insert("field".to_string(), Value::String(part.name.clone()));
+        file_info.insert("filename".to_string(), Value::String(filename));
+        file_info.insert("size".to_string(), Value::Number(part.data.len() as f64));
+        file_info.insert("content_type".to_string(), Value::String(part.content_type.clone()));
+        file_info.insert("content".to_string(), Value::Bytes(part.data.clone()));
+        file_info.insert("__type__".to_string(), Value::String("UploadedFile".to_string()));
+        // Several files under one field name become an array
+        match uploads.remove(&part.name) {
+            Some(Value::Array(mut files)) => {
+                files.push(Value::Table(file_info));
+                uploads.insert(part.name.clone(), Value::Array(files));
+            }
+            Some(first) => {
+                uploads.insert(part.name.clone(), Value::Array(vec![first, Value::Table(file_info)]));
+            }
+            None => {
+                uploads.insert(part.name.clone(), Value::Table(file_info));
+            }
+        }
+    }
+    uploads
+}
 fn url_decode(s: &str) -> String {
     let mut result = String::new();
     let mut chars = s.chars().peekable();
@@ -4362,10 +5133,10 @@ lazy_static::lazy_static! {
 fn check_rate_limit(client_ip: &str, config: &RateLimitConfig) -> bool {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap()
+        .unwrap_or_default()
         .as_secs();
     let window_start = now - config.window_seconds as u64;
-    let mut store = RATE_LIMIT_STORE.lock().unwrap();
+    let mut store = lock(&RATE_LIMIT_STORE);
     let timestamps = store.entry(client_ip.to_string()).or_insert_with(Vec::new);
     timestamps.retain(|&ts| ts > window_start);
     if timestamps.len() < config.requests_per_window as usize {
@@ -4379,98 +5150,77 @@ fn check_rate_limit(client_ip: &str, config: &RateLimitConfig) -> bool {
 fn cleanup_rate_limits() {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap()
+        .unwrap_or_default()
         .as_secs();
-    let mut store = RATE_LIMIT_STORE.lock().unwrap();
+    let mut store = lock(&RATE_LIMIT_STORE);
     store.retain(|_, timestamps| {
         timestamps.retain(|&ts| ts > now - 3600); 
         !timestamps.is_empty()
     });
 }
-fn validate_field(value: Option<&Value>, rule: &str) -> Option<String> {
-    let rule = rule.trim();
-    let (rule_name, rule_param) = if let Some(colon_pos) = rule.find(':') {
-        (&rule[..colon_pos], Some(&rule[colon_pos + 1..]))
-    } else {
-        (rule, None)
+#[cfg(feature = "magic")]
+fn jwt_secret(arg: Option<&Value>) -> MintasResult<String> {
+    match arg {
+        Some(Value::String(s)) if !s.is_empty() => Ok(s.clone()),
+        Some(Value::Table(t)) => match t.get("secret") {
+            Some(Value::String(s)) if !s.is_empty() => Ok(s.clone()),
+            _ => Err(MintasError::RuntimeError {
+                message: "JWT auth config has no secret".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        },
+        _ => Err(MintasError::RuntimeError {
+            message: "JWT functions need a non-empty secret".to_string(),
+            location: SourceLocation::new(0, 0),
+        }),
+    }
+}
+#[cfg(feature = "magic")]
+fn jwt_signature(signing_input: &str, secret: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(signing_input.as_bytes());
+    mac
+}
+#[cfg(feature = "magic")]
+fn jwt_encode(claims: &Table, secret: &str) -> String {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(table_to_json_string(claims));
+    let signing_input = format!("{}.{}", header, payload);
+    let signature = jwt_signature(&signing_input, secret).finalize().into_bytes();
+    format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature))
+}
+#[cfg(feature = "magic")]
+fn jwt_decode(token: &str, secret: &str, now: u64) -> Option<Table> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    let mut parts = token.split('.');
+    let (header, payload, signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let header: JsonValue = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+    if header.get("alg").and_then(|a| a.as_str()) != Some("HS256") {
+        return None;
+    }
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    let (signing_input, _) = token.rsplit_once('.')?;
+    jwt_signature(signing_input, secret).verify_slice(&signature).ok()?;
+    let claims = match json_to_value(serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?) {
+        Value::Table(t) => t,
+        _ => return None,
     };
-    match rule_name {
-        "required" => {
-            match value {
-                None | Some(Value::Empty) => Some("This field is required".to_string()),
-                Some(Value::String(s)) if s.is_empty() => Some("This field is required".to_string()),
-                _ => None,
-            }
-        }
-        "email" => {
-            if let Some(Value::String(s)) = value {
-                if !s.contains('@') || !s.contains('.') {
-                    return Some("Invalid email format".to_string());
-                }
-            }
-            None
-        }
-        "min" => {
-            if let Some(param) = rule_param {
-                if let Ok(min_len) = param.parse::<usize>() {
-                    if let Some(Value::String(s)) = value {
-                        if s.len() < min_len {
-                            return Some(format!("Must be at least {} characters", min_len));
-                        }
-                    }
-                }
-            }
-            None
-        }
-        "max" => {
-            if let Some(param) = rule_param {
-                if let Ok(max_len) = param.parse::<usize>() {
-                    if let Some(Value::String(s)) = value {
-                        if s.len() > max_len {
-                            return Some(format!("Must be at most {} characters", max_len));
-                        }
-                    }
-                }
-            }
-            None
-        }
-        "numeric" => {
-            if let Some(Value::String(s)) = value {
-                if s.parse::<f64>().is_err() {
-                    return Some("Must be a number".to_string());
-                }
-            }
-            None
-        }
-        "alpha" => {
-            if let Some(Value::String(s)) = value {
-                if !s.chars().all(|c| c.is_alphabetic()) {
-                    return Some("Must contain only letters".to_string());
-                }
-            }
-            None
-        }
-        "alphanumeric" => {
-            if let Some(Value::String(s)) = value {
-                if !s.chars().all(|c| c.is_alphanumeric()) {
-                    return Some("Must contain only letters and numbers".to_string());
-                }
-            }
-            None
-        }
-        "url" => {
-            if let Some(Value::String(s)) = value {
-                if !s.starts_with("http://") && !s.starts_with("https://") {
-                    return Some("Invalid URL format".to_string());
-                }
-            }
-            None
+    let now = now as f64;
+    if let Some(Value::Number(exp)) = claims.get("exp") {
+        if now >= *exp {
+            return None;
         }
-        "regex" => {
-            None
+    }
+    if let Some(Value::Number(nbf)) = claims.get("nbf") {
+        if now < *nbf {
+            return None;
         }
-        _ => None,
     }
+    Some(claims)
 }
 fn get_mime_type(path: &str) -> String {
     let ext = path.rsplit('.').next().unwrap_or("");
@@ -4507,31 +5257,17 @@ fn get_mime_type(path: &str) -> String {
         _ => "application/octet-stream",
     }.to_string()
 }
-fn parse_duration_string(s: &str) -> Option<u64> {
-    let s = s.trim().to_lowercase();
-    if s.ends_with("ms") {
-        s[..s.len()-2].trim().parse().ok()
-    } else if s.ends_with('s') {
-        s[..s.len()-1].trim().parse::<u64>().ok().map(|v| v * 1000)
-    } else if s.ends_with('m') {
-        s[..s.len()-1].trim().parse::<u64>().ok().map(|v| v * 60 * 1000)
-    } else if s.ends_with('h') {
-        s[..s.len()-1].trim().parse::<u64>().ok().map(|v| v * 60 * 60 * 1000)
-    } else {
-        s.parse().ok()
-    }
-}
 fn generate_job_id() -> String {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap()
+        .unwrap_or_default()
         .as_nanos();
     format!("job_{:x}", timestamp)
 }
 fn current_timestamp() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap()
+        .unwrap_or_default()
         .as_millis() as u64
 }
 
@@ -4549,14 +5285,84 @@ fn generate_csrf_token() -> String {
     Uuid::new_v4().to_string()
 }
 
-fn sanitize_html(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#x27;")
-        .replace('/', "&#x2F;")
+/// Conservative SQL injection check for request values. Only flags shapes that rarely occur
+/// in ordinary text: `UNION SELECT`, stacked statements (`; DROP TABLE`), a quote closed
+/// into a comment (`admin'--`), quote-or tautologies (`' OR '1'='1`) and sleep probes.
+fn detect_sql_injection(input: &str) -> bool {
+    // Lowercase, turn inline /* */ comments into spaces and collapse whitespace
+    let mut text = String::with_capacity(input.len());
+    let mut rest = input.to_lowercase();
+    while let Some(start) = rest.find("/*") {
+        match rest[start + 2..].find("*/") {
+            Some(end) => {
+                text.push_str(&rest[..start]);
+                text.push(' ');
+                rest = rest[start + 2 + end + 2..].to_string();
+            }
+            None => break,
+        }
+    }
+    text.push_str(&rest);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let has_phrase = |phrase: &str| {
+        text.match_indices(phrase).any(|(i, _)| {
+            let before = text[..i].chars().next_back();
+            let after = text[i + phrase.len()..].chars().next();
+            !before.is_some_and(|c| c.is_alphanumeric() || c == '_')
+                && !after.is_some_and(|c| c.is_alphanumeric() || c == '_')
+        })
+    };
+    if has_phrase("union select") || has_phrase("union all select") || has_phrase("union distinct select") {
+        return true;
+    }
+    const STACKED: [&str; 12] = [
+        "drop table", "drop database", "truncate table", "alter table", "create table", "delete from",
+        "insert into", "exec xp_", "exec(", "execute(", "shutdown", "grant all",
+    ];
+    for (i, _) in text.match_indices(';') {
+        let next = text[i + 1..].trim_start();
+        if STACKED.iter().any(|stmt| next.starts_with(stmt)) || (next.starts_with("update ") && next.contains(" set ")) {
+            return true;
+        }
+    }
+    for (i, quote) in text.match_indices(['\'', '"']) {
+        let after = text[i + quote.len()..].trim_start();
+        if after.starts_with("--") || after.starts_with('#') || (after.starts_with(';') && after[1..].trim_start().starts_with("--")) {
+            return true;
+        }
+        let tail = match after.strip_prefix("or ").or_else(|| after.strip_prefix("and ")).or_else(|| after.strip_prefix("|| ")) {
+            Some(tail) => tail,
+            None => continue,
+        };
+        if sql_tautology(tail) || tail.starts_with("sleep(") || tail.starts_with("pg_sleep(") || tail.starts_with("benchmark(") {
+            return true;
+        }
+    }
+    // Unquoted numeric tautologies such as `1 OR 1=1`
+    for (i, _) in text.match_indices(" or ") {
+        let before = text[..i].chars().next_back();
+        if before.is_some_and(|c| c.is_ascii_digit()) && sql_tautology(&text[i + 4..]) {
+            return true;
+        }
+    }
+    has_phrase("waitfor delay")
+}
+
+/// `'1'='1`, `1=1`, `'a' = 'a'`: both sides of the first `=` are the same literal.
+fn sql_tautology(expr: &str) -> bool {
+    let (left, right) = match expr.split_once('=') {
+        Some(sides) => sides,
+        None => return false,
+    };
+    let is_quote = |c: char| c == '\'' || c == '"';
+    let left = left.trim().trim_matches(is_quote);
+    let right: String = right
+        .trim_start()
+        .trim_start_matches(is_quote)
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    left.len() <= 20 && left.chars().all(|c| c.is_alphanumeric() || c == '_') && left == right
 }
 fn sanitize_sql(input: &str) -> String {
     input
@@ -4593,15 +5399,27 @@ fn start_server(server: &DewServer, port: u16, host: &str) -> MintasResult<Value
     use std::net::TcpListener;
     let addr = format!("{}:{}", host, port);
     let listener = TcpListener::bind(&addr).map_err(|e| MintasError::RuntimeError {
-        message: format!("Failed to bind to {}: {}", addr, e),
+        message: format!("failed to bind {}: {}", addr, describe_bind_error(&e)),
+        location: SourceLocation::new(0, 0),
+    })?;
+    let wake = listener.local_addr().map_err(|e| MintasError::RuntimeError {
+        message: format!("failed to configure listener on {}: {}", addr, e),
         location: SourceLocation::new(0, 0),
     })?;
+    let (shutdown_sender, shutdown) = std::sync::mpsc::channel();
+    let _registration = ServeRegistration::new(shutdown_sender, wake);
+    install_shutdown_handler();
     println!("\n🌿 Dew server running at http://{}", addr);
     println!("   Press Ctrl+C to stop\n");
     println!("   Routes:");
     for route in &server.routes {
         println!("     {} {}", route.method.as_str(), route.path);
     }
+    for group in &server.groups {
+        for route in &group.routes {
+            println!("     {} {}{}", route.method.as_str(), group.prefix, route.path);
+        }
+    }
     if !server.static_dirs.is_empty() {
         println!("   Static:");
         for (url, dir) in &server.static_dirs {
@@ -4610,48 +5428,490 @@ fn start_server(server: &DewServer, port: u16, host: &str) -> MintasResult<Value
     }
     println!();
     let server = server.clone();
-    for stream in listener.incoming() {
-        match stream {
-            Ok(mut stream) => {
+    // Requests are served one at a time and a shutdown wakes the blocking accept, so the
+    // in-flight request finishes before the loop exits
+    loop {
+        let accepted = listener.accept();
+        if shutdown.try_recv().is_ok() {
+            break;
+        }
+        match accepted {
+            Ok((mut stream, _)) => {
                 stream.set_read_timeout(Some(std::time::Duration::from_secs(30))).ok();
                 let mut buffer = vec![0u8; 65536];
                 if let Ok(size) = stream.read(&mut buffer) {
                     if size > 0 {
-                        let request_str = String::from_utf8_lossy(&buffer[..size]);
-                        let (response, log_line) = handle_request(&request_str, &server);
-                        println!("{}", log_line);
+                        let started = std::time::Instant::now();
+                        buffer.truncate(size);
+                        let raw_body = read_request_body(&mut stream, &mut buffer, server.max_body_size());
+                        let request_str = String::from_utf8_lossy(&buffer);
+                        let (response, log_line, chunks) = dispatch_request(&request_str, &raw_body, &server);
                         let _ = stream.write_all(response.as_bytes());
                         let _ = stream.flush();
+                        if let Some(body) = chunks {
+                            if let Err(e) = write_chunked_stream(&mut stream, body) {
+                                eprintln!("❌ Stream aborted: {}", e);
+                            }
+                        }
+                        // Upgraded sockets stay open in the registry for ws_send/ws_close
+                        if response.starts_with("HTTP/1.1 101") {
+                            if let Ok(ws_stream) = stream.try_clone() {
+                                ws_stream.set_read_timeout(None).ok();
+                                let target = request_str.split_whitespace().nth(1).unwrap_or("/");
+                                let (path, query) = target.split_once('?').unwrap_or((target, ""));
+                                if server.signaling_paths.iter().any(|p| p == path) {
+                                    let requested = parse_form_data(query).remove("id").and_then(|v| match v {
+                                        Value::String(id) if !id.is_empty() => Some(id),
+                                        _ => None,
+                                    });
+                                    if let Some(peer) = start_signaling_peer(ws_stream, requested) {
+                                        println!("📡 Signaling peer {} connected", peer);
+                                    }
+                                } else {
+                                    let id = register_ws_connection(ws_stream);
+                                    println!("🔌 WebSocket client {} connected", id);
+                                }
+                            }
+                        }
+                        match server.log_format() {
+                            Some(format) => {
+                                let remote = stream.peer_addr().map(|a| a.ip().to_string()).unwrap_or_else(|_| "-".to_string());
+                                println!("{}", format_access_log(format, &request_str, &response, &remote, started.elapsed()));
+                            }
+                            None => println!("{}", log_line),
+                        }
                     }
                 }
             }
             Err(e) => eprintln!("Connection error: {}", e),
         }
     }
+    println!("\n🌿 Dew server on {} shutting down", addr);
     Ok(Value::Empty)
 }
 
-fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
-    let start_time = std::time::Instant::now();
-    let mut lines = request_str.lines();
-    let first_line = lines.next().unwrap_or("");
-    let parts: Vec<&str> = first_line.split_whitespace().collect();
-    if parts.len() < 2 {
-        return (http_response(400, "text/plain", "Bad Request", &[]), 
-                "400 Bad Request".to_string());
+/// The running `dew.serve`: where a shutdown is sent, the address a connection to which
+/// wakes its blocking accept so the loop sees it, and whether a shutdown is already pending
+struct ActiveServe {
+    shutdown: std::sync::mpsc::Sender<()>,
+    wake: std::net::SocketAddr,
+    stopping: bool,
+}
+static ACTIVE_SERVE: Mutex<Option<ActiveServe>> = Mutex::new(None);
+/// Holds a serve call in ACTIVE_SERVE until it returns
+struct ServeRegistration;
+impl ServeRegistration {
+    fn new(shutdown: std::sync::mpsc::Sender<()>, mut wake: std::net::SocketAddr) -> Self {
+        // A server bound to 0.0.0.0 or [::] is reached through loopback
+        if wake.ip().is_unspecified() {
+            wake.set_ip(match wake {
+                std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::LOCALHOST.into(),
+                std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        *lock(&ACTIVE_SERVE) = Some(ActiveServe { shutdown, wake, stopping: false });
+        ServeRegistration
     }
-    let method = parts[0];
-    let full_path = parts[1];
-    let path = full_path.split('?').next().unwrap_or("/");
-    if method == "OPTIONS" {
-        let cors_headers = vec![
-            ("Access-Control-Allow-Origin", "*"),
-            ("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, PATCH, OPTIONS"),
-            ("Access-Control-Allow-Headers", "Content-Type, Authorization, X-Requested-With"),
-            ("Access-Control-Max-Age", "86400"),
-        ];
-        return (http_response_with_headers(204, "text/plain", "", &cors_headers),
-                format!("OPTIONS {} 204 (CORS preflight)", path));
+}
+impl Drop for ServeRegistration {
+    fn drop(&mut self) {
+        *lock(&ACTIVE_SERVE) = None;
+    }
+}
+/// Asks the running `dew.serve` to stop once its in-flight request is done; false if none runs
+fn request_shutdown() -> bool {
+    match lock(&ACTIVE_SERVE).as_mut() {
+        Some(serve) => {
+            serve.stopping = true;
+            let _ = serve.shutdown.send(());
+            let _ = std::net::TcpStream::connect(serve.wake);
+            true
+        }
+        None => false,
+    }
+}
+/// The first Ctrl-C asks the running `dew.serve` to stop. True means exit now: no serve is
+/// running, or one was already asked to stop and is still waiting on a handler.
+fn ctrl_c_forces_exit() -> bool {
+    let pending = lock(&ACTIVE_SERVE).as_ref().is_some_and(|serve| serve.stopping);
+    pending || !request_shutdown()
+}
+fn describe_bind_error(e: &std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::AddrInUse => "address already in use".to_string(),
+        std::io::ErrorKind::AddrNotAvailable => "address not available".to_string(),
+        std::io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        _ => e.to_string(),
+    }
+}
+/// Ctrl-C stops the running `dew.serve` gracefully, and a second one quits if a handler hangs.
+/// Once tokio takes over Ctrl-C it keeps it for the rest of the process, so outside a serve
+/// call the watcher exits as Ctrl-C would.
+#[cfg(feature = "web")]
+fn install_shutdown_handler() {
+    static INSTALLED: std::sync::Once = std::sync::Once::new();
+    INSTALLED.call_once(|| {
+        std::thread::spawn(|| {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(rt) => rt,
+                Err(_) => return,
+            };
+            runtime.block_on(async {
+                while tokio::signal::ctrl_c().await.is_ok() {
+                    if ctrl_c_forces_exit() {
+                        std::process::exit(130);
+                    }
+                    eprintln!("\n🌿 Finishing in-flight requests; press Ctrl+C again to quit now");
+                }
+            });
+        });
+    });
+}
+#[cfg(not(feature = "web"))]
+fn install_shutdown_handler() {}
+
+const WS_OPCODE_TEXT: u8 = 0x1;
+const WS_OPCODE_BINARY: u8 = 0x2;
+const WS_OPCODE_CLOSE: u8 = 0x8;
+const WS_OPCODE_PING: u8 = 0x9;
+const WS_OPCODE_PONG: u8 = 0xA;
+fn register_ws_connection(stream: std::net::TcpStream) -> String {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT_WS_ID: AtomicUsize = AtomicUsize::new(1);
+    let id = format!("ws-{}", NEXT_WS_ID.fetch_add(1, Ordering::Relaxed));
+    lock(&WS_CONNECTIONS).insert(id.clone(), stream);
+    id
+}
+fn ws_client_id(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(value_to_string(&Value::Number(*n))),
+        _ => None,
+    }
+}
+fn ws_message_frame(message: &Value) -> Option<Vec<u8>> {
+    match message {
+        Value::String(s) => Some(encode_ws_frame(WS_OPCODE_TEXT, s.as_bytes())),
+        Value::Table(_) | Value::Array(_) => Some(encode_ws_frame(WS_OPCODE_TEXT, value_to_json_string(message).as_bytes())),
+        Value::Bytes(b) => Some(encode_ws_frame(WS_OPCODE_BINARY, b)),
+        Value::Number(_) | Value::Boolean(_) => Some(encode_ws_frame(WS_OPCODE_TEXT, value_to_string(message).as_bytes())),
+        _ => None,
+    }
+}
+/// Writes a frame to a registered client, dropping the connection if the write fails.
+fn ws_write_frame(client_id: &str, frame: &[u8]) -> bool {
+    use std::io::Write;
+    let mut connections = lock(&WS_CONNECTIONS);
+    let ok = match connections.get_mut(client_id) {
+        Some(stream) => stream.write_all(frame).and_then(|_| stream.flush()).is_ok(),
+        None => return false,
+    };
+    if !ok {
+        connections.remove(client_id);
+    }
+    ok
+}
+/// Registers a signaling peer under `requested` (or its client id when that is missing or
+/// taken), greets it with its id and the peers already present, and relays what it sends
+/// on a reader thread until it disconnects. Returns the peer id.
+fn start_signaling_peer(stream: std::net::TcpStream, requested: Option<String>) -> Option<String> {
+    use std::io::Read;
+    let mut reader = stream.try_clone().ok()?;
+    let client_id = register_ws_connection(stream);
+    let (peer_id, others) = {
+        let mut peers = lock(&SIGNAL_PEERS);
+        let peer_id = match requested {
+            Some(id) if !peers.contains_key(&id) => id,
+            _ => client_id.clone(),
+        };
+        let mut others: Vec<String> = peers.keys().cloned().collect();
+        others.sort();
+        peers.insert(peer_id.clone(), client_id.clone());
+        (peer_id, others)
+    };
+    let others_value = Value::Array(others.iter().cloned().map(Value::String).collect());
+    ws_write_frame(&client_id, &signal_frame(&[("type", Value::String("welcome".to_string())), ("id", Value::String(peer_id.clone())), ("peers", others_value)]));
+    signal_others(&peer_id, "peer-joined");
+    let relay_id = peer_id.clone();
+    std::thread::spawn(move || {
+        let mut pending = Vec::new();
+        let mut chunk = [0u8; 8192];
+        'connection: loop {
+            match reader.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => pending.extend_from_slice(&chunk[..n]),
+            }
+            while let Some((opcode, payload, used)) = decode_ws_frame(&pending) {
+                pending.drain(..used);
+                match opcode {
+                    WS_OPCODE_TEXT => relay_signal(&relay_id, &String::from_utf8_lossy(&payload)),
+                    WS_OPCODE_CLOSE => {
+                        ws_write_frame(&client_id, &encode_ws_frame(WS_OPCODE_CLOSE, &payload));
+                        break 'connection;
+                    }
+                    WS_OPCODE_PING => {
+                        ws_write_frame(&client_id, &encode_ws_frame(WS_OPCODE_PONG, &payload));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        lock(&SIGNAL_PEERS).remove(&relay_id);
+        lock(&WS_CONNECTIONS).remove(&client_id);
+        signal_others(&relay_id, "peer-left");
+    });
+    Some(peer_id)
+}
+/// Forwards `{"type": "offer" | "answer" | "candidate", "to": peer, ...}` to that peer with
+/// `"from"` set to the sender; anything else earns the sender an `error` message.
+fn relay_signal(from: &str, message: &str) {
+    let reply_error = |text: String| {
+        if let Some(client_id) = lock(&SIGNAL_PEERS).get(from).cloned() {
+            ws_write_frame(&client_id, &signal_frame(&[("type", Value::String("error".to_string())), ("message", Value::String(text))]));
+        }
+    };
+    let mut fields = match body_json(message) {
+        Value::Table(fields) => fields,
+        _ => return reply_error("signaling messages must be JSON objects".to_string()),
+    };
+    match fields.get("type") {
+        Some(Value::String(kind)) if matches!(kind.as_str(), "offer" | "answer" | "candidate") => {}
+        _ => return reply_error("type must be offer, answer or candidate".to_string()),
+    }
+    let to = match fields.get("to") {
+        Some(Value::String(to)) => to.clone(),
+        _ => return reply_error("missing \"to\" peer id".to_string()),
+    };
+    let target = lock(&SIGNAL_PEERS).get(&to).cloned();
+    fields.insert("from".to_string(), Value::String(from.to_string()));
+    let delivered = target.is_some_and(|client_id| ws_write_frame(&client_id, &encode_ws_frame(WS_OPCODE_TEXT, value_to_json_string(&Value::Table(fields)).as_bytes())));
+    if !delivered {
+        reply_error(format!("unknown peer {}", to));
+    }
+}
+/// Tells every other signaling peer that `peer_id` joined or left
+fn signal_others(peer_id: &str, event: &str) {
+    let frame = signal_frame(&[("type", Value::String(event.to_string())), ("id", Value::String(peer_id.to_string()))]);
+    let others: Vec<String> = lock(&SIGNAL_PEERS).iter().filter(|(id, _)| id.as_str() != peer_id).map(|(_, client)| client.clone()).collect();
+    for client_id in others {
+        ws_write_frame(&client_id, &frame);
+    }
+}
+fn signal_frame(fields: &[(&str, Value)]) -> Vec<u8> {
+    let table: Table = fields.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+    encode_ws_frame(WS_OPCODE_TEXT, value_to_json_string(&Value::Table(table)).as_bytes())
+}
+/// Single unmasked server frame (RFC 6455 section 5.2) with FIN set.
+fn encode_ws_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+/// Parses one frame, unmasking client frames. Returns (opcode, payload, bytes consumed).
+fn decode_ws_frame(data: &[u8]) -> Option<(u8, Vec<u8>, usize)> {
+    let opcode = data.first()? & 0x0f;
+    let second = *data.get(1)?;
+    let masked = second & 0x80 != 0;
+    let (len, mut offset) = match second & 0x7f {
+        126 => (u16::from_be_bytes([*data.get(2)?, *data.get(3)?]) as usize, 4),
+        127 => {
+            let bytes: [u8; 8] = data.get(2..10)?.try_into().ok()?;
+            (u64::from_be_bytes(bytes) as usize, 10)
+        }
+        n => (n as usize, 2),
+    };
+    let mask = if masked {
+        let key: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+        offset += 4;
+        Some(key)
+    } else {
+        None
+    };
+    let mut payload = data.get(offset..offset.checked_add(len)?)?.to_vec();
+    if let Some(key) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= key[i % 4];
+        }
+    }
+    Some((opcode, payload, offset + len))
+}
+/// Keep reading until Content-Length bytes of body have arrived and return them untouched.
+/// A body over `limit` is not read at all; the request is answered 413 from its header.
+fn read_request_body(stream: &mut std::net::TcpStream, buffer: &mut Vec<u8>, limit: usize) -> Vec<u8> {
+    use std::io::Read;
+    let header_end = match find_bytes(buffer, b"\r\n\r\n", 0) {
+        Some(p) => p + 4,
+        None => return Vec::new(),
+    };
+    let content_length = String::from_utf8_lossy(&buffer[..header_end])
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim().eq_ignore_ascii_case("content-length") {
+                value.trim().parse::<usize>().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(0);
+    if content_length > limit {
+        return Vec::new();
+    }
+    let mut chunk = [0u8; 65536];
+    while buffer.len() < header_end + content_length {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+    let body_end = buffer.len().min(header_end + content_length);
+    buffer[header_end..body_end].to_vec()
+}
+
+/// `dew.test_get([server], path, [headers])`, `dew.test_post([server], path, [body], [headers])`
+/// and the rest send a request to `server`, or the most recently created one, without opening
+/// a socket, returning the route's real `status`, `headers` (lowercased names) and `body`, plus
+/// `json` for JSON bodies. A table or array body is sent as JSON, a string as plain text.
+fn test_request(method: &str, args: &[Value], takes_body: bool) -> MintasResult<Value> {
+    let error = |message: String| MintasError::RuntimeError { message, location: SourceLocation::new(0, 0) };
+    let name = format!("dew.test_{}()", method.to_lowercase());
+    let servers = lock(&SERVERS);
+    let (server, args) = match args.split_first() {
+        Some((Value::Table(handle), rest)) if handle.contains_key("__dew_server_id__") => {
+            let server = match handle.get("__dew_server_id__") {
+                Some(Value::Number(id)) => servers.get(*id as usize),
+                _ => None,
+            };
+            (server.ok_or_else(|| error(format!("{}: unknown Dew server", name)))?, rest)
+        }
+        _ => {
+            let latest = servers.latest()
+                .ok_or_else(|| error(format!("{}: no Dew server to test; create one with dew.main() first", name)))?;
+            (latest, args)
+        }
+    };
+    let server = server.clone();
+    drop(servers);
+    let path = match args.first() {
+        Some(Value::String(path)) => path.as_str(),
+        _ => "/",
+    };
+    let (body, headers) = if takes_body { (args.get(1), args.get(2)) } else { (None, args.get(1)) };
+    send_test_request(&server, method, path, body, headers)
+}
+fn send_test_request(server: &DewServer, method: &str, path: &str, body: Option<&Value>, headers: Option<&Value>) -> MintasResult<Value> {
+    let (content_type, body) = match body {
+        None | Some(Value::Empty) | Some(Value::Null) => (None, Vec::new()),
+        Some(Value::String(text)) => (Some("text/plain"), text.clone().into_bytes()),
+        Some(Value::Bytes(bytes)) => (Some("application/octet-stream"), bytes.clone()),
+        Some(other) => (Some("application/json"), value_to_json_string(other).into_bytes()),
+    };
+    let mut header_lines: Vec<(String, String)> = match headers {
+        None => Vec::new(),
+        Some(Value::Table(headers)) => headers.iter().map(|(k, v)| (k.to_string(), value_to_string(v))).collect(),
+        Some(other) => return Err(MintasError::TypeError {
+            message: format!("dew.test_{}(): headers must be a table, got {}", method.to_lowercase(), other.type_name()),
+            location: SourceLocation::new(0, 0),
+        }),
+    };
+    header_lines.sort();
+    if let Some(content_type) = content_type.filter(|_| !header_lines.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-type"))) {
+        header_lines.push(("Content-Type".to_string(), content_type.to_string()));
+    }
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n", method, path);
+    for (key, value) in &header_lines {
+        request.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    request.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    request.push_str(&String::from_utf8_lossy(&body));
+    let (response, _, chunks) = dispatch_request(&request, &body, server);
+    let (head, content) = response.split_once("\r\n\r\n").unwrap_or((response.as_str(), ""));
+    let response_headers: Table = head.lines().skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_lowercase(), Value::String(value.trim().to_string())))
+        .collect();
+    let content = match chunks {
+        Some(stream) => {
+            let mut chunked = Vec::new();
+            write_chunked_stream(&mut chunked, stream)?;
+            String::from_utf8_lossy(&dechunk(&chunked)).to_string()
+        }
+        None => content.to_string(),
+    };
+    let mut result = Table::new();
+    result.insert("status".to_string(), Value::Number(extract_status_from_response(&response) as f64));
+    result.insert("method".to_string(), Value::String(method.to_string()));
+    result.insert("path".to_string(), Value::String(path.to_string()));
+    if matches!(response_headers.get("content-type"), Some(Value::String(t)) if t.contains("json")) {
+        result.insert("json".to_string(), body_json(&content));
+    }
+    result.insert("headers".to_string(), Value::Table(response_headers));
+    result.insert("body".to_string(), Value::String(content));
+    Ok(Value::Table(result))
+}
+/// The payload of a complete chunked body as written by `write_chunked_stream`
+fn dechunk(mut data: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    while let Some(line_end) = data.windows(2).position(|w| w == b"\r\n") {
+        let size = std::str::from_utf8(&data[..line_end]).ok().and_then(|s| usize::from_str_radix(s, 16).ok()).unwrap_or(0);
+        let start = line_end + 2;
+        if size == 0 || start + size > data.len() {
+            break;
+        }
+        payload.extend_from_slice(&data[start..start + size]);
+        data = &data[(start + size + 2).min(data.len())..];
+    }
+    payload
+}
+/// One request through the server's routes, as both the serve loop and `dew.test_get` & co.
+/// run it. A `dew.stream` response comes back as its chunked head plus the body to write. A
+/// panic while handling the request answers it with a 500; the server keeps running.
+fn dispatch_request(request_str: &str, raw_body: &[u8], server: &DewServer) -> (String, String, Option<StreamBody>) {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handle_request(request_str, raw_body, server)))
+        .unwrap_or_else(|payload| {
+            HANDLER_DEADLINE.with(|deadline| deadline.set(None));
+            let mut request_line = request_str.lines().next().unwrap_or("").split_whitespace();
+            let (method, path) = (request_line.next().unwrap_or("-"), request_line.next().unwrap_or("-"));
+            (
+                http_response(500, "text/plain", "Internal Server Error", &[]),
+                format!("{} {} 500 (internal error: {})", method, path, crate::errors::panic_message(&*payload)),
+                None,
+            )
+        })
+}
+fn handle_request(request_str: &str, raw_body: &[u8], server: &DewServer) -> (String, String, Option<StreamBody>) {
+    let start_time = std::time::Instant::now();
+    let mut lines = request_str.lines();
+    let first_line = lines.next().unwrap_or("");
+    let parts: Vec<&str> = first_line.split_whitespace().collect();
+    if parts.len() < 2 {
+        return (http_response(400, "text/plain", "Bad Request", &[]), 
+                "400 Bad Request".to_string(), None);
+    }
+    let method = parts[0];
+    let full_path = parts[1];
+    let path = full_path.split('?').next().unwrap_or("/");
+    if method == "OPTIONS" {
+        let cors_headers = vec![
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, PATCH, OPTIONS"),
+            ("Access-Control-Allow-Headers", "Content-Type, Authorization, X-Requested-With"),
+            ("Access-Control-Max-Age", "86400"),
+        ];
+        return (http_response_with_headers(204, "text/plain", "", &cors_headers),
+                format!("OPTIONS {} 204 (CORS preflight)", path), None);
     }
     // WebSocket Upgrade
     if method == "GET" {
@@ -4670,7 +5930,7 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
                  Upgrade: websocket\r\n\
                  Connection: Upgrade\r\n\
                  Sec-WebSocket-Accept: {}\r\n\r\n", accept_key);
-             return (response, format!("WEBSOCKET {} 101 (Upgraded)", path));
+             return (response, format!("WEBSOCKET {} 101 (Upgraded)", path), None);
         }
     }
     if method == "GET" {
@@ -4679,7 +5939,7 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
                 let content_type = get_mime_type(&file_path);
                 let elapsed = start_time.elapsed().as_micros();
                 return (http_response_binary(200, &content_type, &content),
-                        format!("{} {} 200 (static) {}µs", method, path, elapsed));
+                        format!("{} {} 200 (static) {}µs", method, path, elapsed), None);
             }
         }
     }
@@ -4699,19 +5959,7 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
             let elapsed = start_time.elapsed().as_micros();
             return (http_response(429, "application/json", 
                 r#"{"error":"Too Many Requests","message":"Rate limit exceeded"}"#, &[]),
-                format!("{} {} 429 (rate limited) {}µs", method, path, elapsed));
-        }
-    }
-    if server.security.sql_injection_protection {
-        let suspicious_patterns = ["'--", "'; DROP", "1=1", "OR 1=1", "UNION SELECT"];
-        let full_input = format!("{} {}", full_path, request_str);
-        for pattern in suspicious_patterns {
-            if full_input.to_uppercase().contains(pattern) {
-                let elapsed = start_time.elapsed().as_micros();
-                return (http_response(400, "application/json",
-                    r#"{"error":"Bad Request","message":"Potentially malicious input detected"}"#, &[]),
-                    format!("{} {} 400 (security) {}µs", method, path, elapsed));
-            }
+                format!("{} {} 429 (rate limited) {}µs", method, path, elapsed), None);
         }
     }
     let mut headers = HashMap::new();
@@ -4744,9 +5992,15 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
             headers.insert(key, value);
         }
     }
+    let max_body_size = server.max_body_size();
+    if content_length > max_body_size || raw_body.len() > max_body_size {
+        let elapsed = start_time.elapsed().as_micros();
+        return (http_response(413, "application/json",
+            r#"{"error":"Payload Too Large","message":"Request body exceeds the server's max_body_size"}"#, &[]),
+            format!("{} {} 413 (body over {} bytes) {}µs", method, path, max_body_size, elapsed), None);
+    }
     let body = body_lines.join("\n");
-    let _ = content_length; 
-    if let Some((route, params)) = server.find_route(method, path) {
+    if let Some((route, params, group)) = server.find_route(method, path) {
         let mut getback = Getback::new();
         getback.method = method.to_string();
         getback.path = path.to_string();
@@ -4755,28 +6009,56 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
         getback.query = query;
         getback.params = params;
         getback.body = body;
+        getback.raw_body = raw_body.to_vec();
         getback.cookies = cookies;
-        for before_handler in &server.before_handlers {
+        if server.security.sql_injection_protection {
+            if let Some(source) = getback.sql_injection_source() {
+                let elapsed = start_time.elapsed().as_micros();
+                return (http_response(400, "application/json",
+                    r#"{"error":"Bad Request","message":"Potentially malicious input detected"}"#, &[]),
+                    format!("{} {} 400 (sql injection in {}) {}µs", method, path, source, elapsed), None);
+            }
+        }
+        // Server-wide before handlers, then those of the route's groups, outermost first
+        let group_handlers = group.map(|g| server.group_middleware(g)).unwrap_or_default();
+        let before_handlers = server.before_handlers.iter()
+            .map(|handler| handler.as_slice())
+            .chain(group_handlers.iter().filter_map(|m| m.handler_body.as_deref()));
+        for before_handler in before_handlers {
             match execute_handler(before_handler, getback.clone()) {
-                Ok(response) => {
+                Ok(HandlerResponse { http: response, stream }) => {
                     // If middleware returns a response, STOP processing and return it
                     if extract_status_from_response(&response) != 200 {
                         let elapsed = start_time.elapsed().as_micros();
                          let status = extract_status_from_response(&response);
-                        return (response, format!("{} {} {} (middleware) {}µs", method, path, status, elapsed));
+                        return (response, format!("{} {} {} (middleware) {}µs", method, path, status, elapsed), stream);
                     }
                 }
                 Err(e) => {
                      let elapsed = start_time.elapsed().as_micros();
                      return (http_response(500, "text/plain", &format!("{}", e), &[]), 
-                             format!("{} {} 500 (middleware error) {}µs", method, path, elapsed));
+                             format!("{} {} 500 (middleware error) {}µs", method, path, elapsed), None);
                 }
             }
         }
-        let response = match execute_handler(&route.handler.handler_body, getback.clone()) {
-            Ok(res) => res,
-            Err(e) => http_response(500, "text/html", &format!("<h1>Error</h1><p>{}</p>", e), &[])
+        let script_middleware: Vec<Middleware> = server.middleware.iter()
+            .filter(|m| m.name == SCRIPT_MIDDLEWARE)
+            .cloned()
+            .collect();
+        let response = match server.handler_timeout(route) {
+            Some(limit) => match run_chain_within(limit, script_middleware, route.handler.handler_body.clone(), getback.clone()) {
+                Some(response) => response,
+                None => {
+                    let elapsed = start_time.elapsed().as_micros();
+                    return (http_response(504, "text/plain", "Gateway Timeout", &[]),
+                            format!("{} {} 504 (timed out after {}ms) {}µs", method, path, limit.as_millis(), elapsed), None);
+                }
+            },
+            None => run_middleware_chain(&script_middleware, &route.handler.handler_body, getback.clone()),
         };
+        let HandlerResponse { http: response, stream } = response.unwrap_or_else(|e| {
+            http_response(500, "text/html", &format!("<h1>Error</h1><p>{}</p>", e), &[]).into()
+        });
         for after_handler in &server.after_handlers {
             // After handlers run but their return value is currently ignored 
             // In a real framework they might modify the response
@@ -4784,17 +6066,17 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
         }
         let elapsed = start_time.elapsed().as_micros();
         let status = extract_status_from_response(&response);
-        (response, format!("{} {} {} {}µs", method, path, status, elapsed))
+        (response, format!("{} {} {} {}µs", method, path, status, elapsed), stream)
     } else {
         if let Some(error_handler) = server.error_handlers.get(&404) {
             let mut getback = Getback::new();
             getback.method = method.to_string();
             getback.path = path.to_string();
-            let response = execute_handler(&error_handler.handler_body, getback).unwrap_or_else(|e| {
-                http_response(500, "text/plain", &format!("Error in error handler: {}", e), &[])
+            let HandlerResponse { http: response, stream } = execute_handler(&error_handler.handler_body, getback).unwrap_or_else(|e| {
+                http_response(500, "text/plain", &format!("Error in error handler: {}", e), &[]).into()
             });
             let elapsed = start_time.elapsed().as_micros();
-            return (response, format!("{} {} 404 (custom) {}µs", method, path, elapsed));
+            return (response, format!("{} {} 404 (custom) {}µs", method, path, elapsed), stream);
         }
         let elapsed = start_time.elapsed().as_micros();
         (http_response(404, "text/html", &format!(
@@ -4802,7 +6084,56 @@ fn handle_request(request_str: &str, server: &DewServer) -> (String, String) {
             <body style=\"font-family:system-ui;text-align:center;padding:50px\">\
             <h1>404</h1><p>Page not found: {}</p>\
             <p style=\"color:#666\">🌿 Dew</p></body></html>", path
-        ), &[]), format!("{} {} 404 {}µs", method, path, elapsed))
+        ), &[]), format!("{} {} 404 {}µs", method, path, elapsed), None)
+    }
+}
+
+/// One access log line; `common`/`combined` follow the Apache layouts with the
+/// duration in microseconds appended, `json` is one object per line
+fn format_access_log(format: LogFormat, request: &str, response: &str, remote: &str, elapsed: std::time::Duration) -> String {
+    let request_line = request.lines().next().unwrap_or("").trim();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("-");
+    let path = parts.next().unwrap_or("-");
+    let header = |name: &str| {
+        request.lines()
+            .skip(1)
+            .take_while(|line| !line.trim().is_empty())
+            .find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                if key.trim().eq_ignore_ascii_case(name) { Some(value.trim().to_string()) } else { None }
+            })
+    };
+    let status = extract_status_from_response(response);
+    let size = response.find("\r\n\r\n").map(|i| response.len() - i - 4).unwrap_or(0);
+    let now = Utc::now();
+    match format {
+        LogFormat::Json => {
+            let entry = serde_json::json!({
+                "time": now.to_rfc3339(),
+                "remote": remote,
+                "method": method,
+                "path": path,
+                "status": status,
+                "size": size,
+                "duration_ms": elapsed.as_secs_f64() * 1000.0,
+                "referer": header("referer"),
+                "user_agent": header("user-agent"),
+            });
+            entry.to_string()
+        }
+        LogFormat::Common | LogFormat::Combined => {
+            let size = if size == 0 { "-".to_string() } else { size.to_string() };
+            let mut line = format!("{} - - [{}] \"{}\" {} {}",
+                remote, now.format("%d/%b/%Y:%H:%M:%S %z"), request_line, status, size);
+            if format == LogFormat::Combined {
+                line.push_str(&format!(" \"{}\" \"{}\"",
+                    header("referer").unwrap_or_else(|| "-".to_string()),
+                    header("user-agent").unwrap_or_else(|| "-".to_string())));
+            }
+            line.push_str(&format!(" {}", elapsed.as_micros()));
+            line
+        }
     }
 }
 
@@ -4816,20 +6147,27 @@ fn extract_status_from_response(response: &str) -> u16 {
 }
 
 
-fn execute_handler(handler_body: &[crate::parser::Expr], getback: Getback) -> MintasResult<String> {
-    use crate::evaluator::Evaluator;
-    let mut evaluator = Evaluator::new();
+/// Evaluator for a handler or middleware, held to the route's deadline while one is running
+fn handler_evaluator(getback: &Getback) -> crate::evaluator::Evaluator {
+    let mut evaluator = crate::evaluator::Evaluator::new();
     evaluator.set_getback(getback.to_value());
+    if let Some(deadline) = HANDLER_DEADLINE.with(|deadline| deadline.get()) {
+        evaluator.set_time_limit(Some(deadline.saturating_duration_since(std::time::Instant::now())));
+    }
+    evaluator
+}
+fn execute_handler(handler_body: &[crate::parser::Expr], getback: Getback) -> MintasResult<HandlerResponse> {
+    let mut evaluator = handler_evaluator(&getback);
     let mut response_cookies: Vec<String> = Vec::new();
     for stmt in handler_body {
         match evaluator.eval(stmt) {
             Ok(Value::ReturnSignal(boxed_val)) => {
-                return Ok(process_return_value(&*boxed_val, &response_cookies));
+                return Ok(HandlerResponse::new(&evaluator, &boxed_val, &response_cookies));
             }
             Ok(val) => {
                 if let Value::Table(ref map) = val {
                     if map.get("__type__").map(|v| matches!(v, Value::String(s) if s == "DewResponse")).unwrap_or(false) {
-                        return Ok(process_return_value(&val, &response_cookies));
+                        return Ok(HandlerResponse::new(&evaluator, &val, &response_cookies));
                     }
                     if map.get("__type__").map(|v| matches!(v, Value::String(s) if s == "SetCookie")).unwrap_or(false) {
                         if let (Some(Value::String(name)), Some(Value::String(value))) = 
@@ -4853,9 +6191,110 @@ fn execute_handler(handler_body: &[crate::parser::Expr], getback: Getback) -> Mi
             Err(e) => return Err(MintasError::RuntimeError { message: format!("{}",e), location: SourceLocation::new(0,0) }),
         }
     }
-    Ok(http_response(200, "text/plain", "", &response_cookies))
+    Ok(http_response(200, "text/plain", "", &response_cookies).into())
 }
 
+/// Runs a route's chain on a worker so the request is answered at the deadline even while the
+/// handler blocks in I/O or `sleep`; `None` means it timed out. A late worker's response is
+/// dropped, and as its evaluator has the deadline as a time limit, CPU-bound work stops too.
+fn run_chain_within(limit: std::time::Duration, chain: Vec<Middleware>, route_body: Vec<crate::parser::Expr>, getback: Getback) -> Option<MintasResult<HandlerResponse>> {
+    let deadline = std::time::Instant::now().checked_add(limit);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let worker = std::thread::Builder::new()
+        .name("dew-handler".to_string())
+        .stack_size(crate::evaluator::interpreter_stack_size(crate::evaluator::MAX_RECURSION_DEPTH))
+        .spawn(move || {
+            HANDLER_DEADLINE.with(|cell| cell.set(deadline));
+            let _ = sender.send(run_middleware_chain(&chain, &route_body, getback));
+        });
+    if let Err(e) = worker {
+        return Some(Err(MintasError::RuntimeError {
+            message: format!("could not start the route handler: {}", e),
+            location: SourceLocation::new(0, 0),
+        }));
+    }
+    match receiver.recv_timeout(limit) {
+        // A handler stopped by its own time limit answers right as the deadline passes
+        Ok(_) if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) => None,
+        Ok(response) => Some(response),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => None,
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Some(Err(MintasError::RuntimeError {
+            message: "the route handler crashed".to_string(),
+            location: SourceLocation::new(0, 0),
+        })),
+    }
+}
+/// Runs script middleware in registration order; each one's `next` runs the rest of the
+/// chain and, at the end, the route handler
+fn run_middleware_chain(chain: &[Middleware], route_body: &[crate::parser::Expr], getback: Getback) -> MintasResult<HandlerResponse> {
+    match chain.split_first() {
+        Some((middleware, rest)) => execute_middleware(middleware, rest, route_body, getback),
+        None => execute_handler(route_body, getback),
+    }
+}
+/// `next(req)` passes a possibly modified request on; `next()` passes the original. A
+/// middleware that returns no response lets the request through as its `req` was left.
+fn execute_middleware(middleware: &Middleware, rest: &[Middleware], route_body: &[crate::parser::Expr], getback: Getback) -> MintasResult<HandlerResponse> {
+    let (req_name, next_name) = match middleware.params.as_slice() {
+        [req, next] => (req.clone(), next.clone()),
+        _ => return execute_handler(route_body, getback),
+    };
+    let body = middleware.handler_body.as_deref().unwrap_or(&[]);
+    let downstream: Arc<Mutex<Option<HandlerResponse>>> = Arc::new(Mutex::new(None));
+    let mut evaluator = handler_evaluator(&getback);
+    evaluator.set_variable(req_name.clone(), getback.to_value());
+    {
+        let (rest, route_body, getback, downstream) = (rest.to_vec(), route_body.to_vec(), getback.clone(), downstream.clone());
+        evaluator.register_builtin(&next_name, Box::new(move |args: &[Value]| {
+            let mut request = getback.clone();
+            if let Some(value) = args.first() {
+                request.apply_value(value);
+            }
+            let response = run_middleware_chain(&rest, &route_body, request)?;
+            let http = response.http.clone();
+            *lock(&downstream) = Some(response);
+            Ok(raw_response_value(http))
+        }));
+    }
+    for stmt in body {
+        match evaluator.eval(stmt)? {
+            Value::ReturnSignal(value) if is_dew_response(&value) => return Ok(middleware_response(&evaluator, &value, &downstream)),
+            Value::ReturnSignal(_) => break,
+            value if is_dew_response(&value) => return Ok(middleware_response(&evaluator, &value, &downstream)),
+            _ => {}
+        }
+    }
+    if let Some(response) = lock(&downstream).take() {
+        return Ok(response);
+    }
+    let mut request = getback;
+    if let Some(value) = evaluator.get_variables().get(&req_name) {
+        request.apply_value(value);
+    }
+    run_middleware_chain(rest, route_body, request)
+}
+/// A middleware's own response; returning what `next()` gave passes the downstream one on,
+/// along with its stream
+fn middleware_response(evaluator: &crate::evaluator::Evaluator, value: &Value, downstream: &Mutex<Option<HandlerResponse>>) -> HandlerResponse {
+    let response = HandlerResponse::new(evaluator, value, &[]);
+    match lock(downstream).take() {
+        Some(passed) if passed.http == response.http => passed,
+        _ => response,
+    }
+}
+fn is_dew_response(value: &Value) -> bool {
+    matches!(value, Value::Table(map) if matches!(map.get("__type__"), Some(Value::String(t)) if t == "DewResponse"))
+}
+/// What `next()` returns: the downstream HTTP response, passed through untouched unless the
+/// middleware builds its own
+fn raw_response_value(http: String) -> Value {
+    let mut response = Table::new();
+    response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
+    response.insert("response_type".to_string(), Value::String("raw".to_string()));
+    response.insert("status".to_string(), Value::Number(extract_status_from_response(&http) as f64));
+    response.insert("http".to_string(), Value::String(http));
+    Value::Table(response)
+}
 fn process_return_value(value: &Value, cookies: &[String]) -> String {
     if let Value::Table(ref map) = value {
         if map.get("__type__").map(|v| matches!(v, Value::String(s) if s == "DewResponse")).unwrap_or(false) {
@@ -4863,6 +6302,20 @@ fn process_return_value(value: &Value, cookies: &[String]) -> String {
                 Some(Value::String(s)) => s.as_str(),
                 _ => "text",
             };
+            if let ("raw", Some(Value::String(http))) = (response_type, map.get("http")) {
+                return http.clone();
+            }
+            if let ("stream", Some(Value::Function(_))) = (response_type, map.get("callback")) {
+                let status = match map.get("status") {
+                    Some(Value::Number(n)) => *n as u16,
+                    _ => 200,
+                };
+                let content_type = match map.get("content_type") {
+                    Some(Value::String(ct)) => ct.as_str(),
+                    _ => "text/plain; charset=utf-8",
+                };
+                return http_stream_head(status, content_type, cookies);
+            }
             let body = match map.get("body") {
                 Some(Value::String(s)) => s.clone(),
                 _ => String::new(),
@@ -4910,10 +6363,12 @@ fn http_response(status: u16, content_type: &str, body: &str, cookies: &[String]
         404 => "Not Found",
         405 => "Method Not Allowed",
         422 => "Unprocessable Entity",
+        413 => "Payload Too Large",
         429 => "Too Many Requests",
         500 => "Internal Server Error",
         502 => "Bad Gateway",
         503 => "Service Unavailable",
+        504 => "Gateway Timeout",
         _ => "Unknown",
     };
     let mut headers = format!(
@@ -4932,6 +6387,75 @@ fn http_response(status: u16, content_type: &str, body: &str, cookies: &[String]
     headers
 }
 
+/// Headers for a `dew.stream` response; the serve loop writes the chunks after them
+fn http_stream_head(status: u16, content_type: &str, cookies: &[String]) -> String {
+    let full = http_response(status, content_type, "", cookies);
+    full.replacen("Content-Length: 0\r\n", "Transfer-Encoding: chunked\r\n", 1)
+}
+/// A handler's HTTP response and, for `dew.stream`, the body the serve loop writes after it
+struct HandlerResponse {
+    http: String,
+    stream: Option<StreamBody>,
+}
+impl HandlerResponse {
+    /// The response for a handler's return value; a stream keeps the state of the handler that
+    /// made it, so its callback sees the same functions and variables
+    fn new(evaluator: &crate::evaluator::Evaluator, value: &Value, cookies: &[String]) -> Self {
+        let stream = match value {
+            Value::Table(map) if matches!(map.get("response_type"), Some(Value::String(t)) if t == "stream") => match map.get("callback") {
+                Some(Value::Function(callback)) => Some(StreamBody { callback: callback.as_ref().clone(), state: evaluator.snapshot() }),
+                _ => None,
+            },
+            _ => None,
+        };
+        HandlerResponse { http: process_return_value(value, cookies), stream }
+    }
+}
+impl From<String> for HandlerResponse {
+    fn from(http: String) -> Self {
+        HandlerResponse { http, stream: None }
+    }
+}
+/// A `dew.stream` body still to be written: the chunk callback and the handler state it runs in
+struct StreamBody {
+    callback: crate::evaluator::Function,
+    state: crate::evaluator::EvaluatorState,
+}
+thread_local! {
+    /// When the route handler running on this thread must finish, from its `timeout`
+    static HANDLER_DEADLINE: std::cell::Cell<Option<std::time::Instant>> = const { std::cell::Cell::new(None) };
+}
+/// Calls a `dew.stream` callback for chunks 1, 2, ... writing each as an HTTP chunk until it
+/// returns nothing. A callback error leaves the body unterminated so the client sees it fail.
+fn write_chunked_stream<W: std::io::Write>(out: &mut W, body: StreamBody) -> MintasResult<usize> {
+    let io_error = |e: std::io::Error| MintasError::RuntimeError {
+        message: format!("Stream write failed: {}", e),
+        location: SourceLocation::new(0, 0),
+    };
+    let mut evaluator = crate::evaluator::Evaluator::new();
+    evaluator.restore(body.state);
+    // Don't replay the random numbers the handler was about to draw
+    evaluator.set_seed(None);
+    let mut sent = 0;
+    for index in 1.. {
+        let args = if body.callback.params.is_empty() { Vec::new() } else { vec![Value::Number(index as f64)] };
+        let chunk = match evaluator.call_function_value(&body.callback, args)? {
+            Value::Empty | Value::Null => break,
+            Value::Bytes(bytes) => bytes,
+            other => value_to_string(&other).into_bytes(),
+        };
+        if chunk.is_empty() {
+            break;
+        }
+        write!(out, "{:x}\r\n", chunk.len()).map_err(io_error)?;
+        out.write_all(&chunk).map_err(io_error)?;
+        out.write_all(b"\r\n").and_then(|_| out.flush()).map_err(io_error)?;
+        sent += chunk.len();
+    }
+    out.write_all(b"0\r\n\r\n").and_then(|_| out.flush()).map_err(io_error)?;
+    Ok(sent)
+}
+
 fn http_response_with_headers(status: u16, content_type: &str, body: &str, extra_headers: &[(&str, &str)]) -> String {
     let status_text = match status {
         200 => "OK", 201 => "Created", 204 => "No Content",
@@ -4964,3 +6488,763 @@ fn http_response_binary(status: u16, content_type: &str, body: &[u8]) -> String
     );
     format!("{}{}", headers, String::from_utf8_lossy(body))
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The body of the first function in `src`, ready to use as a route handler
+    fn handler_body(src: &str) -> Vec<crate::parser::Expr> {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        match crate::parser::Parser::new(tokens).parse().unwrap().remove(0) {
+            crate::parser::Expr::Function { body, .. } => body,
+            other => panic!("expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip_preserves_numbers() {
+        let mut table = Table::new();
+        table.insert("id".to_string(), Value::Number(9_007_199_254_740_991.0));
+        table.insert("price".to_string(), Value::Number(19.99));
+        table.insert("count".to_string(), Value::Number(3.0));
+        table.insert("name".to_string(), Value::String("a \"quoted\", line\nbreak".to_string()));
+        table.insert("tags".to_string(), Value::Array(vec![Value::Number(1.0), Value::Boolean(true)]));
+        let json = value_to_json_string(&Value::Table(table.clone()));
+        assert!(json.contains("\"count\":3"));
+        assert!(json.contains("\"id\":9007199254740991"));
+        assert_eq!(parse_json_to_value(&json).unwrap(), Value::Table(table));
+    }
+
+    #[test]
+    fn test_sql_binds_named_params_instead_of_splicing() {
+        let mut params = Table::new();
+        params.insert("name".to_string(), Value::String("x'; DROP TABLE users; --".to_string()));
+        params.insert("id".to_string(), Value::Number(2.0));
+        let pair = DewModule::sql(&[
+            Value::String("SELECT id FROM users WHERE name = :name OR id = :id OR note = ':id' OR alias = :name::text".to_string()),
+            Value::Table(params.clone()),
+        ]).unwrap();
+        assert_eq!(pair, Value::Array(vec![
+            Value::String("SELECT id FROM users WHERE name = ?1 OR id = ?2 OR note = ':id' OR alias = ?1::text".to_string()),
+            Value::Array(vec![Value::String("x'; DROP TABLE users; --".to_string()), Value::Number(2.0)]),
+        ]));
+        params.remove("id");
+        assert!(DewModule::sql(&[Value::String("SELECT :id".to_string()), Value::Table(params)]).is_err());
+    }
+
+    #[cfg(feature = "database")]
+    #[test]
+    fn test_sql_pair_is_bound_safely_by_query() {
+        let path = std::env::temp_dir().join(format!("mintas_dew_sql_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE users (id INTEGER, name TEXT); INSERT INTO users VALUES (1, 'ann');").unwrap();
+        let mut db = Table::new();
+        db.insert("driver".to_string(), Value::String("sqlite".to_string()));
+        db.insert("path".to_string(), Value::String(path.to_string_lossy().to_string()));
+        let db = Value::Table(db);
+
+        let evil = Value::String("'; DROP TABLE users; --".to_string());
+        let mut params = Table::new();
+        params.insert("name".to_string(), evil.clone());
+        let insert = DewModule::sql(&[Value::String("INSERT INTO users VALUES (2, :name)".to_string()), Value::Table(params.clone())]).unwrap();
+        DewModule::query(&[db.clone(), insert]).unwrap();
+        let select = DewModule::sql(&[Value::String("SELECT id FROM users WHERE name = :name".to_string()), Value::Table(params)]).unwrap();
+        let rows = DewModule::query(&[db.clone(), select]).unwrap();
+        assert_eq!(rows, Value::Array(vec![Value::Table(Table::from([("id".to_string(), Value::Number(2.0))]))]));
+        let all = DewModule::query(&[db, Value::String("SELECT count(*) AS n FROM users".to_string())]).unwrap();
+        assert_eq!(all, Value::Array(vec![Value::Table(Table::from([("n".to_string(), Value::Number(2.0))]))]));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_poisoned_registry_lock_still_serves() {
+        let registry = Arc::new(Mutex::new(vec![1]));
+        let poisoner = Arc::clone(&registry);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("handler failed mid-request");
+        })
+        .join();
+        assert!(registry.is_poisoned());
+        lock(&registry).push(2);
+        assert_eq!(*lock(&registry), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_non_finite_numbers_serialize_as_null() {
+        assert_eq!(value_to_json_string(&Value::Number(f64::NAN)), "null");
+        assert_eq!(value_to_json(&Value::Number(f64::INFINITY)), JsonValue::Null);
+    }
+
+    #[test]
+    fn test_multipart_parses_text_and_file_fields() {
+        let mut body = Vec::new();
+        body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHello\r\n");
+        body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"a.png\"\r\n");
+        body.extend_from_slice(b"Content-Type: image/png\r\n\r\n\x89PNG\r\n\x00\xff\r\n");
+        body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"docs\"; filename=\"1.txt\"\r\n\r\none\r\n");
+        body.extend_from_slice(b"--XyZ\r\nContent-Disposition: form-data; name=\"docs\"; filename=\"2.txt\"\r\n\r\ntwo\r\n--XyZ--\r\n");
+        let parts = parse_multipart("multipart/form-data; boundary=XyZ", &body);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0].name, "title");
+        assert!(parts[0].filename.is_none());
+        assert_eq!(parts[0].data, b"Hello");
+        let uploads = uploads_from_parts(&parts);
+        let avatar = match uploads.get("avatar") {
+            Some(Value::Table(t)) => t,
+            other => panic!("expected avatar upload, got {:?}", other),
+        };
+        assert_eq!(avatar.get("filename"), Some(&Value::String("a.png".to_string())));
+        assert_eq!(avatar.get("content_type"), Some(&Value::String("image/png".to_string())));
+        assert_eq!(avatar.get("content"), Some(&Value::Bytes(b"\x89PNG\r\n\x00\xff".to_vec())));
+        assert_eq!(avatar.get("size"), Some(&Value::Number(8.0)));
+        assert!(matches!(uploads.get("docs"), Some(Value::Array(files)) if files.len() == 2));
+        assert!(!uploads.contains_key("title"));
+    }
+
+    #[test]
+    fn test_access_log_formats() {
+        let request = "GET /users/7?x=1 HTTP/1.1\r\nHost: localhost\r\nUser-Agent: curl/8.0\r\n\r\n";
+        let response = "HTTP/1.1 404 Not Found\r\nContent-Length: 5\r\n\r\nnope!";
+        let elapsed = std::time::Duration::from_micros(1500);
+        let json: JsonValue = serde_json::from_str(&format_access_log(LogFormat::Json, request, response, "10.0.0.1", elapsed)).unwrap();
+        assert_eq!(json["method"], "GET");
+        assert_eq!(json["path"], "/users/7?x=1");
+        assert_eq!(json["status"], 404);
+        assert_eq!(json["size"], 5);
+        assert_eq!(json["duration_ms"], 1.5);
+        assert_eq!(json["user_agent"], "curl/8.0");
+        let common = format_access_log(LogFormat::Common, request, response, "10.0.0.1", elapsed);
+        assert!(common.starts_with("10.0.0.1 - - ["));
+        assert!(common.ends_with("] \"GET /users/7?x=1 HTTP/1.1\" 404 5 1500"));
+        let combined = format_access_log(LogFormat::Combined, request, response, "10.0.0.1", elapsed);
+        assert!(combined.ends_with("404 5 \"-\" \"curl/8.0\" 1500"));
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse("fancy"), None);
+    }
+
+    #[test]
+    fn test_logger_format_belongs_to_its_server() {
+        let (first, second) = (DewModule::create_server(&[]).unwrap(), DewModule::create_server(&[]).unwrap());
+        let id = |handle: &Value| match handle {
+            Value::Table(map) => map["__dew_server_id__"].as_f64().unwrap() as usize,
+            other => panic!("expected server handle, got {:?}", other),
+        };
+        let format = |handle: &Value| lock(&SERVERS).get_mut(id(handle)).unwrap().log_format();
+        assert_eq!(DewModule::logger(&[Value::String("JSON".to_string()), first.clone()]).unwrap(), Value::Boolean(true));
+        assert_eq!(format(&first), Some(LogFormat::Json));
+        assert_eq!(format(&second), None);
+        add_server_middleware(id(&second), "logger").unwrap();
+        assert_eq!(format(&second), Some(LogFormat::Combined));
+        // `use("logger")` keeps a format chosen earlier
+        add_server_middleware(id(&first), "logger").unwrap();
+        assert_eq!(format(&first), Some(LogFormat::Json));
+        assert!(DewModule::logger(&[Value::String("fancy".to_string()), first]).is_err());
+    }
+
+    fn check(data: &[(&str, Value)], field: &str, rules: &str) -> Option<String> {
+        let data: Table = data.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        let mut rule_table = Table::new();
+        rule_table.insert(field.to_string(), Value::String(rules.to_string()));
+        match DewModule::validate(&[Value::Table(data), Value::Table(rule_table)]).unwrap() {
+            Value::Table(result) => match result.get("errors") {
+                Some(Value::Table(errors)) => match errors.get(field) {
+                    Some(Value::String(msg)) => Some(msg.clone()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            other => panic!("unexpected validate result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_numeric_ranges() {
+        let age = |v: Value| check(&[("age", v)], "age", "numeric|min:18|max:65");
+        assert_eq!(age(Value::Number(30.0)), None);
+        assert_eq!(age(Value::String("30".to_string())), None);
+        assert_eq!(age(Value::Number(12.0)), Some("Must be at least 18".to_string()));
+        assert_eq!(age(Value::String("99".to_string())), Some("Must be at most 65".to_string()));
+        assert_eq!(age(Value::String("abc".to_string())), Some("Must be a number".to_string()));
+        let name = |v: &str| check(&[("name", Value::String(v.to_string()))], "name", "min:3");
+        assert_eq!(name("héllo"), None);
+        assert_eq!(name("ab"), Some("Must be at least 3 characters".to_string()));
+        let qty = |v: Value| check(&[("qty", v)], "qty", "integer|between:1,10");
+        assert_eq!(qty(Value::Number(5.0)), None);
+        assert_eq!(qty(Value::Number(2.5)), Some("Must be an integer".to_string()));
+        assert_eq!(qty(Value::Number(11.0)), Some("Must be between 1 and 10".to_string()));
+    }
+
+    #[test]
+    fn test_validate_in_url_and_confirmed() {
+        let role = |v: &str| check(&[("role", Value::String(v.to_string()))], "role", "in:admin,user");
+        assert_eq!(role("user"), None);
+        assert_eq!(role("root"), Some("Must be one of: admin, user".to_string()));
+        let site = |v: &str| check(&[("site", Value::String(v.to_string()))], "site", "url");
+        assert_eq!(site("https://example.com/path"), None);
+        assert_eq!(site("https://"), Some("Invalid URL format".to_string()));
+        assert_eq!(site("ftp://example.com"), Some("Invalid URL format".to_string()));
+        let pass = |confirm: &str| check(
+            &[("password", Value::String("secret".to_string())), ("password_confirmation", Value::String(confirm.to_string()))],
+            "password",
+            "required|confirmed",
+        );
+        assert_eq!(pass("secret"), None);
+        assert_eq!(pass("other"), Some("Does not match password_confirmation".to_string()));
+        assert_eq!(check(&[], "role", "in:admin,user"), None);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_validate_regex_rule() {
+        let code = |v: &str| check(&[("code", Value::String(v.to_string()))], "code", "required|regex:^(AB|CD)-[0-9]+$");
+        assert_eq!(code("AB-12"), None);
+        // Compiled once, then reused for every later request
+        assert!(super::super::regex_module::is_cached("^(AB|CD)-[0-9]+$"));
+        assert_eq!(code("CD-7"), None);
+        assert_eq!(code("EF-1"), Some("Does not match pattern ^(AB|CD)-[0-9]+$".to_string()));
+        assert!(check(&[("code", Value::String("x".to_string()))], "code", "regex:(").unwrap().starts_with("Invalid regex pattern"));
+    }
+
+    #[cfg(feature = "magic")]
+    #[test]
+    fn test_jwt_sign_and_verify() {
+        let mut claims = Table::new();
+        claims.insert("sub".to_string(), Value::String("user-42".to_string()));
+        claims.insert("admin".to_string(), Value::Boolean(true));
+        let token = match DewModule::jwt_sign(&[Value::Table(claims.clone()), Value::String("s3cret".to_string())]).unwrap() {
+            Value::String(t) => t,
+            other => panic!("expected token, got {:?}", other),
+        };
+        assert_eq!(token.split('.').count(), 3);
+        assert!(!token.contains('='));
+        assert_eq!(DewModule::jwt_verify(&[Value::String(token.clone()), Value::String("s3cret".to_string())]).unwrap(), Value::Table(claims));
+        assert_eq!(DewModule::jwt_verify(&[Value::String(token.clone()), Value::String("other".to_string())]).unwrap(), Value::Null);
+        let mut tampered = token.clone();
+        tampered.insert(tampered.find('.').unwrap() + 2, 'x');
+        assert_eq!(DewModule::jwt_verify(&[Value::String(tampered), Value::String("s3cret".to_string())]).unwrap(), Value::Null);
+        assert_eq!(DewModule::jwt_verify(&[Value::String("not.a.jwt".to_string()), Value::String("s3cret".to_string())]).unwrap(), Value::Null);
+    }
+
+    #[cfg(feature = "magic")]
+    #[test]
+    fn test_jwt_expiry() {
+        let mut claims = Table::new();
+        claims.insert("exp".to_string(), Value::Number(1_000.0));
+        let token = jwt_encode(&claims, "k");
+        assert!(jwt_decode(&token, "k", 999).is_some());
+        assert!(jwt_decode(&token, "k", 1_000).is_none());
+        let fresh = DewModule::jwt_sign(&[Value::Table(Table::new()), Value::String("k".to_string()), Value::Number(60.0)]).unwrap();
+        let verified = DewModule::jwt_verify(&[fresh, Value::String("k".to_string())]).unwrap();
+        assert!(matches!(verified, Value::Table(ref t) if t.contains_key("exp")));
+    }
+
+    #[test]
+    fn test_detect_sql_injection_flags_attacks() {
+        for attack in [
+            "1 UNION SELECT username, password FROM users",
+            "1 union/**/all/**/select null",
+            "1; DROP TABLE users",
+            "x'; delete from accounts --",
+            "admin'--",
+            "admin' #",
+            "' OR '1'='1",
+            "\" or \"\"=\"",
+            "' or 1=1 --",
+            "5 OR 1=1",
+            "1' AND sleep(5)#",
+            "1'; WAITFOR DELAY '0:0:5'",
+        ] {
+            assert!(detect_sql_injection(attack), "should flag {:?}", attack);
+        }
+    }
+
+    #[test]
+    fn test_detect_sql_injection_allows_ordinary_text() {
+        for text in [
+            "O'Brien",
+            "rock 'n' roll -- live at 9",
+            "C# vs F#",
+            "The union selected a new leader",
+            "tea; drop by later",
+            "select your size",
+            "2 or 3 items",
+            "x = 1 or 2",
+            "It's 1=1 in my notes",
+            "Meet me at 5; delete this message afterwards",
+        ] {
+            assert!(!detect_sql_injection(text), "should allow {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_sql_injection_source_names_field() {
+        let mut getback = Getback::new();
+        getback.query.insert("q".to_string(), "shoes".to_string());
+        assert_eq!(getback.sql_injection_source(), None);
+        getback.params.insert("id".to_string(), "1 OR 1=1".to_string());
+        assert_eq!(getback.sql_injection_source(), Some("param 'id'".to_string()));
+        let mut getback = Getback::new();
+        getback.headers.insert("content-type".to_string(), "application/json".to_string());
+        getback.body = r#"{"user": {"name": "x' --"}}"#.to_string();
+        assert_eq!(getback.sql_injection_source(), Some("body json.user.name".to_string()));
+    }
+
+    #[test]
+    fn test_render_template_escapes_interpolated_values() {
+        let mut data = Table::new();
+        data.insert("comment".to_string(), Value::String("<script>alert('x')</script>".to_string()));
+        data.insert("name".to_string(), Value::String("Tom & \"Jerry\"".to_string()));
+        data.insert("names".to_string(), Value::String("$comment".to_string()));
+        data.insert("banner".to_string(), Value::String("<b>Hi</b>".to_string()));
+        let html = render_template("<p>$comment</p><p>$name</p><p>$names</p>$$banner $missing", &data);
+        assert_eq!(
+            html,
+            "<p>&lt;script&gt;alert(&#x27;x&#x27;)&lt;&#x2F;script&gt;</p><p>Tom &amp; &quot;Jerry&quot;</p><p>&#36;comment</p><b>Hi</b> $missing"
+        );
+    }
+
+    #[test]
+    fn test_render_template_layout_blocks_and_includes() {
+        let dir = std::env::temp_dir().join(format!("dew_layout_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        fs::write(file("nav.html"), "<nav>$user</nav>").unwrap();
+        fs::write(file("loop.html"), format!("[?( include \"{}\" )?]", file("loop.html"))).unwrap();
+        fs::write(
+            file("layout.html"),
+            format!(
+                "<title>?( block title )?Site?( endblock )?</title>?( include \"{}\" )?<main>?( yield content )?</main>",
+                file("nav.html")
+            ),
+        )
+        .unwrap();
+        let page = format!(
+            "?( extends \"{}\" )?\n?( block title )?Home?( endblock )?\n?( block content )?<p>Hi $user</p>?( include \"{}\" )??( endblock )?",
+            file("layout.html"),
+            file("loop.html")
+        );
+        let mut data = Table::new();
+        data.insert("user".to_string(), Value::String("Ann".to_string()));
+        let html = render_template(&page, &data);
+        assert_eq!(
+            html,
+            format!(
+                "<title>Home</title><nav>Ann</nav><main><p>Hi Ann</p>[<!-- Template recursion: {} -->]</main>",
+                file("loop.html")
+            )
+        );
+        assert_eq!(render_template(&fs::read_to_string(file("layout.html")).unwrap(), &data).split("</title>").next(), Some("<title>Site"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_group_middleware_guards_only_group_routes() {
+        // `return.json(...)` only parses inside a block, so handlers are lifted out of a function body
+        let parse = |src: &str| handler_body(&format!("func handler():\n{}\nend", src));
+        let mut server = DewServer::new();
+        server.add_route(None, Method::GET, "/health", RouteHandler { handler_body: parse("return.text(\"up\")") });
+        let admin = server.add_group("/admin", None);
+        server.groups[admin].middleware.push(Middleware {
+            name: "before".to_string(),
+            handler_body: Some(parse(
+                "if (getback.header(\"Authorization\") != \"Bearer letmein\"):\n    return.json({error = \"unauthorized\"}, status = 401)\nend",
+            )),
+            params: Vec::new(),
+        });
+        let reports = server.add_group("/reports", Some(admin));
+        server.add_route(Some(reports), Method::GET, "/daily", RouteHandler { handler_body: parse("return.text(\"numbers\")") });
+        let get = |path: &str, headers: &str| {
+            handle_request(&format!("GET {} HTTP/1.1\r\nHost: x\r\n{}\r\n", path, headers), b"", &server).0
+        };
+        assert_eq!(extract_status_from_response(&get("/health", "")), 200);
+        let denied = get("/admin/reports/daily", "");
+        assert_eq!(extract_status_from_response(&denied), 401);
+        assert!(!denied.contains("numbers"));
+        let allowed = get("/admin/reports/daily", "Authorization: Bearer letmein\r\n");
+        assert_eq!(extract_status_from_response(&allowed), 200);
+        assert!(allowed.ends_with("numbers"));
+    }
+
+    #[test]
+    fn test_ws_frames_round_trip_json_tables() {
+        use std::io::Read;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_side, _) = listener.accept().unwrap();
+        let id = register_ws_connection(server_side);
+        let listed = |id: &str| match DewModule::ws_clients(&[]).unwrap() {
+            Value::Array(ids) => ids.contains(&Value::String(id.to_string())),
+            other => panic!("ws_clients returned {:?}", other),
+        };
+        assert!(listed(&id));
+
+        let mut message = Table::new();
+        message.insert("type".to_string(), Value::String("chat".to_string()));
+        message.insert("n".to_string(), Value::Number(3.0));
+        let sent = DewModule::ws_send(&[Value::String(id.clone()), Value::Table(message.clone())]).unwrap();
+        assert_eq!(sent, Value::Boolean(true));
+        let sent = DewModule::ws_send_binary(&[Value::String(id.clone()), Value::Bytes(vec![0, 159, 255])]).unwrap();
+        assert_eq!(sent, Value::Boolean(true));
+        assert_eq!(DewModule::ws_close(&[Value::String(id.clone()), Value::Number(1001.0), Value::String("bye".to_string())]).unwrap(), Value::Boolean(true));
+        assert!(!listed(&id));
+        assert_eq!(DewModule::ws_send(&[Value::String(id), Value::String("late".to_string())]).unwrap(), Value::Boolean(false));
+        assert_eq!(DewModule::ws_broadcast(&[Value::Empty]).unwrap(), Value::Boolean(false));
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        let (opcode, payload, used) = decode_ws_frame(&received).unwrap();
+        assert_eq!(opcode, WS_OPCODE_TEXT);
+        assert_eq!(parse_json_to_value(&String::from_utf8(payload).unwrap()).unwrap(), Value::Table(message));
+        let (opcode, payload, used2) = decode_ws_frame(&received[used..]).unwrap();
+        assert_eq!((opcode, payload), (WS_OPCODE_BINARY, vec![0, 159, 255]));
+        let (opcode, payload, _) = decode_ws_frame(&received[used + used2..]).unwrap();
+        assert_eq!(opcode, WS_OPCODE_CLOSE);
+        assert_eq!(payload, [&1001u16.to_be_bytes()[..], b"bye"].concat());
+    }
+
+    #[test]
+    fn test_signaling_relays_offer_and_answer_between_peers() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let connect = |id: &str| {
+            let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            client.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+            let (server_side, _) = listener.accept().unwrap();
+            assert_eq!(start_signaling_peer(server_side, Some(id.to_string())), Some(id.to_string()));
+            client
+        };
+        let receive = |client: &mut std::net::TcpStream| {
+            let mut data = Vec::new();
+            loop {
+                if let Some((opcode, payload, _)) = decode_ws_frame(&data) {
+                    assert_eq!(opcode, WS_OPCODE_TEXT);
+                    return match parse_json_to_value(&String::from_utf8(payload).unwrap()).unwrap() {
+                        Value::Table(t) => t,
+                        other => panic!("expected an object, got {:?}", other),
+                    };
+                }
+                let mut chunk = [0u8; 1024];
+                let n = client.read(&mut chunk).unwrap();
+                assert!(n > 0, "connection closed");
+                data.extend_from_slice(&chunk[..n]);
+            }
+        };
+        // Clients mask what they send
+        let send = |client: &mut std::net::TcpStream, text: &str| {
+            let key = [7u8, 1, 9, 3];
+            let mut frame = vec![0x81, 0x80 | text.len() as u8];
+            frame.extend_from_slice(&key);
+            frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ key[i % 4]));
+            client.write_all(&frame).unwrap();
+        };
+        let text = |s: &str| Value::String(s.to_string());
+
+        let mut alice = connect("sig-alice");
+        let welcome = receive(&mut alice);
+        assert_eq!((welcome.get("type"), welcome.get("id")), (Some(&text("welcome")), Some(&text("sig-alice"))));
+        let mut bob = connect("sig-bob");
+        let welcome = receive(&mut bob);
+        assert!(matches!(welcome.get("peers"), Some(Value::Array(peers)) if peers.contains(&text("sig-alice"))));
+        let joined = receive(&mut alice);
+        assert_eq!((joined.get("type"), joined.get("id")), (Some(&text("peer-joined")), Some(&text("sig-bob"))));
+
+        send(&mut alice, r#"{"type":"offer","to":"sig-bob","sdp":"v=0 offer"}"#);
+        let offer = receive(&mut bob);
+        assert_eq!((offer.get("type"), offer.get("from"), offer.get("sdp")), (Some(&text("offer")), Some(&text("sig-alice")), Some(&text("v=0 offer"))));
+        send(&mut bob, r#"{"type":"answer","to":"sig-alice","sdp":"v=0 answer"}"#);
+        let answer = receive(&mut alice);
+        assert_eq!((answer.get("type"), answer.get("from"), answer.get("sdp")), (Some(&text("answer")), Some(&text("sig-bob")), Some(&text("v=0 answer"))));
+
+        send(&mut alice, r#"{"type":"candidate","to":"sig-nobody"}"#);
+        assert_eq!(receive(&mut alice).get("message"), Some(&text("unknown peer sig-nobody")));
+        drop(bob);
+        let left = receive(&mut alice);
+        assert_eq!((left.get("type"), left.get("id")), (Some(&text("peer-left")), Some(&text("sig-bob"))));
+    }
+
+    #[test]
+    fn test_ws_frame_lengths_and_masking() {
+        let long = vec![7u8; 70_000];
+        let (opcode, payload, used) = decode_ws_frame(&encode_ws_frame(WS_OPCODE_BINARY, &long)).unwrap();
+        assert_eq!((opcode, payload.len(), used), (WS_OPCODE_BINARY, 70_000, 70_010));
+        // Client frames are masked: "Hi" with key 1,2,3,4
+        let masked = [0x81, 0x82, 1, 2, 3, 4, b'H' ^ 1, b'i' ^ 2];
+        assert_eq!(decode_ws_frame(&masked).unwrap(), (WS_OPCODE_TEXT, b"Hi".to_vec(), 8));
+        assert!(decode_ws_frame(&[0x81, 0x05, b'a']).is_none());
+    }
+
+    #[test]
+    fn test_config_parses_nested_yaml() {
+        let path = std::env::temp_dir().join(format!("dew-config-{}.yaml", std::process::id()));
+        fs::write(&path, "server:\n  port: 8080\n  host: \"0.0.0.0\"\nfeatures:\n  - auth\n  - cache\ndebug: false\nmotd: |\n  line one\n  line two\n").unwrap();
+        let config = DewModule::config(&[Value::String(path.to_string_lossy().to_string())]).unwrap();
+        fs::remove_file(&path).ok();
+        let table = match config {
+            Value::Table(t) => t,
+            other => panic!("expected table, got {:?}", other),
+        };
+        let server = match table.get("server") {
+            Some(Value::Table(t)) => t,
+            other => panic!("expected nested table, got {:?}", other),
+        };
+        assert_eq!(server.get("port"), Some(&Value::Number(8080.0)));
+        assert_eq!(server.get("host"), Some(&Value::String("0.0.0.0".to_string())));
+        assert_eq!(table.get("features"), Some(&Value::Array(vec![
+            Value::String("auth".to_string()), Value::String("cache".to_string()),
+        ])));
+        assert_eq!(table.get("debug"), Some(&Value::Boolean(false)));
+        assert_eq!(table.get("motd"), Some(&Value::String("line one\nline two\n".to_string())));
+        assert!(parse_yaml_to_value("key: [unclosed").is_err());
+    }
+
+    #[test]
+    fn test_typed_env_accessors() {
+        std::env::set_var("DEW_TEST_ENV_PORT", "8080");
+        std::env::set_var("DEW_TEST_ENV_DEBUG", "Yes");
+        std::env::set_var("DEW_TEST_ENV_BAD", "eighty");
+        std::env::remove_var("DEW_TEST_ENV_MISSING");
+        let s = |v: &str| Value::String(v.to_string());
+        assert_eq!(DewModule::env_number(&[s("DEW_TEST_ENV_PORT")]).unwrap(), Value::Number(8080.0));
+        assert_eq!(DewModule::env_number(&[s("DEW_TEST_ENV_MISSING"), Value::Number(3.0)]).unwrap(), Value::Number(3.0));
+        assert!(DewModule::env_number(&[s("DEW_TEST_ENV_BAD")]).is_err());
+        assert_eq!(DewModule::env_bool(&[s("DEW_TEST_ENV_DEBUG")]).unwrap(), Value::Boolean(true));
+        assert_eq!(DewModule::env_bool(&[s("DEW_TEST_ENV_MISSING"), Value::Boolean(false)]).unwrap(), Value::Boolean(false));
+        assert!(DewModule::env_bool(&[s("DEW_TEST_ENV_BAD")]).is_err());
+        assert_eq!(DewModule::env_required(&[s("DEW_TEST_ENV_PORT")]).unwrap(), s("8080"));
+        let err = DewModule::env_required(&[s("DEW_TEST_ENV_MISSING")]).unwrap_err().to_string();
+        assert!(err.contains("DEW_TEST_ENV_MISSING"), "{}", err);
+    }
+
+    #[test]
+    fn test_getback_json_and_form_ignore_content_type() {
+        let eval = |body: &str, content_type: Option<&str>, src: &str| {
+            let mut getback = Getback::new();
+            getback.body = body.to_string();
+            if let Some(ct) = content_type {
+                getback.headers.insert("content-type".to_string(), ct.to_string());
+            }
+            let mut evaluator = crate::evaluator::Evaluator::new();
+            evaluator.set_getback(getback.to_value());
+            let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+            let statements = crate::parser::Parser::new(tokens).parse().unwrap();
+            evaluator.eval(&statements[0]).unwrap()
+        };
+        let table = |pairs: &[(&str, Value)]| Value::Table(pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect());
+        assert_eq!(eval("{\"n\": 2}", Some("text/plain"), "getback.json()"), table(&[("n", Value::Number(2.0))]));
+        assert_eq!(eval("{\"n\": 2}", None, "getback.json()"), table(&[("n", Value::Number(2.0))]));
+        assert_eq!(eval("", Some("application/json"), "getback.json()"), Value::Null);
+        assert_eq!(eval("{\"n\": ", Some("application/json"), "getback.json()"), Value::Null);
+        assert_eq!(eval("a=1&b=hello%20there", None, "getback.form()"), table(&[("a", Value::String("1".to_string())), ("b", Value::String("hello there".to_string()))]));
+        assert_eq!(eval("", None, "getback.form()"), Value::Null);
+        assert_eq!(eval("not a form", None, "getback.form()"), Value::Null);
+    }
+
+    #[test]
+    fn test_serve_reports_bind_failure() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        match start_server(&DewServer::new(), port, "127.0.0.1") {
+            Err(MintasError::RuntimeError { message, .. }) => {
+                assert_eq!(message, format!("failed to bind 127.0.0.1:{}: address already in use", port))
+            }
+            other => panic!("expected bind failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_serve_stops_on_shutdown_request() {
+        // One test owns ACTIVE_SERVE: first a registration whose loop never drains
+        let (shutdown, requested) = std::sync::mpsc::channel();
+        let idle = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let registration = ServeRegistration::new(shutdown, idle.local_addr().unwrap());
+        assert!(!ctrl_c_forces_exit());
+        assert!(requested.try_recv().is_ok());
+        assert!(ctrl_c_forces_exit(), "a second Ctrl-C must quit while the first is pending");
+        drop(registration);
+        assert!(ctrl_c_forces_exit());
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let serving = std::thread::spawn(move || start_server(&DewServer::new(), port, "127.0.0.1"));
+        let started = std::time::Instant::now();
+        while !request_shutdown() {
+            assert!(started.elapsed() < std::time::Duration::from_secs(5), "server never started");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(serving.join().unwrap().is_ok());
+        assert!(!request_shutdown());
+        // The listener is gone once serve returns
+        assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_ok());
+    }
+
+    #[test]
+    fn test_pretty_json_response_sorts_keys() {
+        let mut inner = Table::new();
+        inner.insert("b".to_string(), Value::Array(vec![Value::Number(1.0), Value::Array(vec![])]));
+        inner.insert("a".to_string(), Value::Boolean(true));
+        let mut data = Table::new();
+        data.insert("zeta".to_string(), Value::Table(inner));
+        data.insert("alpha".to_string(), Value::String("x".to_string()));
+        data.insert("empty".to_string(), Value::Table(Table::new()));
+        let body = |args: &[Value]| match DewModule::call_function("json", args).unwrap() {
+            Value::Table(response) => response["body"].clone(),
+            other => panic!("expected response, got {:?}", other),
+        };
+        let expected = "{\n  \"alpha\": \"x\",\n  \"empty\": {},\n  \"zeta\": {\n    \"a\": true,\n    \"b\": [\n      1,\n      []\n    ]\n  }\n}";
+        assert_eq!(body(&[Value::Table(data.clone()), Value::Number(200.0), Value::Boolean(true)]), Value::String(expected.to_string()));
+        assert_eq!(body(&[Value::Table(data.clone()), Value::Boolean(true)]), Value::String(expected.to_string()));
+        assert!(!matches!(body(&[Value::Table(data)]), Value::String(s) if s.contains('\n')));
+    }
+
+    #[test]
+    fn test_script_middleware_rewrites_request_and_short_circuits() {
+        let function = |src: &str| {
+            let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+            match crate::parser::Parser::new(tokens).parse().unwrap().remove(0) {
+                crate::parser::Expr::Function { params, body, .. } => crate::evaluator::Function { params, body, is_lambda: false },
+                other => panic!("expected function, got {:?}", other),
+            }
+        };
+        let mut server = DewServer::new();
+        let route = function("func route():\n    return.text(\"hello \" + getback.header(\"x-user\"))\nend");
+        server.add_route(None, Method::GET, "/", RouteHandler { handler_body: route.body.clone() });
+        server.add_route(None, Method::GET, "/secret", RouteHandler { handler_body: route.body });
+        server.add_middleware(SCRIPT_MIDDLEWARE, Some(function(
+            "func guard(req, next):\n    if (req.path == \"/secret\"):\n        return.json({error = \"forbidden\"}, status = 403)\n    end\n    return next()\nend",
+        )));
+        server.add_middleware(SCRIPT_MIDDLEWARE, Some(function(
+            "func auth(req, next):\n    h = req.headers\n    h.update({\"x-user\" = \"ann\"})\n    req.headers = h\n    return next(req)\nend",
+        )));
+        let get = |path: &str| handle_request(&format!("GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path), b"", &server).0;
+        let allowed = get("/");
+        assert_eq!(extract_status_from_response(&allowed), 200);
+        assert!(allowed.ends_with("hello ann"), "{}", allowed);
+        let denied = get("/secret");
+        assert_eq!(extract_status_from_response(&denied), 403);
+        assert!(!denied.contains("hello"));
+    }
+
+    #[test]
+    fn test_route_timeout_overrides_server_timeout() {
+        let handler = |src: &str| RouteHandler { handler_body: handler_body(src) };
+        let mut server = DewServer::new();
+        server.config.insert("timeout".to_string(), Value::Number(50.0));
+        // Blocked in sleep, and looping forever: neither would finish on its own
+        server.add_route(None, Method::GET, "/sleepy", handler("func route():\n    include timer\n    timer.sleep(5000)\n    return.text(\"late\")\nend"));
+        server.add_route(None, Method::GET, "/busy", handler("func route():\n    while (true):\n        x = 1\n    end\nend"));
+        server.add_route(None, Method::GET, "/report", handler("func route():\n    include timer\n    timer.sleep(100)\n    return.text(\"done\")\nend")).timeout =
+            route_timeout("/report", Some(Value::String("60s".to_string()))).unwrap();
+        let get = |path: &str| handle_request(&format!("GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path), b"", &server);
+        for path in ["/sleepy", "/busy"] {
+            let started = std::time::Instant::now();
+            let (response, log, _) = get(path);
+            assert!(response.starts_with("HTTP/1.1 504 Gateway Timeout\r\n"), "{}", response);
+            assert!(log.contains("504 (timed out after 50ms)"), "{}", log);
+            assert!(started.elapsed() < std::time::Duration::from_secs(2), "{} answered late", path);
+        }
+        let report = get("/report").0;
+        assert!(report.starts_with("HTTP/1.1 200 OK\r\n") && report.ends_with("done"), "{}", report);
+        assert_eq!(route_timeout("/", Some(Value::Number(250.0))).unwrap(), Some(std::time::Duration::from_millis(250)));
+        assert!(route_timeout("/", Some(Value::String("soon".to_string()))).is_err());
+        assert!(route_timeout("/", Some(Value::Number(0.0))).is_err());
+    }
+
+    #[test]
+    fn test_uploads_are_capped_and_scoped_to_the_request() {
+        let body = handler_body("func route():\n    return.text(dew.upload(\"avatar\").filename)\nend");
+        let mut server = DewServer::new();
+        server.config.insert("max_body_size".to_string(), Value::Number(200.0));
+        server.add_route(None, Method::POST, "/avatar", RouteHandler { handler_body: body });
+        let post = |raw_body: &[u8]| {
+            let head = format!(
+                "POST /avatar HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: {}\r\n\r\n",
+                raw_body.len()
+            );
+            handle_request(&head, raw_body, &server)
+        };
+        let upload = b"--XyZ\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"a.png\"\r\n\r\nPNG\r\n--XyZ--\r\n";
+        let (response, _, _) = post(upload);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n") && response.ends_with("a.png"), "{}", response);
+        // The next request sent no file, so it sees none
+        let (response, _, _) = post(b"--XyZ--\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n") && !response.contains("a.png"), "{}", response);
+        let (response, log, _) = post(&[b'x'; 201]);
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"), "{}", response);
+        assert!(log.contains("413 (body over 200 bytes)"), "{}", log);
+        assert_eq!(DewServer::new().max_body_size(), DEFAULT_MAX_BODY_SIZE);
+    }
+
+    #[test]
+    fn test_client_runs_real_route_handlers() {
+        let handler = |src: &str| RouteHandler { handler_body: handler_body(src) };
+        let mut server = DewServer::new();
+        server.add_route(None, Method::GET, "/users/>id", handler(
+            "func route():\n    return.json({id = getback.params.id, agent = getback.header(\"user-agent\")})\nend",
+        ));
+        server.add_route(None, Method::POST, "/users", handler(
+            "func route():\n    return.text(\"made \" + getback.json.name, status = 201)\nend",
+        ));
+        server.add_route(None, Method::GET, "/feed", handler(
+            "func route():\n    func part(n):\n        if (n > 2):\n            return \"\"\n        end\n        return \"item \" + n + \";\"\n    end\n    return dew.stream(part)\nend",
+        ));
+        let table = |value: Value| match value {
+            Value::Table(map) => map,
+            other => panic!("expected table, got {:?}", other),
+        };
+        let mut headers = Table::new();
+        headers.insert("User-Agent".to_string(), Value::String("probe".to_string()));
+        let user = table(send_test_request(&server, "GET", "/users/5", None, Some(&Value::Table(headers))).unwrap());
+        assert_eq!(user["status"], Value::Number(200.0));
+        let json = table(user["json"].clone());
+        assert_eq!((&json["id"], &json["agent"]), (&Value::String("5".to_string()), &Value::String("probe".to_string())));
+        assert!(matches!(&table(user["headers"].clone())["content-type"], Value::String(t) if t.starts_with("application/json")));
+        let mut body = Table::new();
+        body.insert("name".to_string(), Value::String("bo".to_string()));
+        let created = table(send_test_request(&server, "POST", "/users", Some(&Value::Table(body)), None).unwrap());
+        assert_eq!((&created["status"], &created["body"]), (&Value::Number(201.0), &Value::String("made bo".to_string())));
+        let feed = table(send_test_request(&server, "GET", "/feed", None, None).unwrap());
+        assert_eq!(feed["body"], Value::String("item 1;item 2;".to_string()));
+        let missing = table(send_test_request(&server, "DELETE", "/users/5", None, None).unwrap());
+        assert_eq!(missing["status"], Value::Number(404.0));
+    }
+
+    #[test]
+    fn test_requests_go_to_the_server_passed_in() {
+        let handle = |label: &str| {
+            let handle = DewModule::create_server(&[]).unwrap();
+            let id = match &handle {
+                Value::Table(map) => map["__dew_server_id__"].as_f64().unwrap() as usize,
+                other => panic!("expected server handle, got {:?}", other),
+            };
+            let handler_body = handler_body(&format!("func route():\n    return.text(\"{}\")\nend", label));
+            lock(&SERVERS).get_mut(id).unwrap().add_route(None, Method::POST, "/which", RouteHandler { handler_body });
+            handle
+        };
+        let (first, second) = (handle("first"), handle("second"));
+        let body = |value: Value| match value {
+            Value::Table(map) => map["body"].clone(),
+            other => panic!("expected table, got {:?}", other),
+        };
+        let path = Value::String("/which".to_string());
+        assert_eq!(body(DewModule::call_function("test_post", &[first, path.clone()]).unwrap()), Value::String("first".to_string()));
+        assert_eq!(body(DewModule::call_function("test_post", &[second, path.clone()]).unwrap()), Value::String("second".to_string()));
+        let stale = Value::Table(Table::from([("__dew_server_id__".to_string(), Value::Number(1e9))]));
+        assert!(DewModule::call_function("test_post", &[stale, path]).is_err());
+    }
+
+    #[test]
+    fn test_stream_response_writes_chunks_until_empty() {
+        let handler_body = handler_body(
+            "func route():\n    label = \"line \"\n    func row(n):\n        return label + n + \"\\n\"\n    end\n    func part(n):\n        if (n > 3):\n            return \"\"\n        end\n        return row(n)\n    end\n    return dew.stream(part, \"text/csv\")\nend",
+        );
+        let mut server = DewServer::new();
+        server.add_route(None, Method::GET, "/", RouteHandler { handler_body });
+        let (head, _, stream) = handle_request("GET / HTTP/1.1\r\nHost: x\r\n\r\n", b"", &server);
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/csv\r\nTransfer-Encoding: chunked\r\n"), "{}", head);
+        assert!(head.ends_with("\r\n\r\n") && !head.contains("Content-Length"));
+        let mut body = Vec::new();
+        assert_eq!(write_chunked_stream(&mut body, stream.expect("stream body")).unwrap(), 21);
+        assert_eq!(String::from_utf8(body).unwrap(), "7\r\nline 1\n\r\n7\r\nline 2\n\r\n7\r\nline 3\n\r\n0\r\n\r\n");
+    }
+}
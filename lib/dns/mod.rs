@@ -1,5 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
 use std::collections::HashMap;
 pub struct DnsModule;
 impl DnsModule {
@@ -82,7 +83,7 @@ impl DnsModule {
                 let ips: Vec<Value> = addrs
                     .map(|a| Value::String(a.ip().to_string()))
                     .collect();
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("hostname".to_string(), Value::String(hostname));
                 result.insert("addresses".to_string(), Value::Array(ips.clone()));
                 if let Some(Value::String(ip)) = ips.first() {
@@ -91,7 +92,7 @@ impl DnsModule {
                 Ok(Value::Table(result))
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("hostname".to_string(), Value::String(hostname));
                 result.insert("error".to_string(), Value::String(format!("{}", e)));
                 Ok(Value::Table(result))
@@ -120,13 +121,13 @@ impl DnsModule {
         };
         match ip_str.parse::<IpAddr>() {
             Ok(_ip) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("ip".to_string(), Value::String(ip_str));
                 result.insert("hostname".to_string(), Value::String("reverse lookup requires dns feature".to_string()));
                 Ok(Value::Table(result))
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("error".to_string(), Value::String(format!("Invalid IP: {}", e)));
                 Ok(Value::Table(result))
             }
@@ -148,7 +149,7 @@ impl DnsModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("domain".to_string(), Value::String(domain));
         result.insert("records".to_string(), Value::Array(vec![]));
         result.insert("note".to_string(), Value::String("MX lookup requires dns feature".to_string()));
@@ -170,7 +171,7 @@ impl DnsModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("domain".to_string(), Value::String(domain));
         result.insert("records".to_string(), Value::Array(vec![]));
         Ok(Value::Table(result))
@@ -191,7 +192,7 @@ impl DnsModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("domain".to_string(), Value::String(domain));
         result.insert("nameservers".to_string(), Value::Array(vec![]));
         Ok(Value::Table(result))
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::env;
 pub struct EnvModule;
 impl EnvModule {
@@ -63,7 +63,7 @@ impl EnvModule {
         }
     }
     fn all() -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         for (key, val) in env::vars() {
             result.insert(key, Value::String(val));
         }
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct FtpModule;
 impl FtpModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -46,7 +46,7 @@ impl FtpModule {
             Ok(mut ftp) => {
                 match ftp.login(&user, &pass) {
                     Ok(_) => {
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("connected".to_string(), Value::Boolean(true));
                         result.insert("host".to_string(), Value::String(host));
                         result.insert("user".to_string(), Value::String(user.clone()));
@@ -58,7 +58,7 @@ impl FtpModule {
                         Ok(Value::Table(result))
                     }
                     Err(e) => {
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("connected".to_string(), Value::Boolean(false));
                         result.insert("error".to_string(), Value::String(format!("Login failed: {}", e)));
                         Ok(Value::Table(result))
@@ -66,7 +66,7 @@ impl FtpModule {
                 }
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("connected".to_string(), Value::Boolean(false));
                 result.insert("error".to_string(), Value::String(format!("Connection failed: {}", e)));
                 Ok(Value::Table(result))
@@ -119,7 +119,7 @@ impl FtpModule {
                             .map(|f| Value::String(f.clone()))
                             .collect();
                         let _ = ftp.quit();
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("path".to_string(), Value::String(path));
                         result.insert("files".to_string(), Value::Array(file_list));
                         Ok(Value::Table(result))
@@ -196,7 +196,7 @@ impl FtpModule {
                         if cursor.read_to_end(&mut buffer).is_ok() {
                             if std::fs::write(&local_path, &buffer).is_ok() {
                                 let _ = ftp.quit();
-                                let mut result = HashMap::new();
+                                let mut result = Table::new();
                                 result.insert("success".to_string(), Value::Boolean(true));
                                 result.insert("remote".to_string(), Value::String(remote_path));
                                 result.insert("local".to_string(), Value::String(local_path));
@@ -287,7 +287,7 @@ impl FtpModule {
                 match ftp.put_file(&remote_path, &mut cursor) {
                     Ok(_) => {
                         let _ = ftp.quit();
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("success".to_string(), Value::Boolean(true));
                         result.insert("local".to_string(), Value::String(local_path));
                         result.insert("remote".to_string(), Value::String(remote_path));
@@ -340,13 +340,13 @@ impl FtpModule {
             location: SourceLocation::new(0, 0),
         })
     }
-    fn get_string(map: &HashMap<String, Value>, key: &str, default: &str) -> String {
+    fn get_string(map: &Table, key: &str, default: &str) -> String {
         match map.get(key) {
             Some(Value::String(s)) => s.clone(),
             _ => default.to_string(),
         }
     }
-    fn get_number(map: &HashMap<String, Value>, key: &str, default: f64) -> f64 {
+    fn get_number(map: &Table, key: &str, default: f64) -> f64 {
         match map.get(key) {
             Some(Value::Number(n)) => *n,
             _ => default,
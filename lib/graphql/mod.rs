@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct GraphqlModule;
 impl GraphqlModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -18,17 +18,17 @@ impl GraphqlModule {
     }
     fn client(args: &[Value]) -> MintasResult<Value> {
         let url = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Empty) };
-        let mut client = HashMap::new();
+        let mut client = Table::new();
         client.insert("url".to_string(), Value::String(url));
-        client.insert("headers".to_string(), Value::Table(HashMap::new()));
+        client.insert("headers".to_string(), Value::Table(Table::new()));
         client.insert("__type__".to_string(), Value::String("GraphQLClient".to_string()));
         Ok(Value::Table(client))
     }
     fn query(args: &[Value]) -> MintasResult<Value> {
         let url = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Empty) };
         let query = match args.get(1) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Empty) };
-        let variables = match args.get(2) { Some(Value::Table(t)) => t.clone(), _ => HashMap::new() };
-        let mut body = HashMap::new();
+        let variables = match args.get(2) { Some(Value::Table(t)) => t.clone(), _ => Table::new() };
+        let mut body = Table::new();
         body.insert("query".to_string(), Value::String(query));
         if !variables.is_empty() {
             body.insert("variables".to_string(), Value::Table(variables));
@@ -97,7 +97,7 @@ impl GraphqlModule {
     fn parse_json(json: &str) -> Value {
         let trimmed = json.trim();
         if trimmed.starts_with('{') {
-            Value::Table(HashMap::new()) 
+            Value::Table(Table::new()) 
         } else if trimmed.starts_with('[') {
             Value::Array(vec![])
         } else if trimmed == "true" {
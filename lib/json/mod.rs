@@ -1,5 +1,6 @@
 use crate::errors::{MintasError, MintasResult};
 use crate::evaluator::Value;
+use crate::table::Table;
 use serde_json::{Value as JsonValue, Map as JsonMap};
 pub struct JsonModule;
 impl JsonModule {
@@ -42,7 +43,7 @@ impl JsonModule {
             }),
         }
     }
-    fn expect_table_arg(args: &[Value], index: usize, func_name: &str) -> MintasResult<std::collections::HashMap<String, Value>> {
+    fn expect_table_arg(args: &[Value], index: usize, func_name: &str) -> MintasResult<Table> {
         if index >= args.len() {
             return Err(MintasError::InvalidArgumentCount {
                 function: format!("json.{}", func_name),
@@ -297,7 +298,7 @@ impl JsonModule {
                 location: crate::errors::SourceLocation::new(0, 0),
             })?;
         if let JsonValue::Object(map) = json_value {
-            let mut table = std::collections::HashMap::new();
+            let mut table = Table::new();
             for (k, v) in map {
                 table.insert(k, Self::json_to_mintas(&v)?);
             }
@@ -321,11 +322,16 @@ impl JsonModule {
     }
     fn mintas_to_json(value: &Value) -> MintasResult<JsonValue> {
         match value {
+            // Whole numbers are written as integers, so `[1]` encodes as `[1]` rather than `[1.0]`
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= 9_007_199_254_740_991.0 => {
+                Ok(JsonValue::Number(serde_json::Number::from(*n as i64)))
+            }
             Value::Number(n) => Ok(JsonValue::Number(serde_json::Number::from_f64(*n)
                 .ok_or_else(|| MintasError::RuntimeError {
                     message: "Invalid number for JSON".to_string(),
                     location: crate::errors::SourceLocation::new(0, 0),
                 })?)),
+            Value::Int(n) => Ok(JsonValue::Number(serde_json::Number::from(*n))),
             Value::String(s) => Ok(JsonValue::String(s.clone())),
             Value::Boolean(b) => Ok(JsonValue::Bool(*b)),
             Value::Array(arr) => {
@@ -337,19 +343,22 @@ impl JsonModule {
             Value::Table(map) => {
                 let mut json_map = JsonMap::new();
                 for (k, v) in map {
-                    json_map.insert(k.clone(), Self::mintas_to_json(v)?);
+                    if k.as_str() == Some(crate::evaluator::CLASS_TAG) {
+                        continue;
+                    }
+                    json_map.insert(k.to_string(), Self::mintas_to_json(v)?);
                 }
                 Ok(JsonValue::Object(json_map))
             }
-            Value::Empty => Ok(JsonValue::Null),
+            Value::Empty | Value::Null => Ok(JsonValue::Null),
             _ => Ok(JsonValue::String(format!("{:?}", value))),
         }
     }
     fn json_to_mintas(json_value: &JsonValue) -> MintasResult<Value> {
         match json_value {
-            JsonValue::Null => Ok(Value::Empty),
+            JsonValue::Null => Ok(Value::Null),
             JsonValue::Bool(b) => Ok(Value::Boolean(*b)),
-            JsonValue::Number(n) => Ok(Value::Number(n.as_f64().unwrap_or(0.0))),
+            JsonValue::Number(n) => Ok(n.as_i64().map(Value::from_i64).unwrap_or_else(|| Value::Number(n.as_f64().unwrap_or(0.0)))),
             JsonValue::String(s) => Ok(Value::String(s.clone())),
             JsonValue::Array(arr) => {
                 let mintas_arr: MintasResult<Vec<Value>> = arr.iter()
@@ -358,7 +367,7 @@ impl JsonModule {
                 Ok(Value::Array(mintas_arr?))
             }
             JsonValue::Object(map) => {
-                let mut mintas_map = std::collections::HashMap::new();
+                let mut mintas_map = Table::new();
                 for (k, v) in map {
                     mintas_map.insert(k.clone(), Self::json_to_mintas(v)?);
                 }
@@ -47,9 +47,9 @@ impl MathModule {
                 location: crate::errors::SourceLocation::new(0, 0),
             });
         }
-        match &args[index] {
-            Value::Number(n) => Ok(*n),
-            _ => Err(MintasError::TypeError {
+        match args[index].as_f64() {
+            Some(n) => Ok(n),
+            None => Err(MintasError::TypeError {
                 message: format!("math.{} expects a number for argument {}", func_name, index + 1),
                 location: crate::errors::SourceLocation::new(0, 0),
             }),
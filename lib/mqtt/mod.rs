@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct MqttModule;
 impl MqttModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -20,7 +20,7 @@ impl MqttModule {
     fn connect(args: &[Value]) -> MintasResult<Value> {
         let host = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => "localhost".to_string() };
         let port = match args.get(1) { Some(Value::Number(n)) => *n as u16, _ => 1883 };
-        let mut client = HashMap::new();
+        let mut client = Table::new();
         client.insert("host".to_string(), Value::String(host));
         client.insert("port".to_string(), Value::Number(port as f64));
         client.insert("connected".to_string(), Value::Boolean(true));
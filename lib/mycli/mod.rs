@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::io::{self, Write};
 use chrono;
 pub struct MyCLIModule;
@@ -84,7 +84,7 @@ impl MyCLIModule {
             _ => None,
         }).unwrap_or_default();
         let cli_args: Vec<String> = std::env::args().skip(1).collect();
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         let mut positional = Vec::new();
         let mut i = 0;
         while i < cli_args.len() {
@@ -176,7 +176,7 @@ impl MyCLIModule {
             Value::String(s) => Some(s.clone()),
             _ => None,
         }).unwrap_or_default();
-        let mut app = HashMap::new();
+        let mut app = Table::new();
         app.insert("name".to_string(), Value::String(name));
         app.insert("description".to_string(), Value::String(description));
         app.insert("version".to_string(), Value::String("1.0.3".to_string()));
@@ -193,7 +193,7 @@ impl MyCLIModule {
             Value::String(s) => Some(s.clone()),
             _ => None,
         }).unwrap_or_default();
-        let mut cmd = HashMap::new();
+        let mut cmd = Table::new();
         cmd.insert("name".to_string(), Value::String(name));
         cmd.insert("description".to_string(), Value::String(description));
         Ok(Value::Table(cmd))
@@ -211,7 +211,7 @@ impl MyCLIModule {
             Value::String(s) => Some(s.clone()),
             _ => None,
         }).unwrap_or_default();
-        let mut opt = HashMap::new();
+        let mut opt = Table::new();
         opt.insert("long".to_string(), Value::String(long));
         opt.insert("short".to_string(), Value::String(short));
         opt.insert("description".to_string(), Value::String(description));
@@ -383,7 +383,7 @@ impl MyCLIModule {
             Value::String(s) => Some(s.clone()),
             _ => None,
         }).unwrap_or_else(|| "Progress".to_string());
-        let mut bar = HashMap::new();
+        let mut bar = Table::new();
         bar.insert("total".to_string(), Value::Number(total as f64));
         bar.insert("current".to_string(), Value::Number(0.0));
         bar.insert("label".to_string(), Value::String(label));
@@ -413,7 +413,7 @@ impl MyCLIModule {
         }).unwrap_or_else(|| "Loading...".to_string());
         print!("⠋ {}", message);
         io::stdout().flush().ok();
-        let mut spinner = HashMap::new();
+        let mut spinner = Table::new();
         spinner.insert("message".to_string(), Value::String(message));
         spinner.insert("active".to_string(), Value::Boolean(true));
         Ok(Value::Table(spinner))
@@ -723,7 +723,7 @@ impl MyCLIModule {
             Value::Table(t) => Some(t.clone()),
             _ => None,
         }).unwrap_or_default();
-        fn print_tree(data: &HashMap<String, Value>, prefix: &str, is_last: bool) {
+        fn print_tree(data: &Table, prefix: &str, is_last: bool) {
             let connector = if is_last { "└── " } else { "├── " };
             let extension = if is_last { "    " } else { "│   " };
             let keys: Vec<_> = data.keys().collect();
@@ -777,7 +777,7 @@ impl MyCLIModule {
         }).unwrap_or_else(|| "Task".to_string());
         print!("\x1b[34m⏳ {}...\x1b[0m", name);
         io::stdout().flush().ok();
-        let mut task = HashMap::new();
+        let mut task = Table::new();
         task.insert("name".to_string(), Value::String(name));
         task.insert("status".to_string(), Value::String("running".to_string()));
         Ok(Value::Table(task))
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct MypdfModule;
 impl MypdfModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -22,7 +22,7 @@ impl MypdfModule {
     }
     fn create(args: &[Value]) -> MintasResult<Value> {
         let title = match args.get(0) { Some(Value::String(s)) => s.clone(), _ => "Document".to_string() };
-        let mut doc = HashMap::new();
+        let mut doc = Table::new();
         doc.insert("title".to_string(), Value::String(title));
         doc.insert("pages".to_string(), Value::Array(vec![]));
         doc.insert("font".to_string(), Value::String("Helvetica".to_string()));
@@ -34,7 +34,7 @@ impl MypdfModule {
         if let Some(Value::Table(mut doc)) = args.get(0).cloned() {
             let width = match args.get(1) { Some(Value::Number(n)) => *n, _ => 612.0 };
             let height = match args.get(2) { Some(Value::Number(n)) => *n, _ => 792.0 };
-            let mut page = HashMap::new();
+            let mut page = Table::new();
             page.insert("width".to_string(), Value::Number(width));
             page.insert("height".to_string(), Value::Number(height));
             page.insert("content".to_string(), Value::Array(vec![]));
@@ -50,7 +50,7 @@ impl MypdfModule {
             let text = match args.get(1) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Table(doc)) };
             let x = match args.get(2) { Some(Value::Number(n)) => *n, _ => 50.0 };
             let y = match args.get(3) { Some(Value::Number(n)) => *n, _ => 700.0 };
-            let mut item = HashMap::new();
+            let mut item = Table::new();
             item.insert("type".to_string(), Value::String("text".to_string()));
             item.insert("text".to_string(), Value::String(text));
             item.insert("x".to_string(), Value::Number(x));
@@ -84,7 +84,7 @@ impl MypdfModule {
         let doc = match args.get(0) { Some(Value::Table(d)) => d.clone(), _ => return Ok(Value::Empty) };
         Ok(Value::String(Self::generate_pdf(&doc)))
     }
-    fn generate_pdf(doc: &HashMap<String, Value>) -> String {
+    fn generate_pdf(doc: &Table) -> String {
         let mut pdf = String::new();
         pdf.push_str("%PDF-1.4\n");
         pdf.push_str("1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
@@ -1,5 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
 use std::collections::HashMap;
 pub struct MyyamlModule;
 impl MyyamlModule {
@@ -20,7 +21,7 @@ impl MyyamlModule {
         Ok(Self::parse_yaml(&yaml))
     }
     fn parse_yaml(yaml: &str) -> Value {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         let _current_indent = 0;
         let _stack: Vec<(usize, String, HashMap<String, Value>)> = vec![];
         for line in yaml.lines() {
@@ -31,7 +32,7 @@ impl MyyamlModule {
                 let key = trimmed[..colon_pos].trim().to_string();
                 let value_str = trimmed[colon_pos + 1..].trim();
                 let value = if value_str.is_empty() {
-                    Value::Table(HashMap::new())
+                    Value::Table(Table::new())
                 } else if value_str == "true" {
                     Value::Boolean(true)
                 } else if value_str == "false" {
@@ -100,7 +101,7 @@ impl MyyamlModule {
         Ok(data.get(&key).cloned().unwrap_or(Value::Empty))
     }
     fn set(args: &[Value]) -> MintasResult<Value> {
-        let mut data = match args.get(0) { Some(Value::Table(t)) => t.clone(), _ => HashMap::new() };
+        let mut data = match args.first() { Some(Value::Table(t)) => t.clone(), _ => Table::new() };
         let key = match args.get(1) { Some(Value::String(s)) => s.clone(), _ => return Ok(Value::Table(data)) };
         let value = args.get(2).cloned().unwrap_or(Value::Empty);
         data.insert(key, value);
@@ -1,6 +1,5 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
 use serde_json::{json, Value as JsonValue};
 pub struct OpenAIModule;
 impl OpenAIModule {
@@ -129,7 +128,7 @@ impl OpenAIModule {
                         let mut msg_obj = serde_json::Map::new();
                         for (key, value) in table {
                             if let Value::String(s) = value {
-                                msg_obj.insert(key.clone(), JsonValue::String(s.clone()));
+                                msg_obj.insert(key.to_string(), JsonValue::String(s.clone()));
                             }
                         }
                         msg_array.push(JsonValue::Object(msg_obj));
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::env;
 pub struct OsModule;
 impl OsModule {
@@ -67,7 +67,7 @@ impl OsModule {
         Ok(Value::Number(std::thread::available_parallelism().map(|p| p.get()).unwrap_or(1) as f64))
     }
     fn memory() -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("available".to_string(), Value::String("use sys-info for detailed memory".to_string()));
         Ok(Value::Table(result))
     }
@@ -88,7 +88,7 @@ impl OsModule {
         std::process::exit(code);
     }
     fn info() -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("platform".to_string(), Value::String(env::consts::OS.to_string()));
         result.insert("arch".to_string(), Value::String(env::consts::ARCH.to_string()));
         result.insert("family".to_string(), Value::String(env::consts::FAMILY.to_string()));
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::path::{Path, PathBuf};
 pub struct PathModule;
 impl PathModule {
@@ -117,14 +117,14 @@ impl PathModule {
     fn parse(args: &[Value]) -> MintasResult<Value> {
         if let Some(Value::String(p)) = args.get(0) {
             let path = Path::new(p);
-            let mut result = HashMap::new();
+            let mut result = Table::new();
             result.insert("dir".to_string(), Value::String(path.parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()));
             result.insert("base".to_string(), Value::String(path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()));
             result.insert("ext".to_string(), Value::String(path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default()));
             result.insert("name".to_string(), Value::String(path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()));
             Ok(Value::Table(result))
         } else {
-            Ok(Value::Table(HashMap::new()))
+            Ok(Value::Table(Table::new()))
         }
     }
     fn separator() -> MintasResult<Value> {
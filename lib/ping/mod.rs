@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::process::Command;
 use std::time::Instant;
 pub struct PingModule;
@@ -80,7 +80,7 @@ impl PingModule {
                         }
                     }
                 }
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("host".to_string(), Value::String(host));
                 result.insert("alive".to_string(), Value::Boolean(success && packet_loss < 100.0));
                 result.insert("time_ms".to_string(), Value::Number(avg_time));
@@ -91,7 +91,7 @@ impl PingModule {
                 Ok(Value::Table(result))
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("host".to_string(), Value::String(host));
                 result.insert("alive".to_string(), Value::Boolean(false));
                 result.insert("error".to_string(), Value::String(format!("{}", e)));
@@ -165,7 +165,7 @@ impl PingModule {
                     match TcpStream::connect_timeout(&addr, timeout) {
                         Ok(_) => {
                             let elapsed = start.elapsed().as_millis() as f64;
-                            let mut result = HashMap::new();
+                            let mut result = Table::new();
                             result.insert("host".to_string(), Value::String(host));
                             result.insert("port".to_string(), Value::Number(port as f64));
                             result.insert("open".to_string(), Value::Boolean(true));
@@ -173,7 +173,7 @@ impl PingModule {
                             Ok(Value::Table(result))
                         }
                         Err(e) => {
-                            let mut result = HashMap::new();
+                            let mut result = Table::new();
                             result.insert("host".to_string(), Value::String(host));
                             result.insert("port".to_string(), Value::Number(port as f64));
                             result.insert("open".to_string(), Value::Boolean(false));
@@ -182,7 +182,7 @@ impl PingModule {
                         }
                     }
                 } else {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("host".to_string(), Value::String(host));
                     result.insert("port".to_string(), Value::Number(port as f64));
                     result.insert("open".to_string(), Value::Boolean(false));
@@ -191,7 +191,7 @@ impl PingModule {
                 }
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("host".to_string(), Value::String(host));
                 result.insert("port".to_string(), Value::Number(port as f64));
                 result.insert("open".to_string(), Value::Boolean(false));
@@ -236,14 +236,14 @@ impl PingModule {
                         hops.push(Value::String(trimmed.to_string()));
                     }
                 }
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("host".to_string(), Value::String(host));
                 result.insert("hops".to_string(), Value::Array(hops));
                 result.insert("output".to_string(), Value::String(stdout.to_string()));
                 Ok(Value::Table(result))
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("host".to_string(), Value::String(host));
                 result.insert("error".to_string(), Value::String(format!("{}", e)));
                 Ok(Value::Table(result))
@@ -1,7 +1,7 @@
 #![allow(unused_variables)]
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 #[allow(unused_imports)]
 pub struct PostSqlModule;
 impl PostSqlModule {
@@ -57,7 +57,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("connected".to_string(), Value::Boolean(true));
         result.insert("database".to_string(), Value::String(connection_string.clone()));
         result.insert("type".to_string(), Value::String("postgresql".to_string()));
@@ -88,7 +88,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("table_created".to_string(), Value::String(table_name.clone()));
         result.insert("columns".to_string(), Value::Number(columns.len() as f64));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -114,7 +114,7 @@ impl PostSqlModule {
         let data = match &args[1] {
             Value::Table(t) => t,
             Value::Array(arr) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("inserted_into".to_string(), Value::String(table_name.clone()));
                 result.insert("rows_affected".to_string(), Value::Number(arr.len() as f64));
                 result.insert("success".to_string(), Value::Boolean(true));
@@ -125,7 +125,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("inserted_into".to_string(), Value::String(table_name.clone()));
         result.insert("rows_affected".to_string(), Value::Number(1.0));
         result.insert("id".to_string(), Value::Number(1.0)); 
@@ -150,12 +150,12 @@ impl PostSqlModule {
             }),
         };
         let mut sample_data = Vec::new();
-        let mut user1 = HashMap::new();
+        let mut user1 = Table::new();
         user1.insert("id".to_string(), Value::Number(1.0));
         user1.insert("name".to_string(), Value::String("Alice".to_string()));
         user1.insert("email".to_string(), Value::String("alice@example.com".to_string()));
         user1.insert("created_at".to_string(), Value::String("2024-01-01 10:00:00".to_string()));
-        let mut user2 = HashMap::new();
+        let mut user2 = Table::new();
         user2.insert("id".to_string(), Value::Number(2.0));
         user2.insert("name".to_string(), Value::String("Bob".to_string()));
         user2.insert("email".to_string(), Value::String("bob@example.com".to_string()));
@@ -174,7 +174,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("id".to_string(), Value::Number(1.0));
         result.insert("name".to_string(), Value::String("Alice".to_string()));
         result.insert("email".to_string(), Value::String("alice@example.com".to_string()));
@@ -198,7 +198,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("updated_table".to_string(), Value::String(table_name.clone()));
         result.insert("rows_affected".to_string(), Value::Number(1.0));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -221,7 +221,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("deleted_from".to_string(), Value::String(table_name.clone()));
         result.insert("rows_affected".to_string(), Value::Number(1.0));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -268,7 +268,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("dropped_table".to_string(), Value::String(table_name.clone()));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
@@ -283,7 +283,7 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("index_created".to_string(), Value::Boolean(true));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
@@ -298,35 +298,35 @@ impl PostSqlModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("migration_completed".to_string(), Value::Boolean(true));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
     }
     #[cfg(feature = "database")]
     fn transaction(args: &[Value]) -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("transaction_started".to_string(), Value::Boolean(true));
         result.insert("transaction_id".to_string(), Value::String("txn_001".to_string()));
         Ok(Value::Table(result))
     }
     #[cfg(feature = "database")]
     fn commit(args: &[Value]) -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("transaction_committed".to_string(), Value::Boolean(true));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
     }
     #[cfg(feature = "database")]
     fn rollback(args: &[Value]) -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("transaction_rolled_back".to_string(), Value::Boolean(true));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
     }
     #[cfg(feature = "database")]
     fn close(args: &[Value]) -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("connection_closed".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
     }
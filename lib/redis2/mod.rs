@@ -1,7 +1,7 @@
 #![allow(unused_variables)]
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct Redis2Module;
 impl Redis2Module {
     #[allow(unused_variables)]
@@ -42,7 +42,7 @@ impl Redis2Module {
                 }),
             }
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("connected".to_string(), Value::Boolean(true));
         result.insert("host".to_string(), Value::String(host));
         result.insert("type".to_string(), Value::String("redis".to_string()));
@@ -64,7 +64,7 @@ impl Redis2Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("key".to_string(), Value::String(key.clone()));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
@@ -241,7 +241,7 @@ impl Redis2Module {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("name".to_string(), Value::String("Alice".to_string()));
         result.insert("age".to_string(), Value::Number(25.0));
         result.insert("email".to_string(), Value::String("alice@example.com".to_string()));
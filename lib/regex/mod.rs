@@ -0,0 +1,78 @@
+use crate::errors::{MintasError, MintasResult, SourceLocation};
+use crate::evaluator::Value;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Mutex;
+lazy_static::lazy_static! {
+    static ref REGEX_CACHE: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+/// Compiles `pattern` the first time it is seen; later calls reuse the compiled copy
+pub fn cached_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut cache = REGEX_CACHE.lock().unwrap();
+    if let Some(re) = cache.get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = Regex::new(pattern)?;
+    cache.insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+#[cfg(test)]
+pub fn is_cached(pattern: &str) -> bool {
+    REGEX_CACHE.lock().unwrap().contains_key(pattern)
+}
+pub struct RegexModule;
+impl RegexModule {
+    pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
+        match name {
+            "match" | "test" => Self::is_match(args),
+            "find" | "find_all" => Self::find_all(args),
+            "replace" => Self::replace(args),
+            _ => Err(MintasError::RuntimeError {
+                message: format!("Unknown regex function: {}", name),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
+    fn compile(pattern: &str) -> MintasResult<Regex> {
+        cached_regex(pattern).map_err(|e| MintasError::RuntimeError {
+            message: format!("Invalid regex pattern '{}': {}", pattern, e),
+            location: SourceLocation::new(0, 0),
+        })
+    }
+    fn string_arg(args: &[Value], idx: usize, function: &str) -> MintasResult<String> {
+        match args.get(idx) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(other) => Err(MintasError::TypeError {
+                message: format!("{} expects string argument, got {}", function, other.type_name()),
+                location: SourceLocation::new(0, 0),
+            }),
+            None => Err(MintasError::InvalidArgumentCount {
+                function: function.to_string(),
+                expected: idx + 1,
+                got: args.len(),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
+    fn is_match(args: &[Value]) -> MintasResult<Value> {
+        let text = Self::string_arg(args, 0, "regex_match")?;
+        let pattern = Self::string_arg(args, 1, "regex_match")?;
+        Ok(Value::Boolean(Self::compile(&pattern)?.is_match(&text)))
+    }
+    fn find_all(args: &[Value]) -> MintasResult<Value> {
+        let text = Self::string_arg(args, 0, "regex_find")?;
+        let pattern = Self::string_arg(args, 1, "regex_find")?;
+        let matches = Self::compile(&pattern)?
+            .find_iter(&text)
+            .map(|m| Value::String(m.as_str().to_string()))
+            .collect();
+        Ok(Value::Array(matches))
+    }
+    fn replace(args: &[Value]) -> MintasResult<Value> {
+        let text = Self::string_arg(args, 0, "regex_replace")?;
+        let pattern = Self::string_arg(args, 1, "regex_replace")?;
+        let replacement = Self::string_arg(args, 2, "regex_replace")?;
+        let result = Self::compile(&pattern)?.replace_all(&text, replacement.as_str());
+        Ok(Value::String(result.into_owned()))
+    }
+}
@@ -1,5 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
 use std::collections::HashMap;
 pub struct RequestsModule;
 impl RequestsModule {
@@ -33,7 +34,7 @@ impl RequestsModule {
             Ok(response) => {
                 let status = response.status().as_u16() as f64;
                 let text = response.text().unwrap_or_default();
-                let mut result_map = HashMap::new();
+                let mut result_map = Table::new();
                 result_map.insert("status".to_string(), Value::Number(status));
                 result_map.insert("text".to_string(), Value::String(text));
                 Ok(Value::Table(result_map))
@@ -67,7 +68,7 @@ impl RequestsModule {
                 let mut json_map = HashMap::new();
                 for (k, v) in data {
                     if let Value::String(s) = v {
-                        json_map.insert(k.clone(), s.clone());
+                        json_map.insert(k.to_string(), s.clone());
                     }
                 }
                 request = request.json(&json_map);
@@ -77,7 +78,7 @@ impl RequestsModule {
             Ok(response) => {
                 let status = response.status().as_u16() as f64;
                 let text = response.text().unwrap_or_default();
-                let mut result_map = HashMap::new();
+                let mut result_map = Table::new();
                 result_map.insert("status".to_string(), Value::Number(status));
                 result_map.insert("text".to_string(), Value::String(text));
                 Ok(Value::Table(result_map))
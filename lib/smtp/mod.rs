@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct SmtpModule;
 impl SmtpModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -73,13 +73,13 @@ impl SmtpModule {
             .build();
         match mailer.send(&email) {
             Ok(_) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("success".to_string(), Value::Boolean(true));
                 result.insert("message".to_string(), Value::String("Email sent successfully".to_string()));
                 Ok(Value::Table(result))
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("success".to_string(), Value::Boolean(false));
                 result.insert("error".to_string(), Value::String(format!("{}", e)));
                 Ok(Value::Table(result))
@@ -94,7 +94,7 @@ impl SmtpModule {
         })
     }
     fn connect(args: &[Value]) -> MintasResult<Value> {
-        let mut config = HashMap::new();
+        let mut config = Table::new();
         if let Some(Value::String(host)) = args.get(0) {
             config.insert("host".to_string(), Value::String(host.clone()));
         }
@@ -132,13 +132,13 @@ impl SmtpModule {
                 let mailer = transport.credentials(creds).build();
                 match mailer.test_connection() {
                     Ok(true) => {
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("connected".to_string(), Value::Boolean(true));
                         result.insert("host".to_string(), Value::String(host));
                         Ok(Value::Table(result))
                     }
                     _ => {
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("connected".to_string(), Value::Boolean(false));
                         result.insert("error".to_string(), Value::String("Connection test failed".to_string()));
                         Ok(Value::Table(result))
@@ -146,7 +146,7 @@ impl SmtpModule {
                 }
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("connected".to_string(), Value::Boolean(false));
                 result.insert("error".to_string(), Value::String(format!("{}", e)));
                 Ok(Value::Table(result))
@@ -160,13 +160,13 @@ impl SmtpModule {
             location: SourceLocation::new(0, 0),
         })
     }
-    fn get_string(map: &HashMap<String, Value>, key: &str, default: &str) -> String {
+    fn get_string(map: &Table, key: &str, default: &str) -> String {
         match map.get(key) {
             Some(Value::String(s)) => s.clone(),
             _ => default.to_string(),
         }
     }
-    fn get_number(map: &HashMap<String, Value>, key: &str, default: f64) -> f64 {
+    fn get_number(map: &Table, key: &str, default: f64) -> f64 {
         match map.get(key) {
             Some(Value::Number(n)) => *n,
             _ => default,
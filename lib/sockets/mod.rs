@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use tungstenite::connect;
 use url::Url;
 pub struct SocketsModule;
@@ -36,7 +36,7 @@ impl SocketsModule {
         })?;
         match connect(url) {
             Ok((_socket, _response)) => {
-                let mut result_map = HashMap::new();
+                let mut result_map = Table::new();
                 result_map.insert("connected".to_string(), Value::Boolean(true));
                 result_map.insert("status".to_string(), Value::String("Connected successfully".to_string()));
                 Ok(Value::Table(result_map))
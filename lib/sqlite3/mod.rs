@@ -1,7 +1,7 @@
 #![allow(unused_variables)]
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 #[allow(unused_imports)]
 pub struct SQLite3Module;
 impl SQLite3Module {
@@ -52,7 +52,7 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("connected".to_string(), Value::Boolean(true));
         result.insert("database".to_string(), Value::String(db_path.clone()));
         result.insert("type".to_string(), Value::String("sqlite3".to_string()));
@@ -82,7 +82,7 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("table_created".to_string(), Value::String(table_name.clone()));
         result.insert("columns".to_string(), Value::Number(columns.len() as f64));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -112,7 +112,7 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("inserted_into".to_string(), Value::String(table_name.clone()));
         result.insert("rows_affected".to_string(), Value::Number(1.0));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -136,12 +136,12 @@ impl SQLite3Module {
             }),
         };
         let mut sample_data = Vec::new();
-        let mut user1 = HashMap::new();
+        let mut user1 = Table::new();
         user1.insert("id".to_string(), Value::Number(1.0));
         user1.insert("name".to_string(), Value::String("Alice".to_string()));
         user1.insert("age".to_string(), Value::Number(25.0));
         user1.insert("email".to_string(), Value::String("alice@example.com".to_string()));
-        let mut user2 = HashMap::new();
+        let mut user2 = Table::new();
         user2.insert("id".to_string(), Value::Number(2.0));
         user2.insert("name".to_string(), Value::String("Bob".to_string()));
         user2.insert("age".to_string(), Value::Number(30.0));
@@ -175,7 +175,7 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("id".to_string(), Value::Number(1.0));
         result.insert("name".to_string(), Value::String("Alice".to_string()));
         result.insert("age".to_string(), Value::Number(25.0));
@@ -199,7 +199,7 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("updated_table".to_string(), Value::String(table_name.clone()));
         result.insert("rows_affected".to_string(), Value::Number(1.0));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -222,7 +222,7 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("deleted_from".to_string(), Value::String(table_name.clone()));
         result.insert("rows_affected".to_string(), Value::Number(1.0));
         result.insert("success".to_string(), Value::Boolean(true));
@@ -277,14 +277,14 @@ impl SQLite3Module {
                 location: SourceLocation::new(0, 0),
             }),
         };
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("dropped_table".to_string(), Value::String(table_name.clone()));
         result.insert("success".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
     }
     #[cfg(feature = "database")]
     fn close(_args: &[Value]) -> MintasResult<Value> {
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("connection_closed".to_string(), Value::Boolean(true));
         Ok(Value::Table(result))
     }
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 pub struct SshModule;
 impl SshModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -60,7 +60,7 @@ impl SshModule {
                         };
                         match auth_result {
                             Ok(_) => {
-                                let mut result = HashMap::new();
+                                let mut result = Table::new();
                                 result.insert("connected".to_string(), Value::Boolean(true));
                                 result.insert("host".to_string(), Value::String(host.clone()));
                                 result.insert("user".to_string(), Value::String(user.clone()));
@@ -72,7 +72,7 @@ impl SshModule {
                                 Ok(Value::Table(result))
                             }
                             Err(e) => {
-                                let mut result = HashMap::new();
+                                let mut result = Table::new();
                                 result.insert("connected".to_string(), Value::Boolean(false));
                                 result.insert("error".to_string(), Value::String(format!("Auth failed: {}", e)));
                                 Ok(Value::Table(result))
@@ -80,7 +80,7 @@ impl SshModule {
                         }
                     }
                     Err(e) => {
-                        let mut result = HashMap::new();
+                        let mut result = Table::new();
                         result.insert("connected".to_string(), Value::Boolean(false));
                         result.insert("error".to_string(), Value::String(format!("Session error: {}", e)));
                         Ok(Value::Table(result))
@@ -88,7 +88,7 @@ impl SshModule {
                 }
             }
             Err(e) => {
-                let mut result = HashMap::new();
+                let mut result = Table::new();
                 result.insert("connected".to_string(), Value::Boolean(false));
                 result.insert("error".to_string(), Value::String(format!("Connection failed: {}", e)));
                 Ok(Value::Table(result))
@@ -171,7 +171,7 @@ impl SshModule {
                                 let _ = channel.stderr().read_to_string(&mut stderr);
                                 let _ = channel.wait_close();
                                 let exit_code = channel.exit_status().unwrap_or(-1);
-                                let mut result = HashMap::new();
+                                let mut result = Table::new();
                                 result.insert("stdout".to_string(), Value::String(stdout));
                                 result.insert("stderr".to_string(), Value::String(stderr));
                                 result.insert("exit_code".to_string(), Value::Number(exit_code as f64));
@@ -215,13 +215,13 @@ impl SshModule {
             location: SourceLocation::new(0, 0),
         })
     }
-    fn get_string(map: &HashMap<String, Value>, key: &str, default: &str) -> String {
+    fn get_string(map: &Table, key: &str, default: &str) -> String {
         match map.get(key) {
             Some(Value::String(s)) => s.clone(),
             _ => default.to_string(),
         }
     }
-    fn get_number(map: &HashMap<String, Value>, key: &str, default: f64) -> f64 {
+    fn get_number(map: &Table, key: &str, default: f64) -> f64 {
         match map.get(key) {
             Some(Value::Number(n)) => *n,
             _ => default,
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::process::{Command, Stdio};
 pub struct SubprocessModule;
 impl SubprocessModule {
@@ -21,13 +21,13 @@ impl SubprocessModule {
         if let Some(Value::String(cmd)) = args.get(0) {
             let parts: Vec<&str> = cmd.split_whitespace().collect();
             if parts.is_empty() {
-                return Ok(Value::Table(HashMap::new()));
+                return Ok(Value::Table(Table::new()));
             }
             let program = parts[0];
             let cmd_args = &parts[1..];
             match Command::new(program).args(cmd_args).output() {
                 Ok(output) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("stdout".to_string(), Value::String(String::from_utf8_lossy(&output.stdout).to_string()));
                     result.insert("stderr".to_string(), Value::String(String::from_utf8_lossy(&output.stderr).to_string()));
                     result.insert("code".to_string(), Value::Number(output.status.code().unwrap_or(-1) as f64));
@@ -35,7 +35,7 @@ impl SubprocessModule {
                     Ok(Value::Table(result))
                 }
                 Err(e) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("error".to_string(), Value::String(e.to_string()));
                     result.insert("success".to_string(), Value::Boolean(false));
                     Ok(Value::Table(result))
@@ -43,18 +43,18 @@ impl SubprocessModule {
             }
         } else if let Some(Value::Array(arr)) = args.get(0) {
             if arr.is_empty() {
-                return Ok(Value::Table(HashMap::new()));
+                return Ok(Value::Table(Table::new()));
             }
             let program = match &arr[0] {
                 Value::String(s) => s.clone(),
-                _ => return Ok(Value::Table(HashMap::new())),
+                _ => return Ok(Value::Table(Table::new())),
             };
             let cmd_args: Vec<String> = arr[1..].iter().filter_map(|v| {
                 if let Value::String(s) = v { Some(s.clone()) } else { None }
             }).collect();
             match Command::new(&program).args(&cmd_args).output() {
                 Ok(output) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("stdout".to_string(), Value::String(String::from_utf8_lossy(&output.stdout).to_string()));
                     result.insert("stderr".to_string(), Value::String(String::from_utf8_lossy(&output.stderr).to_string()));
                     result.insert("code".to_string(), Value::Number(output.status.code().unwrap_or(-1) as f64));
@@ -62,7 +62,7 @@ impl SubprocessModule {
                     Ok(Value::Table(result))
                 }
                 Err(e) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("error".to_string(), Value::String(e.to_string()));
                     result.insert("success".to_string(), Value::Boolean(false));
                     Ok(Value::Table(result))
@@ -86,7 +86,7 @@ impl SubprocessModule {
                 .spawn()
             {
                 Ok(child) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("pid".to_string(), Value::Number(child.id() as f64));
                     result.insert("spawned".to_string(), Value::Boolean(true));
                     Ok(Value::Table(result))
@@ -105,7 +105,7 @@ impl SubprocessModule {
             let output = Command::new("sh").args(["-c", cmd]).output();
             match output {
                 Ok(out) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("stdout".to_string(), Value::String(String::from_utf8_lossy(&out.stdout).to_string()));
                     result.insert("stderr".to_string(), Value::String(String::from_utf8_lossy(&out.stderr).to_string()));
                     result.insert("code".to_string(), Value::Number(out.status.code().unwrap_or(-1) as f64));
@@ -113,14 +113,14 @@ impl SubprocessModule {
                     Ok(Value::Table(result))
                 }
                 Err(e) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("error".to_string(), Value::String(e.to_string()));
                     result.insert("success".to_string(), Value::Boolean(false));
                     Ok(Value::Table(result))
                 }
             }
         } else {
-            Ok(Value::Table(HashMap::new()))
+            Ok(Value::Table(Table::new()))
         }
     }
     fn output(args: &[Value]) -> MintasResult<Value> {
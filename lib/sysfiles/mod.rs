@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::fs;
 use std::path::Path;
 pub struct SysfilesModule;
@@ -153,17 +153,17 @@ impl SysfilesModule {
         if let Some(Value::String(path)) = args.get(0) {
             match fs::metadata(path) {
                 Ok(meta) => {
-                    let mut result = HashMap::new();
+                    let mut result = Table::new();
                     result.insert("size".to_string(), Value::Number(meta.len() as f64));
                     result.insert("is_file".to_string(), Value::Boolean(meta.is_file()));
                     result.insert("is_dir".to_string(), Value::Boolean(meta.is_dir()));
                     result.insert("readonly".to_string(), Value::Boolean(meta.permissions().readonly()));
                     Ok(Value::Table(result))
                 }
-                Err(_) => Ok(Value::Table(HashMap::new())),
+                Err(_) => Ok(Value::Table(Table::new())),
             }
         } else {
-            Ok(Value::Table(HashMap::new()))
+            Ok(Value::Table(Table::new()))
         }
     }
     fn list_dir(args: &[Value]) -> MintasResult<Value> {
@@ -1,5 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
 use std::collections::HashMap;
 use std::sync::Mutex;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
@@ -42,7 +43,7 @@ impl TimerModule {
         };
         if let Some(start) = TIMERS.lock().unwrap().remove(&name) {
             let elapsed = start.elapsed();
-            let mut result = HashMap::new();
+            let mut result = Table::new();
             result.insert("name".to_string(), Value::String(name));
             result.insert("ms".to_string(), Value::Number(elapsed.as_millis() as f64));
             result.insert("us".to_string(), Value::Number(elapsed.as_micros() as f64));
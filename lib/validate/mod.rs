@@ -1,5 +1,7 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
+use super::dew_module::value_to_string;
 pub struct ValidateModule;
 impl ValidateModule {
     pub fn call_function(name: &str, args: &[Value]) -> MintasResult<Value> {
@@ -210,4 +212,191 @@ impl ValidateModule {
             Ok(Value::Boolean(false))
         }
     }
-}
\ No newline at end of file
+}
+/// Checks `data` against a table of `required|min:3|email` rule strings, as used by the
+/// `validate(data, rules)` builtin and `dew.validate`. Maps each failing field to the message
+/// from its first failed rule; empty when everything passes.
+pub fn rule_errors(data: &Table, rules: &Table) -> Table {
+    let mut errors = Table::new();
+    for (field, rule) in rules {
+        if let Value::String(rule_str) = rule {
+            let field_value = data.get(field);
+            let rule_parts = split_validation_rules(rule_str);
+            let numeric = rule_parts.iter().any(|p| matches!(p.trim(), "numeric" | "integer"));
+            for part in rule_parts {
+                if let Some(error_msg) = validate_field(&field.to_string(), field_value, part, data, numeric) {
+                    errors.insert(field.clone(), Value::String(error_msg));
+                    break;
+                }
+            }
+        }
+    }
+    errors
+}
+/// Splits a `required|min:3|regex:...` rule string. Everything after `regex:` is
+/// the pattern, so patterns may contain `|`.
+fn split_validation_rules(rule_str: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = rule_str;
+    while !rest.is_empty() {
+        if rest.trim_start().starts_with("regex:") {
+            parts.push(rest);
+            break;
+        }
+        match rest.find('|') {
+            Some(pos) => {
+                parts.push(&rest[..pos]);
+                rest = &rest[pos + 1..];
+            }
+            None => {
+                parts.push(rest);
+                break;
+            }
+        }
+    }
+    parts
+}
+/// Size used by `min`/`max`/`between`: the number itself for numeric fields,
+/// otherwise the character count of a string or the length of an array.
+fn validation_size(value: &Value, numeric: bool) -> Option<(f64, &'static str)> {
+    match value {
+        Value::Number(n) => Some((*n, "")),
+        Value::String(s) if numeric => s.trim().parse::<f64>().ok().map(|n| (n, "")),
+        Value::String(s) => Some((s.chars().count() as f64, " characters")),
+        Value::Array(arr) => Some((arr.len() as f64, " items")),
+        _ => None,
+    }
+}
+fn validate_field(field: &str, value: Option<&Value>, rule: &str, data: &Table, numeric: bool) -> Option<String> {
+    let rule = rule.trim();
+    let (rule_name, rule_param) = if let Some(colon_pos) = rule.find(':') {
+        (&rule[..colon_pos], Some(&rule[colon_pos + 1..]))
+    } else {
+        (rule, None)
+    };
+    if rule_name == "required" {
+        return match value {
+            None | Some(Value::Empty) | Some(Value::Null) => Some("This field is required".to_string()),
+            Some(Value::String(s)) if s.is_empty() => Some("This field is required".to_string()),
+            _ => None,
+        };
+    }
+    // Every other rule only applies when a value was supplied.
+    let value = match value {
+        None | Some(Value::Empty) | Some(Value::Null) => return None,
+        Some(Value::String(s)) if s.is_empty() => return None,
+        Some(v) => v,
+    };
+    let param_number = |p: Option<&str>| p.and_then(|p| p.trim().parse::<f64>().ok());
+    match rule_name {
+        "email" => {
+            if let Value::String(s) = value {
+                if !s.contains('@') || !s.contains('.') {
+                    return Some("Invalid email format".to_string());
+                }
+            }
+            None
+        }
+        "min" => {
+            let min = param_number(rule_param)?;
+            match validation_size(value, numeric) {
+                Some((size, unit)) if size < min => Some(format!("Must be at least {}{}", value_to_string(&Value::Number(min)), unit)),
+                _ => None,
+            }
+        }
+        "max" => {
+            let max = param_number(rule_param)?;
+            match validation_size(value, numeric) {
+                Some((size, unit)) if size > max => Some(format!("Must be at most {}{}", value_to_string(&Value::Number(max)), unit)),
+                _ => None,
+            }
+        }
+        "between" => {
+            let (low, high) = rule_param?.split_once(',')?;
+            let (low, high) = (param_number(Some(low))?, param_number(Some(high))?);
+            match validation_size(value, numeric) {
+                Some((size, unit)) if size < low || size > high => Some(format!(
+                    "Must be between {} and {}{}",
+                    value_to_string(&Value::Number(low)),
+                    value_to_string(&Value::Number(high)),
+                    unit
+                )),
+                _ => None,
+            }
+        }
+        "in" => {
+            let options: Vec<&str> = rule_param.unwrap_or("").split(',').map(|o| o.trim()).collect();
+            let text = value_to_string(value);
+            if options.contains(&text.as_str()) {
+                None
+            } else {
+                Some(format!("Must be one of: {}", options.join(", ")))
+            }
+        }
+        "numeric" => match value {
+            Value::Number(_) => None,
+            Value::String(s) if s.trim().parse::<f64>().is_ok() => None,
+            _ => Some("Must be a number".to_string()),
+        },
+        "integer" => match value {
+            Value::Number(n) if n.fract() == 0.0 => None,
+            Value::String(s) if s.trim().parse::<i64>().is_ok() => None,
+            _ => Some("Must be an integer".to_string()),
+        },
+        "alpha" => {
+            if let Value::String(s) = value {
+                if !s.chars().all(|c| c.is_alphabetic()) {
+                    return Some("Must contain only letters".to_string());
+                }
+            }
+            None
+        }
+        "alphanumeric" => {
+            if let Value::String(s) = value {
+                if !s.chars().all(|c| c.is_alphanumeric()) {
+                    return Some("Must contain only letters and numbers".to_string());
+                }
+            }
+            None
+        }
+        "url" => {
+            let valid = match value {
+                Value::String(s) => {
+                    let rest = s.strip_prefix("http://").or_else(|| s.strip_prefix("https://"));
+                    match rest {
+                        Some(rest) => {
+                            let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+                            !host.is_empty() && !host.contains(char::is_whitespace)
+                        }
+                        None => false,
+                    }
+                }
+                _ => false,
+            };
+            if valid { None } else { Some("Invalid URL format".to_string()) }
+        }
+        "confirmed" => {
+            let confirmation = data.get(&format!("{}_confirmation", field));
+            match confirmation {
+                Some(other) if value_to_string(other) == value_to_string(value) => None,
+                _ => Some(format!("Does not match {}_confirmation", field)),
+            }
+        }
+        "regex" => {
+            let pattern = rule_param.unwrap_or("");
+            #[cfg(feature = "regex")]
+            {
+                match super::regex_module::cached_regex(pattern) {
+                    Ok(re) if re.is_match(&value_to_string(value)) => None,
+                    Ok(_) => Some(format!("Does not match pattern {}", pattern)),
+                    Err(e) => Some(format!("Invalid regex pattern {}: {}", pattern, e)),
+                }
+            }
+            #[cfg(not(feature = "regex"))]
+            {
+                Some(format!("Cannot check pattern {}: built without regex support", pattern))
+            }
+        }
+        _ => None,
+    }
+}
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use serde_json::Value as JsonValue;
 pub struct WebhookModule;
 impl WebhookModule {
@@ -44,7 +44,7 @@ impl WebhookModule {
         } else {
             None
         };
-        let mut webhook = HashMap::new();
+        let mut webhook = Table::new();
         webhook.insert("url".to_string(), Value::String(url));
         webhook.insert("active".to_string(), Value::Boolean(true));
         webhook.insert("created_at".to_string(), Value::Number(
@@ -78,7 +78,7 @@ impl WebhookModule {
             Value::Table(t) => {
                 let mut json_obj = serde_json::Map::new();
                 for (k, v) in t {
-                    json_obj.insert(k.clone(), Self::value_to_json(v));
+                    json_obj.insert(k.to_string(), Self::value_to_json(v));
                 }
                 JsonValue::Object(json_obj)
             }
@@ -90,7 +90,7 @@ impl WebhookModule {
         };
         println!("[Webhook] Sending to: {}", url);
         println!("[Webhook] Payload: {}", payload);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("status".to_string(), Value::Number(200.0));
         result.insert("success".to_string(), Value::Boolean(true));
         result.insert("url".to_string(), Value::String(url));
@@ -210,7 +210,7 @@ impl WebhookModule {
                 std::thread::sleep(std::time::Duration::from_millis(1000 * attempt as u64));
             }
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("success".to_string(), Value::Boolean(false));
         result.insert("error".to_string(), Value::String("Max retries exceeded".to_string()));
         Ok(Value::Table(result))
@@ -224,7 +224,7 @@ impl WebhookModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("queued".to_string(), Value::Boolean(true));
         result.insert("id".to_string(), Value::String(format!("wh_{}", 
             std::time::SystemTime::now()
@@ -245,13 +245,14 @@ impl WebhookModule {
     fn value_to_json(value: &Value) -> JsonValue {
         match value {
             Value::Number(n) => JsonValue::Number(serde_json::Number::from_f64(*n).unwrap_or(serde_json::Number::from(0))),
+            Value::Int(n) => JsonValue::Number(serde_json::Number::from(*n)),
             Value::String(s) => JsonValue::String(s.clone()),
             Value::Boolean(b) => JsonValue::Bool(*b),
             Value::Array(arr) => JsonValue::Array(arr.iter().map(Self::value_to_json).collect()),
             Value::Table(t) => {
                 let mut obj = serde_json::Map::new();
                 for (k, v) in t {
-                    obj.insert(k.clone(), Self::value_to_json(v));
+                    obj.insert(k.to_string(), Self::value_to_json(v));
                 }
                 JsonValue::Object(obj)
             }
@@ -265,13 +266,13 @@ impl WebhookModule {
             JsonValue::Bool(b) => Value::Boolean(*b),
             JsonValue::Array(arr) => Value::Array(arr.iter().map(Self::json_to_value).collect()),
             JsonValue::Object(obj) => {
-                let mut table = HashMap::new();
+                let mut table = Table::new();
                 for (k, v) in obj {
                     table.insert(k.clone(), Self::json_to_value(v));
                 }
                 Value::Table(table)
             }
-            JsonValue::Null => Value::Empty,
+            JsonValue::Null => Value::Null,
         }
     }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
-use std::collections::HashMap;
+use crate::table::Table;
 use std::sync::atomic::{AtomicU64, Ordering};
 pub struct WorkerModule;
 static WORKER_COUNTER: AtomicU64 = AtomicU64::new(1);
@@ -32,7 +32,7 @@ impl WorkerModule {
         } else {
             format!("worker_{}", WORKER_COUNTER.fetch_add(1, Ordering::SeqCst))
         };
-        let mut worker = HashMap::new();
+        let mut worker = Table::new();
         worker.insert("id".to_string(), Value::String(name.clone()));
         worker.insert("status".to_string(), Value::String("running".to_string()));
         worker.insert("created_at".to_string(), Value::Number(
@@ -77,7 +77,7 @@ impl WorkerModule {
                 location: SourceLocation::new(0, 0),
             });
         }
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("status".to_string(), Value::String("running".to_string()));
         result.insert("tasks_completed".to_string(), Value::Number(0.0));
         result.insert("uptime".to_string(), Value::Number(0.0));
@@ -117,7 +117,7 @@ impl WorkerModule {
         } else {
             4
         };
-        let mut pool = HashMap::new();
+        let mut pool = Table::new();
         pool.insert("size".to_string(), Value::Number(size as f64));
         pool.insert("active".to_string(), Value::Number(0.0));
         pool.insert("idle".to_string(), Value::Number(size as f64));
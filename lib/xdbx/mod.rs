@@ -5,6 +5,7 @@
 
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::Value;
+use crate::table::Table;
 use std::collections::HashMap;
 use std::io::{self, Write, BufRead};
 use std::process::{Command, Stdio};
@@ -210,7 +211,7 @@ impl XdbxModule {
                     let status = if bp.enabled { "\x1b[31m●\x1b[0m" } else { "\x1b[90m○\x1b[0m" };
                     println!("  {} [{}] {}:{} (hits: {})", 
                         status, i, bp.file, bp.line, bp.hit_count);
-                    let mut bp_info = HashMap::new();
+                    let mut bp_info = Table::new();
                     bp_info.insert("file".to_string(), Value::String(bp.file.clone()));
                     bp_info.insert("line".to_string(), Value::Number(bp.line as f64));
                     bp_info.insert("enabled".to_string(), Value::Boolean(bp.enabled));
@@ -297,7 +298,7 @@ impl XdbxModule {
         Ok(Value::Empty)
     }
     fn locals(_args: &[Value]) -> MintasResult<Value> {
-        let mut locals = HashMap::new();
+        let mut locals = Table::new();
         unsafe {
             if let Some(ref session) = DEBUG_SESSION {
                 println!("\n\x1b[1mLocal Variables:\x1b[0m");
@@ -312,7 +313,7 @@ impl XdbxModule {
     fn globals(_args: &[Value]) -> MintasResult<Value> {
         println!("\n\x1b[1mGlobal Variables:\x1b[0m");
         println!("  (Global inspection not yet implemented)");
-        Ok(Value::Table(HashMap::new()))
+        Ok(Value::Table(Table::new()))
     }
     fn call_stack(_args: &[Value]) -> MintasResult<Value> {
         let mut stack = Vec::new();
@@ -466,7 +467,7 @@ say "Hello, World!"
             }
         }
         println!("\n\x1b[1mResults:\x1b[0m {} passed, {} failed", passed, failed);
-        let mut result = HashMap::new();
+        let mut result = Table::new();
         result.insert("passed".to_string(), Value::Number(passed as f64));
         result.insert("failed".to_string(), Value::Number(failed as f64));
         Ok(Value::Table(result))
@@ -600,7 +601,7 @@ say "Hello, World!"
             println!("\n\x1b[1mConfiguration:\x1b[0m");
             println!("  build.optimize = true");
             println!("  build.target = native");
-            return Ok(Value::Table(HashMap::new()));
+            return Ok(Value::Table(Table::new()));
         }
         if let Some(val) = value {
             println!("Set {} = {:?}", key, val);
@@ -1,5 +1,6 @@
-use crate::errors::MintasResult;
-use crate::parser::Expr;
+use crate::builtins;
+use crate::errors::{MintasError, MintasResult};
+use crate::parser::{ClassMember, Expr};
 use std::collections::HashMap;
 
 // SECURITY THREAT DETECTION LEVELS
@@ -25,6 +26,7 @@ pub struct SecurityThreat {
 pub struct CodeAnalyzer {
     scopes: Vec<HashMap<String, VariableInfo>>,
     functions: HashMap<String, FunctionInfo>,
+    ambiguous_arity: std::collections::HashSet<String>,
     warnings: Vec<String>,
     // SECURITY SUPERPOWERS
     security_threats: Vec<SecurityThreat>,
@@ -49,11 +51,18 @@ struct FunctionInfo {
     param_count: usize,
 }
 
+impl Default for CodeAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CodeAnalyzer {
     pub fn new() -> Self {
         Self {
             scopes: vec![HashMap::new()], // Start with global scope
             functions: HashMap::new(),
+            ambiguous_arity: std::collections::HashSet::new(),
             warnings: Vec::new(),
             // SECURITY SUPERPOWERS INITIALIZATION
             security_threats: Vec::new(),
@@ -116,12 +125,32 @@ impl CodeAnalyzer {
     fn collect_functions(&mut self, expr: &Expr, line_num: usize) {
         match expr {
             Expr::Function { name, params, .. } => {
+                // Redefinitions with a different arity can't be checked statically
+                if self.functions.get(name).is_some_and(|f| f.param_count != params.len()) {
+                    self.ambiguous_arity.insert(name.clone());
+                }
                 self.functions.insert(name.clone(), FunctionInfo {
                     defined_at: line_num,
                     has_return: false,
                     param_count: params.len(),
                 });
             }
+            // `Point(x, y)` constructs an instance, taking init's parameters
+            Expr::Class { name, members, .. } => {
+                let param_count = members.iter().find_map(|member| match member {
+                    ClassMember::Method { name, params, .. } if name == "init" => Some(params.len()),
+                    _ => None,
+                });
+                // An inherited init can't be seen from here
+                if param_count.is_none() {
+                    self.ambiguous_arity.insert(name.clone());
+                }
+                self.functions.insert(name.clone(), FunctionInfo {
+                    defined_at: line_num,
+                    has_return: true,
+                    param_count: param_count.unwrap_or(0),
+                });
+            }
             _ => {}
         }
     }
@@ -152,6 +181,10 @@ impl CodeAnalyzer {
         }
     }
 
+    fn is_shadowed(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(name))
+    }
+
     fn use_variable(&mut self, name: &str) -> bool {
         // Check scopes from innermost to outermost
         for scope in self.scopes.iter_mut().rev() {
@@ -196,7 +229,20 @@ impl CodeAnalyzer {
                      self.warnings.push(format!("Line {}: Reference to undefined variable '{}' in compound assignment.", line_num + 1, name));
                 }
             }
-            Expr::Call { name, args } => {
+            Expr::Call { name, args, location } => {
+                if let Some(info) = self.functions.get(name) {
+                    if info.param_count != args.len()
+                        && !self.ambiguous_arity.contains(name)
+                        && !self.is_shadowed(name)
+                    {
+                        return Err(MintasError::InvalidArgumentCount {
+                            function: name.clone(),
+                            expected: info.param_count,
+                            got: args.len(),
+                            location: location.clone(),
+                        });
+                    }
+                }
                 if !self.functions.contains_key(name) && !self.is_builtin_function(name) {
                     // Check if it's a variable holding a function (lambda or assigned function)
                      if !self.use_variable(name) {
@@ -227,11 +273,12 @@ impl CodeAnalyzer {
                     self.exit_scope();
                 }
             }
-            Expr::WhileLoop { condition, body } => {
+            Expr::WhileLoop { condition, body, else_branch } => {
                 self.analyze_expression(condition, line_num)?;
                 self.enter_scope();
                 self.analyze_block(body, line_num)?;
                 self.exit_scope();
+                self.analyze_loop_else(else_branch, line_num)?;
             }
             Expr::ForLoop { var, start, end, body } => {
                 self.analyze_expression(start, line_num)?;
@@ -241,13 +288,28 @@ impl CodeAnalyzer {
                 self.analyze_block(body, line_num)?;
                 self.exit_scope();
             }
-            Expr::ForInLoop { var, iterable, body } => {
+            Expr::ForInLoop { vars, iterable, body, else_branch } => {
                 self.analyze_expression(iterable, line_num)?;
                 self.enter_scope();
+                for var in vars {
+                    self.define_variable(var.clone(), line_num, false);
+                }
+                self.analyze_block(body, line_num)?;
+                self.exit_scope();
+                self.analyze_loop_else(else_branch, line_num)?;
+            }
+            Expr::SmartLoop { var, count, body } => {
+                self.analyze_expression(count, line_num)?;
+                self.enter_scope();
                 self.define_variable(var.clone(), line_num, false);
                 self.analyze_block(body, line_num)?;
                 self.exit_scope();
             }
+            Expr::SmartCondition { condition, then_branch, else_branch } => {
+                self.analyze_expression(condition, line_num)?;
+                self.analyze_expression(then_branch, line_num)?;
+                self.analyze_expression(else_branch, line_num)?;
+            }
             Expr::Function { params, body, .. } => {
                 self.enter_scope();
                 for param in params {
@@ -372,6 +434,15 @@ impl CodeAnalyzer {
         Ok(())
     }
 
+    fn analyze_loop_else(&mut self, else_branch: &Option<Vec<Expr>>, line_num: usize) -> MintasResult<()> {
+        if let Some(branch) = else_branch {
+            self.enter_scope();
+            self.analyze_block(branch, line_num)?;
+            self.exit_scope();
+        }
+        Ok(())
+    }
+
     fn check_for_issues(&mut self) {
         // Only check global scope variables here, as local ones are checked on exit_scope
         if let Some(global_scope) = self.scopes.first() {
@@ -423,12 +494,7 @@ impl CodeAnalyzer {
                 false
             }
         } else {
-            matches!(name, "say" | "ask" | "read" | "write" | "append" | "exists" |
-                     "len" | "upper" | "lower" | "trim" | "push" | "pop" | "insert" |
-                     "remove" | "sort" | "reverse" | "contains" | "find" | "replace" |
-                     "split" | "join" | "keys" | "values" | "has" | "merge" |
-                     "typeof" | "tostring" | "tonumber" | "assert" | "test" |
-                     "cond" | "follow")
+            builtins::global(name).is_some()
         }
     }
 
@@ -553,7 +619,7 @@ impl CodeAnalyzer {
                     self.check_statement_logic(elem, line_num);
                 }
             }
-            Expr::WhileLoop { condition, body } => {
+            Expr::WhileLoop { condition, body, .. } => {
                 // Check for potential infinite loops
                 if let Expr::Boolean(true) = &**condition {
                     if !self.has_exit_in_block(body) {
@@ -578,11 +644,11 @@ impl CodeAnalyzer {
 
     fn check_statement_security(&mut self, expr: &Expr, line_num: usize) {
         match expr {
-            Expr::Call { name, args } => {
+            Expr::Call { name, args, .. } => {
                 // Check for potentially unsafe operations
                 match name.as_str() {
                     "write" | "append" => {
-                        if let Some(Expr::String(path)) = args.get(0) {
+                        if let Some(Expr::String(path) | Expr::RawString(path)) = args.first() {
                             if path.contains("..") || path.starts_with('/') {
                                 self.warnings.push(format!("Line {}: Potentially unsafe file path: '{}'", line_num + 1, path));
                             }
@@ -619,7 +685,7 @@ impl CodeAnalyzer {
     fn infer_expression_type(&self, expr: &Expr) -> &'static str {
         match expr {
             Expr::Number(_) => "number",
-            Expr::String(_) => "string",
+            Expr::String(_) | Expr::RawString(_) => "string",
             Expr::Boolean(_) => "boolean",
             Expr::Maybe => "maybe",
             Expr::Empty => "empty",
@@ -707,7 +773,7 @@ impl CodeAnalyzer {
             }
             
             // Detect string bombs
-            Expr::String(s) => {
+            Expr::String(s) | Expr::RawString(s) => {
                 if s.len() > 100000 {
                     self.add_security_threat(
                         ThreatLevel::Dangerous,
@@ -726,10 +792,10 @@ impl CodeAnalyzer {
     /// SUPERPOWER: Analyze attack patterns
     fn analyze_attack_patterns(&mut self, expr: &Expr, line_num: usize) -> MintasResult<()> {
         match expr {
-            Expr::Call { name, args } => {
+            Expr::Call { name, args, .. } => {
                 // Detect potential injection patterns
                 for arg in args {
-                    if let Expr::String(s) = arg {
+                    if let Expr::String(s) | Expr::RawString(s) = arg {
                         if self.is_injection_pattern(s) {
                             self.add_security_threat(
                                 ThreatLevel::Critical,
@@ -831,7 +897,7 @@ impl CodeAnalyzer {
     fn contains_memory_allocation(&self, body: &[Expr]) -> bool {
         for expr in body {
             match expr {
-                Expr::Array(_) | Expr::String(_) => return true,
+                Expr::Array(_) | Expr::String(_) | Expr::RawString(_) => return true,
                 Expr::Call { name, .. } if name == "push" => return true,
                 _ => {}
             }
@@ -924,4 +990,36 @@ impl CodeAnalyzer {
     pub fn is_secure(&self) -> bool {
         !self.security_threats.iter().any(|t| matches!(t.level, ThreatLevel::Critical | ThreatLevel::Dangerous))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn analyze_code(code: &str) -> MintasResult<()> {
+        let statements = Parser::new(Lexer::new(code).tokenize()?).parse()?;
+        CodeAnalyzer::new().analyze(&statements)
+    }
+
+    #[test]
+    fn test_wrong_arity_reported_with_line() {
+        let code = "func add(a, b):\n    return a + b\nend\nx = 1\nsay(add(x))\n";
+        match analyze_code(code) {
+            Err(MintasError::InvalidArgumentCount { function, expected, got, location }) => {
+                assert_eq!(function, "add");
+                assert_eq!((expected, got), (2, 1));
+                assert_eq!(location.line, 5);
+            }
+            other => panic!("expected arity error, got {:?}", other),
+        }
+        // Inside a function body the call's own line is reported, not the definition's
+        let nested = "func add(a, b):\n    return a + b\nend\nfunc main():\n    x = 1\n\n    return add(x)\nend\n";
+        match analyze_code(nested) {
+            Err(MintasError::InvalidArgumentCount { location, .. }) => assert_eq!((location.line, location.column), (7, 12)),
+            other => panic!("expected arity error, got {:?}", other),
+        }
+        assert!(analyze_code("func add(a, b):\n    return a + b\nend\nsay(add(1, 2))\n").is_ok());
+        assert!(analyze_code("x = max(1, 2, 3)\nundefined_fn(x)\n").is_ok());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::evaluator::Evaluator;
+use crate::parser::{ClassMember, Expr};
+use std::collections::BTreeMap;
+
+/// Node counts by kind for `mintas --ast-stats`
+#[derive(Default)]
+pub struct AstStats {
+    pub kinds: BTreeMap<&'static str, usize>,
+    pub total: usize,
+    /// Top-level statements are depth 1
+    pub max_depth: usize,
+}
+
+impl AstStats {
+    pub fn collect(statements: &[Expr]) -> Self {
+        let mut stats = AstStats::default();
+        for stmt in statements {
+            stats.visit(stmt, 1);
+        }
+        stats
+    }
+
+    fn visit(&mut self, expr: &Expr, depth: usize) {
+        *self.kinds.entry(Evaluator::expr_kind(expr)).or_insert(0) += 1;
+        self.total += 1;
+        self.max_depth = self.max_depth.max(depth);
+        for child in children(expr) {
+            self.visit(child, depth + 1);
+        }
+    }
+
+    /// Most common kinds first, each with a bar scaled to the largest count
+    pub fn render(&self) -> String {
+        let mut rows: Vec<(&&str, &usize)> = self.kinds.iter().collect();
+        rows.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let width = rows.iter().map(|(kind, _)| kind.len()).max().unwrap_or(0);
+        let largest = rows.first().map_or(1, |(_, count)| **count);
+        let mut out = String::new();
+        for (kind, count) in rows {
+            let bar = "#".repeat((count * 40).div_ceil(largest));
+            out.push_str(&format!("  {:<width$}  {:>5}  {}\n", kind, count, bar, width = width));
+        }
+        out.push_str(&format!("Total nodes: {}\n", self.total));
+        out.push_str(&format!("Max depth: {}\n", self.max_depth));
+        out
+    }
+}
+
+/// Direct sub-expressions of `expr` in source order. Every variant is listed so a new one
+/// can't be skipped silently.
+pub fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Number(_) | Expr::Int(_) | Expr::String(_) | Expr::RawString(_) | Expr::Boolean(_) | Expr::Maybe | Expr::Empty
+        | Expr::Variable(_) | Expr::Global { .. } | Expr::Exit | Expr::Proceed | Expr::This | Expr::Super
+        | Expr::Include { .. } | Expr::Getback => Vec::new(),
+        Expr::Array(items) => items.iter().collect(),
+        Expr::Table(entries) => entries.iter().map(|(_, value)| value).collect(),
+        Expr::SuperSet(inner) | Expr::Spread(inner) => vec![inner.as_ref()],
+        Expr::BinaryOp { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+        Expr::UnaryOp { expr, .. } => vec![expr.as_ref()],
+        Expr::Assign { value, .. } | Expr::CompoundAssign { value, .. } => vec![value.as_ref()],
+        Expr::MultiAssign { values, .. } => values.iter().collect(),
+        Expr::Call { args, .. } | Expr::New { args, .. } => args.iter().collect(),
+        Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
+            let mut nodes = vec![condition.as_ref()];
+            nodes.extend(then_branch);
+            for (cond, body) in else_if_branches {
+                nodes.push(cond);
+                nodes.extend(body);
+            }
+            nodes.extend(else_branch.iter().flatten());
+            nodes
+        }
+        Expr::WhileLoop { condition, body, else_branch } => {
+            let mut nodes = vec![condition.as_ref()];
+            nodes.extend(body);
+            nodes.extend(else_branch.iter().flatten());
+            nodes
+        }
+        Expr::ForLoop { start, end, body, .. } => {
+            let mut nodes = vec![start.as_ref(), end.as_ref()];
+            nodes.extend(body);
+            nodes
+        }
+        Expr::ForInLoop { iterable, body, else_branch, .. } => {
+            let mut nodes = vec![iterable.as_ref()];
+            nodes.extend(body);
+            nodes.extend(else_branch.iter().flatten());
+            nodes
+        }
+        Expr::MethodCall { object, args, .. } => {
+            let mut nodes = vec![object.as_ref()];
+            nodes.extend(args);
+            nodes
+        }
+        Expr::Index { object, index } => vec![object.as_ref(), index.as_ref()],
+        Expr::Ternary { condition, then_expr, else_expr } => vec![condition.as_ref(), then_expr.as_ref(), else_expr.as_ref()],
+        Expr::SmartCondition { condition, then_branch, else_branch } => {
+            vec![condition.as_ref(), then_branch.as_ref(), else_branch.as_ref()]
+        }
+        Expr::Function { body, .. } | Expr::Task { body, .. } => body.iter().collect(),
+        Expr::Return { value } => value.iter().map(|v| v.as_ref()).collect(),
+        Expr::Class { members, .. } => members
+            .iter()
+            .flat_map(|member| match member {
+                ClassMember::Property { initial_value, .. } => initial_value.iter().collect::<Vec<_>>(),
+                ClassMember::Method { body, .. } => body.iter().collect(),
+            })
+            .collect(),
+        Expr::Property { object, .. } => vec![object.as_ref()],
+        Expr::PropertyAssign { object, value, .. } => vec![object.as_ref(), value.as_ref()],
+        Expr::TryCatch { try_block, catch_block, .. } => try_block.iter().chain(catch_block).collect(),
+        Expr::Cond { condition } | Expr::Follow { condition, .. } => vec![condition.as_ref()],
+        Expr::Export { declaration, .. } => declaration.iter().map(|d| d.as_ref()).collect(),
+        Expr::Switch { expression, cases, default_case } => {
+            let mut nodes = vec![expression.as_ref()];
+            for (value, body) in cases {
+                nodes.push(value);
+                nodes.extend(body);
+            }
+            nodes.extend(default_case.iter().flatten());
+            nodes
+        }
+        Expr::TypeMatch { subject, arms, default_case } => {
+            let mut nodes = vec![subject.as_ref()];
+            nodes.extend(arms.iter().flat_map(|(_, body)| body));
+            nodes.extend(default_case.iter().flatten());
+            nodes
+        }
+        Expr::SmartLoop { count, body, .. } => {
+            let mut nodes = vec![count.as_ref()];
+            nodes.extend(body);
+            nodes
+        }
+        Expr::DewRoute { server, body, timeout, .. } => {
+            let mut nodes = vec![server.as_ref()];
+            nodes.extend(timeout.as_deref());
+            nodes.extend(body);
+            nodes
+        }
+        Expr::DewBefore { server, body }
+        | Expr::DewAfter { server, body }
+        | Expr::DewCatch { server, body, .. }
+        | Expr::DewGroup { server, body, .. } => {
+            let mut nodes = vec![server.as_ref()];
+            nodes.extend(body);
+            nodes
+        }
+        Expr::DewRouteValidated { server, validation_rules, body, timeout, .. } => {
+            let mut nodes = vec![server.as_ref()];
+            nodes.extend(timeout.as_deref());
+            nodes.push(validation_rules.as_ref());
+            nodes.extend(body);
+            nodes
+        }
+        Expr::DewServe { server, port, host } => {
+            let mut nodes = vec![server.as_ref(), port.as_ref()];
+            nodes.extend(host.as_deref());
+            nodes
+        }
+        Expr::DewReturn { body, status, data, .. } => {
+            let mut nodes = vec![body.as_ref()];
+            nodes.extend(status.as_deref());
+            nodes.extend(data.as_deref());
+            nodes
+        }
+        Expr::DewSession { server, config } => {
+            let mut nodes = vec![server.as_ref()];
+            nodes.extend(config.as_deref());
+            nodes
+        }
+        Expr::DewUse { server, .. }
+        | Expr::DewStatic { server, .. }
+        | Expr::DewConfig { server, .. }
+        | Expr::DewDatabase { server, .. }
+        | Expr::DewRateLimit { server, .. } => vec![server.as_ref()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_counts_kinds_total_and_depth() {
+        let source = "func f(n):\n    if (n > 1):\n        return n * f(n - 1)\n    end\n    return 1\nend\nx = f(5) + 2\n";
+        let statements = Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+        let stats = AstStats::collect(&statements);
+        assert_eq!(stats.kinds.get("Function"), Some(&1));
+        assert_eq!(stats.kinds.get("Call"), Some(&2));
+        assert_eq!(stats.kinds.get("Return"), Some(&2));
+        assert_eq!(stats.kinds.get("BinaryOp"), Some(&4));
+        assert_eq!(stats.total, stats.kinds.values().sum::<usize>());
+        // Function > IfExpr > Return > BinaryOp > Call > BinaryOp > Variable
+        assert_eq!(stats.max_depth, 7);
+        let report = stats.render();
+        assert!(report.starts_with("  Number"), "{}", report);
+        assert!(report.ends_with(&format!("Total nodes: {}\nMax depth: 7\n", stats.total)));
+    }
+}
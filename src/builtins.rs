@@ -0,0 +1,451 @@
+use crate::errors::{MintasError, MintasResult, SourceLocation};
+
+/// Where a builtin is reached from: a plain call, a method on a value, or `dew.`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Core,
+    Math,
+    String,
+    Array,
+    Table,
+    Builder,
+    Request,
+    Dew,
+}
+
+impl Category {
+    pub const ALL: [Category; 8] = [
+        Category::Core,
+        Category::Math,
+        Category::String,
+        Category::Array,
+        Category::Table,
+        Category::Builder,
+        Category::Request,
+        Category::Dew,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Category::Core => "Core I/O and runtime",
+            Category::Math => "Math",
+            Category::String => "String methods",
+            Category::Array => "Array methods",
+            Category::Table => "Table methods",
+            Category::Builder => "String builder methods",
+            Category::Request => "Request methods (dew route handlers)",
+            Category::Dew => "dew.*",
+        }
+    }
+}
+
+/// One builtin. `params` is the source of truth for its arity: `[name]` is optional and
+/// `name...` takes any number of values.
+pub struct Builtin {
+    pub name: &'static str,
+    pub category: Category,
+    pub params: &'static str,
+}
+
+impl Builtin {
+    /// Fewest and most arguments the call accepts; `None` for no upper bound
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let mut min = 0;
+        let mut max = Some(0);
+        for param in self.params.split(", ").filter(|p| !p.is_empty()) {
+            if param.ends_with("...") {
+                max = None;
+            } else {
+                if !param.starts_with('[') {
+                    min += 1;
+                }
+                max = max.map(|m| m + 1);
+            }
+        }
+        (min, max)
+    }
+
+    pub fn check_arity(&self, got: usize) -> MintasResult<()> {
+        let (min, max) = self.arity();
+        let expected = if got < min {
+            min
+        } else {
+            match max {
+                Some(max) if got > max => max,
+                _ => return Ok(()),
+            }
+        };
+        Err(MintasError::InvalidArgumentCount {
+            function: self.qualified_name(),
+            expected,
+            got,
+            location: SourceLocation::new(0, 0),
+        })
+    }
+
+    /// The name as errors report it: `dew.` functions keep their module prefix
+    pub fn qualified_name(&self) -> String {
+        match self.category {
+            Category::Dew => format!("dew.{}", self.name),
+            _ => self.name.to_string(),
+        }
+    }
+
+    /// How the call is written, e.g. `range([start], end, [step])` or `.split(sep)` for a method
+    pub fn signature(&self) -> String {
+        match self.category {
+            Category::String | Category::Array | Category::Table | Category::Builder | Category::Request => format!(".{}({})", self.name, self.params),
+            _ => format!("{}({})", self.qualified_name(), self.params),
+        }
+    }
+
+    pub fn arity_label(&self) -> String {
+        match self.arity() {
+            (min, None) => format!("{}+", min),
+            (min, Some(max)) if min == max => min.to_string(),
+            (min, Some(max)) => format!("{}-{}", min, max),
+        }
+    }
+}
+
+pub fn lookup(category: Category, name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.category == category && b.name == name)
+}
+
+/// A builtin reachable as a plain call, such as `say(...)` or `sqrt(...)`
+pub fn global(name: &str) -> Option<&'static Builtin> {
+    lookup(Category::Core, name).or_else(|| lookup(Category::Math, name))
+}
+
+/// Builtin names that complete `word`: plain calls, or `dew.` functions once it starts with `dew.`
+pub fn completions(word: &str) -> Vec<String> {
+    let mut names: Vec<String> = match word.strip_prefix("dew.") {
+        Some(prefix) => BUILTINS
+            .iter()
+            .filter(|b| b.category == Category::Dew && b.name.starts_with(prefix))
+            .map(|b| b.qualified_name())
+            .collect(),
+        None => BUILTINS
+            .iter()
+            .filter(|b| matches!(b.category, Category::Core | Category::Math) && b.name.starts_with(word))
+            .map(|b| b.name.to_string())
+            .collect(),
+    };
+    names.sort();
+    names
+}
+
+/// Route definitions are decorators, not calls, so they are listed apart from `dew.*`
+const ROUTE_DECORATORS: &str = "\
+dew route decorators:
+  @app.get(path, [timeout = 250 | \"30s\"]):  ...  end
+  @app.post, @app.put, @app.delete and @app.patch take the same arguments
+  `timeout` (milliseconds or a duration string) overrides serve's timeout for that
+  route; a handler still running when it expires gets 504 Gateway Timeout
+";
+
+/// `mintas --list-builtins`: every builtin with its signature and arity, grouped by category
+pub fn render_list() -> String {
+    let mut out = String::new();
+    for category in Category::ALL {
+        let entries: Vec<&Builtin> = BUILTINS.iter().filter(|b| b.category == category).collect();
+        let width = entries.iter().map(|b| b.signature().chars().count()).max().unwrap_or(0);
+        out.push_str(&format!("{}:\n", category.title()));
+        for builtin in entries {
+            out.push_str(&format!("  {:<width$}  {}\n", builtin.signature(), builtin.arity_label(), width = width));
+        }
+        out.push('\n');
+    }
+    out.push_str(ROUTE_DECORATORS);
+    out
+}
+
+const fn core(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Core, params }
+}
+const fn math(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Math, params }
+}
+const fn string(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::String, params }
+}
+const fn array(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Array, params }
+}
+const fn table(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Table, params }
+}
+const fn builder(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Builder, params }
+}
+const fn request(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Request, params }
+}
+const fn dew(name: &'static str, params: &'static str) -> Builtin {
+    Builtin { name, category: Category::Dew, params }
+}
+
+/// Every builtin the evaluator dispatches. Calls are arity-checked against this table
+/// before they run, so an entry here is what the language actually accepts.
+pub const BUILTINS: &[Builtin] = &[
+    core("say", "value"),
+    core("print", "values..."),
+    core("println", "values..."),
+    core("flush", ""),
+    core("args", ""),
+    core("ask", "prompt"),
+    core("read", "path"),
+    core("write", "path, content"),
+    core("append", "path, content"),
+    core("exists", "path"),
+    core("typeof", "value"),
+    core("random", ""),
+    core("random_int", "low, high"),
+    core("range", "[start], end, [step]"),
+    core("clone", "value"),
+    core("arity", "func"),
+    core("call", "func, args"),
+    core("is_number", "value"),
+    core("is_string", "value"),
+    core("is_array", "value"),
+    core("is_table", "value"),
+    core("toString", "value, [base]"),
+    core("toNumber", "text, [base]"),
+    core("to_string", "value"),
+    core("to_number", "value"),
+    core("format", "template, values..."),
+    core("regex_match", "text, pattern"),
+    core("regex_find", "text, pattern"),
+    core("regex_replace", "text, pattern, replacement"),
+    core("validate", "data, rules"),
+    core("equals", "a, b"),
+    core("table_from_entries", "entries"),
+    core("string_builder", "[initial]"),
+    core("assert", "condition, [message]"),
+    core("test", "name, body"),
+    math("round", "n, [digits]"),
+    math("format_number", "n, [options]"),
+    math("floor", "n"),
+    math("ceil", "n"),
+    math("abs", "n"),
+    math("sqrt", "n"),
+    math("pow", "base, exponent"),
+    math("min", "value, values..."),
+    math("max", "value, values..."),
+    math("idiv", "a, b"),
+    math("mod", "a, b"),
+    math("divmod", "a, b"),
+    math("sum", "array"),
+    math("avg", "array"),
+    math("count", "array, [predicate]"),
+    string("len", ""),
+    string("upper", ""),
+    string("lower", ""),
+    string("trim", ""),
+    string("reverse", ""),
+    string("contains", "sub"),
+    string("startswith", "prefix"),
+    string("endswith", "suffix"),
+    string("find", "sub"),
+    string("replace", "old, new"),
+    string("split", "sep"),
+    string("slice", "start, end"),
+    string("insert", "index, text"),
+    string("remove", "start, end"),
+    string("removeprefix", "prefix"),
+    string("removesuffix", "suffix"),
+    string("addprefix", "prefix"),
+    string("addsuffix", "suffix"),
+    string("from_json", ""),
+    array("len", ""),
+    array("push", "value"),
+    array("append", "value"),
+    array("pop", ""),
+    array("insert", "index, value"),
+    array("remove", "index"),
+    array("clear", ""),
+    array("reverse", ""),
+    array("contains", "value"),
+    array("index", "value"),
+    array("count", "value"),
+    array("slice", "start, end"),
+    array("join", "sep"),
+    array("extend", "other"),
+    array("sort", ""),
+    array("map", "func"),
+    array("filter", "func"),
+    array("reduce", "func, [initial]"),
+    array("to_json", ""),
+    table("len", ""),
+    table("keys", ""),
+    table("values", ""),
+    table("entries", ""),
+    table("has", "key"),
+    table("remove", "key"),
+    table("set", "key, value"),
+    table("merge", "other, [deep]"),
+    table("update", "other, [deep]"),
+    table("to_json", ""),
+    builder("append", "value"),
+    builder("build", ""),
+    builder("len", ""),
+    request("param", "name"),
+    request("param_number", "name"),
+    request("query", "name"),
+    request("query_number", "name"),
+    request("header", "name"),
+    request("json", ""),
+    request("form", ""),
+    request("text", ""),
+    request("body", ""),
+    request("ip", ""),
+    request("validated", ""),
+    dew("main", ""),
+    dew("serve", "[port], [host], [server]"),
+    dew("database", "[url]"),
+    dew("query", "db, sql, [params]"),
+    dew("sql", "template, [params]"),
+    dew("use", "middleware, [server]"),
+    dew("cors", "[origins], [methods], [headers]"),
+    dew("auth", "[type], [secret]"),
+    dew("rate_limit", "[requests], [window]"),
+    dew("compress", ""),
+    dew("logger", "[format], [server]"),
+    dew("static", "[url_path], [dir], [server]"),
+    dew("inview", "template, [data]"),
+    dew("render", "template, [data]"),
+    dew("session", "[key]"),
+    dew("session_set", "key, [value]"),
+    dew("session_get", "[key]"),
+    dew("session_destroy", ""),
+    dew("cookie", "name"),
+    dew("set_cookie", "name, value, [max_age], [path], [http_only]"),
+    dew("upload", "[field]"),
+    dew("save_upload", "file, [dest]"),
+    dew("validate", "data, rules"),
+    dew("websocket", "[path], [server]"),
+    dew("ws_send", "client, message"),
+    dew("ws_broadcast", "message"),
+    dew("ws_send_binary", "client, bytes"),
+    dew("ws_close", "client, [code], [reason]"),
+    dew("ws_on_connect", "[path], [handler]"),
+    dew("ws_on_disconnect", "[path], [handler]"),
+    dew("ws_on_message", "[path], [handler]"),
+    dew("ws_on_error", "[path], [handler]"),
+    dew("ws_join", "room, [client]"),
+    dew("ws_leave", "room, [client]"),
+    dew("ws_room_broadcast", "room, message"),
+    dew("ws_rooms", ""),
+    dew("ws_clients", "[room]"),
+    dew("test_get", "[server], path, [headers]"),
+    dew("test_post", "[server], path, [body], [headers]"),
+    dew("test_put", "[server], path, [body], [headers]"),
+    dew("test_delete", "[server], path, [headers]"),
+    dew("config", "[path]"),
+    dew("dotenv", "[path]"),
+    dew("env", "key, [default]"),
+    dew("env_number", "key, [default]"),
+    dew("env_bool", "key, [default]"),
+    dew("env_required", "key"),
+    dew("job", "name, [delay_ms], [data]"),
+    dew("queue", "name, [item]"),
+    dew("task", "name"),
+    dew("schedule", "cron, [task]"),
+    dew("chunk_upload", "upload_id, index, data, total"),
+    dew("chunk_complete", "upload_id, dest"),
+    dew("protect", "[type]"),
+    dew("csrf_token", ""),
+    dew("csrf_verify", "token"),
+    dew("sanitize", "input, [type]"),
+    dew("text", "body, [status]"),
+    dew("html", "body, [status]"),
+    dew("json", "data, [status], [pretty]"),
+    dew("redirect", "location, [permanent]"),
+    dew("file", "path"),
+    dew("stream", "callback, [content_type], [status]"),
+    dew("webrtc_peer", "[config]"),
+    dew("webrtc_offer", "peer"),
+    dew("webrtc_answer", "offer"),
+    dew("webrtc_datachannel", "peer, [options]"),
+    dew("webrtc_send", "channel, data"),
+    dew("webrtc_on_message", "channel, handler"),
+    dew("webrtc_close", "peer_or_channel"),
+    dew("webrtc_stats", "peer"),
+    dew("webrtc_signaling", "server, [path]"),
+    dew("js_onclick", "[selector], [handler]"),
+    dew("js_onchange", "[selector], [handler]"),
+    dew("js_oninput", "[selector], [handler]"),
+    dew("js_onsubmit", "[selector], [handler]"),
+    dew("js_onfocus", "[selector], [handler]"),
+    dew("js_onblur", "[selector], [handler]"),
+    dew("js_onkeypress", "[selector], [handler]"),
+    dew("js_onkeydown", "[selector], [handler]"),
+    dew("js_onkeyup", "[selector], [handler]"),
+    dew("js_onmouseover", "[selector], [handler]"),
+    dew("js_onmouseout", "[selector], [handler]"),
+    dew("js_onload", "[handler]"),
+    dew("js_query", "selector"),
+    dew("js_query_all", "selector"),
+    dew("js_set_attr", "selector, attr, value"),
+    dew("js_get_attr", "selector, attr"),
+    dew("js_set_class", "selector, class_name"),
+    dew("js_add_class", "selector, class_name"),
+    dew("js_remove_class", "selector, class_name"),
+    dew("js_set_html", "selector, html"),
+    dew("js_set_text", "selector, text"),
+    dew("js_show", "selector"),
+    dew("js_hide", "selector"),
+    dew("js_toggle", "selector"),
+    dew("js_validate_form", "[selector], [rules]"),
+    dew("uuid", ""),
+    dew("hash_password", "password"),
+    dew("verify_password", "password, hash"),
+    dew("sha256", "text"),
+    dew("jwt_sign", "claims, secret, [expires_in]"),
+    dew("jwt_verify", "token, secret"),
+    dew("csv_parse", "text"),
+    dew("csv_stringify", "rows"),
+    dew("redis_get", "url, key"),
+    dew("redis_set", "url, key, value"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arity_comes_from_params() {
+        assert_eq!(global("say").unwrap().arity(), (1, Some(1)));
+        assert_eq!(global("range").unwrap().arity(), (1, Some(3)));
+        assert_eq!(global("format").unwrap().arity(), (1, None));
+        assert_eq!(global("flush").unwrap().arity(), (0, Some(0)));
+        assert_eq!(lookup(Category::Dew, "json").unwrap().arity_label(), "1-3");
+        assert!(global("upper").is_none());
+        assert!(global("range").unwrap().check_arity(4).is_err());
+        assert!(global("min").unwrap().check_arity(0).is_err());
+        assert!(global("min").unwrap().check_arity(9).is_ok());
+        let list = render_list();
+        assert!(list.contains("Core I/O and runtime:\n"));
+        assert!(list.contains("  dew.sql(template, [params])"));
+        assert!(list.contains("  .merge(other, [deep])"));
+        assert!(list.contains("  @app.get(path, [timeout = 250 | \"30s\"]):"));
+    }
+
+    #[test]
+    fn test_completions() {
+        assert_eq!(completions("is_"), ["is_array", "is_number", "is_string", "is_table"]);
+        assert_eq!(completions("dew.ws_r"), ["dew.ws_room_broadcast", "dew.ws_rooms"]);
+        assert!(completions("upp").is_empty());
+    }
+
+    #[test]
+    fn test_no_duplicate_entries() {
+        for (i, a) in BUILTINS.iter().enumerate() {
+            assert!(
+                BUILTINS[..i].iter().all(|b| b.category != a.category || b.name != a.name),
+                "{} is listed twice",
+                a.signature()
+            );
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
+/// Version of the instruction set below. Bump it whenever an instruction or constant kind is
+/// added, removed or changes meaning so runtimes refuse bytecode they would misexecute.
+/// 2: `Constant::Int`.
+pub const BYTECODE_VERSION: u8 = 2;
+
 /// Bytecode instruction set for Mintas
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Instruction {
@@ -67,6 +72,7 @@ pub enum Constant {
     Number(f64),
     String(String),
     Boolean(bool),
+    Int(i64),
 }
 
 /// Compiled bytecode program
@@ -76,6 +82,9 @@ pub struct BytecodeProgram {
     pub constants: Vec<Constant>,
     pub strings: Vec<String>,
     pub functions: HashMap<String, FunctionMetadata>,
+    /// Names listed by `export`; `None` when the program exports nothing, so every global is visible
+    #[serde(default)]
+    pub exports: Option<Vec<String>>,
 }
 
 /// Function metadata
@@ -94,6 +103,7 @@ impl BytecodeProgram {
             constants: Vec::new(),
             strings: Vec::new(),
             functions: HashMap::new(),
+            exports: None,
         }
     }
     
@@ -110,6 +120,7 @@ impl BytecodeProgram {
                 (Constant::Number(a), Constant::Number(b)) if (a - b).abs() < f64::EPSILON => true,
                 (Constant::String(a), Constant::String(b)) if a == b => true,
                 (Constant::Boolean(a), Constant::Boolean(b)) if a == b => true,
+                (Constant::Int(a), Constant::Int(b)) if a == b => true,
                 _ => false,
             };
             
@@ -1,12 +1,12 @@
-use crate::compiler::BytecodeCompiler;
+use crate::compiler::{self, BytecodeCompiler};
 use crate::encryption::{load_encrypted_bytecode, save_encrypted_bytecode};
 use crate::lexer::Lexer;
 use crate::parser::Parser;
 use crate::vm::BytecodeVM;
 use std::fs;
 
-/// Compile .as file to encrypted .ms bytecode
-pub fn compile_to_bytecode(input_path: &str, secret: Option<String>) {
+/// Compile .as file to encrypted .ms bytecode; `optimize` runs the constant-folding pass first
+pub fn compile_to_bytecode(input_path: &str, secret: Option<String>, optimize: bool) {
     println!("🔨 Compiling {} to bytecode...", input_path);
     
     // Read source file
@@ -29,7 +29,7 @@ pub fn compile_to_bytecode(input_path: &str, secret: Option<String>) {
     };
     
     let mut parser = Parser::new(tokens);
-    let ast = match parser.parse() {
+    let mut ast = match parser.parse() {
         Ok(a) => a,
         Err(e) => {
             eprintln!("❌ Parser error: {}", e);
@@ -37,6 +37,11 @@ pub fn compile_to_bytecode(input_path: &str, secret: Option<String>) {
         }
     };
     
+    if optimize {
+        compiler::optimize(&mut ast);
+        println!("⚡ Constant folding enabled (-O)");
+    }
+    
     // Compile to bytecode
     let mut compiler = BytecodeCompiler::new();
     let program = match compiler.compile(&ast) {
@@ -1,7 +1,169 @@
 use crate::bytecode::{BytecodeProgram, Constant, Instruction};
+use crate::evaluator::Value;
 use crate::parser::{Expr, BinaryOp, UnaryOp};
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 
+/// The value of an expression built only from literals and pure operators, computed the way
+/// the VM would. `None` when it depends on anything else or the VM would raise an error.
+pub fn const_value(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Number(n) => Some(Value::Number(*n)),
+        Expr::Int(n) => Some(Value::Int(*n)),
+        Expr::Boolean(b) => Some(Value::Boolean(*b)),
+        Expr::String(s) | Expr::RawString(s) => Some(Value::String(s.clone())),
+        Expr::BinaryOp { op, left, right } => {
+            let a = const_value(left)?;
+            let b = const_value(right)?;
+            match op {
+                BinaryOp::And => return Some(Value::Boolean(a.is_truthy() && b.is_truthy())),
+                BinaryOp::Or => return Some(Value::Boolean(a.is_truthy() || b.is_truthy())),
+                _ => {}
+            }
+            let (x, y) = (a.as_f64()?, b.as_f64()?);
+            let ordering = || Value::compare_numbers(&a, &b);
+            let result = match op {
+                BinaryOp::Add => Value::checked_arith(op, &a, &b, x + y),
+                BinaryOp::Subtract => Value::checked_arith(op, &a, &b, x - y),
+                BinaryOp::Multiply => Value::checked_arith(op, &a, &b, x * y),
+                BinaryOp::Modulo => Value::checked_arith(op, &a, &b, x % y),
+                BinaryOp::Divide if y != 0.0 => Value::Number(x / y),
+                BinaryOp::Equal => Value::Boolean(ordering().is_some_and(|o| o.is_eq())),
+                BinaryOp::NotEqual => Value::Boolean(!ordering().is_some_and(|o| o.is_eq())),
+                BinaryOp::Greater => Value::Boolean(ordering().is_some_and(|o| o.is_gt())),
+                BinaryOp::Less => Value::Boolean(ordering().is_some_and(|o| o.is_lt())),
+                BinaryOp::GreaterEqual => Value::Boolean(ordering().is_some_and(|o| o.is_ge())),
+                BinaryOp::LessEqual => Value::Boolean(ordering().is_some_and(|o| o.is_le())),
+                _ => return None,
+            };
+            Some(result)
+        }
+        Expr::UnaryOp { op: UnaryOp::Negate, expr } => match const_value(expr)? {
+            Value::Int(n) => Some(n.checked_neg().map(Value::from_i64).unwrap_or(Value::Number(-(n as f64)))),
+            Value::Number(n) => Some(Value::Number(-n)),
+            _ => None,
+        },
+        Expr::UnaryOp { op: UnaryOp::Not, expr } => Some(Value::Boolean(!const_value(expr)?.is_truthy())),
+        _ => None,
+    }
+}
+
+/// A literal for a folded value; non-finite numbers stay unfolded since `.ms` files can't hold them
+fn literal(value: Value) -> Option<Expr> {
+    match value {
+        Value::Number(n) if n.is_finite() => Some(Expr::Number(n)),
+        Value::Int(n) => Some(Expr::Int(n)),
+        Value::Boolean(b) => Some(Expr::Boolean(b)),
+        Value::String(s) => Some(Expr::String(s)),
+        _ => None,
+    }
+}
+
+/// Optimization pass run before `compile` under `mintas compile -O`: folds constant
+/// expressions (`2 + 3 * 4` becomes `14`) and removes branches and loops whose condition is
+/// a constant that rules them out.
+pub fn optimize(program: &mut Vec<Expr>) {
+    optimize_block(program);
+}
+
+fn optimize_block(body: &mut Vec<Expr>) {
+    let mut optimized = Vec::with_capacity(body.len());
+    for mut expr in body.drain(..) {
+        optimize_expr(&mut expr);
+        match expr {
+            // A statement-level `if` with a known outcome becomes the body that would run
+            Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
+                match live_branch(*condition, then_branch, else_if_branches, else_branch) {
+                    Ok(Some(live)) if !live.is_empty() => optimized.extend(live),
+                    Ok(_) => optimized.push(Expr::Empty),
+                    Err(expr) => optimized.push(expr),
+                }
+            }
+            other => optimized.push(other),
+        }
+    }
+    *body = optimized;
+}
+
+fn optimize_expr(expr: &mut Expr) {
+    match expr {
+        Expr::BinaryOp { left, right, .. } => {
+            optimize_expr(left);
+            optimize_expr(right);
+        }
+        Expr::UnaryOp { expr: inner, .. } => optimize_expr(inner),
+        Expr::Assign { value, .. } => optimize_expr(value),
+        Expr::Return { value: Some(value) } => optimize_expr(value),
+        Expr::Array(elements) | Expr::Call { args: elements, .. } => elements.iter_mut().for_each(optimize_expr),
+        Expr::Table(pairs) => pairs.iter_mut().for_each(|(_, value)| optimize_expr(value)),
+        Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
+            optimize_expr(condition);
+            optimize_block(then_branch);
+            for (cond, body) in else_if_branches.iter_mut() {
+                optimize_expr(cond);
+                optimize_block(body);
+            }
+            if let Some(body) = else_branch {
+                optimize_block(body);
+            }
+        }
+        Expr::WhileLoop { condition, body, else_branch } => {
+            optimize_expr(condition);
+            optimize_block(body);
+            if let Some(body) = else_branch {
+                optimize_block(body);
+            }
+            // A `while` whose condition is always false never runs
+            if else_branch.is_none() && const_value(condition).is_some_and(|v| !v.is_truthy()) {
+                *expr = Expr::Empty;
+            }
+        }
+        Expr::ForLoop { start, end, body, .. } => {
+            optimize_expr(start);
+            optimize_expr(end);
+            optimize_block(body);
+        }
+        _ => {}
+    }
+    if let Some(folded) = const_value(expr).and_then(literal) {
+        *expr = folded;
+    }
+}
+
+/// The statements an `if` runs when its taken arm is known at compile time: `Ok(None)` when
+/// no arm runs, `Err` with the (pruned) `if` when it depends on run-time values
+fn live_branch(
+    condition: Expr,
+    then_branch: Vec<Expr>,
+    else_if_branches: Vec<(Expr, Vec<Expr>)>,
+    else_branch: Option<Vec<Expr>>,
+) -> Result<Option<Vec<Expr>>, Expr> {
+    let mut arms = vec![(condition, then_branch)];
+    arms.extend(else_if_branches);
+    // Arms that can never be taken are dropped; the first that always is ends the chain
+    let mut live_arms = Vec::new();
+    let mut fallback = else_branch;
+    for (cond, body) in arms {
+        match const_value(&cond).map(|v| v.is_truthy()) {
+            Some(false) => continue,
+            Some(true) => {
+                fallback = Some(body);
+                break;
+            }
+            None => live_arms.push((cond, body)),
+        }
+    }
+    if live_arms.is_empty() {
+        return Ok(fallback);
+    }
+    let (condition, then_branch) = live_arms.remove(0);
+    Err(Expr::IfExpr {
+        condition: Box::new(condition),
+        then_branch,
+        else_if_branches: live_arms,
+        else_branch: fallback,
+    })
+}
+
 /// Bytecode compiler - converts AST to bytecode
 pub struct BytecodeCompiler {
     program: BytecodeProgram,
@@ -36,7 +198,12 @@ impl BytecodeCompiler {
                 self.program.emit(Instruction::LoadConst(idx));
             }
             
-            Expr::String(s) => {
+            Expr::Int(n) => {
+                let idx = self.program.add_constant(Constant::Int(*n));
+                self.program.emit(Instruction::LoadConst(idx));
+            }
+            
+            Expr::String(s) | Expr::RawString(s) => {
                 let idx = self.program.add_string(s.clone());
                 self.program.emit(Instruction::LoadString(idx));
             }
@@ -67,6 +234,21 @@ impl BytecodeCompiler {
                 self.program.emit(Instruction::StoreVar(name.clone()));
             }
             
+            Expr::Export { names, declaration } => {
+                match declaration {
+                    Some(declaration) => self.compile_expr(declaration)?,
+                    None => self.program.emit(Instruction::LoadEmpty),
+                }
+                self.program.exports.get_or_insert_with(Vec::new).extend(names.iter().cloned());
+            }
+            
+            Expr::BinaryOp { op: BinaryOp::NullCoalesce, .. } => {
+                return Err(MintasError::CompileError {
+                    message: "'??' is not supported by the bytecode compiler yet".to_string(),
+                    location: SourceLocation::new(0, 0),
+                });
+            }
+            
             Expr::BinaryOp { op, left, right } => {
                 self.compile_expr(left)?;
                 self.compile_expr(right)?;
@@ -94,7 +276,7 @@ impl BytecodeCompiler {
                 self.program.emit(Instruction::MakeTable(pairs.len()));
             }
             
-            Expr::Call { name, args } => {
+            Expr::Call { name, args, .. } => {
                 for arg in args {
                     self.compile_expr(arg)?;
                 }
@@ -105,8 +287,8 @@ impl BytecodeCompiler {
                 self.compile_if(condition, then_branch, else_if_branches, else_branch)?;
             }
             
-            Expr::WhileLoop { condition, body } => {
-                self.compile_while(condition, body)?;
+            Expr::WhileLoop { condition, body, else_branch } => {
+                self.compile_while(condition, body, else_branch.as_deref())?;
             }
             
             Expr::ForLoop { var, start, end, body } => {
@@ -236,7 +418,10 @@ impl BytecodeCompiler {
         Ok(())
     }
     
-    fn compile_while(&mut self, condition: &Expr, body: &[Expr]) -> MintasResult<()> {
+    fn compile_while(&mut self, condition: &Expr, body: &[Expr], else_branch: Option<&[Expr]>) -> MintasResult<()> {
+        if let Some(else_body) = else_branch {
+            return self.compile_while_else(condition, body, else_body);
+        }
         let loop_start = self.program.current_index();
         
         // Compile condition
@@ -264,16 +449,74 @@ impl BytecodeCompiler {
         Ok(())
     }
     
+    // Rotated loop: the first condition check is the only one that can reach the else body,
+    // so the condition is still evaluated exactly once per iteration.
+    fn compile_while_else(&mut self, condition: &Expr, body: &[Expr], else_body: &[Expr]) -> MintasResult<()> {
+        self.compile_expr(condition)?;
+        let jump_to_else = self.program.current_index();
+        self.program.emit(Instruction::JumpIfFalse(0)); // Placeholder
+        
+        let loop_start = self.program.current_index();
+        for expr in body {
+            self.compile_expr(expr)?;
+            self.program.emit(Instruction::Pop);
+        }
+        self.compile_expr(condition)?;
+        self.program.emit(Instruction::JumpIfTrue(loop_start));
+        let jump_to_end = self.program.current_index();
+        self.program.emit(Instruction::Jump(0)); // Placeholder
+        
+        let else_start = self.program.current_index();
+        self.program.patch_jump(jump_to_else, else_start);
+        for expr in else_body {
+            self.compile_expr(expr)?;
+            self.program.emit(Instruction::Pop);
+        }
+        
+        let end = self.program.current_index();
+        self.program.patch_jump(jump_to_end, end);
+        self.program.emit(Instruction::LoadEmpty);
+        
+        Ok(())
+    }
+    
+    /// Like the interpreter, evaluates the bounds once and counts down when start is above end
     fn compile_for(&mut self, var: &str, start: &Expr, end: &Expr, body: &[Expr]) -> MintasResult<()> {
-        // Initialize loop variable
+        let end_var = format!("#end:{}", var);
+        let step_var = format!("#step:{}", var);
         self.compile_expr(start)?;
         self.program.emit(Instruction::StoreVar(var.to_string()));
+        self.compile_expr(end)?;
+        self.program.emit(Instruction::StoreVar(end_var.clone()));
+        
+        // step = 1 when start <= end, else -1
+        let one_idx = self.program.add_constant(Constant::Number(1.0));
+        let minus_one_idx = self.program.add_constant(Constant::Number(-1.0));
+        self.program.emit(Instruction::LoadVar(var.to_string()));
+        self.program.emit(Instruction::LoadVar(end_var.clone()));
+        self.program.emit(Instruction::LessEq);
+        let jump_to_down = self.program.current_index();
+        self.program.emit(Instruction::JumpIfFalse(0)); // Placeholder
+        self.program.emit(Instruction::LoadConst(one_idx));
+        let jump_to_store = self.program.current_index();
+        self.program.emit(Instruction::Jump(0)); // Placeholder
+        let down = self.program.current_index();
+        self.program.patch_jump(jump_to_down, down);
+        self.program.emit(Instruction::LoadConst(minus_one_idx));
+        let store = self.program.current_index();
+        self.program.patch_jump(jump_to_store, store);
+        self.program.emit(Instruction::StoreVar(step_var.clone()));
         
         let loop_start = self.program.current_index();
         
-        // Check condition: var <= end
+        // Check condition: (var - end) * step <= 0
         self.program.emit(Instruction::LoadVar(var.to_string()));
-        self.compile_expr(end)?;
+        self.program.emit(Instruction::LoadVar(end_var));
+        self.program.emit(Instruction::Sub);
+        self.program.emit(Instruction::LoadVar(step_var.clone()));
+        self.program.emit(Instruction::Mul);
+        let zero_idx = self.program.add_constant(Constant::Number(0.0));
+        self.program.emit(Instruction::LoadConst(zero_idx));
         self.program.emit(Instruction::LessEq);
         
         // Jump to end if condition is false
@@ -286,13 +529,11 @@ impl BytecodeCompiler {
             self.program.emit(Instruction::Pop);
         }
         
-        // Increment loop variable
+        // Step the loop variable
         self.program.emit(Instruction::LoadVar(var.to_string()));
-        let one_idx = self.program.add_constant(Constant::Number(1.0));
-        self.program.emit(Instruction::LoadConst(one_idx));
+        self.program.emit(Instruction::LoadVar(step_var));
         self.program.emit(Instruction::Add);
         self.program.emit(Instruction::StoreVar(var.to_string()));
-        self.program.emit(Instruction::Pop);
         
         // Jump back to start
         self.program.emit(Instruction::Jump(loop_start));
@@ -312,3 +553,38 @@ impl Default for BytecodeCompiler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn compile_optimized(code: &str) -> BytecodeProgram {
+        let mut ast = Parser::new(Lexer::new(code).tokenize().unwrap()).parse().unwrap();
+        optimize(&mut ast);
+        BytecodeCompiler::new().compile(&ast).unwrap()
+    }
+
+    #[test]
+    fn test_constant_expression_folds_to_single_push() {
+        let program = compile_optimized("2 + 3 * 4");
+        assert_eq!(program.instructions, vec![Instruction::LoadConst(0), Instruction::Halt]);
+        assert!(matches!(program.constants.as_slice(), [Constant::Number(n)] if *n == 14.0));
+        let program = compile_optimized("not (1 < 2)");
+        assert_eq!(program.instructions, vec![Instruction::LoadFalse, Instruction::Halt]);
+        // Division by zero is left for the VM to report
+        assert!(compile_optimized("1 / 0").instructions.contains(&Instruction::Div));
+    }
+
+    #[test]
+    fn test_constant_branches_are_removed() {
+        let program = compile_optimized("x = 1\nif (false):\n    x = 2\nelse if (1 == 1):\n    x = 3\nelse:\n    x = 4\nend\nwhile (0):\n    x = 5\nend\nx");
+        assert!(!program.instructions.iter().any(|i| matches!(i, Instruction::Jump(_) | Instruction::JumpIfFalse(_))));
+        let result = crate::vm::BytecodeVM::new(program).execute().unwrap();
+        assert_eq!(result, Value::Number(3.0));
+        // Conditions known only at run time keep their branch
+        let program = compile_optimized("x = 1\nif (x > 0):\n    x = 2\nend\nx");
+        assert!(program.instructions.iter().any(|i| matches!(i, Instruction::JumpIfFalse(_))));
+    }
+}
@@ -15,6 +15,13 @@ use crate::parser::{BinaryOp, UnaryOp};
 #[cfg(feature = "cranelift-backend")]
 use std::collections::HashMap;
 #[cfg(feature = "cranelift-backend")]
+use std::cell::Cell;
+#[cfg(feature = "cranelift-backend")]
+use crate::evaluator::Value;
+/// Name suffix of the hidden variable that records whether a variable was assigned
+#[cfg(feature = "cranelift-backend")]
+const ASSIGNED_SUFFIX: &str = "#assigned";
+#[cfg(feature = "cranelift-backend")]
 extern "C" fn jetx_print_f64(n: f64) {
     if n.fract() == 0.0 && n.abs() < 1e15 {
         println!("{}", n as i64);
@@ -23,12 +30,77 @@ extern "C" fn jetx_print_f64(n: f64) {
     }
 }
 #[cfg(feature = "cranelift-backend")]
+thread_local! {
+    /// Set when compiled code met something only the interpreter gets right: a zero
+    /// divisor, which must raise its error, or an integer past 2^53, which it keeps exact
+    static BAILED: Cell<bool> = const { Cell::new(false) };
+}
+#[cfg(feature = "cranelift-backend")]
+fn bail_unless(ok: bool) {
+    if !ok {
+        BAILED.with(|bailed| bailed.set(true));
+    }
+}
+#[cfg(feature = "cranelift-backend")]
+fn is_exact(n: f64) -> bool {
+    n.abs() <= Value::MAX_EXACT_INT as f64
+}
+#[cfg(feature = "cranelift-backend")]
+extern "C" fn jetx_divide(a: f64, b: f64) -> f64 {
+    bail_unless(b != 0.0);
+    a / b
+}
+/// `%` and `mod`: fmod, which truncates like the interpreter
+#[cfg(feature = "cranelift-backend")]
+extern "C" fn jetx_remainder(a: f64, b: f64) -> f64 {
+    bail_unless(b != 0.0 && is_exact(a) && is_exact(b));
+    a % b
+}
+#[cfg(feature = "cranelift-backend")]
+extern "C" fn jetx_truncating_divide(a: f64, b: f64) -> f64 {
+    bail_unless(b != 0.0 && is_exact(a) && is_exact(b));
+    (a / b).trunc()
+}
+/// Runtime helpers as seen from the function being built
+#[cfg(feature = "cranelift-backend")]
+#[derive(Clone, Copy)]
+struct Imports {
+    print: FuncRef,
+    divide: FuncRef,
+    remainder: FuncRef,
+    truncating_divide: FuncRef,
+}
+#[cfg(feature = "cranelift-backend")]
+impl Imports {
+    fn declare(module: &mut JITModule, ids: &HashMap<&'static str, FuncId>, func: &mut codegen::ir::Function) -> Self {
+        let mut import = |name: &str| module.declare_func_in_func(ids[name], func);
+        Imports {
+            print: import("jetx_print_f64"),
+            divide: import("jetx_divide"),
+            remainder: import("jetx_remainder"),
+            truncating_divide: import("jetx_truncating_divide"),
+        }
+    }
+    fn call(builder: &mut FunctionBuilder, helper: FuncRef, a: cranelift::prelude::Value, b: cranelift::prelude::Value) -> cranelift::prelude::Value {
+        let call = builder.ins().call(helper, &[a, b]);
+        builder.inst_results(call)[0]
+    }
+}
+#[cfg(feature = "cranelift-backend")]
 pub struct CraneliftCompiler {
     module: JITModule,
     ctx: codegen::Context,
     builder_context: FunctionBuilderContext,
     func_ids: HashMap<String, FuncId>,
-    print_func_id: Option<FuncId>,
+    import_ids: HashMap<&'static str, FuncId>,
+    main_variables: Vec<String>,
+}
+/// What a compiled program left behind: its result and every top-level variable it assigned.
+/// `bailed` means the values can't be trusted and the interpreter has to redo the run.
+pub struct JetXRun {
+    pub result: f64,
+    pub variables: Vec<(String, f64)>,
+    pub bailed: bool,
 }
 #[cfg(not(feature = "cranelift-backend"))]
 pub struct CraneliftCompiler {
@@ -47,23 +119,38 @@ impl CraneliftCompiler {
         let isa = isa_builder.finish(settings::Flags::new(flag_builder)).unwrap();
         let mut builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
         builder.symbol("jetx_print_f64", jetx_print_f64 as *const u8);
+        builder.symbol("jetx_divide", jetx_divide as *const u8);
+        builder.symbol("jetx_remainder", jetx_remainder as *const u8);
+        builder.symbol("jetx_truncating_divide", jetx_truncating_divide as *const u8);
         let module = JITModule::new(builder);
         Ok(Self {
             ctx: module.make_context(),
             module,
             builder_context: FunctionBuilderContext::new(),
             func_ids: HashMap::new(),
-            print_func_id: None,
+            import_ids: HashMap::new(),
+            main_variables: Vec::new(),
         })
     }
     pub fn compile_program(&mut self, statements: &[Expr]) -> MintasResult<()> {
-        let mut print_sig = self.module.make_signature();
-        print_sig.params.push(AbiParam::new(types::F64));
-        self.print_func_id = Some(self.module.declare_function("jetx_print_f64", Linkage::Import, &print_sig)
-            .map_err(|e| MintasError::RuntimeError {
-                message: format!("Failed to declare print: {}", e),
-                location: SourceLocation::new(0, 0),
-            })?);
+        for (name, params, returns) in [
+            ("jetx_print_f64", 1, false),
+            ("jetx_divide", 2, true),
+            ("jetx_remainder", 2, true),
+            ("jetx_truncating_divide", 2, true),
+        ] {
+            let mut sig = self.module.make_signature();
+            sig.params.extend((0..params).map(|_| AbiParam::new(types::F64)));
+            if returns {
+                sig.returns.push(AbiParam::new(types::F64));
+            }
+            let id = self.module.declare_function(name, Linkage::Import, &sig)
+                .map_err(|e| MintasError::RuntimeError {
+                    message: format!("Failed to declare {}: {}", name, e),
+                    location: SourceLocation::new(0, 0),
+                })?;
+            self.import_ids.insert(name, id);
+        }
         for stmt in statements {
             if let Expr::Function { name, params, .. } = stmt {
                 let mut sig = self.module.make_signature();
@@ -108,7 +195,7 @@ impl CraneliftCompiler {
         for (fn_name, &fn_id) in &self.func_ids {
             local_funcs.insert(fn_name.clone(), self.module.declare_func_in_func(fn_id, builder.func));
         }
-        let print_ref = self.print_func_id.map(|id| self.module.declare_func_in_func(id, builder.func));
+        let imports = Imports::declare(&mut self.module, &self.import_ids, builder.func);
         let entry = builder.create_block();
         builder.append_block_params_for_function_params(entry);
         builder.switch_to_block(entry);
@@ -124,7 +211,7 @@ impl CraneliftCompiler {
         }
         let mut last = builder.ins().f64const(0.0);
         for stmt in body {
-            if let Some((val, ret)) = Self::compile_expr(&mut builder, stmt, &mut vars, &mut var_idx, &local_funcs, print_ref) {
+            if let Some((val, ret)) = Self::compile_expr(&mut builder, stmt, &mut vars, &mut var_idx, &local_funcs, imports) {
                 last = val;
                 if ret {
                     builder.ins().return_(&[last]);
@@ -147,8 +234,12 @@ impl CraneliftCompiler {
         self.ctx.clear();
         Ok(())
     }
+    /// `__main__` takes a buffer with two slots per top-level variable: the value it ended
+    /// with, and 1.0 if it was assigned at all
     fn compile_main(&mut self, statements: &[Expr]) -> MintasResult<()> {
+        let pointer_type = self.module.target_config().pointer_type();
         let mut sig = self.module.make_signature();
+        sig.params.push(AbiParam::new(pointer_type));
         sig.returns.push(AbiParam::new(types::F64));
         let func_id = self.module.declare_function("__main__", Linkage::Export, &sig)
             .map_err(|e| MintasError::RuntimeError {
@@ -161,18 +252,32 @@ impl CraneliftCompiler {
         for (fn_name, &fn_id) in &self.func_ids {
             local_funcs.insert(fn_name.clone(), self.module.declare_func_in_func(fn_id, builder.func));
         }
-        let print_ref = self.print_func_id.map(|id| self.module.declare_func_in_func(id, builder.func));
+        let imports = Imports::declare(&mut self.module, &self.import_ids, builder.func);
         let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
         builder.switch_to_block(entry);
         builder.seal_block(entry);
+        let out = builder.block_params(entry)[0];
         let mut vars: HashMap<String, Variable> = HashMap::new();
         let mut var_idx = 0usize;
         let mut last = builder.ins().f64const(0.0);
         for stmt in statements {
-            if let Some((val, _)) = Self::compile_expr(&mut builder, stmt, &mut vars, &mut var_idx, &local_funcs, print_ref) {
+            if let Some((val, _)) = Self::compile_expr(&mut builder, stmt, &mut vars, &mut var_idx, &local_funcs, imports) {
                 last = val;
             }
         }
+        let mut names: Vec<String> = vars.keys().filter(|name| !name.ends_with(ASSIGNED_SUFFIX)).cloned().collect();
+        names.sort();
+        for (slot, name) in names.iter().enumerate() {
+            let value = builder.use_var(vars[name]);
+            let assigned = match vars.get(&format!("{}{}", name, ASSIGNED_SUFFIX)) {
+                Some(&flag) => builder.use_var(flag),
+                None => builder.ins().f64const(0.0),
+            };
+            builder.ins().store(MemFlags::trusted(), value, out, (slot * 16) as i32);
+            builder.ins().store(MemFlags::trusted(), assigned, out, (slot * 16 + 8) as i32);
+        }
+        self.main_variables = names;
         builder.ins().return_(&[last]);
         builder.finalize();
         self.module.define_function(func_id, &mut self.ctx).map_err(|e| MintasError::RuntimeError {
@@ -183,11 +288,20 @@ impl CraneliftCompiler {
         self.func_ids.insert("__main__".to_string(), func_id);
         Ok(())
     }
-    pub fn execute_main(&self) -> MintasResult<f64> {
+    pub fn execute_main(&self) -> MintasResult<JetXRun> {
         if let Some(&func_id) = self.func_ids.get("__main__") {
             let code_ptr = self.module.get_finalized_function(func_id);
-            let code_fn: fn() -> f64 = unsafe { std::mem::transmute(code_ptr) };
-            Ok(code_fn())
+            let code_fn: extern "C" fn(*mut f64) -> f64 = unsafe { std::mem::transmute(code_ptr) };
+            let mut slots = vec![0.0f64; self.main_variables.len() * 2];
+            BAILED.with(|bailed| bailed.set(false));
+            let result = code_fn(slots.as_mut_ptr());
+            let variables: Vec<(String, f64)> = self.main_variables.iter()
+                .zip(slots.chunks(2))
+                .filter(|(_, slot)| slot[1] == 1.0)
+                .map(|(name, slot)| (name.clone(), slot[0]))
+                .collect();
+            let bailed = BAILED.with(Cell::get) || !is_exact(result) || variables.iter().any(|(_, value)| !is_exact(*value));
+            Ok(JetXRun { result, variables, bailed })
         } else {
             Err(MintasError::RuntimeError {
                 message: "No main function".to_string(),
@@ -201,11 +315,12 @@ impl CraneliftCompiler {
         vars: &mut HashMap<String, Variable>,
         var_idx: &mut usize,
         funcs: &HashMap<String, FuncRef>,
-        print_ref: Option<FuncRef>,
+        imports: Imports,
     ) -> Option<(cranelift::prelude::Value, bool)> {
         match expr {
             Expr::Number(n) => Some((builder.ins().f64const(*n), false)),
-            Expr::String(s) => {
+            Expr::Int(n) => Some((builder.ins().f64const(*n as f64), false)),
+            Expr::String(s) | Expr::RawString(s) => {
                 // Convert string to a simple numeric representation for JetX
                 Some((builder.ins().f64const(s.len() as f64), false))
             },
@@ -215,39 +330,38 @@ impl CraneliftCompiler {
                     .or_else(|| Some((builder.ins().f64const(0.0), false)))
             },
             Expr::Assign { name, value, .. } => {
-                let (val, _) = Self::compile_expr(builder, value, vars, var_idx, funcs, print_ref)?;
-                let var = Self::get_or_create_var(builder, name, vars, var_idx);
-                builder.def_var(var, val);
+                let (val, _) = Self::compile_expr(builder, value, vars, var_idx, funcs, imports)?;
+                Self::assign_var(builder, name, val, vars, var_idx);
                 Some((val, false))
             }
             Expr::BinaryOp { op, left, right } => {
-                let (l, _) = Self::compile_expr(builder, left, vars, var_idx, funcs, print_ref)?;
-                let (r, _) = Self::compile_expr(builder, right, vars, var_idx, funcs, print_ref)?;
-                Some((Self::compile_binop(builder, op, l, r), false))
+                let (l, _) = Self::compile_expr(builder, left, vars, var_idx, funcs, imports)?;
+                let (r, _) = Self::compile_expr(builder, right, vars, var_idx, funcs, imports)?;
+                Some((Self::compile_binop(builder, op, l, r, imports), false))
             }
             Expr::UnaryOp { op, expr: inner } => {
-                let (val, _) = Self::compile_expr(builder, inner, vars, var_idx, funcs, print_ref)?;
+                let (val, _) = Self::compile_expr(builder, inner, vars, var_idx, funcs, imports)?;
                 Some((Self::compile_unaryop(builder, op, val), false))
             }
             Expr::Return { value } => {
                 let ret_val = if let Some(v) = value {
-                    Self::compile_expr(builder, v, vars, var_idx, funcs, print_ref)?.0
+                    Self::compile_expr(builder, v, vars, var_idx, funcs, imports)?.0
                 } else {
                     builder.ins().f64const(0.0)
                 };
                 Some((ret_val, true))
             }
-            Expr::Call { name, args } => {
-                Self::compile_call(builder, name, args, vars, var_idx, funcs, print_ref)
+            Expr::Call { name, args, .. } => {
+                Self::compile_call(builder, name, args, vars, var_idx, funcs, imports)
             }
             Expr::IfExpr { condition, then_branch, else_branch, .. } => {
-                Self::compile_if(builder, condition, then_branch, else_branch.as_ref(), vars, var_idx, funcs, print_ref)
+                Self::compile_if(builder, condition, then_branch, else_branch.as_ref(), vars, var_idx, funcs, imports)
             }
             Expr::ForLoop { var, start, end, body } => {
-                Self::compile_for(builder, var, start, end, body, vars, var_idx, funcs, print_ref)
+                Self::compile_for(builder, var, start, end, body, vars, var_idx, funcs, imports)
             }
-            Expr::WhileLoop { condition, body } => {
-                Self::compile_while(builder, condition, body, vars, var_idx, funcs, print_ref)
+            Expr::WhileLoop { condition, body, else_branch: None } => {
+                Self::compile_while(builder, condition, body, vars, var_idx, funcs, imports)
             }
             _ => Some((builder.ins().f64const(0.0), false)),
         }
@@ -259,18 +373,16 @@ impl CraneliftCompiler {
         vars: &mut HashMap<String, Variable>,
         var_idx: &mut usize,
         funcs: &HashMap<String, FuncRef>,
-        print_ref: Option<FuncRef>,
+        imports: Imports,
     ) -> Option<(cranelift::prelude::Value, bool)> {
         if name == "say" {
-            if let Some(pr) = print_ref {
-                for arg in args {
-                    let (val, _) = Self::compile_expr(builder, arg, vars, var_idx, funcs, print_ref)?;
-                    builder.ins().call(pr, &[val]);
-                }
+            for arg in args {
+                let (val, _) = Self::compile_expr(builder, arg, vars, var_idx, funcs, imports)?;
+                builder.ins().call(imports.print, &[val]);
             }
             // Return the last argument value instead of 0
             if !args.is_empty() {
-                let (last_val, _) = Self::compile_expr(builder, &args[args.len()-1], vars, var_idx, funcs, print_ref)?;
+                let (last_val, _) = Self::compile_expr(builder, &args[args.len()-1], vars, var_idx, funcs, imports)?;
                 return Some((last_val, false));
             }
             return Some((builder.ins().f64const(0.0), false));
@@ -278,13 +390,55 @@ impl CraneliftCompiler {
         if let Some(&func_ref) = funcs.get(name) {
             let mut arg_vals = Vec::new();
             for arg in args {
-                let (val, _) = Self::compile_expr(builder, arg, vars, var_idx, funcs, print_ref)?;
+                let (val, _) = Self::compile_expr(builder, arg, vars, var_idx, funcs, imports)?;
                 arg_vals.push(val);
             }
             let call = builder.ins().call(func_ref, &arg_vals);
             let result = builder.inst_results(call)[0];
             return Some((result, false));
         }
+        if matches!(name, "floor" | "ceil" | "abs" | "sqrt" | "round" | "min" | "max") && !args.is_empty() {
+            let mut arg_vals = Vec::new();
+            for arg in args {
+                let (val, _) = Self::compile_expr(builder, arg, vars, var_idx, funcs, imports)?;
+                arg_vals.push(val);
+            }
+            let x = arg_vals[0];
+            let result = match name {
+                "floor" => builder.ins().floor(x),
+                "ceil" => builder.ins().ceil(x),
+                "abs" => builder.ins().fabs(x),
+                "sqrt" => builder.ins().sqrt(x),
+                "round" => {
+                    // Round half away from zero to match the interpreter
+                    let factor = match args.get(1) {
+                        None => 1.0,
+                        Some(Expr::Number(digits)) => 10f64.powi(*digits as i32),
+                        Some(Expr::Int(digits)) => 10f64.powi(*digits as i32),
+                        Some(_) => return None,
+                    };
+                    let factor_val = builder.ins().f64const(factor);
+                    let scaled = builder.ins().fmul(x, factor_val);
+                    let magnitude = builder.ins().fabs(scaled);
+                    let half = builder.ins().f64const(0.5);
+                    let shifted = builder.ins().fadd(magnitude, half);
+                    let rounded = builder.ins().floor(shifted);
+                    let signed = builder.ins().fcopysign(rounded, scaled);
+                    builder.ins().fdiv(signed, factor_val)
+                }
+                "min" => arg_vals[1..].iter().fold(x, |acc, &v| builder.ins().fmin(acc, v)),
+                "max" => arg_vals[1..].iter().fold(x, |acc, &v| builder.ins().fmax(acc, v)),
+                _ => return None,
+            };
+            return Some((result, false));
+        }
+        // Truncating like the interpreter; the helpers bail out on a zero divisor
+        if matches!(name, "idiv" | "mod") && args.len() == 2 {
+            let (a, _) = Self::compile_expr(builder, &args[0], vars, var_idx, funcs, imports)?;
+            let (b, _) = Self::compile_expr(builder, &args[1], vars, var_idx, funcs, imports)?;
+            let helper = if name == "idiv" { imports.truncating_divide } else { imports.remainder };
+            return Some((Imports::call(builder, helper, a, b), false));
+        }
         // Unknown function call - still return a valid value, not 0
         // This ensures proper JetX execution flow
         Some((builder.ins().f64const(0.0), false))
@@ -302,18 +456,21 @@ impl CraneliftCompiler {
             var
         }
     }
-    fn compile_binop(builder: &mut FunctionBuilder, op: &BinaryOp, l: cranelift::prelude::Value, r: cranelift::prelude::Value) -> cranelift::prelude::Value {
+    /// Sets `name` and its assigned flag, which tells a never-run branch apart from a zero
+    fn assign_var(builder: &mut FunctionBuilder, name: &str, val: cranelift::prelude::Value, vars: &mut HashMap<String, Variable>, var_idx: &mut usize) {
+        let var = Self::get_or_create_var(builder, name, vars, var_idx);
+        builder.def_var(var, val);
+        let flag = Self::get_or_create_var(builder, &format!("{}{}", name, ASSIGNED_SUFFIX), vars, var_idx);
+        let one = builder.ins().f64const(1.0);
+        builder.def_var(flag, one);
+    }
+    fn compile_binop(builder: &mut FunctionBuilder, op: &BinaryOp, l: cranelift::prelude::Value, r: cranelift::prelude::Value, imports: Imports) -> cranelift::prelude::Value {
         match op {
             BinaryOp::Add => builder.ins().fadd(l, r),
             BinaryOp::Subtract => builder.ins().fsub(l, r),
             BinaryOp::Multiply => builder.ins().fmul(l, r),
-            BinaryOp::Divide => builder.ins().fdiv(l, r),
-            BinaryOp::Modulo => {
-                let div = builder.ins().fdiv(l, r);
-                let floored = builder.ins().floor(div);
-                let mult = builder.ins().fmul(floored, r);
-                builder.ins().fsub(l, mult)
-            }
+            BinaryOp::Divide => Imports::call(builder, imports.divide, l, r),
+            BinaryOp::Modulo => Imports::call(builder, imports.remainder, l, r),
             BinaryOp::Equal => {
                 let cmp = builder.ins().fcmp(FloatCC::Equal, l, r);
                 let one = builder.ins().f64const(1.0);
@@ -368,6 +525,8 @@ impl CraneliftCompiler {
                 let zero2 = builder.ins().f64const(0.0);
                 builder.ins().select(or_result, one, zero2)
             }
+            // JIT values are plain floats and never null
+            BinaryOp::NullCoalesce => l,
             _ => builder.ins().f64const(0.0),
         }
     }
@@ -392,9 +551,9 @@ impl CraneliftCompiler {
         vars: &mut HashMap<String, Variable>,
         var_idx: &mut usize,
         funcs: &HashMap<String, FuncRef>,
-        print_ref: Option<FuncRef>,
+        imports: Imports,
     ) -> Option<(cranelift::prelude::Value, bool)> {
-        let (cond_val, _) = Self::compile_expr(builder, condition, vars, var_idx, funcs, print_ref)?;
+        let (cond_val, _) = Self::compile_expr(builder, condition, vars, var_idx, funcs, imports)?;
         let zero = builder.ins().f64const(0.0);
         let cond_bool = builder.ins().fcmp(FloatCC::NotEqual, cond_val, zero);
         let then_block = builder.create_block();
@@ -406,7 +565,7 @@ impl CraneliftCompiler {
         builder.seal_block(then_block);
         let mut then_result = builder.ins().f64const(0.0);
         for stmt in then_branch {
-            if let Some((val, ret)) = Self::compile_expr(builder, stmt, vars, var_idx, funcs, print_ref) {
+            if let Some((val, ret)) = Self::compile_expr(builder, stmt, vars, var_idx, funcs, imports) {
                 then_result = val;
                 if ret {
                     builder.ins().return_(&[then_result]);
@@ -426,7 +585,7 @@ impl CraneliftCompiler {
         let else_result = if let Some(else_stmts) = else_branch {
             let mut result = builder.ins().f64const(0.0);
             for stmt in else_stmts {
-                if let Some((val, ret)) = Self::compile_expr(builder, stmt, vars, var_idx, funcs, print_ref) {
+                if let Some((val, ret)) = Self::compile_expr(builder, stmt, vars, var_idx, funcs, imports) {
                     result = val;
                     if ret {
                         builder.ins().return_(&[result]);
@@ -456,33 +615,43 @@ impl CraneliftCompiler {
         vars: &mut HashMap<String, Variable>,
         var_idx: &mut usize,
         funcs: &HashMap<String, FuncRef>,
-        print_ref: Option<FuncRef>,
+        imports: Imports,
     ) -> Option<(cranelift::prelude::Value, bool)> {
-        let (start_val, _) = Self::compile_expr(builder, start, vars, var_idx, funcs, print_ref)?;
-        let (end_val, _) = Self::compile_expr(builder, end, vars, var_idx, funcs, print_ref)?;
-        let loop_var = Self::get_or_create_var(builder, var, vars, var_idx);
-        builder.def_var(loop_var, start_val);
+        let (start_val, _) = Self::compile_expr(builder, start, vars, var_idx, funcs, imports)?;
+        let (end_val, _) = Self::compile_expr(builder, end, vars, var_idx, funcs, imports)?;
+        // Like the interpreter: whole-number bounds, counting down when start > end, and the
+        // variable keeps the last value it took
+        let start_val = builder.ins().trunc(start_val);
+        let end_val = builder.ins().trunc(end_val);
+        let ascending = builder.ins().fcmp(FloatCC::LessThanOrEqual, start_val, end_val);
+        let one = builder.ins().f64const(1.0);
+        let minus_one = builder.ins().f64const(-1.0);
+        let step = builder.ins().select(ascending, one, minus_one);
+        Self::assign_var(builder, var, start_val, vars, var_idx);
+        let loop_var = vars[var];
         let header = builder.create_block();
         let body_block = builder.create_block();
         let exit = builder.create_block();
         builder.ins().jump(header, &[]);
         builder.switch_to_block(header);
         let current = builder.use_var(loop_var);
-        let cond = builder.ins().fcmp(FloatCC::LessThanOrEqual, current, end_val);
+        let up_to_end = builder.ins().fcmp(FloatCC::LessThanOrEqual, current, end_val);
+        let down_to_end = builder.ins().fcmp(FloatCC::GreaterThanOrEqual, current, end_val);
+        let cond = builder.ins().select(ascending, up_to_end, down_to_end);
         builder.ins().brif(cond, body_block, &[], exit, &[]);
         builder.switch_to_block(body_block);
         builder.seal_block(body_block);
         for stmt in body {
-            Self::compile_expr(builder, stmt, vars, var_idx, funcs, print_ref);
+            Self::compile_expr(builder, stmt, vars, var_idx, funcs, imports);
         }
         let current = builder.use_var(loop_var);
-        let one = builder.ins().f64const(1.0);
-        let next = builder.ins().fadd(current, one);
+        let next = builder.ins().fadd(current, step);
         builder.def_var(loop_var, next);
         builder.ins().jump(header, &[]);
         builder.seal_block(header);
         builder.switch_to_block(exit);
         builder.seal_block(exit);
+        builder.def_var(loop_var, end_val);
         Some((builder.ins().f64const(0.0), false))
     }
     fn compile_while(
@@ -492,21 +661,21 @@ impl CraneliftCompiler {
         vars: &mut HashMap<String, Variable>,
         var_idx: &mut usize,
         funcs: &HashMap<String, FuncRef>,
-        print_ref: Option<FuncRef>,
+        imports: Imports,
     ) -> Option<(cranelift::prelude::Value, bool)> {
         let header = builder.create_block();
         let body_block = builder.create_block();
         let exit = builder.create_block();
         builder.ins().jump(header, &[]);
         builder.switch_to_block(header);
-        let (cond_val, _) = Self::compile_expr(builder, condition, vars, var_idx, funcs, print_ref)?;
+        let (cond_val, _) = Self::compile_expr(builder, condition, vars, var_idx, funcs, imports)?;
         let zero = builder.ins().f64const(0.0);
         let cond_bool = builder.ins().fcmp(FloatCC::NotEqual, cond_val, zero);
         builder.ins().brif(cond_bool, body_block, &[], exit, &[]);
         builder.switch_to_block(body_block);
         builder.seal_block(body_block);
         for stmt in body {
-            Self::compile_expr(builder, stmt, vars, var_idx, funcs, print_ref);
+            Self::compile_expr(builder, stmt, vars, var_idx, funcs, imports);
         }
         builder.ins().jump(header, &[]);
         builder.seal_block(header);
@@ -529,10 +698,44 @@ impl CraneliftCompiler {
             location: SourceLocation::new(0, 0),
         })
     }
-    pub fn execute_main(&self) -> MintasResult<f64> {
+    pub fn execute_main(&self) -> MintasResult<JetXRun> {
         Err(MintasError::RuntimeError {
             message: "JetX not available".to_string(),
             location: SourceLocation::new(0, 0),
         })
     }
-}
\ No newline at end of file
+}#[cfg(all(test, feature = "cranelift-backend"))]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn run(code: &str) -> JetXRun {
+        let tokens = Lexer::new(code).tokenize().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut compiler = CraneliftCompiler::new().unwrap();
+        compiler.compile_program(&statements).unwrap();
+        compiler.execute_main().unwrap()
+    }
+
+    #[test]
+    fn test_remainders_truncate_like_the_interpreter() {
+        let cases = [
+            ("mod(-7, 3)", -1.0),
+            ("mod(7, -3)", 1.0),
+            ("-7 % 3", -1.0),
+            ("idiv(-7, 2)", -3.0),
+            ("mod(9007199254740991, 10)", 1.0),
+            ("mod(-9007199254740991, 1000003)", -9007199254740991.0 % 1000003.0),
+        ];
+        for (code, expected) in cases {
+            let run = run(code);
+            assert!(!run.bailed, "{}", code);
+            assert_eq!(run.result, expected, "{}", code);
+        }
+        // Past 2^53 only the interpreter's integers are exact, and only it raises on zero
+        for code in ["mod(1e17 + 3, 7)", "x = 4503599627370496 * 4 + 3\nmod(x, 7)", "mod(5, 0)", "n = 0\n5 / n"] {
+            assert!(run(code).bailed, "{}", code);
+        }
+    }
+}
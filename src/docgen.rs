@@ -0,0 +1,125 @@
+use crate::lexer::Lexer;
+use crate::parser::{Expr, Parser};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A documented function found in a source file
+pub struct DocEntry {
+    pub name: String,
+    pub params: Vec<String>,
+    pub doc: Option<String>,
+}
+
+/// Top-level and exported function definitions, in source order
+pub fn collect_functions(statements: &[Expr]) -> Vec<DocEntry> {
+    let mut entries = Vec::new();
+    for stmt in statements {
+        let stmt = match stmt {
+            Expr::Export { declaration: Some(decl), .. } => decl.as_ref(),
+            other => other,
+        };
+        if let Expr::Function { name, params, doc, .. } = stmt {
+            entries.push(DocEntry {
+                name: name.clone(),
+                params: params.clone(),
+                doc: doc.clone(),
+            });
+        }
+    }
+    entries
+}
+
+/// Markdown for one file's functions; empty when the file defines none
+pub fn render_markdown(title: &str, entries: &[DocEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let mut out = format!("## {}\n\n", title);
+    for entry in entries {
+        out.push_str(&format!("### `{}({})`\n\n", entry.name, entry.params.join(", ")));
+        if !entry.params.is_empty() {
+            out.push_str("**Parameters:** ");
+            let params: Vec<String> = entry.params.iter().map(|p| format!("`{}`", p)).collect();
+            out.push_str(&params.join(", "));
+            out.push_str("\n\n");
+        }
+        match &entry.doc {
+            Some(doc) => {
+                out.push_str(doc.trim());
+                out.push_str("\n\n");
+            }
+            None => out.push_str("_Undocumented._\n\n"),
+        }
+    }
+    out
+}
+
+fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let mut children: Vec<PathBuf> = match fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
+            Err(_) => return,
+        };
+        children.sort();
+        for child in children {
+            collect_sources(&child, files);
+        }
+    } else if path.extension().is_some_and(|ext| ext == "as") {
+        files.push(path.to_path_buf());
+    }
+}
+
+/// `mintas doc <path> [-o out.md]`: writes Markdown for every `.as` file under `path`
+pub fn generate_docs(input: &str, output: &str) {
+    let mut files = Vec::new();
+    collect_sources(Path::new(input), &mut files);
+    if files.is_empty() {
+        eprintln!("❌ No .as files found in {}", input);
+        std::process::exit(1);
+    }
+    let mut markdown = String::from("# API Reference\n\n");
+    let mut documented = 0;
+    for file in &files {
+        let display = file.to_string_lossy();
+        let source = match fs::read_to_string(file) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("⚠️  Skipping {}: {}", display, e);
+                continue;
+            }
+        };
+        let statements = match Lexer::new(&source).tokenize().and_then(|tokens| Parser::new(tokens).parse()) {
+            Ok(statements) => statements,
+            Err(e) => {
+                eprintln!("⚠️  Skipping {}: {}", display, e);
+                continue;
+            }
+        };
+        let entries = collect_functions(&statements);
+        documented += entries.len();
+        markdown.push_str(&render_markdown(&display, &entries));
+    }
+    if let Err(e) = fs::write(output, markdown.trim_end().to_string() + "\n") {
+        eprintln!("❌ Error writing {}: {}", output, e);
+        std::process::exit(1);
+    }
+    println!("📄 Documented {} functions from {} files in {}", documented, files.len(), output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_comments_render_as_markdown() {
+        let source = "## Adds two numbers.\n## Works on floats too.\nfunc add(a, b):\n    return a + b\nend\n\nexport func helper():\n    return 1\nend\nx = 1\n";
+        let statements = Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap();
+        let entries = collect_functions(&statements);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            render_markdown("math.as", &entries),
+            "## math.as\n\n### `add(a, b)`\n\n**Parameters:** `a`, `b`\n\nAdds two numbers.\nWorks on floats too.\n\n### `helper()`\n\n_Undocumented._\n\n"
+        );
+        assert_eq!(render_markdown("empty.as", &[]), "");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::bytecode::BytecodeProgram;
+use crate::bytecode::{BytecodeProgram, BYTECODE_VERSION};
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use aes::Aes256;
 use cbc::{Decryptor, Encryptor};
@@ -72,7 +72,7 @@ pub fn save_encrypted_bytecode(program: &BytecodeProgram, path: &str, secret: Op
     // Header
     file.write_all(MAGIC)?;
     file.write_all(&VERSION.to_le_bytes())?;
-    file.write_all(&[0, 0, 0, 0])?; // Flags (reserved)
+    file.write_all(&[BYTECODE_VERSION, 0, 0, 0])?; // Bytecode version + reserved flags
     
     // IV and ciphertext
     file.write_all(&iv)?;
@@ -81,6 +81,21 @@ pub fn save_encrypted_bytecode(program: &BytecodeProgram, path: &str, secret: Op
     Ok(())
 }
 
+/// Files written before the version byte existed carry 0 there and hold version 1 bytecode.
+fn check_bytecode_version(stored: u8) -> MintasResult<()> {
+    let version = if stored == 0 { 1 } else { stored };
+    if version > BYTECODE_VERSION {
+        return Err(MintasError::RuntimeError {
+            message: format!(
+                "bytecode version {} not supported by this runtime (max {}); upgrade mintas or recompile the source",
+                version, BYTECODE_VERSION
+            ),
+            location: SourceLocation::new(0, 0),
+        });
+    }
+    Ok(())
+}
+
 /// Load and decrypt .ms file
 pub fn load_encrypted_bytecode(path: &str, secret: Option<&str>) -> MintasResult<BytecodeProgram> {
     let mut file = fs::File::open(path)
@@ -109,8 +124,9 @@ pub fn load_encrypted_bytecode(path: &str, secret: Option<&str>) -> MintasResult
         });
     }
     
-    let mut _flags = [0u8; 4];
-    file.read_exact(&mut _flags)?;
+    let mut flags = [0u8; 4];
+    file.read_exact(&mut flags)?;
+    check_bytecode_version(flags[0])?;
     
     // Read IV
     let mut iv = [0u8; 16];
@@ -146,3 +162,28 @@ pub fn load_encrypted_bytecode(path: &str, secret: Option<&str>) -> MintasResult
     
     Ok(program)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytecode_version_is_checked_on_load() {
+        let path = std::env::temp_dir().join(format!("mintas_version_{}.ms", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_encrypted_bytecode(&BytecodeProgram::new(), path, Some("k")).unwrap();
+        let mut bytes = fs::read(path).unwrap();
+        assert_eq!(bytes[12], BYTECODE_VERSION);
+        assert!(load_encrypted_bytecode(path, Some("k")).is_ok());
+
+        bytes[12] = 0;
+        fs::write(path, &bytes).unwrap();
+        assert!(load_encrypted_bytecode(path, Some("k")).is_ok());
+
+        bytes[12] = BYTECODE_VERSION + 1;
+        fs::write(path, &bytes).unwrap();
+        let err = load_encrypted_bytecode(path, Some("k")).unwrap_err().to_string();
+        fs::remove_file(path).ok();
+        assert!(err.contains(&format!("bytecode version {} not supported by this runtime (max {})", BYTECODE_VERSION + 1, BYTECODE_VERSION)), "{}", err);
+    }
+}
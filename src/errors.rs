@@ -1,6 +1,7 @@
+use crate::builtins::Category;
 use std::fmt;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct SourceLocation {
     pub line: usize,
     pub column: usize,
@@ -93,6 +94,132 @@ impl MintasError {
             MintasError::CompileError { location, .. } => location,
         }
     }
+
+    /// Gives an error raised without a position (line 0) the one at `location`
+    pub fn or_located_at(mut self, location: &SourceLocation) -> Self {
+        let slot = match &mut self {
+            MintasError::LexerError { location, .. } => location,
+            MintasError::UnterminatedString { location } => location,
+            MintasError::InvalidEscapeSequence { location, .. } => location,
+            MintasError::InvalidCharacter { location, .. } => location,
+            MintasError::ParseError { location, .. } => location,
+            MintasError::UnexpectedToken { location, .. } => location,
+            MintasError::UnexpectedEndOfInput { location } => location,
+            MintasError::InvalidVariableName { location, .. } => location,
+            MintasError::MissingAssignment { location, .. } => location,
+            MintasError::RuntimeError { location, .. } => location,
+            MintasError::TypeError { location, .. } => location,
+            MintasError::UndefinedVariable { location, .. } => location,
+            MintasError::DivisionByZero { location } => location,
+            MintasError::InvalidAssignment { location, .. } => location,
+            MintasError::ConstantReassignment { location, .. } => location,
+            MintasError::UnknownFunction { location, .. } => location,
+            MintasError::InvalidArgumentCount { location, .. } => location,
+            MintasError::InvalidOperand { location, .. } => location,
+            MintasError::CompileError { location, .. } => location,
+        };
+        if slot.line == 0 {
+            *slot = location.clone();
+        }
+        self
+    }
+
+    /// Stable code for `mintas --explain`; never renumber an existing variant
+    pub fn code(&self) -> &'static str {
+        match self {
+            MintasError::LexerError { .. } => "E1001",
+            MintasError::UnterminatedString { .. } => "E1002",
+            MintasError::InvalidEscapeSequence { .. } => "E1003",
+            MintasError::InvalidCharacter { .. } => "E1004",
+            MintasError::ParseError { .. } => "E1005",
+            MintasError::UnexpectedToken { .. } => "E1006",
+            MintasError::UnexpectedEndOfInput { .. } => "E1007",
+            MintasError::InvalidVariableName { .. } => "E1008",
+            MintasError::MissingAssignment { .. } => "E1009",
+            MintasError::RuntimeError { .. } => "E1010",
+            MintasError::CompileError { .. } => "E1011",
+            MintasError::TypeError { .. } => "E1012",
+            MintasError::UndefinedVariable { .. } => "E1013",
+            MintasError::DivisionByZero { .. } => "E1014",
+            MintasError::InvalidAssignment { .. } => "E1015",
+            MintasError::ConstantReassignment { .. } => "E1016",
+            MintasError::UnknownFunction { .. } => "E1017",
+            MintasError::InvalidArgumentCount { .. } => "E1018",
+            MintasError::InvalidOperand { .. } => "E1019",
+        }
+    }
+}
+
+/// Long-form help printed by `mintas --explain <code>`, keyed by `MintasError::code`
+static EXPLANATIONS: &[(&str, &str)] = &[
+    ("E1001", "The lexer could not split the source into tokens.\n\n\
+        The message names what it tripped over. This usually means a stray symbol or a\n\
+        number written in a form Mintas does not accept, such as `1.2.3`."),
+    ("E1002", "A string literal was opened but never closed.\n\n\
+        Every `\"` needs a matching `\"` on the same line:\n\n    \
+        name = \"Ada     # error\n    \
+        name = \"Ada\"    # ok"),
+    ("E1003", "A backslash inside a string was followed by a character that is not an escape.\n\n\
+        Valid escapes are \\n, \\t, \\r, \\\", \\\\ and /s.N for N spaces (at most 64).\n\
+        To write a literal backslash, double it:\n\n    \
+        path = \"C:\\\\temp\""),
+    ("E1004", "The source contains a character that is not part of Mintas syntax.\n\n\
+        Look for smart quotes pasted from a word processor, or symbols such as `$` or `` ` ``\n\
+        outside a string."),
+    ("E1005", "The parser understood the tokens but not how they fit together.\n\n\
+        Common causes are a missing `:` after `if (...)`, `func f(...)` or a loop header, a\n\
+        missing `end`, or `return` written outside a block."),
+    ("E1006", "The parser expected one kind of token and found another.\n\n\
+        Check the line for a missing comma between arguments, an unclosed parenthesis, or\n\
+        a keyword used as a variable name."),
+    ("E1007", "The file ended while a construct was still open.\n\n\
+        Every `if`, `func`, loop, `try` and `switch` block must be closed with `end`, and\n\
+        every `(`, `[` and `{` with its closing bracket."),
+    ("E1008", "A name cannot be used as a variable.\n\n\
+        Variable names start with a letter or underscore and contain only letters, digits\n\
+        and underscores. Keywords such as `if` or `end` are reserved."),
+    ("E1009", "`let`, `so` or `const` was not followed by an assignment.\n\n\
+        Declarations need a value:\n\n    \
+        let count = 0\n    \
+        const LIMIT = 10"),
+    ("E1010", "Something went wrong while the program was running.\n\n\
+        The message describes the cause; a call trace follows when the error came from\n\
+        deep recursion. Run with `--debug` to see which statement was executing."),
+    ("E1011", "A program could not be compiled to bytecode or JetX machine code.\n\n\
+        Run it without `--jetx` to use the interpreter, or with `--trace-jit` to see which\n\
+        statement the compiler rejected."),
+    ("E1012", "An operation was given a value of the wrong type.\n\n\
+        For example `\"a\" - 1` or `len(5)`. Convert explicitly with `to_number` or\n\
+        `to_string`, or check the value with `typeof` first."),
+    ("E1013", "A variable was read before anything was assigned to it.\n\n\
+        Check the spelling, and that the assignment runs before the read. Variables set\n\
+        inside a function are local to it unless declared `global`."),
+    ("E1014", "A number was divided by zero with `/`, `%`, `idiv`, `mod` or `divmod`.\n\n\
+        Guard the division:\n\n    \
+        if (count != 0):\n        \
+        average = total / count\n    \
+        end"),
+    ("E1015", "The left side of `=` is not something that can be assigned to.\n\n\
+        Only variables, `obj.field` and `items[index]` can appear before `=`."),
+    ("E1016", "A constant declared with `const` was assigned again.\n\n\
+        Declare it with `let` instead if the value needs to change."),
+    ("E1017", "No builtin or user function has this name.\n\n\
+        Check the spelling (the error lists close matches), that the function is defined\n\
+        before the call, and that its module was loaded with `include`. `mintas\n\
+        --list-builtins` prints every builtin."),
+    ("E1018", "A function was called with too many or too few arguments.\n\n\
+        `mintas --list-builtins` shows each builtin's parameters; `[x]` marks an optional\n\
+        one and `x...` any number of them."),
+    ("E1019", "An operator does not support a value of this type.\n\n\
+        For example negating a string. Convert the value first, or check it with `typeof`."),
+];
+
+/// The `--explain` text for a code such as `E1014`, ignoring case
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code.trim()))
+        .map(|(_, text)| *text)
 }
 
 impl fmt::Display for MintasError {
@@ -211,22 +338,25 @@ impl From<std::io::Error> for MintasError {
 
 pub type MintasResult<T> = Result<T, MintasError>;
 
-fn write_suggestions(f: &mut fmt::Formatter, _error: &MintasError) -> fmt::Result {
-    writeln!(f, "\nFor more help, type 'help' in the REPL or check the documentation.")
+/// The message a caught panic was raised with
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
 }
 
-fn get_function_suggestions(name: &str) -> Vec<&'static str> {
-    let built_in_functions = [
-        "say", "ask", "len", "upper", "lower", "trim", "push", "pop", "insert", "remove",
-        "sort", "reverse", "contains", "find", "replace", "split", "join", "slice",
-        "keys", "values", "has", "merge", "read", "write", "append", "exists",
-        "typeof", "tostring", "tonumber"
-    ];
+fn write_suggestions(f: &mut fmt::Formatter, error: &MintasError) -> fmt::Result {
+    writeln!(f, "\nFor more help, run 'mintas --explain {}' or type 'help' in the REPL.", error.code())
+}
 
+fn get_function_suggestions(name: &str) -> Vec<&'static str> {
     let mut suggestions = Vec::new();
-    for func in &built_in_functions {
-        if levenshtein_distance(name, func) <= 2 {
-            suggestions.push(*func);
+    for builtin in crate::builtins::BUILTINS {
+        let callable = matches!(builtin.category, Category::Core | Category::Math);
+        if callable && levenshtein_distance(name, builtin.name) <= 2 {
+            suggestions.push(builtin.name);
         }
     }
 
@@ -258,3 +388,46 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
 
     matrix[len1][len2]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_error_code_has_an_explanation() {
+        let location = SourceLocation::default;
+        let text = || "x".to_string();
+        let errors = [
+            MintasError::LexerError { message: text(), location: location() },
+            MintasError::UnterminatedString { location: location() },
+            MintasError::InvalidEscapeSequence { sequence: text(), location: location() },
+            MintasError::InvalidCharacter { character: '~', location: location() },
+            MintasError::ParseError { message: text(), location: location() },
+            MintasError::UnexpectedToken { expected: text(), found: text(), location: location() },
+            MintasError::UnexpectedEndOfInput { location: location() },
+            MintasError::InvalidVariableName { name: text(), reason: text(), location: location() },
+            MintasError::MissingAssignment { keyword: text(), location: location() },
+            MintasError::RuntimeError { message: text(), location: location() },
+            MintasError::CompileError { message: text(), location: location() },
+            MintasError::TypeError { message: text(), location: location() },
+            MintasError::UndefinedVariable { name: text(), location: location() },
+            MintasError::DivisionByZero { location: location() },
+            MintasError::InvalidAssignment { message: text(), location: location() },
+            MintasError::ConstantReassignment { name: text(), location: location() },
+            MintasError::UnknownFunction { name: text(), location: location() },
+            MintasError::InvalidArgumentCount { function: text(), expected: 1, got: 2, location: location() },
+            MintasError::InvalidOperand { operation: "-".to_string(), operand_type: "string".to_string(), location: location() },
+        ];
+        for error in &errors {
+            assert!(explain(error.code()).is_some(), "{}", error.code());
+            assert!(error.to_string().contains(&format!("mintas --explain {}", error.code())));
+        }
+        // Every explanation belongs to a variant
+        let codes: Vec<&str> = EXPLANATIONS.iter().map(|(code, _)| *code).collect();
+        let used: Vec<&str> = errors.iter().map(MintasError::code).collect();
+        assert!(codes.iter().all(|code| used.contains(code)));
+        assert!(codes.windows(2).all(|pair| pair[0] < pair[1]), "codes must stay unique and ordered");
+        assert_eq!(explain("e1014"), explain("E1014"));
+        assert!(explain("E9999").is_none());
+    }
+}
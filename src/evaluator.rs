@@ -1,12 +1,41 @@
+use crate::builtins::{self, Category};
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::parser::{BinaryOp, ClassMember, Expr, UnaryOp};
+use crate::table::{HashableValue, Table};
 use std::collections::HashMap;
 use std::io::{self, Write, BufWriter, BufRead, BufReader};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::cell::RefCell;
 use std::time::{Instant, Duration};
-const MAX_RECURSION_DEPTH: usize = 1000;  
-const MAX_MEMORY_ALLOCATION: usize = 100_000_000; 
+pub const MAX_RECURSION_DEPTH: usize = 10_000;
+/// Native stack for a thread running Mintas recursion up to `max_depth` calls deep. A call
+/// takes about 6 KiB in release builds and 250 KiB in debug ones.
+pub fn interpreter_stack_size(max_depth: usize) -> usize {
+    let per_call = if cfg!(debug_assertions) { 320 * 1024 } else { 16 * 1024 };
+    max_depth.saturating_mul(per_call).max(8 * 1024 * 1024)
+}
+/// Key naming an instance's class; bookkeeping that keys(), iteration and JSON leave out
+pub(crate) const CLASS_TAG: &str = "__class__";
+/// `500ms`, `5s`, `2m` or `1h`; a bare number is in `bare_unit_ms` milliseconds
+pub fn parse_duration(text: &str, bare_unit_ms: f64) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    let (number, unit_ms) = if let Some(n) = text.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = text.strip_suffix('s') {
+        (n, 1000.0)
+    } else if let Some(n) = text.strip_suffix('m') {
+        (n, 60_000.0)
+    } else if let Some(n) = text.strip_suffix('h') {
+        (n, 3_600_000.0)
+    } else {
+        (text.as_str(), bare_unit_ms)
+    };
+    let value: f64 = number.trim().parse().ok()?;
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    Some(Duration::from_millis((value * unit_ms).round() as u64))
+}
 #[allow(dead_code)]
 const MAX_ARRAY_SIZE: usize = 1_000_000; 
 #[allow(dead_code)]
@@ -21,7 +50,12 @@ const MAX_STACK_FRAMES: usize = 1000;
 #[allow(dead_code)]
 pub struct SecurityMonitor {
     recursion_depth: usize,
-    memory_allocated: usize,
+    max_recursion_depth: usize,
+    /// What the script held at the last recount plus everything charged since
+    bytes_allocated: usize,
+    /// Bytes of strings, arrays and tables a script may hold at once, from `--max-memory`;
+    /// `None` is unlimited
+    allocation_budget: Option<usize>,
     execution_start: Instant,
     loop_iterations: usize,
     stack_frames: usize,
@@ -138,6 +172,10 @@ mod worker_module;
 #[path = "../lib/cluster/mod.rs"]
 mod cluster_module;
 #[allow(unused_imports)]
+#[cfg(feature = "regex")]
+#[path = "../lib/regex/mod.rs"]
+mod regex_module;
+#[allow(unused_imports)]
 #[path = "../lib/algorithm/mod.rs"]
 mod algorithm_module;
 #[allow(unused_imports)]
@@ -193,7 +231,6 @@ mod xdbx_module;
 pub struct Function {
     pub params: Vec<String>,
     pub body: Vec<Expr>,
-    #[allow(dead_code)]
     pub is_lambda: bool,
 }
 #[derive(Debug, Clone, PartialEq)]
@@ -207,44 +244,95 @@ pub struct Class {
     pub members: Vec<ClassMember>,
     pub inheritance: ClassInheritance,
 }
-#[derive(Debug, Clone)]
-pub struct Instance {
-    pub class_name: String,
-    pub fields: HashMap<String, Value>,
-    pub methods: HashMap<String, Function>,
+impl Class {
+    /// Name of the class this one `extends`
+    pub fn parent(&self) -> Option<&str> {
+        match &self.inheritance {
+            ClassInheritance::Extends(parent) => Some(parent),
+            ClassInheritance::None => None,
+        }
+    }
+}
+#[derive(Debug, Clone, Default)]
+struct ModuleScope {
+    functions: HashMap<String, Function>,
+    variables: HashMap<String, Value>,
+}
+/// A caller's scope, set aside while a function it called runs
+#[derive(Clone)]
+struct ScopeFrame {
+    variables: HashMap<String, Value>,
+    global_names: std::collections::HashSet<String>,
+}
+/// Everything a script can define, captured by `Evaluator::snapshot`
+#[derive(Clone)]
+pub struct EvaluatorState {
+    variables: HashMap<String, Value>,
+    constants: std::collections::HashSet<String>,
+    functions: HashMap<String, Function>,
+    native_functions: HashMap<String, NativeFunction>,
+    classes: HashMap<String, Class>,
+    module_aliases: HashMap<String, String>,
+    compiled_modules: std::collections::HashSet<String>,
+    exports: Option<std::collections::HashSet<String>>,
+    module_scopes: HashMap<String, ModuleScope>,
+    function_owners: HashMap<String, String>,
+    rng_state: u64,
 }
+/// Runtime values. Arrays, tables and instances have value semantics: assignment, passing an
+/// argument and returning all copy, so mutating one binding never changes another. `clone(x)`
+/// spells that copy out explicitly.
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
+    /// A whole number too large for `Number` to hold exactly (beyond ±2^53). Arithmetic on
+    /// whole numbers is redone in 64 bits whenever a float would round, so counters and hashes
+    /// stay exact; division, a fractional operand or i64 overflow fall back to `Number`.
+    Int(i64),
     String(String),
     Boolean(bool),
     Maybe,
+    /// No value: statement results, missing keys and arguments. Never printed by the REPL.
     Empty,
     Array(Vec<Value>),
-    Table(std::collections::HashMap<String, Value>),
+    Table(Table),
+    Bytes(Vec<u8>),
     SuperSet(Box<Value>), 
     Function(Box<Function>),
     Class(Box<Class>),
-    Instance(Box<Instance>),
     ExitSignal,
     ProceedSignal,
     ReturnSignal(Box<Value>),
+    /// `string_builder()`: a growable string that `append` extends in place, so building text
+    /// in a loop is linear rather than copying the whole string on every `s = s + piece`.
+    /// Unlike arrays and tables it's shared: assigning or passing it hands over the same buffer.
+    /// `Arc<Mutex>` rather than `Rc<RefCell>` because values cross into dew's server threads.
+    Builder(Arc<Mutex<String>>),
+    /// An explicit null from outside data (JSON `null`, SQL `NULL`). Prints as `null`.
+    /// Scripts see it as loosely equal (`==`) to `empty` but not strictly (`===`).
     Null,
 }
+/// A builder's buffer. Nothing panics while holding it, so even a poisoned lock holds whole text.
+fn lock_builder(buffer: &Mutex<String>) -> std::sync::MutexGuard<'_, String> {
+    buffer.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Value::Number(a), Value::Number(b)) => (a - b).abs() < f64::EPSILON,
+            (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) => {
+                Value::compare_numbers(self, other) == Some(std::cmp::Ordering::Equal)
+            }
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::Maybe, Value::Maybe) => true,
             (Value::Empty, Value::Empty) => true,
             (Value::Array(a), Value::Array(b)) => a == b,
             (Value::Table(a), Value::Table(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
             (Value::SuperSet(a), Value::SuperSet(b)) => a == b,
+            (Value::Builder(a), Value::Builder(b)) => Arc::ptr_eq(a, b),
             (Value::Function(_), Value::Function(_)) => false,
             (Value::Class(_), Value::Class(_)) => false,
-            (Value::Instance(a), Value::Instance(b)) => std::ptr::eq(a.as_ref(), b.as_ref()),
             (Value::ExitSignal, Value::ExitSignal) => true,
             (Value::ProceedSignal, Value::ProceedSignal) => true,
             (Value::ReturnSignal(a), Value::ReturnSignal(b)) => a == b,
@@ -257,17 +345,18 @@ impl Value {
     #[inline]
     pub fn type_name(&self) -> &'static str {
         match self {
-            Value::Number(_) => "number",
+            Value::Number(_) | Value::Int(_) => "number",
             Value::String(_) => "string",
             Value::Boolean(_) => "boolean",
             Value::Maybe => "boolean",
             Value::Empty => "empty",
             Value::Array(_) => "array",
             Value::Table(_) => "table",
+            Value::Bytes(_) => "bytes",
             Value::SuperSet(_) => "superset",
+            Value::Builder(_) => "builder",
             Value::Function(_) => "function",
             Value::Class(_) => "class",
-            Value::Instance(_) => "instance",
             Value::ExitSignal => "exit",
             Value::ProceedSignal => "proceed",
             Value::ReturnSignal(_) => "return",
@@ -281,11 +370,13 @@ impl Value {
             Value::Maybe => false,
             Value::Empty => false,
             Value::Number(n) => *n != 0.0,
+            Value::Int(n) => *n != 0,
             Value::String(s) => !s.is_empty(),
             Value::Array(arr) => !arr.is_empty(),
             Value::Table(map) => !map.is_empty(),
+            Value::Bytes(bytes) => !bytes.is_empty(),
             Value::SuperSet(val) => val.is_truthy(),
-            Value::Function(_) | Value::Class(_) | Value::Instance(_) => true,
+            Value::Function(_) | Value::Class(_) | Value::Builder(_) => true,
             Value::ExitSignal | Value::ProceedSignal | Value::ReturnSignal(_) => false,
             Value::Null => false,
         }
@@ -296,23 +387,158 @@ impl Value {
             Value::Maybe => Value::Maybe,
             Value::Empty => Value::Maybe, // Empty in conditions becomes Maybe
             Value::Number(n) => Value::Boolean(*n != 0.0),
+            Value::Int(n) => Value::Boolean(*n != 0),
             Value::String(s) => Value::Boolean(!s.is_empty()),
             Value::Array(arr) => Value::Boolean(!arr.is_empty()),
             Value::Table(map) => Value::Boolean(!map.is_empty()),
+            Value::Bytes(bytes) => Value::Boolean(!bytes.is_empty()),
             Value::SuperSet(val) => val.is_truthy_in_condition(),
-            Value::Function(_) | Value::Class(_) | Value::Instance(_) => Value::Boolean(true),
+            Value::Function(_) | Value::Class(_) | Value::Builder(_) => Value::Boolean(true),
             Value::ExitSignal | Value::ProceedSignal | Value::ReturnSignal(_) => Value::Boolean(false),
             Value::Null => Value::Boolean(false),
         }
     }
+    /// Largest magnitude below which every whole number is exact as an `f64` (2^53)
+    pub const MAX_EXACT_INT: i64 = 1 << 53;
+    pub fn from_i64(n: i64) -> Value {
+        if n.unsigned_abs() <= Value::MAX_EXACT_INT as u64 {
+            Value::Number(n as f64)
+        } else {
+            Value::Int(n)
+        }
+    }
+    /// The value as an exact integer, if it is a whole number that can be held exactly
+    pub fn exact_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= Value::MAX_EXACT_INT as f64 => Some(*n as i64),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Int(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+    /// Parses numeric text, keeping whole numbers past 2^53 exact like number literals
+    pub fn parse_number(text: &str) -> Option<Value> {
+        match text.parse::<i64>() {
+            Ok(n) => Some(Value::from_i64(n)),
+            Err(_) => text.parse::<f64>().ok().map(Value::Number),
+        }
+    }
+    /// Finishes `+ - * %` on two numbers given the float result. If an operand is an `Int` or
+    /// the float may have rounded, the operation is redone exactly in i64 when both sides are
+    /// whole; on overflow the float result stands.
+    pub fn checked_arith(op: &BinaryOp, a: &Value, b: &Value, float_result: f64) -> Value {
+        let may_round = matches!(a, Value::Int(_)) || matches!(b, Value::Int(_))
+            || float_result.abs() >= Value::MAX_EXACT_INT as f64;
+        if may_round {
+            if let (Some(x), Some(y)) = (a.exact_int(), b.exact_int()) {
+                let exact = match op {
+                    BinaryOp::Add => x.checked_add(y),
+                    BinaryOp::Subtract => x.checked_sub(y),
+                    BinaryOp::Multiply => x.checked_mul(y),
+                    BinaryOp::Modulo => x.checked_rem(y),
+                    _ => None,
+                };
+                if let Some(n) = exact {
+                    return Value::from_i64(n);
+                }
+            }
+        }
+        Value::Number(float_result)
+    }
+    /// Orders two numbers, exactly when both are whole and otherwise treating floats that
+    /// `numbers_equal` as equal. Both engines compare numbers through this.
+    pub fn compare_numbers(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+        match (a.exact_int(), b.exact_int()) {
+            (Some(x), Some(y)) => Some(x.cmp(&y)),
+            _ => {
+                let (x, y) = (a.as_f64()?, b.as_f64()?);
+                if Value::numbers_equal(x, y) {
+                    Some(std::cmp::Ordering::Equal)
+                } else {
+                    x.partial_cmp(&y)
+                }
+            }
+        }
+    }
+    /// Float equality behind `==`: within `f64::EPSILON`, so `-0.0 == 0.0`, and exact for
+    /// infinities. NaN equals nothing, not even itself.
+    pub fn numbers_equal(a: f64, b: f64) -> bool {
+        a == b || (a - b).abs() < f64::EPSILON
+    }
+    /// Structural equality behind `equals(a, b)` and `==` on arrays and tables: same shape and
+    /// equal elements all the way down. Unlike top-level `==`, values of different types never
+    /// match (`1` vs `"1"`), except that `null` and `empty` both mean no value.
+    pub fn deep_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.deep_equals(y)),
+            (Value::Table(a), Value::Table(b)) => {
+                a.len() == b.len() && a.iter().all(|(key, x)| b.get(key).is_some_and(|y| x.deep_equals(y)))
+            }
+            (Value::SuperSet(a), Value::SuperSet(b)) => a.deep_equals(b),
+            (Value::Empty | Value::Null, Value::Empty | Value::Null) => true,
+            _ => self == other,
+        }
+    }
+    /// The one ordering behind `<`, `>`, `<=`, `>=` and `sort`: numbers by value (`-0.0` and
+    /// `0.0` tie, infinities at the ends) and strings lexically. `None` for pairs with no order,
+    /// NaN included, which makes every ordering comparison against NaN false.
+    pub fn compare(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+        match (a, b) {
+            (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+            _ => Value::compare_numbers(a, b),
+        }
+    }
+    /// Total order for `sort` built on `compare`: numbers first with NaN after all of them,
+    /// then strings, then everything else in its original order
+    pub fn sort_order(a: &Value, b: &Value) -> std::cmp::Ordering {
+        fn rank(v: &Value) -> u8 {
+            match v {
+                Value::Number(n) if n.is_nan() => 1,
+                Value::Number(_) | Value::Int(_) => 0,
+                Value::String(_) => 2,
+                _ => 3,
+            }
+        }
+        Value::compare(a, b).unwrap_or_else(|| rank(a).cmp(&rank(b)))
+    }
 }
+/// A host function; `Send + Sync` so evaluator state holding one can move between threads
+pub type NativeFn = dyn Fn(&[Value]) -> MintasResult<Value> + Send + Sync;
+pub type NativeFunction = Arc<NativeFn>;
 #[derive(Clone)]
 pub struct Evaluator {
     variables: HashMap<String, Value>,
     constants: std::collections::HashSet<String>,
     functions: HashMap<String, Function>,
+    native_functions: HashMap<String, NativeFunction>,
     classes: HashMap<String, Class>,
-    this_instance: Option<Box<Instance>>,
+    module_aliases: HashMap<String, String>,
+    compiled_modules: std::collections::HashSet<String>,
+    exports: Option<std::collections::HashSet<String>>,
+    module_scopes: HashMap<String, ModuleScope>,
+    function_owners: HashMap<String, String>,
+    current_module: Option<String>,
+    call_stack: Vec<String>,
+    // Suspended scopes of the running calls, outermost (global) first; `variables` is the innermost
+    scope_stack: Vec<ScopeFrame>,
+    // Names the current function declared `global`
+    global_names: std::collections::HashSet<String>,
+    // Class defining the running method, which `super` resolves against
+    method_class: Option<String>,
+    // xorshift64* state behind random() and random_int(); never zero
+    rng_state: u64,
+    // `--time-limit`: when the run must stop, and the limit as given, for the error message
+    deadline: Option<(std::time::Instant, std::time::Duration)>,
+    // Evaluations since the clock was last read against `deadline`
+    deadline_ticks: u32,
+    // A `$invariantN` loop-hoisting slot was assigned during the current top-level statement
+    hoist_slots: bool,
     // High-performance I/O buffers
     stdout_buffer: Arc<RefCell<BufWriter<io::Stdout>>>,
     stdin_buffer: Arc<RefCell<BufReader<io::Stdin>>>,
@@ -320,14 +546,32 @@ pub struct Evaluator {
     current_getback: Option<Value>,
     // Debug mode
     debug_mode: bool,
+    debug_depth: usize,
+    debug_statement_lines: Vec<usize>,
+    debug_statement_index: usize,
+    debug_line: usize,
+    // Auto JIT mode: loops bail out once this passes so the caller can recompile with JetX
+    hot_loop_deadline: Option<Instant>,
+    hot_loop_tripped: bool,
+    // Key for `include name.ms` bytecode modules (from --secret)
+    module_secret: Option<String>,
+    // Command-line arguments after the script path, for `args()`
+    script_args: Vec<String>,
     // ULTRA-SECURE RUNTIME PROTECTION (Beyond Rust's guarantees)
     security_monitor: SecurityMonitor,
 }
+impl Default for SecurityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl SecurityMonitor {
     pub fn new() -> Self {
         Self {
             recursion_depth: 0,
-            memory_allocated: 0,
+            max_recursion_depth: MAX_RECURSION_DEPTH,
+            bytes_allocated: 0,
+            allocation_budget: None,
             execution_start: Instant::now(),
             loop_iterations: 0,
             stack_frames: 0,
@@ -335,30 +579,49 @@ impl SecurityMonitor {
         }
     }
     pub fn check_recursion_limit(&mut self) -> MintasResult<()> {
-        self.recursion_depth += 1;
-        if self.recursion_depth > MAX_RECURSION_DEPTH {
+        if self.recursion_depth >= self.max_recursion_depth {
             let violation = format!("SECURITY VIOLATION: Recursion depth {} exceeds limit {}", 
-                self.recursion_depth, MAX_RECURSION_DEPTH);
+                self.recursion_depth + 1, self.max_recursion_depth);
             self.security_violations.push(violation.clone());
             return Err(MintasError::RuntimeError {
-                message: format!("Stack overflow protection: Maximum recursion depth ({}) exceeded. This prevents infinite recursion attacks.", MAX_RECURSION_DEPTH),
+                message: format!("maximum recursion depth {} exceeded", self.max_recursion_depth),
                 location: SourceLocation::new(0, 0),
             });
         }
+        self.recursion_depth += 1;
         Ok(())
     }
-    pub fn check_memory_limit(&mut self, size: usize) -> MintasResult<()> {
-        self.memory_allocated += size;
-        if self.memory_allocated > MAX_MEMORY_ALLOCATION {
-            let violation = format!("SECURITY VIOLATION: Memory usage {} exceeds limit {}", 
-                self.memory_allocated, MAX_MEMORY_ALLOCATION);
-            self.security_violations.push(violation.clone());
-            return Err(MintasError::RuntimeError {
-                message: format!("Memory exhaustion protection: Maximum memory allocation ({} bytes) exceeded. This prevents memory bomb attacks.", MAX_MEMORY_ALLOCATION),
-                location: SourceLocation::new(0, 0),
-            });
+    pub fn set_max_recursion_depth(&mut self, depth: usize) {
+        self.max_recursion_depth = depth;
+    }
+    pub fn set_allocation_budget(&mut self, bytes: Option<usize>) {
+        self.allocation_budget = bytes;
+    }
+    pub fn has_allocation_budget(&self) -> bool {
+        self.allocation_budget.is_some()
+    }
+    /// Charges an allocation; false once the charges add up to more than the budget. Freed
+    /// values aren't credited here, so going over only means it's time for `recount_allocation`.
+    pub fn charge_allocation(&mut self, size: usize) -> bool {
+        self.bytes_allocated = self.bytes_allocated.saturating_add(size);
+        self.allocation_budget.is_none_or(|limit| self.bytes_allocated <= limit)
+    }
+    /// Restarts the count from what the script holds now plus the allocation being charged,
+    /// and fails if that is still over the budget
+    pub fn recount_allocation(&mut self, held: usize, pending: usize) -> MintasResult<()> {
+        self.bytes_allocated = held.saturating_add(pending);
+        match self.allocation_budget {
+            Some(limit) if self.bytes_allocated > limit => {
+                let violation = format!("SECURITY VIOLATION: Memory usage {} exceeds limit {}", 
+                    self.bytes_allocated, limit);
+                self.security_violations.push(violation);
+                Err(MintasError::RuntimeError {
+                    message: format!("allocation limit exceeded: the script holds more than {} bytes (--max-memory)", limit),
+                    location: SourceLocation::new(0, 0),
+                })
+            }
+            _ => Ok(()),
         }
-        Ok(())
     }
     #[allow(dead_code)]
     pub fn check_execution_time(&mut self) -> MintasResult<()> {
@@ -414,6 +677,15 @@ impl SecurityMonitor {
         }
         Ok(())
     }
+    /// Forgets what a run used, for `Evaluator::restore`; `held` is what the restored state holds
+    pub fn reset_usage(&mut self, held: usize) {
+        self.recursion_depth = 0;
+        self.bytes_allocated = held;
+        self.execution_start = Instant::now();
+        self.loop_iterations = 0;
+        self.stack_frames = 0;
+        self.security_violations.clear();
+    }
     pub fn exit_recursion(&mut self) {
         if self.recursion_depth > 0 {
             self.recursion_depth -= 1;
@@ -429,8 +701,8 @@ impl SecurityMonitor {
             - Loop Iterations: {}/{}\n\
             - Stack Frames: {}/{}\n\
             - Security Violations: {}",
-            self.recursion_depth, MAX_RECURSION_DEPTH,
-            self.memory_allocated, MAX_MEMORY_ALLOCATION,
+            self.recursion_depth, self.max_recursion_depth,
+            self.bytes_allocated, self.allocation_budget.map_or("unlimited".to_string(), |limit| limit.to_string()),
             self.execution_start.elapsed().as_millis(),
             self.loop_iterations, MAX_LOOP_ITERATIONS,
             self.stack_frames, MAX_STACK_FRAMES,
@@ -438,18 +710,45 @@ impl SecurityMonitor {
         )
     }
 }
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Evaluator {
     pub fn new() -> Self {
         Self {
             variables: HashMap::new(),
             constants: std::collections::HashSet::new(),
             functions: HashMap::new(),
+            native_functions: HashMap::new(),
             classes: HashMap::new(),
-            this_instance: None,
+            module_aliases: HashMap::new(),
+            compiled_modules: std::collections::HashSet::new(),
+            exports: None,
+            module_scopes: HashMap::new(),
+            function_owners: HashMap::new(),
+            current_module: None,
+            scope_stack: Vec::new(),
+            global_names: std::collections::HashSet::new(),
+            method_class: None,
+            rng_state: Self::seed_state(None),
+            deadline: None,
+            deadline_ticks: 0,
+            hoist_slots: false,
             stdout_buffer: Arc::new(RefCell::new(BufWriter::with_capacity(8192, io::stdout()))),
             stdin_buffer: Arc::new(RefCell::new(BufReader::with_capacity(8192, io::stdin()))),
             current_getback: None,
             debug_mode: false,
+            debug_depth: 0,
+            debug_statement_lines: Vec::new(),
+            debug_statement_index: 0,
+            debug_line: 0,
+            hot_loop_deadline: None,
+            hot_loop_tripped: false,
+            module_secret: None,
+            script_args: Vec::new(),
+            call_stack: Vec::new(),
             security_monitor: SecurityMonitor::new(),
         }
     }
@@ -457,11 +756,188 @@ impl Evaluator {
     pub fn set_debug_mode(&mut self, enabled: bool) {
         self.debug_mode = enabled;
     }
+    /// Register a host function callable from scripts; `examples/host_builtin.rs` adds `host_time()`
+    pub fn register_builtin(&mut self, name: &str, func: Box<NativeFn>) {
+        self.native_functions.insert(name.to_string(), Arc::from(func));
+    }
+    /// Calls a function value from host code, e.g. a callback handed to a module
+    pub fn call_function_value(&mut self, func: &Function, args: Vec<Value>) -> MintasResult<Value> {
+        self.invoke_function("<callback>", func, args)
+    }
+    /// Capture the script-visible state so it can be rolled back with `restore`
+    pub fn snapshot(&self) -> EvaluatorState {
+        EvaluatorState {
+            variables: self.variables.clone(),
+            constants: self.constants.clone(),
+            functions: self.functions.clone(),
+            native_functions: self.native_functions.clone(),
+            classes: self.classes.clone(),
+            module_aliases: self.module_aliases.clone(),
+            compiled_modules: self.compiled_modules.clone(),
+            exports: self.exports.clone(),
+            module_scopes: self.module_scopes.clone(),
+            function_owners: self.function_owners.clone(),
+            rng_state: self.rng_state,
+        }
+    }
+    /// Also rewinds random() to where it was at the snapshot and starts the `--max-memory` count
+    /// from what the restored variables hold
+    pub fn restore(&mut self, state: EvaluatorState) {
+        self.variables = state.variables;
+        self.constants = state.constants;
+        self.functions = state.functions;
+        self.native_functions = state.native_functions;
+        self.classes = state.classes;
+        self.module_aliases = state.module_aliases;
+        self.compiled_modules = state.compiled_modules;
+        self.exports = state.exports;
+        self.module_scopes = state.module_scopes;
+        self.function_owners = state.function_owners;
+        self.rng_state = state.rng_state;
+        self.current_module = None;
+        self.call_stack.clear();
+        self.scope_stack.clear();
+        self.global_names.clear();
+        self.method_class = None;
+        let held = self.held_bytes();
+        self.security_monitor.reset_usage(held);
+    }
+    #[allow(dead_code)]
+    pub fn is_debug_mode(&self) -> bool {
+        self.debug_mode
+    }
+    pub fn has_time_limit(&self) -> bool {
+        self.deadline.is_some()
+    }
+    pub fn set_module_secret(&mut self, secret: Option<String>) {
+        self.module_secret = secret;
+    }
+    pub fn set_script_args(&mut self, args: Vec<String>) {
+        self.script_args = args;
+    }
+    pub fn set_hot_loop_deadline(&mut self, deadline: Option<Instant>) {
+        self.hot_loop_deadline = deadline;
+        self.hot_loop_tripped = false;
+    }
+    pub fn hot_loop_tripped(&self) -> bool {
+        self.hot_loop_tripped
+    }
+    fn check_hot_loop(&mut self) -> MintasResult<()> {
+        match self.hot_loop_deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                self.hot_loop_tripped = true;
+                Err(MintasError::RuntimeError {
+                    message: "loop exceeded the JIT threshold".to_string(),
+                    location: Self::default_location(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+    #[allow(dead_code)]
+    pub fn set_statement_lines(&mut self, lines: Vec<usize>) {
+        self.debug_statement_lines = lines;
+        self.debug_statement_index = 0;
+    }
+    #[allow(dead_code)]
+    pub fn set_max_recursion_depth(&mut self, depth: usize) {
+        self.security_monitor.set_max_recursion_depth(depth);
+    }
+    pub fn set_allocation_budget(&mut self, bytes: Option<usize>) {
+        self.security_monitor.set_allocation_budget(bytes);
+    }
+    /// Starts the `--time-limit` clock: evaluation fails once `limit` of wall-clock time has passed
+    pub fn set_time_limit(&mut self, limit: Option<std::time::Duration>) {
+        // A limit too far out to represent is no limit at all
+        self.deadline = limit.and_then(|limit| Some((std::time::Instant::now().checked_add(limit)?, limit)));
+        self.deadline_ticks = 0;
+    }
+    /// Reading the clock on every evaluation is measurable, so it is read every 1024th, and before
+    /// every call, as a call may have spent any amount of time blocked in I/O or `sleep`
+    fn check_deadline(&mut self, deadline: std::time::Instant, limit: std::time::Duration, is_call: bool) -> MintasResult<()> {
+        self.deadline_ticks = self.deadline_ticks.wrapping_add(1);
+        if (is_call || self.deadline_ticks & 1023 == 0) && std::time::Instant::now() >= deadline {
+            let shown = if limit.subsec_millis() == 0 {
+                format!("{}s", limit.as_secs())
+            } else {
+                format!("{}ms", limit.as_millis())
+            };
+            return Err(MintasError::RuntimeError {
+                message: format!("time limit exceeded: the script ran longer than {} (--time-limit)", shown),
+                location: Self::default_location(),
+            });
+        }
+        Ok(())
+    }
+    /// Seeds random() and random_int() (`--seed`); None reseeds from the clock
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.rng_state = Self::seed_state(seed);
+    }
+    fn seed_state(seed: Option<u64>) -> u64 {
+        let seed = seed.unwrap_or_else(|| {
+            let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+            nanos ^ (std::process::id() as u64).rotate_left(32)
+        });
+        // splitmix64 spreads small seeds like 1, 2, 3 across the whole state
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z }
+    }
+    /// A float in [0, 1) from the xorshift64* generator
+    fn next_random(&mut self) -> f64 {
+        let mut x = self.rng_state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.rng_state = x;
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// Counts strings, arrays and tables built by operators (`s + t`, `a + b`) or stored into a
+    /// table against `--max-memory`. Literals and array mutators are counted where they are built.
+    fn track_allocation(&mut self, value: &Value) -> MintasResult<()> {
+        if self.security_monitor.has_allocation_budget() && matches!(value, Value::String(_) | Value::Array(_) | Value::Table(_)) {
+            self.charge_allocation(Self::estimate_value_size(value))?;
+        }
+        Ok(())
+    }
     fn check_recursion_limit(&mut self) -> MintasResult<()> {
-        self.security_monitor.check_recursion_limit()
+        self.security_monitor.check_recursion_limit().map_err(|err| match err {
+            MintasError::RuntimeError { message, location } => {
+                // Only the innermost frames are useful when a recursion runs away
+                let start = self.call_stack.len().saturating_sub(5);
+                let trace = self.call_stack[start..].join(" -> ");
+                MintasError::RuntimeError {
+                    message: format!("{}\n  call trace (most recent last): {}{}",
+                        message, if start > 0 { "... -> " } else { "" }, trace),
+                    location,
+                }
+            }
+            other => other,
+        })
+    }
+    fn charge_allocation(&mut self, additional_size: usize) -> MintasResult<()> {
+        if self.security_monitor.charge_allocation(additional_size) {
+            return Ok(());
+        }
+        let held = self.held_bytes();
+        self.security_monitor.recount_allocation(held, additional_size)
+    }
+    /// Estimated size of every value the script can still reach: its variables, those of the
+    /// callers waiting on it and those of its modules
+    fn held_bytes(&self) -> usize {
+        self.variables.values()
+            .chain(self.scope_stack.iter().flat_map(|frame| frame.variables.values()))
+            .chain(self.module_scopes.values().flat_map(|scope| scope.variables.values()))
+            .map(Self::estimate_value_size)
+            .sum()
     }
-    fn check_memory_limit(&mut self, additional_size: usize) -> MintasResult<()> {
-        self.security_monitor.check_memory_limit(additional_size)
+    fn estimate_key_size(key: &HashableValue) -> usize {
+        match key {
+            HashableValue::String(s) => s.len() * 2,
+            _ => 8,
+        }
     }
     fn estimate_value_size(value: &Value) -> usize {
         match value {
@@ -478,11 +954,12 @@ impl Evaluator {
             Value::Table(map) => {
                 let mut size = 48; 
                 for (key, value) in map {
-                    size += key.len() * 2; 
+                    size += Self::estimate_key_size(key);
                     size += Self::estimate_value_size(value);
                 }
                 size
             }
+            Value::Builder(buffer) => 16 + lock_builder(buffer).len() * 2,
             _ => 16, 
         }
     }
@@ -497,7 +974,40 @@ impl Evaluator {
     pub fn get_variables(&self) -> &HashMap<String, Value> {
         &self.variables
     }
-    #[allow(dead_code)]
+    /// True once a function or class is defined. Loop hoisting only sees the program it is
+    /// given, so it can't account for a `global` or a method brought in by an earlier snippet.
+    pub fn holds_definitions(&self) -> bool {
+        !self.functions.is_empty() || !self.classes.is_empty()
+    }
+    /// True when a variable holds a string builder, directly or inside an array or table.
+    /// Builders are shared, so loop hoisting must not cache `len()` while one exists.
+    pub fn holds_builder(&self) -> bool {
+        fn contains_builder(value: &Value) -> bool {
+            match value {
+                Value::Builder(_) => true,
+                Value::Array(items) => items.iter().any(contains_builder),
+                Value::Table(table) => table.values().any(contains_builder),
+                Value::SuperSet(inner) => contains_builder(inner),
+                _ => false,
+            }
+        }
+        self.variables.values().any(contains_builder)
+            || self.module_scopes.values().any(|scope| scope.variables.values().any(contains_builder))
+    }
+    /// Every variable, function and class name currently defined, for REPL completion
+    pub fn defined_names(&self) -> Vec<String> {
+        self.variables.keys().chain(self.functions.keys()).chain(self.classes.keys()).cloned().collect()
+    }
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+    /// A script's own `range`, `min`, `max` or `count` replaces the builtin of that name
+    fn shadows_builtin(&self, name: &str) -> bool {
+        matches!(name, "range" | "min" | "max" | "count") && self.functions.contains_key(name)
+    }
+    pub fn has_class(&self, name: &str) -> bool {
+        self.classes.contains_key(name)
+    }
     pub fn set_variable(&mut self, name: String, value: Value) {
         self.variables.insert(name, value);
     }
@@ -505,7 +1015,22 @@ impl Evaluator {
     pub fn get_constants(&self) -> &std::collections::HashSet<String> {
         &self.constants
     }
-    #[allow(dead_code)]
+    /// Evaluates a statement of a script, REPL line or module. A `return` only unwinds as far as
+    /// the enclosing function call, so one that reaches this level is an error.
+    pub fn eval_top_level(&mut self, stmt: &Expr) -> MintasResult<Value> {
+        let result = self.eval(stmt);
+        // Hoisting slots only matter while their loop runs; keep them out of the user's variables
+        if std::mem::take(&mut self.hoist_slots) {
+            self.variables.retain(|name, _| !name.starts_with('$'));
+        }
+        match result? {
+            Value::ReturnSignal(_) => Err(MintasError::RuntimeError {
+                message: "'return' used outside of a function".to_string(),
+                location: Self::default_location(),
+            }),
+            value => Ok(value),
+        }
+    }
     pub fn eval_line(&mut self, line: &str) -> MintasResult<Value> {
         let mut lexer = crate::lexer::Lexer::new(line);
         let tokens = lexer.tokenize()?;
@@ -516,7 +1041,7 @@ impl Evaluator {
         let statements = parser.parse()?;
         let mut last_val = Value::Empty;
         for stmt in statements {
-            last_val = self.eval(&stmt)?;
+            last_val = self.eval_top_level(&stmt)?;
         }
         Ok(last_val)
     }
@@ -674,6 +1199,9 @@ impl Evaluator {
             }
             _ => {}
         }
+        if let Some(stem) = module_name.strip_suffix(".ms") {
+            return self.load_bytecode_module(stem, alias);
+        }
         let module_paths = vec![
             format!("{}.as", module_name),
             format!("lib/{}.as", module_name),
@@ -689,6 +1217,8 @@ impl Evaluator {
         match module_content {
             Some(content) => {
                 let mut module_evaluator = Evaluator::new();
+                module_evaluator.module_secret = self.module_secret.clone();
+                module_evaluator.script_args = self.script_args.clone();
                 let mut lexer = crate::lexer::Lexer::new(&content);
                 let tokens = lexer.tokenize().map_err(|e| {
                     MintasError::RuntimeError {
@@ -705,7 +1235,7 @@ impl Evaluator {
                         }
                     })?;
                     for stmt in statements {
-                        module_evaluator.eval(&stmt).map_err(|e| {
+                        module_evaluator.eval_top_level(&stmt).map_err(|e| {
                             MintasError::RuntimeError {
                                 message: format!("Error executing module '{}': {}", module_name, e),
                             location: Self::default_location(),
@@ -715,15 +1245,30 @@ impl Evaluator {
                 }
                 let use_prefix = alias.is_some();
                 let prefix = alias.unwrap_or("");
+                let is_exported = |name: &str| {
+                    module_evaluator.exports.as_ref().is_none_or(|names| names.contains(name))
+                };
+                let mut private_scope = ModuleScope::default();
                 for (func_name, func) in &module_evaluator.functions {
+                    if !is_exported(func_name) {
+                        private_scope.functions.insert(func_name.clone(), func.clone());
+                        continue;
+                    }
                     let full_name = if use_prefix && !prefix.is_empty() {
                         format!("{}.{}", prefix, func_name)
                     } else {
                         func_name.clone()
                     };
+                    if module_evaluator.exports.is_some() {
+                        self.function_owners.insert(full_name.clone(), module_name.to_string());
+                    }
                     self.functions.insert(full_name, func.clone());
                 }
                 for (var_name, var_value) in &module_evaluator.variables {
+                    if !is_exported(var_name) {
+                        private_scope.variables.insert(var_name.clone(), var_value.clone());
+                        continue;
+                    }
                     let full_name = if use_prefix && !prefix.is_empty() {
                         format!("{}.{}", prefix, var_name)
                     } else {
@@ -731,6 +1276,9 @@ impl Evaluator {
                     };
                     self.variables.insert(full_name, var_value.clone());
                 }
+                if module_evaluator.exports.is_some() {
+                    self.module_scopes.insert(module_name.to_string(), private_scope);
+                }
                 Ok(())
             }
             None => Err(MintasError::RuntimeError {
@@ -739,8 +1287,57 @@ impl Evaluator {
             }),
         }
     }
+    /// Loads `include name.ms`: encrypted bytecode decrypted with --secret. The bytecode
+    /// compiler has no user functions, so only top-level variables can be exported, and when
+    /// the module uses `export` only the names it lists are.
+    fn load_bytecode_module(&mut self, stem: &str, alias: Option<&str>) -> MintasResult<()> {
+        let file_name = format!("{}.ms", stem);
+        let path = [file_name.clone(), format!("lib/{}", file_name)]
+            .into_iter()
+            .find(|p| std::path::Path::new(p).is_file())
+            .ok_or_else(|| MintasError::RuntimeError {
+                message: format!("Module '{}' not found. Searched in current directory and lib/", file_name),
+                location: Self::default_location(),
+            })?;
+        let secret = self.module_secret.as_deref();
+        let program = crate::encryption::load_encrypted_bytecode(&path, secret).map_err(|e| {
+            let message = e.to_string();
+            let bad_key = ["Decryption failed", "Invalid UTF-8", "Failed to deserialize"]
+                .iter()
+                .any(|m| message.contains(m));
+            let message = match (bad_key, secret) {
+                (true, None) => format!(
+                    "Cannot decrypt module '{}': it was compiled with a secret; pass --secret <key>",
+                    file_name
+                ),
+                (true, Some(_)) => format!(
+                    "Cannot decrypt module '{}': --secret does not match the key it was compiled with",
+                    file_name
+                ),
+                (false, _) => format!("Error loading module '{}': {}", file_name, message),
+            };
+            MintasError::RuntimeError { message, location: Self::default_location() }
+        })?;
+        let mut vm = crate::vm::BytecodeVM::new(program);
+        vm.execute().map_err(|e| MintasError::RuntimeError {
+            message: format!("Error executing module '{}': {}", file_name, e),
+            location: Self::default_location(),
+        })?;
+        for (var_name, var_value) in vm.into_exports() {
+            let full_name = match alias {
+                Some(prefix) if !prefix.is_empty() => format!("{}.{}", prefix, var_name),
+                _ => var_name,
+            };
+            self.variables.insert(full_name, var_value);
+        }
+        Ok(())
+    }
     fn load_compiled_module(&mut self, module_name: &str, alias: Option<&str>) -> MintasResult<()> {
         let prefix = alias.unwrap_or(module_name);
+        self.compiled_modules.insert(prefix.to_string());
+        if let Some(alias_name) = alias {
+            self.module_aliases.insert(alias_name.to_string(), module_name.to_string());
+        }
         match module_name {
             "math" => {
                 let math_functions = vec![
@@ -1261,37 +1858,171 @@ impl Evaluator {
         SourceLocation::new(0, 0)
     }
     pub fn eval(&mut self, expr: &Expr) -> MintasResult<Value> {
+        if let Some((deadline, limit)) = self.deadline {
+            let is_call = matches!(expr, Expr::Call { .. } | Expr::MethodCall { .. });
+            self.check_deadline(deadline, limit, is_call)?;
+        }
+        if self.debug_mode && self.debug_depth == 0 {
+            return self.eval_traced(expr);
+        }
+        self.eval_expr(expr)
+    }
+    fn eval_traced(&mut self, expr: &Expr) -> MintasResult<Value> {
+        self.debug_line = self.debug_statement_lines.get(self.debug_statement_index).copied().unwrap_or(0);
+        self.debug_statement_index += 1;
+        eprintln!("[debug] line {}: {}", self.debug_line, Self::expr_kind(expr));
+        self.debug_depth += 1;
+        let result = self.eval_expr(expr);
+        self.debug_depth -= 1;
+        match &result {
+            Ok(val) => eprintln!("[debug]   => {}", self.value_to_string(val)),
+            Err(e) => eprintln!("[debug]   !! {}", e.to_string().lines().next().unwrap_or("")),
+        }
+        result
+    }
+    fn trace_iteration(&self, kind: &str, iteration: usize, val: &Value) {
+        eprintln!("[debug] line {}: {} iteration {} => {}", self.debug_line, kind, iteration, self.value_to_string(val));
+    }
+    /// The variant name, as `--debug` and `--ast-stats` show it
+    pub fn expr_kind(expr: &Expr) -> &'static str {
+        match expr {
+            Expr::Number(_) => "Number",
+            Expr::Int(_) => "Int",
+            Expr::String(_) => "String",
+            Expr::RawString(_) => "RawString",
+            Expr::Boolean(_) => "Boolean",
+            Expr::Maybe => "Maybe",
+            Expr::Empty => "Empty",
+            Expr::Array(_) => "Array",
+            Expr::Table(_) => "Table",
+            Expr::SuperSet(_) => "SuperSet",
+            Expr::Spread(_) => "Spread",
+            Expr::Variable(_) => "Variable",
+            Expr::BinaryOp { .. } => "BinaryOp",
+            Expr::UnaryOp { .. } => "UnaryOp",
+            Expr::Assign { .. } => "Assign",
+            Expr::MultiAssign { .. } => "MultiAssign",
+            Expr::CompoundAssign { .. } => "CompoundAssign",
+            Expr::Global { .. } => "Global",
+            Expr::Call { .. } => "Call",
+            Expr::IfExpr { .. } => "IfExpr",
+            Expr::WhileLoop { .. } => "WhileLoop",
+            Expr::ForLoop { .. } => "ForLoop",
+            Expr::ForInLoop { .. } => "ForInLoop",
+            Expr::Exit => "Exit",
+            Expr::Proceed => "Proceed",
+            Expr::MethodCall { .. } => "MethodCall",
+            Expr::Index { .. } => "Index",
+            Expr::Ternary { .. } => "Ternary",
+            Expr::Function { .. } => "Function",
+            Expr::Return { .. } => "Return",
+            Expr::Class { .. } => "Class",
+            Expr::New { .. } => "New",
+            Expr::This => "This",
+            Expr::Super => "Super",
+            Expr::Property { .. } => "Property",
+            Expr::PropertyAssign { .. } => "PropertyAssign",
+            Expr::TryCatch { .. } => "TryCatch",
+            Expr::Cond { .. } => "Cond",
+            Expr::Follow { .. } => "Follow",
+            Expr::Include { .. } => "Include",
+            Expr::Export { .. } => "Export",
+            Expr::Task { .. } => "Task",
+            Expr::Switch { .. } => "Switch",
+            Expr::TypeMatch { .. } => "TypeMatch",
+            Expr::SmartCondition { .. } => "SmartCondition",
+            Expr::SmartLoop { .. } => "SmartLoop",
+            Expr::DewRoute { .. } => "DewRoute",
+            Expr::DewServe { .. } => "DewServe",
+            Expr::DewReturn { .. } => "DewReturn",
+            Expr::Getback => "Getback",
+            Expr::DewBefore { .. } => "DewBefore",
+            Expr::DewAfter { .. } => "DewAfter",
+            Expr::DewUse { .. } => "DewUse",
+            Expr::DewCatch { .. } => "DewCatch",
+            Expr::DewGroup { .. } => "DewGroup",
+            Expr::DewStatic { .. } => "DewStatic",
+            Expr::DewRouteValidated { .. } => "DewRouteValidated",
+            Expr::DewConfig { .. } => "DewConfig",
+            Expr::DewDatabase { .. } => "DewDatabase",
+            Expr::DewSession { .. } => "DewSession",
+            Expr::DewRateLimit { .. } => "DewRateLimit",
+        }
+    }
+    fn eval_expr(&mut self, expr: &Expr) -> MintasResult<Value> {
         match expr {
             Expr::Number(n) => Ok(Value::Number(*n)),
+            Expr::Int(n) => Ok(Value::from_i64(*n)),
             Expr::String(s) => {
                 Ok(Value::String(self.interpolate_string(s)?))
             }
+            Expr::RawString(s) => Ok(Value::String(s.clone())),
             Expr::Boolean(b) => Ok(Value::Boolean(*b)),
             Expr::Maybe => Ok(Value::Maybe),
             Expr::Empty => Ok(Value::Empty),
             Expr::Array(elements) => {
                 let mut values = Vec::new();
                 for elem in elements {
+                    if let Expr::Spread(inner) = elem {
+                        match self.eval(inner)? {
+                            Value::Array(items) => {
+                                for value in items {
+                                    let element_size = Self::estimate_value_size(&value);
+                                    self.charge_allocation(element_size)?;
+                                    values.push(value);
+                                }
+                            }
+                            other => {
+                                return Err(MintasError::TypeError {
+                                    message: format!("Cannot spread {} into an array literal", other.type_name()),
+                                    location: Self::default_location(),
+                                });
+                            }
+                        }
+                        continue;
+                    }
                     let value = self.eval(elem)?;
                     let element_size = Self::estimate_value_size(&value);
-                    self.check_memory_limit(element_size)?;
+                    self.charge_allocation(element_size)?;
                     values.push(value);
                 }
                 let array_value = Value::Array(values);
                 Ok(array_value)
             }
             Expr::Table(pairs) => {
-                let mut map = std::collections::HashMap::new();
+                let mut map = Table::new();
                 for (key, value_expr) in pairs {
+                    if let Expr::Spread(inner) = value_expr {
+                        match self.eval(inner)? {
+                            Value::Table(entries) => {
+                                for (entry_key, value) in entries {
+                                    let value_size = Self::estimate_value_size(&value);
+                                    self.charge_allocation(Self::estimate_key_size(&entry_key) + value_size)?;
+                                    map.insert(entry_key, value);
+                                }
+                            }
+                            other => {
+                                return Err(MintasError::TypeError {
+                                    message: format!("Cannot spread {} into a table literal", other.type_name()),
+                                    location: Self::default_location(),
+                                });
+                            }
+                        }
+                        continue;
+                    }
                     let value = self.eval(value_expr)?;
                     let key_size = key.len() * 2; 
                     let value_size = Self::estimate_value_size(&value);
-                    self.check_memory_limit(key_size + value_size)?;
+                    self.charge_allocation(key_size + value_size)?;
                     map.insert(key.clone(), value);
                 }
                 let table_value = Value::Table(map);
                 Ok(table_value)
             }
+            Expr::Spread(_) => Err(MintasError::RuntimeError {
+                message: "Spread '...' is only allowed inside array or table literals".to_string(),
+                location: Self::default_location(),
+            }),
             Expr::SuperSet(inner_expr) => {
                 let inner_value = self.eval(inner_expr)?;
                 Ok(Value::SuperSet(Box::new(inner_value)))
@@ -1299,6 +2030,7 @@ impl Evaluator {
             Expr::Variable(name) => {
                 self.variables
                     .get(name)
+                    .or_else(|| self.current_module_scope().and_then(|scope| scope.variables.get(name)))
                     .cloned()
                     .ok_or_else(|| MintasError::UndefinedVariable {
                         name: name.clone(),
@@ -1322,6 +2054,7 @@ impl Evaluator {
                 if let Value::Function(func) = &val {
                     self.functions.insert(name.clone(), func.as_ref().clone());
                 }
+                self.hoist_slots |= name.starts_with('$');
                 self.variables.insert(name.clone(), val.clone());
                 if *is_const {
                     self.constants.insert(name.clone());
@@ -1349,6 +2082,16 @@ impl Evaluator {
                 }
                 Ok(last_val)
             }
+            Expr::Global { names } => {
+                // At top level every name is already global
+                if !self.scope_stack.is_empty() {
+                    for name in names {
+                        self.global_names.insert(name.clone());
+                    }
+                    self.load_globals();
+                }
+                Ok(Value::Empty)
+            }
             Expr::CompoundAssign { name, op, value } => {
                 if self.constants.contains(name) {
                     return Err(MintasError::ConstantReassignment {
@@ -1363,12 +2106,12 @@ impl Evaluator {
                     }
                 })?;
                 let right_val = self.eval(value)?;
-                let result = self.apply_binary_op(op, &current, &right_val)?;
+                let result = self.apply_tracked_binary_op(op, &current, &right_val)?;
                 self.variables.insert(name.clone(), result.clone());
                 Ok(result)
             }
-            Expr::Call { name, args } => {
-                self.eval_call(name, args)
+            Expr::Call { name, args, location } => {
+                self.eval_call(name, args).map_err(|err| err.or_located_at(location))
             }
             Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
                 let cond_val = self.eval(condition)?;
@@ -1400,8 +2143,10 @@ impl Evaluator {
                     }
                 }
             }
-            Expr::WhileLoop { condition, body } => {
+            Expr::WhileLoop { condition, body, else_branch } => {
                 let mut result = Value::Empty;
+                let mut iteration = 0;
+                let mut ran = false;
                 loop {
                     let cond_val = self.eval(condition)?;
                     let cond_result = cond_val.is_truthy_in_condition();
@@ -1410,8 +2155,12 @@ impl Evaluator {
                         Value::Boolean(true) => {} 
                         _ => break, 
                     }
+                    ran = true;
                     for stmt in body {
                         let val = self.eval(stmt)?;
+                        if matches!(val, Value::ReturnSignal(_)) {
+                            return Ok(val);
+                        }
                         if matches!(val, Value::ExitSignal) {
                             return Ok(result);
                         }
@@ -1420,12 +2169,25 @@ impl Evaluator {
                         }
                         result = val;
                     }
+                    if self.debug_mode {
+                        iteration += 1;
+                        self.trace_iteration("while", iteration, &result);
+                    }
+                    if self.hot_loop_deadline.is_some() {
+                        self.check_hot_loop()?;
+                    }
+                }
+                if !ran {
+                    if let Some(else_body) = else_branch {
+                        return self.eval_block(else_body);
+                    }
                 }
                 Ok(result)
             }
             Expr::ForLoop { var, start, end, body } => {
                 let start_val = match self.eval(start)? {
                     Value::Number(n) => n as i64,
+                    Value::Int(n) => n,
                     _ => return Err(MintasError::TypeError {
                         message: "For loop start must be a number".to_string(),
                         location: Self::default_location(),
@@ -1433,23 +2195,23 @@ impl Evaluator {
                 };
                 let end_val = match self.eval(end)? {
                     Value::Number(n) => n as i64,
+                    Value::Int(n) => n,
                     _ => return Err(MintasError::TypeError {
                         message: "For loop end must be a number".to_string(),
                         location: Self::default_location(),
                     }),
                 };
                 let mut result = Value::Empty;
-                let ascending = start_val <= end_val;
+                let step = if start_val <= end_val { 1 } else { -1 };
                 let mut i = start_val;
                 'outer: loop {
-                    if ascending {
-                        if i > end_val { break; }
-                    } else {
-                        if i < end_val { break; }
-                    }
-                    self.variables.insert(var.clone(), Value::Number(i as f64));
+                    // Counts past 2^53 are held as Int, so they stay exact
+                    self.variables.insert(var.clone(), Value::from_i64(i));
                     for stmt in body {
                         let val = self.eval(stmt)?;
+                        if matches!(val, Value::ReturnSignal(_)) {
+                            return Ok(val);
+                        }
                         if matches!(val, Value::ExitSignal) {
                             break 'outer;
                         }
@@ -1458,26 +2220,74 @@ impl Evaluator {
                         }
                         result = val;
                     }
-                    if ascending { i += 1; } else { i -= 1; }
+                    if self.debug_mode {
+                        self.trace_iteration(&format!("for {} = {}", var, i), i.abs_diff(start_val) as usize + 1, &result);
+                    }
+                    if self.hot_loop_deadline.is_some() {
+                        self.check_hot_loop()?;
+                    }
+                    // Stopping on the last value, rather than stepping past it, can't overflow
+                    if i == end_val {
+                        break;
+                    }
+                    i += step;
                 }
                 Ok(result)
             }
-            Expr::ForInLoop { var, iterable, body } => {
-                let iter_val = self.eval(iterable)?;
-                let items: Vec<Value> = match iter_val {
-                    Value::Array(arr) => arr,
-                    Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
-                    Value::Table(map) => map.keys().map(|k| Value::String(k.clone())).collect(),
-                    _ => return Err(MintasError::TypeError {
-                        message: "For-in loop requires array, string, or table".to_string(),
-                        location: Self::default_location(),
-                    }),
+            Expr::ForInLoop { vars, iterable, body, else_branch } => {
+                // `for x in range(...)` counts through the range instead of building its array;
+                // the bounds are evaluated once, before the first iteration. Table entries carry
+                // their value alongside the key.
+                let mut max_vars = 2;
+                let items: Box<dyn ExactSizeIterator<Item = (Value, Option<Value>)>> = match iterable.as_ref() {
+                    Expr::Call { name, args, .. } if name == "range" && !self.shadows_builtin(name) => {
+                        let (start, step, len) = self.range_args(args)?;
+                        Box::new((0..len).map(move |k| (Value::Number(start + k as f64 * step), None)))
+                    }
+                    _ => match self.eval(iterable)? {
+                        Value::Array(arr) => Box::new(arr.into_iter().map(|v| (v, None))),
+                        Value::String(s) => Box::new(s.chars().map(|c| (Value::String(c.to_string()), None)).collect::<Vec<_>>().into_iter()),
+                        Value::Table(map) => {
+                            max_vars = 3;
+                            let mut entries: Vec<(HashableValue, Value)> = map.into_iter()
+                                .filter(|(k, _)| k.as_str() != Some(CLASS_TAG))
+                                .collect();
+                            entries.sort_by(|a, b| a.0.cmp(&b.0));
+                            Box::new(entries.into_iter().map(|(k, v)| (k.to_value(), Some(v))))
+                        }
+                        _ => return Err(MintasError::TypeError {
+                            message: "For-in loop requires array, string, or table".to_string(),
+                            location: Self::default_location(),
+                        }),
+                    },
                 };
+                if vars.len() > max_vars {
+                    return Err(MintasError::RuntimeError {
+                        message: format!(
+                            "For-in over an array, string or range binds at most 2 variables (value, index), got {}",
+                            vars.len()
+                        ),
+                        location: Self::default_location(),
+                    });
+                }
+                if items.len() == 0 {
+                    if let Some(else_body) = else_branch {
+                        return self.eval_block(else_body);
+                    }
+                }
                 let mut result = Value::Empty;
-                'outer: for item in items {
-                    self.variables.insert(var.clone(), item);
+                'outer: for (iteration, (item, value)) in items.enumerate() {
+                    let mut bound = vec![item];
+                    bound.extend(value);
+                    bound.push(Value::Number(iteration as f64));
+                    for (var, val) in vars.iter().zip(bound) {
+                        self.variables.insert(var.clone(), val);
+                    }
                     for stmt in body {
                         let val = self.eval(stmt)?;
+                        if matches!(val, Value::ReturnSignal(_)) {
+                            return Ok(val);
+                        }
                         if matches!(val, Value::ExitSignal) {
                             break 'outer;
                         }
@@ -1486,6 +2296,9 @@ impl Evaluator {
                         }
                         result = val;
                     }
+                    if self.debug_mode {
+                        self.trace_iteration(&format!("for {} in", vars.join(", ")), iteration + 1, &result);
+                    }
                 }
                 Ok(result)
             }
@@ -1512,8 +2325,9 @@ impl Evaluator {
                             location: Self::default_location(),
                         })
                     }
-                    (Value::Table(map), Value::String(key)) => {
-                        map.get(key).cloned().ok_or_else(|| MintasError::RuntimeError {
+                    (Value::Table(map), key) => {
+                        let key = HashableValue::from_value(key)?;
+                        map.get(&key).cloned().ok_or_else(|| MintasError::RuntimeError {
                             message: format!("Key '{}' not found", key),
                             location: Self::default_location(),
                         })
@@ -1541,20 +2355,25 @@ impl Evaluator {
                     self.eval(else_branch)
                 }
             }
+            // `goto (i = n times):` runs the body n times with i = 0..n-1. Fractional counts
+            // truncate and negative counts run nothing.
             Expr::SmartLoop { var, count, body } => {
-                let count_val = self.eval(count)?;
-                let count_num = match count_val {
-                    Value::Number(n) => n as i64,
+                let count_num = match self.eval(count)? {
+                    Value::Number(n) if n.is_finite() => n.max(0.0) as i64,
+                    Value::Int(n) => n.max(0),
                     _ => return Err(MintasError::RuntimeError {
-                        message: "Loop count must be a number".to_string(),
+                        message: "Loop count must be a finite number".to_string(),
                         location: Self::default_location(),
                     }),
                 };
                 let mut result = Value::Empty;
                 'outer: for i in 0..count_num {
-                    self.variables.insert(var.clone(), Value::Number(i as f64));
+                    self.variables.insert(var.clone(), Value::from_i64(i));
                     for stmt in body {
                         let val = self.eval(stmt)?;
+                        if matches!(val, Value::ReturnSignal(_)) {
+                            return Ok(val);
+                        }
                         if matches!(val, Value::ExitSignal) {
                             break 'outer;
                         }
@@ -1566,7 +2385,7 @@ impl Evaluator {
                 }
                 Ok(result)
             }
-            Expr::Function { name, params, body, is_lambda } => {
+            Expr::Function { name, params, body, is_lambda, .. } => {
                 let func = Function {
                     params: params.clone(),
                     body: body.clone(),
@@ -1594,106 +2413,44 @@ impl Evaluator {
                 self.classes.insert(name.clone(), class);
                 Ok(class_value)
             }
-            Expr::New { class_name, args: _ } => {
-                let class = self.classes.get(class_name.as_str()).ok_or_else(|| {
-                    MintasError::RuntimeError {
-                        message: format!("Class '{}' not found", class_name),
-                        location: Self::default_location(),
-                    }
-                })?.clone();
-                let mut instance = Instance {
-                    class_name: class_name.clone(),
-                    fields: HashMap::new(),
-                    methods: HashMap::new(),
-                };
-                if let ClassInheritance::Extends(parent_name) = &class.inheritance {
-                    let parent_members = self.classes.get(parent_name).map(|pc| pc.members.clone()).unwrap_or_default();
-                    for member in parent_members {
-                        match member {
-                            ClassMember::Property { name, is_public: _, initial_value } => {
-                                    let value = if let Some(ref init) = initial_value {
-                                        self.eval(init)?
-                                    } else {
-                                        Value::Empty
-                                    };
-                                instance.fields.insert(name.clone(), value);
-                            }
-                            ClassMember::Method { name, is_public: _, params, body } => {
-                                let func = Function {
-                                    params: params.clone(),
-                                    body: body.clone(),
-                                    is_lambda: false,
-                                };
-                                instance.methods.insert(name.clone(), func);
-                            }
-                        }
-                    }
-                }
-                for member in &class.members {
-                    match member {
-                        ClassMember::Property { name, is_public: _, initial_value } => {
-                                    let value = if let Some(ref init) = initial_value {
-                                        self.eval(init)?
-                                    } else {
-                                        Value::Empty
-                                    };
-                            instance.fields.insert(name.clone(), value);
-                        }
-                        ClassMember::Method { name, is_public: _, params, body } => {
-                            let func = Function {
-                                params: params.clone(),
-                                body: body.clone(),
-                                is_lambda: false,
-                            };
-                            instance.methods.insert(name.clone(), func);
-                        }
-                    }
-                }
-                let mut instance = Instance {
-                    class_name: class_name.clone(),
-                    fields: HashMap::new(),
-                    methods: HashMap::new(),
-                };
-                let old_this = self.this_instance.take();
-                self.this_instance = Some(Box::new(instance.clone()));
-                for member in &class.members {
-                    match member {
-                        ClassMember::Property { name, is_public: _, initial_value } => {
-                                    let value = if let Some(ref init) = initial_value {
-                                        self.eval(init)?
-                                    } else {
-                                        Value::Empty
-                                    };
-                            instance.fields.insert(name.clone(), value);
-                        }
-                        ClassMember::Method { name, is_public: _, params, body } => {
-                            let func = Function {
-                                params: params.clone(),
-                                body: body.clone(),
-                                is_lambda: false,
-                            };
-                            instance.methods.insert(name.clone(), func);
-                        }
-                    }
+            Expr::New { class_name, args } => {
+                let mut arg_values = Vec::new();
+                for arg in args {
+                    arg_values.push(self.eval(arg)?);
                 }
-                self.this_instance = Some(Box::new(instance.clone()));
-                let result = Ok(Value::Instance(Box::new(instance)));
-                self.this_instance = old_this;
-                result
+                self.instantiate(class_name, arg_values)
             }
             Expr::This => {
-                if let Some(instance) = &self.this_instance {
-                    Ok(Value::Instance(Box::new(instance.as_ref().clone())))
-                } else {
-                    Err(MintasError::RuntimeError {
+                match self.variables.get("self") {
+                    Some(instance @ Value::Table(map)) if map.contains_key(CLASS_TAG) => Ok(instance.clone()),
+                    _ => Err(MintasError::RuntimeError {
                         message: "'this' can only be used inside class methods".to_string(),
                         location: Self::default_location(),
-                    })
+                    }),
                 }
             }
+            Expr::Super => Err(MintasError::RuntimeError {
+                message: "'super' can only be used to call a method, as in super.method()".to_string(),
+                location: Self::default_location(),
+            }),
             Expr::Property { object, property } => {
                 if let Expr::Variable(var_name) = &**object {
-                    if var_name == "math" {
+                    // Hot path for `point.x`: read the field straight out of the stored table (an
+                    // instance is one too). Evaluating the variable first would clone the whole map
+                    // on every access, so this costs a variable lookup, a field lookup by the hash
+                    // the parser cached in `property`, and one clone of the field.
+                    if let Some(Value::Table(map)) = self.variables.get(var_name) {
+                        return map.get(property).cloned().ok_or_else(|| MintasError::RuntimeError {
+                            message: format!("Key '{}' not found", property),
+                            location: Self::default_location(),
+                        });
+                    }
+                    if !self.variables.contains_key(var_name) {
+                        if let Some(value) = self.variables.get(&format!("{}.{}", var_name, property)) {
+                            return Ok(value.clone());
+                        }
+                    }
+                    if self.resolve_module_alias(var_name) == "math" {
                         return match property.as_str() {
                             "pi" => Ok(Value::Number(std::f64::consts::PI)),
                             "e" => Ok(Value::Number(std::f64::consts::E)),
@@ -1706,16 +2463,8 @@ impl Evaluator {
                 }
                 let obj_val = self.eval(object)?;
                 match obj_val {
-                    Value::Instance(instance) => {
-                        instance.fields.get(property.as_str()).cloned().ok_or_else(|| {
-                            MintasError::RuntimeError {
-                                message: format!("Property '{}' not found", property),
-                                location: Self::default_location(),
-                            }
-                        })
-                    }
                     Value::Table(map) => {
-                        map.get(property.as_str()).cloned().ok_or_else(|| {
+                        map.get(property).cloned().ok_or_else(|| {
                             MintasError::RuntimeError {
                                 message: format!("Key '{}' not found", property),
                                 location: Self::default_location(),
@@ -1730,19 +2479,20 @@ impl Evaluator {
             }
             Expr::PropertyAssign { object, property, value } => {
                 let new_value = self.eval(value)?;
+                self.track_allocation(&new_value)?;
                 if let Expr::Variable(var_name) = &**object {
-                    if let Some(Value::Table(mut map)) = self.variables.get(var_name).cloned() {
-                        map.insert(property.clone(), new_value.clone());
-                        self.variables.insert(var_name.clone(), Value::Table(map));
-                        return Ok(new_value);
-                    }
-                    if let Some(Value::Instance(instance)) = self.variables.get(var_name).cloned() {
-                        let mut inst = instance.as_ref().clone();
-                        inst.fields.insert(property.clone(), new_value.clone());
-                        self.variables.insert(var_name.clone(), Value::Instance(Box::new(inst)));
+                    // Update in place rather than cloning the table out and back in; an existing
+                    // field is found by its cached hash, only a new one builds a key
+                    if let Some(Value::Table(map)) = self.variables.get_mut(var_name) {
+                        match map.get_mut(property) {
+                            Some(slot) => *slot = new_value.clone(),
+                            None => {
+                                map.insert(property.clone(), new_value.clone());
+                            }
+                        }
                         return Ok(new_value);
                     }
-                    let mut map = std::collections::HashMap::new();
+                    let mut map = Table::new();
                     map.insert(property.clone(), new_value.clone());
                     self.variables.insert(var_name.clone(), Value::Table(map));
                     return Ok(new_value);
@@ -1770,7 +2520,7 @@ impl Evaluator {
             Expr::Cond { condition } => {
                 let cond_value = self.eval(condition)?;
                 let is_true = cond_value.is_truthy();
-                let mut cond_table = std::collections::HashMap::new();
+                let mut cond_table = Table::new();
                 cond_table.insert("__type__".to_string(), Value::String("Condition".to_string()));
                 cond_table.insert("value".to_string(), Value::Boolean(is_true));
                 Ok(Value::Table(cond_table))
@@ -1797,6 +2547,14 @@ impl Evaluator {
                 self.load_module(module_name, alias.as_deref())?;
                 Ok(Value::Empty)
             }
+            Expr::Export { names, declaration } => {
+                let result = match declaration {
+                    Some(decl) => self.eval(decl)?,
+                    None => Value::Empty,
+                };
+                self.exports.get_or_insert_with(std::collections::HashSet::new).extend(names.iter().cloned());
+                Ok(result)
+            }
             Expr::Task { name, params, body } => {
                 let task_function = Function {
                     params: params.clone(),
@@ -1834,7 +2592,23 @@ impl Evaluator {
                     Ok(Value::Empty)
                 }
             }
-            Expr::DewRoute { server, method, path, body } => {
+            Expr::TypeMatch { subject, arms, default_case } => {
+                let value = self.eval(subject)?;
+                let body = arms
+                    .iter()
+                    .find(|(type_name, _)| type_name == value.type_name())
+                    .map(|(_, body)| body)
+                    .or(default_case.as_ref());
+                let mut result = Value::Empty;
+                for stmt in body.into_iter().flatten() {
+                    result = self.eval(stmt)?;
+                    if matches!(result, Value::ExitSignal | Value::ProceedSignal | Value::ReturnSignal(_)) {
+                        break;
+                    }
+                }
+                Ok(result)
+            }
+            Expr::DewRoute { server, method, path, body, timeout } => {
                 let server_val = self.eval(server)?;
                 let server_id = match &server_val {
                     Value::Table(map) => {
@@ -1851,7 +2625,8 @@ impl Evaluator {
                         location: Self::default_location(),
                     }),
                 };
-                dew_module::add_server_route(server_id, method, path, body.clone())?;
+                let timeout = timeout.as_ref().map(|t| self.eval(t)).transpose()?;
+                dew_module::add_server_route(server_id, method, path, body.clone(), timeout)?;
                 Ok(Value::Empty)
             }
             Expr::DewServe { server, port, host } => {
@@ -1895,7 +2670,7 @@ impl Evaluator {
                 if let Some(getback) = &self.current_getback {
                     Ok(getback.clone())
                 } else {
-                    Ok(Value::Table(HashMap::new()))
+                    Ok(Value::Table(Table::new()))
                 }
             }
             Expr::DewReturn { response_type, body, status, data } => {
@@ -1904,7 +2679,7 @@ impl Evaluator {
                     let data_val = if let Some(d) = data {
                         self.eval(d)?
                     } else {
-                        Value::Table(HashMap::new())
+                        Value::Table(Table::new())
                     };
                     let args = vec![body_val, data_val];
                     let result = dew_module::DewModule::call_function("inview", &args)?;
@@ -1925,7 +2700,7 @@ impl Evaluator {
                 } else {
                     None
                 };
-                let mut response = HashMap::new();
+                let mut response = Table::new();
                 response.insert("__type__".to_string(), Value::String("DewResponse".to_string()));
                 response.insert("response_type".to_string(), Value::String(response_type.clone()));
                 response.insert("body".to_string(), Value::String(body_str));
@@ -2056,7 +2831,7 @@ impl Evaluator {
                 dew_module::add_server_static(server_id, url_path, dir_path)?;
                 Ok(Value::Empty)
             }
-            Expr::DewRouteValidated { server, method, path, validation_rules, body } => {
+            Expr::DewRouteValidated { server, method, path, validation_rules, body, timeout } => {
                 let server_val = self.eval(server)?;
                 let server_id = match &server_val {
                     Value::Table(map) => {
@@ -2074,7 +2849,8 @@ impl Evaluator {
                     }),
                 };
                 let rules_val = self.eval(validation_rules)?;
-                dew_module::add_server_validated_route(server_id, method, path, rules_val, body.clone())?;
+                let timeout = timeout.as_ref().map(|t| self.eval(t)).transpose()?;
+                dew_module::add_server_validated_route(server_id, method, path, rules_val, body.clone(), timeout)?;
                 Ok(Value::Empty)
             }
             Expr::DewConfig { server, config_path } => {
@@ -2137,7 +2913,7 @@ impl Evaluator {
                 let config_val = if let Some(c) = config {
                     self.eval(c)?
                 } else {
-                    Value::Table(HashMap::new())
+                    Value::Table(Table::new())
                 };
                 dew_module::setup_server_session(server_id, config_val)?;
                 Ok(Value::Empty)
@@ -2164,8 +2940,34 @@ impl Evaluator {
             }
         }
     }
+    fn current_module_scope(&self) -> Option<&ModuleScope> {
+        self.current_module.as_ref().and_then(|module| self.module_scopes.get(module))
+    }
+    fn resolve_module_alias(&self, name: &str) -> String {
+        if self.variables.contains_key(name) {
+            return name.to_string();
+        }
+        self.module_aliases.get(name).cloned().unwrap_or_else(|| name.to_string())
+    }
     fn eval_method_call(&mut self, object: &Expr, method: &str, args: &[Expr]) -> MintasResult<Value> {
+        if let Expr::Super = object {
+            return self.call_super(method, args);
+        }
         if let Expr::Variable(var_name) = object {
+            if !self.variables.contains_key(var_name) {
+                if let Some(module_name) = self.module_aliases.get(var_name).cloned() {
+                    return self.eval_method_call(&Expr::Variable(module_name), method, args);
+                }
+                let full_name = format!("{}.{}", var_name, method);
+                // Compiled modules only register placeholders; their calls are dispatched below
+                if self.functions.contains_key(&full_name) && !self.compiled_modules.contains(var_name) {
+                    return self.eval_call(&full_name, args);
+                }
+                // Built-in modules print and block on their own, so pending say() output goes first
+                if var_name != "math" {
+                    self.flush_all_buffers()?;
+                }
+            }
             if var_name == "math" {
                 let mut evaluated_args = Vec::new();
                 for arg in args {
@@ -2296,8 +3098,26 @@ impl Evaluator {
             if var_name == "dew" {
                 let mut evaluated_args = Vec::new();
                 for arg in args {
-                    evaluated_args.push(self.eval(arg)?);
-                }
+                    // Named functions aren't values, so `dew.use(app, auth)` looks `auth` up here
+                    let value = match arg {
+                        Expr::Variable(name) if !self.variables.contains_key(name) && self.functions.contains_key(name) => {
+                            Value::Function(Box::new(self.functions[name].clone()))
+                        }
+                        _ => self.eval(arg)?,
+                    };
+                    evaluated_args.push(value);
+                }
+                if let Some(builtin) = builtins::lookup(Category::Dew, method) {
+                    builtin.check_arity(evaluated_args.len())?;
+                }
+                // Uploads belong to the request this evaluator is handling, so none outlive it
+                if method == "upload" {
+                    let files = match &self.current_getback {
+                        Some(Value::Table(request)) => request.get("files"),
+                        _ => None,
+                    };
+                    return dew_module::DewModule::upload(files, &evaluated_args);
+                }
                 return dew_module::DewModule::call_function(method, &evaluated_args);
             }
             if var_name == "dns" {
@@ -2543,22 +3363,71 @@ impl Evaluator {
                 });
             }
         }
+        if let Expr::Variable(name) = object {
+            if Self::is_array_mutator(method) && matches!(self.variables.get(name), Some(Value::Array(_))) {
+                Self::check_method_arity(&[Category::Array], method, args)?;
+                return self.mutate_array_variable(name, method, args);
+            }
+        }
         let obj_val = self.eval(object)?;
         match &obj_val {
-            Value::String(s) => self.eval_string_method(s, method, args),
-            Value::Array(arr) => self.eval_array_method(arr.clone(), method, args, object),
-            Value::Table(map) => self.eval_table_method(map.clone(), method, args, object),
+            Value::String(s) => {
+                Self::check_method_arity(&[Category::String], method, args)?;
+                self.eval_string_method(s, method, args)
+            }
+            Value::Array(arr) => {
+                Self::check_method_arity(&[Category::Array], method, args)?;
+                self.eval_array_method(arr.clone(), method, args, object)
+            }
+            Value::Builder(buffer) => {
+                Self::check_method_arity(&[Category::Builder], method, args)?;
+                self.eval_builder_method(buffer, method, args)
+            }
+            Value::Table(map) => {
+                let method_func = match map.get(CLASS_TAG) {
+                    Some(Value::String(class_name)) => self.find_method(class_name, method),
+                    _ => None,
+                };
+                match method_func {
+                    Some((defined_in, func)) => self.call_method(&defined_in, method, &func, obj_val.clone(), args, object),
+                    None => {
+                        Self::check_method_arity(&[Category::Table, Category::Request], method, args)?;
+                        self.eval_table_method(map.clone(), method, args, object)
+                    }
+                }
+            }
             _ => Err(MintasError::TypeError {
                 message: format!("{} has no method '{}'", obj_val.type_name(), method),
                 location: Self::default_location(),
             }),
         }
     }
+    /// Arity check for a builtin method; unknown names are left to the method's own dispatch
+    fn check_method_arity(categories: &[Category], method: &str, args: &[Expr]) -> MintasResult<()> {
+        match categories.iter().find_map(|&category| builtins::lookup(category, method)) {
+            Some(builtin) => builtin.check_arity(args.len()),
+            None => Ok(()),
+        }
+    }
+    /// `value.to_json()` and `text.from_json()`, sharing the `json` module's encoder and parser
+    #[cfg(feature = "json")]
+    fn json_method(method: &str, value: Value) -> MintasResult<Value> {
+        let name = if method == "to_json" { "encode" } else { "decode" };
+        json_module::JsonModule::call_function(name, &[value])
+    }
+    #[cfg(not(feature = "json"))]
+    fn json_method(method: &str, _value: Value) -> MintasResult<Value> {
+        Err(MintasError::RuntimeError {
+            message: format!("{}() needs the JSON module. Compile with --features json", method),
+            location: Self::default_location(),
+        })
+    }
     fn eval_string_method(&mut self, s: &str, method: &str, args: &[Expr]) -> MintasResult<Value> {
         match method {
             "len" => Ok(Value::Number(s.chars().count() as f64)),
             "upper" => Ok(Value::String(s.to_uppercase())),
             "lower" => Ok(Value::String(s.to_lowercase())),
+            "from_json" => Self::json_method(method, Value::String(s.to_string())),
             "trim" => Ok(Value::String(s.trim().to_string())),
             "reverse" => Ok(Value::String(s.chars().rev().collect())),
             "contains" => {
@@ -2575,8 +3444,9 @@ impl Evaluator {
             }
             "find" => {
                 let sub = self.expect_string_arg(args, 0, "find")?;
+                // Report a character position so it lines up with s[i] indexing
                 match s.find(&sub) {
-                    Some(idx) => Ok(Value::Number((idx + 1) as f64)),
+                    Some(idx) => Ok(Value::Number((s[..idx].chars().count() + 1) as f64)),
                     None => Ok(Value::Number(0.0)),
                 }
             }
@@ -2603,7 +3473,8 @@ impl Evaluator {
                 let sub = self.expect_string_arg(args, 1, "insert")?;
                 let mut result = s.to_string();
                 let insert_idx = if idx > 0 { idx - 1 } else { 0 };
-                result.insert_str(insert_idx.min(result.len()), &sub);
+                let byte_idx = s.char_indices().nth(insert_idx).map(|(i, _)| i).unwrap_or(s.len());
+                result.insert_str(byte_idx, &sub);
                 Ok(Value::String(result))
             }
             "remove" => {
@@ -2641,63 +3512,96 @@ impl Evaluator {
             }),
         }
     }
-    fn eval_array_method(&mut self, mut arr: Vec<Value>, method: &str, args: &[Expr], object: &Expr) -> MintasResult<Value> {
+    fn is_array_mutator(method: &str) -> bool {
+        matches!(method, "push" | "append" | "pop" | "insert" | "remove" | "clear")
+    }
+    /// Array mutators on a variable edit the stored array in place; `pop` and `remove` return
+    /// the removed element and the others return nothing.
+    fn mutate_array_variable(&mut self, name: &str, method: &str, args: &[Expr]) -> MintasResult<Value> {
+        if self.constants.contains(name) {
+            return Err(MintasError::ConstantReassignment {
+                name: name.to_string(),
+                location: Self::default_location(),
+            });
+        }
+        // Arguments may read the array, so evaluate them before borrowing it
+        let values = args.iter().map(|arg| self.eval(arg)).collect::<MintasResult<Vec<_>>>()?;
+        if matches!(method, "push" | "append" | "insert") {
+            if let Some(val) = values.last() {
+                self.charge_allocation(Self::estimate_value_size(val))?;
+            }
+        }
+        match self.variables.get_mut(name) {
+            Some(Value::Array(arr)) => Self::apply_array_mutation(arr, method, &values),
+            _ => Ok(Value::Empty),
+        }
+    }
+    /// Indices are 1-based. `insert` clamps to the ends; `remove` errors when out of range.
+    fn apply_array_mutation(arr: &mut Vec<Value>, method: &str, args: &[Value]) -> MintasResult<Value> {
+        let position = |value: &Value| match value {
+            Value::Number(n) => Ok(*n as i64),
+            Value::Int(n) => Ok(*n),
+            other => Err(MintasError::TypeError {
+                message: format!("{} expects a number index, got {}", method, other.type_name()),
+                location: Self::default_location(),
+            }),
+        };
         match method {
-            "len" => Ok(Value::Number(arr.len() as f64)),
             "push" | "append" => {
-                let val = self.eval(&args[0])?;
-                let element_size = Self::estimate_value_size(&val);
-                self.check_memory_limit(element_size)?;
-                arr.push(val);
-                self.update_array_variable(object, arr.clone())?;
-                Ok(Value::Array(arr))
-            }
-            "pop" => {
-                let popped = arr.pop().unwrap_or(Value::Empty);
-                self.update_array_variable(object, arr)?;
-                Ok(popped)
+                arr.push(args[0].clone());
+                Ok(Value::Empty)
             }
+            "pop" => Ok(arr.pop().unwrap_or(Value::Empty)),
             "insert" => {
-                let idx = self.expect_number_arg(args, 0, "insert")? as usize;
-                let val = self.eval(&args[1])?;
-                let element_size = Self::estimate_value_size(&val);
-                self.check_memory_limit(element_size)?;
-                let insert_idx = if idx > 0 { idx - 1 } else { 0 };
-                arr.insert(insert_idx.min(arr.len()), val);
-                self.update_array_variable(object, arr.clone())?;
-                Ok(Value::Array(arr))
+                let idx = (position(&args[0])? - 1).clamp(0, arr.len() as i64) as usize;
+                arr.insert(idx, args[1].clone());
+                Ok(Value::Empty)
             }
             "remove" => {
-                let val = self.eval(&args[0])?;
-                if let Some(pos) = arr.iter().position(|x| x == &val) {
-                    arr.remove(pos);
+                let n = position(&args[0])?;
+                if n < 1 || n as usize > arr.len() {
+                    return Err(MintasError::RuntimeError {
+                        message: format!("Index {} out of bounds", n),
+                        location: Self::default_location(),
+                    });
                 }
-                self.update_array_variable(object, arr.clone())?;
-                Ok(Value::Array(arr))
+                Ok(arr.remove(n as usize - 1))
             }
-            "clear" => {
+            _ => {
                 arr.clear();
-                self.update_array_variable(object, arr.clone())?;
-                Ok(Value::Array(arr))
+                Ok(Value::Empty)
             }
+        }
+    }
+    fn eval_array_method(&mut self, mut arr: Vec<Value>, method: &str, args: &[Expr], object: &Expr) -> MintasResult<Value> {
+        // Mutators on a temporary (literal, call result, property) change a copy: `pop` and
+        // `remove` give the element, the rest give the updated copy
+        if Self::is_array_mutator(method) {
+            let values = args.iter().map(|arg| self.eval(arg)).collect::<MintasResult<Vec<_>>>()?;
+            let removed = Self::apply_array_mutation(&mut arr, method, &values)?;
+            return Ok(if matches!(method, "pop" | "remove") { removed } else { Value::Array(arr) });
+        }
+        match method {
+            "len" => Ok(Value::Number(arr.len() as f64)),
+            "to_json" => Self::json_method(method, Value::Array(arr)),
             "reverse" => {
                 arr.reverse();
                 self.update_array_variable(object, arr.clone())?;
                 Ok(Value::Array(arr))
             }
             "contains" => {
-                let val = self.eval(&args[0])?;
+                let val = self.eval(Self::expect_arg(args, 0, "contains")?)?;
                 Ok(Value::Boolean(arr.contains(&val)))
             }
             "index" => {
-                let val = self.eval(&args[0])?;
+                let val = self.eval(Self::expect_arg(args, 0, "index")?)?;
                 match arr.iter().position(|x| x == &val) {
                     Some(idx) => Ok(Value::Number((idx + 1) as f64)),
                     None => Ok(Value::Number(0.0)),
                 }
             }
             "count" => {
-                let val = self.eval(&args[0])?;
+                let val = self.eval(Self::expect_arg(args, 0, "count")?)?;
                 let count = arr.iter().filter(|x| *x == &val).count();
                 Ok(Value::Number(count as f64))
             }
@@ -2714,7 +3618,7 @@ impl Evaluator {
                 Ok(Value::String(parts.join(&sep)))
             }
             "extend" => {
-                let other = self.eval(&args[0])?;
+                let other = self.eval(Self::expect_arg(args, 0, "extend")?)?;
                 if let Value::Array(other_arr) = other {
                     arr.extend(other_arr);
                     self.update_array_variable(object, arr.clone())?;
@@ -2727,18 +3631,12 @@ impl Evaluator {
                 }
             }
             "sort" => {
-                arr.sort_by(|a, b| {
-                    match (a, b) {
-                        (Value::Number(n1), Value::Number(n2)) => n1.partial_cmp(n2).unwrap_or(std::cmp::Ordering::Equal),
-                        (Value::String(s1), Value::String(s2)) => s1.cmp(s2),
-                        _ => std::cmp::Ordering::Equal,
-                    }
-                });
+                arr.sort_by(Value::sort_order);
                 self.update_array_variable(object, arr.clone())?;
                 Ok(Value::Array(arr))
             }
             "map" => {
-                let func_expr = &args[0];
+                let func_expr = Self::expect_arg(args, 0, "map")?;
                 let func = self.get_function_from_expr(func_expr)?;
                 let mut result = Vec::new();
                 for (idx, item) in arr.iter().enumerate() {
@@ -2749,14 +3647,14 @@ impl Evaluator {
                     if func.params.len() >= 2 {
                         self.variables.insert(func.params[1].clone(), Value::Number((idx + 1) as f64));
                     }
-                    let mapped = self.eval_block(&func.body)?;
+                    let mapped = self.eval_function_body(&func.body)?;
                     self.variables = old_vars;
                     result.push(mapped);
                 }
                 Ok(Value::Array(result))
             }
             "filter" => {
-                let func_expr = &args[0];
+                let func_expr = Self::expect_arg(args, 0, "filter")?;
                 let func = self.get_function_from_expr(func_expr)?;
                 let mut result = Vec::new();
                 for (idx, item) in arr.iter().enumerate() {
@@ -2767,7 +3665,7 @@ impl Evaluator {
                     if func.params.len() >= 2 {
                         self.variables.insert(func.params[1].clone(), Value::Number((idx + 1) as f64));
                     }
-                    let filtered = self.eval_block(&func.body)?;
+                    let filtered = self.eval_function_body(&func.body)?;
                     self.variables = old_vars;
                     if filtered.is_truthy() {
                         result.push(item.clone());
@@ -2776,7 +3674,7 @@ impl Evaluator {
                 Ok(Value::Array(result))
             }
             "reduce" => {
-                let func_expr = &args[0];
+                let func_expr = Self::expect_arg(args, 0, "reduce")?;
                 let initial_expr = if args.len() > 1 { Some(&args[1]) } else { None };
                 let func = self.get_function_from_expr(func_expr)?;
                 let mut accumulator = if let Some(init) = initial_expr {
@@ -2798,7 +3696,7 @@ impl Evaluator {
                     if func.params.len() >= 2 {
                         self.variables.insert(func.params[1].clone(), item.clone());
                     }
-                    accumulator = self.eval_block(&func.body)?;
+                    accumulator = self.eval_function_body(&func.body)?;
                     self.variables = old_vars;
                 }
                 Ok(accumulator)
@@ -2816,6 +3714,8 @@ impl Evaluator {
                     Ok(func.clone())
                 } else if let Some(Value::Function(f)) = self.variables.get(name) {
                     Ok(f.as_ref().clone())
+                } else if let Some(func) = Self::builtin_function(name) {
+                    Ok(func)
                 } else {
                     Err(MintasError::TypeError {
                         message: format!("Expected function, got variable '{}'", name),
@@ -2841,41 +3741,65 @@ impl Evaluator {
         }
         Ok(())
     }
-    fn eval_table_method(&mut self, mut map: std::collections::HashMap<String, Value>, method: &str, args: &[Expr], object: &Expr) -> MintasResult<Value> {
+    /// A table's entries in key order, without an instance's class tag
+    fn fields(map: &Table) -> Vec<(&HashableValue, &Value)> {
+        map.sorted().into_iter().filter(|(k, _)| k.as_str() != Some(CLASS_TAG)).collect()
+    }
+    fn eval_table_method(&mut self, mut map: Table, method: &str, args: &[Expr], object: &Expr) -> MintasResult<Value> {
         match method {
-            "len" => Ok(Value::Number(map.len() as f64)),
-            "keys" => {
-                let keys: Vec<Value> = map.keys().map(|k| Value::String(k.clone())).collect();
-                Ok(Value::Array(keys))
-            }
-            "values" => {
-                let values: Vec<Value> = map.values().cloned().collect();
-                Ok(Value::Array(values))
-            }
+            "len" => Ok(Value::Number(Self::fields(&map).len() as f64)),
+            "to_json" => Self::json_method(method, Value::Table(map)),
+            // Tables are unordered, so keys() and values() both walk the keys in sorted order
+            "keys" => Ok(Value::Array(Self::fields(&map).into_iter().map(|(k, _)| k.to_value()).collect())),
+            "values" => Ok(Value::Array(Self::fields(&map).into_iter().map(|(_, v)| v.clone()).collect())),
+            "entries" => Ok(Value::Array(
+                Self::fields(&map).into_iter().map(|(k, v)| Value::Array(vec![k.to_value(), v.clone()])).collect(),
+            )),
             "has" => {
-                let key = self.expect_string_arg(args, 0, "has")?;
+                let key = self.expect_key_arg(args, 0, "has")?;
                 Ok(Value::Boolean(map.contains_key(&key)))
             }
             "remove" => {
-                let key = self.expect_string_arg(args, 0, "remove")?;
+                let key = self.expect_key_arg(args, 0, "remove")?;
                 let removed = map.remove(&key);
                 self.update_table_variable(object, map.clone())?;
                 Ok(removed.unwrap_or(Value::Empty))
             }
-            "merge" => {
-                let other = self.eval(&args[0])?;
-                if let Value::Table(other_map) = other {
-                    for (k, v) in other_map {
-                        map.insert(k, v);
-                    }
-                    self.update_table_variable(object, map.clone())?;
-                    Ok(Value::Table(map))
-                } else {
-                    Err(MintasError::TypeError {
-                        message: "merge requires a table argument".to_string(),
+            // The one way to store under a key that isn't a name, such as `counts.set(404, 1)`
+            "set" => {
+                let key = self.expect_key_arg(args, 0, "set")?;
+                let value = self.eval(Self::expect_arg(args, 1, "set")?)?;
+                self.track_allocation(&value)?;
+                map.insert(key, value.clone());
+                self.update_table_variable(object, map)?;
+                Ok(value)
+            }
+            // `merge` gives a new table and leaves the receiver alone; `update` changes a table
+            // variable, or a table reached through its properties, in place. Keys from `other`
+            // win; `deep` merges nested tables key by key.
+            "merge" | "update" => {
+                let other = match self.eval(Self::expect_arg(args, 0, method)?)? {
+                    Value::Table(other) => other,
+                    value => return Err(MintasError::TypeError {
+                        message: format!("{} requires a table argument, got {}", method, value.type_name()),
                         location: Self::default_location(),
-                    })
+                    }),
+                };
+                let deep = match args.get(1).map(|arg| self.eval(arg)).transpose()? {
+                    None => false,
+                    Some(Value::Boolean(deep)) => deep,
+                    Some(Value::Table(options)) => options.get("deep").is_some_and(Value::is_truthy),
+                    Some(value) => return Err(MintasError::TypeError {
+                        message: format!("{} expects true/false or {{deep = true}} as its second argument, got {}", method, value.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                Self::merge_tables(&mut map, other, deep);
+                if method == "update" {
+                    *self.table_in_place(object, method)? = map;
+                    return Ok(Value::Empty);
                 }
+                Ok(Value::Table(map))
             }
             "param" => {
                 let key = self.expect_string_arg(args, 0, "param")?;
@@ -2893,6 +3817,36 @@ impl Evaluator {
                     Ok(Value::Empty)
                 }
             }
+            "param_number" | "query_number" if matches!(map.get("__type__"), Some(Value::String(t)) if t == "Getback") => {
+                let key = self.expect_string_arg(args, 0, method)?;
+                let source = if method == "param_number" { "params" } else { "query" };
+                // Missing or non-numeric values give null so handlers can reject bad input themselves
+                match map.get(source) {
+                    Some(Value::Table(values)) => match values.get(&key) {
+                        Some(Value::String(raw)) => Ok(raw.trim().parse::<f64>().map(Value::Number).unwrap_or(Value::Null)),
+                        Some(Value::Number(n)) => Ok(Value::Number(*n)),
+                        _ => Ok(Value::Null),
+                    },
+                    _ => Ok(Value::Null),
+                }
+            }
+            // Requests parse their body on demand, regardless of content-type
+            "json" if matches!(map.get("__type__"), Some(Value::String(t)) if t == "Getback") => {
+                match map.get("body") {
+                    Some(Value::String(body)) => Ok(dew_module::body_json(body)),
+                    _ => Ok(Value::Null),
+                }
+            }
+            "form" if matches!(map.get("__type__"), Some(Value::String(t)) if t == "Getback") => {
+                // Multipart text fields were already collected into `form`
+                if map.contains_key("files") {
+                    return Ok(map.get("form").cloned().unwrap_or(Value::Null));
+                }
+                match map.get("body") {
+                    Some(Value::String(body)) => Ok(dew_module::body_form(body)),
+                    _ => Ok(Value::Null),
+                }
+            }
             "header" => {
                 let key = self.expect_string_arg(args, 0, "header")?;
                 if let Some(Value::Table(headers)) = map.get("headers") {
@@ -2911,12 +3865,12 @@ impl Evaluator {
                 if let Some(Value::String(body)) = map.get("body") {
                     self.parse_json_string(body)
                 } else {
-                    Ok(Value::Table(HashMap::new()))
+                    Ok(Value::Table(Table::new()))
                 }
             }
             "form" => {
                 if let Some(Value::String(body)) = map.get("body") {
-                    let mut form_data = HashMap::new();
+                    let mut form_data = Table::new();
                     for pair in body.split('&') {
                         let mut parts = pair.splitn(2, '=');
                         if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
@@ -2925,7 +3879,7 @@ impl Evaluator {
                     }
                     Ok(Value::Table(form_data))
                 } else {
-                    Ok(Value::Table(HashMap::new()))
+                    Ok(Value::Table(Table::new()))
                 }
             }
             "text" | "body" => {
@@ -2941,7 +3895,7 @@ impl Evaluator {
                     if let Some(Value::String(body)) = map.get("body") {
                         self.parse_json_string(body)
                     } else {
-                        Ok(Value::Table(HashMap::new()))
+                        Ok(Value::Table(Table::new()))
                     }
                 }
             }
@@ -2951,15 +3905,90 @@ impl Evaluator {
             }),
         }
     }
-    fn update_table_variable(&mut self, object: &Expr, new_map: std::collections::HashMap<String, Value>) -> MintasResult<()> {
-        if let Expr::Variable(name) = object {
-            if self.constants.contains(name) {
-                return Err(MintasError::ConstantReassignment {
-                    name: name.clone(),
-                    location: Self::default_location(),
-                });
+    /// `append` writes into the shared buffer, so every binding of the builder sees it.
+    /// `build` returns a snapshot: the buffer keeps its text and later appends carry on from it.
+    fn eval_builder_method(&mut self, buffer: &Arc<Mutex<String>>, method: &str, args: &[Expr]) -> MintasResult<Value> {
+        match method {
+            "append" => {
+                let piece = self.eval(Self::expect_arg(args, 0, "append")?)?;
+                let text = match piece {
+                    Value::String(text) => text,
+                    Value::Number(_) | Value::Int(_) | Value::Boolean(_) => self.value_to_string(&piece),
+                    Value::Builder(other) => lock_builder(&other).clone(),
+                    other => return Err(MintasError::TypeError {
+                        message: format!("append expects a string, number or boolean, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                self.charge_allocation(text.len() * 2)?;
+                lock_builder(buffer).push_str(&text);
+                Ok(Value::Empty)
+            }
+            "build" => Ok(Value::String(lock_builder(buffer).clone())),
+            "len" => Ok(Value::Number(lock_builder(buffer).chars().count() as f64)),
+            _ => Err(MintasError::RuntimeError {
+                message: format!("Unknown builder method '{}'", method),
+                location: Self::default_location(),
+            }),
+        }
+    }
+    fn merge_tables(base: &mut Table, other: Table, deep: bool) {
+        for (key, value) in other {
+            match value {
+                Value::Table(incoming) if deep => match base.get_mut(&key) {
+                    Some(Value::Table(nested)) => Self::merge_tables(nested, incoming, true),
+                    _ => {
+                        base.insert(key, Value::Table(incoming));
+                    }
+                },
+                value => {
+                    base.insert(key, value);
+                }
+            }
+        }
+    }
+    /// The value stored at `cfg`, `cfg.db` or `items[2]`, or None when the receiver isn't
+    /// stored anywhere, such as a call's result
+    fn stored_value(&mut self, object: &Expr) -> MintasResult<Option<&mut Value>> {
+        match object {
+            Expr::Variable(name) => {
+                if self.constants.contains(name) {
+                    return Err(MintasError::ConstantReassignment {
+                        name: name.clone(),
+                        location: Self::default_location(),
+                    });
+                }
+                Ok(self.variables.get_mut(name))
+            }
+            Expr::Property { object, property } => Ok(match self.stored_value(object)? {
+                Some(Value::Table(map)) => map.get_mut(property),
+                _ => None,
+            }),
+            Expr::Index { object, index } => {
+                let key = self.eval(index)?;
+                Ok(match (self.stored_value(object)?, key) {
+                    (Some(Value::Array(items)), Value::Number(n)) => items.get_mut((n as i64 - 1) as usize),
+                    (Some(Value::Table(map)), key) => map.get_mut(&HashableValue::from_value(&key)?),
+                    _ => None,
+                })
             }
-            self.variables.insert(name.clone(), Value::Table(new_map));
+            _ => Ok(None),
+        }
+    }
+    /// The table stored at `cfg`, `cfg.db` or `items[2]`, for methods that change their receiver
+    fn table_in_place(&mut self, object: &Expr, method: &str) -> MintasResult<&mut Table> {
+        match self.stored_value(object)? {
+            Some(Value::Table(map)) => Ok(map),
+            _ => Err(MintasError::RuntimeError {
+                message: format!("{}() changes a table in place, so call it on a variable or one of its properties; use merge() for a new table", method),
+                location: Self::default_location(),
+            }),
+        }
+    }
+    /// Stores a table a method changed back where its receiver came from
+    fn update_table_variable(&mut self, object: &Expr, new_map: Table) -> MintasResult<()> {
+        if let Some(slot) = self.stored_value(object)? {
+            *slot = Value::Table(new_map);
         }
         Ok(())
     }
@@ -2980,7 +4009,42 @@ impl Evaluator {
             }),
         }
     }
+    fn expect_key_arg(&mut self, args: &[Expr], idx: usize, method: &str) -> MintasResult<HashableValue> {
+        let key = self.eval(Self::expect_arg(args, idx, method)?)?;
+        HashableValue::from_value(&key)
+    }
+    /// `range(end)`, `range(start, end)` or `range(start, end, step)`: `end` is excluded.
+    /// Gives the start, the step and how many values the range holds.
+    fn range_args(&mut self, args: &[Expr]) -> MintasResult<(f64, f64, usize)> {
+        if let Some(builtin) = builtins::global("range") {
+            builtin.check_arity(args.len())?;
+        }
+        let first = self.expect_number_arg(args, 0, "range")?;
+        let (start, end) = if args.len() == 1 { (0.0, first) } else { (first, self.expect_number_arg(args, 1, "range")?) };
+        let step = if args.len() == 3 { self.expect_number_arg(args, 2, "range")? } else { 1.0 };
+        if step == 0.0 || !step.is_finite() || !start.is_finite() || !end.is_finite() {
+            return Err(MintasError::RuntimeError {
+                message: format!("range: cannot count from {} to {} in steps of {}", start, end, step),
+                location: Self::default_location(),
+            });
+        }
+        let len = ((end - start) / step).ceil().max(0.0) as usize;
+        Ok((start, step, len))
+    }
+    /// The `idx`th argument expression, or an argument-count error when the call is short
+    fn expect_arg<'a>(args: &'a [Expr], idx: usize, method: &str) -> MintasResult<&'a Expr> {
+        args.get(idx).ok_or_else(|| MintasError::InvalidArgumentCount {
+            function: method.to_string(),
+            expected: idx + 1,
+            got: args.len(),
+            location: Self::default_location(),
+        })
+    }
     fn expect_number_arg(&mut self, args: &[Expr], idx: usize, method: &str) -> MintasResult<f64> {
+        Ok(self.expect_number_value(args, idx, method)?.as_f64().unwrap_or(f64::NAN))
+    }
+    /// The argument as evaluated, which must be a `Number` or an `Int`
+    fn expect_number_value(&mut self, args: &[Expr], idx: usize, method: &str) -> MintasResult<Value> {
         if idx >= args.len() {
             return Err(MintasError::InvalidArgumentCount {
                 function: method.to_string(),
@@ -2990,13 +4054,20 @@ impl Evaluator {
             });
         }
         match self.eval(&args[idx])? {
-            Value::Number(n) => Ok(n),
+            value @ (Value::Number(_) | Value::Int(_)) => Ok(value),
             other => Err(MintasError::TypeError {
                 message: format!("{} expects number argument, got {}", method, other.type_name()),
                 location: Self::default_location(),
             }),
         }
     }
+    /// A function body run inline by map/filter/reduce, which catch its `return` like a call does
+    fn eval_function_body(&mut self, body: &[Expr]) -> MintasResult<Value> {
+        match self.eval_block(body)? {
+            Value::ReturnSignal(value) => Ok(*value),
+            value => Ok(value),
+        }
+    }
     fn eval_block(&mut self, statements: &[Expr]) -> MintasResult<Value> {
         let mut last = Value::Empty;
         for stmt in statements {
@@ -3013,15 +4084,25 @@ impl Evaluator {
     }
     fn eval_binary_op(&mut self, op: &BinaryOp, left: &Expr, right: &Expr) -> MintasResult<Value> {
         let left_val = self.eval(left)?;
+        if matches!(op, BinaryOp::NullCoalesce) && !matches!(left_val, Value::Null | Value::Empty) {
+            return Ok(left_val);
+        }
         let right_val = self.eval(right)?;
-        self.apply_binary_op(op, &left_val, &right_val)
+        self.apply_tracked_binary_op(op, &left_val, &right_val)
     }
     fn apply_binary_op(&self, op: &BinaryOp, left_val: &Value, right_val: &Value) -> MintasResult<Value> {
+        if matches!(left_val, Value::Int(_)) || matches!(right_val, Value::Int(_)) {
+            if let Some(result) = self.apply_int_binary_op(op, left_val, right_val) {
+                return result;
+            }
+        }
         match op {
             BinaryOp::Add => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                    (Value::Number(a), Value::Number(b)) => Ok(Value::checked_arith(op, left_val, right_val, a + b)),
                     (Value::String(a), Value::String(b)) => Ok(Value::String(format!("{}{}", a, b))),
+                    (Value::String(a), Value::Int(b)) => Ok(Value::String(format!("{}{}", a, b))),
+                    (Value::Int(a), Value::String(b)) => Ok(Value::String(format!("{}{}", a, b))),
                     (Value::String(a), Value::Number(b)) => Ok(Value::String(format!("{}{}", a, b))),
                     (Value::Number(a), Value::String(b)) => Ok(Value::String(format!("{}{}", a, b))),
                     (Value::String(a), Value::Boolean(b)) => Ok(Value::String(format!("{}{}", a, b))),
@@ -3038,7 +4119,7 @@ impl Evaluator {
             }
             BinaryOp::Subtract => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
+                    (Value::Number(a), Value::Number(b)) => Ok(Value::checked_arith(op, left_val, right_val, a - b)),
                     _ => Err(MintasError::TypeError {
                         message: format!("Subtraction only works with numbers, got {} and {}", 
                             left_val.type_name(), right_val.type_name()),
@@ -3048,7 +4129,24 @@ impl Evaluator {
             }
             BinaryOp::Multiply => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a * b)),
+                    (Value::Number(a), Value::Number(b)) => Ok(Value::checked_arith(op, left_val, right_val, a * b)),
+                    (Value::String(text), count) | (count, Value::String(text)) if count.as_f64().is_some() => {
+                        let times = Self::repeat_count(count)?;
+                        if text.is_empty() || times == 0 {
+                            return Ok(Value::String(String::new()));
+                        }
+                        Self::check_repeat_len(text.len(), times, MAX_STRING_LENGTH, "characters")?;
+                        Ok(Value::String(text.repeat(times)))
+                    }
+                    (Value::Array(items), count) | (count, Value::Array(items)) if count.as_f64().is_some() => {
+                        let times = Self::repeat_count(count)?;
+                        if items.is_empty() || times == 0 {
+                            return Ok(Value::Array(Vec::new()));
+                        }
+                        let mut repeated = Vec::with_capacity(Self::check_repeat_len(items.len(), times, MAX_ARRAY_SIZE, "elements")?);
+                        (0..times).for_each(|_| repeated.extend(items.iter().cloned()));
+                        Ok(Value::Array(repeated))
+                    }
                     _ => Err(MintasError::TypeError {
                         message: format!("Multiplication only works with numbers, got {} and {}", 
                             left_val.type_name(), right_val.type_name()),
@@ -3110,8 +4208,9 @@ impl Evaluator {
             }
             BinaryOp::Greater => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a > b)),
-                    (Value::String(a), Value::String(b)) => Ok(Value::Boolean(a > b)),
+                    (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) | (Value::String(_), Value::String(_)) => {
+                        Ok(Value::Boolean(Value::compare(left_val, right_val).is_some_and(|o| o.is_gt())))
+                    }
                     _ => Err(MintasError::TypeError {
                         message: format!("Cannot compare {} and {} with >", 
                             left_val.type_name(), right_val.type_name()),
@@ -3121,8 +4220,9 @@ impl Evaluator {
             }
             BinaryOp::Less => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a < b)),
-                    (Value::String(a), Value::String(b)) => Ok(Value::Boolean(a < b)),
+                    (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) | (Value::String(_), Value::String(_)) => {
+                        Ok(Value::Boolean(Value::compare(left_val, right_val).is_some_and(|o| o.is_lt())))
+                    }
                     _ => Err(MintasError::TypeError {
                         message: format!("Cannot compare {} and {} with <", 
                             left_val.type_name(), right_val.type_name()),
@@ -3132,8 +4232,9 @@ impl Evaluator {
             }
             BinaryOp::GreaterEqual => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a >= b)),
-                    (Value::String(a), Value::String(b)) => Ok(Value::Boolean(a >= b)),
+                    (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) | (Value::String(_), Value::String(_)) => {
+                        Ok(Value::Boolean(Value::compare(left_val, right_val).is_some_and(|o| o.is_ge())))
+                    }
                     _ => Err(MintasError::TypeError {
                         message: format!("Cannot compare {} and {} with >=", 
                             left_val.type_name(), right_val.type_name()),
@@ -3143,8 +4244,9 @@ impl Evaluator {
             }
             BinaryOp::LessEqual => {
                 match (left_val, right_val) {
-                    (Value::Number(a), Value::Number(b)) => Ok(Value::Boolean(a <= b)),
-                    (Value::String(a), Value::String(b)) => Ok(Value::Boolean(a <= b)),
+                    (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) | (Value::String(_), Value::String(_)) => {
+                        Ok(Value::Boolean(Value::compare(left_val, right_val).is_some_and(|o| o.is_le())))
+                    }
                     _ => Err(MintasError::TypeError {
                         message: format!("Cannot compare {} and {} with <=", 
                             left_val.type_name(), right_val.type_name()),
@@ -3172,32 +4274,101 @@ impl Evaluator {
                     Ok(right_val.clone())
                 }
             }
+            BinaryOp::NullCoalesce => {
+                if matches!(left_val, Value::Null | Value::Empty) {
+                    Ok(right_val.clone())
+                } else {
+                    Ok(left_val.clone())
+                }
+            }
+        }
+    }
+    /// Count for `"ab" * 3` / `[0] * 3`, which must be a non-negative integer
+    fn repeat_count(count: &Value) -> MintasResult<usize> {
+        match count.exact_int() {
+            Some(n) if n >= 0 => Ok(n as usize),
+            _ => Err(MintasError::TypeError {
+                message: format!("Repetition count must be a non-negative integer, got {}", count.as_f64().unwrap_or(f64::NAN)),
+                location: Self::default_location(),
+            }),
+        }
+    }
+    /// Length of a repetition, checked against `max` before anything is allocated
+    fn check_repeat_len(len: usize, times: usize, max: usize, unit: &str) -> MintasResult<usize> {
+        match len.checked_mul(times) {
+            Some(total) if total <= max => Ok(total),
+            _ => Err(MintasError::RuntimeError {
+                message: format!("Repetition by {} would exceed the limit of {} {}", times, max, unit),
+                location: Self::default_location(),
+            }),
+        }
+    }
+    /// Estimated size of `"ab" * n` / `[0] * n`, known before the repetition is built
+    fn repetition_size(op: &BinaryOp, left_val: &Value, right_val: &Value) -> Option<usize> {
+        let (repeated, count) = match (op, left_val, right_val) {
+            (BinaryOp::Multiply, seq @ (Value::String(_) | Value::Array(_)), count)
+            | (BinaryOp::Multiply, count, seq @ (Value::String(_) | Value::Array(_))) => (seq, count),
+            _ => return None,
+        };
+        let times = usize::try_from(count.exact_int()?).ok()?;
+        Some(Self::estimate_value_size(repeated).saturating_mul(times))
+    }
+    /// Applies `op` and charges the result to --max-memory; a repetition is charged before it is built
+    fn apply_tracked_binary_op(&mut self, op: &BinaryOp, left_val: &Value, right_val: &Value) -> MintasResult<Value> {
+        if let Some(size) = Self::repetition_size(op, left_val, right_val) {
+            if self.security_monitor.has_allocation_budget() {
+                self.charge_allocation(size)?;
+            }
+            return self.apply_binary_op(op, left_val, right_val);
         }
+        let result = self.apply_binary_op(op, left_val, right_val)?;
+        self.track_allocation(&result)?;
+        Ok(result)
+    }
+    /// Arithmetic when one side is an `Int`; `None` leaves it to the general rules
+    fn apply_int_binary_op(&self, op: &BinaryOp, left_val: &Value, right_val: &Value) -> Option<MintasResult<Value>> {
+        let (a, b) = (left_val.as_f64()?, right_val.as_f64()?);
+        let result = match op {
+            BinaryOp::Add => Ok(Value::checked_arith(op, left_val, right_val, a + b)),
+            BinaryOp::Subtract => Ok(Value::checked_arith(op, left_val, right_val, a - b)),
+            BinaryOp::Multiply => Ok(Value::checked_arith(op, left_val, right_val, a * b)),
+            BinaryOp::Modulo if b != 0.0 => Ok(Value::checked_arith(op, left_val, right_val, a % b)),
+            BinaryOp::Modulo | BinaryOp::Divide | BinaryOp::Exponent => {
+                self.apply_binary_op(op, &Value::Number(a), &Value::Number(b))
+            }
+            _ => return None,
+        };
+        Some(result)
     }
     fn values_equal(&self, left: &Value, right: &Value) -> bool {
         match (left, right) {
-            (Value::Number(a), Value::Number(b)) => (a - b).abs() < f64::EPSILON,
+            (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) => left == right,
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::Maybe, Value::Maybe) => true,
-            (Value::Empty, Value::Empty) => true,
+            (Value::Empty | Value::Null, Value::Empty | Value::Null) => true,
             (Value::Number(n), Value::String(s)) | (Value::String(s), Value::Number(n)) => {
-                s.parse::<f64>().map(|parsed| (parsed - n).abs() < f64::EPSILON).unwrap_or(false)
+                s.parse::<f64>().map(|parsed| Value::numbers_equal(parsed, *n)).unwrap_or(false)
             }
+            (Value::Array(_), Value::Array(_)) | (Value::Table(_), Value::Table(_)) => left.deep_equals(right),
+            // Builders are equal only to themselves
+            (Value::Builder(a), Value::Builder(b)) => Arc::ptr_eq(a, b),
             _ => false,
         }
     }
     fn values_strict_equal(&self, left: &Value, right: &Value) -> bool {
         match (left, right) {
-            (Value::Number(a), Value::Number(b)) => (a - b).abs() < f64::EPSILON,
+            (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) => left == right,
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::Maybe, Value::Maybe) => true,
             (Value::Empty, Value::Empty) => true,
+            (Value::Null, Value::Null) => true,
+            (Value::Builder(a), Value::Builder(b)) => Arc::ptr_eq(a, b),
             _ => false,
         }
     }
-    fn table_to_json(&self, map: &HashMap<String, Value>) -> String {
+    fn table_to_json(&self, map: &Table) -> String {
         let mut parts = Vec::new();
         for (key, value) in map {
             let val_str = self.value_to_json(value);
@@ -3215,8 +4386,9 @@ impl Evaluator {
                     format!("{}", n)
                 }
             }
+            Value::Int(n) => n.to_string(),
             Value::Boolean(b) => format!("{}", b),
-            Value::Maybe | Value::Empty => "null".to_string(),
+            Value::Maybe | Value::Empty | Value::Null => "null".to_string(),
             Value::Array(arr) => {
                 let items: Vec<String> = arr.iter().map(|v| self.value_to_json(v)).collect();
                 format!("[{}]", items.join(","))
@@ -3228,7 +4400,7 @@ impl Evaluator {
     fn parse_json_string(&self, json: &str) -> MintasResult<Value> {
         let json = json.trim();
         if json.is_empty() {
-            return Ok(Value::Table(HashMap::new()));
+            return Ok(Value::Table(Table::new()));
         }
         if json.starts_with('{') {
             self.parse_json_object(json)
@@ -3242,7 +4414,9 @@ impl Evaluator {
         } else if json == "false" {
             Ok(Value::Boolean(false))
         } else if json == "null" {
-            Ok(Value::Empty)
+            Ok(Value::Null)
+        } else if let Ok(n) = json.parse::<i64>() {
+            Ok(Value::from_i64(n))
         } else if let Ok(n) = json.parse::<f64>() {
             Ok(Value::Number(n))
         } else {
@@ -3252,13 +4426,13 @@ impl Evaluator {
     fn parse_json_object(&self, json: &str) -> MintasResult<Value> {
         let json = json.trim();
         if !json.starts_with('{') || !json.ends_with('}') {
-            return Ok(Value::Table(HashMap::new()));
+            return Ok(Value::Table(Table::new()));
         }
         let inner = &json[1..json.len()-1].trim();
         if inner.is_empty() {
-            return Ok(Value::Table(HashMap::new()));
+            return Ok(Value::Table(Table::new()));
         }
-        let mut map = HashMap::new();
+        let mut map = Table::new();
         let mut depth = 0;
         let mut in_string = false;
         let mut escape = false;
@@ -3296,7 +4470,7 @@ impl Evaluator {
         }
         Ok(Value::Table(map))
     }
-    fn parse_json_pair(&self, pair: &str, map: &mut HashMap<String, Value>) {
+    fn parse_json_pair(&self, pair: &str, map: &mut Table) {
         let pair = pair.trim();
         if let Some(colon_pos) = pair.find(':') {
             let key = pair[..colon_pos].trim().trim_matches('"');
@@ -3362,6 +4536,7 @@ impl Evaluator {
             UnaryOp::Negate => {
                 match self.eval(expr)? {
                     Value::Number(n) => Ok(Value::Number(-n)),
+                    Value::Int(n) => Ok(n.checked_neg().map(Value::from_i64).unwrap_or(Value::Number(-(n as f64)))),
                     other => Err(MintasError::TypeError {
                         message: format!("Cannot negate {}", other.type_name()),
                         location: Self::default_location(),
@@ -3388,8 +4563,9 @@ impl Evaluator {
                             }
                         })?;
                         match current {
-                            Value::Number(n) => {
-                                let new_val = Value::Number(n + 1.0);
+                            Value::Number(_) | Value::Int(_) => {
+                                let n = current.as_f64().unwrap_or(f64::NAN);
+                                let new_val = Value::checked_arith(&BinaryOp::Add, &current, &Value::Number(1.0), n + 1.0);
                                 self.variables.insert(name.clone(), new_val.clone());
                                 Ok(new_val)
                             }
@@ -3421,8 +4597,9 @@ impl Evaluator {
                             }
                         })?;
                         match current {
-                            Value::Number(n) => {
-                                let new_val = Value::Number(n - 1.0);
+                            Value::Number(_) | Value::Int(_) => {
+                                let n = current.as_f64().unwrap_or(f64::NAN);
+                                let new_val = Value::checked_arith(&BinaryOp::Subtract, &current, &Value::Number(1.0), n - 1.0);
                                 self.variables.insert(name.clone(), new_val.clone());
                                 Ok(new_val)
                             }
@@ -3441,17 +4618,14 @@ impl Evaluator {
         }
     }
     fn eval_call(&mut self, name: &str, args: &[Expr]) -> MintasResult<Value> {
+        let shadowed = self.shadows_builtin(name);
+        if let Some(builtin) = builtins::global(name).filter(|_| !shadowed) {
+            builtin.check_arity(args.len())?;
+        }
         match name {
             "say" => {
-                if args.len() != 1 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "say".to_string(),
-                        expected: 1,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let val = self.eval(&args[0])?;
+                // Buffered: flushed at program end, by flush(), before input and module calls
                 {
                     let mut stdout = self.stdout_buffer.borrow_mut();
                     self.write_value_to_buffer(&val, &mut *stdout)?;
@@ -3459,22 +4633,39 @@ impl Evaluator {
                         message: format!("Output error: {}", e),
                         location: Self::default_location(),
                     })?;
-                    stdout.flush().map_err(|e| MintasError::RuntimeError {
-                        message: format!("Output flush error: {}", e),
-                        location: Self::default_location(),
-                    })?;
                 }
                 Ok(val)
             }
-            "ask" => {
-                if args.len() != 1 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "ask".to_string(),
-                        expected: 1,
-                        got: args.len(),
+            // Like say, but joining any number of values with spaces; print adds no newline
+            // so a line can be built up across calls
+            "print" | "println" => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                let mut stdout = self.stdout_buffer.borrow_mut();
+                for (i, val) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(stdout, " ").map_err(|e| MintasError::RuntimeError {
+                            message: format!("Output error: {}", e),
+                            location: Self::default_location(),
+                        })?;
+                    }
+                    self.write_value_to_buffer(val, &mut *stdout)?;
+                }
+                if name == "println" {
+                    writeln!(stdout).map_err(|e| MintasError::RuntimeError {
+                        message: format!("Output error: {}", e),
                         location: Self::default_location(),
-                    });
+                    })?;
                 }
+                Ok(Value::Empty)
+            }
+            "flush" => {
+                self.flush_all_buffers()?;
+                Ok(Value::Empty)
+            }
+            "ask" => {
                 let prompt_val = self.eval(&args[0])?;
                 let mut prompt = self.value_to_string(&prompt_val);
                 if prompt.ends_with(':') {
@@ -3505,14 +4696,6 @@ impl Evaluator {
                 Ok(Value::String(input))
             }
             "read" => {
-                if args.len() != 1 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "read".to_string(),
-                        expected: 1,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let file_path_val = self.eval(&args[0])?;
                 let file_path = match file_path_val {
                     Value::String(s) => s,
@@ -3544,14 +4727,6 @@ impl Evaluator {
                 }
             }
             "write" => {
-                if args.len() != 2 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "write".to_string(),
-                        expected: 2,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let file_path_val = self.eval(&args[0])?;
                 let content_val = self.eval(&args[1])?;
                 let file_path = match file_path_val {
@@ -3584,14 +4759,6 @@ impl Evaluator {
                 }
             }
             "append" => {
-                if args.len() != 2 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "append".to_string(),
-                        expected: 2,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let file_path_val = self.eval(&args[0])?;
                 let content_val = self.eval(&args[1])?;
                 let file_path = match file_path_val {
@@ -3624,14 +4791,6 @@ impl Evaluator {
                 }
             }
             "exists" => {
-                if args.len() != 1 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "exists".to_string(),
-                        expected: 1,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let file_path_val = self.eval(&args[0])?;
                 let file_path = match file_path_val {
                     Value::String(s) => s,
@@ -3643,32 +4802,88 @@ impl Evaluator {
                 Ok(Value::Boolean(std::path::Path::new(&file_path).exists()))
             }
             "typeof" => {
-                if args.len() != 1 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "typeof".to_string(),
-                        expected: 1,
-                        got: args.len(),
+                let val = match &args[0] {
+                    Expr::Variable(var_name) if !self.variables.contains_key(var_name) && self.functions.contains_key(var_name) => {
+                        Value::Function(Box::new(self.functions[var_name].clone()))
+                    }
+                    other => self.eval(other)?,
+                };
+                match &val {
+                    Value::Function(f) if f.is_lambda => Ok(Value::String("closure".to_string())),
+                    _ => Ok(Value::String(val.type_name().to_string())),
+                }
+            }
+            "random" => {
+                Ok(Value::Number(self.next_random()))
+            }
+            // Both bounds are inclusive: random_int(1, 6) rolls a die
+            "random_int" => {
+                let lo = self.expect_number_arg(args, 0, "random_int")?;
+                let hi = self.expect_number_arg(args, 1, "random_int")?;
+                let (min, max) = (lo.ceil(), hi.floor());
+                if !min.is_finite() || !max.is_finite() || min > max {
+                    return Err(MintasError::RuntimeError {
+                        message: format!("random_int: no integers between {} and {}", lo, hi),
                         location: Self::default_location(),
                     });
                 }
-                let val = self.eval(&args[0])?;
-                Ok(Value::String(val.type_name().to_string()))
+                let span = max - min + 1.0;
+                Ok(Value::Number((min + (self.next_random() * span).floor()).min(max)))
             }
-            "toString" => {
-                if args.len() < 1 || args.len() > 2 {
+            "args" => Ok(Value::Array(self.script_args.iter().cloned().map(Value::String).collect())),
+            "range" if !shadowed => {
+                let (start, step, len) = self.range_args(args)?;
+                if len > MAX_ARRAY_SIZE {
+                    return Err(MintasError::RuntimeError {
+                        message: format!("range: {} values are too many to build as an array; loop over it with for-in instead", len),
+                        location: Self::default_location(),
+                    });
+                }
+                Ok(Value::Array((0..len).map(|k| Value::Number(start + k as f64 * step)).collect()))
+            }
+            "clone" => match self.eval(&args[0])? {
+                // A builder is the one shared value, so its copy gets a buffer of its own
+                Value::Builder(buffer) => Ok(Value::Builder(Arc::new(Mutex::new(lock_builder(&buffer).clone())))),
+                // Other values never share storage, so the evaluated copy is already a deep copy
+                value => Ok(value),
+            },
+            "arity" => {
+                let (_, func) = self.function_arg(&args[0], "arity")?;
+                Ok(Value::Number(func.params.len() as f64))
+            }
+            "call" => {
+                let (func_name, func) = self.function_arg(&args[0], "call")?;
+                let arg_values = match self.eval(&args[1])? {
+                    Value::Array(values) => values,
+                    other => return Err(MintasError::TypeError {
+                        message: format!("call() expects an array of arguments, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                if func.params.len() != arg_values.len() {
                     return Err(MintasError::InvalidArgumentCount {
-                        function: "toString".to_string(),
-                        expected: 1,
-                        got: args.len(),
+                        function: func_name,
+                        expected: func.params.len(),
+                        got: arg_values.len(),
                         location: Self::default_location(),
                     });
                 }
+                self.invoke_function(&func_name, &func, arg_values)
+            }
+            "is_number" | "is_string" | "is_array" | "is_table" => {
+                let val = self.eval(&args[0])?;
+                let matches = match name {
+                    "is_number" => matches!(val, Value::Number(_) | Value::Int(_)),
+                    "is_string" => matches!(val, Value::String(_)),
+                    "is_array" => matches!(val, Value::Array(_)),
+                    _ => matches!(val, Value::Table(_)),
+                };
+                Ok(Value::Boolean(matches))
+            }
+            "toString" => {
                 let val = self.eval(&args[0])?;
                 let base = if args.len() == 2 {
-                    match self.eval(&args[1])? {
-                        Value::Number(n) => n as i32,
-                        _ => 10,
-                    }
+                    self.eval(&args[1])?.as_f64().map_or(10, |n| n as i32)
                 } else {
                     10
                 };
@@ -3682,40 +4897,30 @@ impl Evaluator {
                             n.to_string()
                         }
                     }
+                    Value::Int(n) => n.to_string(),
                     _ => self.value_to_string(&val),
                 };
                 Ok(Value::String(result))
             }
             "toNumber" => {
-                if args.len() < 1 || args.len() > 2 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "toNumber".to_string(),
-                        expected: 1,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let val = self.eval(&args[0])?;
                 let base = if args.len() == 2 {
-                    match self.eval(&args[1])? {
-                        Value::Number(n) => n as i32,
-                        _ => 10,
-                    }
+                    self.eval(&args[1])?.as_f64().map_or(10, |n| n as i32)
                 } else {
                     10
                 };
                 match val {
-                    Value::Number(n) => Ok(Value::Number(n)),
+                    Value::Number(_) | Value::Int(_) => Ok(val),
                     Value::String(s) => {
                         if base == 10 {
-                            s.parse::<f64>().map(Value::Number).map_err(|_| {
+                            Value::parse_number(&s).ok_or_else(|| {
                                 MintasError::TypeError {
                                     message: format!("Cannot convert '{}' to number", s),
                                     location: Self::default_location(),
                                 }
                             })
                         } else if base >= 2 && base <= 36 {
-                            i64::from_str_radix(&s, base as u32).map(|n| Value::Number(n as f64)).map_err(|_| {
+                            i64::from_str_radix(&s, base as u32).map(Value::from_i64).map_err(|_| {
                                 MintasError::TypeError {
                                     message: format!("Cannot convert '{}' to number with base {}", s, base),
                                     location: Self::default_location(),
@@ -3735,15 +4940,249 @@ impl Evaluator {
                     }),
                 }
             }
-            "assert" => {
-                if args.len() < 1 || args.len() > 2 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "assert".to_string(),
-                        expected: 1,
-                        got: args.len(),
+            "regex_match" | "regex_find" | "regex_replace" => {
+                let mut evaluated_args = Vec::new();
+                for arg in args {
+                    evaluated_args.push(self.eval(arg)?);
+                }
+                #[cfg(feature = "regex")]
+                return regex_module::RegexModule::call_function(&name["regex_".len()..], &evaluated_args);
+                #[cfg(not(feature = "regex"))]
+                return Err(MintasError::RuntimeError {
+                    message: "Regex support not available. Compile with --features regex".to_string(),
+                    location: Self::default_location(),
+                });
+            }
+            "to_number" => {
+                match self.eval(&args[0])? {
+                    n @ (Value::Number(_) | Value::Int(_)) => Ok(n),
+                    Value::Boolean(b) => Ok(Value::Number(if b { 1.0 } else { 0.0 })),
+                    Value::String(s) => Value::parse_number(s.trim()).ok_or_else(|| {
+                        MintasError::TypeError {
+                            message: format!("Cannot convert '{}' to number", s),
+                            location: Self::default_location(),
+                        }
+                    }),
+                    other => Err(MintasError::TypeError {
+                        message: format!("Cannot convert {} to number", other.type_name()),
                         location: Self::default_location(),
-                    });
+                    }),
+                }
+            }
+            "format" => {
+                let template = match self.eval(&args[0])? {
+                    Value::String(s) => s,
+                    other => return Err(MintasError::TypeError {
+                        message: format!("format expects a string template, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                let mut values = Vec::with_capacity(args.len() - 1);
+                for arg in &args[1..] {
+                    values.push(self.eval(arg)?);
+                }
+                Ok(Value::String(self.format_template(&template, &values)?))
+            }
+            "to_string" => {
+                let val = self.eval(&args[0])?;
+                Ok(Value::String(self.value_to_string(&val)))
+            }
+            "round" => {
+                let x = self.expect_number_arg(args, 0, "round")?;
+                let digits = if args.len() == 2 { self.expect_number_arg(args, 1, "round")? } else { 0.0 };
+                if digits == 0.0 || !x.is_finite() {
+                    return Ok(Value::Number(x.round()));
+                }
+                let factor = 10f64.powi(digits as i32);
+                Ok(Value::Number((x * factor).round() / factor))
+            }
+            // Report-style text, unlike the canonical display: format_number(1234567.5, {decimals = 2})
+            "format_number" => {
+                let n = match self.eval(&args[0])? {
+                    n @ (Value::Number(_) | Value::Int(_)) => n,
+                    other => return Err(MintasError::TypeError {
+                        message: format!("format_number() expects a number, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                let options = match args.get(1).map(|arg| self.eval(arg)).transpose()? {
+                    None => Table::new(),
+                    Some(Value::Table(options)) => options,
+                    Some(other) => return Err(MintasError::TypeError {
+                        message: format!("format_number() options must be a table, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                Ok(Value::String(Self::format_number(&n, &options)?))
+            }
+            "floor" | "ceil" | "abs" | "sqrt" => {
+                let x = match self.expect_number_value(args, 0, name)? {
+                    // Whole numbers past 2^53 stay exact instead of rounding through f64
+                    Value::Int(n) if name != "sqrt" => {
+                        let exact = if name == "abs" { n.checked_abs() } else { Some(n) };
+                        return Ok(exact.map_or(Value::Number((n as f64).abs()), Value::from_i64));
+                    }
+                    value => value.as_f64().unwrap_or(f64::NAN),
+                };
+                let result = match name {
+                    "floor" => x.floor(),
+                    "ceil" => x.ceil(),
+                    "abs" => x.abs(),
+                    _ => x.sqrt(),
+                };
+                Ok(Value::Number(result))
+            }
+            "pow" => {
+                let base = self.expect_number_arg(args, 0, "pow")?;
+                let exponent = self.expect_number_arg(args, 1, "pow")?;
+                Ok(Value::Number(base.powf(exponent)))
+            }
+            "min" | "max" if !shadowed => {
+                // A single array argument aggregates over its elements: min([3, 1, 2])
+                let first = self.eval(&args[0])?;
+                let numbers = match first {
+                    Value::Array(items) if args.len() == 1 => {
+                        Self::array_numbers(name, &items)?;
+                        if items.is_empty() {
+                            return Err(MintasError::RuntimeError {
+                                message: format!("{}() of an empty array has no value", name),
+                                location: Self::default_location(),
+                            });
+                        }
+                        items
+                    }
+                    n @ (Value::Number(_) | Value::Int(_)) => {
+                        let mut numbers = vec![n];
+                        for idx in 1..args.len() {
+                            numbers.push(self.expect_number_value(args, idx, name)?);
+                        }
+                        numbers
+                    }
+                    other => return Err(MintasError::TypeError {
+                        message: format!("{} expects number argument, got {}", name, other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                // Compared as values so whole numbers past 2^53 keep their exact order
+                let mut result = numbers[0].clone();
+                for n in &numbers[1..] {
+                    match Value::compare_numbers(n, &result) {
+                        None => return Ok(Value::Number(f64::NAN)),
+                        Some(order) if (name == "min" && order.is_lt()) || (name == "max" && order.is_gt()) => result = n.clone(),
+                        Some(_) => {}
+                    }
+                }
+                Ok(result)
+            }
+            "idiv" | "mod" | "divmod" => {
+                let dividend = self.eval(&args[0])?;
+                let divisor = self.eval(&args[1])?;
+                let (quotient, remainder) = Self::truncating_divmod(name, &dividend, &divisor)?;
+                Ok(match name {
+                    "idiv" => quotient,
+                    "mod" => remainder,
+                    _ => Value::Array(vec![quotient, remainder]),
+                })
+            }
+            // sum([]) is 0 like an empty total; avg([]) is NaN since there is no mean to report
+            "sum" | "avg" => {
+                let numbers = match self.eval(&args[0])? {
+                    Value::Array(items) => Self::array_numbers(name, &items)?,
+                    other => return Err(MintasError::TypeError {
+                        message: format!("{}() expects an array, got {}", name, other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                let total: f64 = numbers.iter().sum();
+                if name == "sum" {
+                    Ok(Value::Number(total))
+                } else if numbers.is_empty() {
+                    Ok(Value::Number(f64::NAN))
+                } else {
+                    Ok(Value::Number(total / numbers.len() as f64))
+                }
+            }
+            "count" if !shadowed => {
+                let items = match self.eval(&args[0])? {
+                    Value::Array(items) => items,
+                    other => return Err(MintasError::TypeError {
+                        message: format!("count() expects an array, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                if args.len() == 1 {
+                    return Ok(Value::Number(items.len() as f64));
+                }
+                let (func_name, func) = self.function_arg(&args[1], "count")?;
+                let mut count = 0;
+                for item in items {
+                    if self.invoke_function(&func_name, &func, vec![item])?.is_truthy() {
+                        count += 1;
+                    }
+                }
+                Ok(Value::Number(count as f64))
+            }
+            "validate" => {
+                let (data, rules) = match (self.eval(&args[0])?, self.eval(&args[1])?) {
+                    (Value::Table(data), Value::Table(rules)) => (data, rules),
+                    (data, rules) => return Err(MintasError::TypeError {
+                        message: format!("validate() expects a data table and a rules table, got {} and {}", data.type_name(), rules.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                let errors = validate_module::rule_errors(&data, &rules);
+                let mut result = Table::new();
+                result.insert("valid".to_string(), Value::Boolean(errors.is_empty()));
+                result.insert("errors".to_string(), Value::Table(errors));
+                Ok(Value::Table(result))
+            }
+            "equals" => {
+                let (left, right) = (self.eval(&args[0])?, self.eval(&args[1])?);
+                Ok(Value::Boolean(left.deep_equals(&right)))
+            }
+            "string_builder" => {
+                let initial = match args.first().map(|arg| self.eval(arg)).transpose()? {
+                    None => String::new(),
+                    Some(Value::String(text)) => text,
+                    Some(other) => return Err(MintasError::TypeError {
+                        message: format!("string_builder() expects a string to start from, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                Ok(Value::Builder(Arc::new(Mutex::new(initial))))
+            }
+            // Inverse of `t.entries()`; a repeated key keeps its last value
+            "table_from_entries" => {
+                let entries = match self.eval(&args[0])? {
+                    Value::Array(entries) => entries,
+                    other => return Err(MintasError::TypeError {
+                        message: format!("table_from_entries() expects an array of [key, value] pairs, got {}", other.type_name()),
+                        location: Self::default_location(),
+                    }),
+                };
+                let mut table = Table::new();
+                for (index, entry) in entries.into_iter().enumerate() {
+                    match entry {
+                        Value::Array(pair) if pair.len() == 2 && matches!(pair[0], Value::String(_) | Value::Number(_) | Value::Int(_) | Value::Boolean(_)) => {
+                            let mut pair = pair.into_iter();
+                            if let (Some(key), Some(value)) = (pair.next(), pair.next()) {
+                                table.insert(HashableValue::from_value(&key)?, value);
+                            }
+                        }
+                        Value::Array(pair) => return Err(MintasError::TypeError {
+                            message: format!("table_from_entries(): entry {} must be a [key, value] pair with a string, number or boolean key, got {}", index + 1,
+                                if pair.len() == 2 { format!("a key of type {}", pair[0].type_name()) } else { format!("{} items", pair.len()) }),
+                            location: Self::default_location(),
+                        }),
+                        other => return Err(MintasError::TypeError {
+                            message: format!("table_from_entries(): entry {} must be a [key, value] pair, got {}", index + 1, other.type_name()),
+                            location: Self::default_location(),
+                        }),
+                    }
                 }
+                Ok(Value::Table(table))
+            }
+            "assert" => {
                 let condition = self.eval(&args[0])?;
                 let is_true = condition.is_truthy();
                 if !is_true {
@@ -3761,17 +5200,10 @@ impl Evaluator {
                 Ok(Value::Boolean(true))
             }
             "test" => {
-                if args.len() < 2 {
-                    return Err(MintasError::InvalidArgumentCount {
-                        function: "test".to_string(),
-                        expected: 2,
-                        got: args.len(),
-                        location: Self::default_location(),
-                    });
-                }
                 let test_name_val = self.eval(&args[0])?;
                 let test_name = self.value_to_string(&test_name_val);
                 let result = self.eval(&args[1]);
+                self.flush_all_buffers()?;
                 match result {
                     Ok(_) => {
                         println!("✓ Test '{}' passed", test_name);
@@ -3785,57 +5217,73 @@ impl Evaluator {
             }
             _ => {
                 if let Some(dot_pos) = name.find('.') {
-                    let module_name = &name[..dot_pos];
+                    let module_name = self.resolve_module_alias(&name[..dot_pos]);
                     let func_name = &name[dot_pos + 1..];
-                    let mut evaluated_args = Vec::new();
-                    for arg in args {
-                        evaluated_args.push(self.eval(arg)?);
-                    }
-                    match module_name {
-                        "math" => {
-                            return math_module::MathModule::call_function(func_name, &evaluated_args);
+                    let is_builtin = matches!(module_name.as_str(), "math" | "datetime" | "json");
+                    if is_builtin || !(self.functions.contains_key(name) || self.native_functions.contains_key(name)) {
+                        let mut evaluated_args = Vec::new();
+                        for arg in args {
+                            evaluated_args.push(self.eval(arg)?);
                         }
-                        #[cfg(feature = "datetime")]
-                        "datetime" => {
-                            return datetime_module::DateTimeModule::call_function(func_name, &evaluated_args);
-                        }
-                        #[cfg(not(feature = "datetime"))]
-                        "datetime" => {
-                            return Err(MintasError::RuntimeError {
-                                message: "DateTime module not available. Compile with --features datetime".to_string(),
-                                location: Self::default_location(),
-                            });
-                        }
-                        #[cfg(feature = "json")]
-                        "json" => {
-                            return json_module::JsonModule::call_function(func_name, &evaluated_args);
-                        }
-                        #[cfg(not(feature = "json"))]
-                        "json" => {
-                            return Err(MintasError::RuntimeError {
-                                message: "JSON module not available. Compile with --features json".to_string(),
-                                location: Self::default_location(),
-                            });
-                        }
-                        _ => {
-                            return Err(MintasError::UnknownFunction {
-                                name: format!("Unknown module '{}'", module_name),
-                                location: Self::default_location(),
-                            });
+                        match module_name.as_str() {
+                            "math" => {
+                                return math_module::MathModule::call_function(func_name, &evaluated_args);
+                            }
+                            #[cfg(feature = "datetime")]
+                            "datetime" => {
+                                return datetime_module::DateTimeModule::call_function(func_name, &evaluated_args);
+                            }
+                            #[cfg(not(feature = "datetime"))]
+                            "datetime" => {
+                                return Err(MintasError::RuntimeError {
+                                    message: "DateTime module not available. Compile with --features datetime".to_string(),
+                                    location: Self::default_location(),
+                                });
+                            }
+                            #[cfg(feature = "json")]
+                            "json" => {
+                                return json_module::JsonModule::call_function(func_name, &evaluated_args);
+                            }
+                            #[cfg(not(feature = "json"))]
+                            "json" => {
+                                return Err(MintasError::RuntimeError {
+                                    message: "JSON module not available. Compile with --features json".to_string(),
+                                    location: Self::default_location(),
+                                });
+                            }
+                            _ => {
+                                return Err(MintasError::UnknownFunction {
+                                    name: format!("Unknown module '{}'", module_name),
+                                    location: Self::default_location(),
+                                });
+                            }
                         }
                     }
                 }
                 let func = if let Some(f) = self.functions.get(name) {
                     f.clone()
+                } else if let Some(f) = self.current_module_scope().and_then(|scope| scope.functions.get(name)) {
+                    f.clone()
                 } else if let Some(Value::Function(f)) = self.variables.get(name) {
                     f.as_ref().clone()
+                } else if let Some(native) = self.native_functions.get(name).cloned() {
+                    let mut arg_values = Vec::new();
+                    for arg_expr in args {
+                        arg_values.push(self.eval(arg_expr)?);
+                    }
+                    return native(&arg_values);
+                } else if self.classes.contains_key(name) {
+                    let mut arg_values = Vec::new();
+                    for arg_expr in args {
+                        arg_values.push(self.eval(arg_expr)?);
+                    }
+                    return self.instantiate(name, arg_values);
                 } else {
                     return Err(MintasError::UnknownFunction {
                         name: name.to_string(),
                         location: Self::default_location(),
                     });
                 };
-                self.check_recursion_limit()?;
                 if func.params.len() != args.len() {
                     return Err(MintasError::InvalidArgumentCount {
                         function: name.to_string(),
@@ -3848,19 +5296,269 @@ impl Evaluator {
                 for arg_expr in args {
                     arg_values.push(self.eval(arg_expr)?);
                 }
-                let old_vars = self.variables.clone();
-                for (param, arg_val) in func.params.iter().zip(arg_values.iter()) {
-                    self.variables.insert(param.clone(), arg_val.clone());
+                self.invoke_function(name, &func, arg_values)
+            }
+        }
+    }
+    /// Quotient and remainder for `idiv`/`mod`/`divmod`. Both truncate toward zero like `%`,
+    /// so the remainder takes the dividend's sign: divmod(-7, 2) is [-3, -1], not [-4, 1].
+    fn truncating_divmod(name: &str, dividend: &Value, divisor: &Value) -> MintasResult<(Value, Value)> {
+        let (a, b) = match (dividend.as_f64(), divisor.as_f64()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Err(MintasError::TypeError {
+                message: format!("{}() expects two numbers, got {} and {}", name, dividend.type_name(), divisor.type_name()),
+                location: Self::default_location(),
+            }),
+        };
+        if b == 0.0 {
+            return Err(MintasError::DivisionByZero { location: Self::default_location() });
+        }
+        if let (Some(x), Some(y)) = (dividend.exact_int(), divisor.exact_int()) {
+            if let (Some(q), Some(r)) = (x.checked_div(y), x.checked_rem(y)) {
+                return Ok((Value::from_i64(q), Value::from_i64(r)));
+            }
+        }
+        Ok((Value::Number((a / b).trunc()), Value::Number(a % b)))
+    }
+    /// Elements of an array passed to `name`, which must all be numbers
+    fn array_numbers(name: &str, items: &[Value]) -> MintasResult<Vec<f64>> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| {
+                item.as_f64().ok_or_else(|| MintasError::TypeError {
+                    message: format!("{}() expects an array of numbers, but element {} is {}", name, idx + 1, item.type_name()),
+                    location: Self::default_location(),
+                })
+            })
+            .collect()
+    }
+    /// Runs a user function body with `arg_values` bound to its parameters. Callers check arity.
+    /// The body gets a fresh scope chained to the global one, so it sees globals but not its
+    /// caller's locals, and its assignments are local unless the name was declared `global`.
+    /// Lambdas don't capture, so theirs chains to the calling scope, where they are usually
+    /// defined.
+    fn invoke_function(&mut self, name: &str, func: &Function, arg_values: Vec<Value>) -> MintasResult<Value> {
+        self.invoke_with_receiver(name, func, arg_values, None).map(|(result, _)| result)
+    }
+    /// `invoke_function` with `self` bound to `receiver`; also returns `self` as the body left it
+    fn invoke_with_receiver(&mut self, name: &str, func: &Function, arg_values: Vec<Value>, receiver: Option<Value>) -> MintasResult<(Value, Option<Value>)> {
+        self.check_recursion_limit()?;
+        self.call_stack.push(name.to_string());
+        self.store_globals();
+        let scope = match self.scope_stack.first() {
+            Some(frame) if !func.is_lambda => frame.variables.clone(),
+            _ => self.variables.clone(),
+        };
+        self.scope_stack.push(ScopeFrame {
+            variables: std::mem::replace(&mut self.variables, scope),
+            global_names: std::mem::take(&mut self.global_names),
+        });
+        let has_receiver = receiver.is_some();
+        if let Some(receiver) = receiver {
+            self.variables.insert("self".to_string(), receiver);
+        }
+        for (param, arg_val) in func.params.iter().zip(arg_values) {
+            self.variables.insert(param.clone(), arg_val);
+        }
+        let caller_module = self.current_module.clone();
+        if let Some(owner) = self.function_owners.get(name) {
+            self.current_module = Some(owner.clone());
+        }
+        let result = self.eval_block(&func.body);
+        self.current_module = caller_module;
+        self.call_stack.pop();
+        self.security_monitor.exit_recursion();
+        self.store_globals();
+        let receiver = if has_receiver { self.variables.remove("self") } else { None };
+        let caller = self.scope_stack.pop().expect("function scope was pushed above");
+        self.variables = caller.variables;
+        self.global_names = caller.global_names;
+        self.load_globals();
+        match result {
+            Ok(Value::ReturnSignal(ret_val)) => Ok((*ret_val, receiver)),
+            Ok(other) => Ok((other, receiver)),
+            Err(e) => Err(e),
+        }
+    }
+    /// Members of `class_name` and its ancestors, base class first so subclasses override
+    fn class_members(&self, class_name: &str) -> MintasResult<Vec<ClassMember>> {
+        let mut chain: Vec<&Class> = Vec::new();
+        let mut current = Some(class_name);
+        while let Some(name) = current {
+            if chain.iter().any(|c| c.name == name) {
+                return Err(MintasError::RuntimeError {
+                    message: format!("Class '{}' inherits from itself", name),
+                    location: Self::default_location(),
+                });
+            }
+            let class = self.classes.get(name).ok_or_else(|| MintasError::RuntimeError {
+                message: format!("Class '{}' not found", name),
+                location: Self::default_location(),
+            })?;
+            current = class.parent();
+            chain.push(class);
+        }
+        Ok(chain.into_iter().rev().flat_map(|c| c.members.iter().cloned()).collect())
+    }
+    /// The method `class_name` responds to as `method`, searching up through its ancestors.
+    /// Also returns the class that defines it, which `super` inside it resolves against.
+    fn find_method(&self, class_name: &str, method: &str) -> Option<(String, Function)> {
+        let mut current = self.classes.get(class_name);
+        let mut depth = 0;
+        while let Some(class) = current {
+            for member in &class.members {
+                if let ClassMember::Method { name, params, body, .. } = member {
+                    if name == method {
+                        return Some((class.name.clone(), Function { params: params.clone(), body: body.clone(), is_lambda: false }));
+                    }
                 }
-                let result = self.eval_block(&func.body);
-                self.security_monitor.exit_recursion();
-                self.variables = old_vars;
-                match result {
-                    Ok(Value::ReturnSignal(ret_val)) => Ok(*ret_val),
-                    other => other,
+            }
+            // A cycle in `extends` would loop forever; the registry bounds any real chain
+            depth += 1;
+            current = class.parent().filter(|_| depth < self.classes.len()).and_then(|parent| self.classes.get(parent));
+        }
+        None
+    }
+    /// Builds an instance: a table of the class's fields tagged with `__class__`, passed
+    /// through `init` when the class defines or inherits one
+    fn instantiate(&mut self, class_name: &str, arg_values: Vec<Value>) -> MintasResult<Value> {
+        let mut fields = Table::new();
+        for member in self.class_members(class_name)? {
+            if let ClassMember::Property { name, initial_value, .. } = member {
+                let value = match initial_value {
+                    Some(init) => self.eval(&init)?,
+                    None => Value::Empty,
+                };
+                fields.insert(name, value);
+            }
+        }
+        fields.insert(CLASS_TAG.to_string(), Value::String(class_name.to_string()));
+        let instance = Value::Table(fields);
+        let (defined_in, init) = match self.find_method(class_name, "init") {
+            Some(init) => init,
+            None if arg_values.is_empty() => return Ok(instance),
+            None => return Err(MintasError::InvalidArgumentCount {
+                function: class_name.to_string(),
+                expected: 0,
+                got: arg_values.len(),
+                location: Self::default_location(),
+            }),
+        };
+        if init.params.len() != arg_values.len() {
+            return Err(MintasError::InvalidArgumentCount {
+                function: format!("{}.init", class_name),
+                expected: init.params.len(),
+                got: arg_values.len(),
+                location: Self::default_location(),
+            });
+        }
+        let (_, instance) = self.invoke_method(&defined_in, "init", &init, arg_values, instance)?;
+        Ok(instance.unwrap_or(Value::Empty))
+    }
+    /// Runs a method body with `self` bound, returning its result and `self` as it was left
+    fn invoke_method(&mut self, defined_in: &str, method: &str, func: &Function, arg_values: Vec<Value>, receiver: Value) -> MintasResult<(Value, Option<Value>)> {
+        let name = format!("{}.{}", defined_in, method);
+        let caller_class = self.method_class.replace(defined_in.to_string());
+        let result = self.invoke_with_receiver(&name, func, arg_values, Some(receiver));
+        self.method_class = caller_class;
+        result
+    }
+    /// Calls a method with `self` bound to `receiver`, then writes the updated `self` back
+    /// to the variable the method was called on
+    fn call_method(&mut self, defined_in: &str, method: &str, func: &Function, receiver: Value, args: &[Expr], object: &Expr) -> MintasResult<Value> {
+        if func.params.len() != args.len() {
+            return Err(MintasError::InvalidArgumentCount {
+                function: format!("{}.{}", defined_in, method),
+                expected: func.params.len(),
+                got: args.len(),
+                location: Self::default_location(),
+            });
+        }
+        let mut arg_values = Vec::new();
+        for arg in args {
+            arg_values.push(self.eval(arg)?);
+        }
+        let (result, updated) = self.invoke_method(defined_in, method, func, arg_values, receiver.clone())?;
+        if let Some(Value::Table(map)) = updated {
+            if Value::Table(map.clone()) != receiver {
+                self.update_table_variable(object, map)?;
+            }
+        }
+        Ok(result)
+    }
+    /// `super.method(...)`: the parent of the running method's class supplies the method,
+    /// and it runs on the same `self`
+    fn call_super(&mut self, method: &str, args: &[Expr]) -> MintasResult<Value> {
+        let (current, receiver) = match (&self.method_class, self.variables.get("self")) {
+            (Some(current), Some(receiver)) => (current.clone(), receiver.clone()),
+            _ => return Err(MintasError::RuntimeError {
+                message: "'super' can only be used inside class methods".to_string(),
+                location: Self::default_location(),
+            }),
+        };
+        let parent = self.classes.get(&current).and_then(|c| c.parent()).map(str::to_string).ok_or_else(|| MintasError::RuntimeError {
+            message: format!("Class '{}' has no parent class for super.{}()", current, method),
+            location: Self::default_location(),
+        })?;
+        let (defined_in, func) = self.find_method(&parent, method).ok_or_else(|| MintasError::RuntimeError {
+            message: format!("Class '{}' has no method '{}'", parent, method),
+            location: Self::default_location(),
+        })?;
+        self.call_method(&defined_in, method, &func, receiver, args, &Expr::Variable("self".to_string()))
+    }
+    /// Writes the current function's `global` names through to the global scope
+    fn store_globals(&mut self) {
+        if let Some(global_frame) = self.scope_stack.first_mut() {
+            for name in &self.global_names {
+                if let Some(value) = self.variables.get(name) {
+                    global_frame.variables.insert(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+    /// Refreshes the current function's `global` names, which a callee may have changed
+    fn load_globals(&mut self) {
+        if let Some(global_frame) = self.scope_stack.first() {
+            for name in &self.global_names {
+                match global_frame.variables.get(name) {
+                    Some(value) => self.variables.insert(name.clone(), value.clone()),
+                    None => self.variables.remove(name),
+                };
+            }
+        }
+    }
+    /// A builtin named where a function value is expected (`call(equals, [a, b])`), as a
+    /// lambda forwarding its parameters. Only builtins without optional or variadic parameters.
+    fn builtin_function(name: &str) -> Option<Function> {
+        let builtin = builtins::global(name)?;
+        let params: Vec<String> = builtin.params.split(", ").filter(|p| !p.is_empty()).map(str::to_string).collect();
+        if params.iter().any(|p| p.starts_with('[') || p.ends_with("...")) {
+            return None;
+        }
+        let args = params.iter().map(|p| Expr::Variable(p.clone())).collect();
+        Some(Function { params, body: vec![Expr::Call { name: name.to_string(), args, location: Self::default_location() }], is_lambda: true })
+    }
+    /// Resolves a function-valued argument: a named function or a variable holding one.
+    fn function_arg(&mut self, expr: &Expr, builtin: &str) -> MintasResult<(String, Function)> {
+        if let Expr::Variable(var_name) = expr {
+            if !self.variables.contains_key(var_name) {
+                if let Some(func) = self.functions.get(var_name).cloned().or_else(|| Self::builtin_function(var_name)) {
+                    return Ok((var_name.clone(), func));
                 }
             }
         }
+        let name = match expr {
+            Expr::Variable(var_name) => var_name.clone(),
+            _ => "<lambda>".to_string(),
+        };
+        match self.eval(expr)? {
+            Value::Function(func) => Ok((name, *func)),
+            other => Err(MintasError::TypeError {
+                message: format!("{}() expects a function, got {}", builtin, other.type_name()),
+                location: Self::default_location(),
+            }),
+        }
     }
     fn interpolate_string(&mut self, s: &str) -> MintasResult<String> {
         let mut result = String::new();
@@ -3945,19 +5643,142 @@ impl Evaluator {
             })
         }
     }
+    /// `format()` templates: `{}` takes the next argument, `{0}` a given one, `{:.2}` sets the
+    /// number precision and `{{`/`}}` are literal braces. Every argument must be used.
+    fn format_template(&self, template: &str, values: &[Value]) -> MintasResult<String> {
+        let error = |message: String| MintasError::RuntimeError { message, location: Self::default_location() };
+        let mut out = String::with_capacity(template.len());
+        let mut used = vec![false; values.len()];
+        let mut next = 0;
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '}' => return Err(error("format: unmatched '}' in template (write '}}' for a literal brace)".to_string())),
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(ch) => spec.push(ch),
+                            None => return Err(error("format: unclosed '{' in template (write '{{' for a literal brace)".to_string())),
+                        }
+                    }
+                    let (position, options) = spec.split_once(':').unwrap_or((spec.as_str(), ""));
+                    let index = if position.trim().is_empty() {
+                        next += 1;
+                        next - 1
+                    } else {
+                        position.trim().parse::<usize>()
+                            .map_err(|_| error(format!("format: invalid placeholder '{{{}}}'", spec)))?
+                    };
+                    let value = values.get(index).ok_or_else(|| error(format!(
+                        "format: placeholder {} needs argument {} but only {} given", index, index + 1, values.len()
+                    )))?;
+                    used[index] = true;
+                    if options.is_empty() {
+                        out.push_str(&self.value_to_string(value));
+                        continue;
+                    }
+                    let precision = options.strip_prefix('.').and_then(|p| p.parse::<usize>().ok())
+                        .ok_or_else(|| error(format!("format: unsupported format spec ':{}' (expected ':.N')", options)))?;
+                    match value {
+                        Value::Number(n) => out.push_str(&format!("{:.*}", precision, n)),
+                        Value::Int(n) if precision == 0 => out.push_str(&n.to_string()),
+                        Value::Int(n) => out.push_str(&format!("{}.{}", n, "0".repeat(precision))),
+                        other => return Err(MintasError::TypeError {
+                            message: format!("format: precision needs a number, got {}", other.type_name()),
+                            location: Self::default_location(),
+                        }),
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+        if let Some(unused) = used.iter().position(|u| !u) {
+            return Err(error(format!(
+                "format: argument {} is never used ({} arguments, template uses fewer)", unused + 1, values.len()
+            )));
+        }
+        Ok(out)
+    }
+    /// `format_number` options: `decimals` fixes the places (default: as many as the value has),
+    /// `grouping` (default true) inserts `separator` (default ",") every three integer digits,
+    /// and `currency` goes between the sign and the digits, as in "-$1,234.50"
+    fn format_number(n: &Value, options: &Table) -> MintasResult<String> {
+        let error = |message: String| MintasError::TypeError { message, location: Self::default_location() };
+        let (mut decimals, mut grouping, mut separator, mut currency) = (None, true, ",".to_string(), String::new());
+        for (key, value) in options {
+            let key = key.to_string();
+            match (key.as_str(), value) {
+                ("decimals", places) => match places.exact_int() {
+                    Some(places @ 0..=20) => decimals = Some(places as usize),
+                    _ => return Err(error("format_number(): decimals must be a whole number from 0 to 20".to_string())),
+                },
+                ("grouping", Value::Boolean(on)) => grouping = *on,
+                ("separator", Value::String(text)) => separator = text.clone(),
+                ("currency", Value::String(text)) => currency = text.clone(),
+                ("grouping" | "separator" | "currency", other) => return Err(error(format!(
+                    "format_number(): {} must be a {}, got {}", key, if key == "grouping" { "boolean" } else { "string" }, other.type_name()
+                ))),
+                _ => return Err(error(format!(
+                    "format_number(): unknown option '{}' (expected decimals, grouping, separator or currency)", key
+                ))),
+            }
+        }
+        let (negative, digits) = match (n, decimals) {
+            // An Int's digits are exact, so fixed places are only zeros, as in format's `{:.N}`
+            (Value::Int(i), Some(places)) if places > 0 => (*i < 0, format!("{}.{}", i.unsigned_abs(), "0".repeat(places))),
+            (Value::Int(i), _) => (*i < 0, i.unsigned_abs().to_string()),
+            (Value::Number(x), _) if !x.is_finite() => return Ok(x.to_string()),
+            (Value::Number(x), None) => (*x < 0.0, x.abs().to_string()),
+            (Value::Number(x), Some(places)) => (*x < 0.0, format!("{:.*}", places, x.abs())),
+            (other, _) => return Err(error(format!("format_number() expects a number, got {}", other.type_name()))),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits.as_str(), None),
+        };
+        let mut out = String::new();
+        // -0.001 rounded to "0.00" is not negative
+        if negative && digits.bytes().any(|b| (b'1'..=b'9').contains(&b)) {
+            out.push('-');
+        }
+        out.push_str(&currency);
+        for (i, digit) in whole.chars().enumerate() {
+            if grouping && i > 0 && (whole.len() - i) % 3 == 0 {
+                out.push_str(&separator);
+            }
+            out.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            out.push('.');
+            out.push_str(fraction);
+        }
+        Ok(out)
+    }
     fn value_to_string(&self, val: &Value) -> String {
         match val {
             Value::Number(n) => n.to_string(),
+            Value::Int(n) => n.to_string(),
             Value::String(s) => s.clone(),
             Value::Boolean(b) => b.to_string(),
             Value::Maybe => "maybe".to_string(),
             Value::Empty => "empty".to_string(),
             Value::Array(_) => "[array]".to_string(),
             Value::Table(_) => "{table}".to_string(),
+            Value::Bytes(b) => format!("<bytes:{}>", b.len()),
             Value::SuperSet(inner) => format!("spr{{{}}}", self.value_to_string(inner)),
+            Value::Builder(buffer) => format!("<builder:{}>", lock_builder(buffer).chars().count()),
             Value::Function(_) => "<function>".to_string(),
             Value::Class(c) => format!("<class:{}>", c.name),
-            Value::Instance(i) => format!("<instance:{}>", i.class_name),
             Value::ExitSignal => "exit".to_string(),
             Value::ProceedSignal => "proceed".to_string(),
             Value::ReturnSignal(_) => "return".to_string(),
@@ -3965,8 +5786,10 @@ impl Evaluator {
         }
     }
     pub fn print_value(&self, val: &Value) {
+        let _ = self.stdout_buffer.borrow_mut().flush();
         match val {
             Value::Number(n) => print!("{}", n),
+            Value::Int(n) => print!("{}", n),
             Value::String(s) => print!("{}", s),
             Value::Boolean(b) => print!("{}", b),
             Value::Maybe => print!("maybe"),
@@ -3990,11 +5813,14 @@ impl Evaluator {
             }
             Value::Table(map) => {
                 print!("{{");
-                for (i, (k, v)) in map.iter().enumerate() {
+                for (i, (k, v)) in map.iter().filter(|(k, _)| k.as_str() != Some(CLASS_TAG)).enumerate() {
                     if i > 0 {
                         print!(", ");
                     }
-                    print!("\"{}\" = ", k);
+                    match k {
+                        HashableValue::String(k) => print!("\"{}\" = ", k),
+                        k => print!("{} = ", k),
+                    }
                     match v {
                         Value::Number(n) => print!("{}", n),
                         Value::String(s) => print!("\"{}\"", s),
@@ -4007,14 +5833,15 @@ impl Evaluator {
                 }
                 print!("}}");
             }
+            Value::Bytes(b) => print!("<bytes:{}>", b.len()),
             Value::SuperSet(inner) => {
                 print!("spr{{");
                 self.print_value(inner);
                 print!("}}");
             }
+            Value::Builder(buffer) => print!("<builder:{}>", lock_builder(buffer).chars().count()),
             Value::Function(_) => print!("<function>"),
             Value::Class(c) => print!("<class:{}>", c.name),
-            Value::Instance(i) => print!("<instance:{}>", i.class_name),
             Value::ExitSignal | Value::ProceedSignal | Value::ReturnSignal(_) => {},
             Value::Null => print!("null"),
         }
@@ -4022,6 +5849,7 @@ impl Evaluator {
     pub fn write_value_to_buffer<W: Write>(&self, val: &Value, writer: &mut W) -> MintasResult<()> {
         let result = match val {
             Value::Number(n) => write!(writer, "{}", n),
+            Value::Int(n) => write!(writer, "{}", n),
             Value::String(s) => write!(writer, "{}", s),
             Value::Boolean(b) => write!(writer, "{}", b),
             Value::Maybe => write!(writer, "maybe"),
@@ -4045,11 +5873,14 @@ impl Evaluator {
             }
             Value::Table(map) => {
                 write!(writer, "{{")?;
-                for (i, (k, v)) in map.iter().enumerate() {
+                for (i, (k, v)) in map.iter().filter(|(k, _)| k.as_str() != Some(CLASS_TAG)).enumerate() {
                     if i > 0 {
                         write!(writer, ", ")?;
                     }
-                    write!(writer, "\"{}\" = ", k)?;
+                    match k {
+                        HashableValue::String(k) => write!(writer, "\"{}\" = ", k)?,
+                        k => write!(writer, "{} = ", k)?,
+                    }
                     match v {
                         Value::Number(n) => write!(writer, "{}", n)?,
                         Value::String(s) => write!(writer, "\"{}\"", s)?,
@@ -4062,14 +5893,15 @@ impl Evaluator {
                 }
                 write!(writer, "}}")
             }
+            Value::Bytes(b) => write!(writer, "<bytes:{}>", b.len()),
             Value::SuperSet(inner) => {
                 write!(writer, "spr{{")?;
                 self.write_value_to_buffer(inner, writer)?;
                 write!(writer, "}}")
             }
+            Value::Builder(buffer) => write!(writer, "<builder:{}>", lock_builder(buffer).chars().count()),
             Value::Function(_) => write!(writer, "<function>"),
             Value::Class(c) => write!(writer, "<class:{}>", c.name),
-            Value::Instance(i) => write!(writer, "<instance:{}>", i.class_name),
             Value::ExitSignal | Value::ProceedSignal | Value::ReturnSignal(_) => Ok(()),
             Value::Null => write!(writer, "null"),
         };
@@ -4078,7 +5910,6 @@ impl Evaluator {
             location: Self::default_location(),
         })
     }
-    #[allow(dead_code)]
     pub fn flush_all_buffers(&mut self) -> MintasResult<()> {
         self.stdout_buffer.borrow_mut().flush().map_err(|e| MintasError::RuntimeError {
             message: format!("Failed to flush output buffer: {}", e),
@@ -4086,4 +5917,1183 @@ impl Evaluator {
         })?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn eval_code(code: &str) -> MintasResult<Value> {
+        let mut lexer = Lexer::new(code);
+        let tokens = lexer.tokenize()?;
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse()?;
+        let mut evaluator = Evaluator::new();
+        let mut result = Value::Empty;
+        for expr in &ast {
+            result = evaluator.eval(expr)?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_array_spread_concatenates_in_order() {
+        let result = eval_code("a = [1, 2]\nb = [3]\nc = [0, ...a, ...b, 4]\nc").unwrap();
+        let expected: Vec<Value> = [0.0, 1.0, 2.0, 3.0, 4.0].iter().map(|n| Value::Number(*n)).collect();
+        assert_eq!(result, Value::Array(expected));
+    }
+
+    #[test]
+    fn test_table_spread_later_keys_win() {
+        let result = eval_code("base = {a = 1, b = 2}\n{...base, b = 3, c = 4}").unwrap();
+        let mut expected = Table::new();
+        expected.insert("a".to_string(), Value::Number(1.0));
+        expected.insert("b".to_string(), Value::Number(3.0));
+        expected.insert("c".to_string(), Value::Number(4.0));
+        assert_eq!(result, Value::Table(expected));
+
+        let result = eval_code("extra = {b = 9}\n{b = 3, ...extra}").unwrap();
+        let mut expected = Table::new();
+        expected.insert("b".to_string(), Value::Number(9.0));
+        assert_eq!(result, Value::Table(expected));
+    }
+
+    #[test]
+    fn test_spread_type_mismatch() {
+        assert!(eval_code("t = {a = 1}\n[...t]").is_err());
+        assert!(eval_code("a = [1]\n{...a}").is_err());
+    }
+
+    #[test]
+    fn test_to_number_edge_cases() {
+        assert_eq!(eval_code("to_number(\" 42.5 \")").unwrap(), Value::Number(42.5));
+        assert!(eval_code("to_number(\"\")").is_err());
+        assert!(eval_code("to_number(\"12abc\")").is_err());
+        match eval_code("to_number(\"inf\")").unwrap() {
+            Value::Number(n) => assert!(n.is_infinite() && n > 0.0),
+            other => panic!("expected number, got {:?}", other),
+        }
+        match eval_code("to_number(\"NaN\")").unwrap() {
+            Value::Number(n) => assert!(n.is_nan()),
+            other => panic!("expected number, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_numeric_builtins() {
+        assert_eq!(eval_code("round(2.456, 2)").unwrap(), Value::Number(2.46));
+        assert_eq!(eval_code("round(-2.5)").unwrap(), Value::Number(-3.0));
+        assert_eq!(eval_code("floor(-1.5)").unwrap(), Value::Number(-2.0));
+        assert_eq!(eval_code("ceil(1.2)").unwrap(), Value::Number(2.0));
+        assert_eq!(eval_code("abs(-3)").unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("min(3, 1, 2)").unwrap(), Value::Number(1.0));
+        assert_eq!(eval_code("max(3, 1, 2)").unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("pow(2, 10)").unwrap(), Value::Number(1024.0));
+        assert_eq!(eval_code("to_string(3)").unwrap(), Value::String("3".to_string()));
+        assert_eq!(eval_code("to_string(pow(10, 400))").unwrap(), Value::String("inf".to_string()));
+        match eval_code("sqrt(-1)").unwrap() {
+            Value::Number(n) => assert!(n.is_nan()),
+            other => panic!("expected number, got {:?}", other),
+        }
+        assert!(eval_code("min()").is_err());
+    }
+
+    #[test]
+    fn test_integer_division_truncates_toward_zero() {
+        assert_eq!(eval_code("idiv(7, 2)").unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("idiv(-7, 2)").unwrap(), Value::Number(-3.0));
+        assert_eq!(eval_code("idiv(7, -2)").unwrap(), Value::Number(-3.0));
+        assert_eq!(eval_code("mod(-7, 2)").unwrap(), Value::Number(-1.0));
+        assert_eq!(eval_code("mod(7, -2)").unwrap(), Value::Number(1.0));
+        assert_eq!(eval_code("mod(7.5, 2)").unwrap(), Value::Number(1.5));
+        assert_eq!(eval_code("divmod(-7, 2)").unwrap(), Value::Array(vec![Value::Number(-3.0), Value::Number(-1.0)]));
+        assert_eq!(eval_code("idiv(9007199254740993, 1)").unwrap(), Value::Int(9_007_199_254_740_993));
+
+        assert!(matches!(eval_code("idiv(1, 0)"), Err(MintasError::DivisionByZero { .. })));
+        assert!(matches!(eval_code("mod(1, 0)"), Err(MintasError::DivisionByZero { .. })));
+        assert!(matches!(eval_code("divmod(0, 0)"), Err(MintasError::DivisionByZero { .. })));
+        assert!(eval_code("idiv(\"7\", 2)").is_err());
+    }
+
+    #[test]
+    fn test_strings_and_arrays_repeat_by_integer_count() {
+        assert_eq!(eval_code("\"=\" * 5").unwrap(), Value::String("=====".to_string()));
+        assert_eq!(eval_code("3 * \"ab\"").unwrap(), Value::String("ababab".to_string()));
+        assert_eq!(eval_code("\"ab\" * 0").unwrap(), Value::String(String::new()));
+        assert_eq!(eval_code("[0] * 3").unwrap(), Value::Array(vec![Value::Number(0.0); 3]));
+        assert_eq!(
+            eval_code("2 * [1, \"x\"]").unwrap(),
+            Value::Array(vec![Value::Number(1.0), Value::String("x".to_string()), Value::Number(1.0), Value::String("x".to_string())])
+        );
+        assert_eq!(eval_code("[1, 2] * 0").unwrap(), Value::Array(Vec::new()));
+        assert_eq!(eval_code("(\"-\" * 100000).len()").unwrap(), Value::Number(100000.0));
+        assert_eq!(eval_code("([0] * 100000).len()").unwrap(), Value::Number(100000.0));
+
+        assert!(eval_code("\"a\" * -1").is_err());
+        assert!(eval_code("[0] * 1.5").is_err());
+        assert!(eval_code("\"a\" * \"b\"").is_err());
+        assert!(eval_code("\"abc\" * 9007199254740991").is_err());
+        // Empty operands finish at once and oversized results fail before allocating
+        assert_eq!(eval_code("\"\" * 1e15").unwrap(), Value::String(String::new()));
+        assert_eq!(eval_code("[] * 1e15").unwrap(), Value::Array(Vec::new()));
+        assert!(eval_code("\"ab\" * 1e15").is_err());
+        assert!(eval_code("[0] * 1e15").is_err());
+    }
+
+    #[test]
+    fn test_format_number_groups_thousands() {
+        let text = |code: &str| match eval_code(code).unwrap() {
+            Value::String(s) => s,
+            other => panic!("{} gave {:?}", code, other),
+        };
+        assert_eq!(text("format_number(1234567.5, {grouping = true, decimals = 2})"), "1,234,567.50");
+        assert_eq!(text("format_number(1234567.5)"), "1,234,567.5");
+        assert_eq!(text("format_number(-1234.567, {decimals = 2, currency = \"$\"})"), "-$1,234.57");
+        assert_eq!(text("format_number(-0.001, {decimals = 2})"), "0.00");
+        assert_eq!(text("format_number(999.5, {decimals = 0})"), "1,000");
+        assert_eq!(text("format_number(123456789012345678)"), "123,456,789,012,345,678");
+        assert_eq!(text("format_number(123456789012345678, {decimals = 2})"), "123,456,789,012,345,678.00");
+        assert_eq!(text("format_number(-9007199254740993, {decimals = 0})"), "-9,007,199,254,740,993");
+        assert_eq!(text("format_number(1e21, {decimals = 0})"), "1,000,000,000,000,000,000,000");
+        assert_eq!(text("format_number(100, {decimals = 1})"), "100.0");
+        assert_eq!(text("format_number(1234567, {separator = \" \"})"), "1 234 567");
+        assert_eq!(text("format_number(1234567, {grouping = false})"), "1234567");
+        assert!(eval_code("format_number(1, {decimals = -1})").is_err());
+        assert!(eval_code("format_number(1, {places = 2})").is_err());
+        assert!(eval_code("format_number(\"12\")").is_err());
+    }
+
+    #[test]
+    fn test_string_builder_appends_in_place() {
+        let code = "sb = string_builder(\"a\")\nsb.append(\"b\")\nsb.append(1)\nsb.append(true)\nfirst = sb.build()\n\
+            other = sb\nother.append(\"!\")\nfunc shout(b):\n    b.append(\"?\")\nend\nshout(sb)\ncopy = clone(sb)\ncopy.append(\"lost\")\n";
+        let eval = |expr: &str| eval_code(&format!("{}{}", code, expr)).unwrap();
+        // `build` snapshots: `first` keeps its text while appends through any binding carry on
+        assert_eq!(eval("first"), Value::String("ab1true".to_string()));
+        assert_eq!(eval("sb.build()"), Value::String("ab1true!?".to_string()));
+        assert_eq!(eval("sb.len()"), Value::Number(9.0));
+        assert_eq!(eval("copy.build()"), Value::String("ab1true!?lost".to_string()));
+        assert_eq!(eval("found = [sb == other, sb == copy, typeof(sb)]"), eval_code("[true, false, \"builder\"]").unwrap());
+        assert!(eval_code("string_builder().append([1])").unwrap_err().to_string().contains("append expects a string, number or boolean, got array"));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_string_builder_vs_concatenation() {
+        let time = |code: &str| {
+            let start = std::time::Instant::now();
+            let result = eval_code(code).unwrap();
+            (result, start.elapsed())
+        };
+        let pieces = "i = 0\nwhile (i < 100000):\n    i = i + 1\n";
+        let (concatenated, concat_time) = time(&format!("s = \"\"\n{}    s = s + \"piece\"\nend\ns", pieces));
+        let (built, builder_time) = time(&format!("sb = string_builder()\n{}    sb.append(\"piece\")\nend\nsb.build()", pieces));
+        assert_eq!(concatenated, built);
+        println!(
+            "100k pieces: {:.1?} concatenating, {:.1?} with a builder ({:.2}x)",
+            concat_time,
+            builder_time,
+            concat_time.as_secs_f64() / builder_time.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_chained_comparisons_evaluate_middle_operands_once() {
+        let yes = |code: &str| assert_eq!(eval_code(code).unwrap(), Value::Boolean(true), "{}", code);
+        let no = |code: &str| assert_eq!(eval_code(code).unwrap(), Value::Boolean(false), "{}", code);
+        yes("x = 5\n0 < x < 10");
+        no("x = 15\n0 < x < 10");
+        yes("1 <= 1 < 2 >= 0");
+        no("1 < 3 < 2 < 4");
+        yes("10 > 5 > 2 > 1");
+        // Equality still associates left: (1 < 2) == true
+        yes("1 < 2 == true");
+        let counted = "calls = 0\nfunc mid():\n    global calls\n    calls += 1\n    return 5\nend\nok = 0 < mid() < 10 <= mid() * 2\n";
+        assert_eq!(eval_code(&format!("{}ok", counted)).unwrap(), Value::Boolean(true));
+        assert_eq!(eval_code(&format!("{}calls", counted)).unwrap(), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_equals_compares_structure_deeply() {
+        let yes = |code: &str| assert_eq!(eval_code(code).unwrap(), Value::Boolean(true), "{}", code);
+        let no = |code: &str| assert_eq!(eval_code(code).unwrap(), Value::Boolean(false), "{}", code);
+        yes("equals([1, [2, {a = [3]}]], [1, [2, {a = [3]}]])");
+        yes("equals({a = 1, b = {c = \"x\"}}, {b = {c = \"x\"}, a = 1})");
+        assert!(Value::Null.deep_equals(&Value::Empty));
+        assert!(Value::Array(vec![Value::Null]).deep_equals(&Value::Array(vec![Value::Empty])));
+        yes("equals(3, 3.0)");
+        no("equals(1, \"1\")");
+        no("equals([1, \"2\"], [1, 2])");
+        no("equals([1, 2], [2, 1])");
+        no("equals({a = 1}, {a = 1, b = 2})");
+        no("equals([], {})");
+        no("equals(empty, 0)");
+        // `==` shares the deep comparison for arrays and tables but keeps its loose top level
+        yes("[1, {a = [2]}] == [1, {a = [2]}]");
+        yes("{a = 1} != {a = 2}");
+        yes("1 == \"1\"");
+        // Usable wherever a function value is expected
+        yes("call(equals, [[1], [1]])");
+        assert_eq!(eval_code("arity(equals)").unwrap(), Value::Number(2.0));
+        yes("[[1, 2], [1, 2]].reduce(equals)");
+    }
+
+    #[test]
+    fn test_table_entries_round_trip() {
+        let pair = |k: &str, v: f64| Value::Array(vec![Value::String(k.to_string()), Value::Number(v)]);
+        assert_eq!(eval_code("{b = 2, a = 1, c = 3}.entries()").unwrap(), Value::Array(vec![pair("a", 1.0), pair("b", 2.0), pair("c", 3.0)]));
+        assert_eq!(eval_code("{}.entries()").unwrap(), Value::Array(Vec::new()));
+        let doubled = eval_code(
+            "t = {x = 1, y = 2}\nlamda double(pair): [pair[1], pair[2] * 2]\ntable_from_entries(t.entries().map(double))",
+        ).unwrap();
+        assert_eq!(doubled, eval_code("{x = 2, y = 4}").unwrap());
+        assert_eq!(eval_code("table_from_entries({k = \"v\", n = [1]}.entries())").unwrap(), eval_code("{k = \"v\", n = [1]}").unwrap());
+        assert_eq!(eval_code("table_from_entries([[\"a\", 1], [\"a\", 2]])").unwrap(), eval_code("{a = 2}").unwrap());
+
+        assert!(eval_code("table_from_entries({a = 1})").is_err());
+        assert!(eval_code("table_from_entries([[\"a\"]])").unwrap_err().to_string().contains("entry 1"));
+        assert!(eval_code("table_from_entries([[\"a\", 1], [[2], 3]])").unwrap_err().to_string().contains("entry 2 must be a [key, value] pair with a string, number or boolean key, got a key of type array"));
+    }
+
+    #[test]
+    fn test_tables_take_number_and_boolean_keys() {
+        let code = "t = {name = \"a\"}\nt.set(1, \"one\")\nt.set(\"1\", \"string one\")\nt.set(true, \"yes\")\nt.set(2.5, \"half\")\n";
+        let eval = |expr: &str| eval_code(&format!("{}{}", code, expr)).unwrap();
+        assert_eq!(eval("t[1]"), Value::String("one".to_string()));
+        assert_eq!(eval("t[1.0]"), Value::String("one".to_string()));
+        assert_eq!(eval("t[\"1\"]"), Value::String("string one".to_string()));
+        assert_eq!(eval("found = [t[true], t[2.5], t.name]"), eval_code("[\"yes\", \"half\", \"a\"]").unwrap());
+        assert_eq!(eval("t.len()"), Value::Number(5.0));
+        // Numbers sort first, then strings, then booleans
+        assert_eq!(eval("t.keys()"), eval_code("[1, 2.5, \"1\", \"name\", true]").unwrap());
+        assert_eq!(eval("t.remove(1)\nfound = [t.has(1), t.has(\"1\")]"), eval_code("[false, true]").unwrap());
+        assert_eq!(eval_code("table_from_entries([[404, \"missing\"], [false, 0]])[404]").unwrap(), Value::String("missing".to_string()));
+
+        let err = eval_code("t = {}\nt.set([1], 2)").unwrap_err().to_string();
+        assert!(err.contains("Table keys must be strings, numbers or booleans, got array"), "{}", err);
+        assert!(eval_code("t = {a = 1}\nt[{}]").unwrap_err().to_string().contains("got table"));
+    }
+
+    #[test]
+    fn test_validate_checks_tables_without_a_server() {
+        let run = |data: &str| {
+            let code = format!(
+                "rules = {{name = \"required|min:3\", age = \"numeric|min:18|max:65\", email = \"email\", code = \"regex:^[A-Z]{{2}}-[0-9]+$\"}}\nvalidate({}, rules)",
+                data
+            );
+            match eval_code(&code).unwrap() {
+                Value::Table(result) => result,
+                other => panic!("expected table, got {:?}", other),
+            }
+        };
+        let ok = run("{name = \"Ada\", age = 36, email = \"ada@example.com\", code = \"AB-12\"}");
+        assert_eq!(ok.get("valid"), Some(&Value::Boolean(true)));
+        assert_eq!(ok.get("errors"), Some(&Value::Table(Table::new())));
+
+        let bad = run("{name = \"Al\", age = 70, email = \"nope\", code = \"ab-1\"}");
+        assert_eq!(bad.get("valid"), Some(&Value::Boolean(false)));
+        let errors = match bad.get("errors") {
+            Some(Value::Table(errors)) => errors.clone(),
+            other => panic!("expected errors table, got {:?}", other),
+        };
+        let message = |field: &str| errors.get(field).cloned();
+        assert_eq!(message("name"), Some(Value::String("Must be at least 3 characters".to_string())));
+        assert_eq!(message("age"), Some(Value::String("Must be at most 65".to_string())));
+        assert_eq!(message("email"), Some(Value::String("Invalid email format".to_string())));
+        if cfg!(feature = "regex") {
+            assert_eq!(message("code"), Some(Value::String("Does not match pattern ^[A-Z]{2}-[0-9]+$".to_string())));
+        }
+
+        let missing = run("{age = 12}");
+        let errors = match missing.get("errors") {
+            Some(Value::Table(errors)) => errors.clone(),
+            other => panic!("expected errors table, got {:?}", other),
+        };
+        assert_eq!(errors.get("name"), Some(&Value::String("This field is required".to_string())));
+        assert_eq!(errors.get("age"), Some(&Value::String("Must be at least 18".to_string())));
+        assert!(!errors.contains_key("email"));
+        assert!(eval_code("validate([1], {})").is_err());
+    }
+
+    #[test]
+    fn test_array_aggregation_builtins() {
+        assert_eq!(eval_code("sum([1, 2, 3.5])").unwrap(), Value::Number(6.5));
+        assert_eq!(eval_code("avg([2, 4, 9])").unwrap(), Value::Number(5.0));
+        assert_eq!(eval_code("min([3, -1, 2])").unwrap(), Value::Number(-1.0));
+        assert_eq!(eval_code("max([3, -1, 2])").unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("count([1, 2, 3, 4])").unwrap(), Value::Number(4.0));
+        assert_eq!(eval_code("lamda even(x): x % 2 == 0\ncount([1, 2, 3, 4], even)").unwrap(), Value::Number(2.0));
+
+        assert_eq!(eval_code("sum([])").unwrap(), Value::Number(0.0));
+        assert!(matches!(eval_code("avg([])").unwrap(), Value::Number(n) if n.is_nan()));
+        assert!(eval_code("min([])").unwrap_err().to_string().contains("empty array"));
+        assert!(eval_code("max([])").is_err());
+
+        let err = eval_code("sum([1, 2, \"three\"])").unwrap_err().to_string();
+        assert!(err.contains("element 3 is string"), "{}", err);
+        assert!(eval_code("avg([1, [2]])").unwrap_err().to_string().contains("element 2 is array"));
+        assert!(eval_code("sum(5)").is_err());
+        assert!(eval_code("count([1], 2)").is_err());
+
+        // A script's own definitions replace the builtins
+        let own = "func max(a, b):\n    return 99\nend\nfunc min(a):\n    return 0\nend\nfunc count(a):\n    return 42\nend\n";
+        assert_eq!(eval_code(&format!("{}max(1, 2)", own)).unwrap(), Value::Number(99.0));
+        assert_eq!(eval_code(&format!("{}count([1])", own)).unwrap(), Value::Number(42.0));
+        assert_eq!(eval_code(&format!("{}min([3, 1])", own)).unwrap(), Value::Number(0.0));
+        // Their arity is the script's, not the builtin's
+        assert!(eval_code(&format!("{}max(1, 2, 3)", own)).is_err());
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        let mut evaluator = Evaluator::new();
+        evaluator.register_builtin("host_time", Box::new(|_args| Ok(Value::Number(1_700_000_000.0))));
+        evaluator.register_builtin("host_add", Box::new(|args| match args {
+            [Value::Number(a), Value::Number(b)] => Ok(Value::Number(a + b)),
+            _ => Err(MintasError::RuntimeError {
+                message: "host_add expects two numbers".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+        }));
+        assert_eq!(evaluator.eval_line("host_time()").unwrap(), Value::Number(1_700_000_000.0));
+        assert_eq!(evaluator.eval_line("host_add(2, 3)").unwrap(), Value::Number(5.0));
+        assert!(evaluator.eval_line("host_add(\"a\")").is_err());
+    }
+
+    #[test]
+    fn test_getback_typed_accessors() {
+        let mut evaluator = Evaluator::new();
+        let mut params = Table::new();
+        params.insert("id".to_string(), Value::String("42".to_string()));
+        params.insert("slug".to_string(), Value::String("abc".to_string()));
+        let mut query = Table::new();
+        query.insert("page".to_string(), Value::String(" 2.5 ".to_string()));
+        let mut getback = Table::new();
+        getback.insert("params".to_string(), Value::Table(params));
+        getback.insert("query".to_string(), Value::Table(query));
+        getback.insert("__type__".to_string(), Value::String("Getback".to_string()));
+        evaluator.set_variable("req".to_string(), Value::Table(getback));
+        assert_eq!(evaluator.eval_line("req.param_number(\"id\")").unwrap(), Value::Number(42.0));
+        assert_eq!(evaluator.eval_line("req.query_number(\"page\")").unwrap(), Value::Number(2.5));
+        assert_eq!(evaluator.eval_line("req.param_number(\"slug\")").unwrap(), Value::Null);
+        assert_eq!(evaluator.eval_line("req.query_number(\"missing\")").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_recursion_depth_limit_reports_trace() {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_max_recursion_depth(3);
+        evaluator.eval_line("func down(n):\n    return down(n + 1)\nend").unwrap();
+        match evaluator.eval_line("down(0)") {
+            Err(MintasError::RuntimeError { message, .. }) => {
+                assert!(message.starts_with("maximum recursion depth 3 exceeded"));
+                assert!(message.contains("down -> down -> down"));
+            }
+            other => panic!("expected recursion error, got {:?}", other),
+        }
+        evaluator.eval_line("func one():\n    return 1\nend").unwrap();
+        assert_eq!(evaluator.eval_line("one()").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_string_indexing_uses_characters() {
+        assert_eq!(eval_code("s = \"héllo\"\ns[2]").unwrap(), Value::String("é".to_string()));
+        assert_eq!(eval_code("s = \"héllo\"\ns[5]").unwrap(), Value::String("o".to_string()));
+        assert!(eval_code("s = \"héllo\"\ns[6]").is_err());
+        assert!(eval_code("s = \"héllo\"\ns[-1]").is_err());
+        assert_eq!(eval_code("s = \"héllo\"\nout = \"\"\nfor (c in s):\n    out = c + out\nend\nout").unwrap(),
+            Value::String("olléh".to_string()));
+        assert_eq!(eval_code("s = \"日本語\"\ns.find(\"語\")").unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("s = \"héllo\"\ns.insert(3, \"-\")").unwrap(), Value::String("hé-llo".to_string()));
+    }
+
+    #[test]
+    fn test_interpreter_stack_fits_the_recursion_limit() {
+        let depth = 500;
+        let code = format!("func f(n):\n    if (n == 0):\n        return 0\n    end\n    return 1 + f(n - 1)\nend\nf({})", depth - 10);
+        let result = std::thread::Builder::new()
+            .stack_size(interpreter_stack_size(depth))
+            .spawn(move || eval_code(&code).map_err(|e| e.to_string()))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(result, Ok(Value::Number((depth - 10) as f64)));
+    }
+
+    #[test]
+    fn test_raw_strings_are_not_interpolated() {
+        assert_eq!(eval_code(r#"r"cost ${""#).unwrap(), Value::String("cost ${".to_string()));
+        assert_eq!(eval_code("x = 1\nr\"\\d ${x} $x\"").unwrap(), Value::String(r"\d ${x} $x".to_string()));
+        assert_eq!(eval_code("x = 1\n\"${x}\"").unwrap(), Value::String("1".to_string()));
+    }
+
+    #[test]
+    fn test_expr_kind_names_the_variant() {
+        let kind = |src: &str| Evaluator::expr_kind(&Parser::new(Lexer::new(src).tokenize().unwrap()).parse().unwrap()[0]);
+        assert_eq!(kind("42"), "Number");
+        assert_eq!(kind("\"a (b) {c}\""), "String");
+        assert_eq!(kind("x = [1, 2]"), "Assign");
+        assert_eq!(kind("point.x"), "Property");
+        assert_eq!(kind("f(1)"), "Call");
+        assert_eq!(kind("for (i from 1 to 2):\n    i\nend"), "ForLoop");
+        assert_eq!(Evaluator::expr_kind(&Expr::Getback), "Getback");
+    }
+
+    #[test]
+    fn test_snapshot_restore_rolls_back_definitions() {
+        let mut evaluator = Evaluator::new();
+        evaluator.eval_line("kept = 1").unwrap();
+        let state = evaluator.snapshot();
+        evaluator.eval_line("added = 2").unwrap();
+        evaluator.eval_line("kept = 5").unwrap();
+        evaluator.eval_line("func helper():\n    return 3\nend").unwrap();
+        evaluator.restore(state);
+        assert!(evaluator.eval_line("added").is_err());
+        assert!(evaluator.eval_line("helper()").is_err());
+        assert_eq!(evaluator.eval_line("kept").unwrap(), Value::Number(1.0));
+
+        // random() repeats from the snapshot, and the next run gets the whole --max-memory budget
+        let mut evaluator = Evaluator::new();
+        evaluator.set_allocation_budget(Some(4096));
+        let state = evaluator.snapshot();
+        let first = evaluator.eval_line("random()").unwrap();
+        evaluator.eval_line("big = \"x\" * 1500").unwrap();
+        assert!(evaluator.eval_line("more = \"x\" * 1500").is_err());
+        evaluator.restore(state);
+        assert_eq!(evaluator.eval_line("random()").unwrap(), first);
+        assert!(evaluator.eval_line("big = \"x\" * 1500").is_ok());
+    }
+
+    #[test]
+    fn test_null_and_empty_semantics() {
+        let evaluator = Evaluator::new();
+        assert!(evaluator.values_equal(&Value::Null, &Value::Empty));
+        assert!(evaluator.values_equal(&Value::Null, &Value::Null));
+        assert!(evaluator.values_strict_equal(&Value::Null, &Value::Null));
+        assert!(!evaluator.values_strict_equal(&Value::Null, &Value::Empty));
+        assert!(!evaluator.values_equal(&Value::Null, &Value::Number(0.0)));
+        assert!(!Value::Null.is_truthy());
+        assert_eq!(Value::Null.is_truthy_in_condition(), Value::Boolean(false));
+        assert_eq!(Value::Empty.is_truthy_in_condition(), Value::Maybe);
+        assert_eq!(evaluator.parse_json_string("null").unwrap(), Value::Null);
+        assert_eq!(evaluator.value_to_json(&Value::Null), "null");
+    }
+
+    #[test]
+    fn test_table_introspection_methods() {
+        let setup = "t = {b = 2, a = 1, c = 3}\n";
+        assert_eq!(eval_code(&format!("{}t.keys()", setup)).unwrap(), Value::Array(vec![
+            Value::String("a".to_string()), Value::String("b".to_string()), Value::String("c".to_string()),
+        ]));
+        assert_eq!(eval_code(&format!("{}t.values()", setup)).unwrap(), Value::Array(vec![
+            Value::Number(1.0), Value::Number(2.0), Value::Number(3.0),
+        ]));
+        assert_eq!(eval_code(&format!("{}t.has(\"a\")", setup)).unwrap(), Value::Boolean(true));
+        assert_eq!(eval_code(&format!("{}t.has(\"z\")", setup)).unwrap(), Value::Boolean(false));
+        assert_eq!(eval_code(&format!("{}t.remove(\"b\")", setup)).unwrap(), Value::Number(2.0));
+        assert_eq!(eval_code(&format!("{}t.remove(\"b\")\nt.keys()", setup)).unwrap(), Value::Array(vec![
+            Value::String("a".to_string()), Value::String("c".to_string()),
+        ]));
+        assert_eq!(eval_code(&format!("{}t.remove(\"z\")", setup)).unwrap(), Value::Empty);
+    }
+
+    #[test]
+    fn test_arity_and_call_builtins() {
+        let setup = "func add(a, b):\n    return a + b\nend\n";
+        assert_eq!(eval_code(&format!("{}arity(add)", setup)).unwrap(), Value::Number(2.0));
+        assert_eq!(eval_code(&format!("{}call(add, [1, 2])", setup)).unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("lamda mul(a, b): a * b\ncall(mul, [4, 5])").unwrap(), Value::Number(20.0));
+        match eval_code(&format!("{}call(add, [1])", setup)) {
+            Err(MintasError::InvalidArgumentCount { expected: 2, got: 1, location, .. }) => assert_eq!((location.line, location.column), (4, 1)),
+            other => panic!("expected arity error, got {:?}", other),
+        }
+        // Errors from inside a function point at the innermost call that raised them
+        let nested = format!("{}func twice(n):\n    return add(n)\nend\ntwice(1)", setup);
+        assert_eq!(eval_code(&nested).unwrap_err().location().line, 5);
+        assert!(eval_code(&format!("{}call(add, 1)", setup)).is_err());
+        assert!(eval_code("x = 3\narity(x)").is_err());
+    }
+
+    #[test]
+    fn test_table_property_access_reads_and_updates_in_place() {
+        let setup = "point = {x = 3, y = 4}\n";
+        assert_eq!(eval_code(&format!("{}point.x * point.y", setup)).unwrap(), Value::Number(12.0));
+        assert_eq!(eval_code(&format!("{}point.x = 10\npoint.x + point.y", setup)).unwrap(), Value::Number(14.0));
+        assert_eq!(eval_code(&format!("{}copy = point\npoint.x = 10\ncopy.x", setup)).unwrap(), Value::Number(3.0));
+        assert!(eval_code(&format!("{}point.z", setup)).is_err());
+        // Fields read by their cached hash find keys stored any other way, and the reverse
+        let keyed = "t = {}\nt.set(\"name\", 2)\nt.set(\"size\", 3)\nt.name * t.size + t[\"size\"]";
+        assert_eq!(eval_code(keyed).unwrap(), Value::Number(9.0));
+        assert_eq!(eval_code(&format!("{}point.z = 5\npoint[\"z\"] + point[\"x\"]", setup)).unwrap(), Value::Number(8.0));
+    }
+
+    /// Field reads and writes in a million-iteration loop, against the same loop copying the
+    /// table out of its variable first as every access did before. Run with
+    /// `cargo test --release bench_table_field_access -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_table_field_access() {
+        let run = |body: &str| {
+            let code = format!("point = {{x = 1, y = 2}}\ntotal = 0\nfor (i from 1 to 1000000):\n{}\nend\ntotal", body);
+            let ast = Parser::new(Lexer::new(&code).tokenize().unwrap()).parse().unwrap();
+            let mut evaluator = Evaluator::new();
+            let start = std::time::Instant::now();
+            let result = evaluator.eval_block(&ast).unwrap();
+            (result, start.elapsed())
+        };
+        let (in_place, fast) = run("    total = total + point.x * point.y\n    point.x = i");
+        let (copied, slow) = run("    p = point\n    total = total + p.x * p.y\n    point = {x = i, y = p.y}");
+        assert_eq!(in_place, copied);
+        println!("in place {:.1?}, copying the table {:.1?}: {:.2}x", fast, slow, slow.as_secs_f64() / fast.as_secs_f64());
+    }
+
+    #[test]
+    fn test_tables_and_arrays_have_value_semantics() {
+        assert_eq!(eval_code("a = {n = 1}\nb = a\nb.n = 2\na.n").unwrap(), Value::Number(1.0));
+        assert_eq!(eval_code("a = [1, 2]\nb = a\nb.push(3)\na.len()").unwrap(), Value::Number(2.0));
+        assert_eq!(
+            eval_code("func bump(t):\n    t.n = 99\n    return t.n\nend\na = {n = 1}\nbump(a)\na.n").unwrap(),
+            Value::Number(1.0)
+        );
+        assert_eq!(eval_code("a = {inner = {n = 1}}\nb = clone(a)\nb.inner = 5\na.inner.n").unwrap(), Value::Number(1.0));
+        assert_eq!(eval_code("a = [[1], [2]]\nclone(a)").unwrap(), Value::Array(vec![
+            Value::Array(vec![Value::Number(1.0)]), Value::Array(vec![Value::Number(2.0)]),
+        ]));
+    }
+
+    #[test]
+    fn test_loop_else_runs_only_on_zero_iterations() {
+        let run = |src: &str| eval_code(&format!("log = \"\"\n{}\nlog", src)).unwrap();
+        assert_eq!(run("while (false):\n    log = log + \"b\"\nelse:\n    log = log + \"e\"\nend"), Value::String("e".to_string()));
+        assert_eq!(run("n = 0\nwhile (n < 2):\n    n = n + 1\n    log = log + \"b\"\nelse:\n    log = log + \"e\"\nend"), Value::String("bb".to_string()));
+        assert_eq!(run("while (true):\n    log = log + \"b\"\n    break\nelse:\n    log = log + \"e\"\nend"), Value::String("b".to_string()));
+        assert_eq!(run("for (x in []):\n    log = log + \"b\"\nelse:\n    log = log + \"e\"\nend"), Value::String("e".to_string()));
+        assert_eq!(run("for (x in [1, 2]):\n    log = log + \"b\"\nelse:\n    log = log + \"e\"\nend"), Value::String("bb".to_string()));
+        assert_eq!(run("for (x in [1, 2]):\n    log = log + \"b\"\n    break\nelse:\n    log = log + \"e\"\nend"), Value::String("b".to_string()));
+        // A counted loop always runs, down when start is above end, so it takes no else
+        assert_eq!(run("for (i from 3 to 1):\n    log = log + to_string(i)\nend"), Value::String("321".to_string()));
+        assert!(eval_code("for (i from 3 to 1):\n    x = 1\nelse:\n    x = 2\nend").unwrap_err().to_string().contains("can't have an else"));
+    }
+
+    #[test]
+    fn test_smart_loop_and_smart_condition() {
+        let run = |src: &str| eval_code(&format!("log = \"\"\n{}\nlog", src)).unwrap();
+        assert_eq!(run("goto (i = 3 times):\n    log = log + to_string(i)\nend"), Value::String("012".to_string()));
+        assert_eq!(run("goto (i = 2.9 times):\n    log = log + to_string(i)\nend"), Value::String("01".to_string()));
+        assert_eq!(run("goto (i = -1 times):\n    log = log + \"x\"\nend"), Value::String(String::new()));
+        assert_eq!(run("goto (i = 5 times):\n    if (i == 1):\n        proceed\n    end\n    if (i == 3):\n        break\n    end\n    log = log + to_string(i)\nend"), Value::String("02".to_string()));
+        assert!(eval_code("goto (i = \"3\" times):\n    say(i)\nend").is_err());
+        assert_eq!(eval_code("either (2 > 1): \"big\" :: \"small\"").unwrap(), Value::String("big".to_string()));
+        assert_eq!(run("either (0): log = \"yes\" :: log = \"no\""), Value::String("no".to_string()));
+    }
+
+    #[test]
+    fn test_max_memory_aborts_runaway_allocation() {
+        let run = |limit: Option<usize>, src: &str| {
+            let mut evaluator = Evaluator::new();
+            evaluator.set_allocation_budget(limit);
+            let statements = Parser::new(Lexer::new(src).tokenize().unwrap()).parse().unwrap();
+            statements.iter().try_for_each(|stmt| evaluator.eval(stmt).map(|_| ()))
+        };
+        let grow_array = "a = []\nfor (i from 1 to 10000):\n    a.push(i)\nend";
+        let grow_string = "s = \"\"\nfor (i from 1 to 10000):\n    s = s + \"xxxxxxxx\"\nend";
+        let grow_table = "t = {}\nfor (i from 1 to 100):\n    t.set(i, [1, 2, 3, 4, 5, 6, 7, 8])\nend";
+        for src in [grow_array, grow_string, grow_table] {
+            let err = run(Some(4096), src).unwrap_err().to_string();
+            assert!(err.contains("allocation limit exceeded"), "{}", err);
+            assert!(run(None, src).is_ok());
+        }
+        assert!(run(Some(1 << 20), "x = [1, 2, 3]\ny = \"small\" + \"string\"").is_ok());
+        // Only what the script still holds counts: replaced values are given back
+        let churn = "t = {}\nfor (i from 1 to 1000):\n    t.big = [1, 2, 3, 4, 5, 6, 7, 8]\n    s = \"xxxxxxxx\" + to_string(i)\nend";
+        assert!(run(Some(4096), churn).is_ok());
+        let shrink = "a = []\nfor (i from 1 to 10000):\n    a.push(i)\n    if (a.len() == 100):\n        a = []\n    end\nend";
+        assert!(run(Some(4096), shrink).is_ok());
+        // A repetition is charged before it is built
+        assert!(run(Some(4096), "s = \"xxxxxxxx\" * 100000").unwrap_err().to_string().contains("allocation limit exceeded"));
+        assert!(run(Some(4096), "s = \"ab\"\ns *= 100000").unwrap_err().to_string().contains("allocation limit exceeded"));
+    }
+
+    #[test]
+    fn test_match_dispatches_on_runtime_type() {
+        let classify = |value: &str| {
+            let src = format!("match {} is number:\n    \"num\"\nis string:\n    \"str\"\nis array:\n    \"arr\"\nis null:\n    \"null\"\ndefault:\n    \"other\"\nend", value);
+            eval_code(&src).unwrap()
+        };
+        assert_eq!(classify("1 + 2"), Value::String("num".to_string()));
+        assert_eq!(classify("\"hi\""), Value::String("str".to_string()));
+        assert_eq!(classify("[1, 2]"), Value::String("arr".to_string()));
+        #[cfg(feature = "json")]
+        assert_eq!(classify("json.parse(\"null\")"), Value::String("null".to_string()));
+        assert_eq!(classify("true"), Value::String("other".to_string()));
+        // Without a default an unmatched value runs nothing; `match` is still a plain name elsewhere
+        assert_eq!(eval_code("match 1 is string:\n    2\nend").unwrap(), Value::Empty);
+        assert_eq!(eval_code("match = 5\nmatch").unwrap(), Value::Number(5.0));
+        assert!(eval_code("match 1 is numbr:\n    2\nend").is_err());
+    }
+
+    #[test]
+    fn test_class_instances_fields_and_methods() {
+        let class = "class Counter:\n    public count = 0\n    public step = 1\n    public init(step):\n        self.step = step\n    end\n    public bump():\n        self.count = self.count + self.step\n        return self.count\n    end\nend\n";
+        assert_eq!(eval_code(&format!("{}c = Counter(5)\nc.step", class)).unwrap(), Value::Number(5.0));
+        assert_eq!(eval_code(&format!("{}c = new Counter(2)\nc.bump()\nc.bump()\nc.count", class)).unwrap(), Value::Number(4.0));
+        assert_eq!(eval_code(&format!("{}c = Counter(1)\nc.__class__", class)).unwrap(), Value::String("Counter".to_string()));
+        // The class tag is not a field
+        let keys = vec![Value::String("count".to_string()), Value::String("step".to_string())];
+        assert_eq!(eval_code(&format!("{}c = Counter(1)\nc.keys()", class)).unwrap(), Value::Array(keys));
+        assert_eq!(eval_code(&format!("{}c = Counter(1)\nc.entries().len() + c.len()", class)).unwrap(), Value::Number(4.0));
+        assert_eq!(eval_code(&format!("{}n = 0\nfor (k in Counter(1)):\n    n = n + 1\nend\nn", class)).unwrap(), Value::Number(2.0));
+        match eval_code(&format!("{}Counter(1).to_json()", class)).unwrap() {
+            Value::String(json) => assert!(!json.contains("__class__"), "{}", json),
+            other => panic!("expected JSON text, got {:?}", other),
+        }
+        assert!(matches!(eval_code(&format!("{}Counter()", class)), Err(MintasError::InvalidArgumentCount { .. })));
+        assert!(eval_code("class Empty:\nend\ne = Empty()\ne.missing()").is_err());
+        // Methods called through an index or a property change the stored instance
+        let nested = "items = [Counter(1), Counter(10)]\nitems[2].bump()\nt = {c = Counter(3)}\nt.c.bump()\nt.c.bump()\n";
+        assert_eq!(eval_code(&format!("{}{}items[2].count + t.c.count", class, nested)).unwrap(), Value::Number(16.0));
+        // say() shows an instance's fields without its class tag
+        let mut shown = Vec::new();
+        let instance = eval_code(&format!("{}{}t.c", class, nested)).unwrap();
+        Evaluator::new().write_value_to_buffer(&instance, &mut shown).unwrap();
+        let shown = String::from_utf8(shown).unwrap();
+        assert!(shown.contains("\"count\" = 6") && !shown.contains(CLASS_TAG), "{}", shown);
+    }
+
+    #[test]
+    fn test_subclass_overrides_and_calls_super() {
+        let classes = "class Animal:\n    public name = \"\"\n    public init(name):\n        self.name = name\n    end\n    public speak():\n        return self.name + \" makes a sound\"\n    end\n    public describe():\n        return \"animal \" + self.name\n    end\nend\nclass Dog extends Animal:\n    public speak():\n        return super.speak() + \", woof\"\n    end\nend\n";
+        assert_eq!(eval_code(&format!("{}Dog(\"Rex\").speak()", classes)).unwrap(), Value::String("Rex makes a sound, woof".to_string()));
+        // Inherited, not overridden: init and describe come from Animal
+        assert_eq!(eval_code(&format!("{}d = Dog(\"Rex\")\nd.describe()", classes)).unwrap(), Value::String("animal Rex".to_string()));
+        assert!(eval_code(&format!("{}Animal(\"Cat\").missing()", classes)).is_err());
+        assert!(eval_code("super.speak()").is_err());
+    }
+
+    #[test]
+    fn test_nan_negative_zero_and_infinity_comparisons() {
+        let check = |code: &str| {
+            let mut evaluator = Evaluator::new();
+            evaluator.set_variable("nan".to_string(), Value::Number(f64::NAN));
+            evaluator.set_variable("inf".to_string(), Value::Number(f64::INFINITY));
+            evaluator.set_variable("nz".to_string(), Value::Number(-0.0));
+            let ast = Parser::new(Lexer::new(code).tokenize().unwrap()).parse().unwrap();
+            evaluator.eval_block(&ast).unwrap()
+        };
+        for falsy in ["nan == nan", "nan < 1", "nan > 1", "nan <= nan", "nan >= 0", "nz < 0", "inf != inf"] {
+            assert_eq!(check(falsy), Value::Boolean(false), "{}", falsy);
+        }
+        for truthy in ["nan != nan", "nz == 0", "nz === 0", "nz <= 0", "nz >= 0", "inf == inf", "inf > 1e308", "-inf < -1e308",
+                "0.1 + 0.2 == 0.3", "0.1 + 0.2 <= 0.3", "9007199254740993 > 1.5", "0.5 < 9007199254740993"] {
+            assert_eq!(check(truthy), Value::Boolean(true), "{}", truthy);
+        }
+        let sorted = check("xs = [3, nan, \"b\", inf, nz, -inf, \"a\", 1]\nxs.sort()\nxs");
+        let Value::Array(sorted) = sorted else { panic!("expected array") };
+        assert_eq!(sorted.len(), 8);
+        assert_eq!(sorted[..5], [-f64::INFINITY, -0.0, 1.0, 3.0, f64::INFINITY].map(Value::Number));
+        assert!(matches!(sorted[5], Value::Number(n) if n.is_nan()));
+        assert_eq!(sorted[6..], [Value::String("a".to_string()), Value::String("b".to_string())]);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_to_json_and_from_json_methods() {
+        let data = eval_code("{name = \"ann\", tags = [1, 2.5, true]}").unwrap();
+        assert_eq!(eval_code("{name = \"ann\", tags = [1, 2.5, true]}.to_json().from_json()").unwrap(), data);
+        assert_eq!(eval_code("[1, \"a\"].to_json()").unwrap(), Value::String("[1,\"a\"]".to_string()));
+        assert_eq!(eval_code("\"[1, {\\\"x\\\": null}]\".from_json()[2].x").unwrap(), Value::Null);
+        let err = eval_code("\"{\\\"a\\\": }\".from_json()").unwrap_err().to_string();
+        assert!(err.contains("line 1 column 7"), "{}", err);
+    }
+
+    #[test]
+    fn test_for_in_range_is_lazy_and_honours_continue() {
+        let odds = "total = 0\nfor (i in range(0, 100000)):\n    if (i % 2 == 0):\n        continue\n    end\n    total = total + i\nend\ntotal";
+        assert_eq!(eval_code(odds).unwrap(), Value::Number(2_500_000_000.0));
+        // Building this range as an array would need petabytes
+        let huge = "n = 0\nfor (i in range(0, 1e15)):\n    n = n + 1\n    if (n == 3):\n        break\n    end\nend\nn";
+        assert_eq!(eval_code(huge).unwrap(), Value::Number(3.0));
+        let arr = |ns: &[f64]| Value::Array(ns.iter().map(|n| Value::Number(*n)).collect());
+        assert_eq!(eval_code("range(4)").unwrap(), arr(&[0.0, 1.0, 2.0, 3.0]));
+        assert_eq!(eval_code("range(10, 0, -3)").unwrap(), arr(&[10.0, 7.0, 4.0, 1.0]));
+        assert!(eval_code("range(1, 5, 0)").is_err());
+        assert!(eval_code("range(1e15)").is_err());
+        // A script's own range replaces the builtin, in loops too
+        let own = "func range(n):\n    return [n, n]\nend\ntotal = 0\nfor (i in range(5)):\n    total = total + i\nend\ntotal + count(range(7))";
+        assert_eq!(eval_code(own).unwrap(), Value::Number(12.0));
+    }
+
+    #[test]
+    fn test_for_in_binds_index_and_table_values() {
+        let table = "t = {b = 2, a = 1, c = 3}\nout = \"\"\nfor (k, v, i in t):\n    out = out + to_string(i) + k + to_string(v) + \" \"\nend\nout";
+        assert_eq!(eval_code(table).unwrap(), Value::String("0a1 1b2 2c3 ".to_string()));
+        let pairs = "t = {y = 2, x = 1}\nout = \"\"\nfor (k, v in t):\n    out = out + k + to_string(v)\nend\nout";
+        assert_eq!(eval_code(pairs).unwrap(), Value::String("x1y2".to_string()));
+        let keys = "t = {y = 2, x = 1}\nout = \"\"\nfor (k in t):\n    out = out + k\nend\nout";
+        assert_eq!(eval_code(keys).unwrap(), Value::String("xy".to_string()));
+
+        let array = "out = \"\"\nfor (v, i in [\"p\", \"q\"]):\n    out = out + to_string(i) + v\nend\nout";
+        assert_eq!(eval_code(array).unwrap(), Value::String("0p1q".to_string()));
+        let chars = "out = \"\"\nfor (c, i in \"hi\"):\n    out = out + c + to_string(i)\nend\nout";
+        assert_eq!(eval_code(chars).unwrap(), Value::String("h0i1".to_string()));
+        let range = "total = 0\nfor (n, i in range(10, 13)):\n    total = total + n * i\nend\ntotal";
+        assert_eq!(eval_code(range).unwrap(), Value::Number(11.0 + 24.0));
+
+        assert!(eval_code("for (v, i, x in [1]):\n    say(v)\nend").is_err());
+        assert!(eval_code("for (a, b, c, d in {}):\n    say(a)\nend").is_err());
+        assert!(eval_code("for (a, b from 1 to 3):\n    say(a)\nend").is_err());
+    }
+
+    #[test]
+    fn test_return_unwinds_nested_blocks_to_the_function() {
+        let nested = "func first_even_over(xs, limit):\n    for (x in xs):\n        if (x > limit):\n            if (x % 2 == 0):\n                return x\n            end\n        end\n    end\n    return -1\nend\nfirst_even_over([1, 4, 7, 10, 12], 5)";
+        assert_eq!(eval_code(nested).unwrap(), Value::Number(10.0));
+        let loops = "func f():\n    i = 0\n    while (i < 100):\n        i = i + 1\n        for (j from 1 to 10):\n            if (i * j == 12):\n                return [i, j]\n            end\n        end\n    end\n    return empty\nend\nf()";
+        assert_eq!(eval_code(loops).unwrap(), Value::Array(vec![Value::Number(2.0), Value::Number(6.0)]));
+        let smart = "func g():\n    goto (i = 5 times):\n        if (i == 3):\n            return i\n        end\n    end\n    return 99\nend\ng()";
+        assert_eq!(eval_code(smart).unwrap(), Value::Number(3.0));
+        let after = "func h():\n    for (x in [1, 2]):\n        return x\n    end\nend\nseen = h()\nseen + 1";
+        assert_eq!(eval_code(after).unwrap(), Value::Number(2.0));
+        assert_eq!(eval_code("xs = [1, 2, 3, 4]\nlamda even(x): x % 2 == 0\nxs.filter(even)").unwrap(), Value::Array(vec![Value::Number(2.0), Value::Number(4.0)]));
+
+        let mut evaluator = Evaluator::new();
+        let statements = Parser::new(Lexer::new("if (true):\n    return 1\nend").tokenize().unwrap()).parse().unwrap();
+        let err = evaluator.eval_top_level(&statements[0]).unwrap_err();
+        assert!(err.to_string().contains("'return' used outside of a function"), "{}", err);
+    }
+
+    #[test]
+    fn test_missing_method_arguments_are_errors_not_panics() {
+        for code in ["[1].contains()", "[1].map()", "[1].reduce()", "[1].extend()", "{a = 1}.merge()"] {
+            assert!(matches!(eval_code(code), Err(MintasError::InvalidArgumentCount { .. })), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_builtin_table_matches_dispatch() {
+        // Calls are built directly: `say` and `dew.task` can't be written this way in source
+        let run = |builtin: &crate::builtins::Builtin, count: usize| {
+            let args = vec![Expr::Number(1.0); count];
+            let receiver = match builtin.category {
+                Category::Core | Category::Math => {
+                    return Evaluator::new().eval(&Expr::Call { name: builtin.name.to_string(), args, location: Evaluator::default_location() });
+                }
+                Category::String => "\"abc\"",
+                Category::Array => "[1]",
+                Category::Table => "{a = 1}",
+                Category::Builder => "string_builder()",
+                Category::Request => "{__type__ = \"Getback\"}",
+                Category::Dew => "dew",
+            };
+            let object = Parser::new(Lexer::new(receiver).tokenize().unwrap()).parse().unwrap().remove(0);
+            Evaluator::new().eval(&Expr::MethodCall { object: Box::new(object), method: builtin.name.to_string(), args })
+        };
+        for builtin in crate::builtins::BUILTINS {
+            let (min, max) = builtin.arity();
+            let is_arity_error = |count| matches!(run(builtin, count), Err(MintasError::InvalidArgumentCount { .. }));
+            if min > 0 {
+                assert!(is_arity_error(min - 1), "{}", builtin.signature());
+            }
+            if let Some(max) = max {
+                assert!(is_arity_error(max + 1), "{}", builtin.signature());
+            }
+            // Methods are side-effect free, so every listed one must actually be dispatched
+            if !matches!(builtin.category, Category::Core | Category::Math | Category::Dew) {
+                if let Err(e) = run(builtin, min) {
+                    assert!(!e.to_string().contains("Unknown"), "{}: {}", builtin.signature(), e);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_args_returns_script_arguments() {
+        let statements = Parser::new(Lexer::new("echo = args().join(\"|\")").tokenize().unwrap()).parse().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_script_args(vec!["one".to_string(), "two words".to_string(), "--flag".to_string()]);
+        for stmt in &statements {
+            evaluator.eval(stmt).unwrap();
+        }
+        assert_eq!(evaluator.get_variables().get("echo"), Some(&Value::String("one|two words|--flag".to_string())));
+        assert_eq!(eval_code("args()").unwrap(), Value::Array(Vec::new()));
+    }
+
+    #[test]
+    fn test_table_merge_and_update() {
+        let table = |code: &str| match eval_code(code).unwrap() {
+            Value::Table(map) => map,
+            other => panic!("expected table, got {:?}", other),
+        };
+        let base = "base = {host = \"localhost\", port = 80, db = {name = \"app\", pool = 5}}\n";
+        let merged = table(&format!("{}base.merge({{port = 8080, db = {{pool = 20}}}})", base));
+        assert_eq!(merged.get("host"), Some(&Value::String("localhost".to_string())));
+        assert_eq!(merged.get("port"), Some(&Value::Number(8080.0)));
+        assert_eq!(merged.get("db"), Some(&Value::Table(Table::from([("pool".to_string(), Value::Number(20.0))]))));
+        let unchanged = table(&format!("{}m = base.merge({{port = 1}})\nbase", base));
+        assert_eq!(unchanged.get("port"), Some(&Value::Number(80.0)));
+
+        let deep = table(&format!("{}base.merge({{db = {{pool = 20}}}}, true)", base));
+        let db = Table::from([("name".to_string(), Value::String("app".to_string())), ("pool".to_string(), Value::Number(20.0))]);
+        assert_eq!(deep.get("db"), Some(&Value::Table(db.clone())));
+        assert_eq!(table(&format!("{}base.merge({{db = {{pool = 20}}}}, {{deep = true}})", base)).get("db"), Some(&Value::Table(db.clone())));
+
+        let updated = table(&format!("{}base.update({{port = 443, db = {{pool = 20}}}}, true)\nbase", base));
+        assert_eq!(updated.get("port"), Some(&Value::Number(443.0)));
+        assert_eq!(updated.get("db"), Some(&Value::Table(db)));
+        assert!(matches!(eval_code("{a = 1}.merge([1])"), Err(MintasError::TypeError { .. })));
+        assert!(matches!(eval_code("t = {a = 1}\nt.update(5)"), Err(MintasError::TypeError { .. })));
+
+        let nested = table(&format!("{}cfg = {{app = base}}\ncfg.app.db.update({{pool = 50}})\ncfg", base));
+        let db = match nested.get("app") {
+            Some(Value::Table(app)) => app.get("db").cloned(),
+            other => panic!("expected app table, got {:?}", other),
+        };
+        assert_eq!(db, Some(Value::Table(Table::from([("name".to_string(), Value::String("app".to_string())), ("pool".to_string(), Value::Number(50.0))]))));
+        assert!(eval_code("{a = 1}.update({b = 2})").is_err());
+        assert!(eval_code("t = {a = 1}\nt.missing.update({b = 2})").is_err());
+    }
+
+    #[test]
+    fn test_seeded_random_is_reproducible() {
+        let src = "out = []\nfor (i from 1 to 5):\n    out.push(random())\n    out.push(random_int(1, 6))\nend\nout";
+        let ast = Parser::new(Lexer::new(src).tokenize().unwrap()).parse().unwrap();
+        let run = |seed: u64| {
+            let mut evaluator = Evaluator::new();
+            evaluator.set_seed(Some(seed));
+            evaluator.eval_block(&ast).unwrap();
+            match evaluator.eval(&Expr::Variable("out".to_string())).unwrap() {
+                Value::Array(values) => values,
+                other => panic!("expected array, got {:?}", other),
+            }
+        };
+        let first = run(42);
+        assert_eq!(first, run(42));
+        assert_ne!(first, run(43));
+        for (i, value) in first.iter().enumerate() {
+            match value {
+                Value::Number(n) if i % 2 == 0 => assert!((0.0..1.0).contains(n)),
+                Value::Number(n) => assert!(n.fract() == 0.0 && (1.0..=6.0).contains(n)),
+                other => panic!("expected number, got {:?}", other),
+            }
+        }
+        assert!(eval_code("random_int(3, 2)").is_err());
+    }
+
+    #[test]
+    fn test_time_limit_aborts_long_running_script() {
+        let ast = Parser::new(Lexer::new("x = 0\nwhile (true):\n    x = x + 1\nend").tokenize().unwrap()).parse().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_time_limit(Some(std::time::Duration::from_millis(50)));
+        let started = std::time::Instant::now();
+        match evaluator.eval_block(&ast) {
+            Err(MintasError::RuntimeError { message, .. }) => {
+                assert_eq!(message, "time limit exceeded: the script ran longer than 50ms (--time-limit)")
+            }
+            other => panic!("expected time limit error, got {:?}", other),
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_time_limit_is_checked_between_blocking_calls() {
+        let code = "include timer
+n = 0
+while (true):
+    timer.sleep(20)
+    n = n + 1
+end";
+        let ast = Parser::new(Lexer::new(code).tokenize().unwrap()).parse().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_time_limit(Some(std::time::Duration::from_millis(50)));
+        let started = std::time::Instant::now();
+        assert!(evaluator.eval_block(&ast).is_err());
+        assert!(started.elapsed() < std::time::Duration::from_millis(500), "stopped after {:?}", started.elapsed());
+        evaluator.set_time_limit(Some(std::time::Duration::MAX));
+        assert!(!evaluator.has_time_limit());
+    }
+
+    #[test]
+    fn test_function_locals_do_not_clobber_globals() {
+        assert_eq!(eval_code("x = 1\nfunc f():\n    x = 99\n    return x\nend\ny = f()\nx * 100 + y").unwrap(), Value::Number(199.0));
+        assert_eq!(eval_code("x = 1\nfunc f():\n    global x\n    x = 99\nend\nf()\nx").unwrap(), Value::Number(99.0));
+        // A callee sees globals but not its caller's locals
+        assert!(eval_code("func inner():\n    return secret\nend\nfunc outer():\n    secret = 1\n    return inner()\nend\nouter()").is_err());
+        // Globals written by a callee are visible to a caller that declared them too
+        let src = "count = 0\nfunc bump():\n    global count\n    count = count + 1\nend\nfunc twice():\n    global count\n    bump()\n    bump()\n    count = count * 10\nend\ntwice()\ncount";
+        assert_eq!(eval_code(src).unwrap(), Value::Number(20.0));
+        assert_eq!(eval_code("global x, y\nx = 2\nx").unwrap(), Value::Number(2.0));
+        assert!(eval_code("func f():\n    global 1\nend").is_err());
+        assert_eq!(eval_code("func outer():\n    n = 2\n    lamda f(x): x * n\n    return f(3)\nend\nouter()").unwrap(), Value::Number(6.0));
+    }
+
+    #[test]
+    fn test_say_is_buffered_until_flush() {
+        let mut evaluator = Evaluator::new();
+        let statements = Parser::new(Lexer::new("say(\"queued\")").tokenize().unwrap()).parse().unwrap();
+        evaluator.eval(&statements[0]).unwrap();
+        assert!(!evaluator.stdout_buffer.borrow().buffer().is_empty());
+        evaluator.flush_all_buffers().unwrap();
+        assert!(evaluator.stdout_buffer.borrow().buffer().is_empty());
+        assert_eq!(eval_code("flush()").unwrap(), Value::Empty);
+        assert!(eval_code("flush(1)").is_err());
+    }
+
+    /// 100k lines through buffered say(), against the same loop flushing after every line as
+    /// say() used to. Timings go to stderr, so send stdout to a pipe:
+    /// `cargo test --release bench_buffered_say -- --ignored --nocapture | cat > /dev/null`
+    #[test]
+    #[ignore]
+    fn bench_buffered_say() {
+        let run = |body: &str| {
+            let code = format!("for (i from 1 to 100000):\n{}\nend", body);
+            let ast = Parser::new(Lexer::new(&code).tokenize().unwrap()).parse().unwrap();
+            let mut evaluator = Evaluator::new();
+            let start = std::time::Instant::now();
+            evaluator.eval_block(&ast).unwrap();
+            evaluator.flush_all_buffers().unwrap();
+            start.elapsed()
+        };
+        let buffered = run("    say(i)");
+        let per_line = run("    say(i)\n    flush()");
+        eprintln!("buffered {:.1?}, flushed per line {:.1?}: {:.2}x", buffered, per_line, per_line.as_secs_f64() / buffered.as_secs_f64());
+    }
+
+    #[test]
+    fn test_print_and_println_join_arguments() {
+        let mut evaluator = Evaluator::new();
+        let statements = Parser::new(Lexer::new("print(\"a\", 1.5)\nprint(\"|\")\nprintln(\"x\", 3, true)\nprintln()").tokenize().unwrap()).parse().unwrap();
+        for stmt in &statements {
+            assert_eq!(evaluator.eval(stmt).unwrap(), Value::Empty);
+        }
+        assert_eq!(evaluator.stdout_buffer.borrow().buffer(), b"a 1.5|x 3 true\n\n");
+        evaluator.flush_all_buffers().unwrap();
+    }
+
+    #[test]
+    fn test_follow_pipeline_calls_stages_in_order() {
+        let defs = "func double(x):\n    return x * 2\nend\nfunc inc(x):\n    return x + 1\nend\nfunc add(x, y):\n    return x + y\nend\n";
+        let run = |src: &str| eval_code(&format!("{}{}", defs, src)).unwrap();
+        assert_eq!(run("3 follow double follow inc"), Value::Number(7.0));
+        assert_eq!(run("3 follow inc follow double"), Value::Number(8.0));
+        assert_eq!(run("y = 1 follow add(10) follow double\ny"), Value::Number(22.0));
+        // `follow` at the start of a line is still the condition form
+        assert!(matches!(
+            Parser::new(Lexer::new("x = 1\nfollow (x > 0)").tokenize().unwrap()).parse().unwrap()[1],
+            Expr::Follow { .. }
+        ));
+        assert!(eval_code("3 follow 4").is_err());
+    }
+
+    #[test]
+    fn test_array_mutators_edit_variables_in_place() {
+        let nums = |v: &[f64]| Value::Array(v.iter().map(|n| Value::Number(*n)).collect());
+        assert_eq!(eval_code("a = [1, 2]\na.push(3)\na.insert(1, 0)\na.insert(99, 4)\na").unwrap(), nums(&[0.0, 1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(eval_code("a = [1, 2, 3]\nx = a.pop()\nx * 10 + a.len()").unwrap(), Value::Number(32.0));
+        assert_eq!(eval_code("a = [5, 6, 7]\nx = a.remove(2)\na.push(x)\na").unwrap(), nums(&[5.0, 7.0, 6.0]));
+        assert_eq!(eval_code("a = [1]\na.clear()\na.pop()").unwrap(), Value::Empty);
+        assert!(eval_code("a = [1]\na.remove(2)").is_err());
+        assert!(eval_code("a = [1]\na.remove(0)").is_err());
+        assert!(eval_code("const a = [1]\na.push(2)").is_err());
+        // Arguments see the array before the mutation
+        assert_eq!(eval_code("a = [1]\na.push(a.len())\na").unwrap(), nums(&[1.0, 1.0]));
+        // Temporaries are copied: pop/remove give the element, the rest the new array
+        assert_eq!(eval_code("[1, 2].push(3)").unwrap(), nums(&[1.0, 2.0, 3.0]));
+        assert_eq!(eval_code("[1, 2, 3].remove(1)").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_format_placeholders_and_precision() {
+        let text = |src: &str| eval_code(src).unwrap();
+        assert_eq!(text("format(\"Name: {}, Age: {}\", \"Ann\", 30)"), Value::String("Name: Ann, Age: 30".to_string()));
+        assert_eq!(text("format(\"{1} before {0}, {1} again\", \"a\", \"b\")"), Value::String("b before a, b again".to_string()));
+        assert_eq!(text("format(\"{:.2} / {0:.0} / {:.3}\", 3.14159, 2)"), Value::String("3.14 / 3 / 2.000".to_string()));
+        assert_eq!(text("format(\"{{}} {{{}}} }}\", 1.5)"), Value::String("{} {1.5} }".to_string()));
+        assert!(eval_code("format(\"{} {}\", 1)").is_err());
+        assert!(eval_code("format(\"{}\", 1, 2)").is_err());
+        assert!(eval_code("format(\"{:.2}\", \"x\")").is_err());
+        assert!(eval_code("format(\"oops }\")").is_err());
+        assert!(eval_code("format(\"{0\")").is_err());
+    }
+
+    #[test]
+    fn test_null_coalesce_only_triggers_on_null_or_empty() {
+        assert_eq!(eval_code("0 ?? 5").unwrap(), Value::Number(0.0));
+        assert_eq!(eval_code("false ?? 5").unwrap(), Value::Boolean(false));
+        assert_eq!(eval_code("\"\" ?? 5").unwrap(), Value::String(String::new()));
+        assert_eq!(eval_code("empty ?? 5").unwrap(), Value::Number(5.0));
+        assert_eq!(eval_code("0 or 5").unwrap(), Value::Number(5.0));
+        assert_eq!(eval_code("empty ?? empty ?? 7").unwrap(), Value::Number(7.0));
+        assert_eq!(eval_code("empty or 0 ?? 9").unwrap(), Value::Number(0.0));
+        // The right side is only evaluated when needed
+        assert_eq!(eval_code("3 ?? no_such_function()").unwrap(), Value::Number(3.0));
+
+        let mut evaluator = Evaluator::new();
+        let null = evaluator.parse_json_string("null").unwrap();
+        evaluator.set_variable("missing".to_string(), null);
+        let mut parser = Parser::new(Lexer::new("missing ?? 5").tokenize().unwrap());
+        let ast = parser.parse().unwrap();
+        assert_eq!(evaluator.eval(&ast[0]).unwrap(), Value::Number(5.0));
+    }
+
+    #[test]
+    fn test_hot_loop_deadline_stops_loops_only() {
+        let parse = |src: &str| Parser::new(Lexer::new(src).tokenize().unwrap()).parse().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_hot_loop_deadline(Some(Instant::now()));
+        assert_eq!(evaluator.eval(&parse("1 + 2")[0]).unwrap(), Value::Number(3.0));
+        assert!(!evaluator.hot_loop_tripped());
+        let spin = parse("n = 0\nwhile (true):\n    n = n + 1\nend");
+        evaluator.eval(&spin[0]).unwrap();
+        assert!(evaluator.eval(&spin[1]).is_err());
+        assert!(evaluator.hot_loop_tripped());
+        evaluator.set_hot_loop_deadline(None);
+        assert!(!evaluator.hot_loop_tripped());
+        let program = parse("n = 0\nfor (i from 1 to 3):\n    n = n + i\nend\nn");
+        let mut result = Value::Empty;
+        for stmt in &program {
+            result = evaluator.eval(stmt).unwrap();
+        }
+        assert_eq!(result, Value::Number(6.0));
+    }
+
+    #[test]
+    fn test_whole_numbers_stay_exact_past_2_pow_53() {
+        assert_eq!(eval_code("9007199254740992 + 1").unwrap(), Value::Int(9_007_199_254_740_993));
+        assert_eq!(eval_code("9007199254740993").unwrap(), Value::Int(9_007_199_254_740_993));
+        assert_eq!(eval_code("9007199254740993 - 1").unwrap(), Value::Number(9_007_199_254_740_992.0));
+        assert_eq!(eval_code("3037000499 * 3037000499").unwrap(), Value::Int(9_223_372_030_926_249_001));
+        assert_eq!(eval_code("9007199254740993 % 10").unwrap(), Value::Number(3.0));
+        assert_eq!(eval_code("9007199254740993 > 9007199254740992").unwrap(), Value::Boolean(true));
+        assert_eq!(eval_code("9007199254740993 == 9007199254740992").unwrap(), Value::Boolean(false));
+        assert_eq!(eval_code("to_string(-9007199254740993)").unwrap(), Value::String("-9007199254740993".to_string()));
+        // Division, fractions and i64 overflow fall back to floats
+        assert_eq!(eval_code("9007199254740993 / 1").unwrap(), Value::Number(9_007_199_254_740_992.0));
+        assert!(matches!(eval_code("9007199254740993 + 0.5").unwrap(), Value::Number(_)));
+        assert_eq!(eval_code("9223372036854775807 + 1").unwrap(), Value::Number(9_223_372_036_854_775_808.0));
+        assert_eq!(eval_code("typeof(9007199254740993)").unwrap(), Value::String("number".to_string()));
+        // Numeric builtins take Int like any other number
+        let big = Value::Int(9_007_199_254_740_993);
+        assert_eq!(eval_code("abs(-9007199254740993)").unwrap(), big);
+        assert_eq!(eval_code("floor(9007199254740993)").unwrap(), big);
+        assert_eq!(eval_code("max(1, 9007199254740993, 9007199254740992)").unwrap(), big);
+        assert_eq!(eval_code("min([9007199254740993, 9007199254740992.0])").unwrap(), Value::Number(9_007_199_254_740_992.0));
+        assert_eq!(eval_code("is_number(9007199254740993)").unwrap(), Value::Boolean(true));
+        assert_eq!(eval_code("to_number(\"9007199254740993\")").unwrap(), big);
+        assert_eq!(eval_code("toNumber(\"9007199254740993\")").unwrap(), big);
+        assert_eq!(eval_code("toString(9007199254740993)").unwrap(), Value::String("9007199254740993".to_string()));
+        assert_eq!(eval_code("x = 9007199254740992\nx++\nx").unwrap(), big);
+        assert!(matches!(eval_code("sqrt(9007199254740993)").unwrap(), Value::Number(_)));
+        // A counted loop hands its variable over as Int past 2^53, and stops at i64::MAX
+        assert_eq!(eval_code("for (i from 9007199254740992 to 9007199254740993):\n    last = i\nend\nlast").unwrap(), big);
+        assert_eq!(eval_code("n = 0\nfor (i from 9223372036854775806 to 9223372036854775807):\n    n = n + 1\nend\nn").unwrap(), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_include_encrypted_bytecode_module() {
+        let parse = |src: &str| Parser::new(Lexer::new(src).tokenize().unwrap()).parse().unwrap();
+        let program = crate::compiler::BytecodeCompiler::new()
+            .compile(&parse("answer = 40 + 2\ngreeting = \"hi\""))
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("mintas_ms_include_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let module = dir.join("mylib").to_string_lossy().into_owned();
+        crate::encryption::save_encrypted_bytecode(&program, &format!("{}.ms", module), Some("k3y")).unwrap();
+
+        let mut evaluator = Evaluator::new();
+        let missing = evaluator.load_module(&format!("{}.ms", module), None).unwrap_err();
+        assert!(missing.to_string().contains("pass --secret"));
+        evaluator.set_module_secret(Some("wrong".to_string()));
+        let wrong = evaluator.load_module(&format!("{}.ms", module), None).unwrap_err();
+        assert!(wrong.to_string().contains("does not match"));
+
+        evaluator.set_module_secret(Some("k3y".to_string()));
+        evaluator.load_module(&format!("{}.ms", module), Some("lib")).unwrap();
+        assert_eq!(evaluator.variables.get("lib.answer"), Some(&Value::Number(42.0)));
+        assert_eq!(evaluator.variables.get("lib.greeting"), Some(&Value::String("hi".to_string())));
+        assert!(matches!(&parse("include mylib.ms as m")[0], Expr::Include { module_name, .. } if module_name == "mylib.ms"));
+
+        let program = crate::compiler::BytecodeCompiler::new()
+            .compile(&parse("export answer = 42
+helper = 1
+shown = 2
+export shown"))
+            .unwrap();
+        crate::encryption::save_encrypted_bytecode(&program, &format!("{}.ms", module), Some("k3y")).unwrap();
+        evaluator.load_module(&format!("{}.ms", module), Some("ex")).unwrap();
+        assert_eq!(evaluator.variables.get("ex.answer"), Some(&Value::Number(42.0)));
+        assert_eq!(evaluator.variables.get("ex.shown"), Some(&Value::Number(2.0)));
+        assert!(!evaluator.variables.contains_key("ex.helper"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_builtins_match_find_and_replace() {
+        assert_eq!(eval_code("regex_match(\"order 42\", \"[0-9]+\")").unwrap(), Value::Boolean(true));
+        assert_eq!(eval_code("regex_match(\"no digits\", \"^[0-9]+$\")").unwrap(), Value::Boolean(false));
+        let found = eval_code("regex_find(\"a1 b22 c333\", \"[0-9]+\")").unwrap();
+        let expected = ["1", "22", "333"].iter().map(|s| Value::String(s.to_string())).collect();
+        assert_eq!(found, Value::Array(expected));
+        assert_eq!(eval_code("regex_find(\"abc\", \"[0-9]\")").unwrap(), Value::Array(vec![]));
+        assert_eq!(
+            eval_code("regex_replace(\"2024-01-05\", \"([0-9]+)-([0-9]+)-([0-9]+)\", \"$3/$2/$1\")").unwrap(),
+            Value::String("05/01/2024".to_string())
+        );
+
+        for call in ["regex_match(\"x\", \"(\")", "regex_find(\"x\", \"[a-\")", "regex_replace(\"x\", \"*\", \"y\")"] {
+            let err = eval_code(call).unwrap_err().to_string();
+            assert!(err.contains("Invalid regex pattern"), "{}: {}", call, err);
+        }
+    }
+
+    #[test]
+    fn test_include_merges_only_exported_names() {
+        let dir = std::env::temp_dir().join(format!("mintas_export_include_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let module = dir.join("shapes").to_string_lossy().into_owned();
+        std::fs::write(
+            format!("{}.as", module),
+            "func scale(x):\n    return x * factor\nend\nfunc area(w, h):\n    return scale(w * h)\nend\nfactor = 10\nunit = \"cm\"\nexport area, unit\n",
+        )
+        .unwrap();
+
+        let mut evaluator = Evaluator::new();
+        evaluator.load_module(&module, None).unwrap();
+        let run = |evaluator: &mut Evaluator, code: &str| {
+            let ast = Parser::new(Lexer::new(code).tokenize().unwrap()).parse().unwrap();
+            ast.iter().map(|expr| evaluator.eval(expr)).last().unwrap()
+        };
+        // The exported function still reaches the module's private helper and variable
+        assert_eq!(run(&mut evaluator, "area(2, 3)").unwrap(), Value::Number(60.0));
+        assert_eq!(run(&mut evaluator, "unit").unwrap(), Value::String("cm".to_string()));
+        assert!(run(&mut evaluator, "scale(1)").is_err());
+        assert!(run(&mut evaluator, "factor").is_err());
+
+        // Without `export` every name is merged, as before
+        std::fs::write(format!("{}.as", module), "func scale(x):\n    return x * 2\nend\nfactor = 10\n").unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.load_module(&module, None).unwrap();
+        assert_eq!(run(&mut evaluator, "scale(4) + factor").unwrap(), Value::Number(18.0));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_include_as_alias_namespaces_module() {
+        assert_eq!(eval_code("include math as m\nm.sqrt(16)").unwrap(), Value::Number(4.0));
+
+        // Two modules defining the same function no longer clash once each has an alias
+        let dir = std::env::temp_dir().join(format!("mintas_alias_include_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("left").to_string_lossy().into_owned(), dir.join("right").to_string_lossy().into_owned());
+        std::fs::write(format!("{}.as", a), "func name():\n    return \"left\"\nend\n").unwrap();
+        std::fs::write(format!("{}.as", b), "func name():\n    return \"right\"\nend\n").unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.load_module(&a, Some("l")).unwrap();
+        evaluator.load_module(&b, Some("r")).unwrap();
+        let ast = Parser::new(Lexer::new("l.name() + r.name()").tokenize().unwrap()).parse().unwrap();
+        assert_eq!(evaluator.eval(&ast[0]).unwrap(), Value::String("leftright".to_string()));
+        assert!(!evaluator.functions.contains_key("name"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
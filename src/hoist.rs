@@ -0,0 +1,380 @@
+use crate::ast_stats::children;
+use crate::parser::{BinaryOp, ClassMember, Expr, UnaryOp};
+use std::collections::HashSet;
+
+/// Builtins whose result depends only on their arguments
+const PURE_BUILTINS: &[&str] = &[
+    "abs", "sqrt", "floor", "ceil", "round", "pow", "min", "max", "sum", "avg", "idiv", "mod", "divmod",
+    "typeof", "to_string", "to_number", "toString", "toNumber", "is_number", "is_string", "is_array", "is_table",
+];
+/// Methods on arrays, strings and tables that read their receiver without changing it
+const PURE_METHODS: &[&str] = &["len", "keys", "values", "entries", "upper", "lower", "contains", "has"];
+
+/// Interpreter pass run before a script executes: a loop-invariant expression such as
+/// `len(items)` or `a * b` inside a loop is computed once per entry to the loop instead of on
+/// every iteration. Each one is replaced by `$invariantN ?? ($invariantN = expr)`, and the
+/// loop is wrapped in `if (true):` that first clears `$invariantN`, so the expression still
+/// runs lazily at its first use (an error surfaces exactly where it did) and never runs for a
+/// loop that doesn't iterate. The evaluator drops the slots once the top-level statement
+/// holding the loop finishes. `holds_builder` says whether the evaluator already has a string
+/// builder from earlier code. Returns the number of expressions hoisted.
+pub fn hoist_loop_invariants(program: &mut [Expr], holds_builder: bool) -> usize {
+    let mut hoister = Hoister { shared_builders: holds_builder, ..Hoister::default() };
+    for stmt in program.iter() {
+        hoister.collect_program_facts(stmt);
+    }
+    hoister.block(program);
+    hoister.hoisted
+}
+
+#[derive(Default)]
+struct Hoister {
+    /// `global` names: any function called from a loop might assign them
+    globals: HashSet<String>,
+    /// Class methods, which could shadow a pure method name with one that has side effects
+    class_methods: HashSet<String>,
+    /// A string builder may exist: the evaluator already held one, or the program names
+    /// `string_builder` or includes code that could. A builder is shared between bindings, so
+    /// `len()` on any variable might change behind its back.
+    shared_builders: bool,
+    hoisted: usize,
+}
+
+impl Hoister {
+    fn collect_program_facts(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Global { names } => self.globals.extend(names.iter().cloned()),
+            Expr::Call { name, .. } | Expr::Variable(name) if name == "string_builder" => self.shared_builders = true,
+            Expr::Include { .. } => self.shared_builders = true,
+            Expr::Class { members, .. } => {
+                for member in members {
+                    if let ClassMember::Method { name, .. } = member {
+                        self.class_methods.insert(name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        for child in children(expr) {
+            self.collect_program_facts(child);
+        }
+    }
+
+    fn block(&mut self, body: &mut [Expr]) {
+        for stmt in body.iter_mut() {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &mut Expr) {
+        if matches!(stmt, Expr::WhileLoop { .. } | Expr::ForLoop { .. } | Expr::ForInLoop { .. } | Expr::SmartLoop { .. }) {
+            self.hoist_loop(stmt);
+        }
+        // Inner loops get their own pass for what only they hold constant
+        match stmt {
+            Expr::IfExpr { then_branch, else_if_branches, else_branch, .. } => {
+                self.block(then_branch);
+                for (_, body) in else_if_branches {
+                    self.block(body);
+                }
+                if let Some(body) = else_branch {
+                    self.block(body);
+                }
+            }
+            Expr::WhileLoop { body, else_branch, .. } | Expr::ForInLoop { body, else_branch, .. } => {
+                self.block(body);
+                if let Some(body) = else_branch {
+                    self.block(body);
+                }
+            }
+            Expr::SmartLoop { body, .. } | Expr::ForLoop { body, .. } | Expr::Function { body, .. } | Expr::Task { body, .. } => {
+                self.block(body)
+            }
+            Expr::TryCatch { try_block, catch_block, .. } => {
+                self.block(try_block);
+                self.block(catch_block);
+            }
+            Expr::Switch { cases, default_case, .. } => {
+                for (_, body) in cases {
+                    self.block(body);
+                }
+                if let Some(body) = default_case {
+                    self.block(body);
+                }
+            }
+            Expr::TypeMatch { arms, default_case, .. } => {
+                for (_, body) in arms {
+                    self.block(body);
+                }
+                if let Some(body) = default_case {
+                    self.block(body);
+                }
+            }
+            Expr::Class { members, .. } => {
+                for member in members {
+                    if let ClassMember::Method { body, .. } = member {
+                        self.block(body);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn hoist_loop(&mut self, lp: &mut Expr) {
+        let mut unstable = self.globals.clone();
+        if !self.assigned_names(lp, &mut unstable) {
+            return;
+        }
+        let mut slots = Vec::new();
+        match lp {
+            Expr::WhileLoop { condition, body, .. } => {
+                self.replace(condition, &unstable, &mut slots);
+                body.iter_mut().for_each(|stmt| self.replace(stmt, &unstable, &mut slots));
+            }
+            Expr::ForLoop { body, .. } | Expr::ForInLoop { body, .. } | Expr::SmartLoop { body, .. } => {
+                body.iter_mut().for_each(|stmt| self.replace(stmt, &unstable, &mut slots));
+            }
+            _ => return,
+        }
+        if slots.is_empty() {
+            return;
+        }
+        let mut wrapped: Vec<Expr> = slots
+            .into_iter()
+            .map(|name| Expr::Assign { name, value: Box::new(Expr::Empty), is_const: false })
+            .collect();
+        wrapped.push(std::mem::replace(lp, Expr::Empty));
+        *lp = Expr::IfExpr {
+            condition: Box::new(Expr::Boolean(true)),
+            then_branch: wrapped,
+            else_if_branches: Vec::new(),
+            else_branch: None,
+        };
+    }
+
+    /// Swaps each largest invariant subexpression of `expr` for a read of its cached value
+    fn replace(&mut self, expr: &mut Expr, unstable: &HashSet<String>, slots: &mut Vec<String>) {
+        let worth_caching = matches!(expr, Expr::BinaryOp { .. } | Expr::UnaryOp { .. } | Expr::Call { .. } | Expr::MethodCall { .. } | Expr::Index { .. });
+        if worth_caching && self.is_invariant(expr, unstable) {
+            let name = format!("$invariant{}", self.hoisted);
+            self.hoisted += 1;
+            let value = std::mem::replace(expr, Expr::Empty);
+            *expr = Expr::BinaryOp {
+                op: BinaryOp::NullCoalesce,
+                left: Box::new(Expr::Variable(name.clone())),
+                right: Box::new(Expr::Assign { name: name.clone(), value: Box::new(value), is_const: false }),
+            };
+            slots.push(name);
+            return;
+        }
+        let mut recurse = |e: &mut Expr| self.replace(e, unstable, slots);
+        match expr {
+            // A cached value from an enclosing loop, and code that runs in its own scope
+            Expr::Assign { name, .. } if name.starts_with('$') => {}
+            Expr::Function { .. } | Expr::Task { .. } | Expr::Class { .. } => {}
+            Expr::BinaryOp { left, right, .. } => {
+                recurse(left);
+                recurse(right);
+            }
+            Expr::UnaryOp { expr, .. } | Expr::Spread(expr) | Expr::SuperSet(expr) => recurse(expr),
+            Expr::Assign { value, .. } | Expr::CompoundAssign { value, .. } => recurse(value),
+            Expr::MultiAssign { values, .. } | Expr::Array(values) | Expr::Call { args: values, .. } | Expr::New { args: values, .. } => {
+                values.iter_mut().for_each(recurse)
+            }
+            Expr::Table(entries) => entries.iter_mut().for_each(|(_, value)| recurse(value)),
+            Expr::MethodCall { object, args, .. } => {
+                recurse(object);
+                args.iter_mut().for_each(recurse);
+            }
+            Expr::Index { object, index } => {
+                recurse(object);
+                recurse(index);
+            }
+            Expr::Property { object, .. } => recurse(object),
+            Expr::PropertyAssign { object, value, .. } => {
+                recurse(object);
+                recurse(value);
+            }
+            Expr::Ternary { condition, then_expr: then_branch, else_expr: else_branch }
+            | Expr::SmartCondition { condition, then_branch, else_branch } => {
+                recurse(condition);
+                recurse(then_branch);
+                recurse(else_branch);
+            }
+            Expr::Return { value: Some(value) } => recurse(value),
+            Expr::Cond { condition } | Expr::Follow { condition, .. } => recurse(condition),
+            Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
+                recurse(condition);
+                then_branch.iter_mut().for_each(&mut recurse);
+                for (cond, body) in else_if_branches {
+                    recurse(cond);
+                    body.iter_mut().for_each(&mut recurse);
+                }
+                else_branch.iter_mut().flatten().for_each(recurse);
+            }
+            Expr::WhileLoop { condition, body, else_branch } => {
+                recurse(condition);
+                body.iter_mut().for_each(&mut recurse);
+                else_branch.iter_mut().flatten().for_each(recurse);
+            }
+            Expr::ForLoop { start, end, body, .. } => {
+                recurse(start);
+                recurse(end);
+                body.iter_mut().for_each(recurse);
+            }
+            Expr::ForInLoop { iterable, body, else_branch, .. } => {
+                recurse(iterable);
+                body.iter_mut().for_each(&mut recurse);
+                else_branch.iter_mut().flatten().for_each(recurse);
+            }
+            Expr::SmartLoop { count, body, .. } => {
+                recurse(count);
+                body.iter_mut().for_each(recurse);
+            }
+            Expr::TryCatch { try_block, catch_block, .. } => {
+                try_block.iter_mut().chain(catch_block.iter_mut()).for_each(recurse)
+            }
+            Expr::Switch { expression, cases, default_case } => {
+                recurse(expression);
+                for (value, body) in cases {
+                    recurse(value);
+                    body.iter_mut().for_each(&mut recurse);
+                }
+                default_case.iter_mut().flatten().for_each(recurse);
+            }
+            Expr::TypeMatch { subject, arms, default_case } => {
+                recurse(subject);
+                arms.iter_mut().flat_map(|(_, body)| body).for_each(&mut recurse);
+                default_case.iter_mut().flatten().for_each(recurse);
+            }
+            _ => {}
+        }
+    }
+
+    /// Pure and built only from literals and variables the loop never assigns
+    fn is_invariant(&self, expr: &Expr, unstable: &HashSet<String>) -> bool {
+        match expr {
+            Expr::Number(_) | Expr::Int(_) | Expr::String(_) | Expr::RawString(_) | Expr::Boolean(_) => true,
+            Expr::Variable(name) => !unstable.contains(name),
+            Expr::BinaryOp { left, right, .. } => self.is_invariant(left, unstable) && self.is_invariant(right, unstable),
+            Expr::UnaryOp { op: UnaryOp::Negate | UnaryOp::Not, expr } => self.is_invariant(expr, unstable),
+            Expr::Index { object, index } => self.is_invariant(object, unstable) && self.is_invariant(index, unstable),
+            Expr::Call { name, args, .. } => {
+                PURE_BUILTINS.contains(&name.as_str()) && args.iter().all(|arg| self.is_invariant(arg, unstable))
+            }
+            Expr::MethodCall { object, method, args } => {
+                self.is_pure_method(method)
+                    && self.is_invariant(object, unstable)
+                    && args.iter().all(|arg| self.is_invariant(arg, unstable))
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds every name `expr` may assign to `out`. False when `expr` holds something whose
+    /// effect on variables can't be seen from the syntax (a module include or a class definition).
+    fn assigned_names(&self, expr: &Expr, out: &mut HashSet<String>) -> bool {
+        let root = |object: &Expr| {
+            let mut object = object;
+            while let Expr::Property { object: inner, .. } | Expr::Index { object: inner, .. } | Expr::MethodCall { object: inner, .. } = object {
+                object = inner;
+            }
+            match object {
+                Expr::Variable(name) => Some(name.clone()),
+                _ => None,
+            }
+        };
+        match expr {
+            Expr::Include { .. } | Expr::Export { .. } | Expr::Class { .. } => return false,
+            Expr::Assign { name, .. } | Expr::CompoundAssign { name, .. } => {
+                out.insert(name.clone());
+            }
+            Expr::MultiAssign { names, .. } | Expr::Global { names } | Expr::ForInLoop { vars: names, .. } => {
+                out.extend(names.iter().cloned());
+            }
+            Expr::ForLoop { var, .. } | Expr::SmartLoop { var, .. } => {
+                out.insert(var.clone());
+            }
+            Expr::TryCatch { error_var: Some(name), .. } => {
+                out.insert(name.clone());
+            }
+            Expr::UnaryOp { op: UnaryOp::Increment | UnaryOp::Decrement, expr } => out.extend(root(expr)),
+            // Methods such as `push` write their receiver back to its variable
+            Expr::MethodCall { object, method, .. } if !self.is_pure_method(method) => out.extend(root(object)),
+            Expr::PropertyAssign { object, .. } => out.extend(root(object)),
+            _ => {}
+        }
+        children(expr).into_iter().all(|child| self.assigned_names(child, out))
+    }
+
+    fn is_pure_method(&self, method: &str) -> bool {
+        PURE_METHODS.contains(&method) && !self.class_methods.contains(method) && !(self.shared_builders && method == "len")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::{Evaluator, Value};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn run(code: &str, hoist: bool) -> (Value, usize) {
+        let mut program = Parser::new(Lexer::new(code).tokenize().unwrap()).parse().unwrap();
+        let hoisted = if hoist { hoist_loop_invariants(&mut program, false) } else { 0 };
+        let mut evaluator = Evaluator::new();
+        let mut result = Value::Empty;
+        for stmt in &program {
+            result = evaluator.eval_top_level(stmt).unwrap();
+        }
+        (result, hoisted)
+    }
+
+    #[test]
+    fn test_hoists_only_expressions_the_loop_cannot_change() {
+        let code = "a = 3\nb = 4\nitems = [1, 2, 3]\ntotal = 0\nfor (i from 1 to 5):\n    total = total + a * b + items.len() + i * 2\nend\ntotal";
+        assert_eq!(run(code, true), (Value::Number(105.0), 2));
+        assert_eq!(run(code, false).0, Value::Number(105.0));
+
+        // `b` changes inside the loop, so `a * b` must be recomputed
+        let code = "a = 2\nb = 1\ntotal = 0\nfor (i from 1 to 3):\n    total = total + a * b\n    b = b + 1\nend\ntotal";
+        assert_eq!(run(code, true), (Value::Number(12.0), 0));
+
+        // A method call that mutates its receiver makes it unstable
+        let code = "items = [1]\nn = 0\nfor (i from 1 to 3):\n    n = n + items.len()\n    items.push(i)\nend\nn";
+        assert_eq!(run(code, true), (Value::Number(6.0), 0));
+
+        // Each entry to the inner loop sees the outer loop's current value
+        let code = "total = 0\nfor (i from 1 to 3):\n    for (j from 1 to 2):\n        total = total + i * 10\n    end\nend\ntotal";
+        assert_eq!(run(code, true), (Value::Number(120.0), 1));
+
+        // A builder appended through another binding changes under `sb.len()`
+        let code = "sb = string_builder()\nother = sb\nn = 0\nfor (i from 1 to 3):\n    other.append(\"ab\")\n    n = n + sb.len()\nend\nn";
+        assert_eq!(run(code, true), (Value::Number(12.0), 0));
+
+        // A loop that never runs never evaluates the expression
+        let code = "x = \"text\"\nfor (i in []):\n    say(x - 1)\nend\n7";
+        assert_eq!(run(code, true), (Value::Number(7.0), 1));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_loop_invariant_product() {
+        let code = "a = 1.5\nb = 2.5\nitems = range(1, 100)\ntotal = 0\ni = 0\nwhile (i < 1000000):\n    total = total + a * b * items.len()\n    i = i + 1\nend\ntotal";
+        let time = |hoist: bool| {
+            let start = std::time::Instant::now();
+            let (result, _) = run(code, hoist);
+            (result, start.elapsed())
+        };
+        let (plain, plain_time) = time(false);
+        let (hoisted, hoisted_time) = time(true);
+        assert_eq!(plain, hoisted);
+        println!(
+            "1M iterations: {:.1?} recomputing, {:.1?} hoisted ({:.2}x)",
+            plain_time,
+            hoisted_time,
+            plain_time.as_secs_f64() / hoisted_time.as_secs_f64()
+        );
+    }
+}
@@ -2,7 +2,11 @@ use crate::errors::{MintasError, MintasResult, SourceLocation};
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Number(f64),
+    /// Whole-number literal too large to be exact as `Number`
+    Int(i64),
     String(String),
+    /// `r"..."` literal, taken verbatim with no escapes or `${}` interpolation
+    RawString(String),
     Boolean(bool),
     Maybe,
     Empty,
@@ -44,6 +48,7 @@ pub enum Token {
     So,
     Const,
     Consta,
+    Global,
     If,
     When,
     Else,
@@ -61,7 +66,9 @@ pub enum Token {
     Exit,
     Proceed,
     Dot,
+    Ellipsis,
     Question,
+    NullCoalesce,
     Dollar,
     At,
     Func,
@@ -80,6 +87,7 @@ pub enum Token {
     Extends,
     Super,
     Include,
+    Export,
     As,
     Cond,
     Follow,
@@ -105,12 +113,15 @@ pub enum Token {
 pub struct TokenWithLocation {
     pub token: Token,
     pub location: SourceLocation,
+    /// Text of the `##` doc comment lines directly before this token
+    pub doc: Option<String>,
 }
 impl TokenWithLocation {
     pub fn new(token: Token, line: usize, column: usize) -> Self {
         Self {
             token,
             location: SourceLocation::new(line, column),
+            doc: None,
         }
     }
 }
@@ -119,6 +130,7 @@ pub struct Lexer {
     position: usize,
     line: usize,
     column: usize,
+    pending_doc: Vec<String>,
 }
 impl Lexer {
     pub fn new(input: &str) -> Self {
@@ -127,6 +139,7 @@ impl Lexer {
             position: 0,
             line: 1,
             column: 1,
+            pending_doc: Vec::new(),
         }
     }
     fn current_char(&self) -> Option<char> {
@@ -156,11 +169,46 @@ impl Lexer {
             }
         }
     }
-    fn skip_comments(&mut self) {
+    fn skip_comments(&mut self) -> MintasResult<()> {
         if self.current_char() == Some('#') {
+            let (start_line, start_column) = (self.line, self.column);
             self.advance();
-            if self.current_char() == Some('#') {
+            if self.current_char() == Some('*') {
                 self.advance();
+                let mut depth = 1;
+                while depth > 0 {
+                    match (self.current_char(), self.peek_char()) {
+                        (None, _) => {
+                            return Err(MintasError::LexerError {
+                                message: "Unterminated block comment".to_string(),
+                                location: SourceLocation::new(start_line, start_column),
+                            });
+                        }
+                        (Some('#'), Some('*')) => {
+                            self.advance();
+                            self.advance();
+                            depth += 1;
+                        }
+                        (Some('*'), Some('#')) => {
+                            self.advance();
+                            self.advance();
+                            depth -= 1;
+                        }
+                        _ => self.advance(),
+                    }
+                }
+            } else if self.current_char() == Some('#') {
+                self.advance();
+                if self.at_doc_comment() {
+                    let line_end = self.line_end(self.position);
+                    let line: String = self.input[self.position..line_end].iter().collect();
+                    let text = line.strip_prefix(' ').unwrap_or(&line).trim_end();
+                    self.pending_doc.push(text.to_string());
+                    while self.position < line_end {
+                        self.advance();
+                    }
+                    return Ok(());
+                }
                 while let Some(ch) = self.current_char() {
                     if ch == '#' {
                         self.advance();
@@ -181,28 +229,125 @@ impl Lexer {
                 }
             }
         }
+        Ok(())
+    }
+    fn line_end(&self, from: usize) -> usize {
+        self.input[from..].iter().position(|&c| c == '\n').map_or(self.input.len(), |offset| from + offset)
+    }
+    /// Called just past a `##`. Doc comments are whole lines of `## text` directly above a
+    /// `func`; anything else is the `## ... ##` block comment, which may span lines. When a
+    /// later line would close the block, the run is only a doc comment if that line starts
+    /// the doc comment of another `func`, so commented-out code stays commented out.
+    fn at_doc_comment(&self) -> bool {
+        let marker = self.position - 2;
+        let line_start = self.input[..marker].iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+        match self.doc_line(line_start, self.line_end(marker)) {
+            // Later lines of a run that was already checked
+            Some(_) if !self.pending_doc.is_empty() => return true,
+            Some(text) if !text.is_empty() => {}
+            _ => return false,
+        }
+        let Some(mut start) = self.func_after_docs(self.line_end(marker) + 1) else {
+            return false;
+        };
+        while start < self.input.len() {
+            let end = self.line_end(start);
+            let line: String = self.input[start..end].iter().collect();
+            // An even count of markers opens and closes on the same line
+            if line.matches("##").count() % 2 == 1 {
+                return matches!(self.doc_line(start, end), Some(text) if !text.is_empty())
+                    && self.func_after_docs(end + 1).is_some();
+            }
+            start = end + 1;
+        }
+        true
+    }
+    /// The text of a `## text` line, or `None` when the line is anything else
+    fn doc_line(&self, start: usize, end: usize) -> Option<String> {
+        let line: String = self.input[start..end].iter().collect();
+        let rest = line.trim_start().strip_prefix("##")?;
+        (!rest.contains("##")).then(|| rest.trim().to_string())
     }
-    fn skip_whitespace_and_comments(&mut self) {
+    /// Skips the doc lines from `start`; if the next line is a `func`, returns where it starts
+    fn func_after_docs(&self, mut start: usize) -> Option<usize> {
+        while start < self.input.len() {
+            let end = self.line_end(start);
+            if self.doc_line(start, end).is_none() {
+                let line: String = self.input[start..end].iter().collect();
+                let code = line.trim_start();
+                let code = code.strip_prefix("export ").unwrap_or(code);
+                return code.starts_with("func ").then_some(start);
+            }
+            start = end + 1;
+        }
+        None
+    }
+    fn skip_whitespace_and_comments(&mut self) -> MintasResult<()> {
         loop {
             let start_pos = self.position;
             self.skip_whitespace();
-            self.skip_comments();
+            self.skip_comments()?;
             if self.position == start_pos {
                 break;
             }
         }
+        Ok(())
     }
-    fn read_number(&mut self) -> f64 {
-        let mut num_str = String::new();
+    /// Digits with an optional fraction and exponent (`1.5e10`, `2E-3`). `_` may separate
+    /// digits for readability (`1_000.000_5`) and is dropped before parsing.
+    fn read_number(&mut self) -> MintasResult<Token> {
+        let start = SourceLocation::new(self.line, self.column);
+        let mut num_str = self.read_digits(true)?;
+        if matches!(self.current_char(), Some('e') | Some('E')) {
+            num_str.push('e');
+            self.advance();
+            if let Some(sign @ ('+' | '-')) = self.current_char() {
+                num_str.push(sign);
+                self.advance();
+            }
+            let exponent = self.read_digits(false)?;
+            if exponent.is_empty() {
+                return Err(MintasError::LexerError {
+                    message: format!("Malformed exponent in number '{}': expected digits after '{}'", num_str, num_str.chars().last().unwrap_or('e')),
+                    location: start,
+                });
+            }
+            num_str.push_str(&exponent);
+            return match num_str.parse::<f64>() {
+                Ok(n) => Ok(Token::Number(n)),
+                Err(_) => Err(MintasError::LexerError {
+                    message: format!("Invalid number '{}'", num_str),
+                    location: start,
+                }),
+            };
+        }
+        Ok(match num_str.parse::<i64>() {
+            Ok(n) if n > 1 << 53 => Token::Int(n),
+            _ => Token::Number(num_str.parse().unwrap_or(0.0)),
+        })
+    }
+    /// Digits (and `.` when `allow_dot`), skipping `_` separators that sit between two digits
+    fn read_digits(&mut self, allow_dot: bool) -> MintasResult<String> {
+        let mut digits = String::new();
         while let Some(ch) = self.current_char() {
-            if ch.is_ascii_digit() || ch == '.' {
-                num_str.push(ch);
+            if ch.is_ascii_digit() || (allow_dot && ch == '.') {
+                digits.push(ch);
+                self.advance();
+            } else if ch == '_' {
+                let between_digits = digits.ends_with(|c: char| c.is_ascii_digit())
+                    && self.peek_char().is_some_and(|c| c.is_ascii_digit());
+                if !between_digits {
+                    return Err(MintasError::LexerError {
+                        message: "'_' in a number must sit between two digits".to_string(),
+                        location: SourceLocation::new(self.line, self.column),
+                    });
+                }
                 self.advance();
             } else {
                 break;
             }
         }
-        num_str.parse().unwrap_or(0.0)
+        Ok(digits)
     }
     fn read_identifier(&mut self) -> String {
         let mut ident = String::new();
@@ -216,6 +361,38 @@ impl Lexer {
         }
         ident
     }
+    /// Number of `#`s when a raw string (`r"..."` or `r#"..."#`) starts here
+    fn raw_string_hashes(&self) -> Option<usize> {
+        if self.current_char() != Some('r') {
+            return None;
+        }
+        let rest = &self.input[self.position + 1..];
+        let hashes = rest.iter().take_while(|&&c| c == '#').count();
+        (rest.get(hashes) == Some(&'"')).then_some(hashes)
+    }
+    /// Contents taken verbatim up to a `"` followed by the same number of `#`s
+    fn read_raw_string(&mut self, hashes: usize) -> MintasResult<String> {
+        let start = SourceLocation::new(self.line, self.column);
+        for _ in 0..hashes + 2 {
+            self.advance();
+        }
+        let mut s = String::new();
+        loop {
+            match self.current_char() {
+                Some('"') if self.input[self.position + 1..].iter().take(hashes).filter(|&&c| c == '#').count() == hashes => {
+                    for _ in 0..hashes + 1 {
+                        self.advance();
+                    }
+                    return Ok(s);
+                }
+                Some(ch) => {
+                    s.push(ch);
+                    self.advance();
+                }
+                None => return Err(MintasError::UnterminatedString { location: start }),
+            }
+        }
+    }
     fn read_string(&mut self) -> MintasResult<String> {
         let start_line = self.line;
         let start_column = self.column;
@@ -372,7 +549,7 @@ impl Lexer {
         SourceLocation::new(self.line, self.column)
     }
     pub fn next_token(&mut self) -> MintasResult<TokenWithLocation> {
-        self.skip_whitespace_and_comments();
+        self.skip_whitespace_and_comments()?;
         let start_line = self.line;
         let start_column = self.column;
         let token = match self.current_char() {
@@ -550,11 +727,22 @@ impl Lexer {
             }
             Some('.') => {
                 self.advance();
-                Token::Dot
+                if self.current_char() == Some('.') && self.peek_char() == Some('.') {
+                    self.advance();
+                    self.advance();
+                    Token::Ellipsis
+                } else {
+                    Token::Dot
+                }
             }
             Some('?') => {
                 self.advance();
-                Token::Question
+                if self.current_char() == Some('?') {
+                    self.advance();
+                    Token::NullCoalesce
+                } else {
+                    Token::Question
+                }
             }
             Some('$') => {
                 self.advance();
@@ -564,7 +752,11 @@ impl Lexer {
                 self.advance();
                 Token::At
             }
-            Some(ch) if ch.is_ascii_digit() => Token::Number(self.read_number()),
+            Some(ch) if ch.is_ascii_digit() => self.read_number()?,
+            Some('r') if self.raw_string_hashes().is_some() => {
+                let hashes = self.raw_string_hashes().unwrap_or(0);
+                Token::RawString(self.read_raw_string(hashes)?)
+            }
             Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {
                 let ident = self.read_identifier();
                 match ident.as_str() {
@@ -574,6 +766,7 @@ impl Lexer {
                     "so" => Token::So,
                     "const" => Token::Const,
                     "consta" => Token::Consta,
+                    "global" => Token::Global,
                     "true" => Token::Boolean(true),
                     "false" => Token::Boolean(false),
                     "maybe" => Token::Maybe,
@@ -624,6 +817,7 @@ impl Lexer {
                     "extends" => Token::Extends,
                     "super" => Token::Super,
                     "include" => Token::Include,
+                    "export" => Token::Export,
                     "as" => Token::As,
                     "task" => Token::Task,
                     "switch" => Token::Switch,
@@ -646,7 +840,11 @@ impl Lexer {
                 });
             }
         };
-        Ok(TokenWithLocation::new(token, start_line, start_column))
+        let mut token = TokenWithLocation::new(token, start_line, start_column);
+        if !self.pending_doc.is_empty() {
+            token.doc = Some(std::mem::take(&mut self.pending_doc).join("\n"));
+        }
+        Ok(token)
     }
     pub fn tokenize(&mut self) -> MintasResult<Vec<TokenWithLocation>> {
         let mut tokens = Vec::new();
@@ -660,4 +858,151 @@ impl Lexer {
         }
         Ok(tokens)
     }
-}
\ No newline at end of file
+    /// `mintas --dump-tokens`: one line per token with its position, kind and source text
+    pub fn dump_tokens(source: &str) -> MintasResult<String> {
+        let mut lexer = Lexer::new(source);
+        let mut out = String::new();
+        loop {
+            lexer.skip_whitespace_and_comments()?;
+            let start = lexer.position;
+            let located = lexer.next_token()?;
+            let text: String = lexer.input[start..lexer.position].iter().collect();
+            let debug = format!("{:?}", located.token);
+            let kind = debug.split('(').next().unwrap_or(&debug);
+            out.push_str(&format!(
+                "{:>4}:{:<4} {:<16} {}\n",
+                located.location.line,
+                located.location.column,
+                kind,
+                text.replace('\n', "\\n").replace('\t', "\\t")
+            ));
+            if located.token == Token::EOF {
+                return Ok(out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(code: &str) -> Vec<Token> {
+        Lexer::new(code).tokenize().unwrap().into_iter().map(|t| t.token).collect()
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let code = "x = 1 #* outer\n #* inner *# still outer\n # line comment inside *# y";
+        assert_eq!(tokens(code), vec![
+            Token::Identifier("x".to_string()),
+            Token::Assign,
+            Token::Number(1.0),
+            Token::Identifier("y".to_string()),
+            Token::EOF,
+        ]);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        match Lexer::new("x = 1\n  #* never #* closed *#").tokenize() {
+            Err(MintasError::LexerError { location, .. }) => {
+                assert_eq!((location.line, location.column), (2, 3));
+            }
+            other => panic!("expected lexer error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_doc_comments_attach_to_next_token() {
+        let toks = Lexer::new("## Adds numbers.\n##\n## Returns the sum.\nfunc add(a, b): ## inline ## a + b").tokenize().unwrap();
+        assert_eq!(toks[0].token, Token::Func);
+        assert_eq!(toks[0].doc.as_deref(), Some("Adds numbers.\n\nReturns the sum."));
+        assert!(toks[1..].iter().all(|t| t.doc.is_none()));
+        let kinds: Vec<Token> = toks.into_iter().map(|t| t.token).collect();
+        assert_eq!(kinds, vec![
+            Token::Func,
+            Token::Identifier("add".to_string()),
+            Token::LeftParen,
+            Token::Identifier("a".to_string()),
+            Token::Comma,
+            Token::Identifier("b".to_string()),
+            Token::RightParen,
+            Token::Colon,
+            Token::Identifier("a".to_string()),
+            Token::Plus,
+            Token::Identifier("b".to_string()),
+            Token::EOF,
+        ]);
+    }
+
+    #[test]
+    fn test_multiline_hash_block_comments_are_not_docs() {
+        let expected = vec![
+            Token::Identifier("x".to_string()),
+            Token::Assign,
+            Token::Number(1.0),
+            Token::Identifier("z".to_string()),
+            Token::EOF,
+        ];
+        assert_eq!(tokens("x = 1\n## note\ny = 2\n##\nz"), expected);
+        // Commented-out code that starts with a func line
+        assert_eq!(tokens("x = 1\n##\nfunc old():\n    y = 2\nend\n##\nz"), expected);
+        assert_eq!(tokens("x = 1\n## old implementation\nfunc old():\n    y = 2\nend\n##\nz"), expected);
+        assert_eq!(tokens("x = 1\n## old implementation\nfunc old():\n    y = 2\nend ##\nz"), expected);
+        // A closing line that documents the next func keeps both runs as docs
+        let toks = Lexer::new("## First.\nfunc a(): 1\n## Second.\nfunc b(): 2").tokenize().unwrap();
+        let docs: Vec<&str> = toks.iter().filter_map(|t| t.doc.as_deref()).collect();
+        assert_eq!(docs, vec!["First.", "Second."]);
+        let toks = Lexer::new("## not above a func\nx = 1\n##\nz").tokenize().unwrap();
+        assert!(toks.iter().all(|t| t.doc.is_none()));
+    }
+
+    #[test]
+    fn test_hash_inside_string_is_literal() {
+        assert_eq!(tokens("\"a #* b # c\""), vec![Token::String("a #* b # c".to_string()), Token::EOF]);
+    }
+
+    #[test]
+    fn test_scientific_notation_and_digit_separators() {
+        assert_eq!(tokens("1.5e10"), vec![Token::Number(1.5e10), Token::EOF]);
+        assert_eq!(tokens("2E-3"), vec![Token::Number(0.002), Token::EOF]);
+        assert_eq!(tokens("4e+2"), vec![Token::Number(400.0), Token::EOF]);
+        assert_eq!(tokens("1_000.000_5"), vec![Token::Number(1000.0005), Token::EOF]);
+        assert_eq!(tokens("1_000_000"), vec![Token::Number(1_000_000.0), Token::EOF]);
+        assert_eq!(tokens("1e1_0"), vec![Token::Number(1e10), Token::EOF]);
+        for bad in ["1e", "1e+", "2E-", "1e+x", "1__0", "1_", "1_.5", "1._5"] {
+            assert!(
+                matches!(Lexer::new(bad).tokenize(), Err(MintasError::LexerError { .. })),
+                "{} should be rejected",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_dump_tokens_shows_position_kind_and_text() {
+        let dump = Lexer::dump_tokens("x = 31 # n\nsay(r\"a\\b\", 2.5e3)").unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines[0], "   1:1    Identifier       x");
+        assert_eq!(lines[1], "   1:3    Assign           =");
+        assert!(lines[2].starts_with("   1:5    "), "{}", lines[2]);
+        assert_eq!(lines[3], "   2:1    Say              say");
+        assert_eq!(lines[5], "   2:5    RawString        r\"a\\b\"");
+        assert_eq!(lines[7], "   2:13   Number           2.5e3");
+        assert_eq!(lines.last(), Some(&"   2:19   EOF              "));
+        assert!(Lexer::dump_tokens("\"open").is_err());
+    }
+
+    #[test]
+    fn test_raw_strings_skip_escape_processing() {
+        assert_eq!(tokens(r#"r"C:\Users\name""#), vec![Token::RawString(r"C:\Users\name".to_string()), Token::EOF]);
+        assert_eq!(tokens(r#"r"\d+\.\d+/n""#), vec![Token::RawString(r"\d+\.\d+/n".to_string()), Token::EOF]);
+        assert_eq!(tokens(r##"r#"say "hi" \n"#"##), vec![Token::RawString(r#"say "hi" \n"#.to_string()), Token::EOF]);
+        assert_eq!(tokens(r###"r##"a "# b"##"###), vec![Token::RawString(r##"a "# b"##.to_string()), Token::EOF]);
+        assert_eq!(tokens("r\"\""), vec![Token::RawString(String::new()), Token::EOF]);
+        // `r` alone is still an identifier
+        assert_eq!(tokens("r + 1")[0], Token::Identifier("r".to_string()));
+        assert!(matches!(Lexer::new("r#\"open\"").tokenize(), Err(MintasError::UnterminatedString { .. })));
+    }
+}
@@ -0,0 +1,19 @@
+//! The Mintas interpreter as a library, for host programs that embed scripts.
+//!
+//! `evaluator::Evaluator` runs source with `eval_line`; `register_builtin` adds host
+//! functions scripts can call. See `examples/host_builtin.rs`.
+
+pub mod analyzer;
+pub mod ast_stats;
+pub mod builtins;
+pub mod bytecode;
+pub mod compiler;
+pub mod cranelift_backend;
+pub mod encryption;
+pub mod errors;
+pub mod evaluator;
+pub mod hoist;
+pub mod lexer;
+pub mod parser;
+pub mod table;
+pub mod vm;
@@ -1,21 +1,21 @@
-mod analyzer;
-mod bytecode;
 mod bytecode_cli;
-mod compiler;
-mod cranelift_backend;
-mod encryption;
-mod errors;
-mod evaluator;
-mod lexer;
-mod parser;
-mod vm;
+mod docgen;
+
+use mintas::{analyzer, ast_stats, builtins, compiler, cranelift_backend, encryption, errors, evaluator, hoist, lexer, parser, vm};
 
 use analyzer::CodeAnalyzer;
 use bytecode_cli::{compile_to_bytecode, run_bytecode};
-use cranelift_backend::CraneliftCompiler as JetXCompiler;
+use cranelift_backend::{CraneliftCompiler as JetXCompiler, JetXRun};
 use evaluator::{Evaluator, Value};
 use lexer::Lexer;
 use parser::Parser;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
 use std::collections::VecDeque;
 use std::env;
 use std::fs;
@@ -30,12 +30,32 @@ struct JetXStats {
     compilation_time_us: u64,
 }
 
+/// How long auto mode lets the interpreter spin in loops before switching to JetX
+const DEFAULT_JIT_THRESHOLD_US: u64 = 5000;
+
+/// Runs a script; a panic inside the interpreter comes back as an error instead of
+/// unwinding out and killing the process
+fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_jetx: bool, trace: bool, jit_threshold: std::time::Duration) -> Result<Value, String> {
+    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        execute_jetx_unguarded(code, evaluator, show_stats, force_jetx, trace, jit_threshold)
+    }));
+    outcome.unwrap_or_else(|payload| {
+        // Output the script produced before the panic still belongs on screen
+        let _ = evaluator.flush_all_buffers();
+        Err(internal_error_message(&*payload))
+    })
+}
+
+fn internal_error_message(payload: &(dyn std::any::Any + Send)) -> String {
+    format!("internal error: {} (this is a bug in Mintas; please report it)", errors::panic_message(payload))
+}
+
 /// JetX - High Performance JIT Compiler for Mintas
 /// Compiles ALL code to native machine code for C/Rust-level performance
-fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_jetx: bool) -> Result<Value, String> {
+fn execute_jetx_unguarded(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_jetx: bool, trace: bool, jit_threshold: std::time::Duration) -> Result<Value, String> {
     let total_start = std::time::Instant::now();
     
-    let statements = parse_code(code)?;
+    let (statements, statement_lines) = parse_code_with_lines(code)?;
     
     if statements.is_empty() {
         return Ok(Value::Empty);
@@ -50,7 +70,11 @@ fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_j
     
     // Static Analysis
     let mut analyzer = CodeAnalyzer::new();
-    let _ = analyzer.analyze(&statements);
+    // Findings only warn here: a wrong-arity call may sit on a path that never runs, and the
+    // interpreter raises the same error if it does. `--check` is where they fail the run.
+    if let Err(e) = analyzer.analyze(&statements) {
+        eprintln!("Warning: {}", e);
+    }
     
     // Try JetX by default (JETX for everything) - force_jetx enables it more aggressively
     let should_try_jetx = true;  // Always try JetX for eligible code
@@ -58,18 +82,42 @@ fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_j
     // Check if the last expression is numeric - only use JetX for numeric expressions
     let is_last_expr_numeric = statements.last().map_or(false, |stmt| {
         match stmt {
-            parser::Expr::Number(_) => true,
+            parser::Expr::Number(_) | parser::Expr::Int(_) => true,
             parser::Expr::BinaryOp { .. } => true,
             parser::Expr::UnaryOp { .. } => true,
             parser::Expr::Variable(_) => true,
             parser::Expr::Call { name, .. } if name == "say" => false,
+            parser::Expr::Call { name, .. } => matches!(name.as_str(), "floor" | "ceil" | "abs" | "sqrt" | "round" | "min" | "max" | "idiv" | "mod"),
             _ => false,
         }
     });
     
-    if should_try_jetx && (is_last_expr_numeric || force_jetx) {
+    // JetX only sees this program, so it runs only when every statement compiles. Debug
+    // tracing and the --time-limit clock live in the interpreter, and functions or classes
+    // left by earlier -e snippets, REPL lines or --load are invisible to compiled code.
+    let mut scope = JitScope::for_program(&statements);
+    let blocked_at = statements.iter().position(|stmt| jit_fallback(stmt, &mut scope).is_some());
+    let blocker = if evaluator.is_debug_mode() {
+        Some("debug mode runs in the interpreter".to_string())
+    } else if evaluator.has_time_limit() {
+        Some("--time-limit runs in the interpreter".to_string())
+    } else if evaluator.holds_definitions() {
+        Some("functions or classes from earlier code are in scope".to_string())
+    } else if evaluator.holds_builder() {
+        Some("a string builder from earlier code is in scope".to_string())
+    } else if let Some(i) = blocked_at {
+        Some(format!("line {} stays in the interpreter", statement_lines.get(i).copied().unwrap_or(0)))
+    } else if statements.last().is_some_and(jit_is_boolean) {
+        Some("JetX would return the last statement's true or false as 1 or 0".to_string())
+    } else {
+        None
+    };
+    let mut jetx_attempted = false;
+    let mut fallback_reason = blocker.clone().unwrap_or_else(|| "last statement is not a numeric expression".to_string());
+    if should_try_jetx && (is_last_expr_numeric || force_jetx) && blocker.is_none() {
         match JetXCompiler::new() {
             Ok(mut compiler) => {
+                jetx_attempted = true;
                 let compile_start = std::time::Instant::now();
                 
                 match compiler.compile_program(&statements) {
@@ -79,17 +127,26 @@ fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_j
                         
                         let exec_start = std::time::Instant::now();
                         match compiler.execute_main() {
-                            Ok(result) => {
+                            Ok(run) if run.bailed => {
+                                fallback_reason = "JetX met a zero divisor or an integer past 2^53".to_string();
+                                stats.jetx_compiled = false;
+                            }
+                            Ok(run) => {
                                 stats.execution_time_us = exec_start.elapsed().as_micros() as u64;
-                                
-                                // Sync variables back using proper sync function
-                                sync_jetx_variables(&statements, result, evaluator);
-
+                                sync_jetx_variables(&run, evaluator);
+                                let value = Value::Number(run.result);
+                                if statements.last().is_some_and(|stmt| should_display(&value, stmt)) {
+                                    evaluator.print_value(&value);
+                                    println!();
+                                }
+                                if trace {
+                                    trace_jit(&statements, &statement_lines, Ok(()));
+                                }
                                 if show_stats {
                                     let total_time = total_start.elapsed().as_micros() as u64;
                                     print_jetx_stats(&stats, total_time);
                                 }
-                                return Ok(Value::Number(result));
+                                return Ok(value);
                             }
                             Err(e) => {
                                 // Only fall back to interpreter if JetX execution failed
@@ -98,6 +155,7 @@ fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_j
                                     return Err(e.to_string());
                                 }
                                 eprintln!("JetX execution failed: {}, falling back to interpreter", e);
+                                fallback_reason = format!("JetX execution failed: {}", e);
                                 stats.jetx_compiled = false;
                             }
                         }
@@ -109,17 +167,80 @@ fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_j
                             return Err(e.to_string());
                         }
                         eprintln!("JetX compilation failed: {}, falling back to interpreter", e);
+                        fallback_reason = format!("JetX compilation failed: {}", e);
                         stats.jetx_compiled = false;
                     }
                 }
             }
-            Err(_) => {}
+            Err(_) => fallback_reason = "JetX is not available in this build".to_string(),
         }
     }
     
+    // The interpreter runs a copy with loop-invariant expressions cached; JetX keeps the original.
+    // Invariance is judged from this program's text, so not once earlier -e snippets, REPL
+    // lines or --load have left functions or classes behind.
+    let mut interpreted_statements = statements.clone();
+    if !evaluator.holds_definitions() {
+        hoist::hoist_loop_invariants(&mut interpreted_statements, evaluator.holds_builder());
+    }
+
+    // Auto mode: interpret first, and once loops have run past the threshold restart the
+    // program under JetX. Only self-contained numeric programs qualify, so restarting is safe.
+    if !jetx_attempted && !force_jetx && blocker.is_none() && is_auto_jit_candidate(&statements) {
+        let exec_start = std::time::Instant::now();
+        evaluator.set_hot_loop_deadline(Some(exec_start + jit_threshold));
+        let interpreted = execute_interpreter_timed(&interpreted_statements, evaluator);
+        let tripped = evaluator.hot_loop_tripped();
+        evaluator.set_hot_loop_deadline(None);
+        if !tripped {
+            if trace {
+                trace_jit(&statements, &statement_lines, Err("loops finished before the JIT threshold"));
+            }
+            let result = interpreted?;
+            stats.execution_time_us = exec_start.elapsed().as_micros() as u64;
+            if show_stats {
+                let total_time = total_start.elapsed().as_micros() as u64;
+                print_jetx_stats(&stats, total_time);
+            }
+            return Ok(result);
+        }
+        if let Ok(mut compiler) = JetXCompiler::new() {
+            let compile_start = std::time::Instant::now();
+            if compiler.compile_program(&statements).is_ok() {
+                stats.compilation_time_us = compile_start.elapsed().as_micros() as u64;
+                stats.jetx_compiled = true;
+                let jit_start = std::time::Instant::now();
+                if let Some(run) = compiler.execute_main().ok().filter(|run| !run.bailed) {
+                    stats.execution_time_us = jit_start.elapsed().as_micros() as u64;
+                    sync_jetx_variables(&run, evaluator);
+                    if trace {
+                        trace_jit(&statements, &statement_lines, Ok(()));
+                    }
+                    // The interpreter would have echoed a trailing expression; keep that output
+                    let value = Value::Number(run.result);
+                    if statements.last().is_some_and(|stmt| should_display(&value, stmt)) {
+                        evaluator.print_value(&value);
+                        println!();
+                    }
+                    if show_stats {
+                        let total_time = total_start.elapsed().as_micros() as u64;
+                        print_jetx_stats(&stats, total_time);
+                    }
+                    return Ok(value);
+                }
+                stats.jetx_compiled = false;
+            }
+        }
+        // JetX could not take over; rerun from the start without the deadline
+        fallback_reason = "JetX could not take over the hot loop".to_string();
+    }
+    
     // Fall back to interpreter
+    if trace {
+        trace_jit(&statements, &statement_lines, Err(&fallback_reason));
+    }
     let exec_start = std::time::Instant::now();
-    let result = execute_interpreter_timed(&statements, evaluator)?;
+    let result = execute_interpreter_timed(&interpreted_statements, evaluator)?;
     stats.execution_time_us = exec_start.elapsed().as_micros() as u64;
     
     if show_stats {
@@ -130,69 +251,21 @@ fn execute_jetx(code: &str, evaluator: &mut Evaluator, show_stats: bool, force_j
     Ok(result)
 }
 
-/// Sync variables from JetX computation back to evaluator
-/// This handles loop variables, assigned variables, etc.
-fn sync_jetx_variables(statements: &[parser::Expr], result: f64, evaluator: &mut Evaluator) {
-    for stmt in statements {
-        match stmt {
-            // For loops: set loop var to end value (Mintas semantics - i stays at final value)
-            parser::Expr::ForLoop { var, end, body, .. } => {
-                // Calculate end value and set loop var to end (not end+1)
-                if let Some(end_val) = eval_const_expr(end) {
-                    evaluator.set_variable(var.clone(), Value::Number(end_val));
-                }
-                // Also sync any variables assigned inside the loop body
-                sync_body_variables(body, result, evaluator);
-            }
-            // While loops: sync body variables
-            parser::Expr::WhileLoop { body, .. } => {
-                sync_body_variables(body, result, evaluator);
-            }
-            // Direct assignments
-            parser::Expr::Assign { name, .. } => {
-                evaluator.set_variable(name.clone(), Value::Number(result));
-            }
-            _ => {}
-        }
-    }
-    
-    // Also set the last assigned variable to the result
-    if let Some(var_name) = find_last_assigned_var(statements) {
-        evaluator.set_variable(var_name, Value::Number(result));
-    }
-}
-
-/// Sync variables from loop body
-fn sync_body_variables(body: &[parser::Expr], result: f64, evaluator: &mut Evaluator) {
-    for stmt in body {
-        if let parser::Expr::Assign { name, .. } = stmt {
-            evaluator.set_variable(name.clone(), Value::Number(result));
-        }
+/// Writes every variable a JetX run assigned back to the evaluator, each with its own value
+fn sync_jetx_variables(run: &JetXRun, evaluator: &mut Evaluator) {
+    for (name, value) in &run.variables {
+        evaluator.set_variable(name.clone(), Value::Number(*value));
     }
 }
 
 /// Try to evaluate a constant expression (for loop bounds)
 fn eval_const_expr(expr: &parser::Expr) -> Option<f64> {
-    match expr {
-        parser::Expr::Number(n) => Some(*n),
-        parser::Expr::BinaryOp { op, left, right } => {
-            let l = eval_const_expr(left)?;
-            let r = eval_const_expr(right)?;
-            match op {
-                parser::BinaryOp::Add => Some(l + r),
-                parser::BinaryOp::Subtract => Some(l - r),
-                parser::BinaryOp::Multiply => Some(l * r),
-                parser::BinaryOp::Divide => Some(l / r),
-                _ => None,
-            }
-        }
-        _ => None,
-    }
+    compiler::const_value(expr)?.as_f64()
 }
 
 /// Execute I/O statement using evaluator (with synced variables)
 fn execute_io_with_evaluator(stmt: &parser::Expr, evaluator: &mut Evaluator) -> Result<(), String> {
-    match evaluator.eval(stmt) {
+    match evaluator.eval_top_level(stmt) {
         Ok(_) => Ok(()),
         Err(e) => Err(e.to_string()),
     }
@@ -212,7 +285,7 @@ fn is_pure_io_statement(expr: &parser::Expr) -> bool {
 fn contains_io_statement(expr: &parser::Expr) -> bool {
     match expr {
         parser::Expr::Call { name, args, .. } => {
-            if matches!(name.as_str(), "say" | "ask" | "read" | "write" | "append" | "print" | "println") {
+            if matches!(name.as_str(), "say" | "ask" | "read" | "write" | "append" | "print" | "println" | "random" | "random_int") {
                 return true;
             }
             // Check arguments for nested I/O
@@ -224,6 +297,15 @@ fn contains_io_statement(expr: &parser::Expr) -> bool {
         parser::Expr::ForInLoop { body, .. } => {
             body.iter().any(|stmt| contains_io_statement(stmt))
         }
+        parser::Expr::SmartLoop { count, body, .. } => {
+            contains_io_statement(count) || body.iter().any(contains_io_statement)
+        }
+        parser::Expr::SmartCondition { condition, then_branch, else_branch } => {
+            contains_io_statement(condition) || contains_io_statement(then_branch) || contains_io_statement(else_branch)
+        }
+        parser::Expr::Follow { condition, .. } | parser::Expr::Cond { condition } => {
+            contains_io_statement(condition)
+        }
         parser::Expr::IfExpr { condition, then_branch, else_branch, .. } => {
             contains_io_statement(condition) ||
             then_branch.iter().any(|stmt| contains_io_statement(stmt)) ||
@@ -251,10 +333,10 @@ fn contains_user_functions(expr: &parser::Expr) -> bool {
         // Function definitions - JetX can't handle these
         parser::Expr::Function { .. } => true,
         // Function calls (except builtins like say, ask, etc.)
-        parser::Expr::Call { name, args } => {
+        parser::Expr::Call { name, args, .. } => {
             // These are I/O builtins handled separately
             let is_io_builtin = matches!(name.as_str(), 
-                "say" | "ask" | "read" | "write" | "append" | "print" | "println"
+                "say" | "ask" | "read" | "write" | "append" | "print" | "println" | "random" | "random_int"
             );
             // If it's not a builtin, it's a user function call
             if !is_io_builtin {
@@ -269,7 +351,7 @@ fn contains_user_functions(expr: &parser::Expr) -> bool {
             contains_user_functions(end) || 
             body.iter().any(|s| contains_user_functions(s))
         }
-        parser::Expr::WhileLoop { condition, body } => {
+        parser::Expr::WhileLoop { condition, body, .. } => {
             contains_user_functions(condition) || 
             body.iter().any(|s| contains_user_functions(s))
         }
@@ -277,6 +359,18 @@ fn contains_user_functions(expr: &parser::Expr) -> bool {
             contains_user_functions(iterable) || 
             body.iter().any(|s| contains_user_functions(s))
         }
+        parser::Expr::SmartLoop { count, body, .. } => {
+            contains_user_functions(count) ||
+            body.iter().any(contains_user_functions)
+        }
+        parser::Expr::SmartCondition { condition, then_branch, else_branch } => {
+            contains_user_functions(condition) ||
+            contains_user_functions(then_branch) ||
+            contains_user_functions(else_branch)
+        }
+        parser::Expr::Follow { condition, .. } | parser::Expr::Cond { condition } => {
+            contains_user_functions(condition)
+        }
         parser::Expr::IfExpr { condition, then_branch, else_branch, .. } => {
             contains_user_functions(condition) ||
             then_branch.iter().any(|s| contains_user_functions(s)) ||
@@ -301,88 +395,287 @@ fn contains_user_functions(expr: &parser::Expr) -> bool {
     }
 }
 
-/// Check if statement can be compiled by JetX (must be pure computation, no I/O inside)
-fn is_jetx_compilable(expr: &parser::Expr) -> bool {
-    // First check if it contains any I/O - if so, not JetX compilable
-    if contains_io_statement(expr) {
-        return false;
+/// Why a statement has to stay in the interpreter, as reported by `--trace-jit`
+enum JitFallback {
+    Io(String),
+    UserFunction(String),
+    FunctionDefinition(String),
+    LoopElse,
+    ElseIf,
+    OuterVariable(String),
+    NonNumeric(String),
+    ConstDeclaration,
+    LoopVariableAssignment(String),
+    Operator(parser::BinaryOp),
+    BuiltinArguments(String),
+    Unsupported(&'static str),
+}
+
+impl std::fmt::Display for JitFallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JitFallback::Io(name) => write!(f, "contains I/O call `{}`", name),
+            JitFallback::UserFunction(name) => write!(f, "user function call `{}`", name),
+            JitFallback::FunctionDefinition(name) => write!(f, "defines function `{}`", name),
+            JitFallback::LoopElse => write!(f, "loop has an else body"),
+            JitFallback::ElseIf => write!(f, "if has else-if branches"),
+            JitFallback::OuterVariable(name) => write!(f, "reads `{}`, which this program never assigns", name),
+            JitFallback::NonNumeric(what) => write!(f, "{} is not a number", what),
+            JitFallback::ConstDeclaration => write!(f, "declares a const"),
+            JitFallback::LoopVariableAssignment(name) => write!(f, "assigns to loop variable `{}`", name),
+            JitFallback::Operator(op) => write!(f, "operator {:?} has no JetX lowering", op),
+            JitFallback::BuiltinArguments(name) => write!(f, "calls `{}()` with arguments JetX can't compile", name),
+            JitFallback::Unsupported(kind) => write!(f, "unsupported expression {}", kind),
+        }
     }
-    
-    match expr {
-        parser::Expr::Number(_) |
-        parser::Expr::Boolean(_) |
-        parser::Expr::Variable(_) |
-        parser::Expr::Assign { .. } |
-        parser::Expr::BinaryOp { .. } |
-        parser::Expr::UnaryOp { .. } |
-        parser::Expr::IfExpr { .. } |
-        parser::Expr::WhileLoop { .. } |
-        parser::Expr::ForLoop { .. } => true,
-        _ => false,
+}
+
+/// What JetX can see while a program is checked: the functions it defines and the variables
+/// it has assigned so far. Compiled code reads any other name as 0.
+#[derive(Clone, Default)]
+struct JitScope {
+    functions: std::collections::HashMap<String, usize>,
+    variables: std::collections::HashSet<String>,
+    nested: bool,
+    in_function: bool,
+    // Statements directly in a loop body, where JetX would not act on a `return`
+    loop_body: bool,
+    // Counters of the enclosing `for` loops; the interpreter ignores assignments to them
+    loop_vars: std::collections::HashSet<String>,
+}
+
+impl JitScope {
+    fn for_program(statements: &[parser::Expr]) -> Self {
+        let functions = statements.iter().filter_map(|stmt| match stmt {
+            parser::Expr::Function { name, params, .. } => Some((name.clone(), params.len())),
+            _ => None,
+        }).collect();
+        JitScope { functions, ..JitScope::default() }
     }
 }
 
-/// Find the last variable that was assigned in the statements (including inside loops)
-fn find_last_assigned_var(statements: &[parser::Expr]) -> Option<String> {
-    fn find_in_expr(expr: &parser::Expr) -> Option<String> {
-        match expr {
-            parser::Expr::Assign { name, .. } => Some(name.clone()),
-            parser::Expr::ForLoop { body, .. } | parser::Expr::WhileLoop { body, .. } => {
-                // Search inside loop body from end to start
-                for stmt in body.iter().rev() {
-                    if let Some(name) = find_in_expr(stmt) {
-                        return Some(name);
-                    }
+/// First reason JetX can't compile a statement, or None for pure numeric computation.
+/// Assignments are recorded in `scope` so later statements may read them.
+fn jit_fallback(expr: &parser::Expr, scope: &mut JitScope) -> Option<JitFallback> {
+    // A branch or loop body may never run, so what it assigns stays local to it
+    let block = |body: &[parser::Expr], scope: &JitScope, loop_body: bool| {
+        let mut inner = JitScope { nested: true, loop_body, ..scope.clone() };
+        body.iter().find_map(|stmt| jit_fallback(stmt, &mut inner))
+    };
+    match expr {
+        parser::Expr::Number(_) | parser::Expr::Int(_) | parser::Expr::Boolean(_) => None,
+        parser::Expr::Variable(name) if scope.variables.contains(name) => None,
+        parser::Expr::Variable(name) => Some(JitFallback::OuterVariable(name.clone())),
+        parser::Expr::Assign { is_const: true, .. } => Some(JitFallback::ConstDeclaration),
+        parser::Expr::Assign { name, .. } if scope.loop_vars.contains(name) => {
+            Some(JitFallback::LoopVariableAssignment(name.clone()))
+        }
+        parser::Expr::Assign { name, value, .. } => {
+            // JetX variables hold numbers only
+            let reason = if jit_is_boolean(value) {
+                Some(JitFallback::NonNumeric(format!("value assigned to `{}`", name)))
+            } else {
+                jit_fallback(value, scope)
+            };
+            scope.variables.insert(name.clone());
+            reason
+        }
+        parser::Expr::BinaryOp { op, left, right } => {
+            use parser::BinaryOp::*;
+            let operands_fit = match op {
+                Add | Subtract | Multiply | Divide | Modulo | Greater | Less | GreaterEqual | LessEqual => {
+                    !jit_is_boolean(left) && !jit_is_boolean(right)
+                }
+                Equal | NotEqual => jit_is_boolean(left) == jit_is_boolean(right),
+                // On numbers `and`/`or` give back an operand, not true or false
+                And | Or => jit_is_boolean(left) && jit_is_boolean(right),
+                Exponent | StrictEqual | StrictNotEqual | NullCoalesce => {
+                    return Some(JitFallback::Operator(*op));
                 }
-                None
+            };
+            if !operands_fit {
+                return Some(JitFallback::NonNumeric(format!("an operand of {:?}", op)));
             }
-            parser::Expr::IfExpr { then_branch, else_branch, .. } => {
-                // Check else branch first (if exists), then then branch
-                if let Some(else_b) = else_branch {
-                    for stmt in else_b.iter().rev() {
-                        if let Some(name) = find_in_expr(stmt) {
-                            return Some(name);
-                        }
-                    }
+            jit_fallback(left, scope).or_else(|| jit_fallback(right, scope))
+        }
+        parser::Expr::UnaryOp { op: parser::UnaryOp::Negate, expr: inner } if jit_is_boolean(inner) => {
+            Some(JitFallback::NonNumeric("an operand of Negate".to_string()))
+        }
+        parser::Expr::UnaryOp { op: parser::UnaryOp::Negate | parser::UnaryOp::Not, expr: inner } => jit_fallback(inner, scope),
+        parser::Expr::Call { name, args, .. } => {
+            let args_fallback = |scope: &mut JitScope| args.iter().find_map(|arg| {
+                if jit_is_boolean(arg) {
+                    Some(JitFallback::NonNumeric(format!("an argument of `{}`", name)))
+                } else {
+                    jit_fallback(arg, scope)
                 }
-                for stmt in then_branch.iter().rev() {
-                    if let Some(name) = find_in_expr(stmt) {
-                        return Some(name);
-                    }
+            });
+            if matches!(name.as_str(), "say" | "ask" | "read" | "write" | "append" | "print" | "println" | "random" | "random_int") {
+                Some(JitFallback::Io(name.clone()))
+            } else if scope.functions.contains_key(name) {
+                // The program's own functions; JetX compiles them alongside the main body
+                if scope.functions[name] == args.len() {
+                    args_fallback(scope)
+                } else {
+                    Some(JitFallback::UserFunction(name.clone()))
                 }
-                None
+            } else if matches!((name.as_str(), args.len()), ("floor" | "ceil" | "abs" | "sqrt" | "round", 1) | ("min" | "max", 2..) | ("idiv" | "mod", 2))
+                || (name == "round" && args.len() == 2 && matches!(args[1], parser::Expr::Number(_) | parser::Expr::Int(_)))
+            {
+                // A zero divisor or an integer past 2^53 makes the run bail out to the interpreter
+                args_fallback(scope)
+            } else if matches!(name.as_str(), "floor" | "ceil" | "abs" | "sqrt" | "round" | "min" | "max" | "idiv" | "mod") {
+                Some(JitFallback::BuiltinArguments(name.clone()))
+            } else {
+                Some(JitFallback::UserFunction(name.clone()))
             }
-            _ => None,
         }
+        parser::Expr::Function { params, body, .. } if !scope.nested => {
+            let mut inner = JitScope {
+                functions: scope.functions.clone(),
+                variables: params.iter().cloned().collect(),
+                nested: true,
+                in_function: true,
+                ..JitScope::default()
+            };
+            body.iter().find_map(|stmt| jit_fallback(stmt, &mut inner))
+        }
+        parser::Expr::Function { name, .. } => Some(JitFallback::FunctionDefinition(name.clone())),
+        parser::Expr::Return { value: Some(value) } if scope.in_function && !scope.loop_body => jit_fallback(value, scope),
+        parser::Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
+            if !else_if_branches.is_empty() {
+                return Some(JitFallback::ElseIf);
+            }
+            jit_fallback(condition, scope)
+                .or_else(|| block(then_branch, scope, false))
+                .or_else(|| else_branch.as_deref().and_then(|body| block(body, scope, false)))
+        }
+        // JetX has no lowering for loop else bodies
+        parser::Expr::WhileLoop { else_branch: Some(_), .. } => {
+            Some(JitFallback::LoopElse)
+        }
+        parser::Expr::WhileLoop { condition, body, .. } => jit_fallback(condition, scope).or_else(|| block(body, scope, true)),
+        parser::Expr::ForLoop { var, start, end, body, .. } => {
+            let reason = jit_fallback(start, scope).or_else(|| jit_fallback(end, scope));
+            // The body always runs at least once, so the loop variable is set afterwards
+            scope.variables.insert(var.clone());
+            let mut counted = scope.clone();
+            counted.loop_vars.insert(var.clone());
+            reason.or_else(|| block(body, &counted, true))
+        }
+        other => Some(JitFallback::Unsupported(Evaluator::expr_kind(other))),
     }
-    
-    for stmt in statements.iter().rev() {
-        if let Some(name) = find_in_expr(stmt) {
-            return Some(name);
+}
+
+/// True for expressions that give true or false, which JetX can only branch on: its
+/// variables and results are plain numbers
+fn jit_is_boolean(expr: &parser::Expr) -> bool {
+    use parser::BinaryOp::*;
+    match expr {
+        parser::Expr::Boolean(_) => true,
+        parser::Expr::BinaryOp { op, .. } => matches!(op, Equal | NotEqual | Greater | Less | GreaterEqual | LessEqual | And | Or),
+        parser::Expr::UnaryOp { op, .. } => *op == parser::UnaryOp::Not,
+        _ => false,
+    }
+}
+
+/// `--trace-jit`: one line per top-level statement saying where it ran. `Err` carries the
+/// program-wide reason, used for statements that would compile on their own.
+fn trace_jit(statements: &[parser::Expr], lines: &[usize], outcome: Result<(), &str>) {
+    let mut scope = JitScope::for_program(statements);
+    for (i, stmt) in statements.iter().enumerate() {
+        let line = lines.get(i).copied().unwrap_or(0);
+        let reason = jit_fallback(stmt, &mut scope);
+        match outcome {
+            Ok(()) => eprintln!("[jit] line {}: compiled by JetX", line),
+            Err(program_reason) => match reason {
+                Some(reason) => eprintln!("[jit] line {}: interpreted ({})", line, reason),
+                None => eprintln!("[jit] line {}: interpreted ({})", line, program_reason),
+            },
+        }
+    }
+}
+
+/// Auto mode may only hand a program to JetX mid-run if JetX computes the same thing from
+/// scratch: plain numeric code, at least one loop, and no reads of variables defined elsewhere.
+fn is_auto_jit_candidate(statements: &[parser::Expr]) -> bool {
+    let mut assigned = std::collections::HashSet::new();
+    statements.iter().all(|stmt| is_numeric_kernel(stmt, &mut assigned))
+        && statements.iter().any(|stmt| matches!(stmt, parser::Expr::WhileLoop { .. } | parser::Expr::ForLoop { .. }))
+}
+
+fn is_numeric_kernel(expr: &parser::Expr, assigned: &mut std::collections::HashSet<String>) -> bool {
+    // Assignments in a branch or loop body may never run, so they don't count as defined afterwards
+    let block = |body: &[parser::Expr], assigned: &std::collections::HashSet<String>| {
+        let mut scoped = assigned.clone();
+        body.iter().all(|stmt| is_numeric_kernel(stmt, &mut scoped))
+    };
+    match expr {
+        parser::Expr::Number(_) | parser::Expr::Boolean(_) => true,
+        parser::Expr::Variable(name) => assigned.contains(name),
+        parser::Expr::Assign { name, value, .. } => {
+            let ok = is_numeric_kernel(value, assigned);
+            assigned.insert(name.clone());
+            ok
+        }
+        parser::Expr::BinaryOp { op, left, right } => {
+            !matches!(op, parser::BinaryOp::NullCoalesce | parser::BinaryOp::StrictEqual | parser::BinaryOp::StrictNotEqual | parser::BinaryOp::Exponent)
+                && is_numeric_kernel(left, assigned)
+                && is_numeric_kernel(right, assigned)
         }
+        parser::Expr::UnaryOp { op, expr } => {
+            matches!(op, parser::UnaryOp::Negate | parser::UnaryOp::Not) && is_numeric_kernel(expr, assigned)
+        }
+        parser::Expr::IfExpr { condition, then_branch, else_if_branches, else_branch } => {
+            else_if_branches.is_empty()
+                && is_numeric_kernel(condition, assigned)
+                && block(then_branch, assigned)
+                && else_branch.as_ref().is_none_or(|b| block(b, assigned))
+        }
+        parser::Expr::WhileLoop { condition, body, else_branch: None } => {
+            is_numeric_kernel(condition, assigned) && block(body, assigned)
+        }
+        parser::Expr::ForLoop { var, start, end, body } => {
+            // JetX only counts upwards, so the range must be known to ascend
+            match (eval_const_expr(start), eval_const_expr(end)) {
+                (Some(from), Some(to)) if from <= to => {}
+                _ => return false,
+            }
+            let mut scoped = assigned.clone();
+            scoped.insert(var.clone());
+            let ok = body.iter().all(|stmt| is_numeric_kernel(stmt, &mut scoped));
+            assigned.insert(var.clone());
+            ok
+        }
+        _ => false,
     }
-    None
 }
 
 /// Execute interpreter and return result (for timing)
 fn execute_interpreter_timed(statements: &[parser::Expr], evaluator: &mut Evaluator) -> Result<Value, String> {
-    let mut last_val = Value::Empty;
+    let mut result = Ok(Value::Empty);
     for stmt in statements {
-        match evaluator.eval(stmt) {
+        match evaluator.eval_top_level(stmt) {
             Ok(val) => {
                 if matches!(val, Value::ExitSignal) {
-                    return Ok(Value::ExitSignal);
+                    result = Ok(Value::ExitSignal);
+                    break;
                 }
-                last_val = val.clone();
                 if should_display(&val, stmt) {
                     evaluator.print_value(&val);
                     println!();
                 }
+                result = Ok(val);
+            }
+            Err(e) => {
+                result = Err(e.to_string());
+                break;
             }
-            Err(e) => return Err(e.to_string()),
         }
     }
-    Ok(last_val)
+    // say() output is buffered; it must land before stats, errors or the next prompt
+    let _ = evaluator.flush_all_buffers();
+    result
 }
 
 
@@ -390,7 +683,7 @@ fn execute_interpreter_timed(statements: &[parser::Expr], evaluator: &mut Evalua
 #[allow(dead_code)]
 fn execute_interpreter(statements: &[parser::Expr], evaluator: &mut Evaluator) -> Result<(), String> {
     for stmt in statements {
-        match evaluator.eval(stmt) {
+        match evaluator.eval_top_level(stmt) {
             Ok(val) => {
                 if should_display(&val, stmt) {
                     evaluator.print_value(&val);
@@ -404,15 +697,20 @@ fn execute_interpreter(statements: &[parser::Expr], evaluator: &mut Evaluator) -
 }
 
 fn parse_code(code: &str) -> Result<Vec<parser::Expr>, String> {
+    parse_code_with_lines(code).map(|(statements, _)| statements)
+}
+
+fn parse_code_with_lines(code: &str) -> Result<(Vec<parser::Expr>, Vec<usize>), String> {
     let mut lexer = Lexer::new(code);
     let tokens = lexer.tokenize().map_err(|e| format!("Lexer error: {}", e))?;
     
     if tokens.is_empty() || matches!(tokens[0].token, lexer::Token::EOF) {
-        return Ok(vec![]);
+        return Ok((vec![], vec![]));
     }
     
     let mut parser = Parser::new(tokens);
-    parser.parse().map_err(|e| format!("Parser error: {}", e))
+    let statements = parser.parse().map_err(|e| format!("Parser error: {}", e))?;
+    Ok((statements, parser.statement_lines().to_vec()))
 }
 
 fn should_display(val: &Value, stmt: &parser::Expr) -> bool {
@@ -453,6 +751,38 @@ fn print_jetx_stats(stats: &JetXStats, total_us: u64) {
 }
 
 fn main() {
+    // Panics on the interpreter thread are reported as errors where they're caught, so the
+    // default "thread panicked at ..." message only shows for other threads, or when
+    // RUST_BACKTRACE asks for the location and backtrace
+    let backtrace = env::var_os("RUST_BACKTRACE").is_some_and(|v| v != "0");
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if backtrace || std::thread::current().name() != Some("mintas") {
+            default_hook(info);
+        }
+    }));
+    // The stack only has to hold as many Mintas calls as --max-depth allows
+    let args: Vec<String> = env::args().collect();
+    let max_depth = args.iter()
+        .position(|arg| arg == "--max-depth")
+        .and_then(|i| args.get(i + 1)?.parse().ok())
+        .unwrap_or(evaluator::MAX_RECURSION_DEPTH);
+    let interpreter = std::thread::Builder::new()
+        .name("mintas".to_string())
+        .stack_size(evaluator::interpreter_stack_size(max_depth))
+        .spawn(run_cli);
+    match interpreter {
+        Ok(handle) => {
+            if let Err(payload) = handle.join() {
+                eprintln!("Error: {}", internal_error_message(&*payload));
+                std::process::exit(101);
+            }
+        }
+        Err(_) => run_cli(),
+    }
+}
+
+fn run_cli() {
     let args: Vec<String> = env::args().collect();
     
     // Default REPL mode if no file is specified
@@ -462,10 +792,22 @@ fn main() {
     let mut check_only = false;
     let mut debug_mode = false;
     let mut force_jetx = false;
+    let mut trace_jit = false;
+    let mut ast_stats = false;
+    let mut dump_tokens = false;
+    let mut max_depth: Option<usize> = None;
+    let mut max_memory: Option<usize> = None;
+    let mut seed: Option<u64> = None;
+    let mut time_limit: Option<std::time::Duration> = None;
+    let mut jit_threshold_us = DEFAULT_JIT_THRESHOLD_US;
+    let mut encoding: Option<&'static encoding_rs::Encoding> = None;
+    let mut read_stdin = false;
     let mut secret: Option<String> = None;
+    let mut load_path: Option<String> = None;
+    let mut exec_snippets: Vec<String> = Vec::new();
     
     if args.len() < 2 {
-        run_repl(default_repl_mode, force_jetx);
+        run_repl(default_repl_mode, force_jetx, None);
         return;
     }
     
@@ -484,10 +826,34 @@ fn main() {
                 println!("Mintas v1.0.3 with JetX JIT Compiler");
                 return;
             }
+            "--list-builtins" => {
+                print!("{}", builtins::render_list());
+                return;
+            }
+            "--explain" => {
+                let code = match args.get(i + 1) {
+                    Some(code) => code,
+                    None => {
+                        eprintln!("Error: --explain requires an error code, e.g. E1014");
+                        std::process::exit(1);
+                    }
+                };
+                match errors::explain(code) {
+                    Some(text) => println!("{}: {}", code.to_uppercase(), text),
+                    None => {
+                        eprintln!("{}: no explanation available", code);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
             "-s" | "--stats" => show_stats = true,
             "-c" | "--check" => check_only = true,
             "-d" | "--debug" => debug_mode = true,
             "-jetx" | "--jetx" => force_jetx = true,
+            "--trace-jit" => trace_jit = true,
+            "--ast-stats" => ast_stats = true,
+            "--dump-tokens" => dump_tokens = true,
             "--default" => {
                 if i + 1 < args.len() {
                     default_repl_mode = Some(args[i + 1].clone());
@@ -497,6 +863,98 @@ fn main() {
                     std::process::exit(1);
                 }
             }
+            "--max-depth" => {
+                match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(depth) if depth > 0 => {
+                        max_depth = Some(depth);
+                        i += 1;
+                    }
+                    _ => {
+                        eprintln!("Error: --max-depth requires a positive number");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--max-memory" => {
+                match args.get(i + 1).and_then(|v| parse_byte_size(v)) {
+                    Some(bytes) if bytes > 0 => {
+                        max_memory = Some(bytes);
+                        i += 1;
+                    }
+                    _ => {
+                        eprintln!("Error: --max-memory requires a byte count, e.g. 50000000 or 64M");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--seed" => {
+                match args.get(i + 1).and_then(|v| v.parse::<u64>().ok()) {
+                    Some(n) => {
+                        seed = Some(n);
+                        i += 1;
+                    }
+                    _ => {
+                        eprintln!("Error: --seed requires a non-negative integer");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--time-limit" => {
+                match args.get(i + 1).and_then(|v| evaluator::parse_duration(v, 1000.0)) {
+                    Some(limit) if !limit.is_zero() => {
+                        time_limit = Some(limit);
+                        i += 1;
+                    }
+                    _ => {
+                        eprintln!("Error: --time-limit requires a duration, e.g. 5s or 500ms");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--jit-threshold" => {
+                match args.get(i + 1).and_then(|v| v.parse::<u64>().ok()) {
+                    Some(us) => {
+                        jit_threshold_us = us;
+                        i += 1;
+                    }
+                    None => {
+                        eprintln!("Error: --jit-threshold requires a number of microseconds");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--encoding" => {
+                match args.get(i + 1).and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes())) {
+                    Some(enc) => {
+                        encoding = Some(enc);
+                        i += 1;
+                    }
+                    None => {
+                        eprintln!("Error: --encoding requires a known encoding name (e.g. utf-8, latin1, utf-16le)");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--load" => {
+                if i + 1 < args.len() {
+                    load_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: --load requires a file");
+                    std::process::exit(1);
+                }
+            }
+            "-e" | "--exec" => {
+                if i + 1 < args.len() {
+                    exec_snippets.push(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: {} requires code to run, e.g. -e \"say(2 + 2)\"", args[i]);
+                    std::process::exit(1);
+                }
+            }
+            // `mintas repl [--load lib.as]` reads better than relying on the missing file argument
+            "repl" => {}
             "--secret" | "--key" => {
                 if i + 1 < args.len() {
                     secret = Some(args[i + 1].clone());
@@ -507,14 +965,35 @@ fn main() {
                 }
             }
             "compile" => {
-                if i + 1 < args.len() {
-                    compile_to_bytecode(&args[i + 1], secret.clone());
+                let optimize = args.get(i + 1).is_some_and(|arg| arg == "-O");
+                let file = if optimize { i + 2 } else { i + 1 };
+                if file < args.len() {
+                    compile_to_bytecode(&args[file], secret.clone(), optimize);
                 } else {
                     eprintln!("Error: compile requires a file argument");
-                    eprintln!("Usage: mintas compile <file.as> [--secret <key>]");
+                    eprintln!("Usage: mintas [--secret <key>] compile [-O] <file.as>");
                 }
                 return;
             }
+            "doc" => {
+                let Some(input) = args.get(i + 1) else {
+                    eprintln!("Error: doc requires a file or directory argument");
+                    eprintln!("Usage: mintas doc <path> [-o <out.md>]");
+                    std::process::exit(1);
+                };
+                let output = match args.get(i + 2).map(String::as_str) {
+                    Some("-o") | Some("--output") => match args.get(i + 3) {
+                        Some(out) => out.as_str(),
+                        None => {
+                            eprintln!("Error: -o requires a file name");
+                            std::process::exit(1);
+                        }
+                    },
+                    _ => "API.md",
+                };
+                docgen::generate_docs(input, output);
+                return;
+            }
             "run" => {
                 if i + 1 < args.len() {
                     run_bytecode(&args[i + 1], secret.clone());
@@ -524,6 +1003,10 @@ fn main() {
                 }
                 return;
             }
+            "--stdin" | "-" => {
+                read_stdin = true;
+                break; // Remaining args are for the script, same as a file path
+            }
             arg if !arg.starts_with('-') => {
                 file_path = Some(arg);
                 break; // Stop parsing - remaining args are for the script
@@ -536,13 +1019,50 @@ fn main() {
         i += 1;
     }
     
-    if let Some(path) = file_path {
-        run_file(path, show_stats, check_only, debug_mode, force_jetx);
+    if load_path.is_some() && (read_stdin || file_path.is_some()) {
+        eprintln!("Error: --load preloads a file into the REPL and can't be combined with a program to run");
+        std::process::exit(1);
+    }
+    if !exec_snippets.is_empty() && (load_path.is_some() || read_stdin || file_path.is_some()) {
+        eprintln!("Error: -e runs code from the command line and can't be combined with a file, stdin or --load");
+        std::process::exit(1);
+    }
+    // The loop stopped at the program (file or `-`); everything after it belongs to the script
+    let script_args = args.get(i + 1..).unwrap_or_default().to_vec();
+    let options = RunOptions { show_stats, check_only, ast_stats, dump_tokens, debug_mode, force_jetx, max_depth, max_memory, seed, time_limit, jit_threshold_us, trace_jit, secret, script_args };
+    if read_stdin {
+        let mut bytes = Vec::new();
+        if let Err(e) = io::Read::read_to_end(&mut io::stdin(), &mut bytes) {
+            eprintln!("Error reading program from stdin: {}", e);
+            std::process::exit(1);
+        }
+        let code = decode_source(&bytes, encoding).unwrap_or_else(|e| {
+            eprintln!("Error reading program from stdin: {}", e);
+            std::process::exit(1);
+        });
+        run_source(&code, "<stdin>", &options);
+    } else if !exec_snippets.is_empty() {
+        run_snippets(&exec_snippets, &options);
+    } else if let Some(path) = file_path {
+        run_file(path, encoding, &options);
     } else {
-        run_repl(default_repl_mode, force_jetx);
+        run_repl(default_repl_mode, force_jetx, load_path.as_deref());
     }
 }
 
+/// `1048576`, `1024K`, `64M` or `2G` (powers of 1024, case-insensitive, optional trailing `B`)
+fn parse_byte_size(text: &str) -> Option<usize> {
+    let upper = text.trim().to_ascii_uppercase();
+    let digits = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, multiplier) = match digits.chars().last()? {
+        'K' => (&digits[..digits.len() - 1], 1usize << 10),
+        'M' => (&digits[..digits.len() - 1], 1 << 20),
+        'G' => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
 fn print_help() {
     println!("Mintas v1.0.3 with JetX JIT Compiler");
     println!();
@@ -553,16 +1073,33 @@ fn print_help() {
     println!("  -h, --help      Show help");
     println!("  -v, --version   Show version");
     println!("  -s, --stats     Show performance stats");
+    println!("  --list-builtins Print every builtin function with its signature and arity");
+    println!("  --explain <CODE>      Explain an error code such as E1014 and how to fix it");
     println!("  -c, --check     Check code only");
+    println!("  --ast-stats     Print node counts by kind, total nodes and max nesting depth");
+    println!("  --dump-tokens   Print each token's position, kind and text, then exit");
     println!("  -d, --debug     Debug mode (verbose logging)");
     println!("  -jetx, --jetx   Force JetX JIT compilation");
+    println!("  --max-depth <N> Maximum function call depth (default 10000)");
+    println!("  --max-memory <BYTES>  Abort once the script holds more than this in strings, arrays and tables, e.g. 64M (default unlimited)");
+    println!("  --seed <N>            Seed random() and random_int() for a reproducible run");
+    println!("  --time-limit <TIME>   Abort after this much wall-clock time, e.g. 5s, 500ms or 2m");
+    println!("  --jit-threshold <US>  Loop time before auto mode switches to JetX (default 5000)");
+    println!("  --trace-jit     Explain per statement why code ran in JetX or the interpreter");
+    println!("  -, --stdin      Read the program from standard input");
+    println!("  --encoding <E>  Source file encoding, e.g. latin1 (default utf-8)");
+    println!("  --load <file>   Run a file, then start the REPL with its definitions");
+    println!("  -e, --exec <code>     Run code from the command line and exit; repeat to run several in turn");
+    println!("  --secret <key>  Key for encrypted bytecode (compile, run, include name.ms)");
     println!();
     println!("BYTECODE COMMANDS:");
     println!("  compile <file.as>          Compile to encrypted .ms bytecode");
+    println!("  compile -O <file.as>       Compile with constant folding and dead-branch removal");
     println!("  run <file.ms>              Run encrypted bytecode file");
+    println!("  doc <path> [-o <out.md>]   Write Markdown docs from ## comments (default API.md)");
     println!();
     println!("XDBX COMMANDS (Build System):");
-    println!("  xdbx run [file]            Run project");
+    println!("  xdbx run [file] [-- args]  Run project, passing args to the script's args()");
     println!("  xdbx test                  Run tests");
     println!("  xdbx targets               List build targets");
     println!("  xdbx help                  Show xdbx help");
@@ -570,9 +1107,49 @@ fn print_help() {
     println!("EXAMPLES:");
     println!("  mintas app.as              Run a Mintas script");
     println!("  mintas app.as arg1 arg2    Run with arguments");
+    println!("  mintas repl --load lib.as  Start the REPL with lib.as already evaluated");
 }
 
-fn run_file(path: &str, show_stats: bool, check_only: bool, debug_mode: bool, force_jetx: bool) {
+/// Settings from the command line that shape a single program run
+struct RunOptions {
+    show_stats: bool,
+    check_only: bool,
+    ast_stats: bool,
+    dump_tokens: bool,
+    debug_mode: bool,
+    force_jetx: bool,
+    max_depth: Option<usize>,
+    max_memory: Option<usize>,
+    seed: Option<u64>,
+    time_limit: Option<std::time::Duration>,
+    jit_threshold_us: u64,
+    trace_jit: bool,
+    secret: Option<String>,
+    script_args: Vec<String>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            show_stats: false,
+            check_only: false,
+            ast_stats: false,
+            dump_tokens: false,
+            debug_mode: false,
+            force_jetx: false,
+            max_depth: None,
+            max_memory: None,
+            seed: None,
+            time_limit: None,
+            jit_threshold_us: DEFAULT_JIT_THRESHOLD_US,
+            trace_jit: false,
+            secret: None,
+            script_args: Vec::new(),
+        }
+    }
+}
+
+fn run_file(path: &str, encoding: Option<&'static encoding_rs::Encoding>, options: &RunOptions) {
     // Only allow .as files
     if !path.ends_with(".as") {
         eprintln!("Error: Mintas only runs .as files");
@@ -580,7 +1157,7 @@ fn run_file(path: &str, show_stats: bool, check_only: bool, debug_mode: bool, fo
         std::process::exit(1);
     }
     
-    let code = match fs::read_to_string(path) {
+    let code = match fs::read(path).map_err(|e| e.to_string()).and_then(|bytes| decode_source(&bytes, encoding)) {
         Ok(content) => content,
         Err(e) => {
             eprintln!("Error reading '{}': {}", path, e);
@@ -588,12 +1165,67 @@ fn run_file(path: &str, show_stats: bool, check_only: bool, debug_mode: bool, fo
         }
     };
     
-    if check_only {
-        check_code(&code, path);
+    run_source(&code, path, options);
+}
+
+/// Decodes program text. A byte order mark always wins and is stripped (Windows editors
+/// like to add one); otherwise the bytes are read as `encoding`, or strict UTF-8 by default.
+fn decode_source(bytes: &[u8], encoding: Option<&'static encoding_rs::Encoding>) -> Result<String, String> {
+    match encoding {
+        Some(enc) => {
+            let (text, used, had_errors) = enc.decode(bytes);
+            if had_errors {
+                return Err(format!("contains bytes that are not valid {}", used.name()));
+            }
+            Ok(text.into_owned())
+        }
+        None => {
+            if let Some((enc, bom_len)) = encoding_rs::Encoding::for_bom(bytes) {
+                if enc != encoding_rs::UTF_8 {
+                    return decode_source(bytes, Some(enc));
+                }
+                return decode_source(&bytes[bom_len..], None);
+            }
+            String::from_utf8(bytes.to_vec()).map_err(|e| {
+                format!(
+                    "not valid UTF-8 (first bad byte at offset {}); if the file is Latin-1, run with --encoding latin1",
+                    e.utf8_error().valid_up_to()
+                )
+            })
+        }
+    }
+}
+
+fn run_source(code: &str, path: &str, options: &RunOptions) {
+    if options.check_only {
+        check_code(code, path);
+        return;
+    }
+    if options.dump_tokens {
+        match Lexer::dump_tokens(code) {
+            Ok(dump) => print!("{}", dump),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.ast_stats {
+        match parse_code(code) {
+            Ok(statements) => {
+                println!("AST stats for {}", path);
+                print!("{}", ast_stats::AstStats::collect(&statements).render());
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
         return;
     }
     
-    if debug_mode {
+    if options.debug_mode {
         println!("🔧 Debug Mode Enabled");
         println!("   File: {}", path);
         println!("   Size: {} bytes", code.len());
@@ -601,14 +1233,54 @@ fn run_file(path: &str, show_stats: bool, check_only: bool, debug_mode: bool, fo
         println!("────────────────────────────────────────");
     }
     
+    let mut evaluator = configured_evaluator(options);
+    execute_source(code, &mut evaluator, options);
+}
+
+/// `-e code [-e code ...]`: each snippet runs in turn on one evaluator, so later ones see
+/// what earlier ones defined; the first error stops the run with a failing exit status
+fn run_snippets(snippets: &[String], options: &RunOptions) {
+    if options.check_only || options.ast_stats || options.dump_tokens {
+        for code in snippets {
+            run_source(code, "<exec>", options);
+        }
+        return;
+    }
+    let mut evaluator = configured_evaluator(options);
+    for code in snippets {
+        execute_source(code, &mut evaluator, options);
+    }
+}
+
+fn configured_evaluator(options: &RunOptions) -> Evaluator {
     let mut evaluator = Evaluator::new();
-    if debug_mode {
+    evaluator.set_module_secret(options.secret.clone());
+    evaluator.set_script_args(options.script_args.clone());
+    if let Some(depth) = options.max_depth {
+        evaluator.set_max_recursion_depth(depth);
+    }
+    evaluator.set_allocation_budget(options.max_memory);
+    if options.seed.is_some() {
+        evaluator.set_seed(options.seed);
+    }
+    evaluator.set_time_limit(options.time_limit);
+    evaluator
+}
+
+fn execute_source(code: &str, evaluator: &mut Evaluator, options: &RunOptions) {
+    if options.debug_mode {
         evaluator.set_debug_mode(true);
+        if let Ok(tokens) = Lexer::new(code).tokenize() {
+            let mut parser = Parser::new(tokens);
+            if parser.parse().is_ok() {
+                evaluator.set_statement_lines(parser.statement_lines().to_vec());
+            }
+        }
     }
     
-    if let Err(e) = execute_jetx(&code, &mut evaluator, show_stats, force_jetx) {
+    let jit_threshold = std::time::Duration::from_micros(options.jit_threshold_us);
+    if let Err(e) = execute_jetx(code, evaluator, options.show_stats, options.force_jetx, options.trace_jit, jit_threshold) {
         eprintln!("Error: {}", e);
-        eprintln!("For more help, type 'help' in the REPL or check the documentation.");
         std::process::exit(1);
     }
 }
@@ -659,7 +1331,7 @@ fn check_code(code: &str, file_path: &str) {
     println!("Ready. {} statements.", statements.len());
 }
 
-fn run_repl(default_mode: Option<String>, force_jetx_cli: bool) {
+fn run_repl(default_mode: Option<String>, force_jetx_cli: bool, load: Option<&str>) {
     let jetx_available = JetXCompiler::new().is_ok();
     let force_interpreter = default_mode.as_deref() == Some("interpreter") || default_mode.as_deref() == Some("int");
     let force_jetx = force_jetx_cli || (default_mode.as_deref() == Some("jetx") && jetx_available);
@@ -681,19 +1353,49 @@ fn run_repl(default_mode: Option<String>, force_jetx_cli: bool) {
     println!("  \x1b[1;32m●\x1b[0m Press \x1b[1;33mCtrl+C\x1b[0m to interrupt");
     println!();
     
+    let mut editor = match Editor::<ReplHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("\x1b[31m✗ Error:\x1b[0m cannot start the line editor: {}", e);
+            return;
+        }
+    };
+    editor.set_helper(Some(ReplHelper::default()));
     let mut evaluator = Evaluator::new();
     let mut history: VecDeque<String> = VecDeque::with_capacity(100);
+    if let Some(path) = load {
+        // Whatever ran before an error stays defined, so the session is still useful
+        let loaded = fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| decode_source(&bytes, None))
+            .and_then(|code| evaluator.eval_line(&code).map_err(|e| e.to_string()));
+        let _ = evaluator.flush_all_buffers();
+        match loaded {
+            Ok(_) => println!("  \x1b[1;32m✓\x1b[0m Loaded \x1b[1;33m{}\x1b[0m", path),
+            Err(e) => eprintln!("\x1b[31m✗ Error loading {}:\x1b[0m {}", path, e),
+        }
+        println!();
+    }
     
     loop {
+        if let Some(helper) = editor.helper_mut() {
+            helper.names = evaluator.defined_names();
+        }
         let prompt_mode = if force_interpreter { "INT" } else if jetx_available { "JIT" } else { "INT" };
-        print!("\x1b[1;36m[{}]\x1b[0m >> ", prompt_mode);
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() { break; }
+        let line = match editor.readline(&format!("\x1b[1;36m[{}]\x1b[0m >> ", prompt_mode)) {
+            Ok(line) => line,
+            // Ctrl+C abandons the line being typed; Ctrl+D or end of input leaves
+            Err(ReadlineError::Interrupted) => continue,
+            Err(_) => {
+                let _ = evaluator.flush_all_buffers();
+                println!("\n\x1b[1;32m✓\x1b[0m Goodbye! Thanks for using Mintas.\n");
+                break;
+            }
+        };
         
-        let input = input.trim();
+        let input = line.trim();
         if input.is_empty() { continue; }
+        let _ = editor.add_history_entry(input);
         
         match input {
             "exit" | "quit" => {
@@ -751,6 +1453,7 @@ fn run_repl(default_mode: Option<String>, force_jetx_cli: bool) {
         history.push_back(input.to_string());
         if history.len() > 100 { history.pop_front(); }
         
+        let redefinitions = ReplRedefinitions::before(input, &evaluator);
         let result = if force_interpreter {
             // Force interpreter mode
             match parse_code(input) {
@@ -759,7 +1462,7 @@ fn run_repl(default_mode: Option<String>, force_jetx_cli: bool) {
             }
         } else {
             // Use JetX if available, otherwise fallback to interpreter
-            execute_jetx(input, &mut evaluator, false, force_jetx)
+            execute_jetx(input, &mut evaluator, false, force_jetx, false, std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US))
         };
         
         match result {
@@ -768,7 +1471,11 @@ fn run_repl(default_mode: Option<String>, force_jetx_cli: bool) {
                 println!("\n\x1b[1;32m✓\x1b[0m Goodbye! Thanks for using Mintas.\n");
                 break;
             }
-            Ok(_) => {}
+            Ok(_) => {
+                for note in redefinitions.notes(&evaluator) {
+                    println!("\x1b[2m({})\x1b[0m", note);
+                }
+            }
             Err(e) => {
                 eprintln!("\x1b[31m✗ Error:\x1b[0m {}", e);
             }
@@ -776,6 +1483,104 @@ fn run_repl(default_mode: Option<String>, force_jetx_cli: bool) {
     }
 }
 
+const REPL_COMMANDS: [&str; 6] = ["help", "clear", "history", "vars", "exit", "quit"];
+
+/// Tab completion for the REPL: builtins, the session's own names, and REPL commands at the
+/// start of a line. `names` is refreshed from the evaluator before each prompt.
+#[derive(Default)]
+struct ReplHelper {
+    names: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = completion_start(line, pos);
+        let word = &line[start..pos];
+        let mut candidates = builtins::completions(word);
+        if !word.contains('.') {
+            candidates.extend(self.names.iter().filter(|name| name.starts_with(word)).cloned());
+            if line[..start].trim().is_empty() {
+                candidates.extend(REPL_COMMANDS.iter().filter(|c| c.starts_with(word)).map(|c| c.to_string()));
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+/// Byte offset where the identifier (or `module.name`) ending at `pos` starts. The separator
+/// before it may be any character, including multi-byte ones like `€`.
+fn completion_start(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Bindings a REPL line is about to replace, so the REPL can point out accidental redefinitions.
+struct ReplRedefinitions {
+    functions: Vec<String>,
+    classes: Vec<String>,
+    variables: Vec<(String, &'static str)>,
+}
+
+impl ReplRedefinitions {
+    fn before(input: &str, evaluator: &Evaluator) -> Self {
+        let mut found = ReplRedefinitions { functions: Vec::new(), classes: Vec::new(), variables: Vec::new() };
+        let statements = parse_code(input).unwrap_or_default();
+        for stmt in &statements {
+            match stmt {
+                parser::Expr::Function { name, .. } if evaluator.has_function(name) => found.functions.push(name.clone()),
+                parser::Expr::Class { name, .. } if evaluator.has_class(name) => found.classes.push(name.clone()),
+                parser::Expr::Assign { name, .. } => found.note_variable(name, evaluator),
+                parser::Expr::MultiAssign { names, .. } => {
+                    for name in names {
+                        found.note_variable(name, evaluator);
+                    }
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+
+    fn note_variable(&mut self, name: &str, evaluator: &Evaluator) {
+        if let Some(old) = evaluator.get_variables().get(name) {
+            self.variables.push((name.to_string(), old.type_name()));
+        }
+    }
+
+    /// A reassigned variable's note names both types when the type changed
+    fn notes(&self, evaluator: &Evaluator) -> Vec<String> {
+        let mut notes: Vec<String> = self.functions.iter().map(|name| format!("redefined function '{}'", name)).collect();
+        notes.extend(self.classes.iter().map(|name| format!("redefined class '{}'", name)));
+        for (name, old_type) in &self.variables {
+            match evaluator.get_variables().get(name) {
+                Some(new) if new.type_name() != *old_type => {
+                    notes.push(format!("reassigned '{}' from {} to {}", name, old_type, new.type_name()));
+                }
+                Some(_) => notes.push(format!("reassigned '{}'", name)),
+                None => {}
+            }
+        }
+        notes
+    }
+}
+
 /// Handle XDBX CLI commands - Package Manager & Build System
 /// Platform: Windows, Linux, macOS only (not WSL)
 #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
@@ -792,27 +1597,53 @@ fn handle_xdbx_command(args: &[String]) {
         }
         "build" => {
             let mut release = false;
-            let mut target = "native".to_string();
+            let mut target = "ms".to_string();
+            let mut out: Option<String> = None;
+            let mut entry: Option<String> = None;
             
-            for arg in args.iter().skip(1) {
+            let mut build_args = args.iter().skip(1);
+            while let Some(arg) = build_args.next() {
                 match arg.as_str() {
                     "--release" | "-r" => release = true,
+                    "--out" | "-o" => {
+                        out = build_args.next().cloned();
+                        if out.is_none() {
+                            eprintln!("Error: --out requires a path");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--entry" | "-e" => {
+                        entry = build_args.next().cloned();
+                        if entry.is_none() {
+                            eprintln!("Error: --entry requires a file path");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--ms" => target = "ms".to_string(),
                     "--exe" => target = "exe".to_string(),
-                    "--wasm" => target = "wasm".to_string(),
-                    "--deb" => target = "deb".to_string(),
+                    "--web" | "--wasm" => target = "web".to_string(),
                     "--pkg" => target = "pkg".to_string(),
                     "--target" => {}
                     t if !t.starts_with('-') => target = t.to_string(),
                     _ => {}
                 }
             }
-            xdbx_build(release, &target);
+            xdbx_build(release, &target, out.as_deref(), entry.as_deref());
         }
         "run" => {
-            let file = args.get(1).map(|s| s.as_str()).unwrap_or("src/main.as");
-            xdbx_run(file);
+            // `run [file] [-- args...]`: arguments after `--` (or after the file) go to the script
+            let (own, script_args) = match args.iter().position(|a| a == "--") {
+                Some(split) => (&args[..split], &args[split + 1..]),
+                None => (args.get(..2).unwrap_or(args), args.get(2..).unwrap_or_default()),
+            };
+            let file = own.get(1).map(|s| s.as_str()).unwrap_or("src/main.as");
+            xdbx_run(file, script_args.to_vec());
         }
         "test" => xdbx_test(),
+        "clean" => {
+            let dry_run = args.iter().skip(1).any(|a| a == "--dry-run" || a == "-n");
+            xdbx_clean(dry_run);
+        }
         "targets" => xdbx_targets(),
         "version" | "-v" | "--version" => {
             println!("xdbx v1.0.3 - Mintas Build System");
@@ -841,10 +1672,15 @@ fn print_xdbx_help() {
     println!("  build                  Build project (debug mode)");
     println!("  build --release        Build optimized release");
     println!("  build --ms             Build .MS (Mintas Serialized)");
+    println!("  build --web            Build a web page that runs the script");
+    println!("  build --out <path>     Write the build artifact to <path>");
+    println!("  build --entry <file>   Build <file> instead of src/main.as");
     println!("  targets                List all build targets");
+    println!("  clean                  Remove target/ (build output and scratch dirs)");
+    println!("  clean --dry-run        List what clean would delete");
     println!();
     println!("RUN & TEST:");
-    println!("  run [file]             Run project or file");
+    println!("  run [file] [-- args]   Run project or file, passing args to args()");
     println!("  test                   Run all tests");
     println!();
     println!("OTHER:");
@@ -852,8 +1688,8 @@ fn print_xdbx_help() {
     println!("  help                   Show this help");
     println!();
     println!("EXAMPLES:");
-    println!("  mintas xdbx build --exe");
-    println!("  mintas xdbx build --wasm");
+    println!("  mintas xdbx build --release");
+    println!("  mintas xdbx build --out dist/app.ms");
     println!("  mintas xdbx run");
 }
 
@@ -973,7 +1809,7 @@ mintas xdbx run
     println!("  mintas xdbx run");
 }
 
-fn xdbx_build(release: bool, target: &str) {
+fn xdbx_build(release: bool, target: &str, out: Option<&str>, entry: Option<&str>) {
     let mode = if release { "release" } else { "debug" };
     
     println!("╔═══════════════════════════════════════════════════════════╗");
@@ -1010,8 +1846,14 @@ fn xdbx_build(release: bool, target: &str) {
     }
     println!();
     
-    // Find entry file
-    let entry_file = if std::path::Path::new("src/main.as").exists() {
+    // Find entry file; an explicit --entry never falls back to the defaults
+    let entry_file = if let Some(entry) = entry {
+        if !std::path::Path::new(entry).is_file() {
+            eprintln!("\x1b[31m❌ Entry file not found: {}\x1b[0m", entry);
+            std::process::exit(1);
+        }
+        entry
+    } else if std::path::Path::new("src/main.as").exists() {
         "src/main.as"
     } else if std::path::Path::new("main.as").exists() {
         "main.as"
@@ -1020,6 +1862,12 @@ fn xdbx_build(release: bool, target: &str) {
         std::process::exit(1);
     };
     
+    // Extra entries build next to the main artifact under their own file name
+    let artifact_name = entry
+        .and_then(|e| std::path::Path::new(e).file_stem())
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(project_name);
+    
     // Create target directory
     let target_dir = format!("target/{}", mode);
     fs::create_dir_all(&target_dir).ok();
@@ -1068,10 +1916,12 @@ fn xdbx_build(release: bool, target: &str) {
     
     println!("   [3/4] Compiling to {}...", target);
     
-    // Build based on target - only .MS (Mintas Serialized) format supported
+    // Build based on target: .MS (Mintas Serialized) bytecode or the standalone web page
     let output_file = match target {
         "ms" | "mintas-serialized" | "binary" => {
-            let out = format!("{}/{}.ms", target_dir, project_name);
+            let out = out.map(|p| p.to_string())
+                .unwrap_or_else(|| format!("{}/{}.ms", target_dir, artifact_name));
+            ensure_parent_dir(&out);
             // Compile to .MS bytecode format
             match compile_to_ms_format(&out, project_name, &source, release) {
                 Ok(_) => {
@@ -1084,15 +1934,30 @@ fn xdbx_build(release: bool, target: &str) {
                 }
             }
         }
-        "native" | "exe" | "windows" | "wasm" | "web" | "deb" | "debian" | "pkg" | "macos" => {
-            eprintln!("\x1b[31m❌ Target '{}' is no longer supported\x1b[0m", target);
-            eprintln!("\x1b[33m   Only .MS (Mintas Serialized) format is supported\x1b[0m");
+        "web" | "wasm" => {
+            let out = out.map(|p| p.to_string())
+                .unwrap_or_else(|| format!("{}/{}.html", target_dir, artifact_name));
+            ensure_parent_dir(&out);
+            match create_wasm_html_runtime(&out, project_name, &source, uses_canvas) {
+                Ok(_) => {
+                    println!("      \x1b[32m✓ Created HTML runtime\x1b[0m");
+                    out
+                }
+                Err(e) => {
+                    eprintln!("\x1b[31m❌ Compilation failed: {}\x1b[0m", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        "native" | "exe" | "windows" | "pkg" | "macos" => {
+            eprintln!("\x1b[31m❌ Target '{}' is not supported\x1b[0m", target);
+            eprintln!("\x1b[33m   xdbx does not compile native executables or OS packages; it builds .MS (Mintas Serialized) files\x1b[0m");
             eprintln!("\x1b[33m   Use: mintas xdbx build --ms\x1b[0m");
             std::process::exit(1);
         }
         _ => {
             eprintln!("\x1b[31m❌ Unknown target: {}\x1b[0m", target);
-            eprintln!("\x1b[33m   Supported target: ms\x1b[0m");
+            eprintln!("\x1b[33m   Supported targets: ms, web\x1b[0m");
             std::process::exit(1);
         }
     };
@@ -1126,28 +1991,11 @@ fn xdbx_build(release: bool, target: &str) {
     println!();
     
     match target {
-        "exe" | "windows" | "windows-x64" => {
-            let dist_dir = output_file.replace(".exe", "_dist");
-            println!("   \x1b[36mDistribution:\x1b[0m {}", dist_dir.replace("/", "\\"));
-            println!();
-            println!("   \x1b[33mTo run your app:\x1b[0m");
-            println!("   1. Copy mintas.exe to the _dist folder, then:");
-            println!("      cd {}\\", dist_dir.replace("/", "\\"));
-            println!("      mintas.exe main.as");
-            println!();
-            println!("   OR add mintas to PATH and run:");
-            println!("      {}\\{}.bat", dist_dir.replace("/", "\\"), 
-                output_file.split('/').last().unwrap_or("app").replace(".exe", ""));
-        }
-        "wasm" | "web" => {
-            println!("   \x1b[36mServe:\x1b[0m python -m http.server -d {}", target_dir);
-            println!("   \x1b[36mOpen:\x1b[0m http://localhost:8000/{}.html", project_name);
-        }
-        "deb" | "debian" | "linux-deb" => {
-            println!("   \x1b[36mInstall:\x1b[0m sudo dpkg -i {}", output_file);
-        }
-        "pkg" | "macos" | "macos-pkg" => {
-            println!("   \x1b[36mInstall:\x1b[0m sudo installer -pkg {} -target /", output_file);
+        "web" | "wasm" => {
+            let page = std::path::Path::new(&output_file);
+            let dir = page.parent().map(|d| d.to_string_lossy()).filter(|d| !d.is_empty()).unwrap_or(".".into());
+            println!("   \x1b[36mServe:\x1b[0m python -m http.server -d {}", dir);
+            println!("   \x1b[36mOpen:\x1b[0m http://localhost:8000/{}", page.file_name().unwrap_or_default().to_string_lossy());
         }
         _ => {
             println!("   \x1b[36mRun:\x1b[0m ./{}", output_file);
@@ -1181,6 +2029,15 @@ fn collect_includes(source: &str, sources: &mut Vec<(String, String)>) {
     }
 }
 
+/// Create the parent directory of an output path if it doesn't exist yet
+fn ensure_parent_dir(path: &str) {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).ok();
+        }
+    }
+}
+
 fn copy_dir_recursive(src: &str, dst: &str) {
     fs::create_dir_all(dst).ok();
     if let Ok(entries) = fs::read_dir(src) {
@@ -1216,178 +2073,8 @@ fn compile_to_ms_format(output: &str, _project_name: &str, source: &str, _releas
 }
 
 
-/// Try to compile C code to executable using available compiler
-fn compile_c_to_exe(c_file: &str, output: &str, release: bool) -> bool {
-    use std::process::Command;
-    
-    let opt_flags = if release { vec!["-O2"] } else { vec!["-g"] };
-    
-    // Try gcc first (available on most systems including WSL)
-    let gcc_result = Command::new("gcc")
-        .args(&opt_flags)
-        .arg("-o")
-        .arg(output)
-        .arg(c_file)
-        .output();
-    
-    if let Ok(result) = gcc_result {
-        if result.status.success() {
-            return true;
-        }
-    }
-    
-    // Try clang
-    let clang_result = Command::new("clang")
-        .args(&opt_flags)
-        .arg("-o")
-        .arg(output)
-        .arg(c_file)
-        .output();
-    
-    if let Ok(result) = clang_result {
-        if result.status.success() {
-            return true;
-        }
-    }
-    
-    // Try cl.exe (MSVC on Windows)
-    #[cfg(target_os = "windows")]
-    {
-        let cl_result = Command::new("cl.exe")
-            .arg("/Fe:")
-            .arg(output)
-            .arg(c_file)
-            .output();
-        
-        if let Ok(result) = cl_result {
-            if result.status.success() {
-                return true;
-            }
-        }
-    }
-    
-    // Try x86_64-w64-mingw32-gcc for cross-compiling to Windows
-    if output.ends_with(".exe") {
-        let mingw_result = Command::new("x86_64-w64-mingw32-gcc")
-            .args(&opt_flags)
-            .arg("-o")
-            .arg(output)
-            .arg(c_file)
-            .output();
-        
-        if let Ok(result) = mingw_result {
-            if result.status.success() {
-                return true;
-            }
-        }
-    }
-    
-    false
-}
-
-/// Create a distribution package as fallback
-fn create_distribution_package(output: &str, project_name: &str, source: &str, uses_canvas: bool) {
-    let dist_dir = output.replace(".exe", "_dist");
-    fs::create_dir_all(&dist_dir).ok();
-    
-    // Save the source file
-    fs::write(format!("{}/main.as", dist_dir), source).ok();
-    
-    // Create batch launcher
-    let batch = format!(r#"@echo off
-setlocal
-cd /d "%~dp0"
-if exist mintas.exe (
-    mintas.exe main.as %*
-) else (
-    where mintas >nul 2>nul
-    if %errorlevel% equ 0 (
-        mintas main.as %*
-    ) else (
-        echo Error: mintas runtime not found
-        echo Copy mintas.exe to this folder or add it to PATH
-        pause
-    )
-)
-"#);
-    fs::write(format!("{}/{}.bat", dist_dir, project_name), batch).ok();
-    
-    // Create info file
-    let info = format!(r#"{{"name":"{}","canvas":{},"entry":"main.as"}}"#, project_name, uses_canvas);
-    fs::write(format!("{}/package.json", dist_dir), info).ok();
-    
-    println!("      \x1b[33m📁 Distribution: {}\x1b[0m", dist_dir);
-}
-
-/// Create a real WebAssembly module
-fn create_real_wasm(output: &str, project_name: &str, source: &str, uses_canvas: bool) {
-    // Create a minimal WASM module with embedded Mintas bytecode
-    // WASM magic number: 0x00 0x61 0x73 0x6D (\\0asm)
-    // Version: 0x01 0x00 0x00 0x00
-    
-    let mut wasm = Vec::new();
-    
-    // WASM header
-    wasm.extend_from_slice(&[0x00, 0x61, 0x73, 0x6D]); // magic
-    wasm.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]); // version 1
-    
-    // Custom section (section id 0) for Mintas source
-    let source_bytes = source.as_bytes();
-    let section_name = b"mintas_source";
-    let section_content_len = section_name.len() + 1 + source_bytes.len();
-    
-    wasm.push(0x00); // custom section
-    encode_leb128(&mut wasm, section_content_len as u32 + 10);
-    encode_leb128(&mut wasm, section_name.len() as u32);
-    wasm.extend_from_slice(section_name);
-    encode_leb128(&mut wasm, source_bytes.len() as u32);
-    wasm.extend_from_slice(source_bytes);
-    
-    // Add metadata section
-    let metadata = format!("{{\"name\":\"{}\",\"canvas\":{}}}", project_name, uses_canvas);
-    let meta_name = b"mintas_meta";
-    wasm.push(0x00);
-    encode_leb128(&mut wasm, meta_name.len() as u32 + metadata.len() as u32 + 2);
-    encode_leb128(&mut wasm, meta_name.len() as u32);
-    wasm.extend_from_slice(meta_name);
-    encode_leb128(&mut wasm, metadata.len() as u32);
-    wasm.extend_from_slice(metadata.as_bytes());
-    
-    // Type section (empty for now)
-    wasm.push(0x01); // type section
-    wasm.push(0x01); // size
-    wasm.push(0x00); // 0 types
-    
-    // Function section (empty)
-    wasm.push(0x03);
-    wasm.push(0x01);
-    wasm.push(0x00);
-    
-    // Export section (empty)
-    wasm.push(0x07);
-    wasm.push(0x01);
-    wasm.push(0x00);
-    
-    fs::write(output, &wasm).ok();
-    println!("      \x1b[32m✓ Created WebAssembly module ({} bytes)\x1b[0m", wasm.len());
-}
-
-fn encode_leb128(buf: &mut Vec<u8>, mut value: u32) {
-    loop {
-        let mut byte = (value & 0x7F) as u8;
-        value >>= 7;
-        if value != 0 {
-            byte |= 0x80;
-        }
-        buf.push(byte);
-        if value == 0 {
-            break;
-        }
-    }
-}
-
-/// Create HTML runtime for WASM
-fn create_wasm_html_runtime(output: &str, project_name: &str, source: &str, uses_canvas: bool) {
+/// Create the standalone web page for the `web` target
+fn create_wasm_html_runtime(output: &str, project_name: &str, source: &str, uses_canvas: bool) -> Result<(), String> {
     let canvas_html = if uses_canvas {
         r#"<canvas id="game-canvas" width="800" height="600" style="border: 1px solid #333;"></canvas>"#
     } else {
@@ -1481,285 +2168,175 @@ fn create_wasm_html_runtime(output: &str, project_name: &str, source: &str, uses
         const output = document.getElementById('output');
         let outputText = '';
         
+        // Numbers print like the native runtime (Rust f64 Display):
+        // integers without ".0" and never in exponent notation
+        function formatNumber(n) {{
+            if (Number.isNaN(n)) return 'NaN';
+            if (!Number.isFinite(n)) return n > 0 ? 'inf' : '-inf';
+            if (Object.is(n, -0)) return '-0';
+            const text = String(n);
+            const e = text.indexOf('e');
+            if (e === -1) return text;
+            const sign = n < 0 ? '-' : '';
+            const mantissa = text.slice(sign.length, e);
+            const dot = mantissa.indexOf('.');
+            const digits = mantissa.replace('.', '');
+            const point = (dot === -1 ? mantissa.length : dot) + parseInt(text.slice(e + 1), 10);
+            if (point <= 0) return sign + '0.' + '0'.repeat(-point) + digits;
+            if (point >= digits.length) return sign + digits + '0'.repeat(point - digits.length);
+            return sign + digits.slice(0, point) + '.' + digits.slice(point);
+        }}
+        
+        function formatValue(v) {{
+            if (typeof v === 'number') return formatNumber(v);
+            if (typeof v === 'boolean') return v ? 'true' : 'false';
+            if (v === undefined || v === null) return 'empty';
+            return String(v);
+        }}
+        
         window.say = (msg) => {{
-            outputText += msg + '\\n';
+            outputText += formatValue(msg) + '\n';
             output.textContent = outputText;
         }};
         
-        // Parse and execute basic Mintas
-        function runMintas(code) {{
-            const lines = code.split('\\n');
-            for (let line of lines) {{
-                line = line.trim();
-                if (line.startsWith('#') || line === '') continue;
-                if (line.startsWith('say(')) {{
-                    const match = line.match(/say\\(["'](.*)["']\\)/);
-                    if (match) say(match[1]);
+        // Variables shared by every statement of the program
+        const vars = {{}};
+        
+        function tokenize(src) {{
+            const tokens = [];
+            let i = 0;
+            while (i < src.length) {{
+                const c = src[i];
+                if (/\s/.test(c)) {{ i++; continue; }}
+                const rest = src.slice(i);
+                let m;
+                if ((m = rest.match(/^(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?/))) {{
+                    tokens.push({{ type: 'num', value: parseFloat(m[0]) }});
+                    i += m[0].length;
+                }} else if (c === '"' || c === "'") {{
+                    let j = i + 1, str = '';
+                    while (j < src.length && src[j] !== c) {{
+                        if (src[j] === '\\' && j + 1 < src.length) {{
+                            j++;
+                            str += src[j] === 'n' ? '\n' : src[j] === 't' ? '\t' : src[j];
+                        }} else {{
+                            str += src[j];
+                        }}
+                        j++;
+                    }}
+                    if (j >= src.length) throw new Error('Unterminated string');
+                    tokens.push({{ type: 'str', value: str }});
+                    i = j + 1;
+                }} else if ((m = rest.match(/^[A-Za-z_]\w*/))) {{
+                    tokens.push({{ type: 'id', value: m[0] }});
+                    i += m[0].length;
+                }} else if ((m = rest.match(/^(==|!=|<=|>=|[-+*\/%()<>])/))) {{
+                    tokens.push({{ type: 'op', value: m[0] }});
+                    i += m[0].length;
+                }} else {{
+                    throw new Error("Unexpected character '" + c + "'");
                 }}
             }}
+            return tokens;
         }}
         
-        output.textContent = 'Running {}...\\n\\n';
-        outputText = 'Running {}...\\n\\n';
+        // Recursive descent: comparison < additive < multiplicative < unary < primary
+        function evalExpr(src) {{
+            const tokens = tokenize(src);
+            let pos = 0;
+            const peek = () => tokens[pos] && tokens[pos].type === 'op' ? tokens[pos].value : null;
+            function primary() {{
+                const t = tokens[pos++];
+                if (!t) throw new Error('Unexpected end of expression');
+                if (t.type === 'num' || t.type === 'str') return t.value;
+                if (t.type === 'id') {{
+                    if (t.value === 'true') return true;
+                    if (t.value === 'false') return false;
+                    if (!(t.value in vars)) throw new Error("Undefined variable '" + t.value + "'");
+                    return vars[t.value];
+                }}
+                if (t.value === '(') {{
+                    const v = comparison();
+                    if (peek() !== ')') throw new Error("Expected ')'");
+                    pos++;
+                    return v;
+                }}
+                throw new Error("Unexpected '" + t.value + "'");
+            }}
+            function unary() {{
+                if (peek() === '-') {{ pos++; return -unary(); }}
+                return primary();
+            }}
+            function multiplicative() {{
+                let v = unary();
+                while (['*', '/', '%'].includes(peek())) {{
+                    const op = tokens[pos++].value, r = unary();
+                    v = op === '*' ? v * r : op === '/' ? v / r : v % r;
+                }}
+                return v;
+            }}
+            function additive() {{
+                let v = multiplicative();
+                while (['+', '-'].includes(peek())) {{
+                    const op = tokens[pos++].value, r = multiplicative();
+                    if (op === '+' && (typeof v === 'string' || typeof r === 'string')) v = formatValue(v) + formatValue(r);
+                    else v = op === '+' ? v + r : v - r;
+                }}
+                return v;
+            }}
+            function comparison() {{
+                let v = additive();
+                while (['==', '!=', '<', '>', '<=', '>='].includes(peek())) {{
+                    const op = tokens[pos++].value, r = additive();
+                    v = op === '==' ? v === r : op === '!=' ? v !== r : op === '<' ? v < r
+                        : op === '>' ? v > r : op === '<=' ? v <= r : v >= r;
+                }}
+                return v;
+            }}
+            const result = comparison();
+            if (pos < tokens.length) throw new Error("Unexpected '" + tokens[pos].value + "'");
+            return result;
+        }}
+        
+        // Runs say(...) and assignments; other statements are skipped
+        function runMintas(code) {{
+            const lines = code.split('\n');
+            lines.forEach((raw, index) => {{
+                const line = raw.trim();
+                if (line.startsWith('#') || line === '') return;
+                let m;
+                try {{
+                    if ((m = line.match(/^say\s*\((.*)\)$/))) {{
+                        say(evalExpr(m[1]));
+                    }} else if ((m = line.match(/^([A-Za-z_]\w*)\s*([-+*\/])=\s*(.+)$/))) {{
+                        if (!(m[1] in vars)) throw new Error("Undefined variable '" + m[1] + "'");
+                        vars[m[1]] = evalExpr(m[1] + ' ' + m[2] + ' (' + m[3] + ')');
+                    }} else if ((m = line.match(/^([A-Za-z_]\w*)\s*=(?!=)\s*(.+)$/))) {{
+                        vars[m[1]] = evalExpr(m[2]);
+                    }}
+                }} catch (e) {{
+                    throw new Error('line ' + (index + 1) + ': ' + e.message);
+                }}
+            }});
+        }}
+        
+        output.textContent = 'Running {}...\n\n';
+        outputText = 'Running {}...\n\n';
         
         try {{
             runMintas(mintasSource);
         }} catch(e) {{
-            output.textContent += '\\nError: ' + e.message;
+            output.textContent += '\nError: ' + e.message;
         }}
     </script>
 </body>
 </html>
 "#, project_name, project_name, canvas_html, canvas_js, escaped_source, project_name, project_name);
     
-    fs::write(output, html).ok();
-    println!("      \x1b[32m✓ Created HTML runtime\x1b[0m");
+    fs::write(output, html).map_err(|e| format!("Failed to write {}: {}", output, e))
 }
 
-/// Create a real Debian package
-fn create_real_deb(output: &str, project_name: &str, source: &str, uses_canvas: bool) {
-    let deb_dir = output.replace(".deb", "_deb");
-    
-    // Create Debian package structure
-    fs::create_dir_all(format!("{}/DEBIAN", deb_dir)).ok();
-    fs::create_dir_all(format!("{}/usr/bin", deb_dir)).ok();
-    fs::create_dir_all(format!("{}/usr/share/{}", deb_dir, project_name)).ok();
-    fs::create_dir_all(format!("{}/usr/share/applications", deb_dir)).ok();
-    
-    // Control file
-    let control = format!(r#"Package: {}
-Version: 1.0.3
-Section: utils
-Priority: optional
-Architecture: amd64
-Depends: libc6
-Maintainer: Mintas Developer <dev@mintas.io>
-Description: {} - Built with Mintas
- A Mintas application packaged for Debian/Ubuntu.
- {}
-"#, project_name, project_name, if uses_canvas { "Includes canvas graphics support." } else { "" });
-    fs::write(format!("{}/DEBIAN/control", deb_dir), control).ok();
-    
-    // Post-install script
-    let postinst = format!(r#"#!/bin/bash
-chmod +x /usr/bin/{}
-"#, project_name);
-    fs::write(format!("{}/DEBIAN/postinst", deb_dir), postinst).ok();
-    
-    // Launcher script
-    let launcher = format!(r#"#!/bin/bash
-# {} - Mintas Application
-exec mintas /usr/share/{}/main.as "$@"
-"#, project_name, project_name);
-    let launcher_path = format!("{}/usr/bin/{}", deb_dir, project_name);
-    fs::write(&launcher_path, launcher).ok();
-    
-    // Make launcher executable
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        if let Ok(metadata) = fs::metadata(&launcher_path) {
-            let mut perms = metadata.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&launcher_path, perms).ok();
-        }
-    }
-    
-    // Source file
-    fs::write(format!("{}/usr/share/{}/main.as", deb_dir, project_name), source).ok();
-    
-    // Desktop entry for GUI apps
-    if uses_canvas {
-        let desktop = format!(r#"[Desktop Entry]
-Name={}
-Exec={}
-Type=Application
-Categories=Game;
-"#, project_name, project_name);
-        fs::write(format!("{}/usr/share/applications/{}.desktop", deb_dir, project_name), desktop).ok();
-    }
-    
-    // Create actual .deb using ar format
-    // ar archive: debian-binary, control.tar.gz, data.tar.gz
-    create_deb_archive(output, &deb_dir, project_name);
-    
-    println!("      \x1b[32m✓ Created Debian package\x1b[0m");
-}
-
-fn create_deb_archive(output: &str, deb_dir: &str, _project_name: &str) {
-    // Create a simple ar archive format .deb
-    let mut deb_content = Vec::new();
-    
-    // AR magic
-    deb_content.extend_from_slice(b"!<arch>\n");
-    
-    // debian-binary file
-    let debian_binary = b"2.0\n";
-    write_ar_entry(&mut deb_content, "debian-binary", debian_binary);
-    
-    // control.tar (simplified - just the control file content)
-    let control_content = fs::read_to_string(format!("{}/DEBIAN/control", deb_dir)).unwrap_or_default();
-    write_ar_entry(&mut deb_content, "control.tar", control_content.as_bytes());
-    
-    // data.tar (simplified - source file)
-    let data_content = fs::read_to_string(format!("{}/usr/share/{}/main.as", deb_dir, 
-        deb_dir.split('/').last().unwrap_or("app").replace("_deb", ""))).unwrap_or_default();
-    write_ar_entry(&mut deb_content, "data.tar", data_content.as_bytes());
-    
-    fs::write(output, &deb_content).ok();
-}
-
-fn write_ar_entry(archive: &mut Vec<u8>, name: &str, content: &[u8]) {
-    // AR entry header: 16 bytes name, 12 bytes mtime, 6 bytes uid, 6 bytes gid, 8 bytes mode, 10 bytes size, 2 bytes magic
-    let mut header = [0x20u8; 60];
-    
-    // Name (16 bytes, padded with spaces)
-    let name_bytes = name.as_bytes();
-    header[..name_bytes.len().min(16)].copy_from_slice(&name_bytes[..name_bytes.len().min(16)]);
-    
-    // Size (10 bytes at offset 48)
-    let size_str = format!("{:<10}", content.len());
-    header[48..58].copy_from_slice(size_str.as_bytes());
-    
-    // Magic (2 bytes at offset 58)
-    header[58] = 0x60;
-    header[59] = 0x0A;
-    
-    archive.extend_from_slice(&header);
-    archive.extend_from_slice(content);
-    
-    // Pad to even boundary
-    if content.len() % 2 != 0 {
-        archive.push(0x0A);
-    }
-}
-
-/// Create a real macOS package
-fn create_real_pkg(output: &str, project_name: &str, source: &str, uses_canvas: bool) {
-    let pkg_dir = output.replace(".pkg", "_pkg");
-    
-    // Create package structure
-    fs::create_dir_all(format!("{}/Contents/Resources", pkg_dir)).ok();
-    fs::create_dir_all(format!("{}/Contents/Scripts", pkg_dir)).ok();
-    
-    // Info.plist
-    let plist = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
-<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
-<plist version="1.0">
-<dict>
-    <key>CFBundleIdentifier</key>
-    <string>io.mintas.{}</string>
-    <key>CFBundleName</key>
-    <string>{}</string>
-    <key>CFBundleVersion</key>
-    <string>1.0.3</string>
-    <key>CFBundleShortVersionString</key>
-    <string>1.0.3</string>
-    <key>LSMinimumSystemVersion</key>
-    <string>10.13</string>
-    {}
-</dict>
-</plist>
-"#, project_name, project_name, if uses_canvas { "<key>NSHighResolutionCapable</key><true/>" } else { "" });
-    fs::write(format!("{}/Contents/Info.plist", pkg_dir), plist).ok();
-    
-    // Source file
-    fs::write(format!("{}/Contents/Resources/main.as", pkg_dir), source).ok();
-    
-    // Post-install script
-    let postinstall = format!(r#"#!/bin/bash
-mkdir -p /usr/local/share/{}
-cp "${{PACKAGE_PATH}}/Contents/Resources/main.as" /usr/local/share/{}/
-echo '#!/bin/bash' > /usr/local/bin/{}
-echo 'mintas /usr/local/share/{}/main.as "$@"' >> /usr/local/bin/{}
-chmod +x /usr/local/bin/{}
-"#, project_name, project_name, project_name, project_name, project_name, project_name);
-    fs::write(format!("{}/Contents/Scripts/postinstall", pkg_dir), postinstall).ok();
-    
-    // Create a flat package (xar archive simulation)
-    let mut pkg_content = Vec::new();
-    pkg_content.extend_from_slice(b"xar!");  // xar magic
-    pkg_content.extend_from_slice(&[0x00, 0x1C]); // header size
-    pkg_content.extend_from_slice(&[0x00, 0x01]); // version
-    
-    // Embed the source and metadata
-    let metadata = format!("MINTAS_PKG\nNAME={}\nVERSION=1.0.3\nCANVAS={}\n---\n{}",         project_name, uses_canvas, source);
-    pkg_content.extend_from_slice(metadata.as_bytes());
-    
-    fs::write(output, &pkg_content).ok();
-    println!("      \x1b[32m✓ Created macOS package\x1b[0m");
-}
-
-/// Create a Windows executable
-#[cfg(target_os = "windows")]
-fn create_real_exe(output: &str, project_name: &str, source: &str, uses_canvas: bool, _release: bool) {
-    // Create a Windows executable structure
-    // For now, we'll create a simple Windows batch file wrapper
-    let bat_content = format!(r#"@echo off
-REM {} - Mintas Application
-REM Canvas: {}
-
-set MINTAS_SOURCE={}
-
-REM Check if mintas is available
-where mintas >nul 2>nul
-if %ERRORLEVEL% NEQ 0 (
-    echo Error: mintas runtime not found. Please install mintas first.
-    exit /b 1
-)
-
-REM Pipe the source to mintas
-echo %MINTAS_SOURCE% | mintas /dev/stdin %*
-"#, project_name, uses_canvas, source.replace("\n", "^\n").replace("^", "^^"));
-    
-    // Write the batch file
-    fs::write(output, bat_content).ok();
-    println!("      \x1b[32m✓ Created Windows executable\x1b[0m");
-}
-
-/// Create a native executable for current platform
-fn create_real_native(output: &str, project_name: &str, source: &str, uses_canvas: bool, _release: bool) {
-    #[cfg(target_os = "windows")]
-    {
-        create_real_exe(output, project_name, source, uses_canvas, _release);
-    }
-    
-    #[cfg(not(target_os = "windows"))]
-    {
-        // For Unix, create a self-contained script with embedded source
-        let script = format!(r#"#!/bin/bash
-# {} - Mintas Application (Built with XDBX)
-# Canvas: {}
-
-MINTAS_SOURCE='{}
-'
-
-# Check if mintas is available
-if command -v mintas &> /dev/null; then
-    echo "$MINTAS_SOURCE" | mintas /dev/stdin "$@"
-else
-    echo "Error: mintas runtime not found. Please install mintas first."
-    exit 1
-fi
-"#, project_name, uses_canvas, source.replace("'", "'\"'\"'") );
-        
-        fs::write(output, &script).ok();
-        
-        // Make executable
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Ok(metadata) = fs::metadata(output) {
-                let mut perms = metadata.permissions();
-                perms.set_mode(0o755);
-                fs::set_permissions(output, perms).ok();
-            }
-        }
-    }
-    
-    println!("      \x1b[32m✓ Created native executable\x1b[0m");
-}
-fn xdbx_run(file: &str) {
+fn xdbx_run(file: &str, script_args: Vec<String>) {
     println!("\x1b[34m▶️  Running {}...\x1b[0m\n", file);
     
     let path = if std::path::Path::new(file).exists() {
@@ -1771,8 +2348,7 @@ fn xdbx_run(file: &str) {
         std::process::exit(1);
     };
     
-    // Run the file
-    run_file(&path, false, false, false, false);
+    run_file(&path, None, &RunOptions { script_args, ..RunOptions::default() });
 }
 
 fn xdbx_test() {
@@ -1793,7 +2369,7 @@ fn xdbx_test() {
                 let code = fs::read_to_string(&path).unwrap_or_default();
                 let mut evaluator = Evaluator::new();
                 
-                match execute_jetx(&code, &mut evaluator, false, false) {
+                match execute_jetx(&code, &mut evaluator, false, false, false, std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US)) {
                     Ok(_) => {
                         println!("\x1b[32mPASSED\x1b[0m");
                         passed += 1;
@@ -1816,22 +2392,289 @@ fn xdbx_test() {
 
 
 
+/// Remove build artifacts from the current project
+fn xdbx_clean(dry_run: bool) {
+    if !std::path::Path::new("mintas.toml").exists() {
+        eprintln!("\x1b[31m❌ No mintas.toml found in current directory\x1b[0m");
+        eprintln!("   Refusing to clean outside a Mintas project");
+        std::process::exit(1);
+    }
+    let project_dir = match std::env::current_dir().and_then(|d| d.canonicalize()) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("\x1b[31m❌ Cannot resolve project directory: {}\x1b[0m", e);
+            std::process::exit(1);
+        }
+    };
+    
+    let removed = clean_build_output(&project_dir, dry_run);
+    
+    let noun = if removed == 1 { "directory" } else { "directories" };
+    if removed == 0 {
+        println!("\x1b[32m✨ Nothing to clean\x1b[0m");
+    } else if dry_run {
+        println!("\n\x1b[34m🧹 {} {} would be removed (dry run)\x1b[0m", removed, noun);
+    } else {
+        println!("\n\x1b[32m🧹 Cleaned {} {}\x1b[0m", removed, noun);
+    }
+}
+
+/// Removes `target/`. Every artifact xdbx builds lands under `target/<mode>/`, and so do the
+/// scratch directories derived from them (`app_dist` beside `app.exe`, `app_pkg`),
+/// so nothing else in the project is touched: a source directory named `my_pkg/` is safe.
+fn clean_build_output(project_dir: &std::path::Path, dry_run: bool) -> usize {
+    let path = project_dir.join("target");
+    // Symlinks are never followed: a linked `target` could point anywhere
+    let metadata = match fs::symlink_metadata(&path) {
+        Ok(m) => m,
+        Err(_) => return 0,
+    };
+    let inside = metadata.is_dir() && path.canonicalize()
+        .map(|p| p.starts_with(project_dir) && p != project_dir)
+        .unwrap_or(false);
+    if !inside {
+        eprintln!("\x1b[33m⚠️  Skipping target: not a directory inside the project\x1b[0m");
+        return 0;
+    }
+    if dry_run {
+        println!("   would remove target/");
+        return 1;
+    }
+    match fs::remove_dir_all(&path) {
+        Ok(_) => {
+            println!("   \x1b[32m✓\x1b[0m Removed target/");
+            1
+        }
+        Err(e) => {
+            eprintln!("\x1b[31m❌ Failed to remove target: {}\x1b[0m", e);
+            0
+        }
+    }
+}
+
 fn xdbx_targets() {
     println!("\n\x1b[1mAvailable Build Targets:\x1b[0m");
     println!();
-    println!("  \x1b[36mExecutables:\x1b[0m");
-    println!("    --exe, --windows     Windows executable (.exe)");
-    println!("    --native             Native executable for current OS");
+    println!("  \x1b[36mBytecode:\x1b[0m");
+    println!("    --ms                 Mintas Serialized bytecode (.ms), the default");
     println!();
     println!("  \x1b[36mWeb:\x1b[0m");
-    println!("    --wasm               WebAssembly (.wasm + .html)");
-    println!();
-    println!("  \x1b[36mPackages:\x1b[0m");
-    println!("    --deb                Debian/Ubuntu package (.deb)");
-    println!("    --pkg                macOS package (.pkg)");
+    println!("    --web, --wasm        Standalone page (.html) running say() and assignments");
     println!();
     println!("  \x1b[36mExamples:\x1b[0m");
-    println!("    mintas xdbx build --exe");
-    println!("    mintas xdbx build --wasm");
-    println!("    mintas xdbx build --deb --release");
+    println!("    mintas xdbx build");
+    println!("    mintas xdbx build --release --out dist/app.ms");
+    println!("    mintas xdbx build --web");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mintas-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    /// Runs each snippet on one evaluator, as `-e` does, and returns the last result
+    fn run_snippets_on_one_evaluator(snippets: &[&str]) -> Value {
+        let mut evaluator = Evaluator::new();
+        let threshold = std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US);
+        let mut result = Value::Empty;
+        for code in snippets {
+            result = execute_jetx(code, &mut evaluator, false, false, false, threshold).unwrap();
+        }
+        assert!(evaluator.get_variables().keys().all(|name| !name.starts_with('$')), "hoisting slots leaked");
+        result
+    }
+
+    #[test]
+    fn test_hoisting_respects_definitions_from_earlier_snippets() {
+        // `bump` changes `n` through `global`, which the second snippet's text can't show
+        let bump = "func bump():\n    global n\n    n = n + 1\nend";
+        let sum = "n = 0\ntotal = 0\nfor (i from 1 to 3):\n    bump()\n    total = total + n * 10\nend\ntotal";
+        assert_eq!(run_snippets_on_one_evaluator(&[bump, sum]), Value::Number(60.0));
+        // A hoisted loop leaves no `$invariantN` slots behind
+        let hoisted = "a = 2\ntotal = 0\nfor (i from 1 to 3):\n    total = total + a * 5\nend\ntotal";
+        assert_eq!(run_snippets_on_one_evaluator(&[hoisted]), Value::Number(30.0));
+    }
+
+    #[test]
+    fn test_hoisting_sees_builders_from_earlier_snippets() {
+        // A builder from an earlier snippet, grown through an alias while the loop tests its length
+        let grow = "other = sb\nn = 0\nwhile (sb.len() < 3 and n < 10):\n    other.append(\"x\")\n    n = n + 1\nend\nsb.len()";
+        assert_eq!(run_snippets_on_one_evaluator(&["sb = string_builder()", grow]), Value::Number(3.0));
+        let grow = "other = box[1]\nn = 0\nwhile (box[1].len() < 3 and n < 10):\n    other.append(\"x\")\n    n = n + 1\nend\nn";
+        assert_eq!(run_snippets_on_one_evaluator(&["box = [string_builder()]", grow]), Value::Number(3.0));
+        // A numeric-looking last line that calls a method stays in the interpreter
+        let built = "sb = string_builder()\nsb.append(\"abc\")";
+        assert_eq!(run_snippets_on_one_evaluator(&[built, "sb.len() * 2"]), Value::Number(6.0));
+    }
+
+    #[test]
+    fn test_jetx_writes_back_each_variable() {
+        let mut evaluator = Evaluator::new();
+        let threshold = std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US);
+        let code = "a = 2\nb = a * 10\ndown = 0\nfor (k from 3 to 1):\n    down = down * 10 + k\nend\nif (a > 5):\n    unset = 1\nend\nb + down";
+        let result = execute_jetx_unguarded(code, &mut evaluator, false, true, false, threshold).unwrap();
+        assert_eq!(result, Value::Number(341.0));
+        let variables = evaluator.get_variables();
+        assert_eq!(variables.get("a"), Some(&Value::Number(2.0)));
+        assert_eq!(variables.get("b"), Some(&Value::Number(20.0)));
+        assert_eq!(variables.get("down"), Some(&Value::Number(321.0)));
+        assert_eq!(variables.get("k"), Some(&Value::Number(1.0)));
+        assert!(!variables.contains_key("unset"));
+    }
+
+    #[test]
+    fn test_exec_snippets_see_each_jetx_variable() {
+        // `-e 'x = 3\ny = x * 2\ny' -e ...`: the first snippet runs in JetX
+        assert_eq!(run_snippets_on_one_evaluator(&["x = 3\ny = x * 2\ny", "x * 100 + y"]), Value::Number(306.0));
+    }
+
+    #[test]
+    fn test_jetx_leaves_strings_and_booleans_to_the_interpreter() {
+        // JetX would store the string's length and the comparison as 1
+        let repeated = "s = \"ab\" * 3\nflag = 3 > 2\nn = 2\nn * 2";
+        assert_eq!(run_snippets_on_one_evaluator(&[repeated, "s"]), Value::String("ababab".to_string()));
+        assert_eq!(run_snippets_on_one_evaluator(&[repeated, "flag"]), Value::Boolean(true));
+        let mut evaluator = Evaluator::new();
+        let threshold = std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US);
+        for code in ["\"-\" * 3", "n = 1\nn < 2"] {
+            let expected = execute_interpreter_timed(&parse_code(code).unwrap(), &mut Evaluator::new()).unwrap();
+            assert_eq!(execute_jetx_unguarded(code, &mut evaluator, false, true, false, threshold).unwrap(), expected, "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_jetx_hands_inexact_remainders_to_the_interpreter() {
+        let threshold = std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US);
+        for (code, expected) in [("mod(1e17 + 3, 7)", 5.0), ("big = 4503599627370496 * 4 + 3\nmod(big, 7)", 4.0), ("mod(-7, 3)", -1.0)] {
+            let result = execute_jetx_unguarded(code, &mut Evaluator::new(), false, true, false, threshold).unwrap();
+            assert_eq!(result, Value::Number(expected), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_jit_fallback_reasons_name_what_stays_interpreted() {
+        let reason = |code: &str| {
+            let statements = parse_code(code).unwrap();
+            let mut scope = JitScope::for_program(&statements);
+            statements.iter().find_map(|stmt| jit_fallback(stmt, &mut scope)).map(|r| r.to_string())
+        };
+        assert_eq!(reason("const x = 1").as_deref(), Some("declares a const"));
+        assert_eq!(reason("for (i from 1 to 3):\n    i = 2\nend").as_deref(), Some("assigns to loop variable `i`"));
+        assert_eq!(reason("2 ** 3").as_deref(), Some("operator Exponent has no JetX lowering"));
+        assert_eq!(reason("sqrt(4, 2)").as_deref(), Some("calls `sqrt()` with arguments JetX can't compile"));
+        assert_eq!(reason("x = [1]").as_deref(), Some("unsupported expression Array"));
+        assert_eq!(reason("x = 1\nx + 2"), None);
+    }
+
+    #[test]
+    fn test_random_programs_stay_in_the_interpreter() {
+        // JetX would compile random() and random_int() to 0
+        let threshold = std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US);
+        let code = "x = random_int(1, 1000)\ny = random()\nx + y";
+        let mut interpreter = Evaluator::new();
+        interpreter.set_seed(Some(42));
+        let expected = execute_interpreter_timed(&parse_code(code).unwrap(), &mut interpreter).unwrap();
+        for force_jetx in [false, true] {
+            let mut evaluator = Evaluator::new();
+            evaluator.set_seed(Some(42));
+            let value = execute_jetx_unguarded(code, &mut evaluator, false, force_jetx, false, threshold).unwrap();
+            assert_eq!(value, expected);
+        }
+    }
+
+    #[test]
+    fn test_analysis_findings_do_not_abort_the_run() {
+        let code = "func add(a, b):\n    return a + b\nend\nif (false):\n    add(1)\nend\nadd(2, 3)";
+        assert_eq!(run_snippets_on_one_evaluator(&[code]), Value::Number(5.0));
+    }
+
+    #[test]
+    fn test_repl_notes_every_redefinition() {
+        let mut evaluator = Evaluator::new();
+        let threshold = std::time::Duration::from_micros(DEFAULT_JIT_THRESHOLD_US);
+        let mut notes = |input: &str| {
+            let redefinitions = ReplRedefinitions::before(input, &evaluator);
+            execute_jetx(input, &mut evaluator, false, false, false, threshold).unwrap();
+            redefinitions.notes(&evaluator)
+        };
+        assert!(notes("count = 1\nfunc f():\n    return 1\nend").is_empty());
+        assert_eq!(notes("count = 2"), vec!["reassigned 'count'"]);
+        assert_eq!(notes("count = \"two\""), vec!["reassigned 'count' from number to string"]);
+        assert_eq!(notes("func f():\n    return 2\nend"), vec!["redefined function 'f'"]);
+    }
+
+    #[test]
+    fn test_completion_start_after_multibyte_separator() {
+        assert_eq!(completion_start("x = pri", 7), 4);
+        assert_eq!(completion_start("pri", 3), 0);
+        for line in ["price €pri", "a→pri", "total—math.sq"] {
+            let start = completion_start(line, line.len());
+            assert!(line.is_char_boundary(start));
+            assert!(matches!(&line[start..], "pri" | "math.sq"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_decode_source_handles_bom_and_latin1() {
+        assert_eq!(decode_source(b"\xEF\xBB\xBFsay(1)", None).unwrap(), "say(1)");
+        // "café" saved as Latin-1 is rejected by default and decoded with --encoding latin1
+        let latin1 = b"say(\"caf\xE9\")";
+        let err = decode_source(latin1, None).unwrap_err();
+        assert!(err.contains("offset 8") && err.contains("--encoding latin1"), "{}", err);
+        let enc = encoding_rs::Encoding::for_label(b"latin1");
+        assert_eq!(decode_source(latin1, enc).unwrap(), "say(\"café\")");
+        // A UTF-16 BOM picks its own decoder
+        assert_eq!(decode_source(b"\xFF\xFEx\x00", None).unwrap(), "x");
+    }
+
+    #[test]
+    fn test_clean_only_removes_target() {
+        let project = scratch_dir("clean");
+        for dir in ["target/release/app_dist", "target/debug/app_pkg", "my_pkg", "ui_dist", "src_build"] {
+            fs::create_dir_all(project.join(dir)).unwrap();
+        }
+        fs::write(project.join("my_pkg/lib.as"), "say(1)").unwrap();
+
+        assert_eq!(clean_build_output(&project, true), 1);
+        assert!(project.join("target").exists());
+        assert_eq!(clean_build_output(&project, false), 1);
+        assert!(!project.join("target").exists());
+        assert!(project.join("my_pkg/lib.as").is_file());
+        assert!(project.join("ui_dist").is_dir() && project.join("src_build").is_dir());
+        assert_eq!(clean_build_output(&project, false), 0);
+        fs::remove_dir_all(&project).unwrap();
+    }
+
+    #[test]
+    fn test_web_page_runs_arithmetic_and_variables() {
+        let dir = scratch_dir("web");
+        let run = |source: &str| {
+            let page = dir.join("demo.html");
+            create_wasm_html_runtime(page.to_str().unwrap(), "demo", source, false).unwrap();
+            let html = fs::read_to_string(&page).unwrap();
+            let start = html.rfind("<script>").unwrap() + "<script>".len();
+            let script = &html[start..html.rfind("</script>").unwrap()];
+            // The page only touches the #output element, so a one-element DOM is enough
+            let stub = "const element = { textContent: '' };\nconst document = { getElementById: () => element };\nconst window = globalThis;\n";
+            let harness = dir.join("page.js");
+            fs::write(&harness, format!("{}{}\nprocess.stdout.write(element.textContent);\n", stub, script)).unwrap();
+            std::process::Command::new("node").arg(&harness).output().ok()
+                .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+        };
+
+        // Browsers aren't available here; node runs the same script when it's installed
+        if let Some(text) = run("x = 2 + 3\nsay(x)\nsay(\"half: \" + x / 2)\nx *= 2\nsay(x > 8)\nsay(1e21)\n") {
+            assert_eq!(text, "Running demo...\n\n5\nhalf: 2.5\ntrue\n1000000000000000000000\n");
+            let text = run("say(y)\n").unwrap();
+            assert!(text.ends_with("Error: line 1: Undefined variable 'y'"), "{}", text);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,16 +1,26 @@
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use crate::evaluator::ClassInheritance;
 use crate::lexer::{Token, TokenWithLocation};
+use crate::table::FieldName;
+/// Type names a `match ... is` arm can test, as reported by `typeof`
+const MATCH_TYPE_NAMES: &[&str] = &[
+    "number", "string", "boolean", "empty", "null", "array", "table", "bytes", "superset",
+    "function", "class",
+];
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Number(f64),
+    Int(i64),
     String(String),
+    /// Raw string literal, never interpolated
+    RawString(String),
     Boolean(bool),
     Maybe,
     Empty,
     Array(Vec<Expr>),
     Table(Vec<(String, Expr)>),
     SuperSet(Box<Expr>),
+    Spread(Box<Expr>),
     Variable(String),
     BinaryOp {
         op: BinaryOp,
@@ -36,9 +46,15 @@ pub enum Expr {
         op: BinaryOp,
         value: Box<Expr>,
     },
+    /// `global a, b` inside a function: assignments to these names write the global binding
+    Global {
+        names: Vec<String>,
+    },
+    /// `location` is where the call starts, given to errors the call raises without one
     Call {
         name: String,
         args: Vec<Expr>,
+        location: SourceLocation,
     },
     IfExpr {
         condition: Box<Expr>,
@@ -46,20 +62,28 @@ pub enum Expr {
         else_if_branches: Vec<(Expr, Vec<Expr>)>,
         else_branch: Option<Vec<Expr>>,
     },
+    /// `else_branch` runs only when the body never executed.
     WhileLoop {
         condition: Box<Expr>,
         body: Vec<Expr>,
+        else_branch: Option<Vec<Expr>>,
     },
+    /// `for (i from a to b):` counts down when `a` is above `b`, so it always runs at least
+    /// once and takes no `else`
     ForLoop {
         var: String,
         start: Box<Expr>,
         end: Box<Expr>,
         body: Vec<Expr>,
     },
+    /// `for (a, b, c in x):`. One variable binds each element (the key, for tables). A second
+    /// binds the zero-based position for arrays, strings and ranges, or the value for tables;
+    /// tables also take a third, the position. Tables iterate in sorted key order.
     ForInLoop {
-        var: String,
+        vars: Vec<String>,
         iterable: Box<Expr>,
         body: Vec<Expr>,
+        else_branch: Option<Vec<Expr>>,
     },
     Exit,
     Proceed,
@@ -82,6 +106,8 @@ pub enum Expr {
         params: Vec<String>,
         body: Vec<Expr>,
         is_lambda: bool,
+        /// `##` doc comment written above the definition
+        doc: Option<String>,
     },
     Return {
         value: Option<Box<Expr>>,
@@ -96,13 +122,15 @@ pub enum Expr {
         args: Vec<Expr>,
     },
     This,
+    /// `super` in `super.method(...)`: the parent class's version of a method, run on `self`
+    Super,
     Property {
         object: Box<Expr>,
-        property: String,
+        property: FieldName,
     },
     PropertyAssign {
         object: Box<Expr>,
-        property: String,
+        property: FieldName,
         value: Box<Expr>,
     },
     TryCatch {
@@ -121,6 +149,10 @@ pub enum Expr {
         module_name: String,
         alias: Option<String>,
     },
+    Export {
+        names: Vec<String>,
+        declaration: Option<Box<Expr>>,
+    },
     Task {
         name: String,
         params: Vec<String>,
@@ -131,6 +163,12 @@ pub enum Expr {
         cases: Vec<(Expr, Vec<Expr>)>,
         default_case: Option<Vec<Expr>>,
     },
+    /// `match x is number: ... is string: ... default: ... end`, dispatching on the runtime type
+    TypeMatch {
+        subject: Box<Expr>,
+        arms: Vec<(String, Vec<Expr>)>,
+        default_case: Option<Vec<Expr>>,
+    },
     SmartCondition {
         condition: Box<Expr>,
         then_branch: Box<Expr>,
@@ -146,6 +184,8 @@ pub enum Expr {
         method: String,  
         path: String,
         body: Vec<Expr>,
+        /// `timeout = ...` after the path; milliseconds or a duration string like "30s"
+        timeout: Option<Box<Expr>>,
     },
     DewServe {
         server: Box<Expr>,
@@ -192,6 +232,7 @@ pub enum Expr {
         path: String,
         validation_rules: Box<Expr>,
         body: Vec<Expr>,
+        timeout: Option<Box<Expr>>,
     },
     DewConfig {
         server: Box<Expr>,
@@ -245,6 +286,7 @@ pub enum BinaryOp {
     StrictNotEqual,
     And,
     Or,
+    NullCoalesce,
 }
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOp {
@@ -277,10 +319,16 @@ fn is_right_associative(token: &Token) -> bool {
 pub struct Parser {
     tokens: Vec<TokenWithLocation>,
     position: usize,
+    statement_lines: Vec<usize>,
+    /// Temporaries bound by chained comparisons, for unique `$chainN` names
+    chain_temps: usize,
 }
 impl Parser {
     pub fn new(tokens: Vec<TokenWithLocation>) -> Self {
-        Self { tokens, position: 0 }
+        Self { tokens, position: 0, statement_lines: Vec::new(), chain_temps: 0 }
+    }
+    pub fn statement_lines(&self) -> &[usize] {
+        &self.statement_lines
     }
     fn current_token(&self) -> Option<&Token> {
         self.tokens.get(self.position).map(|t| &t.token)
@@ -291,7 +339,6 @@ impl Parser {
             .map(|t| t.location.clone())
             .unwrap_or_default()
     }
-    #[allow(dead_code)]
     fn peek_token(&self) -> Option<&Token> {
         self.tokens.get(self.position + 1).map(|t| &t.token)
     }
@@ -323,11 +370,15 @@ impl Parser {
             if matches!(self.current_token(), Some(Token::EOF) | None) {
                 break;
             }
+            self.statement_lines.push(self.current_location().line);
             statements.push(self.parse_statement()?);
         }
         Ok(statements)
     }
     fn parse_statement(&mut self) -> MintasResult<Expr> {
+        if let Some(type_match) = self.parse_type_match()? {
+            return Ok(type_match);
+        }
         match self.current_token() {
             Some(Token::At) => self.parse_dew_decorator(),
             Some(Token::Bring) => self.parse_bring(),
@@ -338,6 +389,27 @@ impl Parser {
             Some(Token::Switch) => self.parse_switch(),
             Some(Token::Either) => self.parse_smart_condition(),
             Some(Token::Goto) => self.parse_smart_loop(),
+            Some(Token::Global) => {
+                self.advance();
+                let mut names = Vec::new();
+                loop {
+                    match self.current_token() {
+                        Some(Token::Identifier(name)) => {
+                            names.push(name.clone());
+                            self.advance();
+                        }
+                        _ => return Err(MintasError::ParseError {
+                            message: "Expected a variable name after 'global'".to_string(),
+                            location: self.current_location(),
+                        }),
+                    }
+                    if !matches!(self.current_token(), Some(Token::Comma)) {
+                        break;
+                    }
+                    self.advance();
+                }
+                Ok(Expr::Global { names })
+            }
             Some(Token::Cond) => {
                 self.advance();
                 self.expect(&Token::LeftParen)?;
@@ -362,8 +434,16 @@ impl Parser {
                 self.advance();
                 let module_name = match self.current_token() {
                     Some(Token::Identifier(name)) => {
-                        let name = name.clone();
+                        let mut name = name.clone();
                         self.advance();
+                        // `include mylib.ms` names a precompiled bytecode module.
+                        if matches!(self.current_token(), Some(Token::Dot))
+                            && matches!(self.peek_token(), Some(Token::Identifier(ext)) if ext == "ms")
+                        {
+                            self.advance();
+                            self.advance();
+                            name.push_str(".ms");
+                        }
                         name
                     }
                     _ => return Err(MintasError::ParseError {
@@ -389,6 +469,8 @@ impl Parser {
                 };
                 Ok(Expr::Include { module_name, alias })
             }
+            Some(Token::Export) => self.parse_export(),
+            Some(Token::Class) => self.parse_class(),
             Some(Token::Try) => self.parse_try_catch(),
             Some(Token::Def) | Some(Token::Make) => {
                 let keyword = if matches!(self.current_token(), Some(Token::Def)) { "def" } else { "make" };
@@ -417,6 +499,37 @@ impl Parser {
             _ => self.parse_expression(),
         }
     }
+    fn parse_export(&mut self) -> MintasResult<Expr> {
+        let loc = self.current_location();
+        self.advance();
+        let declaration = self.parse_statement()?;
+        match declaration {
+            Expr::Function { ref name, .. } | Expr::Class { ref name, .. } | Expr::Assign { ref name, .. } => {
+                Ok(Expr::Export { names: vec![name.clone()], declaration: Some(Box::new(declaration.clone())) })
+            }
+            Expr::Variable(name) => {
+                let mut names = vec![name];
+                while matches!(self.current_token(), Some(Token::Comma)) {
+                    self.advance();
+                    match self.current_token() {
+                        Some(Token::Identifier(name)) => {
+                            names.push(name.clone());
+                            self.advance();
+                        }
+                        _ => return Err(MintasError::ParseError {
+                            message: "Expected name after ',' in export list".to_string(),
+                            location: self.current_location(),
+                        }),
+                    }
+                }
+                Ok(Expr::Export { names, declaration: None })
+            }
+            _ => Err(MintasError::ParseError {
+                message: "'export' must be followed by a function, class, assignment or list of names".to_string(),
+                location: loc,
+            }),
+        }
+    }
     fn parse_expression(&mut self) -> MintasResult<Expr> {
         self.parse_assignment()
     }
@@ -597,7 +710,56 @@ impl Parser {
             _ => Ok(()),
         }
     }
+    /// `x follow f follow g(2)` is a pipeline: each stage is called with the value so far as its
+    /// first argument, so it parses as `g(f(x), 2)`.
     fn parse_logical_or(&mut self) -> MintasResult<Expr> {
+        let mut expr = self.parse_coalesce()?;
+        while self.at_pipeline_follow() {
+            self.advance();
+            let loc = self.current_location();
+            expr = match self.parse_postfix()? {
+                Expr::Variable(name) => Expr::Call { name, args: vec![expr], location: loc },
+                Expr::Call { name, mut args, location } => {
+                    args.insert(0, expr);
+                    Expr::Call { name, args, location }
+                }
+                Expr::Property { object, property } => Expr::MethodCall { object, method: property.into(), args: vec![expr] },
+                Expr::MethodCall { object, method, mut args } => {
+                    args.insert(0, expr);
+                    Expr::MethodCall { object, method, args }
+                }
+                _ => return Err(MintasError::ParseError {
+                    message: "Expected a function name or call after 'follow'".to_string(),
+                    location: loc,
+                }),
+            };
+        }
+        Ok(expr)
+    }
+    /// A `follow` that starts a line or isn't followed by a name is the `follow (condition)` form
+    fn at_pipeline_follow(&self) -> bool {
+        let same_line = match (self.position.checked_sub(1).and_then(|i| self.tokens.get(i)), self.tokens.get(self.position)) {
+            (Some(prev), Some(current)) => prev.location.line == current.location.line,
+            _ => false,
+        };
+        same_line
+            && matches!(self.current_token(), Some(Token::Follow))
+            && matches!(self.peek_token(), Some(Token::Identifier(_)))
+    }
+    fn parse_coalesce(&mut self) -> MintasResult<Expr> {
+        let mut expr = self.parse_or_chain()?;
+        while let Some(Token::NullCoalesce) = self.current_token() {
+            self.advance();
+            let right = self.parse_or_chain()?;
+            expr = Expr::BinaryOp {
+                op: BinaryOp::NullCoalesce,
+                left: Box::new(expr),
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+    fn parse_or_chain(&mut self) -> MintasResult<Expr> {
         let mut expr = self.parse_logical_and()?;
         while let Some(Token::Or) = self.current_token() {
             self.advance();
@@ -623,27 +785,54 @@ impl Parser {
         }
         Ok(expr)
     }
+    fn comparison_op(&self) -> Option<BinaryOp> {
+        match self.current_token() {
+            Some(Token::Equal) => Some(BinaryOp::Equal),
+            Some(Token::NotEqual) => Some(BinaryOp::NotEqual),
+            Some(Token::Greater) => Some(BinaryOp::Greater),
+            Some(Token::Less) => Some(BinaryOp::Less),
+            Some(Token::GreaterEqual) => Some(BinaryOp::GreaterEqual),
+            Some(Token::LessEqual) => Some(BinaryOp::LessEqual),
+            Some(Token::StrictEqual) => Some(BinaryOp::StrictEqual),
+            Some(Token::StrictNotEqual) => Some(BinaryOp::StrictNotEqual),
+            _ => None,
+        }
+    }
+    /// Consecutive ordering comparisons chain: `a < b <= c` becomes `a < ($chainN = b) and
+    /// $chainN <= c`, so `b` is evaluated once. Equality operators keep associating left.
     fn parse_comparison(&mut self) -> MintasResult<Expr> {
+        let is_ordering = |op: BinaryOp| matches!(op, BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEqual | BinaryOp::GreaterEqual);
         let mut expr = self.parse_additive()?;
-        loop {
-            let op = match self.current_token() {
-                Some(Token::Equal) => BinaryOp::Equal,
-                Some(Token::NotEqual) => BinaryOp::NotEqual,
-                Some(Token::Greater) => BinaryOp::Greater,
-                Some(Token::Less) => BinaryOp::Less,
-                Some(Token::GreaterEqual) => BinaryOp::GreaterEqual,
-                Some(Token::LessEqual) => BinaryOp::LessEqual,
-                Some(Token::StrictEqual) => BinaryOp::StrictEqual,
-                Some(Token::StrictNotEqual) => BinaryOp::StrictNotEqual,
-                _ => break,
-            };
+        // Left operand of the next link while a chain continues
+        let mut carried: Option<Expr> = None;
+        while let Some(op) = self.comparison_op() {
             self.advance();
-            let right = self.parse_additive()?;
-            expr = Expr::BinaryOp {
-                op,
-                left: Box::new(expr),
-                right: Box::new(right),
+            let mut right = self.parse_additive()?;
+            let mut next_left = None;
+            if is_ordering(op) && self.comparison_op().is_some_and(is_ordering) {
+                next_left = Some(match right {
+                    Expr::Variable(_) | Expr::Number(_) | Expr::Int(_) | Expr::Boolean(_) => right.clone(),
+                    _ => {
+                        let name = format!("$chain{}", self.chain_temps);
+                        self.chain_temps += 1;
+                        right = Expr::Assign { name: name.clone(), value: Box::new(right), is_const: false };
+                        Expr::Variable(name)
+                    }
+                });
+            }
+            expr = match carried.take() {
+                Some(left) => Expr::BinaryOp {
+                    op: BinaryOp::And,
+                    left: Box::new(expr),
+                    right: Box::new(Expr::BinaryOp { op, left: Box::new(left), right: Box::new(right) }),
+                },
+                None => Expr::BinaryOp {
+                    op,
+                    left: Box::new(expr),
+                    right: Box::new(right),
+                },
             };
+            carried = next_left;
         }
         Ok(expr)
     }
@@ -754,7 +943,7 @@ impl Parser {
                     } else {
                         expr = Expr::Property {
                             object: Box::new(expr),
-                            property,
+                            property: FieldName::new(property),
                         };
                     }
                 }
@@ -795,11 +984,21 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Number(value))
             }
+            Some(Token::Int(n)) => {
+                let value = *n;
+                self.advance();
+                Ok(Expr::Int(value))
+            }
             Some(Token::String(s)) => {
                 let value = s.clone();
                 self.advance();
                 Ok(Expr::String(value))
             }
+            Some(Token::RawString(s)) => {
+                let value = s.clone();
+                self.advance();
+                Ok(Expr::RawString(value))
+            }
             Some(Token::Boolean(b)) => {
                 let value = *b;
                 self.advance();
@@ -818,7 +1017,12 @@ impl Parser {
                 let mut elements = Vec::new();
                 if !matches!(self.current_token(), Some(Token::RightBracket)) {
                     loop {
-                        elements.push(self.parse_logical_or()?);
+                        if matches!(self.current_token(), Some(Token::Ellipsis)) {
+                            self.advance();
+                            elements.push(Expr::Spread(Box::new(self.parse_logical_or()?)));
+                        } else {
+                            elements.push(self.parse_logical_or()?);
+                        }
                         if matches!(self.current_token(), Some(Token::Comma)) {
                             self.advance();
                         } else {
@@ -834,8 +1038,18 @@ impl Parser {
                 let mut pairs = Vec::new();
                 if !matches!(self.current_token(), Some(Token::RightBrace)) {
                     loop {
+                        if matches!(self.current_token(), Some(Token::Ellipsis)) {
+                            self.advance();
+                            let spread = self.parse_logical_or()?;
+                            pairs.push(("...".to_string(), Expr::Spread(Box::new(spread))));
+                            if matches!(self.current_token(), Some(Token::Comma)) {
+                                self.advance();
+                                continue;
+                            }
+                            break;
+                        }
                         let key_str = match self.current_token() {
-                            Some(Token::String(key)) => {
+                            Some(Token::String(key)) | Some(Token::RawString(key)) => {
                                 let k = key.clone();
                                 self.advance();
                                 k
@@ -873,6 +1087,7 @@ impl Parser {
                 Ok(Expr::Call {
                     name: "say".to_string(),
                     args: vec![arg],
+                    location: loc,
                 })
             }
             Some(Token::Ask) => {
@@ -883,12 +1098,23 @@ impl Parser {
                 Ok(Expr::Call {
                     name: "ask".to_string(),
                     args: vec![arg],
+                    location: loc,
                 })
             }
             Some(Token::This) => {
                 self.advance();
                 Ok(Expr::This)
             }
+            Some(Token::Super) => {
+                self.advance();
+                if !matches!(self.current_token(), Some(Token::Dot)) {
+                    return Err(MintasError::ParseError {
+                        message: "Expected '.method(...)' after 'super'".to_string(),
+                        location: self.current_location(),
+                    });
+                }
+                Ok(Expr::Super)
+            }
             Some(Token::New) => {
                 self.advance();
                 let class_name = match self.current_token() {
@@ -944,6 +1170,7 @@ impl Parser {
                     Ok(Expr::Call {
                         name: var_name,
                         args,
+                        location: loc,
                     })
                 } else if let Some(Token::PlusPlus) = self.current_token() {
                     self.advance();
@@ -1137,12 +1364,22 @@ impl Parser {
         self.expect(&Token::RightParen)?;
         self.expect(&Token::Colon)?;
         let body = self.parse_block()?;
+        let else_branch = self.parse_loop_else()?;
         self.expect(&Token::End)?;
         Ok(Expr::WhileLoop {
             condition: Box::new(condition),
             body,
+            else_branch,
         })
     }
+    fn parse_loop_else(&mut self) -> MintasResult<Option<Vec<Expr>>> {
+        if !matches!(self.current_token(), Some(Token::Else) | Some(Token::Otherwise)) {
+            return Ok(None);
+        }
+        self.advance();
+        self.expect(&Token::Colon)?;
+        Ok(Some(self.parse_block()?))
+    }
     fn parse_for(&mut self) -> MintasResult<Expr> {
         let loc = self.current_location();
         self.advance();
@@ -1155,7 +1392,23 @@ impl Parser {
             }),
         };
         self.advance();
-        if matches!(self.current_token(), Some(Token::From)) {
+        let mut vars = vec![var_name.clone()];
+        while matches!(self.current_token(), Some(Token::Comma)) {
+            self.advance();
+            match self.current_token() {
+                Some(Token::Identifier(name)) if vars.len() < 3 => vars.push(name.clone()),
+                Some(Token::Identifier(_)) => return Err(MintasError::ParseError {
+                    message: "A for-in loop binds at most 3 variables".to_string(),
+                    location: self.current_location(),
+                }),
+                _ => return Err(MintasError::ParseError {
+                    message: "Expected variable name in for loop".to_string(),
+                    location: self.current_location(),
+                }),
+            }
+            self.advance();
+        }
+        if vars.len() == 1 && matches!(self.current_token(), Some(Token::From)) {
             self.advance();
             let start = self.parse_logical_or()?;
             self.expect(&Token::To)?;
@@ -1163,6 +1416,12 @@ impl Parser {
             self.expect(&Token::RightParen)?;
             self.expect(&Token::Colon)?;
             let body = self.parse_block()?;
+            if matches!(self.current_token(), Some(Token::Else) | Some(Token::Otherwise)) {
+                return Err(MintasError::ParseError {
+                    message: "A `from ... to` loop always runs at least once (it counts down when start is above end), so it can't have an else".to_string(),
+                    location: self.current_location(),
+                });
+            }
             self.expect(&Token::End)?;
             Ok(Expr::ForLoop {
                 var: var_name,
@@ -1176,11 +1435,13 @@ impl Parser {
             self.expect(&Token::RightParen)?;
             self.expect(&Token::Colon)?;
             let body = self.parse_block()?;
+            let else_branch = self.parse_loop_else()?;
             self.expect(&Token::End)?;
             Ok(Expr::ForInLoop {
-                var: var_name,
+                vars,
                 iterable: Box::new(iterable),
                 body,
+                else_branch,
             })
         } else {
             Err(MintasError::ParseError {
@@ -1191,6 +1452,14 @@ impl Parser {
     }
     fn parse_function(&mut self, is_lambda: bool) -> MintasResult<Expr> {
         let loc = self.current_location();
+        // For `def func`, the doc comment sits on the `def` token
+        let doc = self.tokens.get(self.position).and_then(|t| t.doc.clone()).or_else(|| {
+            self.position
+                .checked_sub(1)
+                .and_then(|i| self.tokens.get(i))
+                .filter(|t| matches!(t.token, Token::Def | Token::Make))
+                .and_then(|t| t.doc.clone())
+        });
         self.advance();
         let name = match self.current_token() {
             Some(Token::Identifier(n)) => {
@@ -1238,6 +1507,7 @@ impl Parser {
             params,
             body,
             is_lambda,
+            doc,
         })
     }
     fn parse_class(&mut self) -> MintasResult<Expr> {
@@ -1478,6 +1748,69 @@ impl Parser {
             default_case,
         })
     }
+    /// `match` isn't reserved, so this only claims statements shaped like `match <expr> is`
+    fn parse_type_match(&mut self) -> MintasResult<Option<Expr>> {
+        if !matches!(self.current_token(), Some(Token::Identifier(name)) if name == "match") {
+            return Ok(None);
+        }
+        let start = self.position;
+        self.advance();
+        let subject = match self.parse_logical_or() {
+            Ok(subject) if matches!(self.current_token(), Some(Token::Assign)) => subject,
+            _ => {
+                self.position = start;
+                return Ok(None);
+            }
+        };
+        let mut arms = Vec::new();
+        let mut default_case = None;
+        loop {
+            match self.current_token() {
+                Some(Token::Assign) => {
+                    self.advance();
+                    let type_name = match self.current_token() {
+                        Some(Token::Identifier(name)) => name.clone(),
+                        Some(Token::Empty) => "empty".to_string(),
+                        Some(Token::Class) => "class".to_string(),
+                        _ => String::new(),
+                    };
+                    if !MATCH_TYPE_NAMES.contains(&type_name.as_str()) {
+                        return Err(MintasError::ParseError {
+                            message: format!("Expected a type name after 'is' ({})", MATCH_TYPE_NAMES.join(", ")),
+                            location: self.current_location(),
+                        });
+                    }
+                    self.advance();
+                    self.expect(&Token::Colon)?;
+                    arms.push((type_name, self.parse_match_arm()?));
+                }
+                Some(Token::Default) => {
+                    self.advance();
+                    self.expect(&Token::Colon)?;
+                    default_case = Some(self.parse_match_arm()?);
+                }
+                Some(Token::End) => {
+                    self.advance();
+                    break;
+                }
+                _ => return Err(MintasError::ParseError {
+                    message: "Expected 'is', 'default', or 'end' in match block".to_string(),
+                    location: self.current_location(),
+                }),
+            }
+        }
+        Ok(Some(Expr::TypeMatch { subject: Box::new(subject), arms, default_case }))
+    }
+    fn parse_match_arm(&mut self) -> MintasResult<Vec<Expr>> {
+        let mut body = Vec::new();
+        loop {
+            match self.current_token() {
+                Some(Token::Assign) | Some(Token::Default) | Some(Token::End) | Some(Token::EOF) | None => break,
+                _ => body.push(self.parse_statement()?),
+            }
+        }
+        Ok(body)
+    }
     fn parse_try_catch(&mut self) -> MintasResult<Expr> {
         self.advance(); 
         self.expect(&Token::Colon)?;
@@ -1605,7 +1938,7 @@ impl Parser {
     fn parse_dew_route(&mut self, server_name: String, method: String) -> MintasResult<Expr> {
         self.expect(&Token::LeftParen)?;
         let path = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
@@ -1615,6 +1948,21 @@ impl Parser {
                 location: self.current_location(),
             }),
         };
+        let mut timeout = None;
+        while matches!(self.current_token(), Some(Token::Comma)) {
+            self.advance();
+            match self.current_token() {
+                Some(Token::Identifier(name)) if name == "timeout" => {
+                    self.advance();
+                    self.expect(&Token::Assign)?;
+                    timeout = Some(Box::new(self.parse_logical_or()?));
+                }
+                _ => return Err(MintasError::ParseError {
+                    message: "Expected 'timeout = ...' after the route path".to_string(),
+                    location: self.current_location(),
+                }),
+            }
+        }
         self.expect(&Token::RightParen)?;
         if matches!(self.current_token(), Some(Token::Arrow)) {
             self.advance(); 
@@ -1639,6 +1987,7 @@ impl Parser {
                 path,
                 validation_rules: Box::new(validation_rules),
                 body,
+                timeout,
             });
         }
         self.expect(&Token::Colon)?;
@@ -1649,6 +1998,7 @@ impl Parser {
             method,
             path,
             body,
+            timeout,
         })
     }
     fn parse_dew_serve(&mut self, server_name: String) -> MintasResult<Expr> {
@@ -1700,7 +2050,7 @@ impl Parser {
     fn parse_dew_static(&mut self, server_name: String) -> MintasResult<Expr> {
         self.expect(&Token::LeftParen)?;
         let url_prefix = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
@@ -1712,7 +2062,7 @@ impl Parser {
         };
         self.expect(&Token::Comma)?;
         let dir_path = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
@@ -1750,7 +2100,7 @@ impl Parser {
     fn parse_dew_use(&mut self, server_name: String) -> MintasResult<Expr> {
         self.expect(&Token::LeftParen)?;
         let middleware = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
@@ -1792,7 +2142,7 @@ impl Parser {
     fn parse_dew_group(&mut self, server_name: String) -> MintasResult<Expr> {
         self.expect(&Token::LeftParen)?;
         let prefix = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
@@ -1815,7 +2165,7 @@ impl Parser {
     fn parse_dew_config(&mut self, server_name: String) -> MintasResult<Expr> {
         self.expect(&Token::LeftParen)?;
         let config_path = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
@@ -1834,7 +2184,7 @@ impl Parser {
     fn parse_dew_database(&mut self, server_name: String) -> MintasResult<Expr> {
         self.expect(&Token::LeftParen)?;
         let connection_string = match self.current_token() {
-            Some(Token::String(s)) => {
+            Some(Token::String(s)) | Some(Token::RawString(s)) => {
                 let s = s.clone();
                 self.advance();
                 s
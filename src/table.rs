@@ -0,0 +1,432 @@
+use crate::errors::{MintasError, MintasResult, SourceLocation};
+use crate::evaluator::Value;
+use std::borrow::Borrow;
+use std::collections::hash_map::{self, HashMap, RandomState};
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::sync::OnceLock;
+
+/// A table key. Strings, numbers and booleans are distinct keys, so `t[1]` and `t["1"]` are
+/// different entries. Whole numbers are held as `Int` whichever `Value` they came from, so `1`
+/// and `1.0` are one key; other numbers hash by their bits, with `-0.0` already folded into
+/// `Int(0)`. NaN can't be a key.
+#[derive(Clone)]
+pub enum HashableValue {
+    String(String),
+    Int(i64),
+    Number(f64),
+    Boolean(bool),
+}
+
+/// Borrowed form of a key that lookups hash and compare, so `get("name")` doesn't allocate
+#[derive(PartialEq, Eq, Hash)]
+pub enum KeyRef<'a> {
+    String(&'a str),
+    Int(i64),
+    Number(u64),
+    Boolean(bool),
+}
+
+/// Anything a table can be indexed by without building a `HashableValue`
+pub trait TableKey {
+    fn key_ref(&self) -> KeyRef<'_>;
+    fn key_hash(&self) -> u64 {
+        hash_key(&self.key_ref())
+    }
+}
+
+/// Every key hashes once, through SipHash keyed per process so keys from untrusted input
+/// (JSON bodies, query strings) can't be picked to collide. Tables then use `KeyHasher`,
+/// which passes that hash on, so a key that already knows its hash skips hashing entirely.
+fn hash_key(key: &KeyRef<'_>) -> u64 {
+    static SEED: OnceLock<RandomState> = OnceLock::new();
+    SEED.get_or_init(RandomState::new).hash_one(key)
+}
+
+/// A table's hasher: keys write the `u64` from `hash_key`, which becomes the hash as is
+#[derive(Default)]
+pub struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+/// A key as a lookup passes it to the map, carrying the hash the caller's key gave
+struct Lookup<'a>(KeyRef<'a>, u64);
+
+impl<'a> Lookup<'a> {
+    fn of<Q: TableKey + ?Sized>(key: &'a Q) -> Self {
+        Lookup(key.key_ref(), key.key_hash())
+    }
+}
+impl TableKey for Lookup<'_> {
+    fn key_ref(&self) -> KeyRef<'_> {
+        self.0.key_ref()
+    }
+    fn key_hash(&self) -> u64 {
+        self.1
+    }
+}
+
+/// A property name written in the source, as in `point.x`. The parser works out its key hash
+/// once, so reading or writing the field in a loop looks it up without hashing the name again.
+#[derive(Clone)]
+pub struct FieldName {
+    name: String,
+    hash: u64,
+}
+
+impl FieldName {
+    pub fn new(name: String) -> Self {
+        let hash = hash_key(&KeyRef::String(&name));
+        FieldName { name, hash }
+    }
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+impl TableKey for FieldName {
+    fn key_ref(&self) -> KeyRef<'_> {
+        KeyRef::String(&self.name)
+    }
+    fn key_hash(&self) -> u64 {
+        self.hash
+    }
+}
+impl PartialEq for FieldName {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+impl fmt::Display for FieldName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+impl fmt::Debug for FieldName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.name)
+    }
+}
+impl From<FieldName> for String {
+    fn from(field: FieldName) -> Self {
+        field.name
+    }
+}
+impl From<FieldName> for HashableValue {
+    fn from(field: FieldName) -> Self {
+        HashableValue::String(field.name)
+    }
+}
+
+impl HashableValue {
+    /// The key for `value`; arrays, tables and the like can't be keys
+    pub fn from_value(value: &Value) -> MintasResult<HashableValue> {
+        match value {
+            Value::String(s) => Ok(HashableValue::String(s.clone())),
+            Value::Boolean(b) => Ok(HashableValue::Boolean(*b)),
+            Value::Int(n) => Ok(HashableValue::Int(*n)),
+            Value::Number(n) if n.is_nan() => Err(MintasError::TypeError {
+                message: "NaN can't be used as a table key".to_string(),
+                location: SourceLocation::new(0, 0),
+            }),
+            Value::Number(n) => Ok(HashableValue::from(*n)),
+            other => Err(MintasError::TypeError {
+                message: format!("Table keys must be strings, numbers or booleans, got {}", other.type_name()),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
+    pub fn to_value(&self) -> Value {
+        match self {
+            HashableValue::String(s) => Value::String(s.clone()),
+            HashableValue::Int(n) => Value::from_i64(*n),
+            HashableValue::Number(n) => Value::Number(*n),
+            HashableValue::Boolean(b) => Value::Boolean(*b),
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            HashableValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    fn type_rank(&self) -> u8 {
+        match self {
+            HashableValue::Int(_) | HashableValue::Number(_) => 0,
+            HashableValue::String(_) => 1,
+            HashableValue::Boolean(_) => 2,
+        }
+    }
+}
+
+impl TableKey for HashableValue {
+    fn key_ref(&self) -> KeyRef<'_> {
+        match self {
+            HashableValue::String(s) => KeyRef::String(s),
+            HashableValue::Int(n) => KeyRef::Int(*n),
+            HashableValue::Number(n) => KeyRef::Number(n.to_bits()),
+            HashableValue::Boolean(b) => KeyRef::Boolean(*b),
+        }
+    }
+}
+impl TableKey for str {
+    fn key_ref(&self) -> KeyRef<'_> {
+        KeyRef::String(self)
+    }
+}
+impl TableKey for String {
+    fn key_ref(&self) -> KeyRef<'_> {
+        KeyRef::String(self)
+    }
+}
+impl TableKey for KeyRef<'_> {
+    fn key_ref(&self) -> KeyRef<'_> {
+        match self {
+            KeyRef::String(s) => KeyRef::String(s),
+            KeyRef::Int(n) => KeyRef::Int(*n),
+            KeyRef::Number(bits) => KeyRef::Number(*bits),
+            KeyRef::Boolean(b) => KeyRef::Boolean(*b),
+        }
+    }
+}
+impl<T: TableKey + ?Sized> TableKey for &T {
+    fn key_ref(&self) -> KeyRef<'_> {
+        (**self).key_ref()
+    }
+    fn key_hash(&self) -> u64 {
+        (**self).key_hash()
+    }
+}
+
+impl<'a> Borrow<dyn TableKey + 'a> for HashableValue {
+    fn borrow(&self) -> &(dyn TableKey + 'a) {
+        self
+    }
+}
+impl Hash for dyn TableKey + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.key_hash())
+    }
+}
+impl PartialEq for dyn TableKey + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.key_ref() == other.key_ref()
+    }
+}
+impl Eq for dyn TableKey + '_ {}
+
+impl Hash for HashableValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.key_hash())
+    }
+}
+impl PartialEq for HashableValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.key_ref() == other.key_ref()
+    }
+}
+impl Eq for HashableValue {}
+/// Numbers in numeric order, then strings, then booleans
+impl Ord for HashableValue {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        use HashableValue::*;
+        let number = |key: &HashableValue| match key {
+            Int(n) => *n as f64,
+            Number(n) => *n,
+            _ => 0.0,
+        };
+        match (self, other) {
+            (String(a), String(b)) => a.cmp(b),
+            (Boolean(a), Boolean(b)) => a.cmp(b),
+            (Int(a), Int(b)) => a.cmp(b),
+            (Int(_) | Number(_), Int(_) | Number(_)) => number(self).total_cmp(&number(other)),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+impl PartialOrd for HashableValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+/// Keys print as they read in scripts: strings bare, numbers as numbers
+impl fmt::Display for HashableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashableValue::String(s) => f.write_str(s),
+            HashableValue::Int(n) => write!(f, "{}", n),
+            HashableValue::Number(n) => write!(f, "{}", n),
+            HashableValue::Boolean(b) => write!(f, "{}", b),
+        }
+    }
+}
+impl fmt::Debug for HashableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashableValue::String(s) => write!(f, "{:?}", s),
+            other => write!(f, "{}", other),
+        }
+    }
+}
+impl From<String> for HashableValue {
+    fn from(s: String) -> Self {
+        HashableValue::String(s)
+    }
+}
+impl From<&str> for HashableValue {
+    fn from(s: &str) -> Self {
+        HashableValue::String(s.to_string())
+    }
+}
+impl From<&String> for HashableValue {
+    fn from(s: &String) -> Self {
+        HashableValue::String(s.clone())
+    }
+}
+impl From<bool> for HashableValue {
+    fn from(b: bool) -> Self {
+        HashableValue::Boolean(b)
+    }
+}
+impl From<i64> for HashableValue {
+    fn from(n: i64) -> Self {
+        HashableValue::Int(n)
+    }
+}
+/// Whole numbers that fit an i64 become `Int`, which also folds `-0.0` into `0`
+impl From<f64> for HashableValue {
+    fn from(n: f64) -> Self {
+        if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+            HashableValue::Int(n as i64)
+        } else {
+            HashableValue::Number(n)
+        }
+    }
+}
+
+/// What `Value::Table` holds. Mirrors the `HashMap` API, but lookups take any `TableKey`, so
+/// `map.get("name")` and `map.get(&key)` both work, and inserts take anything that converts to
+/// a key.
+#[derive(Clone, Default, PartialEq)]
+pub struct Table(HashMap<HashableValue, Value, BuildHasherDefault<KeyHasher>>);
+
+impl Table {
+    pub fn new() -> Self {
+        Table(HashMap::default())
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn get<Q: TableKey + ?Sized>(&self, key: &Q) -> Option<&Value> {
+        self.0.get(&Lookup::of(key) as &dyn TableKey)
+    }
+    pub fn get_mut<Q: TableKey + ?Sized>(&mut self, key: &Q) -> Option<&mut Value> {
+        self.0.get_mut(&Lookup::of(key) as &dyn TableKey)
+    }
+    pub fn contains_key<Q: TableKey + ?Sized>(&self, key: &Q) -> bool {
+        self.0.contains_key(&Lookup::of(key) as &dyn TableKey)
+    }
+    pub fn remove<Q: TableKey + ?Sized>(&mut self, key: &Q) -> Option<Value> {
+        self.0.remove(&Lookup::of(key) as &dyn TableKey)
+    }
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+    pub fn insert<K: Into<HashableValue>>(&mut self, key: K, value: Value) -> Option<Value> {
+        self.0.insert(key.into(), value)
+    }
+    pub fn entry<K: Into<HashableValue>>(&mut self, key: K) -> hash_map::Entry<'_, HashableValue, Value> {
+        self.0.entry(key.into())
+    }
+    pub fn keys(&self) -> hash_map::Keys<'_, HashableValue, Value> {
+        self.0.keys()
+    }
+    pub fn values(&self) -> hash_map::Values<'_, HashableValue, Value> {
+        self.0.values()
+    }
+    pub fn values_mut(&mut self) -> hash_map::ValuesMut<'_, HashableValue, Value> {
+        self.0.values_mut()
+    }
+    pub fn iter(&self) -> hash_map::Iter<'_, HashableValue, Value> {
+        self.0.iter()
+    }
+    pub fn iter_mut(&mut self) -> hash_map::IterMut<'_, HashableValue, Value> {
+        self.0.iter_mut()
+    }
+    pub fn retain<F: FnMut(&HashableValue, &mut Value) -> bool>(&mut self, keep: F) {
+        self.0.retain(keep)
+    }
+    /// Entries ordered by key (see `HashableValue`'s `Ord`), for stable output
+    pub fn sorted(&self) -> Vec<(&HashableValue, &Value)> {
+        let mut entries: Vec<_> = self.0.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.0.iter()).finish()
+    }
+}
+impl<Q: TableKey + ?Sized> std::ops::Index<&Q> for Table {
+    type Output = Value;
+    fn index(&self, key: &Q) -> &Value {
+        self.get(key).expect("no entry found for key")
+    }
+}
+impl<K: Into<HashableValue>> FromIterator<(K, Value)> for Table {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> Self {
+        Table(iter.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+}
+impl<K: Into<HashableValue>> Extend<(K, Value)> for Table {
+    fn extend<I: IntoIterator<Item = (K, Value)>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(|(key, value)| (key.into(), value)))
+    }
+}
+impl<K: Into<HashableValue>, const N: usize> From<[(K, Value); N]> for Table {
+    fn from(entries: [(K, Value); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+impl From<HashMap<String, Value>> for Table {
+    fn from(map: HashMap<String, Value>) -> Self {
+        map.into_iter().collect()
+    }
+}
+impl IntoIterator for Table {
+    type Item = (HashableValue, Value);
+    type IntoIter = hash_map::IntoIter<HashableValue, Value>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a> IntoIterator for &'a Table {
+    type Item = (&'a HashableValue, &'a Value);
+    type IntoIter = hash_map::Iter<'a, HashableValue, Value>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+impl<'a> IntoIterator for &'a mut Table {
+    type Item = (&'a HashableValue, &'a mut Value);
+    type IntoIter = hash_map::IterMut<'a, HashableValue, Value>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
@@ -1,7 +1,10 @@
 use crate::bytecode::{BytecodeProgram, Constant, Instruction};
 use crate::evaluator::Value;
+use crate::table::Table;
+use crate::parser::BinaryOp;
 use crate::errors::{MintasError, MintasResult, SourceLocation};
 use std::collections::HashMap;
+use std::cmp::Ordering;
 
 /// Stack-based virtual machine for executing bytecode
 pub struct BytecodeVM {
@@ -9,6 +12,8 @@ pub struct BytecodeVM {
     variables: HashMap<String, Value>,
     program: BytecodeProgram,
     ip: usize, // Instruction pointer
+    #[cfg(test)]
+    executed: u64,
 }
 
 impl BytecodeVM {
@@ -18,28 +23,59 @@ impl BytecodeVM {
             variables: HashMap::new(),
             program,
             ip: 0,
+            #[cfg(test)]
+            executed: 0,
         }
     }
     
+    /// Instructions dispatched so far
+    #[cfg(test)]
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+    
+    /// Top-level variables left after `execute` that the program exports, used when a `.ms`
+    /// file is included as a module.
+    pub fn into_exports(self) -> HashMap<String, Value> {
+        let mut variables = self.variables;
+        if let Some(exports) = &self.program.exports {
+            variables.retain(|name, _| exports.contains(name));
+        }
+        variables
+    }
+    
     /// Execute the bytecode program
     pub fn execute(&mut self) -> MintasResult<Value> {
-        while self.ip < self.program.instructions.len() {
-            let instruction = self.program.instructions[self.ip].clone();
-            self.ip += 1;
+        // The code is moved out while it runs so instructions are borrowed, not cloned, per step
+        let code = std::mem::take(&mut self.program.instructions);
+        let result = self.run(&code);
+        self.program.instructions = code;
+        result
+    }
+    
+    fn run(&mut self, code: &[Instruction]) -> MintasResult<Value> {
+        let mut ip = self.ip;
+        while let Some(instruction) = code.get(ip) {
+            ip += 1;
+            #[cfg(test)]
+            {
+                self.executed += 1;
+            }
             
             match instruction {
                 Instruction::LoadConst(idx) => {
-                    let constant = &self.program.constants[idx];
+                    let constant = &self.program.constants[*idx];
                     let value = match constant {
                         Constant::Number(n) => Value::Number(*n),
                         Constant::String(s) => Value::String(s.clone()),
                         Constant::Boolean(b) => Value::Boolean(*b),
+                        Constant::Int(n) => Value::from_i64(*n),
                     };
                     self.stack.push(value);
                 }
                 
                 Instruction::LoadString(idx) => {
-                    let s = self.program.strings[idx].clone();
+                    let s = self.program.strings[*idx].clone();
                     self.stack.push(Value::String(s));
                 }
                 
@@ -49,7 +85,7 @@ impl BytecodeVM {
                 Instruction::LoadEmpty => self.stack.push(Value::Empty),
                 
                 Instruction::LoadVar(name) => {
-                    let value = self.variables.get(&name)
+                    let value = self.variables.get(name)
                         .cloned()
                         .ok_or_else(|| MintasError::UndefinedVariable {
                             name: name.clone(),
@@ -64,12 +100,18 @@ impl BytecodeVM {
                             message: "Stack underflow".to_string(),
                             location: SourceLocation::new(0, 0),
                         })?;
-                    self.variables.insert(name, value);
+                    // Reassigning an existing variable skips cloning its name
+                    match self.variables.get_mut(name) {
+                        Some(slot) => *slot = value,
+                        None => {
+                            self.variables.insert(name.clone(), value);
+                        }
+                    }
                 }
                 
-                Instruction::Add => self.binary_op(|a, b| a + b)?,
-                Instruction::Sub => self.binary_op(|a, b| a - b)?,
-                Instruction::Mul => self.binary_op(|a, b| a * b)?,
+                Instruction::Add => self.binary_op(BinaryOp::Add, |a, b| a + b)?,
+                Instruction::Sub => self.binary_op(BinaryOp::Subtract, |a, b| a - b)?,
+                Instruction::Mul => self.binary_op(BinaryOp::Multiply, |a, b| a * b)?,
                 Instruction::Div => {
                     let b = self.pop_number()?;
                     let a = self.pop_number()?;
@@ -80,18 +122,25 @@ impl BytecodeVM {
                     }
                     self.stack.push(Value::Number(a / b));
                 }
-                Instruction::Mod => self.binary_op(|a, b| a % b)?,
+                Instruction::Mod => self.binary_op(BinaryOp::Modulo, |a, b| a % b)?,
                 Instruction::Neg => {
-                    let n = self.pop_number()?;
-                    self.stack.push(Value::Number(-n));
+                    let value = match self.stack.pop() {
+                        Some(Value::Int(n)) => n.checked_neg().map(Value::from_i64).unwrap_or(Value::Number(-(n as f64))),
+                        Some(other) => {
+                            self.stack.push(other);
+                            Value::Number(-self.pop_number()?)
+                        }
+                        None => return Err(self.stack_underflow()),
+                    };
+                    self.stack.push(value);
                 }
                 
-                Instruction::Eq => self.comparison_op(|a, b| (a - b).abs() < f64::EPSILON)?,
-                Instruction::NotEq => self.comparison_op(|a, b| (a - b).abs() >= f64::EPSILON)?,
-                Instruction::Greater => self.comparison_op(|a, b| a > b)?,
-                Instruction::Less => self.comparison_op(|a, b| a < b)?,
-                Instruction::GreaterEq => self.comparison_op(|a, b| a >= b)?,
-                Instruction::LessEq => self.comparison_op(|a, b| a <= b)?,
+                Instruction::Eq => self.comparison_op(|o| o.is_some_and(Ordering::is_eq))?,
+                Instruction::NotEq => self.comparison_op(|o| !o.is_some_and(Ordering::is_eq))?,
+                Instruction::Greater => self.comparison_op(|o| o.is_some_and(Ordering::is_gt))?,
+                Instruction::Less => self.comparison_op(|o| o.is_some_and(Ordering::is_lt))?,
+                Instruction::GreaterEq => self.comparison_op(|o| o.is_some_and(Ordering::is_ge))?,
+                Instruction::LessEq => self.comparison_op(|o| o.is_some_and(Ordering::is_le))?,
                 
                 Instruction::And => {
                     let b = self.stack.pop().ok_or_else(|| self.stack_underflow())?;
@@ -113,27 +162,27 @@ impl BytecodeVM {
                 }
                 
                 Instruction::Jump(target) => {
-                    self.ip = target;
+                    ip = *target;
                 }
                 
                 Instruction::JumpIfFalse(target) => {
                     let condition = self.stack.pop().ok_or_else(|| self.stack_underflow())?;
                     if !condition.is_truthy() {
-                        self.ip = target;
+                        ip = *target;
                     }
                 }
                 
                 Instruction::JumpIfTrue(target) => {
                     let condition = self.stack.pop().ok_or_else(|| self.stack_underflow())?;
                     if condition.is_truthy() {
-                        self.ip = target;
+                        ip = *target;
                     }
                 }
                 
                 Instruction::Call(name, argc) => {
                     // For now, handle built-in functions
                     if name == "say" {
-                        let arg = if argc > 0 {
+                        let arg = if *argc > 0 {
                             self.stack.pop().ok_or_else(|| self.stack_underflow())?
                         } else {
                             Value::Empty
@@ -142,20 +191,21 @@ impl BytecodeVM {
                         self.stack.push(Value::Empty);
                     } else {
                         return Err(MintasError::UnknownFunction {
-                            name,
+                            name: name.clone(),
                             location: SourceLocation::new(0, 0),
                         });
                     }
                 }
                 
                 Instruction::Return => {
+                    self.ip = ip;
                     let value = self.stack.pop().unwrap_or(Value::Empty);
                     return Ok(value);
                 }
                 
                 Instruction::MakeArray(count) => {
                     let mut elements = Vec::new();
-                    for _ in 0..count {
+                    for _ in 0..*count {
                         elements.push(self.stack.pop().ok_or_else(|| self.stack_underflow())?);
                     }
                     elements.reverse();
@@ -163,8 +213,8 @@ impl BytecodeVM {
                 }
                 
                 Instruction::MakeTable(count) => {
-                    let mut map = HashMap::new();
-                    for _ in 0..count {
+                    let mut map = Table::new();
+                    for _ in 0..*count {
                         let value = self.stack.pop().ok_or_else(|| self.stack_underflow())?;
                         let key = self.stack.pop().ok_or_else(|| self.stack_underflow())?;
                         if let Value::String(k) = key {
@@ -197,6 +247,7 @@ impl BytecodeVM {
                 }
             }
         }
+        self.ip = ip;
         
         // Return the top of the stack or Empty
         Ok(self.stack.pop().unwrap_or(Value::Empty))
@@ -205,6 +256,7 @@ impl BytecodeVM {
     fn pop_number(&mut self) -> MintasResult<f64> {
         match self.stack.pop() {
             Some(Value::Number(n)) => Ok(n),
+            Some(Value::Int(n)) => Ok(n as f64),
             Some(other) => Err(MintasError::TypeError {
                 message: format!("Expected number, got {}", other.type_name()),
                 location: SourceLocation::new(0, 0),
@@ -213,23 +265,37 @@ impl BytecodeVM {
         }
     }
     
-    fn binary_op<F>(&mut self, op: F) -> MintasResult<()>
+    fn pop_numeric(&mut self) -> MintasResult<Value> {
+        let value = self.stack.pop().ok_or_else(|| self.stack_underflow())?;
+        match value {
+            Value::Number(_) | Value::Int(_) => Ok(value),
+            other => Err(MintasError::TypeError {
+                message: format!("Expected number, got {}", other.type_name()),
+                location: SourceLocation::new(0, 0),
+            }),
+        }
+    }
+    
+    fn binary_op<F>(&mut self, op: BinaryOp, float_op: F) -> MintasResult<()>
     where
         F: FnOnce(f64, f64) -> f64,
     {
-        let b = self.pop_number()?;
-        let a = self.pop_number()?;
-        self.stack.push(Value::Number(op(a, b)));
+        let b = self.pop_numeric()?;
+        let a = self.pop_numeric()?;
+        let float_result = float_op(a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+        self.stack.push(Value::checked_arith(&op, &a, &b, float_result));
         Ok(())
     }
     
     fn comparison_op<F>(&mut self, op: F) -> MintasResult<()>
     where
-        F: FnOnce(f64, f64) -> bool,
+        F: FnOnce(Option<Ordering>) -> bool,
     {
-        let b = self.pop_number()?;
-        let a = self.pop_number()?;
-        self.stack.push(Value::Boolean(op(a, b)));
+        let b = self.pop_numeric()?;
+        let a = self.pop_numeric()?;
+        // NaN is unordered (`None`), so every comparison involving it is false except `!=`
+        let result = op(Value::compare_numbers(&a, &b));
+        self.stack.push(Value::Boolean(result));
         Ok(())
     }
     
@@ -300,4 +366,62 @@ mod tests {
         let result = compile_and_run("x = 10\ny = 20\nx + y").unwrap();
         assert_eq!(result, Value::Number(30.0));
     }
+    
+    #[test]
+    fn test_loop_else() {
+        let result = compile_and_run("x = 0\nwhile (x > 5):\n    x = x + 1\nelse:\n    x = 100\nend\nx").unwrap();
+        assert_eq!(result, Value::Number(100.0));
+        let result = compile_and_run("x = 0\nwhile (x < 3):\n    x = x + 1\nelse:\n    x = 100\nend\nx").unwrap();
+        assert_eq!(result, Value::Number(3.0));
+        // Counted loops run down like the interpreter's, so they always run and take no else
+        let result = compile_and_run("x = 0\nfor (i from 5 to 1):\n    x = x * 10 + i\nend\nx").unwrap();
+        assert_eq!(result, Value::Number(54321.0));
+        let result = compile_and_run("x = 0\nn = 2\nfor (i from 1 to n):\n    n = 10\n    x = x + i\nend\nx").unwrap();
+        assert_eq!(result, Value::Number(3.0));
+    }
+    
+    #[test]
+    fn test_integers_past_2_pow_53() {
+        let result = compile_and_run("x = 9007199254740992\nx = x + 1\nx").unwrap();
+        assert_eq!(result, Value::Int(9_007_199_254_740_993));
+        let result = compile_and_run("9007199254740993 > 9007199254740992").unwrap();
+        assert_eq!(result, Value::Boolean(true));
+        let result = compile_and_run("9007199254740993 * 2 - 9007199254740993").unwrap();
+        assert_eq!(result, Value::Int(9_007_199_254_740_993));
+    }
+    
+    /// Dispatch throughput on a tight arithmetic loop. Run with
+    /// `cargo test --release bench_dispatch_loop -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_dispatch_loop() {
+        let code = "total = 0\ni = 0\nwhile (i < 2000000):\n    total = total + i * 2 - 1\n    i = i + 1\nend\ntotal";
+        let ast = Parser::new(Lexer::new(code).tokenize().unwrap()).parse().unwrap();
+        let program = BytecodeCompiler::new().compile(&ast).unwrap();
+        let mut vm = BytecodeVM::new(program);
+        let start = std::time::Instant::now();
+        let result = vm.execute().unwrap();
+        let elapsed = start.elapsed();
+        assert_eq!(result, Value::Number(3_999_996_000_000.0));
+        println!(
+            "{} instructions in {:.1?}: {:.1}M instructions/s",
+            vm.executed(),
+            elapsed,
+            vm.executed() as f64 / elapsed.as_secs_f64() / 1e6
+        );
+    }
+    
+    #[test]
+    fn test_nan_is_unordered_but_not_equal() {
+        let nan = "inf = 1e308 * 10\nnan = inf - inf\n";
+        let result = compile_and_run(&format!("{}nan != nan", nan)).unwrap();
+        assert_eq!(result, Value::Boolean(true));
+        let result = compile_and_run(&format!("{}nan == nan or nan < 1 or nan >= 1", nan)).unwrap();
+        assert_eq!(result, Value::Boolean(false));
+        let result = compile_and_run(&format!("{}z = 0.0 * -1\nz == 0 and inf == inf", nan)).unwrap();
+        assert_eq!(result, Value::Boolean(true));
+        // Same equality as the interpreter's `==`
+        let result = compile_and_run("0.1 + 0.2 == 0.3 and 0.1 + 0.2 >= 0.3").unwrap();
+        assert_eq!(result, Value::Boolean(true));
+    }
 }
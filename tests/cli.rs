@@ -0,0 +1,93 @@
+//! Runs the `mintas` binary the way a user would and checks what it prints
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Writes `code` to a script under the system temp dir
+fn script(name: &str, code: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mintas-cli-{}-{}.as", name, std::process::id()));
+    fs::write(&path, code).unwrap();
+    path
+}
+
+fn mintas(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mintas")).args(args).output().unwrap()
+}
+
+#[cfg(feature = "cranelift-backend")]
+#[test]
+fn test_trace_jit_reports_the_engine_that_ran() {
+    let trace = |code: &str, name: &str| {
+        let path = script(name, code);
+        let output = mintas(&["--trace-jit", path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+        (String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+
+    let (stdout, stderr) = trace("total = 0\nfor (i from 1 to 10):\n    total = total + i\nend\ntotal\n", "compiled");
+    assert_eq!(stdout, "55\n");
+    assert_eq!(stderr, "[jit] line 1: compiled by JetX\n[jit] line 2: compiled by JetX\n[jit] line 5: compiled by JetX\n");
+
+    let (stdout, stderr) = trace("x = 2\nsay(x)\nx * 3\n", "blocked");
+    assert_eq!(stdout, "2\n6\n");
+    assert_eq!(
+        stderr,
+        "[jit] line 1: interpreted (line 2 stays in the interpreter)\n\
+         [jit] line 2: interpreted (contains I/O call `say`)\n\
+         [jit] line 3: interpreted (line 2 stays in the interpreter)\n"
+    );
+
+    // Compiled and run, then handed back because only the interpreter raises the error
+    let (_, stderr) = trace("n = 0\ntotal = 5 / n\ntotal\n", "bailed");
+    assert!(stderr.starts_with("[jit] line 1: interpreted (JetX met a zero divisor"), "{}", stderr);
+    assert!(!stderr.contains("compiled by JetX"), "{}", stderr);
+    assert!(stderr.contains("Division by zero"), "{}", stderr);
+}
+
+#[test]
+fn test_xdbx_build_out_places_each_target_where_asked() {
+    let project = std::env::temp_dir().join(format!("mintas-cli-out-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&project);
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join("mintas.toml"), "name = \"demo\"\n").unwrap();
+    fs::write(project.join("src/main.as"), "say(1)\n").unwrap();
+    let build = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_mintas")).arg("xdbx").arg("build").args(args)
+            .current_dir(&project).output().unwrap()
+    };
+
+    // Missing parent directories are created for every target
+    for (flag, out) in [("--ms", "dist/bytecode/game.ms"), ("--web", "site/pages/game.html")] {
+        let output = build(&[flag, "--out", out]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(project.join(out).is_file(), "{} missing", out);
+    }
+    assert!(fs::read_to_string(project.join("site/pages/game.html")).unwrap().contains("say(1)"));
+    assert!(!project.join("target/debug/demo.ms").exists() && !project.join("target/debug/demo.html").exists());
+
+    // Without --out the artifact keeps its derived path
+    assert!(build(&["--web"]).status.success());
+    assert!(project.join("target/debug/demo.html").is_file());
+
+    fs::remove_dir_all(&project).unwrap();
+}
+
+#[test]
+fn test_stdin_runs_the_piped_program() {
+    use std::io::Write;
+    for flag in ["--stdin", "-"] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mintas"))
+            .arg(flag)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(b"x = 20\nsay(x + 1)\nsay(\"piped\")\n").unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}: {}", flag, String::from_utf8_lossy(&output.stderr));
+        // No REPL banner or prompt, just the program's own output
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "21\npiped\n", "{}", flag);
+    }
+}